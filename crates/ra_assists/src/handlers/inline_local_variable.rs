@@ -1,8 +1,5 @@
 use ra_ide_db::defs::Definition;
-use ra_syntax::{
-    ast::{self, AstNode, AstToken},
-    TextRange,
-};
+use ra_syntax::ast::{self, edit::AstNodeEdit, AstNode};
 use test_utils::mark;
 
 use crate::{
@@ -50,19 +47,6 @@ pub(crate) fn inline_local_variable(acc: &mut Assists, ctx: &AssistContext) -> O
         return None;
     };
 
-    let delete_range = if let Some(whitespace) = let_stmt
-        .syntax()
-        .next_sibling_or_token()
-        .and_then(|it| ast::Whitespace::cast(it.as_token()?.clone()))
-    {
-        TextRange::new(
-            let_stmt.syntax().text_range().start(),
-            whitespace.syntax().text_range().end(),
-        )
-    } else {
-        let_stmt.syntax().text_range()
-    };
-
     let mut wrap_in_parens = vec![true; refs.len()];
 
     for (i, desc) in refs.iter().enumerate() {
@@ -115,7 +99,7 @@ pub(crate) fn inline_local_variable(acc: &mut Assists, ctx: &AssistContext) -> O
         "Inline variable",
         target,
         move |builder| {
-            builder.delete(delete_range);
+            builder.rewrite(let_stmt.remove_keeping_comments());
             for (desc, should_wrap) in refs.iter().zip(wrap_in_parens) {
                 let replacement =
                     if should_wrap { init_in_paren.clone() } else { init_str.clone() };
@@ -666,6 +650,23 @@ fn foo() {
         );
     }
 
+    #[test]
+    fn test_keeps_trailing_comment() {
+        check_assist(
+            inline_local_variable,
+            r"
+fn foo() {
+    let a<|> = 1 > 0; // always true
+    return a;
+}",
+            r"
+fn foo() {
+    // always true
+    return 1 > 0;
+}",
+        );
+    }
+
     #[test]
     fn test_not_applicable_if_variable_unused() {
         mark::check!(test_not_applicable_if_variable_unused);
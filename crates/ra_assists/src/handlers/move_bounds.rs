@@ -1,6 +1,7 @@
 use ra_syntax::{
-    ast::{self, edit::AstNodeEdit, make, AstNode, NameOwner, TypeBoundsOwner},
-    match_ast,
+    algo::SyntaxRewriter,
+    ast::{self, make, AstNode, NameOwner, TypeBoundsOwner},
+    match_ast, SyntaxElement,
     SyntaxKind::*,
     T,
 };
@@ -55,29 +56,26 @@ pub(crate) fn move_bounds_to_where_clause(acc: &mut Assists, ctx: &AssistContext
         "Move to where clause",
         target,
         |edit| {
-            let new_params = type_param_list
-                .type_params()
-                .filter(|it| it.type_bound_list().is_some())
-                .map(|type_param| {
-                    let without_bounds = type_param.remove_bounds();
-                    (type_param, without_bounds)
-                });
-
-            let new_type_param_list = type_param_list.replace_descendants(new_params);
-            edit.replace_ast(type_param_list.clone(), new_type_param_list);
+            let mut rewriter = SyntaxRewriter::default();
+            for type_param in type_param_list.type_params() {
+                if type_param.type_bound_list().is_some() {
+                    rewriter.replace_ast(&type_param, &type_param.remove_bounds());
+                }
+            }
 
             let where_clause = {
                 let predicates = type_param_list.type_params().filter_map(build_predicate);
                 make::where_clause(predicates)
             };
-
+            let where_clause: SyntaxElement = where_clause.syntax().clone().into();
+            let space: SyntaxElement = make::tokens::single_space().into();
             let to_insert = match anchor.prev_sibling_or_token() {
-                Some(ref elem) if elem.kind() == WHITESPACE => {
-                    format!("{} ", where_clause.syntax())
-                }
-                _ => format!(" {}", where_clause.syntax()),
+                Some(ref elem) if elem.kind() == WHITESPACE => vec![where_clause, space],
+                _ => vec![space, where_clause],
             };
-            edit.insert(anchor.text_range().start(), to_insert);
+            rewriter.insert_many_before(&anchor, to_insert);
+
+            edit.rewrite(rewriter);
         },
     )
 }
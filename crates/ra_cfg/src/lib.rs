@@ -47,6 +47,33 @@ impl CfgOptions {
         self.key_values.insert((key, value));
     }
 
+    pub fn remove_key_value(&mut self, key: &str, value: &str) {
+        self.key_values.remove(&(key.into(), value.into()));
+    }
+
+    pub fn insert(&mut self, atom: CfgAtom) {
+        match atom {
+            CfgAtom::Flag(name) => self.insert_atom(name),
+            CfgAtom::KeyValue { key, value } => self.insert_key_value(key, value),
+        }
+    }
+
+    pub fn remove(&mut self, atom: &CfgAtom) {
+        match atom {
+            CfgAtom::Flag(name) => self.remove_atom(name),
+            CfgAtom::KeyValue { key, value } => self.remove_key_value(key, value),
+        }
+    }
+
+    pub fn apply_diff(&mut self, diff: &CfgDiff) {
+        for atom in diff.enable.iter() {
+            self.insert(atom.clone());
+        }
+        for atom in diff.disable.iter() {
+            self.remove(atom);
+        }
+    }
+
     pub fn append(&mut self, other: &CfgOptions) {
         for atom in &other.atoms {
             self.atoms.insert(atom.clone());
@@ -57,3 +84,72 @@ impl CfgOptions {
         }
     }
 }
+
+/// A single cfg option, either an atom like `unix` or a key-value pair like
+/// `target_os = "linux"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CfgAtom {
+    Flag(SmolStr),
+    KeyValue { key: SmolStr, value: SmolStr },
+}
+
+impl CfgAtom {
+    /// Parses the `--cfg` syntax used by rustc and build scripts: `name`,
+    /// `key=value` or `key="value"`.
+    pub fn parse(s: &str) -> CfgAtom {
+        match s.find('=') {
+            None => CfgAtom::Flag(s.trim().into()),
+            Some(pos) => CfgAtom::KeyValue {
+                key: s[..pos].trim().into(),
+                value: s[pos + 1..].trim().trim_matches('"').into(),
+            },
+        }
+    }
+}
+
+impl From<CfgAtom> for CfgExpr {
+    fn from(atom: CfgAtom) -> CfgExpr {
+        match atom {
+            CfgAtom::Flag(name) => CfgExpr::Atom(name),
+            CfgAtom::KeyValue { key, value } => CfgExpr::KeyValue { key, value },
+        }
+    }
+}
+
+/// Options to enable and disable on top of the computed `CfgOptions`.
+/// Disabling wins if an option is in both lists.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CfgDiff {
+    pub enable: Vec<CfgAtom>,
+    pub disable: Vec<CfgAtom>,
+}
+
+impl CfgDiff {
+    pub fn is_empty(&self) -> bool {
+        self.enable.is_empty() && self.disable.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_diff() {
+        let mut opts = CfgOptions::default();
+        opts.insert(CfgAtom::parse("test"));
+        opts.insert(CfgAtom::parse("feature=\"std\""));
+
+        let diff = CfgDiff {
+            enable: vec![CfgAtom::parse("fuzzing"), CfgAtom::parse("target_os=linux")],
+            disable: vec![CfgAtom::parse("test"), CfgAtom::parse("feature = \"std\"")],
+        };
+        opts.apply_diff(&diff);
+
+        let check = |atom: &str| opts.check(&CfgExpr::from(CfgAtom::parse(atom)));
+        assert_eq!(check("fuzzing"), Some(true));
+        assert_eq!(check("target_os=\"linux\""), Some(true));
+        assert_eq!(check("test"), Some(false));
+        assert_eq!(check("feature=\"std\""), Some(false));
+    }
+}
@@ -65,8 +65,8 @@ use test_utils::{extract_range_or_offset, Fixture, RangeOrOffset, CURSOR_MARKER}
 use vfs::{file_set::FileSet, VfsPath};
 
 use crate::{
-    input::CrateName, CrateGraph, CrateId, Edition, Env, FileId, FilePosition, SourceDatabaseExt,
-    SourceRoot, SourceRootId,
+    input::CrateName, CrateGraph, CrateId, Edition, Env, FileId, FilePosition, ProcMacro,
+    SourceDatabaseExt, SourceRoot, SourceRootId,
 };
 
 pub const WORKSPACE: SourceRootId = SourceRootId(0);
@@ -109,6 +109,37 @@ pub trait WithFixture: Default + SourceDatabaseExt + 'static {
         assert!(it.next().is_none());
         res
     }
+
+    /// Makes the crate named `name` provide `proc_macros`, which can't be
+    /// written in fixtures.
+    fn set_proc_macros(&mut self, name: &str, proc_macros: Vec<ProcMacro>) {
+        let old_graph = self.crate_graph();
+        let mut krates: Vec<CrateId> = old_graph.iter().collect();
+        krates.sort_by_key(|it| it.0);
+        let mut crate_graph = CrateGraph::default();
+        for &krate in krates.iter() {
+            let data = &old_graph[krate];
+            let proc_macro = if data.display_name.as_deref() == Some(name) {
+                proc_macros.clone()
+            } else {
+                data.proc_macro.clone()
+            };
+            crate_graph.add_crate_root(
+                data.root_file_id,
+                data.edition,
+                data.display_name.clone(),
+                data.cfg_options.clone(),
+                data.env.clone(),
+                proc_macro,
+            );
+        }
+        for &krate in krates.iter() {
+            for dep in old_graph[krate].dependencies.iter() {
+                crate_graph.add_dep(krate, dep.name.clone(), dep.crate_id).unwrap();
+            }
+        }
+        self.set_crate_graph(Arc::new(crate_graph));
+    }
 }
 
 impl<DB: SourceDatabaseExt + Default + 'static> WithFixture for DB {}
@@ -124,6 +155,8 @@ fn with_files(
     let mut crates = FxHashMap::default();
     let mut crate_deps = Vec::new();
     let mut default_crate_root: Option<FileId> = None;
+    let mut default_cfg = CfgOptions::default();
+    let mut default_env = Env::default();
 
     let mut file_set = FileSet::default();
     let source_root_id = WORKSPACE;
@@ -164,6 +197,8 @@ fn with_files(
         } else if meta.path == "/main.rs" || meta.path == "/lib.rs" {
             assert!(default_crate_root.is_none());
             default_crate_root = Some(file_id);
+            default_cfg = meta.cfg;
+            default_env = meta.env;
         }
 
         db.set_file_text(file_id, Arc::new(text));
@@ -180,8 +215,8 @@ fn with_files(
             crate_root,
             Edition::Edition2018,
             None,
-            CfgOptions::default(),
-            Env::default(),
+            default_cfg,
+            default_env,
             Default::default(),
         );
     } else {
@@ -194,6 +229,7 @@ fn with_files(
 
     db.set_source_root(source_root_id, Arc::new(SourceRoot::new_local(file_set)));
     db.set_crate_graph(Arc::new(crate_graph));
+    db.set_open_files(Default::default());
 
     (file_position, files)
 }
@@ -104,16 +104,26 @@ impl ops::Deref for CrateName {
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct ProcMacroId(pub u32);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProcMacroKind {
+    CustomDerive,
+    FuncLike,
+    Attr,
+}
+
 #[derive(Debug, Clone)]
 pub struct ProcMacro {
     pub name: SmolStr,
+    pub kind: ProcMacroKind,
     pub expander: Arc<dyn TokenExpander>,
 }
 
 impl Eq for ProcMacro {}
 impl PartialEq for ProcMacro {
     fn eq(&self, other: &ProcMacro) -> bool {
-        self.name == other.name && Arc::ptr_eq(&self.expander, &other.expander)
+        self.name == other.name
+            && self.kind == other.kind
+            && Arc::ptr_eq(&self.expander, &other.expander)
     }
 }
 
@@ -156,11 +166,8 @@ impl CrateGraph {
         display_name: Option<String>,
         cfg_options: CfgOptions,
         env: Env,
-        proc_macro: Vec<(SmolStr, Arc<dyn ra_tt::TokenExpander>)>,
+        proc_macro: Vec<ProcMacro>,
     ) -> CrateId {
-        let proc_macro =
-            proc_macro.into_iter().map(|(name, it)| ProcMacro { name, expander: it }).collect();
-
         let data = CrateData {
             root_file_id: file_id,
             edition,
@@ -221,21 +228,68 @@ impl CrateGraph {
         Some(crate_id)
     }
 
-    /// Extends this crate graph by adding a complete disjoint second crate
-    /// graph.
+    /// Extends this crate graph by adding a complete second crate graph.
+    ///
+    /// Crates of `other` which are identical to an existing crate (including
+    /// their dependencies), like the sysroot crates or dependencies shared by
+    /// several workspaces, are not added again.
     ///
-    /// The ids of the crates in the `other` graph are shifted by the return
-    /// amount.
-    pub fn extend(&mut self, other: CrateGraph) -> u32 {
-        let start = self.arena.len() as u32;
-        self.arena.extend(other.arena.into_iter().map(|(id, mut data)| {
-            let new_id = id.shift(start);
+    /// Returns the mapping from the crate ids of `other` to the ids in `self`.
+    pub fn extend(&mut self, other: CrateGraph) -> FxHashMap<CrateId, CrateId> {
+        // Identical crates have the same root file, so only the crates with
+        // the same root need to be compared.
+        let mut by_root: FxHashMap<FileId, Vec<CrateId>> = FxHashMap::default();
+        for (&id, data) in self.arena.iter() {
+            by_root.entry(data.root_file_id).or_default().push(id);
+        }
+        let mut id_map = FxHashMap::default();
+        for other_id in other.crates_in_topological_order() {
+            let mut data = other[other_id].clone();
             for dep in &mut data.dependencies {
-                dep.crate_id = dep.crate_id.shift(start);
+                dep.crate_id = id_map[&dep.crate_id];
             }
-            (new_id, data)
-        }));
-        start
+            let candidates = by_root.entry(data.root_file_id).or_default();
+            let existing = candidates.iter().copied().find(|id| self.arena[id] == data);
+            let new_id = match existing {
+                Some(id) => id,
+                None => {
+                    let id = CrateId(self.arena.len() as u32);
+                    candidates.push(id);
+                    self.arena.insert(id, data);
+                    id
+                }
+            };
+            id_map.insert(other_id, new_id);
+        }
+        id_map
+    }
+
+    /// Returns all crates in the graph, such that every crate comes after
+    /// its dependencies.
+    pub fn crates_in_topological_order(&self) -> Vec<CrateId> {
+        let mut res = Vec::new();
+        let mut visited = FxHashSet::default();
+        let mut crates = self.iter().collect::<Vec<_>>();
+        crates.sort();
+        for krate in crates {
+            self.go_topological(krate, &mut visited, &mut res);
+        }
+        res
+    }
+
+    fn go_topological(
+        &self,
+        krate: CrateId,
+        visited: &mut FxHashSet<CrateId>,
+        res: &mut Vec<CrateId>,
+    ) {
+        if !visited.insert(krate) {
+            return;
+        }
+        for dep in self[krate].dependencies.iter() {
+            self.go_topological(dep.crate_id, visited, res)
+        }
+        res.push(krate)
     }
 
     fn dfs_find(&self, target: CrateId, from: CrateId, visited: &mut FxHashSet<CrateId>) -> bool {
@@ -398,6 +452,55 @@ mod tests {
         assert!(graph.add_dep(crate2, CrateName::new("crate2").unwrap(), crate2).is_err());
     }
 
+    #[test]
+    fn extend_reuses_identical_crates() {
+        let mut graph = CrateGraph::default();
+        let core = graph.add_crate_root(
+            FileId(1u32),
+            Edition2018,
+            Some("core".to_string()),
+            CfgOptions::default(),
+            Env::default(),
+            Default::default(),
+        );
+        let foo = graph.add_crate_root(
+            FileId(2u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+            Default::default(),
+        );
+        graph.add_dep(foo, CrateName::new("core").unwrap(), core).unwrap();
+
+        let mut other = CrateGraph::default();
+        let bar = other.add_crate_root(
+            FileId(3u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+            Default::default(),
+        );
+        let other_core = other.add_crate_root(
+            FileId(1u32),
+            Edition2018,
+            Some("core".to_string()),
+            CfgOptions::default(),
+            Env::default(),
+            Default::default(),
+        );
+        other.add_dep(bar, CrateName::new("core").unwrap(), other_core).unwrap();
+
+        let id_map = graph.extend(other);
+        assert_eq!(id_map[&other_core], core);
+        assert_eq!(graph.iter().count(), 3);
+        assert_eq!(
+            graph[id_map[&bar]].dependencies,
+            vec![Dependency { crate_id: core, name: CrateName::new("core").unwrap() }]
+        );
+    }
+
     #[test]
     fn it_works() {
         let mut graph = CrateGraph::default();
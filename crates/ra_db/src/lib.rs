@@ -12,8 +12,8 @@ use rustc_hash::FxHashSet;
 pub use crate::{
     cancellation::Canceled,
     input::{
-        CrateData, CrateGraph, CrateId, CrateName, Dependency, Edition, Env, FileId, ProcMacroId,
-        SourceRoot, SourceRootId,
+        CrateData, CrateGraph, CrateId, CrateName, Dependency, Edition, Env, FileId, ProcMacro,
+        ProcMacroId, ProcMacroKind, SourceRoot, SourceRootId,
     },
 };
 pub use salsa;
@@ -97,6 +97,10 @@ pub trait FileLoader {
     /// `#[path = "C://no/way"]`
     fn resolve_path(&self, anchor: FileId, path: &str) -> Option<FileId>;
     fn relevant_crates(&self, file_id: FileId) -> Arc<FxHashSet<CrateId>>;
+    /// Whether the file belongs to a library, which is never edited.
+    fn is_library(&self, file_id: FileId) -> bool;
+    /// Whether the file is open in the editor.
+    fn is_open(&self, file_id: FileId) -> bool;
 }
 
 /// Database which stores all significant input facts: source code and project
@@ -133,6 +137,13 @@ pub trait SourceDatabaseExt: SourceDatabase {
     fn source_root(&self, id: SourceRootId) -> Arc<SourceRoot>;
 
     fn source_root_crates(&self, id: SourceRootId) -> Arc<FxHashSet<CrateId>>;
+
+    /// Files open in the editor.
+    #[salsa::input]
+    fn open_files(&self) -> Arc<FxHashSet<FileId>>;
+    /// Split out of `open_files`, so that opening a file only invalidates the
+    /// queries which asked about this particular file.
+    fn is_file_open(&self, file_id: FileId) -> bool;
 }
 
 fn source_root_crates(db: &dyn SourceDatabaseExt, id: SourceRootId) -> Arc<FxHashSet<CrateId>> {
@@ -147,6 +158,10 @@ fn source_root_crates(db: &dyn SourceDatabaseExt, id: SourceRootId) -> Arc<FxHas
     Arc::new(res)
 }
 
+fn is_file_open(db: &dyn SourceDatabaseExt, file_id: FileId) -> bool {
+    db.open_files().contains(&file_id)
+}
+
 /// Silly workaround for cyclic deps between the traits
 pub struct FileLoaderDelegate<T>(pub T);
 
@@ -165,4 +180,13 @@ impl<T: SourceDatabaseExt> FileLoader for FileLoaderDelegate<&'_ T> {
         let source_root = self.0.file_source_root(file_id);
         self.0.source_root_crates(source_root)
     }
+
+    fn is_library(&self, file_id: FileId) -> bool {
+        let source_root = self.0.file_source_root(file_id);
+        self.0.source_root(source_root).is_library
+    }
+
+    fn is_open(&self, file_id: FileId) -> bool {
+        self.0.is_file_open(file_id)
+    }
 }
@@ -33,7 +33,7 @@ use hir_ty::{
 };
 use ra_db::{CrateId, Edition, FileId};
 use ra_prof::profile;
-use ra_syntax::ast::{self, AttrsOwner, NameOwner};
+use ra_syntax::ast::{self, NameOwner};
 use rustc_hash::FxHashSet;
 
 use crate::{
@@ -306,6 +306,8 @@ impl Module {
         for decl in self.declarations(db) {
             match decl {
                 crate::ModuleDef::Function(f) => f.diagnostics(db, sink),
+                crate::ModuleDef::Const(c) => c.diagnostics(db, sink),
+                crate::ModuleDef::Static(s) => s.diagnostics(db, sink),
                 crate::ModuleDef::Module(m) => {
                     // Only add diagnostics from inline modules
                     if crate_def_map[m.id.local_id].origin.is_inline() {
@@ -318,8 +320,10 @@ impl Module {
 
         for impl_def in self.impl_defs(db) {
             for item in impl_def.items(db) {
-                if let AssocItem::Function(f) = item {
-                    f.diagnostics(db, sink);
+                match item {
+                    AssocItem::Function(f) => f.diagnostics(db, sink),
+                    AssocItem::Const(c) => c.diagnostics(db, sink),
+                    AssocItem::TypeAlias(_) => (),
                 }
             }
         }
@@ -667,16 +671,57 @@ impl Function {
         db.function_data(self.id).has_self_param
     }
 
+    pub fn self_param(self, db: &dyn HirDatabase) -> Option<SelfParam> {
+        if !db.function_data(self.id).has_self_param {
+            return None;
+        }
+        Some(SelfParam { func: self.id })
+    }
+
     pub fn params(self, db: &dyn HirDatabase) -> Vec<TypeRef> {
         db.function_data(self.id).params.clone()
     }
 
+    /// Returns the types of the parameters (including `self`), as in the
+    /// signature of the function, with placeholder types for type parameters.
+    pub fn param_types(self, db: &dyn HirDatabase) -> Vec<Type> {
+        let krate = self.module(db).id.krate;
+        let substs = Substs::type_params(db, self.id);
+        let sig = db.callable_item_signature(CallableDef::FunctionId(self.id)).subst(&substs);
+        sig.params().iter().map(|ty| Type::new(db, krate, self.id, ty.clone())).collect()
+    }
+
+    /// Returns the return type as in the signature of the function, see
+    /// `param_types`.
+    pub fn ret_type(self, db: &dyn HirDatabase) -> Type {
+        let krate = self.module(db).id.krate;
+        let substs = Substs::type_params(db, self.id);
+        let sig = db.callable_item_signature(CallableDef::FunctionId(self.id)).subst(&substs);
+        Type::new(db, krate, self.id, sig.ret().clone())
+    }
+
     pub fn is_unsafe(self, db: &dyn HirDatabase) -> bool {
         db.function_data(self.id).is_unsafe
     }
 
+    /// Dumps the lowered body of the function, for debugging.
+    pub fn debug_hir(self, db: &dyn HirDatabase) -> String {
+        let body = db.body(self.id.into());
+        let mut res = format!("HIR expressions in the body of `{}`:\n", self.name(db));
+        for (id, expr) in body.exprs.iter() {
+            res += &format!("{:?}: {:?}\n", id, expr);
+        }
+        res += "\nHIR patterns:\n";
+        for (id, pat) in body.pats.iter() {
+            res += &format!("{:?}: {:?}\n", id, pat);
+        }
+        res
+    }
+
     pub fn diagnostics(self, db: &dyn HirDatabase, sink: &mut DiagnosticSink) {
         let _p = profile("Function::diagnostics");
+        let (_, source_map) = db.body_with_source_map(self.id.into());
+        source_map.add_diagnostics(sink);
         let infer = db.infer(self.id.into());
         infer.add_diagnostics(db, self.id, sink);
         let mut validator = ExprValidator::new(self.id, infer.clone(), sink);
@@ -686,6 +731,38 @@ impl Function {
     }
 }
 
+// Note: logically, this belongs to `hir_ty`, but we are not using it there yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Access {
+    Shared,
+    Exclusive,
+    Owned,
+}
+
+impl From<Mutability> for Access {
+    fn from(mutability: Mutability) -> Access {
+        match mutability {
+            Mutability::Shared => Access::Shared,
+            Mutability::Mut => Access::Exclusive,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SelfParam {
+    func: FunctionId,
+}
+
+impl SelfParam {
+    pub fn access(self, db: &dyn HirDatabase) -> Access {
+        let func_data = db.function_data(self.func);
+        match func_data.params.first() {
+            Some(TypeRef::Reference(_, mutability)) => (*mutability).into(),
+            _ => Access::Owned,
+        }
+    }
+}
+
 impl HasVisibility for Function {
     fn visibility(&self, db: &dyn HirDatabase) -> Visibility {
         let function_data = db.function_data(self.id);
@@ -711,6 +788,11 @@ impl Const {
     pub fn name(self, db: &dyn HirDatabase) -> Option<Name> {
         db.const_data(self.id).name.clone()
     }
+
+    pub fn diagnostics(self, db: &dyn HirDatabase, sink: &mut DiagnosticSink) {
+        let (_, source_map) = db.body_with_source_map(self.id.into());
+        source_map.add_diagnostics(sink);
+    }
 }
 
 impl HasVisibility for Const {
@@ -742,6 +824,11 @@ impl Static {
     pub fn is_mut(self, db: &dyn HirDatabase) -> bool {
         db.static_data(self.id).mutable
     }
+
+    pub fn diagnostics(self, db: &dyn HirDatabase, sink: &mut DiagnosticSink) {
+        let (_, source_map) = db.body_with_source_map(self.id.into());
+        source_map.add_diagnostics(sink);
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -829,13 +916,18 @@ impl MacroDef {
 
     /// Indicate it is a proc-macro
     pub fn is_proc_macro(&self) -> bool {
-        matches!(self.id.kind, MacroDefKind::CustomDerive(_))
+        matches!(self.id.kind, MacroDefKind::CustomDerive(_) | MacroDefKind::ProcMacro(_))
     }
 
     /// Indicate it is a derive macro
     pub fn is_derive_macro(&self) -> bool {
         matches!(self.id.kind, MacroDefKind::CustomDerive(_) | MacroDefKind::BuiltInDerive(_))
     }
+
+    /// Indicate it is a compiler builtin function-like macro
+    pub fn is_builtin(&self) -> bool {
+        matches!(self.id.kind, MacroDefKind::BuiltIn(_) | MacroDefKind::BuiltInEager(_))
+    }
 }
 
 /// Invariant: `inner.as_assoc_item(db).is_some()`
@@ -1099,25 +1191,10 @@ impl ImplDef {
         Crate { id: self.module(db).id.krate }
     }
 
-    pub fn is_builtin_derive(self, db: &dyn HirDatabase) -> Option<InFile<ast::Attr>> {
-        let src = self.source(db);
-        let item = src.file_id.is_builtin_derive(db.upcast())?;
-        let hygenic = hir_expand::hygiene::Hygiene::new(db.upcast(), item.file_id);
-
-        let attr = item
-            .value
-            .attrs()
-            .filter_map(|it| {
-                let path = hir_def::path::ModPath::from_src(it.path()?, &hygenic)?;
-                if path.as_ident()?.to_string() == "derive" {
-                    Some(it)
-                } else {
-                    None
-                }
-            })
-            .last()?;
-
-        Some(item.with_value(attr))
+    /// If this impl was generated by a builtin or custom derive, returns the
+    /// `#[derive]` attribute which lists it.
+    pub fn derive_attr(self, db: &dyn HirDatabase) -> Option<InFile<ast::Attr>> {
+        self.source(db).file_id.derive_attr(db.upcast())
     }
 }
 
@@ -1166,6 +1243,20 @@ impl Type {
         matches!(self.ty.value, Ty::Apply(ApplicationTy { ctor: TypeCtor::Bool, .. }))
     }
 
+    /// Checks that the type implements `Copy`, using the `copy` lang item.
+    pub fn is_copy(&self, db: &dyn HirDatabase) -> bool {
+        let lang_item = db.lang_item(self.krate, "copy".into());
+        let copy_trait = match lang_item.and_then(|it| it.as_trait()) {
+            Some(it) => it,
+            None => return false,
+        };
+        self.impls_trait(db, copy_trait.into(), &[])
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(self.ty.value, Ty::Apply(ApplicationTy { ctor: TypeCtor::Ref(_), .. }))
+    }
+
     pub fn is_mutable_reference(&self) -> bool {
         matches!(
             self.ty.value,
@@ -15,7 +15,10 @@ use ra_db::{FileId, FileRange};
 use ra_prof::profile;
 use ra_syntax::{
     algo::{find_node_at_offset, skip_trivia_token},
-    ast, AstNode, Direction, SyntaxNode, SyntaxToken, TextRange, TextSize,
+    ast::{self, AttrsOwner},
+    AstNode, Direction,
+    SyntaxKind::{ITEM_LIST, SOURCE_FILE},
+    SyntaxNode, SyntaxToken, TextRange, TextSize,
 };
 use rustc_hash::{FxHashMap, FxHashSet};
 
@@ -184,6 +187,20 @@ impl<'db, DB: HirDatabase> Semantics<'db, DB> {
         self.imp.descend_node_at_offset(node, offset).find_map(N::cast)
     }
 
+    /// Like `find_node_at_offset_with_descend`, but prefers the node in the
+    /// macro expansion. Items with attribute macros only exist in their
+    /// expansion, so this is what navigation wants.
+    pub fn find_node_at_offset_with_descend_first<N: AstNode>(
+        &self,
+        node: &SyntaxNode,
+        offset: TextSize,
+    ) -> Option<N> {
+        match self.imp.descend_node_at_offset(node, offset).find_map(N::cast) {
+            Some(it) if find_root(it.syntax()) != find_root(node) => Some(it),
+            _ => find_node_at_offset(node, offset),
+        }
+    }
+
     pub fn type_of_expr(&self, expr: &ast::Expr) -> Option<Type> {
         self.imp.type_of_expr(expr)
     }
@@ -248,6 +265,12 @@ impl<'db, DB: HirDatabase> Semantics<'db, DB> {
         self.imp.to_module_def(file)
     }
 
+    /// Returns all modules defined by the file, as a file might be included
+    /// from several `mod` declarations or several crates.
+    pub fn to_module_defs(&self, file: FileId) -> Vec<Module> {
+        self.imp.to_module_defs(file)
+    }
+
     pub fn scope(&self, node: &SyntaxNode) -> SemanticsScope<'db> {
         self.imp.scope(node)
     }
@@ -309,15 +332,39 @@ impl<'db> SemanticsImpl<'db> {
     pub fn descend_into_macros(&self, token: SyntaxToken) -> SyntaxToken {
         let parent = token.parent();
         let parent = self.find_file(parent);
-        let sa = self.analyze2(parent.as_ref(), None);
 
         let token = successors(Some(parent.with_value(token)), |token| {
-            let macro_call = token.value.ancestors().find_map(ast::MacroCall::cast)?;
-            let tt = macro_call.token_tree()?;
-            if !tt.syntax().text_range().contains_range(token.value.text_range()) {
-                return None;
-            }
-            let file_id = sa.expand(self.db, token.with_value(&macro_call))?;
+            // An attribute macro gets the whole item, including the macro
+            // calls inside of it, so the outermost one is expanded first.
+            let attr_expansion = token
+                .value
+                .ancestors()
+                .filter_map(ast::ModuleItem::cast)
+                .filter_map(|item| {
+                    // Only the items of modules are expanded, and the item
+                    // itself is replaced by its expansion, so it is resolved
+                    // in its module.
+                    let module = item.syntax().parent()?;
+                    if !matches!(module.kind(), SOURCE_FILE | ITEM_LIST) {
+                        return None;
+                    }
+                    item.attrs().next()?;
+                    let sa = self.analyze2(token.with_value(&module), None);
+                    sa.expand_attr(self.db, token.with_value(&item))
+                })
+                .last();
+            let file_id = match attr_expansion {
+                Some(it) => it,
+                None => {
+                    let macro_call = token.value.ancestors().find_map(ast::MacroCall::cast)?;
+                    let tt = macro_call.token_tree()?;
+                    if !tt.syntax().text_range().contains_range(token.value.text_range()) {
+                        return None;
+                    }
+                    let sa = self.analyze2(token.with_value(macro_call.syntax()), None);
+                    sa.expand(self.db, token.with_value(&macro_call))?
+                }
+            };
             let token = file_id.expansion_info(self.db.upcast())?.map_token_down(token.as_ref())?;
 
             self.cache(find_root(&token.value.parent()), token.file_id);
@@ -438,6 +485,10 @@ impl<'db> SemanticsImpl<'db> {
         self.with_ctx(|ctx| ctx.file_to_def(file)).map(Module::from)
     }
 
+    pub fn to_module_defs(&self, file: FileId) -> Vec<Module> {
+        self.with_ctx(|ctx| ctx.file_to_defs(file)).into_iter().map(Module::from).collect()
+    }
+
     pub fn scope(&self, node: &SyntaxNode) -> SemanticsScope<'db> {
         let node = self.find_file(node.clone());
         let resolver = self.analyze2(node.as_ref(), None).resolver;
@@ -625,6 +676,10 @@ impl<'a> SemanticsScope<'a> {
 
 // FIXME: Change `HasSource` trait to work with `Semantics` and remove this?
 pub fn original_range(db: &dyn HirDatabase, node: InFile<&SyntaxNode>) -> FileRange {
+    // Everything a derive generates is attributed to its `#[derive]` attribute.
+    if let Some(attr) = node.file_id.derive_attr(db.upcast()) {
+        return original_range(db, attr.as_ref().map(|it| it.syntax()));
+    }
     if let Some(range) = original_range_opt(db, node) {
         let original_file = range.file_id.original_file(db.upcast());
         if range.file_id == original_file.into() {
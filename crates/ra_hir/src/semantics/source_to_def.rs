@@ -37,6 +37,18 @@ impl SourceToDefCtx<'_, '_> {
         Some(ModuleId { krate, local_id })
     }
 
+    pub(super) fn file_to_defs(&mut self, file: FileId) -> Vec<ModuleId> {
+        let _p = profile("SourceBinder::to_module_defs");
+        let mut res = Vec::new();
+        for &krate in self.db.relevant_crates(file).iter() {
+            let crate_def_map = self.db.crate_def_map(krate);
+            res.extend(
+                crate_def_map.modules_for_file(file).map(|local_id| ModuleId { krate, local_id }),
+            );
+        }
+        res
+    }
+
     pub(super) fn module_to_def(&mut self, src: InFile<ast::Module>) -> Option<ModuleId> {
         let _p = profile("module_to_def");
         let parent_declaration = src
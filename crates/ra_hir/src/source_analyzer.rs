@@ -16,7 +16,11 @@ use hir_def::{
     resolver::{resolver_for_scope, Resolver, TypeNs, ValueNs},
     AsMacroCall, DefWithBodyId, FieldId, LocalFieldId, VariantId,
 };
-use hir_expand::{hygiene::Hygiene, name::AsName, HirFileId, InFile};
+use hir_expand::{
+    hygiene::{Hygiene, SyntaxContext},
+    name::AsName,
+    HirFileId, InFile,
+};
 use hir_ty::{
     expr::{record_literal_missing_fields, record_pattern_missing_fields},
     InferenceResult, Substs, Ty,
@@ -211,8 +215,10 @@ impl SourceAnalyzer {
         db: &dyn HirDatabase,
         path: &ast::Path,
     ) -> Option<PathResolution> {
+        let mut context = None;
         if let Some(path_expr) = path.syntax().parent().and_then(ast::PathExpr::cast) {
             let expr_id = self.expr_id(db, &path_expr.into())?;
+            context = self.body.as_ref().map(|it| it.path_context(expr_id));
             if let Some(assoc) = self.infer.as_ref()?.assoc_resolutions_for_expr(expr_id) {
                 return Some(PathResolution::AssocItem(assoc.into()));
             }
@@ -267,7 +273,7 @@ impl SourceAnalyzer {
             }
         }
 
-        resolve_hir_path(db, &self.resolver, &hir_path)
+        resolve_hir_path_(db, &self.resolver, &hir_path, context)
     }
 
     pub(crate) fn record_literal_missing_fields(
@@ -344,6 +350,19 @@ impl SourceAnalyzer {
         Some(macro_call_id.as_file())
     }
 
+    /// Returns the expansion of the attribute macro `item` is annotated with.
+    pub(crate) fn expand_attr(
+        &self,
+        db: &dyn HirDatabase,
+        item: InFile<&ast::ModuleItem>,
+    ) -> Option<HirFileId> {
+        let krate = self.resolver.krate()?;
+        let macro_call_id = item.as_call_id(db.upcast(), krate, |path| {
+            self.resolver.resolve_path_as_macro(db.upcast(), &path)
+        })?;
+        Some(macro_call_id.as_file())
+    }
+
     pub(crate) fn resolve_variant(
         &self,
         db: &dyn HirDatabase,
@@ -443,6 +462,16 @@ pub(crate) fn resolve_hir_path(
     db: &dyn HirDatabase,
     resolver: &Resolver,
     path: &crate::Path,
+) -> Option<PathResolution> {
+    resolve_hir_path_(db, resolver, path, None)
+}
+
+/// Resolves `path`, only to local variables bound in `context` if it is set.
+fn resolve_hir_path_(
+    db: &dyn HirDatabase,
+    resolver: &Resolver,
+    path: &crate::Path,
+    context: Option<SyntaxContext>,
 ) -> Option<PathResolution> {
     let types =
         resolver.resolve_path_in_type_ns_fully(db.upcast(), path.mod_path()).map(|ty| match ty {
@@ -458,22 +487,29 @@ pub(crate) fn resolve_hir_path(
         });
 
     let body_owner = resolver.body_owner();
-    let values =
-        resolver.resolve_path_in_value_ns_fully(db.upcast(), path.mod_path()).and_then(|val| {
-            let res = match val {
-                ValueNs::LocalBinding(pat_id) => {
-                    let var = Local { parent: body_owner?.into(), pat_id };
-                    PathResolution::Local(var)
-                }
-                ValueNs::FunctionId(it) => PathResolution::Def(Function::from(it).into()),
-                ValueNs::ConstId(it) => PathResolution::Def(Const::from(it).into()),
-                ValueNs::StaticId(it) => PathResolution::Def(Static::from(it).into()),
-                ValueNs::StructId(it) => PathResolution::Def(Struct::from(it).into()),
-                ValueNs::EnumVariantId(it) => PathResolution::Def(EnumVariant::from(it).into()),
-                ValueNs::ImplSelf(impl_id) => PathResolution::SelfType(impl_id.into()),
-            };
-            Some(res)
-        });
+    let values = match context {
+        Some(context) => resolver.resolve_path_in_value_ns_fully_with_context(
+            db.upcast(),
+            path.mod_path(),
+            context,
+        ),
+        None => resolver.resolve_path_in_value_ns_fully(db.upcast(), path.mod_path()),
+    };
+    let values = values.and_then(|val| {
+        let res = match val {
+            ValueNs::LocalBinding(pat_id) => {
+                let var = Local { parent: body_owner?.into(), pat_id };
+                PathResolution::Local(var)
+            }
+            ValueNs::FunctionId(it) => PathResolution::Def(Function::from(it).into()),
+            ValueNs::ConstId(it) => PathResolution::Def(Const::from(it).into()),
+            ValueNs::StaticId(it) => PathResolution::Def(Static::from(it).into()),
+            ValueNs::StructId(it) => PathResolution::Def(Struct::from(it).into()),
+            ValueNs::EnumVariantId(it) => PathResolution::Def(EnumVariant::from(it).into()),
+            ValueNs::ImplSelf(impl_id) => PathResolution::SelfType(impl_id.into()),
+        };
+        Some(res)
+    });
 
     let items = resolver
         .resolve_module_path_in_items(db.upcast(), path.mod_path())
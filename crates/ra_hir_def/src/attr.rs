@@ -16,7 +16,7 @@ use crate::{
     db::DefDatabase,
     item_tree::{ItemTreeId, ItemTreeNode},
     nameres::ModuleSource,
-    path::ModPath,
+    path::{ModPath, PathKind},
     src::HasChildSource,
     AdtId, AttrDefId, Lookup,
 };
@@ -162,8 +162,83 @@ impl Attr {
 
         Some(Attr { path, input })
     }
+
+    /// Whether this attribute is handled by the compiler or by a tool, like
+    /// `#[inline]` or `#[rustfmt::skip]`, rather than naming an attribute macro.
+    pub(crate) fn is_inert(&self) -> bool {
+        if self.path.kind != PathKind::Plain {
+            return false;
+        }
+        match self.path.segments.as_slice() {
+            [name] => {
+                let name = name.to_string();
+                name.starts_with("rustc_") || INERT_ATTRS.contains(&name.as_str())
+            }
+            [tool, _, ..] => TOOL_MODULES.contains(&tool.to_string().as_str()),
+            [] => false,
+        }
+    }
 }
 
+/// The built-in attributes, including unstable ones.
+const INERT_ATTRS: &[&str] = &[
+    "allow",
+    "automatically_derived",
+    "bench",
+    "cfg",
+    "cfg_attr",
+    "cold",
+    "crate_name",
+    "crate_type",
+    "deny",
+    "deprecated",
+    "derive",
+    "doc",
+    "export_name",
+    "feature",
+    "forbid",
+    "global_allocator",
+    "ignore",
+    "inline",
+    "lang",
+    "link",
+    "link_name",
+    "link_section",
+    "macro_export",
+    "macro_use",
+    "must_use",
+    "no_builtins",
+    "no_core",
+    "no_implicit_prelude",
+    "no_link",
+    "no_main",
+    "no_mangle",
+    "no_std",
+    "non_exhaustive",
+    "panic_handler",
+    "path",
+    "proc_macro",
+    "proc_macro_attribute",
+    "proc_macro_derive",
+    "recursion_limit",
+    "repr",
+    "should_panic",
+    "stable",
+    "target_feature",
+    "test",
+    "test_runner",
+    "track_caller",
+    "type_length_limit",
+    "unstable",
+    "used",
+    "warn",
+    "windows_subsystem",
+];
+
+/// The tools whose attributes are allowed without a macro, like
+/// `#[rustfmt::skip]`.
+const TOOL_MODULES: &[&str] = &["clippy", "rustfmt"];
+
 #[derive(Debug, Clone, Copy)]
 pub struct AttrQuery<'a> {
     attrs: &'a Attrs,
@@ -1,22 +1,30 @@
 //! Defines `Body`: a lowered representation of bodies of functions, statics and
 //! consts.
 mod lower;
+mod diagnostics;
 pub mod scope;
 
 use std::{mem, ops::Index, sync::Arc};
 
 use drop_bomb::DropBomb;
 use either::Either;
-use hir_expand::{ast_id_map::AstIdMap, hygiene::Hygiene, AstId, HirFileId, InFile, MacroDefId};
+use hir_expand::{
+    ast_id_map::AstIdMap,
+    db::MacroError,
+    diagnostics::DiagnosticSink,
+    hygiene::{Hygiene, SyntaxContext},
+    AstId, HirFileId, InFile, MacroCallId, MacroDefId,
+};
 use ra_arena::{map::ArenaMap, Arena};
 use ra_cfg::CfgOptions;
 use ra_db::CrateId;
 use ra_prof::profile;
-use ra_syntax::{ast, AstNode, AstPtr};
+use ra_syntax::{ast, AstNode, AstPtr, SyntaxToken};
 use rustc_hash::FxHashMap;
 
 pub(crate) use lower::LowerCtx;
 
+use self::diagnostics::BodyDiagnostic;
 use crate::{
     attr::Attrs,
     db::DefDatabase,
@@ -42,7 +50,7 @@ pub(crate) struct Expander {
     current_file_id: HirFileId,
     ast_id_map: Arc<AstIdMap>,
     module: ModuleId,
-    recursive_limit: usize,
+    recursion_depth: usize,
 }
 
 impl CfgExpander {
@@ -81,7 +89,7 @@ impl Expander {
             current_file_id,
             ast_id_map,
             module,
-            recursive_limit: 0,
+            recursion_depth: 0,
         }
     }
 
@@ -90,9 +98,10 @@ impl Expander {
         db: &dyn DefDatabase,
         local_scope: Option<&ItemScope>,
         macro_call: ast::MacroCall,
-    ) -> Option<(Mark, T)> {
-        if self.recursive_limit > 1024 {
-            return None;
+    ) -> (Option<(Mark, T)>, Option<MacroError>) {
+        let limit = db.macro_limits().expansion_depth;
+        if self.recursion_depth >= limit {
+            return (None, Some(MacroError::DepthLimit { limit }));
         }
 
         let macro_call = InFile::new(self.current_file_id, &macro_call);
@@ -106,6 +115,12 @@ impl Expander {
             self.resolve_path_as_macro(db, &path)
         }) {
             let file_id = call_id.as_file();
+            // Besides the limits, only the failures of eager macros are
+            // reported: they are builtin, so they are surely real errors.
+            let err = db.macro_expand_error(call_id).filter(|err| {
+                matches!(err, MacroError::TokenLimit { .. })
+                    || matches!(call_id, MacroCallId::EagerMacro(_))
+            });
             if let Some(node) = db.parse_or_expand(file_id) {
                 if let Some(expr) = T::cast(node) {
                     log::debug!("macro expansion {:#?}", expr.syntax());
@@ -118,23 +133,22 @@ impl Expander {
                     self.cfg_expander.hygiene = Hygiene::new(db.upcast(), file_id);
                     self.current_file_id = file_id;
                     self.ast_id_map = db.ast_id_map(file_id);
-                    self.recursive_limit += 1;
+                    self.recursion_depth += 1;
 
-                    return Some((mark, expr));
+                    return (Some((mark, expr)), err);
                 }
             }
+            return (None, err);
         }
 
-        // FIXME: Instead of just dropping the error from expansion
-        // report it
-        None
+        (None, None)
     }
 
     pub(crate) fn exit(&mut self, db: &dyn DefDatabase, mut mark: Mark) {
         self.cfg_expander.hygiene = Hygiene::new(db.upcast(), mark.file_id);
         self.current_file_id = mark.file_id;
         self.ast_id_map = mem::take(&mut mark.ast_id_map);
-        self.recursive_limit -= 1;
+        self.recursion_depth -= 1;
         mark.bomb.defuse();
     }
 
@@ -150,6 +164,10 @@ impl Expander {
         Path::from_src(path, &self.cfg_expander.hygiene)
     }
 
+    fn syntax_context(&self, db: &dyn DefDatabase, token: SyntaxToken) -> SyntaxContext {
+        SyntaxContext::of_token(db.upcast(), self.to_source(token))
+    }
+
     fn resolve_path_as_macro(&self, db: &dyn DefDatabase, path: &ModPath) -> Option<MacroDefId> {
         self.crate_def_map
             .resolve_path(db, self.module.local_id, path, BuiltinShadowMode::Other)
@@ -184,6 +202,10 @@ pub struct Body {
     /// The `ExprId` of the actual body expression.
     pub body_expr: ExprId,
     pub item_scope: ItemScope,
+    /// The syntax contexts of bindings and of single-segment path
+    /// expressions, if they are not the root context.
+    binding_contexts: FxHashMap<PatId, SyntaxContext>,
+    path_contexts: FxHashMap<ExprId, SyntaxContext>,
 }
 
 pub type ExprPtr = AstPtr<ast::Expr>;
@@ -211,6 +233,7 @@ pub struct BodySourceMap {
     pat_map_back: ArenaMap<PatId, Result<PatSource, SyntheticSyntax>>,
     field_map: FxHashMap<(ExprId, usize), InFile<AstPtr<ast::RecordField>>>,
     expansions: FxHashMap<InFile<AstPtr<ast::MacroCall>>, HirFileId>,
+    diagnostics: Vec<BodyDiagnostic>,
 }
 
 #[derive(Default, Debug, Eq, PartialEq, Clone, Copy)]
@@ -222,6 +245,24 @@ impl Body {
         def: DefWithBodyId,
     ) -> (Arc<Body>, Arc<BodySourceMap>) {
         let _p = profile("body_with_source_map_query");
+        let (body, source_map) = Body::lower_def(db, def);
+        (Arc::new(body), Arc::new(source_map))
+    }
+
+    pub(crate) fn body_query(db: &dyn DefDatabase, def: DefWithBodyId) -> Arc<Body> {
+        // Source maps are only needed for IDE features and diagnostics in the
+        // edited code, so we don't keep them around for libraries. The library
+        // files open in the editor still need them, so their bodies are shared
+        // with `body_with_source_map` instead of being lowered twice.
+        let krate = def.module(db).krate;
+        if db.is_library(db.crate_graph()[krate].root_file_id) && !db.is_open(def.file_id(db)) {
+            let _p = profile("library_body_query");
+            return Arc::new(Body::lower_def(db, def).0);
+        }
+        db.body_with_source_map(def).0
+    }
+
+    fn lower_def(db: &dyn DefDatabase, def: DefWithBodyId) -> (Body, BodySourceMap) {
         let mut params = None;
 
         let (file_id, module, body) = match def {
@@ -243,12 +284,7 @@ impl Body {
             }
         };
         let expander = Expander::new(db, file_id, module);
-        let (body, source_map) = Body::new(db, def, expander, params, body);
-        (Arc::new(body), Arc::new(source_map))
-    }
-
-    pub(crate) fn body_query(db: &dyn DefDatabase, def: DefWithBodyId) -> Arc<Body> {
-        db.body_with_source_map(def).0
+        Body::new(db, def, expander, params, body)
     }
 
     fn new(
@@ -262,6 +298,16 @@ impl Body {
     }
 }
 
+impl Body {
+    pub fn binding_context(&self, pat: PatId) -> SyntaxContext {
+        self.binding_contexts.get(&pat).copied().unwrap_or(SyntaxContext::ROOT)
+    }
+
+    pub fn path_context(&self, expr: ExprId) -> SyntaxContext {
+        self.path_contexts.get(&expr).copied().unwrap_or(SyntaxContext::ROOT)
+    }
+}
+
 impl Index<ExprId> for Body {
     type Output = Expr;
 
@@ -279,6 +325,14 @@ impl Index<PatId> for Body {
 }
 
 impl BodySourceMap {
+    /// Reports the macro calls in the body which hit the expansion limits, or
+    /// whose eager expansion failed.
+    pub fn add_diagnostics(&self, sink: &mut DiagnosticSink) {
+        for diag in &self.diagnostics {
+            diag.add_to(sink);
+        }
+    }
+
     pub fn expr_syntax(&self, expr: ExprId) -> Result<ExprSource, SyntheticSyntax> {
         self.expr_map_back[expr].clone()
     }
@@ -306,3 +360,45 @@ impl BodySourceMap {
         self.field_map[&(expr, field)].clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ra_db::{
+        fixture::{WithFixture, WORKSPACE},
+        SourceDatabaseExt,
+    };
+
+    use crate::{db::DefDatabase, test_db::TestDB, DefWithBodyId, ModuleDefId};
+
+    fn library_function(open: bool) -> (TestDB, DefWithBodyId) {
+        let (mut db, file_id) = TestDB::with_single_file("fn foo() { 92; }");
+        let mut root = (*db.source_root(WORKSPACE)).clone();
+        root.is_library = true;
+        db.set_source_root(WORKSPACE, Arc::new(root));
+        if open {
+            db.set_open_files(Arc::new(Some(file_id).into_iter().collect()));
+        }
+
+        let crate_def_map = db.crate_def_map(db.test_crate());
+        let module = crate_def_map.modules_for_file(file_id).next().unwrap();
+        let (_, def) = crate_def_map[module].scope.entries().next().unwrap();
+        match def.take_values().unwrap() {
+            ModuleDefId::FunctionId(it) => (db, it.into()),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn library_bodies_are_lowered_without_source_map() {
+        let (db, def) = library_function(false);
+        assert!(!Arc::ptr_eq(&db.body(def), &db.body_with_source_map(def).0));
+    }
+
+    #[test]
+    fn open_library_bodies_share_the_source_map_lowering() {
+        let (db, def) = library_function(true);
+        assert!(Arc::ptr_eq(&db.body(def), &db.body_with_source_map(def).0));
+    }
+}
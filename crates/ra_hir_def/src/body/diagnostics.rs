@@ -0,0 +1,23 @@
+//! Diagnostics emitted during body lowering.
+
+use hir_expand::{diagnostics::DiagnosticSink, InFile};
+use ra_syntax::{ast, AstPtr};
+
+use crate::diagnostics::MacroError;
+
+#[derive(Debug, Eq, PartialEq)]
+pub(super) enum BodyDiagnostic {
+    MacroError { call: InFile<AstPtr<ast::MacroCall>>, message: String },
+}
+
+impl BodyDiagnostic {
+    pub(super) fn add_to(&self, sink: &mut DiagnosticSink) {
+        match self {
+            BodyDiagnostic::MacroError { call, message } => sink.push(MacroError {
+                file: call.file_id,
+                node: call.value.clone().into(),
+                message: message.clone(),
+            }),
+        }
+    }
+}
@@ -3,7 +3,7 @@
 
 use either::Either;
 use hir_expand::{
-    hygiene::Hygiene,
+    hygiene::{Hygiene, SyntaxContext},
     name::{name, AsName, Name},
     HirFileId, MacroDefId, MacroDefKind,
 };
@@ -11,7 +11,7 @@ use ra_arena::Arena;
 use ra_syntax::{
     ast::{
         self, ArgListOwner, ArrayExprKind, LiteralKind, LoopBodyOwner, ModuleItemOwner, NameOwner,
-        SlicePatComponents, TypeAscriptionOwner,
+        RangeOp, SlicePatComponents, TypeAscriptionOwner,
     },
     AstNode, AstPtr,
 };
@@ -19,7 +19,7 @@ use test_utils::mark;
 
 use crate::{
     adt::StructKind,
-    body::{Body, BodySourceMap, Expander, PatPtr, SyntheticSyntax},
+    body::{diagnostics::BodyDiagnostic, Body, BodySourceMap, Expander, PatPtr, SyntheticSyntax},
     builtin_type::{BuiltinFloat, BuiltinInt},
     db::DefDatabase,
     expr::{
@@ -74,6 +74,8 @@ pub(super) fn lower(
             params: Vec::new(),
             body_expr: dummy_expr_id(),
             item_scope: Default::default(),
+            binding_contexts: FxHashMap::default(),
+            path_contexts: FxHashMap::default(),
         },
         item_trees: {
             let mut map = FxHashMap::default();
@@ -323,8 +325,10 @@ impl ExprCollector<'_> {
                     Vec::new()
                 };
                 let method_name = e.name_ref().map(|nr| nr.as_name()).unwrap_or_else(Name::missing);
-                let generic_args =
-                    e.type_arg_list().and_then(|it| GenericArgs::from_ast(&self.ctx(), it));
+                let generic_args = e
+                    .type_arg_list()
+                    .and_then(|it| GenericArgs::from_ast(&self.ctx(), it))
+                    .map(Box::new);
                 self.alloc_expr(
                     Expr::MethodCall { receiver, method_name, args, generic_args },
                     syntax_ptr,
@@ -355,7 +359,19 @@ impl ExprCollector<'_> {
                     .and_then(|path| self.expander.parse_path(path))
                     .map(Expr::Path)
                     .unwrap_or(Expr::Missing);
-                self.alloc_expr(path, syntax_ptr)
+                let id = self.alloc_expr(path, syntax_ptr);
+                // Only plain identifiers can refer to local variables.
+                let ident = e
+                    .path()
+                    .filter(|it| it.qualifier().is_none())
+                    .and_then(|it| it.segment()?.name_ref()?.syntax().first_token());
+                if let Some(ident) = ident {
+                    let context = self.expander.syntax_context(self.db, ident);
+                    if context != SyntaxContext::ROOT {
+                        self.body.path_contexts.insert(id, context);
+                    }
+                }
+                id
             }
             ast::Expr::ContinueExpr(e) => self.alloc_expr(
                 Expr::Continue { label: e.lifetime_token().map(|l| Name::new_lifetime(&l)) },
@@ -380,7 +396,7 @@ impl ExprCollector<'_> {
                 self.alloc_expr(Expr::Return { expr }, syntax_ptr)
             }
             ast::Expr::RecordLit(e) => {
-                let path = e.path().and_then(|path| self.expander.parse_path(path));
+                let path = e.path().and_then(|path| self.expander.parse_path(path)).map(Box::new);
                 let mut field_ptrs = Vec::new();
                 let record_lit = if let Some(nfl) = e.record_field_list() {
                     let fields = nfl
@@ -432,7 +448,7 @@ impl ExprCollector<'_> {
             }
             ast::Expr::CastExpr(e) => {
                 let expr = self.collect_expr_opt(e.expr());
-                let type_ref = TypeRef::from_ast_opt(&self.ctx(), e.type_ref());
+                let type_ref = Box::new(TypeRef::from_ast_opt(&self.ctx(), e.type_ref()));
                 self.alloc_expr(Expr::Cast { expr, type_ref }, syntax_ptr)
             }
             ast::Expr::RefExpr(e) => {
@@ -475,7 +491,7 @@ impl ExprCollector<'_> {
                 let ret_type = e
                     .ret_type()
                     .and_then(|r| r.type_ref())
-                    .map(|it| TypeRef::from_ast(&self.ctx(), it));
+                    .map(|it| Box::new(TypeRef::from_ast(&self.ctx(), it)));
                 let body = self.collect_expr_opt(e.body());
                 self.alloc_expr(Expr::Lambda { args, arg_types, ret_type, body }, syntax_ptr)
             }
@@ -543,7 +559,15 @@ impl ExprCollector<'_> {
                     self.alloc_expr(Expr::Missing, syntax_ptr)
                 } else {
                     let macro_call = self.expander.to_source(AstPtr::new(&e));
-                    match self.expander.enter_expand(self.db, Some(&self.body.item_scope), e) {
+                    let (expansion, err) =
+                        self.expander.enter_expand(self.db, Some(&self.body.item_scope), e);
+                    if let Some(err) = err {
+                        self.source_map.diagnostics.push(BodyDiagnostic::MacroError {
+                            call: macro_call.clone(),
+                            message: err.to_string(),
+                        });
+                    }
+                    match expansion {
                         Some((mark, expansion)) => {
                             self.source_map
                                 .expansions
@@ -608,7 +632,7 @@ impl ExprCollector<'_> {
                 ast::Stmt::LetStmt(stmt) => {
                     let pat = self.collect_pat_opt(stmt.pat());
                     let type_ref =
-                        stmt.ascribed_type().map(|it| TypeRef::from_ast(&self.ctx(), it));
+                        stmt.ascribed_type().map(|it| Box::new(TypeRef::from_ast(&self.ctx(), it)));
                     let initializer = stmt.initializer().map(|e| self.collect_expr(e));
                     Statement::Let { pat, type_ref, initializer }
                 }
@@ -707,8 +731,12 @@ impl ExprCollector<'_> {
     }
 
     fn collect_pat(&mut self, pat: ast::Pat) -> PatId {
+        let mut context = SyntaxContext::ROOT;
         let pattern = match &pat {
             ast::Pat::BindPat(bp) => {
+                if let Some(ident) = bp.name().and_then(|it| it.ident_token()) {
+                    context = self.expander.syntax_context(self.db, ident);
+                }
                 let name = bp.name().map(|nr| nr.as_name()).unwrap_or_else(Name::missing);
                 let annotation =
                     BindingAnnotation::new(bp.mut_token().is_some(), bp.ref_token().is_some());
@@ -746,7 +774,7 @@ impl ExprCollector<'_> {
                 }
             }
             ast::Pat::TupleStructPat(p) => {
-                let path = p.path().and_then(|path| self.expander.parse_path(path));
+                let path = p.path().and_then(|path| self.expander.parse_path(path)).map(Box::new);
                 let (args, ellipsis) = self.collect_tuple_pat(p.args());
                 Pat::TupleStruct { path, args, ellipsis }
             }
@@ -770,7 +798,7 @@ impl ExprCollector<'_> {
             }
             ast::Pat::PlaceholderPat(_) => Pat::Wild,
             ast::Pat::RecordPat(p) => {
-                let path = p.path().and_then(|path| self.expander.parse_path(path));
+                let path = p.path().and_then(|path| self.expander.parse_path(path)).map(Box::new);
                 let record_field_pat_list =
                     p.record_field_pat_list().expect("every struct should have a field list");
                 let mut fields: Vec<_> = record_field_pat_list
@@ -824,11 +852,51 @@ impl ExprCollector<'_> {
 
                 Pat::Missing
             }
+            ast::Pat::RangePat(p) => {
+                let start = self.collect_range_pat_bound(p.start());
+                let end = p.end().map(|it| self.collect_range_pat_bound(Some(it)));
+                let range_type = p.op_kind().unwrap_or(RangeOp::Exclusive);
+                Pat::Range { start, end, range_type }
+            }
             // FIXME: implement
-            ast::Pat::BoxPat(_) | ast::Pat::RangePat(_) | ast::Pat::MacroPat(_) => Pat::Missing,
+            ast::Pat::BoxPat(_) | ast::Pat::MacroPat(_) => Pat::Missing,
         };
         let ptr = AstPtr::new(&pat);
-        self.alloc_pat(pattern, Either::Left(ptr))
+        let id = self.alloc_pat(pattern, Either::Left(ptr));
+        if context != SyntaxContext::ROOT {
+            self.body.binding_contexts.insert(id, context);
+        }
+        id
+    }
+
+    /// Range pattern bounds are parsed as patterns, but they are constant
+    /// expressions: literals or paths to constants.
+    fn collect_range_pat_bound(&mut self, pat: Option<ast::Pat>) -> ExprId {
+        match pat {
+            Some(ast::Pat::LiteralPat(lit)) => match lit.literal() {
+                Some(ast_lit) => {
+                    let expr = Expr::Literal(ast_lit.kind().into());
+                    let expr_ptr = AstPtr::new(&ast::Expr::Literal(ast_lit));
+                    self.alloc_expr(expr, expr_ptr)
+                }
+                None => self.missing_expr(),
+            },
+            Some(ast::Pat::PathPat(p)) => {
+                match p.path().and_then(|path| self.expander.parse_path(path)) {
+                    Some(path) => self.alloc_expr_desugared(Expr::Path(path)),
+                    None => self.missing_expr(),
+                }
+            }
+            // A single identifier is parsed as a binding.
+            Some(ast::Pat::BindPat(p)) if p.pat().is_none() => match p.name() {
+                Some(name) => {
+                    let path = Path::from(name.as_name());
+                    self.alloc_expr_desugared(Expr::Path(path))
+                }
+                None => self.missing_expr(),
+            },
+            _ => self.missing_expr(),
+        }
     }
 
     fn collect_pat_opt(&mut self, pat: Option<ast::Pat>) -> PatId {
@@ -1,7 +1,7 @@
 //! Name resolution for expressions.
 use std::sync::Arc;
 
-use hir_expand::name::Name;
+use hir_expand::{hygiene::SyntaxContext, name::Name};
 use ra_arena::{Arena, Idx};
 use rustc_hash::FxHashMap;
 
@@ -24,6 +24,7 @@ pub struct ExprScopes {
 pub struct ScopeEntry {
     name: Name,
     pat: PatId,
+    context: SyntaxContext,
 }
 
 impl ScopeEntry {
@@ -34,6 +35,10 @@ impl ScopeEntry {
     pub fn pat(&self) -> PatId {
         self.pat
     }
+
+    pub fn context(&self) -> SyntaxContext {
+        self.context
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -89,7 +94,7 @@ impl ExprScopes {
     fn add_bindings(&mut self, body: &Body, scope: ScopeId, pat: PatId) {
         let pattern = &body[pat];
         if let Pat::Bind { name, .. } = pattern {
-            let entry = ScopeEntry { name: name.clone(), pat };
+            let entry = ScopeEntry { name: name.clone(), pat, context: body.binding_context(pat) };
             self.scopes[scope].entries.push(entry);
         }
 
@@ -77,7 +77,7 @@ pub enum Expr {
         receiver: ExprId,
         method_name: Name,
         args: Vec<ExprId>,
-        generic_args: Option<GenericArgs>,
+        generic_args: Option<Box<GenericArgs>>,
     },
     Match {
         expr: ExprId,
@@ -94,7 +94,7 @@ pub enum Expr {
         expr: Option<ExprId>,
     },
     RecordLit {
-        path: Option<Path>,
+        path: Option<Box<Path>>,
         fields: Vec<RecordLitField>,
         spread: Option<ExprId>,
     },
@@ -113,7 +113,7 @@ pub enum Expr {
     },
     Cast {
         expr: ExprId,
-        type_ref: TypeRef,
+        type_ref: Box<TypeRef>,
     },
     Ref {
         expr: ExprId,
@@ -144,7 +144,7 @@ pub enum Expr {
     Lambda {
         args: Vec<PatId>,
         arg_types: Vec<Option<TypeRef>>,
-        ret_type: Option<TypeRef>,
+        ret_type: Option<Box<TypeRef>>,
         body: ExprId,
     },
     Tuple {
@@ -219,7 +219,7 @@ pub struct RecordLitField {
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Statement {
-    Let { pat: PatId, type_ref: Option<TypeRef>, initializer: Option<ExprId> },
+    Let { pat: PatId, type_ref: Option<Box<TypeRef>>, initializer: Option<ExprId> },
     Expr(ExprId),
 }
 
@@ -385,16 +385,43 @@ pub struct RecordFieldPat {
 pub enum Pat {
     Missing,
     Wild,
-    Tuple { args: Vec<PatId>, ellipsis: Option<usize> },
+    Tuple {
+        args: Vec<PatId>,
+        ellipsis: Option<usize>,
+    },
     Or(Vec<PatId>),
-    Record { path: Option<Path>, args: Vec<RecordFieldPat>, ellipsis: bool },
-    Range { start: ExprId, end: ExprId },
-    Slice { prefix: Vec<PatId>, slice: Option<PatId>, suffix: Vec<PatId> },
+    Record {
+        path: Option<Box<Path>>,
+        args: Vec<RecordFieldPat>,
+        ellipsis: bool,
+    },
+    /// The end is missing in half-open patterns like `0..`.
+    Range {
+        start: ExprId,
+        end: Option<ExprId>,
+        range_type: RangeOp,
+    },
+    Slice {
+        prefix: Vec<PatId>,
+        slice: Option<PatId>,
+        suffix: Vec<PatId>,
+    },
     Path(Path),
     Lit(ExprId),
-    Bind { mode: BindingAnnotation, name: Name, subpat: Option<PatId> },
-    TupleStruct { path: Option<Path>, args: Vec<PatId>, ellipsis: Option<usize> },
-    Ref { pat: PatId, mutability: Mutability },
+    Bind {
+        mode: BindingAnnotation,
+        name: Name,
+        subpat: Option<PatId>,
+    },
+    TupleStruct {
+        path: Option<Box<Path>>,
+        args: Vec<PatId>,
+        ellipsis: Option<usize>,
+    },
+    Ref {
+        pat: PatId,
+        mutability: Mutability,
+    },
 }
 
 impl Pat {
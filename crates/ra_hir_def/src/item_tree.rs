@@ -124,6 +124,8 @@ impl ItemTree {
     }
 
     fn shrink_to_fit(&mut self) {
+        self.attrs.shrink_to_fit();
+        self.inner_items.shrink_to_fit();
         if let Some(data) = &mut self.data {
             let ItemTreeData {
                 imports,
@@ -57,10 +57,10 @@ use hir_expand::{
     MacroCallId, MacroCallKind, MacroDefId, MacroDefKind,
 };
 use ra_arena::Idx;
-use ra_db::{impl_intern_key, salsa, CrateId};
-use ra_syntax::ast;
+use ra_db::{impl_intern_key, salsa, CrateId, FileId};
+use ra_syntax::ast::{self, AttrsOwner};
 
-use crate::builtin_type::BuiltinType;
+use crate::{attr::Attrs, builtin_type::BuiltinType};
 use item_tree::{
     Const, Enum, Function, Impl, ItemTreeId, ItemTreeNode, ModItem, Static, Struct, Trait,
     TypeAlias, Union,
@@ -451,6 +451,16 @@ impl DefWithBodyId {
             DefWithBodyId::ConstId(it) => it.lookup(db).id.value.into(),
         }
     }
+
+    /// The file the body is written in, looking through macro expansions.
+    pub fn file_id(self, db: &dyn db::DefDatabase) -> FileId {
+        let file_id = match self {
+            DefWithBodyId::FunctionId(it) => it.lookup(db).id.file_id,
+            DefWithBodyId::StaticId(it) => it.lookup(db).id.file_id,
+            DefWithBodyId::ConstId(it) => it.lookup(db).id.file_id,
+        };
+        file_id.original_file(db.upcast())
+    }
 }
 
 impl HasModule for GenericDefId {
@@ -498,6 +508,25 @@ impl AsMacroCall for InFile<&ast::MacroCall> {
     }
 }
 
+/// Resolves the first attribute macro the item is annotated with, skipping
+/// the attributes which are inert or don't resolve to one, like the def
+/// collector does.
+impl AsMacroCall for InFile<&ast::ModuleItem> {
+    fn as_call_id(
+        &self,
+        db: &dyn db::DefDatabase,
+        krate: CrateId,
+        resolver: impl Fn(path::ModPath) -> Option<MacroDefId>,
+    ) -> Option<MacroCallId> {
+        let ast_id = AstId::new(self.file_id, db.ast_id_map(self.file_id).ast_id(self.value));
+        let attrs = Attrs::from_attrs_owner(db, self.as_ref().map(|it| *it as &dyn AttrsOwner));
+        attrs.iter().filter(|attr| !attr.is_inert()).find_map(|attr| {
+            let def = resolver(attr.path.clone()).filter(|it| it.is_attr_macro(db.upcast()))?;
+            AstIdWithPath { ast_id, path: attr.path.clone() }.as_call_id(db, krate, |_| Some(def))
+        })
+    }
+}
+
 /// Helper wrapper for `AstId` with `ModPath`
 #[derive(Clone, Debug, Eq, PartialEq)]
 struct AstIdWithPath<T: ast::AstNode> {
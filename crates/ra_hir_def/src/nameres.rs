@@ -288,10 +288,15 @@ pub enum ModuleSource {
 }
 
 mod diagnostics {
-    use hir_expand::diagnostics::DiagnosticSink;
-    use ra_syntax::{ast, AstPtr};
+    use hir_expand::{diagnostics::DiagnosticSink, MacroCallKind};
+    use ra_syntax::{ast, AstPtr, SyntaxNodePtr};
 
-    use crate::{db::DefDatabase, diagnostics::UnresolvedModule, nameres::LocalModuleId, AstId};
+    use crate::{
+        db::DefDatabase,
+        diagnostics::{MacroError, UnresolvedModule},
+        nameres::LocalModuleId,
+        AstId,
+    };
 
     #[derive(Debug, PartialEq, Eq)]
     pub(super) enum DefDiagnostic {
@@ -300,6 +305,11 @@ mod diagnostics {
             declaration: AstId<ast::Module>,
             candidate: String,
         },
+        MacroError {
+            module: LocalModuleId,
+            ast: MacroCallKind,
+            message: String,
+        },
     }
 
     impl DefDiagnostic {
@@ -321,6 +331,17 @@ mod diagnostics {
                         candidate: candidate.clone(),
                     })
                 }
+                DefDiagnostic::MacroError { module, ast, message } => {
+                    if *module != target_module {
+                        return;
+                    }
+                    let node = ast.node(db.upcast());
+                    sink.push(MacroError {
+                        file: node.file_id,
+                        node: SyntaxNodePtr::new(&node.value),
+                        message: message.clone(),
+                    })
+                }
             }
         }
     }
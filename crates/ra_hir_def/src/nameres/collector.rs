@@ -7,12 +7,13 @@ use hir_expand::{
     ast_id_map::FileAstId,
     builtin_derive::find_builtin_derive,
     builtin_macro::find_builtin_macro,
+    db::MacroError,
     name::{name, AsName, Name},
     proc_macro::ProcMacroExpander,
     HirFileId, MacroCallId, MacroDefId, MacroDefKind,
 };
 use ra_cfg::CfgOptions;
-use ra_db::{CrateId, FileId, ProcMacroId};
+use ra_db::{CrateId, FileId, ProcMacroId, ProcMacroKind};
 use ra_syntax::ast;
 use rustc_hash::FxHashMap;
 use test_utils::mark;
@@ -65,9 +66,19 @@ pub(super) fn collect_defs(db: &dyn DefDatabase, mut def_map: CrateDefMap) -> Cr
         .map(|(idx, it)| {
             // FIXME: a hacky way to create a Name from string.
             let name = tt::Ident { text: it.name.clone(), id: tt::TokenId::unspecified() };
-            (name.as_name(), ProcMacroExpander::new(def_map.krate, ProcMacroId(idx as u32)))
+            let expander = ProcMacroExpander::new(def_map.krate, ProcMacroId(idx as u32));
+            let kind = match it.kind {
+                ProcMacroKind::CustomDerive => MacroDefKind::CustomDerive(expander),
+                ProcMacroKind::FuncLike | ProcMacroKind::Attr => MacroDefKind::ProcMacro(expander),
+            };
+            (name.as_name(), kind)
         })
         .collect();
+    // Attribute macros can only come from dependencies, so if there are none,
+    // attributes don't have to be resolved.
+    let has_attr_macros = crate_graph
+        .transitive_deps(def_map.krate)
+        .any(|krate| crate_graph[krate].proc_macro.iter().any(|it| it.kind == ProcMacroKind::Attr));
 
     let mut collector = DefCollector {
         db,
@@ -78,9 +89,11 @@ pub(super) fn collect_defs(db: &dyn DefDatabase, mut def_map: CrateDefMap) -> Cr
 
         unexpanded_macros: Vec::new(),
         unexpanded_attribute_macros: Vec::new(),
+        unresolved_attrs: Vec::new(),
         mod_dirs: FxHashMap::default(),
         cfg_options,
         proc_macros,
+        has_attr_macros,
         from_glob_import: Default::default(),
     };
     collector.collect();
@@ -169,6 +182,19 @@ struct DeriveDirective {
     ast_id: AstIdWithPath<ast::ModuleItem>,
 }
 
+/// An item with an attribute which might be an attribute macro.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct AttrMacroDirective {
+    module_id: LocalModuleId,
+    /// The item, with the path of the attribute.
+    ast_id: AstIdWithPath<ast::ModuleItem>,
+    item: ModItem,
+    /// The index of the attribute in the attributes of the item.
+    attr_index: usize,
+    /// The expansion depth of the item.
+    depth: usize,
+}
+
 struct DefData<'a> {
     id: ModuleDefId,
     name: &'a Name,
@@ -185,9 +211,11 @@ struct DefCollector<'a> {
     resolved_imports: Vec<ImportDirective>,
     unexpanded_macros: Vec<MacroDirective>,
     unexpanded_attribute_macros: Vec<DeriveDirective>,
+    unresolved_attrs: Vec<AttrMacroDirective>,
     mod_dirs: FxHashMap<LocalModuleId, ModDir>,
     cfg_options: &'a CfgOptions,
-    proc_macros: Vec<(Name, ProcMacroExpander)>,
+    proc_macros: Vec<(Name, MacroDefKind)>,
+    has_attr_macros: bool,
     from_glob_import: PerNsGlobImports,
 }
 
@@ -251,15 +279,11 @@ impl DefCollector<'_> {
 
     fn collect_proc_macro(&mut self) {
         let proc_macros = std::mem::take(&mut self.proc_macros);
-        for (name, expander) in proc_macros {
+        for (name, kind) in proc_macros {
             let krate = self.def_map.krate;
 
-            let macro_id = MacroDefId {
-                ast_id: None,
-                krate: Some(krate),
-                kind: MacroDefKind::CustomDerive(expander),
-                local_inner: false,
-            };
+            let macro_id =
+                MacroDefId { ast_id: None, krate: Some(krate), kind, local_inner: false };
 
             self.define_proc_macro(name.clone(), macro_id);
         }
@@ -673,17 +697,77 @@ impl DefCollector<'_> {
             true
         });
 
+        let mut attr_macros = std::mem::take(&mut self.unresolved_attrs);
+        let mut resolved_attrs = Vec::new();
+        let mut inert_attrs = Vec::new();
+        attr_macros.retain(|directive| {
+            let resolved_res = self.def_map.resolve_path_fp_with_macro(
+                self.db,
+                ResolveMode::Other,
+                directive.module_id,
+                &directive.ast_id.path,
+                BuiltinShadowMode::Module,
+            );
+            let def = match resolved_res.resolved_def.take_macros() {
+                Some(it) => it,
+                None => return true,
+            };
+            res = ReachedFixedPoint::No;
+            // Other macros can't be used as attributes, so the attribute is
+            // treated as inert.
+            match directive.ast_id.as_call_id(self.db, self.def_map.krate, |_| Some(def)) {
+                Some(call_id) if def.is_attr_macro(self.db.upcast()) => {
+                    resolved_attrs.push((directive.clone(), call_id))
+                }
+                _ => inert_attrs.push(directive.clone()),
+            }
+            false
+        });
+        // Attributes which don't resolve to anything are inert, like the
+        // helper attributes of derives.
+        if res == ReachedFixedPoint::Yes && !attr_macros.is_empty() {
+            inert_attrs.append(&mut attr_macros);
+            res = ReachedFixedPoint::No;
+        }
+
         self.unexpanded_macros = macros;
         self.unexpanded_attribute_macros = attribute_macros;
+        self.unresolved_attrs = attr_macros;
 
+        let depth_limit = self.db.macro_limits().expansion_depth;
         for (module_id, macro_call_id, depth) in resolved {
-            if depth > 1024 {
+            if depth >= depth_limit {
                 log::debug!("Max macro expansion depth reached");
+                let err = MacroError::DepthLimit { limit: depth_limit };
+                self.report_macro_error(module_id, macro_call_id, &err);
                 continue;
             }
             self.collect_macro_expansion(module_id, macro_call_id, depth);
         }
 
+        for directive in inert_attrs {
+            self.collect_attr_macro_item(&directive);
+        }
+        for (directive, macro_call_id) in resolved_attrs {
+            let depth = directive.depth + 1;
+            let err = if depth >= depth_limit {
+                Some(MacroError::DepthLimit { limit: depth_limit })
+            } else {
+                self.collect_macro_expansion(directive.module_id, macro_call_id, depth);
+                self.db.macro_expand_error(macro_call_id)
+            };
+            match err {
+                // Truncated expansions were already reported.
+                None | Some(MacroError::TokenLimit { .. }) => (),
+                Some(err) => {
+                    self.report_macro_error(directive.module_id, macro_call_id, &err);
+                    if self.db.attr_macro_fallback() {
+                        self.collect_attr_macro_item(&directive);
+                    }
+                }
+            }
+        }
+
         res
     }
 
@@ -717,6 +801,16 @@ impl DefCollector<'_> {
         macro_call_id: MacroCallId,
         depth: usize,
     ) {
+        match self.db.macro_expand_error(macro_call_id) {
+            Some(err @ MacroError::TokenLimit { .. }) => {
+                self.report_macro_error(module_id, macro_call_id, &err)
+            }
+            // Eager macros are builtin, so their failures are real errors.
+            Some(err) if matches!(macro_call_id, MacroCallId::EagerMacro(_)) => {
+                self.report_macro_error(module_id, macro_call_id, &err)
+            }
+            _ => (),
+        }
         let file_id: HirFileId = macro_call_id.as_file();
         let item_tree = self.db.item_tree(file_id);
         let mod_dir = self.mod_dirs[&module_id].clone();
@@ -731,6 +825,35 @@ impl DefCollector<'_> {
         .collect(item_tree.top_level_items());
     }
 
+    /// Collects the item of `directive` as if its attribute was not there.
+    fn collect_attr_macro_item(&mut self, directive: &AttrMacroDirective) {
+        let file_id = directive.ast_id.ast_id.file_id;
+        let item_tree = self.db.item_tree(file_id);
+        let mod_dir = self.mod_dirs[&directive.module_id].clone();
+        ModCollector {
+            def_collector: &mut *self,
+            macro_depth: directive.depth,
+            file_id,
+            module_id: directive.module_id,
+            item_tree: &item_tree,
+            mod_dir,
+        }
+        .collect_item(directive.item, directive.attr_index + 1);
+    }
+
+    fn report_macro_error(
+        &mut self,
+        module_id: LocalModuleId,
+        macro_call_id: MacroCallId,
+        err: &MacroError,
+    ) {
+        self.def_map.diagnostics.push(DefDiagnostic::MacroError {
+            module: module_id,
+            ast: macro_call_id.call_kind(self.db.upcast()),
+            message: err.to_string(),
+        });
+    }
+
     fn finish(self) -> CrateDefMap {
         self.def_map
     }
@@ -775,181 +898,211 @@ impl ModCollector<'_, '_> {
         }
 
         for &item in items {
-            let attrs = self.item_tree.attrs(item.into());
-            if self.is_cfg_enabled(attrs) {
+            self.collect_item(item, 0);
+        }
+    }
+
+    /// Collects `item`, ignoring its attributes before `first_attr` when
+    /// looking for attribute macros.
+    fn collect_item(&mut self, item: ModItem, first_attr: usize) {
+        let attrs = self.item_tree.attrs(item.into());
+        if !self.is_cfg_enabled(attrs) || self.collect_attr_macro(item, attrs, first_attr) {
+            return;
+        }
+        let module = ModuleId { krate: self.def_collector.def_map.krate, local_id: self.module_id };
+        let container = ContainerId::ModuleId(module);
+
+        let mut def = None;
+        match item {
+            ModItem::Mod(m) => self.collect_module(&self.item_tree[m], attrs),
+            ModItem::Import(import_id) => {
+                self.def_collector.unresolved_imports.push(ImportDirective {
+                    module_id: self.module_id,
+                    import: Import::from_use(&self.item_tree, import_id),
+                    status: PartialResolvedImport::Unresolved,
+                })
+            }
+            ModItem::ExternCrate(import_id) => {
+                self.def_collector.unresolved_imports.push(ImportDirective {
+                    module_id: self.module_id,
+                    import: Import::from_extern_crate(&self.item_tree, import_id),
+                    status: PartialResolvedImport::Unresolved,
+                })
+            }
+            ModItem::MacroCall(mac) => self.collect_macro(&self.item_tree[mac]),
+            ModItem::Impl(imp) => {
                 let module =
                     ModuleId { krate: self.def_collector.def_map.krate, local_id: self.module_id };
                 let container = ContainerId::ModuleId(module);
-
-                let mut def = None;
-                match item {
-                    ModItem::Mod(m) => self.collect_module(&self.item_tree[m], attrs),
-                    ModItem::Import(import_id) => {
-                        self.def_collector.unresolved_imports.push(ImportDirective {
-                            module_id: self.module_id,
-                            import: Import::from_use(&self.item_tree, import_id),
-                            status: PartialResolvedImport::Unresolved,
-                        })
-                    }
-                    ModItem::ExternCrate(import_id) => {
-                        self.def_collector.unresolved_imports.push(ImportDirective {
-                            module_id: self.module_id,
-                            import: Import::from_extern_crate(&self.item_tree, import_id),
-                            status: PartialResolvedImport::Unresolved,
-                        })
-                    }
-                    ModItem::MacroCall(mac) => self.collect_macro(&self.item_tree[mac]),
-                    ModItem::Impl(imp) => {
-                        let module = ModuleId {
-                            krate: self.def_collector.def_map.krate,
-                            local_id: self.module_id,
-                        };
-                        let container = ContainerId::ModuleId(module);
-                        let impl_id = ImplLoc { container, id: ItemTreeId::new(self.file_id, imp) }
-                            .intern(self.def_collector.db);
-                        self.def_collector.def_map.modules[self.module_id]
-                            .scope
-                            .define_impl(impl_id)
-                    }
-                    ModItem::Function(id) => {
-                        let func = &self.item_tree[id];
-                        def = Some(DefData {
-                            id: FunctionLoc {
-                                container: container.into(),
-                                id: ItemTreeId::new(self.file_id, id),
-                            }
-                            .intern(self.def_collector.db)
-                            .into(),
-                            name: &func.name,
-                            visibility: &self.item_tree[func.visibility],
-                            has_constructor: false,
-                        });
-                    }
-                    ModItem::Struct(id) => {
-                        let it = &self.item_tree[id];
-
-                        // FIXME: check attrs to see if this is an attribute macro invocation;
-                        // in which case we don't add the invocation, just a single attribute
-                        // macro invocation
-                        self.collect_derives(attrs, it.ast_id.upcast());
-
-                        def = Some(DefData {
-                            id: StructLoc { container, id: ItemTreeId::new(self.file_id, id) }
-                                .intern(self.def_collector.db)
-                                .into(),
-                            name: &it.name,
-                            visibility: &self.item_tree[it.visibility],
-                            has_constructor: it.kind != StructDefKind::Record,
-                        });
-                    }
-                    ModItem::Union(id) => {
-                        let it = &self.item_tree[id];
-
-                        // FIXME: check attrs to see if this is an attribute macro invocation;
-                        // in which case we don't add the invocation, just a single attribute
-                        // macro invocation
-                        self.collect_derives(attrs, it.ast_id.upcast());
-
-                        def = Some(DefData {
-                            id: UnionLoc { container, id: ItemTreeId::new(self.file_id, id) }
-                                .intern(self.def_collector.db)
-                                .into(),
-                            name: &it.name,
-                            visibility: &self.item_tree[it.visibility],
-                            has_constructor: false,
-                        });
-                    }
-                    ModItem::Enum(id) => {
-                        let it = &self.item_tree[id];
-
-                        // FIXME: check attrs to see if this is an attribute macro invocation;
-                        // in which case we don't add the invocation, just a single attribute
-                        // macro invocation
-                        self.collect_derives(attrs, it.ast_id.upcast());
-
-                        def = Some(DefData {
-                            id: EnumLoc { container, id: ItemTreeId::new(self.file_id, id) }
-                                .intern(self.def_collector.db)
-                                .into(),
-                            name: &it.name,
-                            visibility: &self.item_tree[it.visibility],
-                            has_constructor: false,
-                        });
+                let impl_id = ImplLoc { container, id: ItemTreeId::new(self.file_id, imp) }
+                    .intern(self.def_collector.db);
+                self.def_collector.def_map.modules[self.module_id].scope.define_impl(impl_id)
+            }
+            ModItem::Function(id) => {
+                let func = &self.item_tree[id];
+                def = Some(DefData {
+                    id: FunctionLoc {
+                        container: container.into(),
+                        id: ItemTreeId::new(self.file_id, id),
                     }
-                    ModItem::Const(id) => {
-                        let it = &self.item_tree[id];
-
-                        if let Some(name) = &it.name {
-                            def = Some(DefData {
-                                id: ConstLoc {
-                                    container: container.into(),
-                                    id: ItemTreeId::new(self.file_id, id),
-                                }
-                                .intern(self.def_collector.db)
-                                .into(),
-                                name,
-                                visibility: &self.item_tree[it.visibility],
-                                has_constructor: false,
-                            });
+                    .intern(self.def_collector.db)
+                    .into(),
+                    name: &func.name,
+                    visibility: &self.item_tree[func.visibility],
+                    has_constructor: false,
+                });
+            }
+            ModItem::Struct(id) => {
+                let it = &self.item_tree[id];
+
+                self.collect_derives(attrs, it.ast_id.upcast());
+
+                def = Some(DefData {
+                    id: StructLoc { container, id: ItemTreeId::new(self.file_id, id) }
+                        .intern(self.def_collector.db)
+                        .into(),
+                    name: &it.name,
+                    visibility: &self.item_tree[it.visibility],
+                    has_constructor: it.kind != StructDefKind::Record,
+                });
+            }
+            ModItem::Union(id) => {
+                let it = &self.item_tree[id];
+
+                self.collect_derives(attrs, it.ast_id.upcast());
+
+                def = Some(DefData {
+                    id: UnionLoc { container, id: ItemTreeId::new(self.file_id, id) }
+                        .intern(self.def_collector.db)
+                        .into(),
+                    name: &it.name,
+                    visibility: &self.item_tree[it.visibility],
+                    has_constructor: false,
+                });
+            }
+            ModItem::Enum(id) => {
+                let it = &self.item_tree[id];
+
+                self.collect_derives(attrs, it.ast_id.upcast());
+
+                def = Some(DefData {
+                    id: EnumLoc { container, id: ItemTreeId::new(self.file_id, id) }
+                        .intern(self.def_collector.db)
+                        .into(),
+                    name: &it.name,
+                    visibility: &self.item_tree[it.visibility],
+                    has_constructor: false,
+                });
+            }
+            ModItem::Const(id) => {
+                let it = &self.item_tree[id];
+
+                if let Some(name) = &it.name {
+                    def = Some(DefData {
+                        id: ConstLoc {
+                            container: container.into(),
+                            id: ItemTreeId::new(self.file_id, id),
                         }
-                    }
-                    ModItem::Static(id) => {
-                        let it = &self.item_tree[id];
-
-                        def = Some(DefData {
-                            id: StaticLoc { container, id: ItemTreeId::new(self.file_id, id) }
-                                .intern(self.def_collector.db)
-                                .into(),
-                            name: &it.name,
-                            visibility: &self.item_tree[it.visibility],
-                            has_constructor: false,
-                        });
-                    }
-                    ModItem::Trait(id) => {
-                        let it = &self.item_tree[id];
-
-                        def = Some(DefData {
-                            id: TraitLoc { container, id: ItemTreeId::new(self.file_id, id) }
-                                .intern(self.def_collector.db)
-                                .into(),
-                            name: &it.name,
-                            visibility: &self.item_tree[it.visibility],
-                            has_constructor: false,
-                        });
-                    }
-                    ModItem::TypeAlias(id) => {
-                        let it = &self.item_tree[id];
-
-                        def = Some(DefData {
-                            id: TypeAliasLoc {
-                                container: container.into(),
-                                id: ItemTreeId::new(self.file_id, id),
-                            }
-                            .intern(self.def_collector.db)
-                            .into(),
-                            name: &it.name,
-                            visibility: &self.item_tree[it.visibility],
-                            has_constructor: false,
-                        });
-                    }
+                        .intern(self.def_collector.db)
+                        .into(),
+                        name,
+                        visibility: &self.item_tree[it.visibility],
+                        has_constructor: false,
+                    });
                 }
+            }
+            ModItem::Static(id) => {
+                let it = &self.item_tree[id];
+
+                def = Some(DefData {
+                    id: StaticLoc { container, id: ItemTreeId::new(self.file_id, id) }
+                        .intern(self.def_collector.db)
+                        .into(),
+                    name: &it.name,
+                    visibility: &self.item_tree[it.visibility],
+                    has_constructor: false,
+                });
+            }
+            ModItem::Trait(id) => {
+                let it = &self.item_tree[id];
+
+                def = Some(DefData {
+                    id: TraitLoc { container, id: ItemTreeId::new(self.file_id, id) }
+                        .intern(self.def_collector.db)
+                        .into(),
+                    name: &it.name,
+                    visibility: &self.item_tree[it.visibility],
+                    has_constructor: false,
+                });
+            }
+            ModItem::TypeAlias(id) => {
+                let it = &self.item_tree[id];
 
-                if let Some(DefData { id, name, visibility, has_constructor }) = def {
-                    self.def_collector.def_map.modules[self.module_id].scope.define_def(id);
-                    let vis = self
-                        .def_collector
-                        .def_map
-                        .resolve_visibility(self.def_collector.db, self.module_id, visibility)
-                        .unwrap_or(Visibility::Public);
-                    self.def_collector.update(
-                        self.module_id,
-                        &[(name.clone(), PerNs::from_def(id, vis, has_constructor))],
-                        vis,
-                        ImportType::Named,
-                    )
-                }
+                def = Some(DefData {
+                    id: TypeAliasLoc {
+                        container: container.into(),
+                        id: ItemTreeId::new(self.file_id, id),
+                    }
+                    .intern(self.def_collector.db)
+                    .into(),
+                    name: &it.name,
+                    visibility: &self.item_tree[it.visibility],
+                    has_constructor: false,
+                });
             }
         }
+
+        if let Some(DefData { id, name, visibility, has_constructor }) = def {
+            self.def_collector.def_map.modules[self.module_id].scope.define_def(id);
+            let vis = self
+                .def_collector
+                .def_map
+                .resolve_visibility(self.def_collector.db, self.module_id, visibility)
+                .unwrap_or(Visibility::Public);
+            self.def_collector.update(
+                self.module_id,
+                &[(name.clone(), PerNs::from_def(id, vis, has_constructor))],
+                vis,
+                ImportType::Named,
+            )
+        }
+    }
+
+    /// Defers the collection of `item` if one of its attributes, starting with
+    /// `first_attr`, might be an attribute macro.
+    fn collect_attr_macro(&mut self, item: ModItem, attrs: &Attrs, first_attr: usize) -> bool {
+        if !self.def_collector.has_attr_macros {
+            return false;
+        }
+        let ast_id = match item {
+            ModItem::Function(it) => self.item_tree[it].ast_id.upcast(),
+            ModItem::Struct(it) => self.item_tree[it].ast_id.upcast(),
+            ModItem::Union(it) => self.item_tree[it].ast_id.upcast(),
+            ModItem::Enum(it) => self.item_tree[it].ast_id.upcast(),
+            ModItem::Const(it) => self.item_tree[it].ast_id.upcast(),
+            ModItem::Static(it) => self.item_tree[it].ast_id.upcast(),
+            ModItem::Trait(it) => self.item_tree[it].ast_id.upcast(),
+            ModItem::Impl(it) => self.item_tree[it].ast_id.upcast(),
+            ModItem::TypeAlias(it) => self.item_tree[it].ast_id.upcast(),
+            ModItem::Import(_)
+            | ModItem::ExternCrate(_)
+            | ModItem::Mod(_)
+            | ModItem::MacroCall(_) => return false,
+        };
+        let (attr_index, attr) =
+            match attrs.iter().enumerate().skip(first_attr).find(|(_, attr)| !attr.is_inert()) {
+                Some(it) => it,
+                None => return false,
+            };
+        let ast_id = AstIdWithPath::new(self.file_id, ast_id, attr.path.clone());
+        self.def_collector.unresolved_attrs.push(AttrMacroDirective {
+            module_id: self.module_id,
+            ast_id,
+            item,
+            attr_index,
+            depth: self.macro_depth,
+        });
+        true
     }
 
     fn collect_module(&mut self, module: &Mod, attrs: &Attrs) {
@@ -1059,14 +1212,16 @@ impl ModCollector<'_, '_> {
 
     fn collect_derives(&mut self, attrs: &Attrs, ast_id: FileAstId<ast::ModuleItem>) {
         for derive_subtree in attrs.by_key("derive").tt_values() {
-            // for #[derive(Copy, Clone)], `derive_subtree` is the `(Copy, Clone)` subtree
-            for tt in &derive_subtree.token_trees {
-                let ident = match &tt {
-                    tt::TokenTree::Leaf(tt::Leaf::Ident(ident)) => ident,
-                    tt::TokenTree::Leaf(tt::Leaf::Punct(_)) => continue, // , is ok
-                    _ => continue, // anything else would be an error (which we currently ignore)
+            // for #[derive(Copy, serde::Serialize)], `derive_subtree` is the
+            // `(Copy, serde::Serialize)` subtree
+            let derives = derive_subtree.token_trees.split(
+                |tt| matches!(tt, tt::TokenTree::Leaf(tt::Leaf::Punct(it)) if it.char == ','),
+            );
+            for derive in derives {
+                let path = match ModPath::from_tt(derive) {
+                    Some(it) => it,
+                    None => continue, // anything else would be an error (which we currently ignore)
                 };
-                let path = ModPath::from_tt_ident(ident);
 
                 let ast_id = AstIdWithPath::new(self.file_id, ast_id, path);
                 self.def_collector
@@ -1179,9 +1334,11 @@ mod tests {
             resolved_imports: Vec::new(),
             unexpanded_macros: Vec::new(),
             unexpanded_attribute_macros: Vec::new(),
+            unresolved_attrs: Vec::new(),
             mod_dirs: FxHashMap::default(),
             cfg_options: &CfgOptions::default(),
             proc_macros: Default::default(),
+            has_attr_macros: false,
             from_glob_import: Default::default(),
         };
         collector.collect();
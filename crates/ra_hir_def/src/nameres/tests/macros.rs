@@ -1,3 +1,9 @@
+use hir_expand::{
+    db::{AstDatabase, MacroLimits},
+    diagnostics::DiagnosticSink,
+};
+use ra_db::{ProcMacro, ProcMacroKind};
+
 use super::*;
 
 #[test]
@@ -637,6 +643,46 @@ fn macro_dollar_crate_is_correct_in_indirect_deps() {
     "###);
 }
 
+#[test]
+fn macro_dollar_crate_is_correct_when_passed_to_other_macros() {
+    let map = def_map(
+        r#"
+        //- /main.rs crate:main deps:foo,bar
+        struct Baz;
+
+        foo::wrap!();
+
+        //- /foo.rs crate:foo deps:bar
+        #[macro_export]
+        macro_rules! wrap {
+            () => {
+                bar::emit!($crate::Foo);
+            }
+        }
+
+        pub struct Foo;
+        pub struct Baz;
+
+        //- /bar.rs crate:bar
+        #[macro_export]
+        macro_rules! emit {
+            ($path:path) => {
+                use $path;
+                use $crate::Bar;
+            }
+        }
+
+        pub struct Bar;
+        "#,
+    );
+    assert_snapshot!(map, @r###"
+        ⋮crate
+        ⋮Bar: t v
+        ⋮Baz: t v
+        ⋮Foo: t v
+    "###);
+}
+
 #[test]
 fn expand_derive() {
     let map = compute_crate_def_map(
@@ -660,3 +706,199 @@ fn expand_multiple_derive() {
     );
     assert_eq!(map.modules[map.root].scope.impls().len(), 2);
 }
+
+#[test]
+fn macro_expansion_limits() {
+    let mut db = TestDB::with_files(
+        r"
+        //- /lib.rs
+        macro_rules! recurse {
+            () => { recurse!(); }
+        }
+        macro_rules! items {
+            () => { struct A; struct B; struct C; }
+        }
+        recurse!();
+        items!();
+        ",
+    );
+    db.set_macro_limits(MacroLimits { expansion_depth: 4, token_count: 6 });
+    let krate = db.crate_graph().iter().next().unwrap();
+    let map = db.crate_def_map(krate);
+
+    let mut messages = Vec::new();
+    map.add_diagnostics(&db, map.root, &mut DiagnosticSink::new(|d| messages.push(d.message())));
+    assert_eq!(
+        messages,
+        [
+            "macro expansion produced 9 tokens, it was truncated to the limit of 6",
+            "macro expansion exceeded the recursion limit of 4",
+        ]
+    );
+    // The truncated expansion is still collected.
+    assert_snapshot!(map.dump(), @r###"
+    ⋮crate
+    ⋮A: t v
+    ⋮B: t v
+    "###);
+}
+
+/// Makes the crate named `name` provide the attribute macros `identity`,
+/// `add_fn`, which also adds `fn generated() {}`, and `fail`, and the derive
+/// `Builder`.
+fn with_proc_macros(db: &mut TestDB, name: &str) {
+    /// Expands to the input item, if `keep_item`, followed by `extra`, or
+    /// fails if there is no `extra`.
+    #[derive(Debug)]
+    struct Expander {
+        keep_item: bool,
+        extra: Option<&'static str>,
+    }
+    impl tt::TokenExpander for Expander {
+        fn expand(
+            &self,
+            subtree: &tt::Subtree,
+            _attrs: Option<&tt::Subtree>,
+        ) -> Result<tt::Subtree, tt::ExpansionError> {
+            let extra =
+                self.extra.ok_or_else(|| tt::ExpansionError::Unknown("fail".to_string()))?;
+            let mut res = if self.keep_item { subtree.clone() } else { tt::Subtree::default() };
+            if let Some((extra, _)) = mbe::parse_to_token_tree(extra) {
+                res.token_trees.extend(extra.token_trees);
+            }
+            Ok(res)
+        }
+    }
+    let builder =
+        "pub struct FooBuilder; impl Foo { pub fn builder() -> FooBuilder { FooBuilder } }";
+    let proc_macros = vec![
+        ("identity", ProcMacroKind::Attr, true, Some("")),
+        ("add_fn", ProcMacroKind::Attr, true, Some("fn generated() {}")),
+        ("fail", ProcMacroKind::Attr, true, None),
+        ("Builder", ProcMacroKind::CustomDerive, false, Some(builder)),
+    ];
+    let proc_macros = proc_macros
+        .into_iter()
+        .map(|(name, kind, keep_item, extra)| ProcMacro {
+            name: name.into(),
+            kind,
+            expander: Arc::new(Expander { keep_item, extra }),
+        })
+        .collect();
+    db.set_proc_macros(name, proc_macros);
+}
+
+fn proc_macro_def_map(ra_fixture: &str, fallback: bool) -> (TestDB, Arc<CrateDefMap>) {
+    let mut db = TestDB::with_files(ra_fixture);
+    with_proc_macros(&mut db, "proc");
+    db.set_attr_macro_fallback(fallback);
+    let krate = db.crate_graph().iter().find(|&it| it.0 == 0).unwrap();
+    let map = db.crate_def_map(krate);
+    (db, map)
+}
+
+#[test]
+fn attr_macro_expansions_are_collected() {
+    let (_db, map) = proc_macro_def_map(
+        r"
+        //- /main.rs crate:main deps:proc
+        use proc::identity;
+
+        #[proc::add_fn]
+        struct S;
+
+        #[identity]
+        #[inline]
+        fn f() {}
+
+        mod m {
+            #[proc::add_fn]
+            pub fn g() {}
+        }
+        use m::generated;
+
+        //- /lib.rs crate:proc
+        ",
+        true,
+    );
+    assert_snapshot!(map.dump(), @r###"
+    ⋮crate
+    ⋮S: t v
+    ⋮f: v
+    ⋮generated: v
+    ⋮identity: m
+    ⋮m: t
+    ⋮
+    ⋮crate::m
+    ⋮g: v
+    ⋮generated: v
+    "###);
+}
+
+#[test]
+fn inert_and_unresolved_attributes_keep_the_item() {
+    let (_db, map) = proc_macro_def_map(
+        r#"
+        //- /main.rs crate:main deps:proc
+        #[inline]
+        #[rustfmt::skip]
+        fn f() {}
+
+        #[serde(rename_all = "camelCase")]
+        struct S;
+
+        //- /lib.rs crate:proc
+        "#,
+        true,
+    );
+    assert_snapshot!(map.dump(), @r###"
+    ⋮crate
+    ⋮S: t v
+    ⋮f: v
+    "###);
+}
+
+#[test]
+fn failed_attr_macro_fallback() {
+    let fixture = r"
+        //- /main.rs crate:main deps:proc
+        #[proc::fail]
+        fn f() {}
+
+        //- /lib.rs crate:proc
+        ";
+    let (db, map) = proc_macro_def_map(fixture, true);
+    let mut messages = Vec::new();
+    map.add_diagnostics(&db, map.root, &mut DiagnosticSink::new(|d| messages.push(d.message())));
+    assert_eq!(messages, [r#"ProcMacroError(Unknown("fail"))"#]);
+    assert_snapshot!(map.dump(), @r###"
+    ⋮crate
+    ⋮f: v
+    "###);
+
+    let (_db, map) = proc_macro_def_map(fixture, false);
+    assert_snapshot!(map.dump(), @r###"
+    ⋮crate
+    "###);
+}
+
+#[test]
+fn custom_derive_expansions_are_collected() {
+    let (_db, map) = proc_macro_def_map(
+        r"
+        //- /main.rs crate:main deps:proc
+        #[derive(Clone, proc::Builder)]
+        struct Foo;
+
+        //- /lib.rs crate:proc
+        ",
+        true,
+    );
+    assert_snapshot!(map.dump(), @r###"
+    ⋮crate
+    ⋮Foo: t v
+    ⋮FooBuilder: t v
+    "###);
+    // `impl Clone for Foo` and `impl Foo`.
+    assert_eq!(map.modules[map.root].scope.impls().len(), 2);
+}
@@ -61,8 +61,43 @@ impl ModPath {
     }
 
     /// Converts an `tt::Ident` into a single-identifier `Path`.
-    pub(crate) fn from_tt_ident(ident: &tt::Ident) -> ModPath {
-        ident.as_name().into()
+    /// Converts the tokens of a path like `serde::Serialize` or
+    /// `::std::fmt::Debug`.
+    pub(crate) fn from_tt(tts: &[tt::TokenTree]) -> Option<ModPath> {
+        fn strip_colons(tts: &[tt::TokenTree]) -> Option<&[tt::TokenTree]> {
+            match tts {
+                [tt::TokenTree::Leaf(tt::Leaf::Punct(a)), tt::TokenTree::Leaf(tt::Leaf::Punct(b)), rest @ ..]
+                    if a.char == ':' && b.char == ':' =>
+                {
+                    Some(rest)
+                }
+                _ => None,
+            }
+        }
+        let (mut kind, mut tts) = match strip_colons(tts) {
+            Some(rest) => (PathKind::Abs, rest),
+            None => (PathKind::Plain, tts),
+        };
+        let mut segments = Vec::new();
+        loop {
+            let (ident, rest) = match tts {
+                [tt::TokenTree::Leaf(tt::Leaf::Ident(ident)), rest @ ..] => (ident, rest),
+                _ => return None,
+            };
+            if kind == PathKind::Plain && segments.is_empty() && ident.text == "crate" {
+                kind = PathKind::Crate;
+            } else {
+                segments.push(ident.as_name());
+            }
+            if rest.is_empty() {
+                break;
+            }
+            tts = strip_colons(rest)?;
+        }
+        if segments.is_empty() {
+            return None;
+        }
+        Some(ModPath::from_segments(kind, segments))
     }
 
     /// Calls `cb` with all paths, represented by this use item.
@@ -113,7 +148,7 @@ pub struct Path {
     type_anchor: Option<Box<TypeRef>>,
     mod_path: ModPath,
     /// Invariant: the same len as `self.mod_path.segments`
-    generic_args: Vec<Option<Arc<GenericArgs>>>,
+    generic_args: Box<[Option<Arc<GenericArgs>>]>,
 }
 
 /// Generic arguments to a path segment (e.g. the `i32` in `Option<i32>`). This
@@ -170,7 +205,7 @@ impl Path {
         path: ModPath,
         generic_args: Vec<Option<Arc<GenericArgs>>>,
     ) -> Path {
-        Path { type_anchor: None, mod_path: path, generic_args }
+        Path { type_anchor: None, mod_path: path, generic_args: generic_args.into() }
     }
 
     pub fn kind(&self) -> &PathKind {
@@ -184,7 +219,7 @@ impl Path {
     pub fn segments(&self) -> PathSegments<'_> {
         PathSegments {
             segments: self.mod_path.segments.as_slice(),
-            generic_args: self.generic_args.as_slice(),
+            generic_args: &self.generic_args,
         }
     }
 
@@ -202,7 +237,7 @@ impl Path {
                 kind: self.mod_path.kind.clone(),
                 segments: self.mod_path.segments[..self.mod_path.segments.len() - 1].to_vec(),
             },
-            generic_args: self.generic_args[..self.generic_args.len() - 1].to_vec(),
+            generic_args: self.generic_args[..self.generic_args.len() - 1].into(),
         };
         Some(res)
     }
@@ -272,7 +307,7 @@ impl From<Name> for Path {
         Path {
             type_anchor: None,
             mod_path: ModPath::from_segments(PathKind::Plain, iter::once(name)),
-            generic_args: vec![None],
+            generic_args: Box::new([None]),
         }
     }
 }
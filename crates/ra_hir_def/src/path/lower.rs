@@ -80,9 +80,7 @@ pub(super) fn lower_path(mut path: ast::Path, hygiene: &Hygiene) -> Option<Path>
                         prefix_segments.reverse();
                         segments.extend(prefix_segments);
 
-                        let mut prefix_args = path.generic_args;
-                        prefix_args.reverse();
-                        generic_args.extend(prefix_args);
+                        generic_args.extend(path.generic_args.iter().rev().cloned());
 
                         // Insert the type reference (T in the above example) as Self parameter for the trait
                         let last_segment = generic_args.last_mut()?;
@@ -124,15 +122,16 @@ pub(super) fn lower_path(mut path: ast::Path, hygiene: &Hygiene) -> Option<Path>
     if segments.len() == 1 && kind == PathKind::Plain {
         if let Some(macro_call) = path.syntax().parent().and_then(ast::MacroCall::cast) {
             if macro_call.is_bang() {
-                if let Some(crate_id) = hygiene.local_inner_macros() {
+                if let Some(crate_id) = hygiene.local_inner_macros(&path) {
                     kind = PathKind::DollarCrate(crate_id);
                 }
             }
         }
     }
 
+    segments.shrink_to_fit();
     let mod_path = ModPath { kind, segments };
-    return Some(Path { type_anchor, mod_path, generic_args });
+    return Some(Path { type_anchor, mod_path, generic_args: generic_args.into() });
 
     fn qualifier(path: &ast::Path) -> Option<ast::Path> {
         if let Some(q) = path.qualifier() {
@@ -2,6 +2,7 @@
 use std::sync::Arc;
 
 use hir_expand::{
+    hygiene::SyntaxContext,
     name::{name, Name},
     MacroDefId,
 };
@@ -254,6 +255,26 @@ impl Resolver {
         &self,
         db: &dyn DefDatabase,
         path: &ModPath,
+    ) -> Option<ResolveValueResult> {
+        self.resolve_path_in_value_ns_impl(db, path, None)
+    }
+
+    /// Like `resolve_path_in_value_ns`, but only resolves to local variables
+    /// bound in the same syntax `context` as the path.
+    pub fn resolve_path_in_value_ns_with_context(
+        &self,
+        db: &dyn DefDatabase,
+        path: &ModPath,
+        context: SyntaxContext,
+    ) -> Option<ResolveValueResult> {
+        self.resolve_path_in_value_ns_impl(db, path, Some(context))
+    }
+
+    fn resolve_path_in_value_ns_impl(
+        &self,
+        db: &dyn DefDatabase,
+        path: &ModPath,
+        context: Option<SyntaxContext>,
     ) -> Option<ResolveValueResult> {
         let n_segments = path.segments.len();
         let tmp = name![self];
@@ -272,11 +293,10 @@ impl Resolver {
                 }
 
                 Scope::ExprScope(scope) if n_segments <= 1 => {
-                    let entry = scope
-                        .expr_scopes
-                        .entries(scope.scope_id)
-                        .iter()
-                        .find(|entry| entry.name() == first_name);
+                    let entry = scope.expr_scopes.entries(scope.scope_id).iter().find(|entry| {
+                        entry.name() == first_name
+                            && (context.is_none() || context == Some(entry.context()))
+                    });
 
                     if let Some(e) = entry {
                         return Some(ResolveValueResult::ValueNs(ValueNs::LocalBinding(e.pat())));
@@ -384,6 +404,18 @@ impl Resolver {
         }
     }
 
+    pub fn resolve_path_in_value_ns_fully_with_context(
+        &self,
+        db: &dyn DefDatabase,
+        path: &ModPath,
+        context: SyntaxContext,
+    ) -> Option<ValueNs> {
+        match self.resolve_path_in_value_ns_with_context(db, path, context)? {
+            ResolveValueResult::ValueNs(it) => Some(it),
+            ResolveValueResult::Partial(..) => None,
+        }
+    }
+
     pub fn resolve_path_as_macro(
         &self,
         db: &dyn DefDatabase,
@@ -18,12 +18,20 @@ use crate::db::DefDatabase;
     crate::db::InternDatabaseStorage,
     crate::db::DefDatabaseStorage
 )]
-#[derive(Default)]
 pub struct TestDB {
     storage: salsa::Storage<TestDB>,
     events: Mutex<Option<Vec<salsa::Event>>>,
 }
 
+impl Default for TestDB {
+    fn default() -> TestDB {
+        let mut db = TestDB { storage: Default::default(), events: Default::default() };
+        db.set_macro_limits(Default::default());
+        db.set_attr_macro_fallback(true);
+        db
+    }
+}
+
 impl Upcast<dyn AstDatabase> for TestDB {
     fn upcast(&self) -> &(dyn AstDatabase + 'static) {
         &*self
@@ -63,6 +71,12 @@ impl FileLoader for TestDB {
     fn relevant_crates(&self, file_id: FileId) -> Arc<FxHashSet<CrateId>> {
         FileLoaderDelegate(self).relevant_crates(file_id)
     }
+    fn is_library(&self, file_id: FileId) -> bool {
+        FileLoaderDelegate(self).is_library(file_id)
+    }
+    fn is_open(&self, file_id: FileId) -> bool {
+        FileLoaderDelegate(self).is_open(file_id)
+    }
 }
 
 impl TestDB {
@@ -1,7 +1,7 @@
 //! Builtin macro
 use crate::{
-    db::AstDatabase, name, quote, AstId, CrateId, EagerMacroId, LazyMacroId, MacroCallId,
-    MacroDefId, MacroDefKind, TextSize,
+    db::{AstDatabase, MacroError},
+    name, quote, AstId, CrateId, EagerMacroId, LazyMacroId, MacroCallId, MacroDefId, MacroDefKind,
 };
 
 use either::Either;
@@ -42,7 +42,7 @@ macro_rules! register_builtin {
                 db: &dyn AstDatabase,
                 arg_id: EagerMacroId,
                 tt: &tt::Subtree,
-            ) -> Result<(tt::Subtree, FragmentKind), mbe::ExpandError> {
+            ) -> Result<(tt::Subtree, FragmentKind), MacroError> {
                 let expander = match *self {
                     $( EagerExpander::$e_kind => $e_expand, )*
                 };
@@ -95,6 +95,9 @@ register_builtin! {
     // format_args_nl only differs in that it adds a newline in the end,
     // so we use the same stub expansion for now
     (format_args_nl, FormatArgsNl) => format_args_expand,
+    (cfg, Cfg) => cfg_expand,
+    (asm, Asm) => asm_expand,
+    (global_asm, GlobalAsm) => global_asm_expand,
 
     EAGER:
     (concat, Concat) => concat_expand,
@@ -105,13 +108,31 @@ register_builtin! {
     (option_env, OptionEnv) => option_env_expand
 }
 
+/// Returns the one-based line and column of the macro call `id`, or of the
+/// first macro call leading to it, like rustc does.
+fn call_site_line_col(db: &dyn AstDatabase, id: LazyMacroId) -> Option<(usize, usize)> {
+    let mut node = db.lookup_intern_macro(id).kind.node(db);
+    while let Some(call) = node.file_id.call_node(db) {
+        node = call;
+    }
+    let file_id = node.file_id.original_file(db);
+    if node.file_id != file_id.into() {
+        // The arguments of eager macros are not in a file.
+        return None;
+    }
+    let text = db.file_text(file_id);
+    let before = &text[..usize::from(node.value.text_range().start())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    Some((line, column))
+}
+
 fn line_expand(
-    _db: &dyn AstDatabase,
-    _id: LazyMacroId,
+    db: &dyn AstDatabase,
+    id: LazyMacroId,
     _tt: &tt::Subtree,
 ) -> Result<tt::Subtree, mbe::ExpandError> {
-    // dummy implementation for type-checking purposes
-    let line_num = 0;
+    let line_num = call_site_line_col(db, id).map_or(0, |(line, _)| line);
     let expanded = quote! {
         #line_num
     };
@@ -120,19 +141,13 @@ fn line_expand(
 }
 
 fn stringify_expand(
-    db: &dyn AstDatabase,
-    id: LazyMacroId,
-    _tt: &tt::Subtree,
+    _db: &dyn AstDatabase,
+    _id: LazyMacroId,
+    tt: &tt::Subtree,
 ) -> Result<tt::Subtree, mbe::ExpandError> {
-    let loc = db.lookup_intern_macro(id);
-
-    let macro_content = {
-        let arg = loc.kind.arg(db).ok_or_else(|| mbe::ExpandError::UnexpectedToken)?;
-        let macro_args = arg;
-        let text = macro_args.text();
-        let without_parens = TextSize::of('(')..text.len() - TextSize::of(')');
-        text.slice(without_parens).to_string()
-    };
+    // Separate the tokens by single spaces, keeping the text of literals.
+    let macro_content =
+        tt::Subtree { delimiter: None, token_trees: tt.token_trees.clone() }.to_string();
 
     let expanded = quote! {
         #macro_content
@@ -142,12 +157,11 @@ fn stringify_expand(
 }
 
 fn column_expand(
-    _db: &dyn AstDatabase,
-    _id: LazyMacroId,
+    db: &dyn AstDatabase,
+    id: LazyMacroId,
     _tt: &tt::Subtree,
 ) -> Result<tt::Subtree, mbe::ExpandError> {
-    // dummy implementation for type-checking purposes
-    let col_num = 0;
+    let col_num = call_site_line_col(db, id).map_or(0, |(_, column)| column);
     let expanded = quote! {
         #col_num
     };
@@ -167,23 +181,7 @@ fn assert_expand(
     // ```,
     // which is wrong but useful.
 
-    let mut args = Vec::new();
-    let mut current = Vec::new();
-    for tt in tt.token_trees.iter().cloned() {
-        match tt {
-            tt::TokenTree::Leaf(tt::Leaf::Punct(p)) if p.char == ',' => {
-                args.push(current);
-                current = Vec::new();
-            }
-            _ => {
-                current.push(tt);
-            }
-        }
-    }
-    if !current.is_empty() {
-        args.push(current);
-    }
-
+    let args = split_args(tt);
     let arg_tts = args.into_iter().flat_map(|arg| {
         quote! { &(##arg), }
     }.token_trees).collect::<Vec<_>>();
@@ -227,6 +225,20 @@ fn compile_error_expand(
     Err(mbe::ExpandError::BindingError("Must be a string".into()))
 }
 
+fn cfg_expand(
+    db: &dyn AstDatabase,
+    id: LazyMacroId,
+    tt: &tt::Subtree,
+) -> Result<tt::Subtree, mbe::ExpandError> {
+    let krate = db.lookup_intern_macro(id).krate;
+    let enabled = db.crate_graph()[krate]
+        .cfg_options
+        .is_cfg_enabled(tt)
+        .ok_or_else(|| mbe::ExpandError::BindingError("invalid cfg predicate".into()))?;
+    let expanded = quote! { #enabled };
+    Ok(expanded)
+}
+
 fn format_args_expand(
     _db: &dyn AstDatabase,
     _id: LazyMacroId,
@@ -240,6 +252,95 @@ fn format_args_expand(
     // ])
     // ```,
     // which is still not really correct, but close enough for now
+    let mut args = split_args(tt);
+    if args.is_empty() {
+        return Err(mbe::ExpandError::NoMatchingRule);
+    }
+    let _format_string = args.remove(0);
+    let arg_tts = args.into_iter().flat_map(|arg| {
+        quote! { std::fmt::ArgumentV1::new(&(##arg), std::fmt::Display::fmt), }
+    }.token_trees).collect::<Vec<_>>();
+    let expanded = quote! {
+        std::fmt::Arguments::new_v1(&[], &[##arg_tts])
+    };
+    Ok(expanded)
+}
+
+fn asm_expand(
+    _db: &dyn AstDatabase,
+    _id: LazyMacroId,
+    tt: &tt::Subtree,
+) -> Result<tt::Subtree, mbe::ExpandError> {
+    // We expand `asm!("...", in(reg) a, inout(reg) b => c, sym f, options(noreturn))` to
+    // ```
+    // {&(a); &(b); &(c); &(f); loop {}}
+    // ```,
+    // so that the operands are resolved and type-checked. The template itself
+    // is validated by the IDE layer, as it does not influence types.
+    let mut stmts = Vec::new();
+    let mut noreturn = false;
+    for arg in split_args(tt) {
+        let mut arg = &arg[..];
+        if let [tt::TokenTree::Leaf(tt::Leaf::Ident(_)), tt::TokenTree::Leaf(tt::Leaf::Punct(eq)), rest @ ..] =
+            arg
+        {
+            if eq.char == '=' && eq.spacing == tt::Spacing::Alone {
+                arg = rest;
+            }
+        }
+        let exprs = match arg {
+            [tt::TokenTree::Leaf(tt::Leaf::Ident(dir)), tt::TokenTree::Subtree(_), rest @ ..]
+                if ["in", "out", "lateout", "inout", "inlateout"].contains(&dir.text.as_str()) =>
+            {
+                split_fat_arrow(rest)
+            }
+            [tt::TokenTree::Leaf(tt::Leaf::Ident(kw)), rest @ ..]
+                if kw.text == "sym" || kw.text == "const" =>
+            {
+                vec![rest]
+            }
+            [tt::TokenTree::Leaf(tt::Leaf::Ident(kw)), tt::TokenTree::Subtree(options)]
+                if kw.text == "options" =>
+            {
+                noreturn |= options.token_trees.iter().any(|it| match it {
+                    tt::TokenTree::Leaf(tt::Leaf::Ident(option)) => option.text == "noreturn",
+                    _ => false,
+                });
+                vec![]
+            }
+            _ => vec![],
+        };
+        for expr in exprs {
+            let is_underscore = match expr {
+                [tt::TokenTree::Leaf(tt::Leaf::Punct(it))] => it.char == '_',
+                _ => false,
+            };
+            if !expr.is_empty() && !is_underscore {
+                let expr = expr.to_vec();
+                stmts.extend(quote! { &(##expr); }.token_trees);
+            }
+        }
+    }
+    if noreturn {
+        stmts.extend(quote! { loop {} }.token_trees);
+    }
+    let expanded = quote! {
+        { ##stmts }
+    };
+    Ok(expanded)
+}
+
+fn global_asm_expand(
+    _db: &dyn AstDatabase,
+    _id: LazyMacroId,
+    _tt: &tt::Subtree,
+) -> Result<tt::Subtree, mbe::ExpandError> {
+    // Expand to nothing (at item-level)
+    Ok(quote! {})
+}
+
+/// Splits the arguments of a builtin macro at the top-level commas.
+fn split_args(tt: &tt::Subtree) -> Vec<Vec<tt::TokenTree>> {
     let mut args = Vec::new();
     let mut current = Vec::new();
     for tt in tt.token_trees.iter().cloned() {
@@ -256,38 +357,78 @@ fn format_args_expand(
     if !current.is_empty() {
         args.push(current);
     }
-    if args.is_empty() {
-        return Err(mbe::ExpandError::NoMatchingRule);
+    args
+}
+
+/// Splits the `in => out` expressions of an `inout` operand.
+fn split_fat_arrow(tts: &[tt::TokenTree]) -> Vec<&[tt::TokenTree]> {
+    for (i, window) in tts.windows(2).enumerate() {
+        if let [tt::TokenTree::Leaf(tt::Leaf::Punct(eq)), tt::TokenTree::Leaf(tt::Leaf::Punct(gt))] =
+            window
+        {
+            if eq.char == '=' && eq.spacing == tt::Spacing::Joint && gt.char == '>' {
+                return vec![&tts[..i], &tts[i + 2..]];
+            }
+        }
     }
-    let _format_string = args.remove(0);
-    let arg_tts = args.into_iter().flat_map(|arg| {
-        quote! { std::fmt::ArgumentV1::new(&(##arg), std::fmt::Display::fmt), }
-    }.token_trees).collect::<Vec<_>>();
-    let expanded = quote! {
-        std::fmt::Arguments::new_v1(&[], &[##arg_tts])
-    };
-    Ok(expanded)
+    vec![tts]
 }
 
 fn unquote_str(lit: &tt::Literal) -> Option<String> {
     let lit = ast::make::tokens::literal(&lit.to_string());
+    if let Some(token) = ast::RawString::cast(lit.clone()) {
+        return token.value().map(|it| it.into_owned());
+    }
     let token = ast::String::cast(lit)?;
     token.value().map(|it| it.into_owned())
 }
 
+/// Returns the text `concat!` appends for a non-string literal: chars
+/// without their quotes, and numbers without their suffix.
+fn unquote_literal(lit: &tt::Literal) -> Option<String> {
+    let text = lit.text.as_str();
+    if let Some(inner) = text.strip_prefix('\'').and_then(|it| it.strip_suffix('\'')) {
+        if inner == "\"" {
+            return Some(inner.to_string());
+        }
+        // Chars are escaped like strings.
+        let lit = ast::make::tokens::literal(&format!("\"{}\"", inner));
+        return ast::String::cast(lit)?.value().map(|it| it.into_owned());
+    }
+    if !text.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    const SUFFIXES: &[&str] = &[
+        "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+        "f32", "f64",
+    ];
+    let text = SUFFIXES.iter().find_map(|it| text.strip_suffix(it)).unwrap_or(text);
+    Some(text.replace('_', ""))
+}
+
 fn concat_expand(
     _db: &dyn AstDatabase,
     _arg_id: EagerMacroId,
     tt: &tt::Subtree,
-) -> Result<(tt::Subtree, FragmentKind), mbe::ExpandError> {
+) -> Result<(tt::Subtree, FragmentKind), MacroError> {
     let mut text = String::new();
     for (i, t) in tt.token_trees.iter().enumerate() {
         match t {
             tt::TokenTree::Leaf(tt::Leaf::Literal(it)) if i % 2 == 0 => {
-                text += &unquote_str(&it).ok_or_else(|| mbe::ExpandError::ConversionError)?;
+                text += &unquote_str(it).or_else(|| unquote_literal(it)).ok_or_else(|| {
+                    MacroError::Other(format!("can't concatenate the literal `{}`", it))
+                })?;
+            }
+            tt::TokenTree::Leaf(tt::Leaf::Ident(it))
+                if i % 2 == 0 && (it.text == "true" || it.text == "false") =>
+            {
+                text += &it.text;
             }
             tt::TokenTree::Leaf(tt::Leaf::Punct(punct)) if i % 2 == 1 && punct.char == ',' => (),
-            _ => return Err(mbe::ExpandError::UnexpectedToken),
+            _ if i % 2 == 0 => {
+                return Err(MacroError::Other(format!("expected a literal, found `{}`", t)))
+            }
+            _ => return Err(MacroError::Other(format!("expected `,`, found `{}`", t))),
         }
     }
 
@@ -310,31 +451,32 @@ fn relative_file(
     }
 }
 
-fn parse_string(tt: &tt::Subtree) -> Result<String, mbe::ExpandError> {
-    tt.token_trees
-        .get(0)
-        .and_then(|tt| match tt {
-            tt::TokenTree::Leaf(tt::Leaf::Literal(it)) => unquote_str(&it),
-            _ => None,
-        })
-        .ok_or_else(|| mbe::ExpandError::ConversionError)
+fn parse_string(tt: &tt::Subtree) -> Result<String, MacroError> {
+    let res = tt.token_trees.get(0).and_then(|tt| match tt {
+        tt::TokenTree::Leaf(tt::Leaf::Literal(it)) => unquote_str(&it),
+        _ => None,
+    });
+    res.ok_or_else(|| match tt.token_trees.get(0) {
+        Some(it) => MacroError::Other(format!("expected a string literal, found `{}`", it)),
+        None => MacroError::Other("expected a string literal".into()),
+    })
 }
 
 fn include_expand(
     db: &dyn AstDatabase,
     arg_id: EagerMacroId,
     tt: &tt::Subtree,
-) -> Result<(tt::Subtree, FragmentKind), mbe::ExpandError> {
+) -> Result<(tt::Subtree, FragmentKind), MacroError> {
     let path = parse_string(tt)?;
     let file_id = relative_file(db, arg_id.into(), &path, false)
-        .ok_or_else(|| mbe::ExpandError::ConversionError)?;
+        .ok_or_else(|| MacroError::UnresolvedInclude { path: path.clone() })?;
 
-    // FIXME:
-    // Handle include as expression
     let res = parse_to_token_tree(&db.file_text(file_id))
-        .ok_or_else(|| mbe::ExpandError::ConversionError)?
+        .ok_or_else(|| MacroError::Other(format!("can't tokenize the included file `{}`", path)))?
         .0;
 
+    // This is replaced by the fragment the call position expects, see
+    // `expand_eager_macro`.
     Ok((res, FragmentKind::Items))
 }
 
@@ -342,7 +484,7 @@ fn include_bytes_expand(
     _db: &dyn AstDatabase,
     _arg_id: EagerMacroId,
     tt: &tt::Subtree,
-) -> Result<(tt::Subtree, FragmentKind), mbe::ExpandError> {
+) -> Result<(tt::Subtree, FragmentKind), MacroError> {
     let _path = parse_string(tt)?;
 
     // FIXME: actually read the file here if the user asked for macro expansion
@@ -360,7 +502,7 @@ fn include_str_expand(
     db: &dyn AstDatabase,
     arg_id: EagerMacroId,
     tt: &tt::Subtree,
-) -> Result<(tt::Subtree, FragmentKind), mbe::ExpandError> {
+) -> Result<(tt::Subtree, FragmentKind), MacroError> {
     let path = parse_string(tt)?;
 
     // FIXME: we're not able to read excluded files (which is most of them because
@@ -389,7 +531,7 @@ fn env_expand(
     db: &dyn AstDatabase,
     arg_id: EagerMacroId,
     tt: &tt::Subtree,
-) -> Result<(tt::Subtree, FragmentKind), mbe::ExpandError> {
+) -> Result<(tt::Subtree, FragmentKind), MacroError> {
     let key = parse_string(tt)?;
 
     // FIXME:
@@ -409,7 +551,7 @@ fn option_env_expand(
     db: &dyn AstDatabase,
     arg_id: EagerMacroId,
     tt: &tt::Subtree,
-) -> Result<(tt::Subtree, FragmentKind), mbe::ExpandError> {
+) -> Result<(tt::Subtree, FragmentKind), MacroError> {
     let key = parse_string(tt)?;
     let expanded = match get_env_inner(db, arg_id, &key) {
         None => quote! { std::option::Option::None::<&str> },
@@ -474,6 +616,7 @@ mod tests {
 
                 let args = macro_calls[1].token_tree().unwrap();
                 let parsed_args = mbe::ast_to_token_tree(&args).unwrap().0;
+                let call = AstId::new(file_id.into(), ast_id_map.ast_id(&macro_calls[1]));
 
                 let arg_id = db.intern_eager_expansion({
                     EagerCallLoc {
@@ -481,7 +624,8 @@ mod tests {
                         fragment: FragmentKind::Expr,
                         subtree: Arc::new(parsed_args.clone()),
                         krate,
-                        file_id: file_id.into(),
+                        call,
+                        error: None,
                     }
                 });
 
@@ -491,7 +635,8 @@ mod tests {
                     fragment,
                     subtree: Arc::new(subtree),
                     krate,
-                    file_id: file_id.into(),
+                    call,
+                    error: None,
                 };
 
                 let id: MacroCallId = db.intern_eager_expansion(eager).into();
@@ -508,11 +653,11 @@ mod tests {
             r#"
             #[rustc_builtin_macro]
             macro_rules! column {() => {}}
-            column!()
+            const C: u32 = column!();
             "#,
         );
 
-        assert_eq!(expanded, "0");
+        assert_eq!(expanded, "16");
     }
 
     #[test]
@@ -521,11 +666,26 @@ mod tests {
             r#"
             #[rustc_builtin_macro]
             macro_rules! line {() => {}}
+
             line!()
             "#,
         );
 
-        assert_eq!(expanded, "0");
+        assert_eq!(expanded, "4");
+    }
+
+    #[test]
+    fn test_cfg_expand() {
+        let expanded = expand_builtin_macro(
+            r#"
+            //- /main.rs cfg:feature=foo
+            #[rustc_builtin_macro]
+            macro_rules! cfg {() => {}}
+            cfg!(all(feature = "foo", not(test)))
+            "#,
+        );
+
+        assert_eq!(expanded, "true");
     }
 
     #[test]
@@ -541,6 +701,46 @@ mod tests {
         assert_eq!(expanded, "\"a b c\"");
     }
 
+    #[test]
+    fn test_stringify_expand_escapes_quotes() {
+        let expanded = expand_builtin_macro(
+            r#"
+            #[rustc_builtin_macro]
+            macro_rules! stringify {() => {}}
+            stringify!(f("x",
+                1))
+            "#,
+        );
+
+        assert_eq!(expanded, r#""f (\"x\" , 1)""#);
+    }
+
+    #[test]
+    fn test_stringify_expand_keeps_literals() {
+        let expanded = expand_builtin_macro(
+            r#"
+            #[rustc_builtin_macro]
+            macro_rules! stringify {() => {}}
+            stringify!("a   b"   ::  c)
+            "#,
+        );
+
+        assert_eq!(expanded, r#""\"a   b\" :: c""#);
+    }
+
+    #[test]
+    fn test_concat_expand() {
+        let expanded = expand_builtin_macro(
+            r##"
+            #[rustc_builtin_macro]
+            macro_rules! concat {}
+            concat!("foo", 'r', 0, r#"bar"#, false, 1_000u32, 'q', '"', "\n")
+            "##,
+        );
+
+        assert_eq!(expanded, r#""foor0barfalse1000q\"\n""#);
+    }
+
     #[test]
     fn test_env_expand() {
         let expanded = expand_builtin_macro(
@@ -631,6 +831,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_asm_expand() {
+        let expanded = expand_builtin_macro(
+            r#"
+            #[rustc_builtin_macro]
+            macro_rules! asm {
+                ($($tt:tt)*) => ({ /* compiler built-in */ })
+            }
+            asm!("mov {0}, {1}", "add {x}, 1", out(reg) a, inout(reg) b => _, x = inlateout("eax") c => d.e, in(reg) 1, sym foo::bar, const N, options(nostack, noreturn));
+            "#,
+        );
+
+        assert_eq!(expanded, "{&(a);&(b);&(c);&(d.e);&(1);&(foo::bar);&(N);loop{}}");
+    }
+
+    #[test]
+    fn test_global_asm_expand() {
+        let expanded = expand_builtin_macro(
+            r#"
+            #[rustc_builtin_macro]
+            macro_rules! global_asm {
+                ($($tt:tt)*) => ({ /* compiler built-in */ })
+            }
+            global_asm!("nop");
+            "#,
+        );
+
+        assert_eq!(expanded, "");
+    }
+
     #[test]
     fn test_include_bytes_expand() {
         let expanded = expand_builtin_macro(
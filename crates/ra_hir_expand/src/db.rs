@@ -1,6 +1,6 @@
 //! Defines database & queries for macro expansion.
 
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
 use mbe::{ExpandResult, MacroRules};
 use ra_db::{salsa, SourceDatabase};
@@ -62,9 +62,75 @@ impl TokenExpander {
     }
 }
 
+/// Limits which keep pathological macros, like accidentally infinite
+/// `macro_rules!` recursion, from hanging the analysis or exhausting memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacroLimits {
+    /// How many macro calls can be nested inside each other's expansions.
+    pub expansion_depth: usize,
+    /// How many tokens a single macro call can expand to. Longer expansions
+    /// are truncated.
+    pub token_count: usize,
+}
+
+impl Default for MacroLimits {
+    fn default() -> MacroLimits {
+        // More than rustc's default `recursion_limit` of 128, as crates raise
+        // it for deeply recursive macros and we don't read the attribute.
+        MacroLimits { expansion_depth: 1024, token_count: 65536 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MacroError {
+    /// The call is nested `limit` levels deep inside other expansions and was
+    /// not expanded.
+    DepthLimit {
+        limit: usize,
+    },
+    /// The expansion was truncated to `limit` tokens.
+    TokenLimit {
+        limit: usize,
+        count: usize,
+    },
+    /// `include!` names a file which isn't loaded, usually one generated by a
+    /// build script. Such calls are left unexpanded rather than reported.
+    UnresolvedInclude {
+        path: String,
+    },
+    Other(String),
+}
+
+impl fmt::Display for MacroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroError::DepthLimit { limit } => {
+                write!(f, "macro expansion exceeded the recursion limit of {}", limit)
+            }
+            MacroError::TokenLimit { limit, count } => write!(
+                f,
+                "macro expansion produced {} tokens, it was truncated to the limit of {}",
+                count, limit
+            ),
+            MacroError::UnresolvedInclude { path } => {
+                write!(f, "can't find the included file `{}`", path)
+            }
+            MacroError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
 // FIXME: rename to ExpandDatabase
 #[salsa::query_group(AstDatabaseStorage)]
 pub trait AstDatabase: SourceDatabase {
+    #[salsa::input]
+    fn macro_limits(&self) -> MacroLimits;
+    /// Whether an item whose attribute macro fails to expand is analyzed as if
+    /// the attribute was not there. Otherwise, like in rustc, the item is
+    /// dropped.
+    #[salsa::input]
+    fn attr_macro_fallback(&self) -> bool;
+
     fn ast_id_map(&self, file_id: HirFileId) -> Arc<AstIdMap>;
 
     #[salsa::transparent]
@@ -76,7 +142,13 @@ pub trait AstDatabase: SourceDatabase {
     fn macro_def(&self, id: MacroDefId) -> Option<Arc<(TokenExpander, mbe::TokenMap)>>;
     fn parse_macro(&self, macro_file: MacroFile)
         -> Option<(Parse<SyntaxNode>, Arc<mbe::TokenMap>)>;
-    fn macro_expand(&self, macro_call: MacroCallId) -> (Option<Arc<tt::Subtree>>, Option<String>);
+    fn macro_expand(
+        &self,
+        macro_call: MacroCallId,
+    ) -> (Option<Arc<tt::Subtree>>, Option<MacroError>);
+    /// Firewall query that returns only the error of `macro_expand`, so that
+    /// its dependents are not invalidated when just the expansion changes.
+    fn macro_expand_error(&self, macro_call: MacroCallId) -> Option<MacroError>;
 
     #[salsa::interned]
     fn intern_eager_expansion(&self, eager: EagerCallLoc) -> EagerMacroId;
@@ -142,7 +214,7 @@ pub(crate) fn macro_def(
             Some(Arc::new((TokenExpander::BuiltinDerive(expander), mbe::TokenMap::default())))
         }
         MacroDefKind::BuiltInEager(_) => None,
-        MacroDefKind::CustomDerive(expander) => {
+        MacroDefKind::CustomDerive(expander) | MacroDefKind::ProcMacro(expander) => {
             Some(Arc::new((TokenExpander::ProcMacro(expander), mbe::TokenMap::default())))
         }
     }
@@ -168,10 +240,14 @@ pub(crate) fn macro_arg(
 pub(crate) fn macro_expand(
     db: &dyn AstDatabase,
     id: MacroCallId,
-) -> (Option<Arc<tt::Subtree>>, Option<String>) {
+) -> (Option<Arc<tt::Subtree>>, Option<MacroError>) {
     macro_expand_with_arg(db, id, None)
 }
 
+pub(crate) fn macro_expand_error(db: &dyn AstDatabase, id: MacroCallId) -> Option<MacroError> {
+    db.macro_expand(id).1
+}
+
 fn expander(db: &dyn AstDatabase, id: MacroCallId) -> Option<Arc<(TokenExpander, mbe::TokenMap)>> {
     let lazy_id = match id {
         MacroCallId::LazyMacro(id) => id,
@@ -189,17 +265,23 @@ fn macro_expand_with_arg(
     db: &dyn AstDatabase,
     id: MacroCallId,
     arg: Option<Arc<(tt::Subtree, mbe::TokenMap)>>,
-) -> (Option<Arc<tt::Subtree>>, Option<String>) {
+) -> (Option<Arc<tt::Subtree>>, Option<MacroError>) {
     let lazy_id = match id {
         MacroCallId::LazyMacro(id) => id,
         MacroCallId::EagerMacro(id) => {
             if arg.is_some() {
                 return (
                     None,
-                    Some("hypothetical macro expansion not implemented for eager macro".to_owned()),
+                    Some(MacroError::Other(
+                        "hypothetical macro expansion not implemented for eager macro".to_owned(),
+                    )),
                 );
             } else {
-                return (Some(db.lookup_intern_eager_expansion(id).subtree), None);
+                let loc = db.lookup_intern_eager_expansion(id);
+                return match loc.error {
+                    Some(err) => (None, Some(err)),
+                    None => (Some(loc.subtree), None),
+                };
             }
         }
     };
@@ -207,20 +289,41 @@ fn macro_expand_with_arg(
     let loc = db.lookup_intern_macro(lazy_id);
     let macro_arg = match arg.or_else(|| db.macro_arg(id)) {
         Some(it) => it,
-        None => return (None, Some("Fail to args in to tt::TokenTree".into())),
+        None => return (None, Some(MacroError::Other("Fail to args in to tt::TokenTree".into()))),
     };
 
     let macro_rules = match db.macro_def(loc.def) {
         Some(it) => it,
-        None => return (None, Some("Fail to find macro definition".into())),
+        None => return (None, Some(MacroError::Other("Fail to find macro definition".into()))),
     };
-    let ExpandResult(tt, err) = macro_rules.0.expand(db, lazy_id, &macro_arg.0);
-    // Set a hard limit for the expanded tt
+    let ExpandResult(mut tt, err) = macro_rules.0.expand(db, lazy_id, &macro_arg.0);
+    let limit = db.macro_limits().token_count;
     let count = tt.count();
-    if count > 65536 {
-        return (None, Some(format!("Total tokens count exceed limit : count = {}", count)));
+    if count > limit {
+        // Keep the beginning of the expansion, so that IDE features still work
+        // for the parts we did expand.
+        truncate(&mut tt, limit);
+        return (Some(Arc::new(tt)), Some(MacroError::TokenLimit { limit, count }));
     }
-    (Some(Arc::new(tt)), err.map(|e| format!("{:?}", e)))
+    (Some(Arc::new(tt)), err.map(|e| MacroError::Other(format!("{:?}", e))))
+}
+
+/// Drops all token trees after the first `budget` ones, counted the same way
+/// as `tt::Subtree::count` does. Returns the remaining budget.
+fn truncate(tt: &mut tt::Subtree, mut budget: usize) -> usize {
+    let mut len = 0;
+    for child in tt.token_trees.iter_mut() {
+        if budget == 0 {
+            break;
+        }
+        budget -= 1;
+        len += 1;
+        if let tt::TokenTree::Subtree(subtree) = child {
+            budget = truncate(subtree, budget);
+        }
+    }
+    tt.token_trees.truncate(len);
+    budget
 }
 
 pub(crate) fn expand_proc_macro(
@@ -243,7 +346,7 @@ pub(crate) fn expand_proc_macro(
     };
 
     let expander = match loc.def.kind {
-        MacroDefKind::CustomDerive(expander) => expander,
+        MacroDefKind::CustomDerive(expander) | MacroDefKind::ProcMacro(expander) => expander,
         _ => unreachable!(),
     };
 
@@ -344,7 +447,11 @@ fn to_fragment_kind(db: &dyn AstDatabase, id: MacroCallId) -> FragmentKind {
         }
     };
     let syn = db.lookup_intern_macro(lazy_id).kind.node(db).value;
+    call_fragment_kind(&syn)
+}
 
+/// Returns the `FragmentKind` expected at the position of the macro call `syn`.
+pub(crate) fn call_fragment_kind(syn: &SyntaxNode) -> FragmentKind {
     let parent = match syn.parent() {
         Some(it) => it,
         None => {
@@ -389,6 +496,8 @@ fn to_fragment_kind(db: &dyn AstDatabase, id: MacroCallId) -> FragmentKind {
         PREFIX_EXPR => FragmentKind::Expr,
         RANGE_EXPR => FragmentKind::Expr,
         BIN_EXPR => FragmentKind::Expr,
+        // The initializer of a `const` or a `static`.
+        CONST_DEF | STATIC_DEF => FragmentKind::Expr,
         _ => {
             // Unknown , Just guess it is `Items`
             FragmentKind::Items
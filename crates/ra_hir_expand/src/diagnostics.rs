@@ -21,11 +21,23 @@ use ra_syntax::{SyntaxNode, SyntaxNodePtr};
 use crate::{db::AstDatabase, InFile};
 
 pub trait Diagnostic: Any + Send + Sync + fmt::Debug + 'static {
+    fn code(&self) -> DiagnosticCode;
     fn message(&self) -> String;
     fn source(&self) -> InFile<SyntaxNodePtr>;
     fn as_any(&self) -> &(dyn Any + Send + 'static);
 }
 
+/// A stable, kebab-case name of a kind of diagnostic, which users can refer to
+/// to enable or disable it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DiagnosticCode(pub &'static str);
+
+impl DiagnosticCode {
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
 pub trait AstDiagnostic {
     type AST;
     fn ast(&self, db: &dyn AstDatabase) -> Self::AST;
@@ -21,8 +21,10 @@
 
 use crate::{
     ast::{self, AstNode},
-    db::AstDatabase,
-    EagerCallLoc, EagerMacroId, InFile, MacroCallId, MacroCallKind, MacroDefId, MacroDefKind,
+    builtin_macro::EagerExpander,
+    db::{call_fragment_kind, AstDatabase, MacroError},
+    AstId, EagerCallLoc, EagerMacroId, InFile, MacroCallId, MacroCallKind, MacroDefId,
+    MacroDefKind,
 };
 
 use ra_db::CrateId;
@@ -30,6 +32,10 @@ use ra_parser::FragmentKind;
 use ra_syntax::{algo::SyntaxRewriter, SyntaxNode};
 use std::sync::Arc;
 
+/// Expands the eager macro call `macro_call`, after expanding the macro calls
+/// in its arguments. Returns `None` if `def` isn't eager, or if an `include!`
+/// names a file which isn't loaded. Other failures are remembered as the error
+/// of the call, which has no expansion.
 pub fn expand_eager_macro(
     db: &dyn AstDatabase,
     krate: CrateId,
@@ -37,8 +43,35 @@ pub fn expand_eager_macro(
     def: MacroDefId,
     resolver: &dyn Fn(ast::Path) -> Option<MacroDefId>,
 ) -> Option<EagerMacroId> {
-    let args = macro_call.value.token_tree()?;
-    let parsed_args = mbe::ast_to_token_tree(&args)?.0;
+    let call =
+        AstId::new(macro_call.file_id, db.ast_id_map(macro_call.file_id).ast_id(&macro_call.value));
+    let (subtree, fragment, error) =
+        match eager_expand(db, krate, &macro_call, call, def, resolver)? {
+            Ok((subtree, fragment)) => (subtree, fragment, None),
+            Err(MacroError::UnresolvedInclude { .. }) => return None,
+            Err(err) => (tt::Subtree::default(), FragmentKind::Expr, Some(err)),
+        };
+    let eager = EagerCallLoc { def, fragment, subtree: Arc::new(subtree), krate, call, error };
+    Some(db.intern_eager_expansion(eager))
+}
+
+fn eager_expand(
+    db: &dyn AstDatabase,
+    krate: CrateId,
+    macro_call: &InFile<ast::MacroCall>,
+    call: AstId<ast::MacroCall>,
+    def: MacroDefId,
+    resolver: &dyn Fn(ast::Path) -> Option<MacroDefId>,
+) -> Option<Result<(tt::Subtree, FragmentKind), MacroError>> {
+    let expander = match def.kind {
+        MacroDefKind::BuiltInEager(it) => it,
+        _ => return None,
+    };
+    let parsed_args = match macro_call.value.token_tree().and_then(|it| mbe::ast_to_token_tree(&it))
+    {
+        Some((it, _)) => it,
+        None => return Some(Err(MacroError::Other("invalid macro arguments".into()))),
+    };
 
     // Note:
     // When `lazy_expand` is called, its *parent* file must be already exists.
@@ -50,34 +83,39 @@ pub fn expand_eager_macro(
             fragment: FragmentKind::Expr,
             subtree: Arc::new(parsed_args.clone()),
             krate,
-            file_id: macro_call.file_id,
+            call,
+            error: None,
         }
     });
     let arg_file_id: MacroCallId = arg_id.into();
 
-    let parsed_args = mbe::token_tree_to_syntax_node(&parsed_args, FragmentKind::Expr).ok()?.0;
-    let result = eager_macro_recur(
+    let parsed_args = match mbe::token_tree_to_syntax_node(&parsed_args, FragmentKind::Expr) {
+        Ok((parse, _)) => parse.syntax_node(),
+        Err(_) => return Some(Err(MacroError::Other("can't parse the macro arguments".into()))),
+    };
+    // The arguments are expanded first, so that the macro sees their result.
+    let result = match eager_macro_recur(
         db,
-        InFile::new(arg_file_id.as_file(), parsed_args.syntax_node()),
+        InFile::new(arg_file_id.as_file(), parsed_args),
         krate,
         resolver,
-    )?;
-    let subtree = to_subtree(&result)?;
-
-    if let MacroDefKind::BuiltInEager(eager) = def.kind {
-        let (subtree, fragment) = eager.expand(db, arg_id, &subtree).ok()?;
-        let eager = EagerCallLoc {
-            def,
-            fragment,
-            subtree: Arc::new(subtree),
-            krate,
-            file_id: macro_call.file_id,
-        };
-
-        Some(db.intern_eager_expansion(eager))
-    } else {
-        None
-    }
+    )? {
+        Ok(it) => it,
+        Err(err) => return Some(Err(err)),
+    };
+    let subtree = match to_subtree(&result) {
+        Some(it) => it,
+        None => return Some(Err(MacroError::Other("invalid macro arguments".into()))),
+    };
+
+    let res = expander.expand(db, arg_id, &subtree).map(|(subtree, fragment)| {
+        if expander == EagerExpander::Include {
+            // The included file can be anything the call position expects.
+            return (subtree, call_fragment_kind(macro_call.value.syntax()));
+        }
+        (subtree, fragment)
+    });
+    Some(res)
 }
 
 fn to_subtree(node: &SyntaxNode) -> Option<tt::Subtree> {
@@ -86,35 +124,36 @@ fn to_subtree(node: &SyntaxNode) -> Option<tt::Subtree> {
     Some(subtree)
 }
 
-fn lazy_expand(
-    db: &dyn AstDatabase,
-    def: &MacroDefId,
-    macro_call: InFile<ast::MacroCall>,
-    krate: CrateId,
-) -> Option<InFile<SyntaxNode>> {
-    let ast_id = db.ast_id_map(macro_call.file_id).ast_id(&macro_call.value);
-
-    let id: MacroCallId =
-        def.as_lazy_macro(db, krate, MacroCallKind::FnLike(macro_call.with_value(ast_id))).into();
-
-    db.parse_or_expand(id.as_file()).map(|node| InFile::new(id.as_file(), node))
+/// Returns the macro calls in `node` which are not inside of other calls.
+fn outermost_macro_calls(node: &SyntaxNode) -> Vec<ast::MacroCall> {
+    match ast::MacroCall::cast(node.clone()) {
+        Some(it) => vec![it],
+        None => node.children().flat_map(|it| outermost_macro_calls(&it)).collect(),
+    }
 }
 
+/// Replaces the macro calls in `curr` with their expansions, which are
+/// expanded recursively in turn.
 fn eager_macro_recur(
     db: &dyn AstDatabase,
     curr: InFile<SyntaxNode>,
     krate: CrateId,
     macro_resolver: &dyn Fn(ast::Path) -> Option<MacroDefId>,
-) -> Option<SyntaxNode> {
+) -> Option<Result<SyntaxNode, MacroError>> {
     let original = curr.value.clone();
-
-    let children = curr.value.descendants().filter_map(ast::MacroCall::cast);
     let mut rewriter = SyntaxRewriter::default();
 
-    // Collect replacement
-    for child in children {
-        let def: MacroDefId = macro_resolver(child.path()?)?;
-        let insert = match def.kind {
+    for child in outermost_macro_calls(&original) {
+        let path = child.path()?;
+        let def: MacroDefId = match macro_resolver(path.clone()) {
+            Some(it) => it,
+            // Like rustc, the names are resolved once, even if a later
+            // expansion would define the macro.
+            None => {
+                return Some(Err(MacroError::Other(format!("unresolved macro `{}`", path))));
+            }
+        };
+        let id: MacroCallId = match def.kind {
             MacroDefKind::BuiltInEager(_) => {
                 let id: MacroCallId = expand_eager_macro(
                     db,
@@ -124,21 +163,51 @@ fn eager_macro_recur(
                     macro_resolver,
                 )?
                 .into();
-                db.parse_or_expand(id.as_file())?
+                if let Some(err) = db.macro_expand_error(id) {
+                    return Some(Err(err));
+                }
+                id
             }
             MacroDefKind::Declarative
             | MacroDefKind::BuiltIn(_)
             | MacroDefKind::BuiltInDerive(_)
-            | MacroDefKind::CustomDerive(_) => {
-                let expanded = lazy_expand(db, &def, curr.with_value(child.clone()), krate)?;
-                // replace macro inside
-                eager_macro_recur(db, expanded, krate, macro_resolver)?
+            | MacroDefKind::CustomDerive(_)
+            | MacroDefKind::ProcMacro(_) => {
+                let ast_id = db.ast_id_map(curr.file_id).ast_id(&child);
+                def.as_lazy_macro(db, krate, MacroCallKind::FnLike(curr.with_value(ast_id))).into()
+            }
+        };
+        let expanded = match db.parse_or_expand(id.as_file()) {
+            Some(it) => it,
+            None => {
+                let err = db.macro_expand_error(id);
+                return Some(Err(err.unwrap_or_else(|| {
+                    MacroError::Other("failed to parse the macro expansion".into())
+                })));
             }
         };
+        // Lazy macros can expand to other macro calls, which are expanded
+        // before the result is used.
+        let insert = match def.kind {
+            MacroDefKind::BuiltInEager(_) => expanded,
+            _ => match eager_macro_recur(
+                db,
+                InFile::new(id.as_file(), expanded),
+                krate,
+                macro_resolver,
+            )? {
+                Ok(it) => it,
+                Err(err) => return Some(Err(err)),
+            },
+        };
 
+        // The expansion of a lazy macro can be a single macro call, which
+        // can't be replaced inside of itself.
+        if child.syntax() == &original {
+            return Some(Ok(insert));
+        }
         rewriter.replace(child.syntax(), &insert);
     }
 
-    let res = rewriter.rewrite(&original);
-    Some(res)
+    Some(Ok(rewriter.rewrite(&original)))
 }
@@ -2,18 +2,77 @@
 //!
 //! Specifically, `ast` + `Hygiene` allows you to create a `Name`. Note that, at
 //! this moment, this is horribly incomplete and handles only `$crate`.
+//!
+//! `$crate` refers to the crate of the macro whose definition it is written
+//! in. A `$crate` can be passed on to other macros, so the expansions of a
+//! file are kept around to find that macro.
+//!
+//! `SyntaxContext` implements the hygiene of local variables in `macro_rules!`
+//! expansions.
 use either::Either;
 use ra_db::CrateId;
-use ra_syntax::ast;
+use ra_syntax::{ast, AstNode, SyntaxToken};
 
 use crate::{
     db::AstDatabase,
     name::{AsName, Name},
-    HirFileId, HirFileIdRepr, MacroCallId, MacroDefKind,
+    ExpansionInfo, HirFileId, HirFileIdRepr, InFile, MacroCallId, MacroDefKind, Origin,
 };
 
+/// The `macro_rules!` expansion whose definition an identifier was written
+/// in, or the root context for identifiers written outside of macro
+/// definitions, including the arguments of macro calls.
+///
+/// Local variables only resolve to bindings from the same context, so that
+/// the bindings a macro introduces neither capture nor are captured by the
+/// bindings at the call site. Items are not hygienic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SyntaxContext(Option<MacroCallId>);
+
+impl SyntaxContext {
+    pub const ROOT: SyntaxContext = SyntaxContext(None);
+
+    /// Returns the context of `token`, by following it up through the
+    /// expansions it is part of.
+    pub fn of_token(db: &dyn AstDatabase, mut token: InFile<SyntaxToken>) -> SyntaxContext {
+        loop {
+            let macro_file = match token.file_id.0 {
+                HirFileIdRepr::FileId(_) => return SyntaxContext::ROOT,
+                HirFileIdRepr::MacroFile(it) => it,
+            };
+            let info = match token.file_id.expansion_info(db) {
+                Some(it) => it,
+                None => return SyntaxContext::ROOT,
+            };
+            token = match info.map_token_up(token.as_ref()) {
+                Some((_, Origin::Def)) => {
+                    return SyntaxContext(Some(macro_file.macro_call_id));
+                }
+                Some((token, Origin::Call)) => token,
+                // Tokens made up by the expansion belong to the macro call.
+                None => match info.call_node().and_then(|it| {
+                    let token = it.value.first_token()?;
+                    Some(it.with_value(token))
+                }) {
+                    Some(it) => it,
+                    None => return SyntaxContext::ROOT,
+                },
+            };
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Hygiene {
+    /// The expansion of the file, followed by the expansions the macro call
+    /// is nested in, innermost first.
+    frames: Vec<HygieneFrame>,
+}
+
+#[derive(Clone, Debug)]
+struct HygieneFrame {
+    expansion: Option<ExpansionInfo>,
+
     // This is what `$crate` expands to
     def_crate: Option<CrateId>,
 
@@ -22,45 +81,77 @@ pub struct Hygiene {
 }
 
 impl Hygiene {
-    pub fn new(db: &dyn AstDatabase, file_id: HirFileId) -> Hygiene {
-        let (def_crate, local_inner) = match file_id.0 {
-            HirFileIdRepr::FileId(_) => (None, false),
-            HirFileIdRepr::MacroFile(macro_file) => match macro_file.macro_call_id {
+    pub fn new(db: &dyn AstDatabase, mut file_id: HirFileId) -> Hygiene {
+        let mut frames = Vec::new();
+        while let HirFileIdRepr::MacroFile(macro_file) = file_id.0 {
+            let (def_crate, local_inner, call_file) = match macro_file.macro_call_id {
                 MacroCallId::LazyMacro(id) => {
                     let loc = db.lookup_intern_macro(id);
-                    match loc.def.kind {
+                    let (def_crate, local_inner) = match loc.def.kind {
                         MacroDefKind::Declarative => (loc.def.krate, loc.def.local_inner),
                         MacroDefKind::BuiltIn(_) => (None, false),
                         MacroDefKind::BuiltInDerive(_) => (None, false),
                         MacroDefKind::BuiltInEager(_) => (None, false),
                         MacroDefKind::CustomDerive(_) => (None, false),
-                    }
+                        MacroDefKind::ProcMacro(_) => (None, false),
+                    };
+                    (def_crate, local_inner, loc.kind.file_id())
                 }
-                MacroCallId::EagerMacro(_id) => (None, false),
-            },
-        };
-        Hygiene { def_crate, local_inner }
+                MacroCallId::EagerMacro(id) => {
+                    (None, false, db.lookup_intern_eager_expansion(id).call.file_id)
+                }
+            };
+            let expansion = file_id.expansion_info(db);
+            frames.push(HygieneFrame { expansion, def_crate, local_inner });
+            file_id = call_file;
+        }
+        Hygiene { frames }
     }
 
     pub fn new_unhygienic() -> Hygiene {
-        Hygiene { def_crate: None, local_inner: false }
+        Hygiene { frames: Vec::new() }
     }
 
     // FIXME: this should just return name
     pub fn name_ref_to_name(&self, name_ref: ast::NameRef) -> Either<Name, CrateId> {
-        if let Some(def_crate) = self.def_crate {
-            if name_ref.text() == "$crate" {
+        if name_ref.text() == "$crate" {
+            let def_crate = name_ref
+                .syntax()
+                .first_token()
+                .and_then(|it| self.def_frame(it))
+                .and_then(|it| it.def_crate);
+            if let Some(def_crate) = def_crate {
                 return Either::Right(def_crate);
             }
         }
         Either::Left(name_ref.as_name())
     }
 
-    pub fn local_inner_macros(&self) -> Option<CrateId> {
-        if self.local_inner {
-            self.def_crate
+    /// Returns the crate of the macro which wrote `path`, if `path` is a macro
+    /// call to be resolved in that crate.
+    pub fn local_inner_macros(&self, path: &ast::Path) -> Option<CrateId> {
+        let frame = self.def_frame(path.syntax().first_token()?)?;
+        if frame.local_inner {
+            frame.def_crate
         } else {
             None
         }
     }
+
+    /// Returns the expansion whose macro definition `token` comes from.
+    fn def_frame(&self, mut token: SyntaxToken) -> Option<&HygieneFrame> {
+        for frame in self.frames.iter() {
+            let expansion = match &frame.expansion {
+                Some(it) => it,
+                None => return Some(frame),
+            };
+            match expansion.map_token_up(expansion.expanded.with_value(&token)) {
+                Some((_, Origin::Def)) => return Some(frame),
+                Some((it, Origin::Call)) => token = it.value,
+                // Tokens made up by the expander belong to the macro.
+                None => return Some(frame),
+            }
+        }
+        None
+    }
 }
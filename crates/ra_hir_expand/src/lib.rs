@@ -18,16 +18,18 @@ pub mod eager;
 use std::hash::Hash;
 use std::sync::Arc;
 
-use ra_db::{impl_intern_key, salsa, CrateId, FileId};
+use ra_db::{impl_intern_key, salsa, CrateId, FileId, ProcMacroKind};
 use ra_syntax::{
     algo,
-    ast::{self, AstNode},
-    SyntaxNode, SyntaxToken, TextSize,
+    ast::{self, AstNode, AttrsOwner},
+    SyntaxKind::IDENT,
+    SyntaxNode, SyntaxToken,
 };
 
 use crate::ast_id_map::FileAstId;
 use crate::builtin_derive::BuiltinDeriveExpander;
 use crate::builtin_macro::{BuiltinFnLikeExpander, EagerExpander};
+use crate::db::MacroError;
 use crate::proc_macro::ProcMacroExpander;
 
 #[cfg(test)]
@@ -80,7 +82,7 @@ impl HirFileId {
                     }
                     MacroCallId::EagerMacro(id) => {
                         let loc = db.lookup_intern_eager_expansion(id);
-                        loc.file_id
+                        loc.call.file_id
                     }
                 };
                 file_id.original_file(db)
@@ -95,9 +97,9 @@ impl HirFileId {
             HirFileIdRepr::MacroFile(macro_file) => {
                 let lazy_id = match macro_file.macro_call_id {
                     MacroCallId::LazyMacro(id) => id,
-                    MacroCallId::EagerMacro(_id) => {
-                        // FIXME: handle call node for eager macro
-                        return None;
+                    MacroCallId::EagerMacro(id) => {
+                        let call = db.lookup_intern_eager_expansion(id).call;
+                        return Some(call.with_value(call.to_node(db).syntax().clone()));
                     }
                 };
                 let loc = db.lookup_intern_macro(lazy_id);
@@ -121,7 +123,14 @@ impl HirFileId {
                 let loc: MacroCallLoc = db.lookup_intern_macro(lazy_id);
 
                 let arg_tt = loc.kind.arg(db)?;
-                let def_tt = loc.def.ast_id?.to_node(db).token_tree()?;
+                // Proc macros and builtin derives don't have a definition to
+                // map tokens into, but their arguments can still be mapped.
+                let def = match loc.def.ast_id {
+                    Some(ast_id) => {
+                        Some(InFile::new(ast_id.file_id, ast_id.to_node(db).token_tree()?))
+                    }
+                    None => None,
+                };
 
                 let macro_def = db.macro_def(loc.def)?;
                 let (parse, exp_map) = db.parse_macro(macro_file)?;
@@ -129,8 +138,9 @@ impl HirFileId {
 
                 Some(ExpansionInfo {
                     expanded: InFile::new(self, parse.syntax_node()),
+                    call: loc.kind.node(db),
                     arg: InFile::new(loc.kind.file_id(), arg_tt),
-                    def: InFile::new(loc.def.ast_id?.file_id, def_tt),
+                    def,
                     macro_arg,
                     macro_def,
                     exp_map,
@@ -139,26 +149,46 @@ impl HirFileId {
         }
     }
 
-    /// Indicate it is macro file generated for builtin derive
-    pub fn is_builtin_derive(&self, db: &dyn db::AstDatabase) -> Option<InFile<ast::ModuleItem>> {
+    /// Whether this is the expansion of an attribute macro.
+    pub fn is_attr_macro(&self, db: &dyn db::AstDatabase) -> bool {
         match self.0 {
-            HirFileIdRepr::FileId(_) => None,
-            HirFileIdRepr::MacroFile(macro_file) => {
-                let lazy_id = match macro_file.macro_call_id {
-                    MacroCallId::LazyMacro(id) => id,
-                    MacroCallId::EagerMacro(_id) => {
-                        return None;
-                    }
-                };
-                let loc: MacroCallLoc = db.lookup_intern_macro(lazy_id);
-                let item = match loc.def.kind {
-                    MacroDefKind::BuiltInDerive(_) => loc.kind.node(db),
-                    _ => return None,
-                };
-                Some(item.with_value(ast::ModuleItem::cast(item.value.clone())?))
+            HirFileIdRepr::MacroFile(MacroFile { macro_call_id: MacroCallId::LazyMacro(id) }) => {
+                let loc = db.lookup_intern_macro(id);
+                matches!(loc.kind, MacroCallKind::Attr(..)) && loc.def.is_attr_macro(db)
             }
+            _ => false,
         }
     }
+
+    /// If this is the expansion of a builtin or custom derive, returns the
+    /// `#[derive]` attribute which lists it.
+    pub fn derive_attr(&self, db: &dyn db::AstDatabase) -> Option<InFile<ast::Attr>> {
+        let lazy_id = match self.0 {
+            HirFileIdRepr::MacroFile(MacroFile { macro_call_id: MacroCallId::LazyMacro(id) }) => id,
+            _ => return None,
+        };
+        let loc: MacroCallLoc = db.lookup_intern_macro(lazy_id);
+        let (ast_id, name) = match (loc.def.kind, &loc.kind) {
+            (MacroDefKind::BuiltInDerive(_), MacroCallKind::Attr(ast_id, name))
+            | (MacroDefKind::CustomDerive(_), MacroCallKind::Attr(ast_id, name)) => (ast_id, name),
+            _ => return None,
+        };
+        let item = ast_id.to_node(db);
+        let is_derive_of = |attr: &ast::Attr| {
+            let path = attr.path()?;
+            if path.segment()?.name_ref()?.text() != "derive" {
+                return None;
+            }
+            let tt = match attr.input()? {
+                ast::AttrInput::TokenTree(it) => it,
+                ast::AttrInput::Literal(_) => return None,
+            };
+            let mut tokens = tt.syntax().descendants_with_tokens().filter_map(|it| it.into_token());
+            Some(tokens.any(|it| it.kind() == IDENT && it.text() == name))
+        };
+        let attr = item.attrs().find(|it| is_derive_of(it) == Some(true))?;
+        Some(ast_id.with_value(attr))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -217,6 +247,14 @@ impl MacroDefId {
     ) -> LazyMacroId {
         db.intern_macro(MacroCallLoc { def: self, krate, kind })
     }
+
+    /// Whether this is a proc macro which can be used as an attribute.
+    pub fn is_attr_macro(&self, db: &dyn db::AstDatabase) -> bool {
+        match self.kind {
+            MacroDefKind::ProcMacro(it) => it.kind(db) == Some(ProcMacroKind::Attr),
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -227,13 +265,14 @@ pub enum MacroDefKind {
     BuiltInDerive(BuiltinDeriveExpander),
     BuiltInEager(EagerExpander),
     CustomDerive(ProcMacroExpander),
+    ProcMacro(ProcMacroExpander),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MacroCallLoc {
-    pub(crate) def: MacroDefId,
-    pub(crate) krate: CrateId,
-    pub(crate) kind: MacroCallKind,
+    pub def: MacroDefId,
+    pub krate: CrateId,
+    pub kind: MacroCallKind,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -273,6 +312,17 @@ impl MacroCallId {
     pub fn as_file(self) -> HirFileId {
         MacroFile { macro_call_id: self }.into()
     }
+
+    /// Returns the call which produced this macro file. Eager calls are always
+    /// function-like.
+    pub fn call_kind(self, db: &dyn db::AstDatabase) -> MacroCallKind {
+        match self {
+            MacroCallId::LazyMacro(id) => db.lookup_intern_macro(id).kind,
+            MacroCallId::EagerMacro(id) => {
+                MacroCallKind::FnLike(db.lookup_intern_eager_expansion(id).call)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -281,15 +331,18 @@ pub struct EagerCallLoc {
     pub(crate) fragment: FragmentKind,
     pub(crate) subtree: Arc<tt::Subtree>,
     pub(crate) krate: CrateId,
-    pub(crate) file_id: HirFileId,
+    pub(crate) call: AstId<ast::MacroCall>,
+    /// Why the expansion failed, if it did.
+    pub(crate) error: Option<MacroError>,
 }
 
 /// ExpansionInfo mainly describes how to map text range between src and expanded macro
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExpansionInfo {
     expanded: InFile<SyntaxNode>,
+    call: InFile<SyntaxNode>,
     arg: InFile<SyntaxNode>,
-    def: InFile<ast::TokenTree>,
+    def: Option<InFile<ast::TokenTree>>,
 
     macro_def: Arc<(db::TokenExpander, mbe::TokenMap)>,
     macro_arg: Arc<(tt::Subtree, mbe::TokenMap)>,
@@ -301,7 +354,7 @@ use ra_parser::FragmentKind;
 
 impl ExpansionInfo {
     pub fn call_node(&self) -> Option<InFile<SyntaxNode>> {
-        Some(self.arg.with_value(self.arg.value.parent()?))
+        Some(self.call.clone())
     }
 
     pub fn map_token_down(&self, token: InFile<&SyntaxToken>) -> Option<InFile<SyntaxToken>> {
@@ -327,7 +380,7 @@ impl ExpansionInfo {
         let (token_map, tt) = match origin {
             mbe::Origin::Call => (&self.macro_arg.1, self.arg.clone()),
             mbe::Origin::Def => {
-                (&self.macro_def.1, self.def.as_ref().map(|tt| tt.syntax().clone()))
+                (&self.macro_def.1, self.def.as_ref()?.as_ref().map(|tt| tt.syntax().clone()))
             }
         };
 
@@ -407,6 +460,11 @@ impl InFile<SyntaxNode> {
             Some(parent) => Some(node.with_value(parent)),
             None => {
                 let parent_node = node.file_id.call_node(db)?;
+                // The expansion of an attribute macro replaces the item.
+                if node.file_id.is_attr_macro(db) {
+                    let parent = parent_node.value.parent()?;
+                    return Some(parent_node.with_value(parent));
+                }
                 Some(parent_node)
             }
         })
@@ -195,6 +195,9 @@ pub mod known {
         include_str,
         format_args,
         format_args_nl,
+        cfg,
+        asm,
+        global_asm,
         env,
         option_env,
         // Builtin derives
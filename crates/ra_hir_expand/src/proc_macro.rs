@@ -1,7 +1,7 @@
 //! Proc Macro Expander stub
 
-use crate::{db::AstDatabase, LazyMacroId};
-use ra_db::{CrateId, ProcMacroId};
+use crate::{db::AstDatabase, LazyMacroId, MacroCallKind};
+use ra_db::{CrateId, ProcMacroId, ProcMacroKind};
 use tt::buffer::{Cursor, TokenBuffer};
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
@@ -24,10 +24,16 @@ impl ProcMacroExpander {
         ProcMacroExpander { krate, proc_macro_id }
     }
 
+    /// Returns whether this is a derive, function-like or attribute macro.
+    pub fn kind(self, db: &dyn AstDatabase) -> Option<ProcMacroKind> {
+        let krate_graph = db.crate_graph();
+        krate_graph[self.krate].proc_macro.get(self.proc_macro_id.0 as usize).map(|it| it.kind)
+    }
+
     pub fn expand(
         self,
         db: &dyn AstDatabase,
-        _id: LazyMacroId,
+        id: LazyMacroId,
         tt: &tt::Subtree,
     ) -> Result<tt::Subtree, mbe::ExpandError> {
         let krate_graph = db.crate_graph();
@@ -35,12 +41,29 @@ impl ProcMacroExpander {
             .proc_macro
             .get(self.proc_macro_id.0 as usize)
             .clone()
-            .ok_or_else(|| err!("No derive macro found."))?;
+            .ok_or_else(|| err!("No proc macro found."))?;
 
-        let tt = remove_derive_attrs(tt)
-            .ok_or_else(|| err!("Fail to remove derive for custom derive"))?;
-
-        proc_macro.expander.expand(&tt, None).map_err(mbe::ExpandError::from)
+        match proc_macro.kind {
+            ProcMacroKind::CustomDerive => {
+                let tt = remove_derive_attrs(tt)
+                    .ok_or_else(|| err!("Fail to remove derive for custom derive"))?;
+                proc_macro.expander.expand(&tt, None).map_err(mbe::ExpandError::from)
+            }
+            ProcMacroKind::FuncLike => {
+                proc_macro.expander.expand(tt, None).map_err(mbe::ExpandError::from)
+            }
+            ProcMacroKind::Attr => {
+                let name = match db.lookup_intern_macro(id).kind {
+                    MacroCallKind::Attr(_, name) => name,
+                    MacroCallKind::FnLike(_) => {
+                        return Err(err!("Attribute macro called like a function"))
+                    }
+                };
+                let (item, attr) = split_attr(tt, &name)
+                    .ok_or_else(|| err!("Fail to find the attribute of attribute macro"))?;
+                proc_macro.expander.expand(&item, Some(&attr)).map_err(mbe::ExpandError::from)
+            }
+        }
     }
 }
 
@@ -100,6 +123,52 @@ fn remove_derive_attrs(tt: &tt::Subtree) -> Option<tt::Subtree> {
     Some(result)
 }
 
+/// Splits the first `#[name(args)]` attribute off the item `tt`, returning the
+/// item without it and the `args`.
+fn split_attr(tt: &tt::Subtree, name: &str) -> Option<(tt::Subtree, tt::Subtree)> {
+    let mut item = tt::Subtree::default();
+    let mut attr = None;
+    let mut trees = tt.token_trees.iter().peekable();
+    while let Some(tree) = trees.next() {
+        if attr.is_none() {
+            if let (tt::TokenTree::Leaf(tt::Leaf::Punct(punct)), Some(tt::TokenTree::Subtree(it))) =
+                (tree, trees.peek())
+            {
+                if punct.char == '#' && it.delimiter_kind() == Some(tt::DelimiterKind::Bracket) {
+                    attr = attr_args(it, name);
+                    if attr.is_some() {
+                        trees.next();
+                        continue;
+                    }
+                }
+            }
+        }
+        item.token_trees.push(tree.clone());
+    }
+    Some((item, attr?))
+}
+
+/// Returns the `args` of `[path(args)]` if the last segment of `path` is
+/// `name`.
+fn attr_args(attr: &tt::Subtree, name: &str) -> Option<tt::Subtree> {
+    let is_path = |tt: &tt::TokenTree| match tt {
+        tt::TokenTree::Leaf(tt::Leaf::Ident(_)) => true,
+        tt::TokenTree::Leaf(tt::Leaf::Punct(punct)) => punct.char == ':',
+        _ => false,
+    };
+    let path_len = attr.token_trees.iter().take_while(|it| is_path(it)).count();
+    let (path, rest) = attr.token_trees.split_at(path_len);
+    match path.last() {
+        Some(tt::TokenTree::Leaf(tt::Leaf::Ident(ident))) if ident.text.as_str() == name => (),
+        _ => return None,
+    }
+    let token_trees = match rest {
+        [tt::TokenTree::Subtree(args)] => args.token_trees.clone(),
+        _ => rest.to_vec(),
+    };
+    Some(tt::Subtree { delimiter: None, token_trees })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -136,6 +205,47 @@ SUBTREE $
     IDENT   bar 18
     PUNCH   : [alone] 19
     IDENT   u32 20
+"#
+            .trim()
+        );
+    }
+
+    #[test]
+    fn test_split_attr() {
+        let tt = mbe::parse_to_token_tree(
+            r#"
+    #[allow(unused)]
+    #[tokio::main(core_threads = 1)]
+    fn main() {}
+"#,
+        )
+        .unwrap()
+        .0;
+        let (item, attr) = split_attr(&tt, "main").unwrap();
+
+        assert_eq_text!(
+            &format!("{:#?}", item),
+            r#"
+SUBTREE $
+  PUNCH   # [alone] 0
+  SUBTREE [] 1
+    IDENT   allow 2
+    SUBTREE () 3
+      IDENT   unused 4
+  IDENT   fn 15
+  IDENT   main 16
+  SUBTREE () 17
+  SUBTREE {} 18
+"#
+            .trim()
+        );
+        assert_eq_text!(
+            &format!("{:#?}", attr),
+            r#"
+SUBTREE $
+  IDENT   core_threads 12
+  PUNCH   = [alone] 13
+  LITERAL 1 14
 "#
             .trim()
         );
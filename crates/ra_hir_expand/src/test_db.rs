@@ -8,17 +8,27 @@ use std::{
 use ra_db::{salsa, CrateId, FileId, FileLoader, FileLoaderDelegate};
 use rustc_hash::FxHashSet;
 
+use crate::db::AstDatabase;
+
 #[salsa::database(
     ra_db::SourceDatabaseExtStorage,
     ra_db::SourceDatabaseStorage,
     crate::db::AstDatabaseStorage
 )]
-#[derive(Default)]
 pub struct TestDB {
     storage: salsa::Storage<TestDB>,
     events: Mutex<Option<Vec<salsa::Event>>>,
 }
 
+impl Default for TestDB {
+    fn default() -> TestDB {
+        let mut db = TestDB { storage: Default::default(), events: Default::default() };
+        db.set_macro_limits(Default::default());
+        db.set_attr_macro_fallback(true);
+        db
+    }
+}
+
 impl fmt::Debug for TestDB {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("TestDB").finish()
@@ -46,4 +56,10 @@ impl FileLoader for TestDB {
     fn relevant_crates(&self, file_id: FileId) -> Arc<FxHashSet<CrateId>> {
         FileLoaderDelegate(self).relevant_crates(file_id)
     }
+    fn is_library(&self, file_id: FileId) -> bool {
+        FileLoaderDelegate(self).is_library(file_id)
+    }
+    fn is_open(&self, file_id: FileId) -> bool {
+        FileLoaderDelegate(self).is_open(file_id)
+    }
 }
@@ -603,6 +603,10 @@ pub(crate) fn is_useful(
     matrix: &Matrix,
     v: &PatStack,
 ) -> MatchCheckResult<Usefulness> {
+    // The matrix grows with the number of arms, and we recurse for every
+    // constructor, so this can take a while for big generated matches.
+    cx.db.check_canceled();
+
     // Handle two special cases:
     // - enum with no variants
     // - `!` type
@@ -2067,6 +2071,22 @@ mod false_negatives {
         );
     }
 
+    #[test]
+    fn half_open_ranges() {
+        // This is a false negative.
+        // We don't currently check integer exhaustiveness, including ranges.
+        check_no_diagnostic(
+            r"
+            fn test_fn(x: u8) {
+                match x {
+                    0..=9 => (),
+                    20.. => (),
+                }
+            }
+        ",
+        );
+    }
+
     #[test]
     fn internal_or() {
         // This is a false negative.
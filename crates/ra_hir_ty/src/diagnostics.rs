@@ -7,7 +7,7 @@ use ra_syntax::{ast, AstNode, AstPtr, SyntaxNodePtr};
 use stdx::format_to;
 
 pub use hir_def::{diagnostics::UnresolvedModule, expr::MatchArm, path::Path};
-pub use hir_expand::diagnostics::{AstDiagnostic, Diagnostic, DiagnosticSink};
+pub use hir_expand::diagnostics::{AstDiagnostic, Diagnostic, DiagnosticCode, DiagnosticSink};
 
 #[derive(Debug)]
 pub struct NoSuchField {
@@ -16,6 +16,9 @@ pub struct NoSuchField {
 }
 
 impl Diagnostic for NoSuchField {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("no-such-field")
+    }
     fn message(&self) -> String {
         "no such field".to_string()
     }
@@ -47,6 +50,9 @@ pub struct MissingFields {
 }
 
 impl Diagnostic for MissingFields {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("missing-structure-fields")
+    }
     fn message(&self) -> String {
         let mut buf = String::from("Missing structure fields:\n");
         for field in &self.missed_fields {
@@ -80,6 +86,9 @@ pub struct MissingPatFields {
 }
 
 impl Diagnostic for MissingPatFields {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("missing-pat-fields")
+    }
     fn message(&self) -> String {
         let mut buf = String::from("Missing structure fields:\n");
         for field in &self.missed_fields {
@@ -103,6 +112,9 @@ pub struct MissingMatchArms {
 }
 
 impl Diagnostic for MissingMatchArms {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("missing-match-arm")
+    }
     fn message(&self) -> String {
         String::from("Missing match arm")
     }
@@ -121,6 +133,9 @@ pub struct MissingOkInTailExpr {
 }
 
 impl Diagnostic for MissingOkInTailExpr {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("missing-ok-in-tail-expr")
+    }
     fn message(&self) -> String {
         "wrap return expression in Ok".to_string()
     }
@@ -149,6 +164,9 @@ pub struct BreakOutsideOfLoop {
 }
 
 impl Diagnostic for BreakOutsideOfLoop {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("break-outside-of-loop")
+    }
     fn message(&self) -> String {
         "break outside of loop".to_string()
     }
@@ -177,6 +195,9 @@ pub struct MissingUnsafe {
 }
 
 impl Diagnostic for MissingUnsafe {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("missing-unsafe")
+    }
     fn message(&self) -> String {
         format!("This operation is unsafe and requires an unsafe function or block")
     }
@@ -207,6 +228,9 @@ pub struct MismatchedArgCount {
 }
 
 impl Diagnostic for MismatchedArgCount {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode("mismatched-arg-count")
+    }
     fn message(&self) -> String {
         let s = if self.expected == 1 { "" } else { "s" };
         format!("Expected {} argument{}, found {}", self.expected, s, self.found)
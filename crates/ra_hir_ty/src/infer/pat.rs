@@ -113,7 +113,7 @@ impl<'a> InferenceContext<'a> {
         let ty = match &body[pat] {
             Pat::Tuple { ref args, .. } => {
                 let expectations = match expected.as_tuple() {
-                    Some(parameters) => &*parameters.0,
+                    Some(parameters) => &parameters[..],
                     _ => &[],
                 };
                 let expectations_iter = expectations.iter().chain(repeat(&Ty::Unknown));
@@ -124,7 +124,7 @@ impl<'a> InferenceContext<'a> {
                     .map(|(&pat, ty)| self.infer_pat(pat, ty, default_bm))
                     .collect();
 
-                Ty::apply(TypeCtor::Tuple { cardinality: args.len() as u16 }, Substs(inner_tys))
+                Ty::apply(TypeCtor::Tuple { cardinality: args.len() as u16 }, inner_tys)
             }
             Pat::Or(ref pats) => {
                 if let Some((first_pat, rest)) = pats.split_first() {
@@ -151,10 +151,10 @@ impl<'a> InferenceContext<'a> {
                 Ty::apply_one(TypeCtor::Ref(*mutability), subty)
             }
             Pat::TupleStruct { path: p, args: subpats, .. } => {
-                self.infer_tuple_struct_pat(p.as_ref(), subpats, expected, default_bm, pat)
+                self.infer_tuple_struct_pat(p.as_deref(), subpats, expected, default_bm, pat)
             }
             Pat::Record { path: p, args: fields, ellipsis: _ } => {
-                self.infer_record_pat(p.as_ref(), fields, expected, default_bm, pat)
+                self.infer_record_pat(p.as_deref(), fields, expected, default_bm, pat)
             }
             Pat::Path(path) => {
                 // FIXME use correct resolver for the surrounding expression
@@ -203,10 +203,12 @@ impl<'a> InferenceContext<'a> {
                 pat_ty
             }
             Pat::Wild => expected.clone(),
-            Pat::Range { start, end } => {
+            Pat::Range { start, end, range_type: _ } => {
                 let start_ty = self.infer_expr(*start, &Expectation::has_type(expected.clone()));
-                let end_ty = self.infer_expr(*end, &Expectation::has_type(start_ty));
-                end_ty
+                match end {
+                    Some(end) => self.infer_expr(*end, &Expectation::has_type(start_ty)),
+                    None => start_ty,
+                }
             }
             Pat::Lit(expr) => self.infer_expr(*expr, &Expectation::has_type(expected.clone())),
             Pat::Missing => Ty::Unknown,
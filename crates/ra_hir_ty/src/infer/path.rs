@@ -47,8 +47,16 @@ impl<'a> InferenceContext<'a> {
                 id,
             )?
         } else {
-            let value_or_partial =
-                resolver.resolve_path_in_value_ns(self.db.upcast(), path.mod_path())?;
+            let value_or_partial = match id {
+                ExprOrPatId::ExprId(expr) => resolver.resolve_path_in_value_ns_with_context(
+                    self.db.upcast(),
+                    path.mod_path(),
+                    self.body.path_context(expr),
+                )?,
+                ExprOrPatId::PatId(_) => {
+                    resolver.resolve_path_in_value_ns(self.db.upcast(), path.mod_path())?
+                }
+            };
 
             match value_or_partial {
                 ResolveValueResult::ValueNs(it) => (it, None),
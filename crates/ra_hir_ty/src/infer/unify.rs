@@ -143,17 +143,15 @@ impl<T> Canonicalized<T> {
 
     pub fn apply_solution(&self, ctx: &mut InferenceContext<'_>, solution: Canonical<Substs>) {
         // the solution may contain new variables, which we need to convert to new inference vars
-        let new_vars = Substs(
-            solution
-                .kinds
-                .iter()
-                .map(|k| match k {
-                    TyKind::General => ctx.table.new_type_var(),
-                    TyKind::Integer => ctx.table.new_integer_var(),
-                    TyKind::Float => ctx.table.new_float_var(),
-                })
-                .collect(),
-        );
+        let new_vars = solution
+            .kinds
+            .iter()
+            .map(|k| match k {
+                TyKind::General => ctx.table.new_type_var(),
+                TyKind::Integer => ctx.table.new_integer_var(),
+                TyKind::Float => ctx.table.new_float_var(),
+            })
+            .collect::<Substs>();
         for (i, ty) in solution.value.into_iter().enumerate() {
             let var = self.free_vars[i];
             // eagerly replace projections in the type; we may be getting types
@@ -166,14 +164,13 @@ impl<T> Canonicalized<T> {
 
 pub fn unify(tys: &Canonical<(Ty, Ty)>) -> Option<Substs> {
     let mut table = InferenceTable::new();
-    let vars = Substs(
-        tys.kinds
-            .iter()
-            // we always use type vars here because we want everything to
-            // fallback to Unknown in the end (kind of hacky, as below)
-            .map(|_| table.new_type_var())
-            .collect(),
-    );
+    let vars = tys
+        .kinds
+        .iter()
+        // we always use type vars here because we want everything to
+        // fallback to Unknown in the end (kind of hacky, as below)
+        .map(|_| table.new_type_var())
+        .collect::<Substs>();
     let ty1_with_vars = tys.value.0.clone().subst_bound_vars(&vars);
     let ty2_with_vars = tys.value.1.clone().subst_bound_vars(&vars);
     if !table.unify(&ty1_with_vars, &ty2_with_vars) {
@@ -0,0 +1,216 @@
+//! Global interning of type data.
+//!
+//! Types are mostly built from the same few lists of parameters over and over
+//! again, so we keep a single copy of each `Substs`, shared by all threads and
+//! queries. `Ty` itself is not interned, but all of its heap data, including
+//! the parameters of projections, lives in `Substs`. Two interned values are
+//! equal if and only if they point to the same allocation, which makes
+//! comparing and hashing types shallow. A value is removed from the interner
+//! once the last `Interned` pointing to it is dropped.
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+use once_cell::sync::OnceCell;
+use rustc_hash::{FxHashSet, FxHasher};
+
+use crate::Ty;
+
+const N_SHARDS: usize = 16;
+
+pub(crate) struct Interned<T: Internable>(Arc<Entry<T>>);
+
+/// The hash of an entry is computed once, when it is interned.
+struct Entry<T> {
+    hash: u64,
+    value: T,
+}
+
+impl<T: PartialEq> PartialEq for Entry<T> {
+    fn eq(&self, other: &Entry<T>) -> bool {
+        self.hash == other.hash && self.value == other.value
+    }
+}
+
+impl<T: Eq> Eq for Entry<T> {}
+
+impl<T> Hash for Entry<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash)
+    }
+}
+
+type Shard<T> = Mutex<FxHashSet<Arc<Entry<T>>>>;
+
+pub(crate) struct InternStorage<T> {
+    shards: OnceCell<Vec<Shard<T>>>,
+}
+
+impl<T> InternStorage<T> {
+    pub(crate) const fn new() -> InternStorage<T> {
+        InternStorage { shards: OnceCell::new() }
+    }
+
+    fn shards(&self) -> &[Shard<T>] {
+        self.shards.get_or_init(|| (0..N_SHARDS).map(|_| Default::default()).collect())
+    }
+
+    fn shard(&self, hash: u64) -> &Shard<T> {
+        &self.shards()[hash as usize % N_SHARDS]
+    }
+
+    /// Removes the entries no `Interned` points to anymore.
+    fn collect_garbage(&self) {
+        for shard in self.shards() {
+            let mut garbage = Vec::new();
+            let mut shard = shard.lock().unwrap();
+            // Nobody can clone an entry only the storage points to, and
+            // interning it anew takes the lock.
+            shard.retain(|it| {
+                if Arc::strong_count(it) == 1 {
+                    garbage.push(it.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+            // The values might hold interned values of the same shard.
+            drop(shard);
+            drop(garbage);
+        }
+    }
+}
+
+/// Removes interned values which are no longer used.
+///
+/// Dropping the last copy of a value usually removes it right away, but two
+/// threads dropping the last two copies at once can both skip the removal.
+/// Checking the count under the lock on every drop would make all drops of
+/// interned values contend for the shards.
+pub fn collect_garbage() {
+    <Box<[Ty]>>::storage().collect_garbage();
+}
+
+pub(crate) trait Internable: Hash + Eq + Send + Sync + Sized + 'static {
+    fn storage() -> &'static InternStorage<Self>;
+}
+
+impl<T: Internable> Interned<T> {
+    pub(crate) fn new(value: T) -> Interned<T> {
+        let mut hasher = FxHasher::default();
+        value.hash(&mut hasher);
+        let entry = Entry { hash: hasher.finish(), value };
+
+        let mut shard = T::storage().shard(entry.hash).lock().unwrap();
+        if let Some(it) = shard.get(&entry) {
+            return Interned(it.clone());
+        }
+        let entry = Arc::new(entry);
+        shard.insert(entry.clone());
+        Interned(entry)
+    }
+}
+
+impl<T: Internable> Drop for Interned<T> {
+    fn drop(&mut self) {
+        // The storage holds the other reference.
+        if Arc::strong_count(&self.0) == 2 {
+            self.drop_slow();
+        }
+    }
+}
+
+impl<T: Internable> Interned<T> {
+    #[cold]
+    fn drop_slow(&mut self) {
+        let mut shard = T::storage().shard(self.0.hash).lock().unwrap();
+        // Another thread could have interned the same value in the meantime.
+        // Two threads dropping the last two copies at once might both skip
+        // the removal, `collect_garbage` removes such entries later.
+        // The value itself is only dropped with `self.0`, after the lock is
+        // released, because it might hold interned values of the same shard.
+        if Arc::strong_count(&self.0) == 2 {
+            shard.remove(&*self.0);
+        }
+    }
+}
+
+impl<T: Internable> Clone for Interned<T> {
+    fn clone(&self) -> Interned<T> {
+        Interned(self.0.clone())
+    }
+}
+
+impl<T: Internable> PartialEq for Interned<T> {
+    fn eq(&self, other: &Interned<T>) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T: Internable> Eq for Interned<T> {}
+
+impl<T: Internable> Hash for Interned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.0.hash)
+    }
+}
+
+impl<T: Internable> Deref for Interned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0.value
+    }
+}
+
+impl<T: Internable + fmt::Debug> fmt::Debug for Interned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.value.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{collect_garbage, Interned};
+    use crate::{BoundVar, DebruijnIndex, Ty};
+
+    #[test]
+    fn interned_values_are_shared_and_freed() {
+        // No other test uses this type, so the counts are not disturbed.
+        let ty = || Ty::Bound(BoundVar::new(DebruijnIndex::new(4242), 42));
+        let a = Interned::new(vec![ty()].into_boxed_slice());
+        let b = Interned::new(vec![ty()].into_boxed_slice());
+        let c = Interned::new(vec![ty(), ty()].into_boxed_slice());
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(Arc::strong_count(&a.0), 3);
+
+        let entry = Arc::downgrade(&a.0);
+        drop(a);
+        assert!(entry.upgrade().is_some());
+        drop(b);
+        assert!(entry.upgrade().is_none());
+        drop(c);
+    }
+
+    #[test]
+    fn leaked_values_are_collected() {
+        let ty = || Ty::Bound(BoundVar::new(DebruijnIndex::new(4243), 42));
+        let a = Interned::new(vec![ty()].into_boxed_slice());
+        let entry = Arc::downgrade(&a.0);
+        // Drop the reference without removing the entry, like two threads
+        // dropping the last copies at once.
+        let a = std::mem::ManuallyDrop::new(a);
+        drop(unsafe { std::ptr::read(&a.0) });
+        assert!(entry.upgrade().is_some());
+
+        collect_garbage();
+        assert!(entry.upgrade().is_none());
+    }
+}
@@ -44,6 +44,7 @@ mod tests;
 #[cfg(test)]
 mod test_db;
 mod _match;
+mod intern;
 
 use std::ops::Deref;
 use std::sync::Arc;
@@ -59,6 +60,7 @@ use ra_db::{impl_intern_key, salsa, CrateId};
 
 use crate::{
     db::HirDatabase,
+    intern::{InternStorage, Internable, Interned},
     primitive::{FloatTy, IntTy},
     utils::{generics, make_mut_slice, Generics},
 };
@@ -74,6 +76,7 @@ pub use lower::{
 pub use traits::{InEnvironment, Obligation, ProjectionPredicate, TraitEnvironment};
 
 pub use chalk_ir::{BoundVar, DebruijnIndex};
+pub use intern::collect_garbage as collect_interned_garbage;
 use itertools::Itertools;
 
 /// A type constructor or type name: this might be something like the primitive
@@ -374,9 +377,17 @@ pub enum Ty {
     Unknown,
 }
 
-/// A list of substitutions for generic parameters.
+/// A list of substitutions for generic parameters. All lists are interned, so
+/// the same list of types is only stored once.
 #[derive(Clone, PartialEq, Eq, Debug, Hash)]
-pub struct Substs(Arc<[Ty]>);
+pub struct Substs(Interned<Box<[Ty]>>);
+
+impl Internable for Box<[Ty]> {
+    fn storage() -> &'static InternStorage<Box<[Ty]>> {
+        static STORAGE: InternStorage<Box<[Ty]>> = InternStorage::new();
+        &STORAGE
+    }
+}
 
 impl TypeWalk for Substs {
     fn walk(&self, f: &mut impl FnMut(&Ty)) {
@@ -390,27 +401,55 @@ impl TypeWalk for Substs {
         f: &mut impl FnMut(&mut Ty, DebruijnIndex),
         binders: DebruijnIndex,
     ) {
-        for t in make_mut_slice(&mut self.0) {
-            t.walk_mut_binders(f, binders);
+        // Most walks don't change anything, then there is no need to intern
+        // a new list.
+        let mut changed: Option<Vec<Ty>> = None;
+        for (idx, t) in self.0.iter().enumerate() {
+            let mut new_t = t.clone();
+            new_t.walk_mut_binders(f, binders);
+            match &mut changed {
+                Some(tys) => tys.push(new_t),
+                None if new_t != *t => {
+                    let mut tys = self.0[..idx].to_vec();
+                    tys.push(new_t);
+                    changed = Some(tys);
+                }
+                None => (),
+            }
+        }
+        if let Some(tys) = changed {
+            *self = Substs::from(tys);
         }
     }
 }
 
+impl From<Vec<Ty>> for Substs {
+    fn from(tys: Vec<Ty>) -> Substs {
+        Substs(Interned::new(tys.into_boxed_slice()))
+    }
+}
+
+impl iter::FromIterator<Ty> for Substs {
+    fn from_iter<I: IntoIterator<Item = Ty>>(iter: I) -> Substs {
+        Substs::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
 impl Substs {
     pub fn empty() -> Substs {
-        Substs(Arc::new([]))
+        Substs::from(Vec::new())
     }
 
     pub fn single(ty: Ty) -> Substs {
-        Substs(Arc::new([ty]))
+        Substs::from(vec![ty])
     }
 
     pub fn prefix(&self, n: usize) -> Substs {
-        Substs(self.0[..std::cmp::min(self.0.len(), n)].into())
+        self[..std::cmp::min(self.len(), n)].iter().cloned().collect()
     }
 
     pub fn suffix(&self, n: usize) -> Substs {
-        Substs(self.0[self.0.len() - std::cmp::min(self.0.len(), n)..].into())
+        self[self.len() - std::cmp::min(self.len(), n)..].iter().cloned().collect()
     }
 
     pub fn as_single(&self) -> &Ty {
@@ -422,7 +461,7 @@ impl Substs {
 
     /// Return Substs that replace each parameter by itself (i.e. `Ty::Param`).
     pub(crate) fn type_params_for_generics(generic_params: &Generics) -> Substs {
-        Substs(generic_params.iter().map(|(id, _)| Ty::Placeholder(id)).collect())
+        generic_params.iter().map(|(id, _)| Ty::Placeholder(id)).collect()
     }
 
     /// Return Substs that replace each parameter by itself (i.e. `Ty::Param`).
@@ -433,13 +472,11 @@ impl Substs {
 
     /// Return Substs that replace each parameter by a bound variable.
     pub(crate) fn bound_vars(generic_params: &Generics, debruijn: DebruijnIndex) -> Substs {
-        Substs(
-            generic_params
-                .iter()
-                .enumerate()
-                .map(|(idx, _)| Ty::Bound(BoundVar::new(debruijn, idx)))
-                .collect(),
-        )
+        generic_params
+            .iter()
+            .enumerate()
+            .map(|(idx, _)| Ty::Bound(BoundVar::new(debruijn, idx)))
+            .collect()
     }
 
     pub fn build_for_def(db: &dyn HirDatabase, def: impl Into<GenericDefId>) -> SubstsBuilder {
@@ -476,7 +513,7 @@ pub struct SubstsBuilder {
 impl SubstsBuilder {
     pub fn build(self) -> Substs {
         assert_eq!(self.vec.len(), self.param_count);
-        Substs(self.vec.into())
+        Substs::from(self.vec)
     }
 
     pub fn push(mut self, ty: Ty) -> Self {
@@ -702,7 +739,7 @@ impl FnSig {
     }
 
     pub fn from_fn_ptr_substs(substs: &Substs) -> FnSig {
-        FnSig { params_and_return: Arc::clone(&substs.0) }
+        FnSig { params_and_return: substs.iter().cloned().collect() }
     }
 
     pub fn params(&self) -> &[Ty] {
@@ -748,7 +785,7 @@ impl Ty {
     pub fn fn_ptr(sig: FnSig) -> Self {
         Ty::apply(
             TypeCtor::FnPtr { num_args: sig.params().len() as u16 },
-            Substs(sig.params_and_return),
+            sig.params_and_return.iter().cloned().collect(),
         )
     }
 
@@ -31,7 +31,7 @@ use crate::{
     primitive::{FloatTy, IntTy},
     utils::{
         all_super_trait_refs, associated_type_by_name_including_super_traits, generics,
-        make_mut_slice, variant_data,
+        variant_data,
     },
     Binders, BoundVar, DebruijnIndex, FnSig, GenericPredicate, OpaqueTy, OpaqueTyId, PolyFnSig,
     ProjectionPredicate, ProjectionTy, ReturnTypeImplTrait, ReturnTypeImplTraits, Substs,
@@ -149,11 +149,8 @@ impl Ty {
         let ty = match type_ref {
             TypeRef::Never => Ty::simple(TypeCtor::Never),
             TypeRef::Tuple(inner) => {
-                let inner_tys: Arc<[Ty]> = inner.iter().map(|tr| Ty::from_hir(ctx, tr)).collect();
-                Ty::apply(
-                    TypeCtor::Tuple { cardinality: inner_tys.len() as u16 },
-                    Substs(inner_tys),
-                )
+                let inner_tys: Substs = inner.iter().map(|tr| Ty::from_hir(ctx, tr)).collect();
+                Ty::apply(TypeCtor::Tuple { cardinality: inner_tys.len() as u16 }, inner_tys)
             }
             TypeRef::Path(path) => {
                 let (ty, res_) = Ty::from_hir_path(ctx, path);
@@ -178,7 +175,7 @@ impl Ty {
             }
             TypeRef::Placeholder => Ty::Unknown,
             TypeRef::Fn(params) => {
-                let sig = Substs(params.iter().map(|tr| Ty::from_hir(ctx, tr)).collect());
+                let sig: Substs = params.iter().map(|tr| Ty::from_hir(ctx, tr)).collect();
                 Ty::apply(TypeCtor::FnPtr { num_args: sig.len() as u16 - 1 }, sig)
             }
             TypeRef::DynTrait(bounds) => {
@@ -583,7 +580,7 @@ fn substs_from_path_segment(
 
             for default_ty in defaults.iter().skip(substs.len()) {
                 // each default can depend on the previous parameters
-                let substs_so_far = Substs(substs.clone().into());
+                let substs_so_far = Substs::from(substs.clone());
                 substs.push(default_ty.clone().subst(&substs_so_far));
             }
         }
@@ -596,7 +593,7 @@ fn substs_from_path_segment(
     }
     assert_eq!(substs.len(), total_len);
 
-    Substs(substs.into())
+    Substs::from(substs)
 }
 
 impl TraitRef {
@@ -622,7 +619,9 @@ impl TraitRef {
     ) -> Self {
         let mut substs = TraitRef::substs_from_path(ctx, segment, resolved);
         if let Some(self_ty) = explicit_self_ty {
-            make_mut_slice(&mut substs.0)[0] = self_ty;
+            let mut tys = substs.to_vec();
+            tys[0] = self_ty;
+            substs = Substs::from(tys);
         }
         TraitRef { trait_: resolved, substs }
     }
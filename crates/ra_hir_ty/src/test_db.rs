@@ -5,7 +5,7 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use hir_def::{db::DefDatabase, AssocItemId, ModuleDefId, ModuleId};
+use hir_def::{db::DefDatabase, AssocItemId, DefWithBodyId, ModuleDefId, ModuleId};
 use hir_expand::{db::AstDatabase, diagnostics::DiagnosticSink};
 use ra_db::{salsa, CrateId, FileId, FileLoader, FileLoaderDelegate, SourceDatabase, Upcast};
 use ra_syntax::TextRange;
@@ -26,11 +26,19 @@ use crate::{
     hir_def::db::DefDatabaseStorage,
     crate::db::HirDatabaseStorage
 )]
-#[derive(Default)]
 pub struct TestDB {
     storage: salsa::Storage<TestDB>,
     events: Mutex<Option<Vec<salsa::Event>>>,
 }
+
+impl Default for TestDB {
+    fn default() -> TestDB {
+        let mut db = TestDB { storage: Default::default(), events: Default::default() };
+        db.set_macro_limits(Default::default());
+        db.set_attr_macro_fallback(true);
+        db
+    }
+}
 impl fmt::Debug for TestDB {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("TestDB").finish()
@@ -79,6 +87,12 @@ impl FileLoader for TestDB {
     fn relevant_crates(&self, file_id: FileId) -> Arc<FxHashSet<CrateId>> {
         FileLoaderDelegate(self).relevant_crates(file_id)
     }
+    fn is_library(&self, file_id: FileId) -> bool {
+        FileLoaderDelegate(self).is_library(file_id)
+    }
+    fn is_open(&self, file_id: FileId) -> bool {
+        FileLoaderDelegate(self).is_open(file_id)
+    }
 }
 
 impl TestDB {
@@ -100,26 +114,39 @@ impl TestDB {
             let crate_def_map = self.crate_def_map(krate);
 
             let mut fns = Vec::new();
+            let mut other_bodies: Vec<DefWithBodyId> = Vec::new();
             for (module_id, _) in crate_def_map.modules.iter() {
                 for decl in crate_def_map[module_id].scope.declarations() {
-                    if let ModuleDefId::FunctionId(f) = decl {
-                        fns.push(f)
+                    match decl {
+                        ModuleDefId::FunctionId(f) => fns.push(f),
+                        ModuleDefId::ConstId(c) => other_bodies.push(c.into()),
+                        ModuleDefId::StaticId(s) => other_bodies.push(s.into()),
+                        _ => (),
                     }
                 }
 
                 for impl_id in crate_def_map[module_id].scope.impls() {
                     let impl_data = self.impl_data(impl_id);
                     for item in impl_data.items.iter() {
-                        if let AssocItemId::FunctionId(f) = item {
-                            fns.push(*f)
+                        match *item {
+                            AssocItemId::FunctionId(f) => fns.push(f),
+                            AssocItemId::ConstId(c) => other_bodies.push(c.into()),
+                            AssocItemId::TypeAliasId(_) => (),
                         }
                     }
                 }
             }
 
+            for body in other_bodies {
+                let (_, source_map) = self.body_with_source_map(body);
+                source_map.add_diagnostics(&mut DiagnosticSink::new(&mut cb));
+            }
+
             for f in fns {
                 let infer = self.infer(f.into());
                 let mut sink = DiagnosticSink::new(&mut cb);
+                let (_, source_map) = self.body_with_source_map(f.into());
+                source_map.add_diagnostics(&mut sink);
                 infer.add_diagnostics(self, f, &mut sink);
                 let mut validator = ExprValidator::new(f, infer.clone(), &mut sink);
                 validator.validate_body(self);
@@ -19,7 +19,10 @@ use hir_def::{
     nameres::CrateDefMap,
     AssocItemId, DefWithBodyId, LocalModuleId, Lookup, ModuleDefId,
 };
-use hir_expand::{db::AstDatabase, InFile};
+use hir_expand::{
+    db::{AstDatabase, MacroLimits},
+    InFile,
+};
 use insta::assert_snapshot;
 use ra_db::{fixture::WithFixture, FileRange, SourceDatabase, SourceDatabaseExt};
 use ra_syntax::{
@@ -47,15 +50,19 @@ fn check_types_source_code(ra_fixture: &str) {
 
 fn check_types_impl(ra_fixture: &str, display_source: bool) {
     let db = TestDB::with_files(ra_fixture);
+    check_types_in_db(&db, display_source)
+}
+
+fn check_types_in_db(db: &TestDB, display_source: bool) {
     let mut checked_one = false;
     for (file_id, annotations) in db.extract_annotations() {
         for (range, expected) in annotations {
-            let ty = type_at_range(&db, FileRange { file_id, range });
+            let ty = type_at_range(db, FileRange { file_id, range });
             let actual = if display_source {
                 let module = db.module_for_file(file_id);
-                ty.display_source_code(&db, module).unwrap()
+                ty.display_source_code(db, module).unwrap()
             } else {
-                ty.display(&db).to_string()
+                ty.display(db).to_string()
             };
             assert_eq!(expected, actual);
             checked_one = true;
@@ -358,6 +365,71 @@ fn no_such_field_diagnostics() {
     );
 }
 
+#[test]
+fn macro_expansion_limit_diagnostics() {
+    let mut db = TestDB::with_files(
+        r"
+        //- /lib.rs
+        macro_rules! recurse {
+            () => { recurse!() }
+        }
+        fn f() {
+            recurse!();
+        }
+        const C: () = recurse!();
+        static S: () = recurse!();
+        struct A;
+        impl A {
+            const C: () = recurse!();
+        }
+        ",
+    );
+    db.set_macro_limits(MacroLimits { expansion_depth: 4, token_count: 65536 });
+    let diagnostics = db.diagnostics().0;
+
+    assert_snapshot!(diagnostics, @r###"
+    "recurse!()": macro expansion exceeded the recursion limit of 4
+    "recurse!()": macro expansion exceeded the recursion limit of 4
+    "recurse!()": macro expansion exceeded the recursion limit of 4
+    "recurse!()": macro expansion exceeded the recursion limit of 4
+    "###
+    );
+}
+
+#[test]
+fn eager_macro_error_diagnostics() {
+    let diagnostics = TestDB::with_files(
+        r#"
+        //- /lib.rs
+        #[rustc_builtin_macro]
+        macro_rules! concat {() => {}}
+        #[rustc_builtin_macro]
+        macro_rules! env {() => {}}
+        #[rustc_builtin_macro]
+        macro_rules! include {() => {}}
+        macro_rules! foo {() => {concat!(foo)}}
+        include!(concat!(env!("OUT_DIR"), "/x.rs"));
+        fn f() {
+            let x = concat!(foo);
+            let y = concat!(foo!(), "/y");
+            let z = concat!("x", "/y");
+            let w = concat!(bar!(), "/w");
+            let v = concat!("a" "b");
+        }
+        "#,
+    )
+    .diagnostics()
+    .0;
+
+    assert_snapshot!(diagnostics, @r###"
+    "concat!(foo)": expected a literal, found `foo`
+    "concat!(foo!(), \"/y\")": expected a literal, found `foo`
+    "concat!(bar!(), \"/w\")": unresolved macro `bar`
+    "concat!(\"a\" \"b\")": expected `,`, found `"b"`
+    "###
+    );
+}
+
 #[test]
 fn no_such_field_with_feature_flag_diagnostics() {
     let diagnostics = TestDB::with_files(
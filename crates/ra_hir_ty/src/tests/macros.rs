@@ -1,9 +1,11 @@
-use std::fs;
+use std::{fs, sync::Arc};
 
 use insta::assert_snapshot;
+use ra_db::{fixture::WithFixture, ProcMacro, ProcMacroKind};
 use test_utils::project_dir;
 
-use super::{check_types, infer};
+use super::{check_types, check_types_in_db, infer};
+use crate::test_db::TestDB;
 
 #[test]
 fn cfg_impl_def() {
@@ -444,6 +446,37 @@ macro_rules! bar {
     );
 }
 
+#[test]
+fn infer_dollar_crate_in_nested_cross_crate_macros() {
+    check_types(
+        r#"
+//- /main.rs crate:main deps:foo,bar
+fn make() -> u8 { 0 }
+
+fn test() {
+    let x = foo::make!();
+    x;
+} //^ u64
+
+//- /lib.rs crate:foo deps:bar
+pub fn make() -> u64 { 0 }
+
+#[macro_export]
+macro_rules! make {
+    () => { bar::call!($crate::make) };
+}
+
+//- /bar.rs crate:bar
+pub fn make() -> i32 { 0 }
+
+#[macro_export]
+macro_rules! call {
+    ($f:expr) => { $f() };
+}
+"#,
+    );
+}
+
 #[test]
 fn infer_builtin_macros_line() {
     assert_snapshot!(
@@ -456,7 +489,7 @@ fn main() {
 }
 "#),
         @r###"
-    !0..1 '0': i32
+    !0..1 '5': i32
     63..87 '{     ...!(); }': ()
     73..74 'x': i32
     "###
@@ -494,13 +527,48 @@ fn main() {
 }
 "#),
         @r###"
-    !0..1 '0': i32
+    !0..2 '13': i32
     65..91 '{     ...!(); }': ()
     75..76 'x': i32
     "###
     );
 }
 
+#[test]
+fn infer_builtin_macros_asm() {
+    assert_snapshot!(
+        infer(r#"
+#[rustc_builtin_macro]
+macro_rules! asm {() => {}}
+
+fn main() {
+    let a = 1u8;
+    let b;
+    asm!("mov {}, {}", out(reg) b, in(reg) a);
+    let c: u8 = b;
+    let x = asm!("ud2", options(noreturn));
+}
+"#),
+        @r###"
+    !0..8 '{loop{}}': !
+    !0..12 '{&(b);&(a);}': ()
+    !1..5 '&(b)': &u8
+    !1..7 'loop{}': !
+    !3..4 'b': u8
+    !5..7 '{}': ()
+    !6..10 '&(a)': &u8
+    !8..9 'a': u8
+    62..203 '{     ...n)); }': ()
+    72..73 'a': u8
+    76..79 '1u8': u8
+    89..90 'b': u8
+    147..148 'c': u8
+    155..156 'b': u8
+    166..167 'x': !
+    "###
+    );
+}
+
 #[test]
 fn infer_builtin_macros_concat() {
     assert_snapshot!(
@@ -628,6 +696,45 @@ fn main() {
     );
 }
 
+#[test]
+fn infer_builtin_macros_include_expression() {
+    check_types(
+        r#"
+//- /main.rs
+#[rustc_builtin_macro]
+macro_rules! include {() => {}}
+
+fn main() {
+    let table = include!("table.in");
+    table;
+} //^ [u8; _]
+
+//- /table.in
+[1u8, 2, 3]
+"#,
+    );
+}
+
+#[test]
+fn infer_builtin_macros_cfg() {
+    assert_snapshot!(
+        infer(r#"
+//- /main.rs cfg:feature=foo
+#[rustc_builtin_macro]
+macro_rules! cfg {() => {}}
+
+fn main() {
+    let x = cfg!(feature = "foo");
+}
+"#),
+        @r###"
+    !0..4 'true': bool
+    62..100 '{     ...o"); }': ()
+    72..73 'x': bool
+    "###
+    );
+}
+
 #[test]
 fn infer_builtin_macros_concat_with_lazy() {
     assert_snapshot!(
@@ -649,6 +756,74 @@ fn main() {
     );
 }
 
+#[test]
+fn infer_builtin_macros_concat_with_env() {
+    assert_snapshot!(
+        infer(r#"
+//- /main.rs env:foo=bar
+#[rustc_builtin_macro]
+macro_rules! env {() => {}}
+
+#[rustc_builtin_macro]
+macro_rules! concat {() => {}}
+
+macro_rules! foo {() => {env!("foo")}}
+
+fn main() {
+    let x = concat!(env!("foo"), "/y");
+    let y = concat!(foo!(), "/y");
+}
+"#),
+        @r###"
+    !0..7 '"bar/y"': &str
+    !0..7 '"bar/y"': &str
+    157..235 '{     ...y"); }': ()
+    167..168 'x': &str
+    207..208 'y': &str
+    "###
+    );
+}
+
+#[test]
+fn infer_builtin_macros_nested_format_args() {
+    check_types(
+        r#"
+//- /main.rs crate:main deps:std env:foo=bar
+#[rustc_builtin_macro]
+macro_rules! format_args {() => {}}
+
+#[rustc_builtin_macro]
+macro_rules! env {() => {}}
+
+#[rustc_builtin_macro]
+macro_rules! concat {() => {}}
+
+fn main() {
+    let x = format_args!("{}", format_args!("{}", concat!(env!("foo"), "/y")));
+    x;
+} //^ Arguments
+
+//- /std.rs crate:std
+pub mod fmt {
+    pub struct Formatter;
+    pub struct Error;
+    pub type Result = core::result::Result<(), Error>;
+    pub trait Display {
+        fn fmt(&self, f: &mut Formatter) -> Result;
+    }
+    pub struct ArgumentV1;
+    impl ArgumentV1 {
+        pub fn new<T>(x: &T, f: fn(&T, &mut Formatter) -> Result) -> ArgumentV1 { ArgumentV1 }
+    }
+    pub struct Arguments;
+    impl Arguments {
+        pub fn new_v1(pieces: &[&str], args: &[ArgumentV1]) -> Arguments { Arguments }
+    }
+}
+"#,
+    );
+}
+
 #[test]
 fn infer_builtin_macros_env() {
     assert_snapshot!(
@@ -662,7 +837,7 @@ fn main() {
 }
 "#),
         @r###"
-    !0..22 '"__RA_...TED__"': &str
+    !0..5 '"bar"': &str
     62..90 '{     ...o"); }': ()
     72..73 'x': &str
     "###
@@ -785,3 +960,96 @@ fn main() {
     "###
     );
 }
+
+#[test]
+fn macro_rules_local_variables_are_hygienic() {
+    check_types(
+        r#"
+macro_rules! shadow {
+    ($e:expr) => {{ let x = "hygienic"; $e }};
+}
+macro_rules! capture {
+    () => {{ let y = 1u8; y }};
+}
+
+fn main() {
+    let x = 0u32;
+    let y = 0u64;
+    let t = (shadow!(x), capture!(), y);
+    t;
+} //^ (u32, u8, u64)
+"#,
+    );
+}
+
+#[test]
+fn macro_rules_hygiene_through_nested_calls() {
+    check_types(
+        r#"
+macro_rules! inner {
+    ($e:expr) => { $e };
+}
+macro_rules! outer {
+    () => {{ let z = 1u8; inner!(z) }};
+}
+macro_rules! use_call_site {
+    () => { w };
+}
+
+fn main() {
+    let z = 0u32;
+    let w = 0u64;
+    let t = (outer!(), z, use_call_site!());
+    t;
+} //^ (u8, u32, {unknown})
+"#,
+    );
+}
+
+#[test]
+fn infer_custom_derive_output() {
+    /// Expands to the given items, ignoring the input.
+    #[derive(Debug)]
+    struct Expander(&'static str);
+    impl tt::TokenExpander for Expander {
+        fn expand(
+            &self,
+            _subtree: &tt::Subtree,
+            _attrs: Option<&tt::Subtree>,
+        ) -> Result<tt::Subtree, tt::ExpansionError> {
+            Ok(mbe::parse_to_token_tree(self.0).unwrap().0)
+        }
+    }
+    let mut db = TestDB::with_files(
+        r#"
+//- /main.rs crate:main deps:proc
+trait Serialize {
+    fn serialize(&self) -> u32;
+}
+
+#[derive(proc::Builder, proc::Serialize)]
+struct Foo;
+
+fn test() {
+    let builder = Foo::builder();
+    (builder, Foo.serialize());
+} //^ (FooBuilder, u32)
+
+//- /lib.rs crate:proc
+"#,
+    );
+    let derives = vec![
+        ("Builder", "struct FooBuilder; impl Foo { fn builder() -> FooBuilder { FooBuilder } }"),
+        ("Serialize", "impl crate::Serialize for Foo { fn serialize(&self) -> u32 { 0 } }"),
+    ];
+    let proc_macros = derives
+        .into_iter()
+        .map(|(name, expansion)| ProcMacro {
+            name: name.into(),
+            kind: ProcMacroKind::CustomDerive,
+            expander: Arc::new(Expander(expansion)),
+        })
+        .collect();
+    db.set_proc_macros("proc", proc_macros);
+    check_types_in_db(&db, false);
+}
@@ -146,22 +146,82 @@ fn test(x: &i32) {
 fn infer_range_pattern() {
     assert_snapshot!(
         infer_with_mismatches(r#"
+const MIN: u32 = 1;
 fn test(x: &i32) {
     if let 1..76 = 2u32 {}
     if let 1..=76 = 2u32 {}
+    if let MIN.. = 2u32 {}
 }
 "#, true),
         @r###"
-    8..9 'x': &i32
-    17..75 '{     ...2 {} }': ()
-    23..45 'if let...u32 {}': ()
-    30..35 '1..76': u32
-    38..42 '2u32': u32
-    43..45 '{}': ()
-    50..73 'if let...u32 {}': ()
-    57..63 '1..=76': u32
-    66..70 '2u32': u32
-    71..73 '{}': ()
+    17..18 '1': u32
+    28..29 'x': &i32
+    37..122 '{     ...2 {} }': ()
+    43..65 'if let...u32 {}': ()
+    50..51 '1': u32
+    50..55 '1..76': u32
+    53..55 '76': u32
+    58..62 '2u32': u32
+    63..65 '{}': ()
+    70..93 'if let...u32 {}': ()
+    77..78 '1': u32
+    77..83 '1..=76': u32
+    81..83 '76': u32
+    86..90 '2u32': u32
+    91..93 '{}': ()
+    98..120 'if let...u32 {}': ()
+    105..110 'MIN..': u32
+    113..117 '2u32': u32
+    118..120 '{}': ()
+    "###
+    );
+}
+
+#[test]
+fn infer_or_pattern() {
+    assert_snapshot!(
+        infer(r#"
+enum E { A(u32), B(u32) }
+
+fn test(e: E, es: [E; 2]) {
+    let E::A(x) | E::B(x) = e;
+    let | E::A(y) | E::B(y) = e;
+    for (E::A(z) | E::B(z)) in es {}
+    let f = |(E::A(w) | E::B(w))| w;
+}
+"#),
+        @r###"
+    35..36 'e': E
+    41..43 'es': [E; _]
+    53..194 '{     ...| w; }': ()
+    63..70 'E::A(x)': E
+    63..80 'E::A(x...::B(x)': E
+    68..69 'x': u32
+    73..80 'E::B(x)': E
+    78..79 'x': u32
+    83..84 'e': E
+    96..103 'E::A(y)': E
+    96..113 'E::A(y...::B(y)': E
+    101..102 'y': u32
+    106..113 'E::B(y)': E
+    111..112 'y': u32
+    116..117 'e': E
+    123..155 'for (E... es {}': ()
+    128..135 'E::A(z)': E
+    128..145 'E::A(z...::B(z)': E
+    133..134 'z': u32
+    138..145 'E::B(z)': E
+    143..144 'z': u32
+    150..152 'es': [E; _]
+    153..155 '{}': ()
+    164..165 'f': |E| -> u32
+    168..191 '|(E::A...w))| w': |E| -> u32
+    170..177 'E::A(w)': E
+    170..187 'E::A(w...::B(w)': E
+    175..176 'w': u32
+    180..187 'E::B(w)': E
+    185..186 'w': u32
+    190..191 'w': u32
     "###
     );
 }
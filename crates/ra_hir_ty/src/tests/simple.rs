@@ -725,6 +725,24 @@ fn test() {
     );
 }
 
+#[test]
+fn infer_union_field() {
+    check_types(
+        r#"
+union U {
+    a: u32,
+    b: f32,
+}
+
+fn test() {
+    let u = U { a: 0 };
+    let x = unsafe { u.b };
+    x;
+} //^ f32
+"#,
+    );
+}
+
 #[test]
 fn infer_field_autoderef() {
     assert_snapshot!(
@@ -170,7 +170,7 @@ fn ref_from_chalk(
         .iter(&Interner)
         .filter_map(|p| Some(from_chalk(db, p.ty(&Interner)?.clone())))
         .collect();
-    Ty::apply(TypeCtor::Ref(from_chalk(db, mutability)), Substs(tys))
+    Ty::apply(TypeCtor::Ref(from_chalk(db, mutability)), tys)
 }
 
 impl ToChalk for Substs {
@@ -181,14 +181,13 @@ impl ToChalk for Substs {
     }
 
     fn from_chalk(db: &dyn HirDatabase, parameters: chalk_ir::Substitution<Interner>) -> Substs {
-        let tys = parameters
+        parameters
             .iter(&Interner)
             .map(|p| match p.ty(&Interner) {
                 Some(ty) => from_chalk(db, ty.clone()),
                 None => unimplemented!(),
             })
-            .collect();
-        Substs(tys)
+            .collect()
     }
 }
 
@@ -71,6 +71,58 @@ mod tests {
         expect.assert_eq(&actual);
     }
 
+    #[test]
+    fn completes_after_unclosed_block() {
+        check(
+            r#"
+fn foo() {
+    if true {
+}
+struct S { foo: u32 }
+impl S { fn bar(&self) {} }
+fn baz(s: S) { s.<|> }
+"#,
+            expect![[r#"
+                me bar() fn bar(&self)
+                fd foo   u32
+            "#]],
+        );
+    }
+
+    #[test]
+    fn completes_in_unclosed_block() {
+        check(
+            r#"
+struct S { foo: u32 }
+fn baz(s: S) {
+    let x = ;
+    s.<|>
+
+impl S { fn bar(&self) {} }
+"#,
+            expect![[r#"
+                me bar() fn bar(&self)
+                fd foo   u32
+            "#]],
+        );
+    }
+
+    #[test]
+    fn completes_after_unterminated_string() {
+        check(
+            r#"
+fn foo() {
+    let s = "unterminated;
+}
+struct S { foo: u32 }
+fn baz(s: S) { s.<|> }
+"#,
+            expect![[r#"
+                fd foo u32
+            "#]],
+        );
+    }
+
     #[test]
     fn test_struct_field_and_method_completion() {
         check(
@@ -23,12 +23,32 @@ use ra_text_edit::{TextEdit, TextEditBuilder};
 
 use crate::{Diagnostic, FileId, FileSystemEdit, Fix, SourceFileEdit};
 
+mod asm;
+
 #[derive(Debug, Copy, Clone)]
 pub enum Severity {
     Error,
     WeakWarning,
 }
 
+/// The names of all diagnostics, see `Diagnostic::name`.
+pub const DIAGNOSTIC_NAMES: &[&str] = &[
+    "break-outside-of-loop",
+    "invalid-asm",
+    "macro-error",
+    "mismatched-arg-count",
+    "missing-match-arm",
+    "missing-ok-in-tail-expr",
+    "missing-pat-fields",
+    "missing-structure-fields",
+    "missing-unsafe",
+    "no-such-field",
+    "syntax-error",
+    "unnecessary-braces",
+    "unresolved-module",
+    "use-field-shorthand",
+];
+
 pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic> {
     let _p = profile("diagnostics");
     let sema = Semantics::new(db);
@@ -37,19 +57,22 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
 
     // [#34344] Only take first 500 errors to prevent slowing down editor/ide, the number 500 is chosen arbitrarily.
     res.extend(parse.errors().iter().take(500).map(|err| Diagnostic {
+        name: "syntax-error",
         range: err.range(),
         message: format!("Syntax Error: {}", err),
         severity: Severity::Error,
         fix: None,
     }));
 
-    for node in parse.tree().syntax().descendants() {
+    for node in sema.parse(file_id).syntax().descendants() {
         check_unnecessary_braces_in_use_statement(&mut res, file_id, &node);
         check_struct_shorthand_initialization(&mut res, file_id, &node);
+        asm::check_asm_macro_call(&mut res, &sema, &node);
     }
     let res = RefCell::new(res);
     let mut sink = DiagnosticSink::new(|d| {
         res.borrow_mut().push(Diagnostic {
+            name: d.code().as_str(),
             message: d.message(),
             range: sema.diagnostics_range(d).range,
             severity: Severity::Error,
@@ -63,6 +86,7 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
             FileSystemEdit::CreateFile { anchor: original_file, dst: d.candidate.clone() }.into(),
         );
         res.borrow_mut().push(Diagnostic {
+            name: d.code().as_str(),
             range: sema.diagnostics_range(d).range,
             message: d.message(),
             severity: Severity::Error,
@@ -94,6 +118,7 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
         };
 
         res.borrow_mut().push(Diagnostic {
+            name: d.code().as_str(),
             range: sema.diagnostics_range(d).range,
             message: d.message(),
             severity: Severity::Error,
@@ -107,6 +132,7 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
         let source_change = SourceFileEdit { file_id, edit }.into();
         let fix = Fix::new("Wrap with ok", source_change);
         res.borrow_mut().push(Diagnostic {
+            name: d.code().as_str(),
             range: sema.diagnostics_range(d).range,
             message: d.message(),
             severity: Severity::Error,
@@ -115,6 +141,7 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
     })
     .on::<hir::diagnostics::NoSuchField, _>(|d| {
         res.borrow_mut().push(Diagnostic {
+            name: d.code().as_str(),
             range: sema.diagnostics_range(d).range,
             message: d.message(),
             severity: Severity::Error,
@@ -126,7 +153,13 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
         m.diagnostics(db, &mut sink);
     };
     drop(sink);
-    res.into_inner()
+    let res = res.into_inner();
+    debug_assert!(
+        res.iter().all(|it| DIAGNOSTIC_NAMES.contains(&it.name)),
+        "unlisted diagnostic name in {:?}",
+        res
+    );
+    res
 }
 
 fn missing_struct_field_fix(
@@ -213,6 +246,7 @@ fn check_unnecessary_braces_in_use_statement(
                 });
 
         acc.push(Diagnostic {
+            name: "unnecessary-braces",
             range,
             message: "Unnecessary braces in use statement".to_string(),
             severity: Severity::WeakWarning,
@@ -258,6 +292,7 @@ fn check_struct_shorthand_initialization(
                 let edit = edit_builder.finish();
 
                 acc.push(Diagnostic {
+                    name: "use-field-shorthand",
                     range: record_field.syntax().text_range(),
                     message: "Shorthand struct initialization".to_string(),
                     severity: Severity::WeakWarning,
@@ -584,6 +619,7 @@ fn test_fn() {
             expect![[r#"
                 [
                     Diagnostic {
+                        name: "unresolved-module",
                         message: "unresolved module",
                         range: 0..8,
                         severity: Error,
@@ -628,7 +664,7 @@ fn main() {
 pub struct Foo { pub a: i32, pub b: i32 }
 "#,
             r#"
-fn {a:42, b: ()} {}
+fn some(, b: ()) {}
 fn items() {}
 fn here() {}
 
@@ -0,0 +1,588 @@
+//! Validates the template strings and the operands of `asm!` and `global_asm!`.
+//!
+//! hir expands the operands for name resolution and type inference, but the
+//! compiler parses the rest of the macro input itself, so we check it here,
+//! syntactically, to give feedback without running `cargo check`.
+
+use hir::Semantics;
+use ra_ide_db::RootDatabase;
+use ra_syntax::{
+    ast::{self, AstNode},
+    NodeOrToken, SyntaxElement,
+    SyntaxKind::*,
+    SyntaxNode, SyntaxToken, TextRange, TextSize, T,
+};
+
+use crate::{diagnostics::Severity, Diagnostic};
+
+const DIRECTIONS: &[&str] = &["in", "out", "lateout", "inout", "inlateout"];
+const OPTIONS: &[&str] =
+    &["pure", "nomem", "readonly", "preserves_flags", "noreturn", "nostack", "att_syntax", "raw"];
+const GLOBAL_OPTIONS: &[&str] = &["att_syntax", "raw"];
+
+pub(super) fn check_asm_macro_call(
+    acc: &mut Vec<Diagnostic>,
+    sema: &Semantics<RootDatabase>,
+    node: &SyntaxNode,
+) -> Option<()> {
+    let macro_call = ast::MacroCall::cast(node.clone())?;
+    let is_global = match macro_call.path()?.segment()?.name_ref()?.text().as_str() {
+        "asm" => false,
+        "global_asm" => true,
+        _ => return None,
+    };
+    // A user-defined macro may reuse the name with a different syntax.
+    if let Some(def) = sema.resolve_macro_call(&macro_call) {
+        if !def.is_builtin() {
+            return None;
+        }
+    }
+    let token_tree = macro_call.token_tree()?;
+    let args = split_args(token_tree.syntax());
+    if args.is_empty() {
+        acc.push(error(token_tree.syntax().text_range(), "requires at least a template string"));
+        return Some(());
+    }
+
+    let mut checker = Checker {
+        acc,
+        is_global,
+        templates: Vec::new(),
+        opaque_template: false,
+        operands: Vec::new(),
+        seen_operand: false,
+        seen_options: false,
+        next_implicit: 0,
+        malformed: false,
+    };
+    for arg in args {
+        checker.arg(&arg);
+    }
+    checker.finish();
+    Some(())
+}
+
+struct Operand {
+    range: TextRange,
+    name: Option<String>,
+    explicit_register: bool,
+    used: bool,
+}
+
+struct Checker<'a> {
+    acc: &'a mut Vec<Diagnostic>,
+    is_global: bool,
+    templates: Vec<SyntaxToken>,
+    /// Set when a template is not a literal, e.g. `concat!(...)`, so that its
+    /// placeholders are unknown.
+    opaque_template: bool,
+    operands: Vec<Operand>,
+    seen_operand: bool,
+    seen_options: bool,
+    /// The next argument referenced by an implicit `{}` placeholder.
+    next_implicit: usize,
+    /// Set once an error is reported. Like the compiler, we don't validate
+    /// the templates against malformed operands.
+    malformed: bool,
+}
+
+impl Checker<'_> {
+    fn arg(&mut self, arg: &[SyntaxElement]) {
+        let range =
+            TextRange::new(arg[0].text_range().start(), arg[arg.len() - 1].text_range().end());
+        if !self.seen_operand {
+            if let [NodeOrToken::Token(token)] = arg {
+                if token.kind() == STRING || token.kind() == RAW_STRING {
+                    self.templates.push(token.clone());
+                    return;
+                }
+            }
+            if is_macro_call(arg) {
+                self.opaque_template = true;
+                return;
+            }
+            if self.templates.is_empty() && !self.opaque_template {
+                self.push_error(range, "asm template must be a string literal");
+                self.opaque_template = true;
+                return;
+            }
+        }
+        self.seen_operand = true;
+
+        if token_text(&arg[0]) == Some("options") {
+            match arg {
+                [_, NodeOrToken::Node(options)] if options.kind() == TOKEN_TREE => {
+                    self.options(options);
+                }
+                _ => self.push_error(range, "expected `(` after `options`"),
+            }
+            self.seen_options = true;
+            return;
+        }
+        if self.seen_options {
+            self.push_error(range, "arguments are not allowed after options");
+            return;
+        }
+
+        let (name, operand) = match arg {
+            [NodeOrToken::Token(name), eq, rest @ ..]
+                if name.kind() == IDENT && eq.kind() == T![=] && !is_fat_arrow(&arg[1..]) =>
+            {
+                (Some(name.text().to_string()), rest)
+            }
+            _ => (None, arg),
+        };
+        if operand.is_empty() {
+            self.push_error(range, "expected operand after `=`");
+            return;
+        }
+        let explicit_register = match self.operand(operand, range) {
+            Some(it) => it,
+            None => return,
+        };
+
+        if let Some(name) = &name {
+            if explicit_register {
+                self.push_error(range, "explicit register arguments cannot have names");
+            } else if self.operands.iter().any(|it| it.name.as_ref() == Some(name)) {
+                self.push_error(range, &format!("duplicate argument named `{}`", name));
+            }
+        } else if !explicit_register
+            && self.operands.iter().any(|it| it.name.is_some() || it.explicit_register)
+        {
+            self.push_error(
+                range,
+                "positional arguments cannot follow named arguments or explicit register arguments",
+            );
+        }
+        self.operands.push(Operand { range, name, explicit_register, used: false });
+    }
+
+    /// Checks an operand without its name. Returns whether it uses an explicit
+    /// register, or `None` if it is malformed.
+    fn operand(&mut self, operand: &[SyntaxElement], range: TextRange) -> Option<bool> {
+        let kind = match &operand[0] {
+            NodeOrToken::Token(it) if it.kind() == T![in] || it.kind() == T![const] => it.text(),
+            NodeOrToken::Token(it) if it.kind() == IDENT => it.text(),
+            _ => {
+                self.push_error(range, "expected operand, options, or additional template string");
+                return None;
+            }
+        };
+        match kind.as_str() {
+            "sym" | "const" => {
+                if operand.len() == 1 {
+                    self.push_error(range, &format!("expected expression after `{}`", kind));
+                    return None;
+                }
+                Some(false)
+            }
+            dir if DIRECTIONS.contains(&dir) => {
+                if self.is_global {
+                    self.push_error(
+                        range,
+                        &format!("the `{}` operand cannot be used with `global_asm!`", dir),
+                    );
+                    return None;
+                }
+                let explicit_register = match operand.get(1).and_then(|it| reg_spec(it)) {
+                    Some(it) => it.kind() == STRING,
+                    None => {
+                        self.push_error(range, "expected register class or explicit register");
+                        return None;
+                    }
+                };
+                let exprs = &operand[2..];
+                let fat_arrow = (0..exprs.len()).find(|&i| is_fat_arrow(&exprs[i..]));
+                let (input, output) = match fat_arrow {
+                    Some(i) => (&exprs[..i], Some(&exprs[i + 2..])),
+                    None => (exprs, None),
+                };
+                if input.is_empty() || output.map_or(false, |it| it.is_empty()) {
+                    self.push_error(range, "expected expression");
+                    return None;
+                }
+                let is_inout = dir == "inout" || dir == "inlateout";
+                if output.is_some() && !is_inout {
+                    self.push_error(range, &format!("`=>` cannot be used with `{}` operands", dir));
+                    return None;
+                }
+                if (dir == "in" || is_inout) && matches!(input, [it] if it.kind() == T![_]) {
+                    self.push_error(range, "_ cannot be used for input operands");
+                    return None;
+                }
+                Some(explicit_register)
+            }
+            _ => {
+                self.push_error(range, "expected operand, options, or additional template string");
+                None
+            }
+        }
+    }
+
+    fn options(&mut self, options: &SyntaxNode) {
+        let mut seen = Vec::new();
+        for arg in split_args(options) {
+            let range =
+                TextRange::new(arg[0].text_range().start(), arg[arg.len() - 1].text_range().end());
+            let option = match &arg[..] {
+                [NodeOrToken::Token(it)] if it.kind() == IDENT => it.text().to_string(),
+                _ => {
+                    self.push_error(range, "expected asm option");
+                    continue;
+                }
+            };
+            let allowed = if self.is_global { GLOBAL_OPTIONS } else { OPTIONS };
+            if !allowed.contains(&option.as_str()) {
+                let message = if OPTIONS.contains(&option.as_str()) {
+                    format!("the `{}` option cannot be used with `global_asm!`", option)
+                } else {
+                    format!("unknown asm option `{}`", option)
+                };
+                self.push_error(range, &message);
+                continue;
+            }
+            seen.push(option);
+        }
+
+        let has = |option: &str| seen.iter().any(|it| it == option);
+        let range = options.text_range();
+        if has("nomem") && has("readonly") {
+            self.push_error(range, "the `nomem` and `readonly` options are mutually exclusive");
+        }
+        if has("pure") && has("noreturn") {
+            self.push_error(range, "the `pure` and `noreturn` options are mutually exclusive");
+        }
+        if has("pure") && !has("nomem") && !has("readonly") {
+            self.push_error(
+                range,
+                "the `pure` option must be combined with either `nomem` or `readonly`",
+            );
+        }
+    }
+
+    fn finish(mut self) {
+        if self.opaque_template || self.malformed {
+            return;
+        }
+        for template in std::mem::take(&mut self.templates) {
+            self.template(&template);
+        }
+        if self.malformed {
+            return;
+        }
+        let unused = self
+            .operands
+            .iter()
+            .filter(|it| !it.used && !it.explicit_register)
+            .map(|it| it.range)
+            .collect::<Vec<_>>();
+        for range in unused {
+            self.push_error(range, "argument never used");
+        }
+    }
+
+    fn template(&mut self, token: &SyntaxToken) {
+        let text = token.text().as_str();
+        let is_raw = token.kind() == RAW_STRING;
+        let (start, end) = match (text.find('"'), text.rfind('"')) {
+            (Some(start), Some(end)) if start < end => (start + 1, end),
+            _ => return,
+        };
+        let content = &text[start..end];
+        let offset = token.text_range().start() + TextSize::from(start as u32);
+        let range = |start: usize, end: usize| {
+            TextRange::new(
+                offset + TextSize::from(start as u32),
+                offset + TextSize::from(end as u32),
+            )
+        };
+
+        let mut chars = content.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' if !is_raw => {
+                    // Skip the escaped char, and the braces of `\u{...}`.
+                    if let Some((_, 'u')) = chars.next() {
+                        if chars.peek().map(|&(_, c)| c) == Some('{') {
+                            chars.by_ref().find(|&(_, c)| c == '}');
+                        }
+                    }
+                }
+                '{' if chars.peek().map(|&(_, c)| c) == Some('{') => {
+                    chars.next();
+                }
+                '{' => match content[i..].find('}') {
+                    Some(len) => {
+                        let end = i + len + 1;
+                        self.placeholder(&content[i + 1..end - 1], range(i, end));
+                        while chars.peek().map_or(false, |&(j, _)| j < end) {
+                            chars.next();
+                        }
+                    }
+                    None => {
+                        self.push_error(range(i, content.len()), "unmatched `{` in asm template");
+                        return;
+                    }
+                },
+                '}' if chars.peek().map(|&(_, c)| c) == Some('}') => {
+                    chars.next();
+                }
+                '}' => self.push_error(range(i, i + 1), "unmatched `}` in asm template"),
+                _ => (),
+            }
+        }
+    }
+
+    fn placeholder(&mut self, placeholder: &str, range: TextRange) {
+        let (arg, modifier) = match placeholder.find(':') {
+            Some(idx) => (&placeholder[..idx], &placeholder[idx + 1..]),
+            None => (placeholder, ""),
+        };
+        if modifier.chars().count() > 1 {
+            self.push_error(range, "asm template modifier must be a single character");
+        }
+
+        let index = if arg.is_empty() {
+            self.next_implicit += 1;
+            self.next_implicit - 1
+        } else if arg.bytes().all(|it| it.is_ascii_digit()) {
+            match arg.parse() {
+                Ok(it) => it,
+                Err(_) => {
+                    self.push_error(range, &format!("invalid reference to argument `{}`", arg));
+                    return;
+                }
+            }
+        } else if is_ident(arg) {
+            match self.operands.iter().position(|it| it.name.as_deref() == Some(arg)) {
+                Some(it) => it,
+                None => {
+                    self.push_error(range, &format!("there is no argument named `{}`", arg));
+                    return;
+                }
+            }
+        } else {
+            self.push_error(range, &format!("invalid asm template placeholder `{{{}}}`", arg));
+            return;
+        };
+
+        match self.operands.get_mut(index) {
+            Some(operand) if operand.explicit_register => self.push_error(
+                range,
+                "explicit register arguments cannot be used in the asm template",
+            ),
+            Some(operand) => operand.used = true,
+            None => {
+                self.push_error(range, &format!("invalid reference to argument at index {}", index))
+            }
+        }
+    }
+
+    fn push_error(&mut self, range: TextRange, message: &str) {
+        self.malformed = true;
+        self.acc.push(error(range, message));
+    }
+}
+
+fn error(range: TextRange, message: &str) -> Diagnostic {
+    Diagnostic {
+        name: "invalid-asm",
+        range,
+        message: message.to_string(),
+        severity: Severity::Error,
+        fix: None,
+    }
+}
+
+/// Splits the contents of a token tree at the top-level commas, skipping
+/// trivia and empty arguments.
+fn split_args(token_tree: &SyntaxNode) -> Vec<Vec<SyntaxElement>> {
+    let mut args = Vec::new();
+    let mut current = Vec::new();
+    let children = token_tree.children_with_tokens().collect::<Vec<_>>();
+    let inner = children.get(1..children.len().saturating_sub(1)).unwrap_or_default();
+    for element in inner.iter().filter(|it| !it.kind().is_trivia()) {
+        if element.kind() == T![,] {
+            args.push(std::mem::take(&mut current));
+        } else {
+            current.push(element.clone());
+        }
+    }
+    args.push(current);
+    args.retain(|it| !it.is_empty());
+    args
+}
+
+/// Returns the register class or the explicit register of `(reg)`.
+fn reg_spec(element: &SyntaxElement) -> Option<SyntaxToken> {
+    let node = element.as_node().filter(|it| it.kind() == TOKEN_TREE)?;
+    let mut tokens = node.children_with_tokens().filter(|it| !it.kind().is_trivia());
+    match (tokens.next()?.kind(), tokens.next()?, tokens.next()?.kind(), tokens.next()) {
+        (T!['('], NodeOrToken::Token(reg), T![')'], None)
+            if reg.kind() == IDENT || reg.kind() == STRING =>
+        {
+            Some(reg)
+        }
+        _ => None,
+    }
+}
+
+fn is_fat_arrow(elements: &[SyntaxElement]) -> bool {
+    match elements {
+        [eq, gt, ..] => {
+            eq.kind() == T![=]
+                && gt.kind() == T![>]
+                && eq.text_range().end() == gt.text_range().start()
+        }
+        _ => false,
+    }
+}
+
+fn is_macro_call(arg: &[SyntaxElement]) -> bool {
+    match arg {
+        [.., bang, NodeOrToken::Node(tt)] => bang.kind() == T![!] && tt.kind() == TOKEN_TREE,
+        _ => false,
+    }
+}
+
+fn is_ident(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().map_or(false, |it| it.is_alphabetic() || it == '_')
+        && chars.all(|it| it.is_alphanumeric() || it == '_')
+}
+
+fn token_text(element: &SyntaxElement) -> Option<&str> {
+    element.as_token().map(|it| it.text().as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use expect::{expect, Expect};
+    use stdx::format_to;
+
+    use crate::mock_analysis::single_file;
+
+    fn check(ra_fixture: &str, expect: Expect) {
+        let (analysis, file_id) = single_file(ra_fixture);
+        let mut actual = String::new();
+        for diagnostic in analysis.diagnostics(file_id).unwrap() {
+            format_to!(actual, "{:?} {}\n", diagnostic.range, diagnostic.message);
+        }
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn accepts_valid_asm() {
+        check(
+            r##"
+fn main() {
+    let (a, b, c, d) = (1u64, 2u64, 3u64, 4u64);
+    unsafe {
+        asm!(
+            "mov {0}, {x:e} // {{not a placeholder}}",
+            "add {}, {y}",
+            "\u{41} {1}",
+            in(reg) a,
+            out(reg) _,
+            x = inout(reg) b => c,
+            y = const 1,
+            in("eax") d,
+            options(nostack, pure, nomem),
+        );
+        asm!(concat!("nop", "{}"), sym main);
+    }
+}
+global_asm!(r#"call {}"#, sym main, options(att_syntax));
+"##,
+            expect![[""]],
+        );
+    }
+
+    #[test]
+    fn reports_malformed_templates() {
+        check(
+            r#"
+fn main() {
+    asm!("mov {0}, {1}", in(reg) a);
+    asm!("mov {x}, {", x = in(reg) a);
+    asm!("}} } {y}", x = in(reg) a);
+    asm!("{} {0:ab}", in("eax") a);
+    asm!(a, in(reg) a);
+}
+"#,
+            expect![[r#"
+                31..34 invalid reference to argument at index 1
+                68..69 unmatched `{` in asm template
+                101..102 unmatched `}` in asm template
+                103..106 there is no argument named `y`
+                135..137 explicit register arguments cannot be used in the asm template
+                138..144 asm template modifier must be a single character
+                138..144 explicit register arguments cannot be used in the asm template
+                170..171 asm template must be a string literal
+            "#]],
+        );
+    }
+
+    #[test]
+    fn reports_malformed_operands() {
+        check(
+            r#"
+fn main() {
+    asm!("{} {x}", x = in(reg) a, in(reg) b);
+    asm!("{}", out a);
+    asm!("{}", foo(reg) a);
+    asm!("{}", in(reg) _);
+    asm!("{}", out(reg) a => b);
+    asm!("{} {}", sym f, options(nostack, whatever, readonly, nomem), const 1);
+    asm!("", options(pure));
+    asm!("{}", x = in("eax") a);
+}
+global_asm!("{}", in(reg) a, options(nostack));
+"#,
+            expect![[r#"
+                46..55 positional arguments cannot follow named arguments or explicit register arguments
+                73..78 expected register class or explicit register
+                96..106 expected operand, options, or additional template string
+                124..133 _ cannot be used for input operands
+                151..166 `=>` cannot be used with `out` operands
+                211..219 unknown asm option `whatever`
+                201..237 the `nomem` and `readonly` options are mutually exclusive
+                239..246 arguments are not allowed after options
+                269..275 the `pure` option must be combined with either `nomem` or `readonly`
+                293..308 explicit register arguments cannot have names
+                331..340 the `in` operand cannot be used with `global_asm!`
+                350..357 the `nostack` option cannot be used with `global_asm!`
+            "#]],
+        );
+    }
+
+    #[test]
+    fn reports_unused_arguments() {
+        check(
+            r#"
+fn main() {
+    asm!("mov {}, 1", in(reg) a, x = out(reg) b, out("eax") c);
+}
+"#,
+            expect![[r#"
+                45..59 argument never used
+            "#]],
+        );
+    }
+
+    #[test]
+    fn ignores_user_defined_asm() {
+        check(
+            r#"
+macro_rules! asm {
+    ($($tt:tt)*) => {};
+}
+fn main() {
+    asm!(mov eax, "{");
+}
+"#,
+            expect![[""]],
+        );
+    }
+}
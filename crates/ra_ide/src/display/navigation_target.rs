@@ -271,7 +271,7 @@ impl ToNav for hir::Module {
 impl ToNav for hir::ImplDef {
     fn to_nav(&self, db: &RootDatabase) -> NavigationTarget {
         let src = self.source(db);
-        let derive_attr = self.is_builtin_derive(db);
+        let derive_attr = self.derive_attr(db);
         let frange = if let Some(item) = &derive_attr {
             original_range(db, item.syntax())
         } else {
@@ -87,10 +87,14 @@ fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
             node_range: node.syntax().text_range(),
             kind: node.syntax().kind(),
             detail,
-            deprecated: node.attrs().filter_map(|x| x.simple_name()).any(|x| x == "deprecated"),
+            deprecated: is_deprecated(&node),
         })
     }
 
+    fn is_deprecated(node: &impl AttrsOwner) -> bool {
+        node.attrs().filter_map(|x| x.simple_name()).any(|x| x == "deprecated")
+    }
+
     fn collapse_ws(node: &SyntaxNode, output: &mut String) {
         let mut can_insert_ws = false;
         node.text().for_each_chunk(|chunk| {
@@ -127,8 +131,19 @@ fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
                 decl_with_detail(it, Some(detail))
             },
             ast::StructDef(it) => decl(it),
+            ast::UnionDef(it) => decl(it),
             ast::EnumDef(it) => decl(it),
-            ast::EnumVariant(it) => decl(it),
+            ast::EnumVariant(it) => {
+                let detail = match it.kind() {
+                    ast::StructKind::Tuple(fields) => {
+                        let mut detail = String::new();
+                        collapse_ws(fields.syntax(), &mut detail);
+                        Some(detail)
+                    }
+                    _ => None,
+                };
+                decl_with_detail(it, detail)
+            },
             ast::TraitDef(it) => decl(it),
             ast::Module(it) => decl(it),
             ast::TypeAliasDef(it) => {
@@ -155,7 +170,7 @@ fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
                     node_range: it.syntax().text_range(),
                     kind: it.syntax().kind(),
                     detail: None,
-                    deprecated: false,
+                    deprecated: is_deprecated(&it),
                 };
                 Some(node)
             },
@@ -202,6 +217,7 @@ mod m {
 }
 
 enum E { X, Y(i32) }
+union U { a: u32, b: f32 }
 type T = ();
 static S: i32 = 92;
 const C: i32 = 92;
@@ -210,6 +226,11 @@ impl E {}
 
 impl fmt::Debug for E {}
 
+#[deprecated]
+impl E {
+    fn old() {}
+}
+
 macro_rules! mc {
     () => {}
 }
@@ -330,14 +351,51 @@ fn very_obsolete() {}
                         navigation_range: 172..173,
                         node_range: 172..178,
                         kind: ENUM_VARIANT,
+                        detail: Some(
+                            "(i32)",
+                        ),
+                        deprecated: false,
+                    },
+                    StructureNode {
+                        parent: None,
+                        label: "U",
+                        navigation_range: 187..188,
+                        node_range: 181..207,
+                        kind: UNION_DEF,
                         detail: None,
                         deprecated: false,
                     },
+                    StructureNode {
+                        parent: Some(
+                            9,
+                        ),
+                        label: "a",
+                        navigation_range: 191..192,
+                        node_range: 191..197,
+                        kind: RECORD_FIELD_DEF,
+                        detail: Some(
+                            "u32",
+                        ),
+                        deprecated: false,
+                    },
+                    StructureNode {
+                        parent: Some(
+                            9,
+                        ),
+                        label: "b",
+                        navigation_range: 199..200,
+                        node_range: 199..205,
+                        kind: RECORD_FIELD_DEF,
+                        detail: Some(
+                            "f32",
+                        ),
+                        deprecated: false,
+                    },
                     StructureNode {
                         parent: None,
                         label: "T",
-                        navigation_range: 186..187,
-                        node_range: 181..193,
+                        navigation_range: 213..214,
+                        node_range: 208..220,
                         kind: TYPE_ALIAS_DEF,
                         detail: Some(
                             "()",
@@ -347,8 +405,8 @@ fn very_obsolete() {}
                     StructureNode {
                         parent: None,
                         label: "S",
-                        navigation_range: 201..202,
-                        node_range: 194..213,
+                        navigation_range: 228..229,
+                        node_range: 221..240,
                         kind: STATIC_DEF,
                         detail: Some(
                             "i32",
@@ -358,8 +416,8 @@ fn very_obsolete() {}
                     StructureNode {
                         parent: None,
                         label: "C",
-                        navigation_range: 220..221,
-                        node_range: 214..232,
+                        navigation_range: 247..248,
+                        node_range: 241..259,
                         kind: CONST_DEF,
                         detail: Some(
                             "i32",
@@ -369,8 +427,8 @@ fn very_obsolete() {}
                     StructureNode {
                         parent: None,
                         label: "impl E",
-                        navigation_range: 239..240,
-                        node_range: 234..243,
+                        navigation_range: 266..267,
+                        node_range: 261..270,
                         kind: IMPL_DEF,
                         detail: None,
                         deprecated: false,
@@ -378,17 +436,39 @@ fn very_obsolete() {}
                     StructureNode {
                         parent: None,
                         label: "impl fmt::Debug for E",
-                        navigation_range: 265..266,
-                        node_range: 245..269,
+                        navigation_range: 292..293,
+                        node_range: 272..296,
                         kind: IMPL_DEF,
                         detail: None,
                         deprecated: false,
                     },
+                    StructureNode {
+                        parent: None,
+                        label: "impl E",
+                        navigation_range: 317..318,
+                        node_range: 298..338,
+                        kind: IMPL_DEF,
+                        detail: None,
+                        deprecated: true,
+                    },
+                    StructureNode {
+                        parent: Some(
+                            17,
+                        ),
+                        label: "old",
+                        navigation_range: 328..331,
+                        node_range: 325..336,
+                        kind: FN_DEF,
+                        detail: Some(
+                            "fn()",
+                        ),
+                        deprecated: false,
+                    },
                     StructureNode {
                         parent: None,
                         label: "mc",
-                        navigation_range: 284..286,
-                        node_range: 271..303,
+                        navigation_range: 353..355,
+                        node_range: 340..372,
                         kind: MACRO_CALL,
                         detail: None,
                         deprecated: false,
@@ -396,8 +476,8 @@ fn very_obsolete() {}
                     StructureNode {
                         parent: None,
                         label: "mcexp",
-                        navigation_range: 334..339,
-                        node_range: 305..356,
+                        navigation_range: 403..408,
+                        node_range: 374..425,
                         kind: MACRO_CALL,
                         detail: None,
                         deprecated: false,
@@ -405,8 +485,8 @@ fn very_obsolete() {}
                     StructureNode {
                         parent: None,
                         label: "mcexp",
-                        navigation_range: 387..392,
-                        node_range: 358..409,
+                        navigation_range: 456..461,
+                        node_range: 427..478,
                         kind: MACRO_CALL,
                         detail: None,
                         deprecated: false,
@@ -414,8 +494,8 @@ fn very_obsolete() {}
                     StructureNode {
                         parent: None,
                         label: "obsolete",
-                        navigation_range: 428..436,
-                        node_range: 411..441,
+                        navigation_range: 497..505,
+                        node_range: 480..510,
                         kind: FN_DEF,
                         detail: Some(
                             "fn()",
@@ -425,8 +505,8 @@ fn very_obsolete() {}
                     StructureNode {
                         parent: None,
                         label: "very_obsolete",
-                        navigation_range: 481..494,
-                        node_range: 443..499,
+                        navigation_range: 550..563,
+                        node_range: 512..568,
                         kind: FN_DEF,
                         detail: Some(
                             "fn()",
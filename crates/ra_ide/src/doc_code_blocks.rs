@@ -0,0 +1,62 @@
+//! Recognizes the code blocks of documentation comments, for doctest
+//! runnables and doctest highlighting alike.
+
+const RUSTDOC_FENCES: &[&str] = &["```", "~~~"];
+const RUSTDOC_FENCE_TOKENS: &[&str] = &[
+    "",
+    "rust",
+    "should_panic",
+    "ignore",
+    "no_run",
+    "compile_fail",
+    "allow_fail",
+    "test_harness",
+    "edition2015",
+    "edition2018",
+];
+
+/// Returns the code block attributes (the text after the fence) if `line`
+/// opens or closes a code block.
+pub(crate) fn fence_attributes(line: &str) -> Option<&str> {
+    RUSTDOC_FENCES.iter().find_map(|fence| {
+        let idx = line.find(fence)?;
+        Some(&line[idx + fence.len()..])
+    })
+}
+
+/// Whether rustdoc treats the code block with these attributes as Rust.
+pub(crate) fn is_rust_code_block(attributes: &str) -> bool {
+    attributes.split(',').all(|sub| {
+        let sub = sub.trim();
+        // `ignore-<target>` is an `ignore` restricted to a particular target.
+        RUSTDOC_FENCE_TOKENS.contains(&sub) || sub.starts_with("ignore-")
+    })
+}
+
+/// Whether `cargo test --doc` tests the code block with these attributes.
+pub(crate) fn is_doc_test(attributes: &str) -> bool {
+    is_rust_code_block(attributes)
+        && !attributes
+            .split(',')
+            .map(str::trim)
+            .any(|it| it == "ignore" || it.starts_with("ignore-"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_code_blocks() {
+        assert_eq!(fence_attributes("/// ```rust,no_run"), Some("rust,no_run"));
+        assert_eq!(fence_attributes("/// ~~~"), Some(""));
+        assert_eq!(fence_attributes("/// let x = 92;"), None);
+
+        assert!(is_doc_test(""));
+        assert!(is_doc_test("rust, should_panic"));
+        assert!(is_doc_test("no_run"));
+        assert!(is_rust_code_block("ignore") && !is_doc_test("ignore"));
+        assert!(is_rust_code_block("ignore-windows") && !is_doc_test("rust,ignore-windows"));
+        assert!(!is_rust_code_block("text") && !is_doc_test("text"));
+    }
+}
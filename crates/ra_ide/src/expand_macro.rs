@@ -16,7 +16,8 @@ pub struct ExpandedMacro {
 
 // Feature: Expand Macro Recursively
 //
-// Shows the full macro expansion of the macro at current cursor.
+// Shows the full macro expansion of the macro at current cursor. Macro calls in
+// the expansion are expanded as well, up to a fixed depth.
 //
 // |===
 // | Editor  | Action Name
@@ -29,7 +30,7 @@ pub(crate) fn expand_macro(db: &RootDatabase, position: FilePosition) -> Option<
     let name_ref = find_node_at_offset::<ast::NameRef>(file.syntax(), position.offset)?;
     let mac = name_ref.syntax().ancestors().find_map(ast::MacroCall::cast)?;
 
-    let expanded = expand_macro_recur(&sema, &mac)?;
+    let expanded = expand_macro_recur(&sema, &mac, 0)?;
 
     // FIXME:
     // macro expansion may lose all white space information
@@ -38,17 +39,25 @@ pub(crate) fn expand_macro(db: &RootDatabase, position: FilePosition) -> Option<
     Some(ExpandedMacro { name: name_ref.text().to_string(), expansion })
 }
 
+/// Limits the nesting of expansions, so that macros expanding to calls of
+/// themselves do not hang the editor.
+const MAX_EXPANSION_DEPTH: usize = 32;
+
 fn expand_macro_recur(
     sema: &Semantics<RootDatabase>,
     macro_call: &ast::MacroCall,
+    depth: usize,
 ) -> Option<SyntaxNode> {
+    if depth >= MAX_EXPANSION_DEPTH {
+        return None;
+    }
     let mut expanded = sema.expand(macro_call)?;
 
     let children = expanded.descendants().filter_map(ast::MacroCall::cast);
     let mut rewriter = SyntaxRewriter::default();
 
     for child in children.into_iter() {
-        if let Some(new_node) = expand_macro_recur(sema, &child) {
+        if let Some(new_node) = expand_macro_recur(sema, &child, depth + 1) {
             // Replace the whole node if it is root
             // `replace_descendants` will not replace the parent node
             // but `SyntaxNode::descendants include itself
@@ -93,9 +102,10 @@ fn insert_whitespaces(syn: SyntaxNode) -> String {
             k if is_text(k) && is_next(|it| !it.is_punct(), true) => token.text().to_string() + " ",
             L_CURLY if is_next(|it| it != R_CURLY, true) => {
                 indent += 1;
-                let leading_space = if is_last(is_text, false) { " " } else { "" };
+                let leading_space = if is_last(is_text_or_close, false) { " " } else { "" };
                 format!("{}{{\n{}", leading_space, "  ".repeat(indent))
             }
+            L_CURLY if is_last(is_text_or_close, false) => " {".to_string(),
             R_CURLY if is_last(|it| it != L_CURLY, true) => {
                 indent = indent.saturating_sub(1);
                 format!("\n{}}}", "  ".repeat(indent))
@@ -105,6 +115,14 @@ fn insert_whitespaces(syn: SyntaxNode) -> String {
             T![->] => " -> ".to_string(),
             T![=] => " = ".to_string(),
             T![=>] => " => ".to_string(),
+            T![,] if is_next(|it| matches!(it, R_PAREN | R_BRACK | R_CURLY), true) => {
+                ",".to_string()
+            }
+            T![,] => ", ".to_string(),
+            T![:] => ": ".to_string(),
+            k if k.is_punct() && token.parent().kind() == BIN_EXPR => {
+                format!(" {} ", token.text())
+            }
             _ => token.text().to_string(),
         };
 
@@ -116,13 +134,20 @@ fn insert_whitespaces(syn: SyntaxNode) -> String {
     fn is_text(k: SyntaxKind) -> bool {
         k.is_keyword() || k.is_literal() || k == IDENT
     }
+
+    fn is_text_or_close(k: SyntaxKind) -> bool {
+        is_text(k) || matches!(k, R_PAREN | R_BRACK | T![>])
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use expect::{expect, Expect};
 
-    use crate::mock_analysis::analysis_and_position;
+    use crate::{
+        mock_analysis::{analysis_and_position, MockAnalysis},
+        MacroLimits,
+    };
 
     fn check(ra_fixture: &str, expect: Expect) {
         let (analysis, pos) = analysis_and_position(ra_fixture);
@@ -148,7 +173,7 @@ f<|>oo!();
 "#,
             expect![[r#"
                 foo
-                fn b(){}
+                fn b() {}
             "#]],
         );
     }
@@ -168,11 +193,11 @@ macro_rules! foo {
 f<|>oo!();
         "#,
             expect![[r#"
-            foo
-            fn some_thing() -> u32 {
-              let a = 0;
-              a+10
-            }"#]],
+                foo
+                fn some_thing() -> u32 {
+                  let a = 0;
+                  a + 10
+                }"#]],
         );
     }
 
@@ -202,16 +227,16 @@ fn main() {
 }
 "#,
             expect![[r#"
-       match_ast
-       {
-         if let Some(it) = ast::TraitDef::cast(container.clone()){}
-         else if let Some(it) = ast::ImplDef::cast(container.clone()){}
-         else {
-           {
-             continue
-           }
-         }
-       }"#]],
+                match_ast
+                {
+                  if let Some(it) = ast::TraitDef::cast(container.clone()) {}
+                  else if let Some(it) = ast::ImplDef::cast(container.clone()) {}
+                  else {
+                    {
+                      continue
+                    }
+                  }
+                }"#]],
         );
     }
 
@@ -280,4 +305,42 @@ fn main() {
                 0 "#]],
         );
     }
+
+    #[test]
+    fn macro_expand_stops_at_depth_limit() {
+        let (mock, pos) = MockAnalysis::with_files_and_position(
+            r#"
+macro_rules! foo {
+    () => { foo!(); }
+}
+f<|>oo!();
+"#,
+        );
+        let mut host = mock.analysis_host();
+        host.set_macro_limits(MacroLimits { expansion_depth: 8, token_count: 65536 });
+        let expansion = host.analysis().expand_macro(pos).unwrap().unwrap();
+        let actual = format!("{}\n{}", expansion.name, expansion.expansion);
+        expect![[r#"
+            foo
+            foo!();
+        "#]]
+        .assert_eq(&actual);
+    }
+
+    #[test]
+    fn macro_expand_spaces_punctuation() {
+        check(
+            r#"
+macro_rules! foo {
+    () => { fn foo(a: u32, b: u32) -> bool { a+b > 92 && bar(a,b,) } }
+}
+f<|>oo!();
+"#,
+            expect![[r#"
+                foo
+                fn foo(a: u32, b: u32) -> bool {
+                  a + b > 92 && bar(a, b,)
+                }"#]],
+        );
+    }
 }
@@ -120,6 +120,18 @@ fn extend_tokens_from_range(
     macro_call: ast::MacroCall,
     original_range: TextRange,
 ) -> Option<TextRange> {
+    // Only tokens between the delimiters of the macro call can be mapped back
+    let token_tree = macro_call.token_tree()?;
+    let inner_range = {
+        let tt = token_tree.syntax();
+        let l_delim = tt.first_token()?;
+        let r_delim = tt.last_token()?;
+        TextRange::new(l_delim.text_range().end(), r_delim.text_range().start())
+    };
+    if !inner_range.contains_range(original_range) {
+        return None;
+    }
+
     let src = find_covering_element(&macro_call.syntax(), original_range);
     let (first_token, last_token) = match src {
         NodeOrToken::Node(it) => (it.first_token()?, it.last_token()?),
@@ -136,45 +148,66 @@ fn extend_tokens_from_range(
         last_token = skip_trivia_token(last_token.prev_token()?, Direction::Prev)?;
     }
 
-    // compute original mapped token range
-    let extended = {
-        let fst_expanded = sema.descend_into_macros(first_token.clone());
-        let lst_expanded = sema.descend_into_macros(last_token.clone());
-        let mut lca = algo::least_common_ancestor(&fst_expanded.parent(), &lst_expanded.parent())?;
-        lca = shallowest_node(&lca);
-        if lca.first_token() == Some(fst_expanded) && lca.last_token() == Some(lst_expanded) {
-            lca = lca.parent()?;
+    // Find the node covering the selection inside the expansion
+    let fst_expanded = sema.descend_into_macros(first_token.clone());
+    let lst_expanded = sema.descend_into_macros(last_token.clone());
+    let lca = algo::least_common_ancestor(&fst_expanded.parent(), &lst_expanded.parent())?;
+
+    // Grow the selection in the expansion and map every step back to the
+    // source, until the mapped range actually gets larger
+    lca.ancestors().find_map(|extended| {
+        let range = mapped_range(sema, &extended, inner_range, &first_token, &last_token)?;
+        if range.contains_range(original_range) && original_range != range {
+            Some(range)
+        } else {
+            None
         }
-        lca
-    };
+    })
+}
 
-    // Compute parent node range
+/// Computes the range of source tokens inside `inner_range` whose expansion
+/// lies under the `extended` node.
+fn mapped_range(
+    sema: &Semantics<RootDatabase>,
+    extended: &SyntaxNode,
+    inner_range: TextRange,
+    first_token: &SyntaxToken,
+    last_token: &SyntaxToken,
+) -> Option<TextRange> {
+    // Tokens which do not appear in the expansion (like separators consumed
+    // by a repetition) are skipped over, but never end the selection.
+    let is_mapped = |token: &SyntaxToken| sema.descend_into_macros(token.clone()) != *token;
     let validate = |token: &SyntaxToken| {
+        if !inner_range.contains_range(token.text_range()) {
+            return false;
+        }
         let expanded = sema.descend_into_macros(token.clone());
-        algo::least_common_ancestor(&extended, &expanded.parent()).as_ref() == Some(&extended)
+        if expanded == *token {
+            return true;
+        }
+        algo::least_common_ancestor(extended, &expanded.parent()).as_ref() == Some(extended)
     };
 
     // Find the first and last text range under expanded parent
-    let first = successors(Some(first_token), |token| {
+    let first = successors(Some(first_token.clone()), |token| {
         let token = token.prev_token()?;
         skip_trivia_token(token, Direction::Prev)
     })
     .take_while(validate)
-    .last()?;
+    .filter(is_mapped)
+    .last()
+    .unwrap_or_else(|| first_token.clone());
 
-    let last = successors(Some(last_token), |token| {
+    let last = successors(Some(last_token.clone()), |token| {
         let token = token.next_token()?;
         skip_trivia_token(token, Direction::Next)
     })
     .take_while(validate)
-    .last()?;
+    .filter(is_mapped)
+    .last()
+    .unwrap_or_else(|| last_token.clone());
 
-    let range = first.text_range().cover(last.text_range());
-    if range.contains_range(original_range) && original_range != range {
-        Some(range)
-    } else {
-        None
-    }
+    Some(first.text_range().cover(last.text_range()))
 }
 
 /// Find the shallowest node with same range, which allows us to traverse siblings.
@@ -651,4 +684,35 @@ fn main() { let (
             ],
         );
     }
+
+    #[test]
+    fn extend_selection_inside_expr_macros() {
+        do_check(
+            r#"macro_rules! foo { ($e:expr) => {$e} }
+fn main() { foo!(1 + 2<|> * 3); }"#,
+            &["2", "2 * 3", "1 + 2 * 3", "(1 + 2 * 3)", "foo!(1 + 2 * 3)"],
+        );
+    }
+
+    #[test]
+    fn extend_selection_inside_assert() {
+        do_check(
+            r#"
+macro_rules! assert {
+    ($cond:expr) => { if !$cond { panic!() } };
+    ($cond:expr, $($arg:tt)+) => { if !$cond { panic!($($arg)+) } };
+}
+fn main() { assert!(xs.l<|>en() == 2, "oops"); }"#,
+            &["len", "xs.len()", "xs.len() == 2", "xs.len() == 2, \"oops\""],
+        );
+    }
+
+    #[test]
+    fn extend_selection_inside_vec() {
+        do_check(
+            r#"macro_rules! vec { ($($e:expr),*) => { [$($e),*] } }
+fn main() { vec![foo(1), ba<|>r(2, 3)]; }"#,
+            &["bar", "bar(2, 3)", "foo(1), bar(2, 3)", "[foo(1), bar(2, 3)]"],
+        );
+    }
 }
@@ -6,7 +6,7 @@ use ra_syntax::{
     ast::{self, AstNode, AstToken, VisibilityOwner},
     Direction, NodeOrToken, SourceFile,
     SyntaxKind::{self, *},
-    SyntaxNode, TextRange,
+    SyntaxNode, TextRange, TextSize,
 };
 
 #[derive(Debug, PartialEq, Eq)]
@@ -16,6 +16,9 @@ pub enum FoldKind {
     Mods,
     Block,
     ArgList,
+    Region,
+    WhereClause,
+    MatchArm,
 }
 
 #[derive(Debug)]
@@ -29,6 +32,8 @@ pub(crate) fn folding_ranges(file: &SourceFile) -> Vec<Fold> {
     let mut visited_comments = FxHashSet::default();
     let mut visited_imports = FxHashSet::default();
     let mut visited_mods = FxHashSet::default();
+    // regions can be nested, here is a LIFO buffer
+    let mut regions_starts: Vec<TextSize> = vec![];
 
     for element in file.syntax().descendants_with_tokens() {
         // Fold items that span multiple lines
@@ -47,7 +52,22 @@ pub(crate) fn folding_ranges(file: &SourceFile) -> Vec<Fold> {
             NodeOrToken::Token(token) => {
                 // Fold groups of comments
                 if let Some(comment) = ast::Comment::cast(token) {
-                    if !visited_comments.contains(&comment) {
+                    if let Some(marker) = region_marker(&comment) {
+                        match marker {
+                            RegionMarker::Start => {
+                                regions_starts.push(comment.syntax().text_range().start())
+                            }
+                            RegionMarker::End => {
+                                if let Some(start) = regions_starts.pop() {
+                                    let end = comment.syntax().text_range().end();
+                                    res.push(Fold {
+                                        range: TextRange::new(start, end),
+                                        kind: FoldKind::Region,
+                                    })
+                                }
+                            }
+                        }
+                    } else if !visited_comments.contains(&comment) {
                         if let Some(range) =
                             contiguous_range_for_comment(comment, &mut visited_comments)
                         {
@@ -77,6 +97,8 @@ pub(crate) fn folding_ranges(file: &SourceFile) -> Vec<Fold> {
         }
     }
 
+    // Regions are only known once they are closed, restore the source order.
+    res.sort_by_key(|fold| fold.range.start());
     res
 }
 
@@ -84,7 +106,9 @@ fn fold_kind(kind: SyntaxKind) -> Option<FoldKind> {
     match kind {
         COMMENT => Some(FoldKind::Comment),
         USE_ITEM => Some(FoldKind::Imports),
-        ARG_LIST => Some(FoldKind::ArgList),
+        ARG_LIST | PARAM_LIST => Some(FoldKind::ArgList),
+        WHERE_CLAUSE => Some(FoldKind::WhereClause),
+        MATCH_ARM => Some(FoldKind::MatchArm),
         RECORD_FIELD_DEF_LIST
         | RECORD_FIELD_PAT_LIST
         | RECORD_FIELD_LIST
@@ -99,6 +123,30 @@ fn fold_kind(kind: SyntaxKind) -> Option<FoldKind> {
     }
 }
 
+enum RegionMarker {
+    Start,
+    End,
+}
+
+/// Recognizes the `// region: name` and `// endregion: name` comments used
+/// to delimit custom folding regions.
+fn region_marker(comment: &ast::Comment) -> Option<RegionMarker> {
+    if comment.kind().doc.is_some() || !comment.kind().shape.is_line() {
+        return None;
+    }
+    let text = comment.text()[comment.prefix().len()..].trim_start();
+    if text.starts_with("region:") {
+        Some(RegionMarker::Start)
+    } else if text
+        .strip_prefix("endregion")
+        .map_or(false, |rest| rest.chars().next().map_or(true, |c| c.is_whitespace() || c == ':'))
+    {
+        Some(RegionMarker::End)
+    } else {
+        None
+    }
+}
+
 fn has_visibility(node: &SyntaxNode) -> bool {
     ast::Module::cast(node.clone()).and_then(|m| m.visibility()).is_some()
 }
@@ -174,7 +222,7 @@ fn contiguous_range_for_comment(
                     }
                 }
                 if let Some(c) = ast::Comment::cast(token) {
-                    if c.kind() == group_kind {
+                    if c.kind() == group_kind && region_marker(&c).is_none() {
                         visited.insert(c.clone());
                         last = c;
                         continue;
@@ -224,6 +272,9 @@ mod tests {
                 FoldKind::Mods => "mods",
                 FoldKind::Block => "block",
                 FoldKind::ArgList => "arglist",
+                FoldKind::Region => "region",
+                FoldKind::WhereClause => "whereclause",
+                FoldKind::MatchArm => "matcharm",
             };
             assert_eq!(kind, &attr.unwrap());
         }
@@ -376,6 +427,91 @@ fn main() <fold block>{
         )
     }
 
+    #[test]
+    fn fold_multiline_params() {
+        check(
+            r#"
+fn foo<fold arglist>(
+    x: i32,
+    y: String,
+)</fold> {}
+"#,
+        )
+    }
+
+    #[test]
+    fn fold_where_clause() {
+        check(
+            r#"
+fn foo<T, U>()
+<fold whereclause>where
+    T: Clone,
+    U: Copy,</fold>
+{}
+"#,
+        )
+    }
+
+    #[test]
+    fn fold_multiline_match_arms() {
+        check(
+            r#"
+fn main() <fold block>{
+    match 0 <fold block>{
+        0 => 0,
+        <fold matcharm>_ => foo<fold arglist>(
+            1,
+        )</fold></fold>,
+        <fold matcharm>_ if true => <fold block>{
+            2
+        }</fold></fold>,
+    }</fold>
+}</fold>
+"#,
+        );
+    }
+
+    #[test]
+    fn fold_region() {
+        check(
+            r#"
+// 1. some normal comment
+<fold region>// region: test
+// 2. some normal comment
+calling_function(x,y);
+// endregion: test</fold>
+"#,
+        )
+    }
+
+    #[test]
+    fn fold_nested_regions() {
+        check(
+            r#"
+<fold region>// region: outer
+<fold region>// region: inner
+fn foo() {}
+// endregion: inner</fold>
+// endregion: outer</fold>
+"#,
+        )
+    }
+
+    #[test]
+    fn fold_region_needs_whole_endregion() {
+        check(
+            r#"
+// region: test
+fn foo() {}
+// endregionfoo
+fn bar() {}
+<fold region>// region: other
+fn baz() {}
+// endregion</fold>
+"#,
+        )
+    }
+
     #[test]
     fn fold_record_literals() {
         check(
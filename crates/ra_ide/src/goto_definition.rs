@@ -106,12 +106,25 @@ mod tests {
     use ra_db::FileRange;
     use ra_syntax::{TextRange, TextSize};
 
-    use crate::mock_analysis::MockAnalysis;
+    use crate::mock_analysis::{with_identity_proc_macros, MockAnalysis};
 
     fn check(ra_fixture: &str) {
+        check_with(ra_fixture, false)
+    }
+
+    /// Like `check`, with the `proc` crate providing identity proc macros.
+    fn check_with_proc_macros(ra_fixture: &str) {
+        check_with(ra_fixture, true)
+    }
+
+    fn check_with(ra_fixture: &str, proc_macros: bool) {
         let (mock, position) = MockAnalysis::with_files_and_position(ra_fixture);
         let (mut expected, data) = mock.annotation();
-        let analysis = mock.analysis();
+        let mut host = mock.analysis_host();
+        if proc_macros {
+            with_identity_proc_macros(&mut host, "proc");
+        }
+        let analysis = host.analysis();
         match data.as_str() {
             "" => (),
             "file" => {
@@ -210,6 +223,69 @@ fn bar() {
         );
     }
 
+    #[test]
+    fn goto_def_for_local_passed_to_macro_with_same_binding() {
+        check(
+            r#"
+macro_rules! shadow { ($e:expr) => {{ let x = 1; $e }} }
+fn bar() {
+    let x = 0;
+      //^
+    shadow!(<|>x);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn goto_def_for_local_in_attr_macro_item() {
+        check_with_proc_macros(
+            r#"
+//- /main.rs
+#[proc::identity]
+fn foo() {
+    let x = 92;
+      //^
+    x<|>;
+}
+//- /proc/lib.rs
+"#,
+        );
+    }
+
+    #[test]
+    fn goto_def_in_fn_like_proc_macro_arg() {
+        check_with_proc_macros(
+            r#"
+//- /main.rs
+struct Foo;
+     //^^^
+fn foo() {
+    proc::identity_fn!(Foo<|>);
+}
+//- /proc/lib.rs
+"#,
+        );
+    }
+
+    #[test]
+    fn goto_def_in_macro_call_in_attr_macro_item() {
+        check_with_proc_macros(
+            r#"
+//- /main.rs
+macro_rules! id { ($($tt:tt)*) => { $($tt)* } }
+
+#[proc::identity]
+fn foo() {
+    let x = 92;
+      //^
+    id!(x<|>);
+}
+//- /proc/lib.rs
+"#,
+        );
+    }
+
     #[test]
     fn goto_def_for_macros_from_other_crates() {
         check(
@@ -1,4 +1,4 @@
-use hir::{Crate, ImplDef, Semantics};
+use hir::{AsAssocItem, AssocItem, AssocItemContainer, Crate, ImplDef, Semantics};
 use ra_ide_db::RootDatabase;
 use ra_syntax::{algo::find_node_at_offset, ast, AstNode};
 
@@ -6,7 +6,8 @@ use crate::{display::ToNav, FilePosition, NavigationTarget, RangeInfo};
 
 // Feature: Go to Implementation
 //
-// Navigates to the impl block of structs, enums or traits. Also implemented as a code lens.
+// Navigates to the impl block of structs, enums or traits, or to the
+// implementations of a trait method. Also implemented as a code lens.
 //
 // |===
 // | Editor  | Shortcut
@@ -23,6 +24,18 @@ pub(crate) fn goto_implementation(
 
     let krate = sema.to_module_def(position.file_id)?.krate();
 
+    if let Some(fn_def) = find_node_at_offset::<ast::FnDef>(&syntax, position.offset) {
+        // Inside of the body of a method, we are after the impls of the trait itself
+        let in_body = fn_def
+            .body()
+            .map_or(false, |body| body.syntax().text_range().contains(position.offset));
+        if !in_body {
+            if let Some(navs) = impls_for_trait_item(&sema, &fn_def, krate) {
+                return Some(RangeInfo::new(fn_def.syntax().text_range(), navs));
+            }
+        }
+    }
+
     if let Some(nominal_def) = find_node_at_offset::<ast::NominalDef>(&syntax, position.offset) {
         return Some(RangeInfo::new(
             nominal_def.syntax().text_range(),
@@ -72,6 +85,33 @@ fn impls_for_trait(
     Some(impls.into_iter().map(|imp| imp.to_nav(sema.db)).collect())
 }
 
+fn impls_for_trait_item(
+    sema: &Semantics<RootDatabase>,
+    node: &ast::FnDef,
+    krate: Crate,
+) -> Option<Vec<NavigationTarget>> {
+    let func = sema.to_def(node)?;
+    let tr = match func.as_assoc_item(sema.db)?.container(sema.db) {
+        AssocItemContainer::Trait(it) => it,
+        AssocItemContainer::ImplDef(_) => return None,
+    };
+    let name = func.name(sema.db);
+
+    let impls = ImplDef::for_trait(sema.db, krate, tr);
+
+    Some(
+        impls
+            .into_iter()
+            .filter_map(|imp| {
+                imp.items(sema.db).into_iter().find_map(|item| match item {
+                    AssocItem::Function(it) if it.name(sema.db) == name => Some(it.to_nav(sema.db)),
+                    _ => None,
+                })
+            })
+            .collect(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use ra_db::FileRange;
@@ -223,6 +263,62 @@ struct Foo<|>;
 mod marker {
     trait Copy {}
 }
+"#,
+        );
+    }
+
+    #[test]
+    fn goto_implementation_to_the_derive_of_the_impl() {
+        check(
+            r#"
+  #[derive(Copy)]
+//^^^^^^^^^^^^^^^
+  #[derive(Clone)]
+//^^^^^^^^^^^^^^^^
+struct Foo<|>;
+
+mod marker {
+    trait Copy {}
+}
+mod clone {
+    trait Clone {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn goto_implementation_trait_method() {
+        check(
+            r#"
+trait T {
+    fn foo<|>(&self);
+    fn bar(&self);
+}
+struct Foo;
+impl T for Foo {
+    fn foo(&self) {}
+     //^^^
+    fn bar(&self) {}
+}
+struct Bar;
+impl T for Bar {
+    fn bar(&self) {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn goto_implementation_from_trait_method_body() {
+        check(
+            r#"
+trait T {
+    fn foo(&self) { <|> }
+}
+struct Foo;
+impl T for Foo {}
+         //^^^
 "#,
         );
     }
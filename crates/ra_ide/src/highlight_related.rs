@@ -0,0 +1,442 @@
+//! Highlights the exit, await and loop control points related to the keyword under the cursor.
+
+use ra_db::SourceDatabase;
+use ra_ide_db::RootDatabase;
+use ra_syntax::{
+    ast::{self, LoopBodyOwner},
+    AstNode,
+    SyntaxKind::*,
+    SyntaxNode, SyntaxToken, TextRange, T,
+};
+
+use crate::FilePosition;
+
+// Feature: Highlight Related
+//
+// Highlights constructs related to the keyword under the cursor:
+//
+// - on `fn`, `return` or `?`, all exit points of the function (or closure),
+//   including the tail expression
+// - on `async` or `await`, all await points of the async function or block
+// - on `loop`, `while`, `for`, `break` or `continue`, the corresponding loop
+//   with all of its `break` and `continue` expressions
+pub(crate) fn highlight_related(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<Vec<TextRange>> {
+    let file = db.parse(position.file_id).tree();
+    let token = file.syntax().token_at_offset(position.offset).find(|it| {
+        matches!(
+            it.kind(),
+            T![fn]
+                | T![return]
+                | T![?]
+                | T![async]
+                | T![await]
+                | T![loop]
+                | T![while]
+                | T![for]
+                | T![break]
+                | T![continue]
+        )
+    })?;
+
+    let mut res = match token.kind() {
+        T![fn] | T![return] | T![?] => highlight_exit_points(&token)?,
+        T![async] | T![await] => highlight_yield_points(&token)?,
+        _ => highlight_break_points(&token)?,
+    };
+    res.sort_by_key(|range| range.start());
+    res.dedup();
+    Some(res)
+}
+
+fn highlight_exit_points(token: &SyntaxToken) -> Option<Vec<TextRange>> {
+    let owner = match token.kind() {
+        T![fn] => Some(token.parent()).filter(|it| it.kind() == FN_DEF)?,
+        _ => token.parent().ancestors().find(|it| is_exit_owner(it, token.kind() == T![?]))?,
+    };
+
+    let mut res = Vec::new();
+    let body = match_owner(&owner, &mut res)?;
+
+    walk_body(&body, &is_boundary, &mut |node| match node.kind() {
+        RETURN_EXPR => res.push(node.text_range()),
+        TRY_EXPR => {
+            // `?` inside of a `try` block exits from that block only
+            let target = node.ancestors().skip(1).find(|it| is_exit_owner(it, true));
+            if target.as_ref() == Some(&owner) {
+                if let Some(question) =
+                    ast::TryExpr::cast(node.clone()).and_then(|it| it.question_mark_token())
+                {
+                    res.push(question.text_range())
+                }
+            }
+        }
+        _ => (),
+    });
+    if let Some(tail) = ast::Expr::cast(body) {
+        push_tail_exprs(&tail, &mut res);
+    }
+    Some(res)
+}
+
+/// Pushes the range of the keyword introducing the owner of exit points, and
+/// returns its body.
+fn match_owner(owner: &SyntaxNode, res: &mut Vec<TextRange>) -> Option<SyntaxNode> {
+    if let Some(func) = ast::FnDef::cast(owner.clone()) {
+        res.extend(func.fn_token().map(|it| it.text_range()));
+        return Some(func.body()?.syntax().clone());
+    }
+    if let Some(closure) = ast::LambdaExpr::cast(owner.clone()) {
+        res.extend(closure.param_list().map(|it| it.syntax().text_range()));
+        return Some(closure.body()?.syntax().clone());
+    }
+    let effect = ast::EffectExpr::cast(owner.clone())?;
+    match effect.effect() {
+        ast::Effect::Async(token) | ast::Effect::Try(token) => res.push(token.text_range()),
+        ast::Effect::Unsafe(_) | ast::Effect::Label(_) => return None,
+    }
+    Some(effect.block_expr()?.syntax().clone())
+}
+
+fn is_exit_owner(node: &SyntaxNode, include_try: bool) -> bool {
+    match node.kind() {
+        FN_DEF | LAMBDA_EXPR => true,
+        EFFECT_EXPR => match ast::EffectExpr::cast(node.clone()).map(|it| it.effect()) {
+            Some(ast::Effect::Async(_)) => true,
+            Some(ast::Effect::Try(_)) => include_try,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn push_tail_exprs(expr: &ast::Expr, res: &mut Vec<TextRange>) {
+    match expr {
+        ast::Expr::BlockExpr(block) => {
+            if let Some(tail) = block.expr() {
+                push_tail_exprs(&tail, res)
+            }
+        }
+        ast::Expr::EffectExpr(effect) => match effect.effect() {
+            ast::Effect::Unsafe(_) | ast::Effect::Label(_) => {
+                if let Some(tail) = effect.block_expr().and_then(|it| it.expr()) {
+                    push_tail_exprs(&tail, res)
+                }
+            }
+            ast::Effect::Async(_) | ast::Effect::Try(_) => res.push(expr.syntax().text_range()),
+        },
+        ast::Expr::IfExpr(if_expr) => {
+            if let Some(tail) = if_expr.then_branch().and_then(|it| it.expr()) {
+                push_tail_exprs(&tail, res)
+            }
+            match if_expr.else_branch() {
+                Some(ast::ElseBranch::Block(block)) => {
+                    if let Some(tail) = block.expr() {
+                        push_tail_exprs(&tail, res)
+                    }
+                }
+                Some(ast::ElseBranch::IfExpr(if_expr)) => {
+                    push_tail_exprs(&ast::Expr::IfExpr(if_expr), res)
+                }
+                None => (),
+            }
+        }
+        ast::Expr::MatchExpr(match_expr) => {
+            let arms = match_expr.match_arm_list().into_iter().flat_map(|it| it.arms());
+            for tail in arms.filter_map(|arm| arm.expr()) {
+                push_tail_exprs(&tail, res)
+            }
+        }
+        // Already accounted for as an explicit exit point
+        ast::Expr::ReturnExpr(_) => (),
+        _ => res.push(expr.syntax().text_range()),
+    }
+}
+
+fn highlight_yield_points(token: &SyntaxToken) -> Option<Vec<TextRange>> {
+    let owner = match token.kind() {
+        T![async] => token.parent(),
+        _ => token.parent().ancestors().find(is_boundary)?,
+    };
+    let async_token = async_token(&owner)?;
+    let body = match_owner(&owner, &mut Vec::new())?;
+
+    let mut res = vec![async_token.text_range()];
+    walk_body(&body, &is_boundary, &mut |node| {
+        if let Some(await_token) =
+            ast::AwaitExpr::cast(node.clone()).and_then(|it| it.await_token())
+        {
+            res.push(await_token.text_range())
+        }
+    });
+    Some(res)
+}
+
+fn async_token(owner: &SyntaxNode) -> Option<SyntaxToken> {
+    if let Some(func) = ast::FnDef::cast(owner.clone()) {
+        return func.async_token();
+    }
+    if let Some(closure) = ast::LambdaExpr::cast(owner.clone()) {
+        return closure.async_token();
+    }
+    ast::EffectExpr::cast(owner.clone())?.async_token()
+}
+
+fn highlight_break_points(token: &SyntaxToken) -> Option<Vec<TextRange>> {
+    let target = match token.kind() {
+        T![break] | T![continue] => break_target(&token.parent())?,
+        _ => {
+            let parent = token.parent();
+            if !matches!(parent.kind(), LOOP_EXPR | WHILE_EXPR | FOR_EXPR) {
+                return None;
+            }
+            parent
+        }
+    };
+
+    let mut res = Vec::new();
+    if let Some(label) = loop_label(&target) {
+        res.push(label.syntax().text_range());
+    }
+    let body = loop_keyword_and_body(&target, &mut res)?;
+
+    walk_body(&body, &is_boundary, &mut |node| {
+        if !matches!(node.kind(), BREAK_EXPR | CONTINUE_EXPR) {
+            return;
+        }
+        if break_target(node).as_ref() != Some(&target) {
+            return;
+        }
+        let keyword = node.first_token().filter(|it| matches!(it.kind(), T![break] | T![continue]));
+        let lifetime = node
+            .children_with_tokens()
+            .filter_map(|it| it.into_token())
+            .find(|it| it.kind() == LIFETIME);
+        match (keyword, lifetime) {
+            (Some(keyword), Some(lifetime)) => {
+                res.push(keyword.text_range().cover(lifetime.text_range()))
+            }
+            (Some(keyword), None) => res.push(keyword.text_range()),
+            (None, _) => (),
+        }
+    });
+    Some(res)
+}
+
+/// Pushes the range of the loop keyword, and returns the loop body.
+fn loop_keyword_and_body(target: &SyntaxNode, res: &mut Vec<TextRange>) -> Option<SyntaxNode> {
+    let (keyword, body) = if let Some(it) = ast::LoopExpr::cast(target.clone()) {
+        (it.loop_token(), it.loop_body())
+    } else if let Some(it) = ast::WhileExpr::cast(target.clone()) {
+        (it.while_token(), it.loop_body())
+    } else if let Some(it) = ast::ForExpr::cast(target.clone()) {
+        (it.for_token(), it.loop_body())
+    } else {
+        (None, ast::EffectExpr::cast(target.clone())?.block_expr())
+    };
+    res.extend(keyword.map(|it| it.text_range()));
+    Some(body?.syntax().clone())
+}
+
+fn loop_label(node: &SyntaxNode) -> Option<ast::Label> {
+    if let Some(effect) = ast::EffectExpr::cast(node.clone()) {
+        return effect.label();
+    }
+    node.children().find_map(ast::Label::cast)
+}
+
+/// Finds the loop (or labeled block) a `break` or `continue` expression
+/// jumps out of.
+fn break_target(expr: &SyntaxNode) -> Option<SyntaxNode> {
+    let label = expr
+        .children_with_tokens()
+        .filter_map(|it| it.into_token())
+        .find(|it| it.kind() == LIFETIME)
+        .map(|it| it.text().clone());
+    expr.ancestors()
+        .skip(1)
+        .take_while(|it| !is_boundary(it))
+        .filter(|it| match it.kind() {
+            LOOP_EXPR | WHILE_EXPR | FOR_EXPR => true,
+            EFFECT_EXPR => label.is_some(),
+            _ => false,
+        })
+        .find(|it| match &label {
+            Some(label) => loop_label(it)
+                .and_then(|it| it.lifetime_token())
+                .map_or(false, |it| it.text() == label),
+            None => true,
+        })
+}
+
+/// Functions, closures and async blocks have their own control flow, so we
+/// never look inside of them.
+fn is_boundary(node: &SyntaxNode) -> bool {
+    is_exit_owner(node, false)
+}
+
+/// Calls `f` for every descendant of `body`, not descending into nodes for
+/// which `is_boundary` returns true.
+fn walk_body(
+    body: &SyntaxNode,
+    is_boundary: &dyn Fn(&SyntaxNode) -> bool,
+    f: &mut dyn FnMut(&SyntaxNode),
+) {
+    for child in body.children() {
+        if is_boundary(&child) {
+            continue;
+        }
+        f(&child);
+        walk_body(&child, is_boundary, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::extract_annotations;
+
+    use crate::mock_analysis::analysis_and_position;
+
+    fn check(ra_fixture: &str) {
+        let (analysis, position) = analysis_and_position(ra_fixture);
+        let text = analysis.file_text(position.file_id).unwrap();
+        let expected =
+            extract_annotations(&text).into_iter().map(|(range, _)| range).collect::<Vec<_>>();
+        let actual = analysis.highlight_related(position).unwrap().unwrap_or_default();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_exit_points_on_fn() {
+        check(
+            r#"
+impl S {
+    f<|>n foo(x: Option<i32>) -> Option<i32> {
+  //^^
+        if x? > 92 {
+          //^
+            return None;
+          //^^^^^^^^^^^
+        }
+        let f = |y: i32| { return y; };
+        if true { Some(1) } else { x }
+                //^^^^^^^          ^
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_exit_points_on_return_in_closure() {
+        check(
+            r#"
+fn foo() -> i32 {
+    let f = |y: i32| { if y > 0 { ret<|>urn y; } y?; y + 1 };
+          //^^^^^^^^              ^^^^^^^^     ^  ^^^^^
+    92
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_exit_points_skip_try_blocks() {
+        check(
+            r#"
+pub fn foo() -> Option<i32> {
+  //^^
+    let x: Option<i32> = try { bar()? };
+    baz()<|>?;
+       //^
+    match x { Some(_) => None, None => Some(1) }
+                       //^^^^          ^^^^^^^
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_yield_points() {
+        check(
+            r#"
+pub async fn foo() {
+  //^^^^^
+    bar().await;
+        //^^^^^
+    let f = async { baz().await };
+    qux().awa<|>it;
+        //^^^^^
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_yield_points_in_async_block() {
+        check(
+            r#"
+fn foo() {
+    let f = as<|>ync { bar().await; async { baz().await }; qux().await };
+          //^^^^^         ^^^^^                               ^^^^^
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_break_points() {
+        check(
+            r#"
+fn foo() {
+    'outer: loop {
+  //^^^^^^^ ^^^^
+        while true {
+            if a { break; }
+            if b { break 'outer; }
+                 //^^^^^^^^^^^^
+            for x in xs {
+                continue 'outer;
+              //^^^^^^^^^^^^^^^
+            }
+        }
+        br<|>eak;
+      //^^^^^
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_break_points_on_loop() {
+        check(
+            r#"
+fn foo() {
+    wh<|>ile true {
+  //^^^^^
+        loop { break; }
+        let f = || { loop { break } };
+        if a { break; }
+             //^^^^^
+        continue;
+      //^^^^^^^^
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_no_related_highlights() {
+        check(
+            r#"
+fn foo() {
+    let x<|> = 92;
+}
+"#,
+        );
+    }
+}
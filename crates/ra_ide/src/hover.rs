@@ -1,6 +1,6 @@
 use hir::{
     Adt, AsAssocItem, AssocItemContainer, Documentation, FieldSource, HasSource, HirDisplay,
-    Module, ModuleDef, ModuleSource, Semantics,
+    Module, ModuleDef, ModuleSource, Semantics, Type,
 };
 use itertools::Itertools;
 use ra_db::SourceDatabase;
@@ -126,6 +126,11 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
     };
 
     res.markup = Markup::fenced_block(&ty.display(db));
+    if let HoverAction::GoToType(targets) = goto_type_action_for_types(db, &[ty]) {
+        if !targets.is_empty() {
+            res.actions.push(HoverAction::GoToType(targets));
+        }
+    }
     let range = sema.original_range(&node).range;
     Some(RangeInfo::new(range, res))
 }
@@ -181,47 +186,58 @@ fn runnable_action(
 }
 
 fn goto_type_action(db: &RootDatabase, def: Definition) -> Option<HoverAction> {
-    match def {
-        Definition::Local(it) => {
-            let mut targets: Vec<ModuleDef> = Vec::new();
-            let mut push_new_def = |item: ModuleDef| {
-                if !targets.contains(&item) {
-                    targets.push(item);
-                }
-            };
-
-            it.ty(db).walk(db, |t| {
-                if let Some(adt) = t.as_adt() {
-                    push_new_def(adt.into());
-                } else if let Some(trait_) = t.as_dyn_trait() {
-                    push_new_def(trait_.into());
-                } else if let Some(traits) = t.as_impl_traits(db) {
-                    traits.into_iter().for_each(|it| push_new_def(it.into()));
-                } else if let Some(trait_) = t.as_associated_type_parent_trait(db) {
-                    push_new_def(trait_.into());
-                }
-            });
-
-            let targets = targets
-                .into_iter()
-                .filter_map(|it| {
-                    Some(HoverGotoTypeData {
-                        mod_path: render_path(
-                            db,
-                            it.module(db)?,
-                            it.name(db).map(|name| name.to_string()),
-                        ),
-                        nav: it.try_to_nav(db)?,
-                    })
-                })
-                .collect();
-
-            Some(HoverAction::GoToType(targets))
+    let types = match def {
+        Definition::Local(it) => return Some(goto_type_action_for_types(db, &[it.ty(db)])),
+        Definition::Field(it) => vec![it.signature_ty(db)],
+        Definition::ModuleDef(ModuleDef::Function(it)) => {
+            let mut types = it.param_types(db);
+            types.push(it.ret_type(db));
+            types
         }
-        _ => None,
+        _ => return None,
+    };
+    match goto_type_action_for_types(db, &types) {
+        HoverAction::GoToType(targets) if targets.is_empty() => None,
+        action => Some(action),
     }
 }
 
+/// Collects the definitions of all ADTs and traits mentioned in `types`.
+fn goto_type_action_for_types(db: &RootDatabase, types: &[Type]) -> HoverAction {
+    let mut targets: Vec<ModuleDef> = Vec::new();
+    let mut push_new_def = |item: ModuleDef| {
+        if !targets.contains(&item) {
+            targets.push(item);
+        }
+    };
+
+    for ty in types {
+        ty.walk(db, |t| {
+            if let Some(adt) = t.as_adt() {
+                push_new_def(adt.into());
+            } else if let Some(trait_) = t.as_dyn_trait() {
+                push_new_def(trait_.into());
+            } else if let Some(traits) = t.as_impl_traits(db) {
+                traits.into_iter().for_each(|it| push_new_def(it.into()));
+            } else if let Some(trait_) = t.as_associated_type_parent_trait(db) {
+                push_new_def(trait_.into());
+            }
+        });
+    }
+
+    let targets = targets
+        .into_iter()
+        .filter_map(|it| {
+            Some(HoverGotoTypeData {
+                mod_path: render_path(db, it.module(db)?, it.name(db).map(|name| name.to_string())),
+                nav: it.try_to_nav(db)?,
+            })
+        })
+        .collect();
+
+    HoverAction::GoToType(targets)
+}
+
 fn hover_markup(
     docs: Option<String>,
     desc: Option<String>,
@@ -352,7 +368,7 @@ mod tests {
     use expect::{expect, Expect};
     use ra_db::FileLoader;
 
-    use crate::mock_analysis::analysis_and_position;
+    use crate::mock_analysis::{analysis_and_position, with_identity_proc_macros, MockAnalysis};
 
     use super::*;
 
@@ -946,6 +962,26 @@ fn foo(bar:u32) { let a = id!(ba<|>r); }
         );
     }
 
+    #[test]
+    fn test_hover_through_attr_macro_item() {
+        let (mock, position) = MockAnalysis::with_files_and_position(
+            r#"
+//- /main.rs
+#[proc::identity]
+fn foo(bar: u32) { let a = ba<|>r; }
+//- /proc/lib.rs
+"#,
+        );
+        let mut host = mock.analysis_host();
+        with_identity_proc_macros(&mut host, "proc");
+        let hover = host.analysis().hover(position).unwrap().unwrap();
+        expect![[r#"
+            ```rust
+            u32
+            ```"#]]
+        .assert_eq(hover.info.markup.as_str());
+    }
+
     #[test]
     fn test_hover_through_expr_in_macro_recursive() {
         check(
@@ -2384,4 +2420,158 @@ fn main() { let s<|>t = test().get(); }
             "#]],
         );
     }
+
+    #[test]
+    fn test_hover_fn_signature_has_goto_type_action() {
+        check_actions(
+            r#"
+struct Arg {}
+struct Ret {}
+
+fn fo<|>o(a: Arg, b: &Arg) -> Ret { Ret {} }
+"#,
+            expect![[r#"
+                [
+                    GoToType(
+                        [
+                            HoverGotoTypeData {
+                                mod_path: "Arg",
+                                nav: NavigationTarget {
+                                    file_id: FileId(
+                                        1,
+                                    ),
+                                    full_range: 0..13,
+                                    name: "Arg",
+                                    kind: STRUCT_DEF,
+                                    focus_range: Some(
+                                        7..10,
+                                    ),
+                                    container_name: None,
+                                    description: Some(
+                                        "struct Arg",
+                                    ),
+                                    docs: None,
+                                },
+                            },
+                            HoverGotoTypeData {
+                                mod_path: "Ret",
+                                nav: NavigationTarget {
+                                    file_id: FileId(
+                                        1,
+                                    ),
+                                    full_range: 14..27,
+                                    name: "Ret",
+                                    kind: STRUCT_DEF,
+                                    focus_range: Some(
+                                        21..24,
+                                    ),
+                                    container_name: None,
+                                    description: Some(
+                                        "struct Ret",
+                                    ),
+                                    docs: None,
+                                },
+                            },
+                        ],
+                    ),
+                ]
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_hover_field_has_goto_type_action() {
+        check_actions(
+            r#"
+struct Bar {}
+struct Wrapper<T>(T);
+struct Foo { ba<|>r: Wrapper<Bar> }
+"#,
+            expect![[r#"
+                [
+                    GoToType(
+                        [
+                            HoverGotoTypeData {
+                                mod_path: "Wrapper",
+                                nav: NavigationTarget {
+                                    file_id: FileId(
+                                        1,
+                                    ),
+                                    full_range: 14..35,
+                                    name: "Wrapper",
+                                    kind: STRUCT_DEF,
+                                    focus_range: Some(
+                                        21..28,
+                                    ),
+                                    container_name: None,
+                                    description: Some(
+                                        "struct Wrapper",
+                                    ),
+                                    docs: None,
+                                },
+                            },
+                            HoverGotoTypeData {
+                                mod_path: "Bar",
+                                nav: NavigationTarget {
+                                    file_id: FileId(
+                                        1,
+                                    ),
+                                    full_range: 0..13,
+                                    name: "Bar",
+                                    kind: STRUCT_DEF,
+                                    focus_range: Some(
+                                        7..10,
+                                    ),
+                                    container_name: None,
+                                    description: Some(
+                                        "struct Bar",
+                                    ),
+                                    docs: None,
+                                },
+                            },
+                        ],
+                    ),
+                ]
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_hover_expression_has_goto_type_action() {
+        check_actions(
+            r#"
+struct Foo {}
+fn foo() -> Foo { Foo {} }
+
+fn main() { let _ = foo()<|>; }
+"#,
+            expect![[r#"
+                [
+                    GoToType(
+                        [
+                            HoverGotoTypeData {
+                                mod_path: "Foo",
+                                nav: NavigationTarget {
+                                    file_id: FileId(
+                                        1,
+                                    ),
+                                    full_range: 0..13,
+                                    name: "Foo",
+                                    kind: STRUCT_DEF,
+                                    focus_range: Some(
+                                        7..10,
+                                    ),
+                                    container_name: None,
+                                    description: Some(
+                                        "struct Foo",
+                                    ),
+                                    docs: None,
+                                },
+                            },
+                        ],
+                    ),
+                ]
+            "#]],
+        );
+    }
 }
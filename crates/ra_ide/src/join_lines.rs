@@ -2,9 +2,9 @@ use itertools::Itertools;
 use ra_fmt::{compute_ws, extract_trivial_expression};
 use ra_syntax::{
     algo::{find_covering_element, non_trivia_sibling},
-    ast::{self, AstNode, AstToken},
+    ast::{self, AstNode, AstToken, HasQuotes},
     Direction, NodeOrToken, SourceFile,
-    SyntaxKind::{self, WHITESPACE},
+    SyntaxKind::{self, BIN_EXPR, IF_EXPR, STRING, WHITESPACE},
     SyntaxNode, SyntaxToken, TextRange, TextSize, T,
 };
 use ra_text_edit::{TextEdit, TextEditBuilder};
@@ -89,6 +89,12 @@ fn remove_newline(edit: &mut TextEditBuilder, token: &SyntaxToken, offset: TextS
         return;
     }
 
+    // Merges string literals, like `"foo" +\n "bar"` or the arguments of
+    // `concat!("foo",\n "bar")`, into `"foobar"`.
+    if join_string_literals(edit, token).is_some() {
+        return;
+    }
+
     if let (Some(_), Some(next)) = (
         prev.as_token().cloned().and_then(ast::Comment::cast),
         next.as_token().cloned().and_then(ast::Comment::cast),
@@ -123,6 +129,18 @@ fn remove_newline(edit: &mut TextEditBuilder, token: &SyntaxToken, offset: TextS
     if join_single_use_tree(edit, token).is_some() {
         return;
     }
+    // and for the bodies of `if` and `else` with a single statement:
+    //
+    // ```
+    // if cond {<|>
+    //     foo();
+    // }
+    // ```
+    //
+    // becomes `if cond { foo(); }`
+    if join_single_stmt_if_body(edit, token).is_some() {
+        return;
+    }
 
     // Remove newline but add a computed amount of whitespace characters
     edit.replace(token.text_range(), compute_ws(prev.kind(), next.kind()).to_string());
@@ -157,6 +175,73 @@ fn join_single_expr_block(edit: &mut TextEditBuilder, token: &SyntaxToken) -> Op
     Some(())
 }
 
+fn join_single_stmt_if_body(edit: &mut TextEditBuilder, token: &SyntaxToken) -> Option<()> {
+    let block_expr = ast::BlockExpr::cast(token.parent())?;
+    if block_expr.syntax().parent()?.kind() != IF_EXPR {
+        return None;
+    }
+    if token.prev_sibling_or_token()?.kind() != T!['{'] {
+        return None;
+    }
+    let body = block_expr
+        .syntax()
+        .children_with_tokens()
+        .filter(|it| !matches!(it.kind(), WHITESPACE | T!['{'] | T!['}']))
+        .exactly_one()
+        .ok()?;
+    let body = body.into_node()?;
+    if !(ast::Stmt::can_cast(body.kind()) || ast::Expr::can_cast(body.kind())) {
+        return None;
+    }
+    let text = body.text().to_string();
+    if text.contains('\n') {
+        return None;
+    }
+    edit.replace(block_expr.syntax().text_range(), format!("{{ {} }}", text));
+    Some(())
+}
+
+fn join_string_literals(edit: &mut TextEditBuilder, token: &SyntaxToken) -> Option<()> {
+    let separator = token.prev_token()?;
+    match separator.kind() {
+        T![+] if separator.parent().kind() == BIN_EXPR => (),
+        T![,] if is_in_concat_macro(&separator) => (),
+        _ => return None,
+    }
+    let left = skip_trivia_token(separator.prev_token()?, Direction::Prev)?;
+    let right = token.next_token()?;
+    if left.kind() != STRING || right.kind() != STRING {
+        return None;
+    }
+    let left = ast::String::cast(left)?;
+    let right = ast::String::cast(right)?;
+    let left_quote = left.close_quote_text_range()?;
+    let right_quote = right.open_quote_text_range()?;
+    edit.delete(TextRange::new(left_quote.start(), right_quote.end()));
+    Some(())
+}
+
+fn is_in_concat_macro(token: &SyntaxToken) -> bool {
+    token
+        .parent()
+        .parent()
+        .and_then(ast::MacroCall::cast)
+        .and_then(|it| it.path())
+        .and_then(|it| it.segment())
+        .and_then(|it| it.name_ref())
+        .map_or(false, |it| it.text() == "concat")
+}
+
+fn skip_trivia_token(mut token: SyntaxToken, direction: Direction) -> Option<SyntaxToken> {
+    while token.kind().is_trivia() {
+        token = match direction {
+            Direction::Next => token.next_token()?,
+            Direction::Prev => token.prev_token()?,
+        }
+    }
+    Some(token)
+}
+
 fn join_single_use_tree(edit: &mut TextEditBuilder, token: &SyntaxToken) -> Option<()> {
     let use_tree_list = ast::UseTreeList::cast(token.parent())?;
     let (tree,) = use_tree_list.use_trees().collect_tuple()?;
@@ -165,7 +250,7 @@ fn join_single_use_tree(edit: &mut TextEditBuilder, token: &SyntaxToken) -> Opti
 }
 
 fn is_trailing_comma(left: SyntaxKind, right: SyntaxKind) -> bool {
-    matches!((left, right), (T![,], T![')']) | (T![,], T![']']))
+    matches!((left, right), (T![,], T![')']) | (T![,], T![']']) | (T![,], T![>]))
 }
 
 #[cfg(test)]
@@ -709,8 +794,7 @@ fn foo() {
         ",
             r"
 fn foo() {
-    <|>if true { 92
-    }
+    <|>if true { 92 }
 }
         ",
         );
@@ -747,4 +831,117 @@ fn foo() {
         ",
         );
     }
+
+    #[test]
+    fn join_lines_if_single_stmt_body() {
+        check_join_lines(
+            r"
+fn foo() {
+    <|>if true {
+        foo();
+    } else {
+        bar();
+    }
+}
+",
+            r"
+fn foo() {
+    <|>if true { foo(); } else {
+        bar();
+    }
+}
+",
+        );
+        check_join_lines(
+            r"
+fn foo() {
+    if true {
+        foo();
+    <|>} else {
+        bar();
+    }
+}
+",
+            r"
+fn foo() {
+    if true {
+        foo();
+    <|>} else { bar(); }
+}
+",
+        );
+    }
+
+    #[test]
+    fn join_lines_if_multi_stmt_body() {
+        check_join_lines(
+            r"
+fn foo() {
+    <|>if true {
+        foo();
+        bar();
+    }
+}
+",
+            r"
+fn foo() {
+    <|>if true { foo();
+        bar();
+    }
+}
+",
+        );
+    }
+
+    #[test]
+    fn join_lines_generic_trailing_comma() {
+        check_join_lines(
+            r"
+<|>type Foo = HashMap<K, V,
+>;
+",
+            r"
+<|>type Foo = HashMap<K, V>;
+",
+        );
+    }
+
+    #[test]
+    fn join_lines_string_concat() {
+        check_join_lines(
+            r#"
+fn foo() {
+    <|>let s = "foo" +
+        "bar";
+}
+"#,
+            r#"
+fn foo() {
+    <|>let s = "foobar";
+}
+"#,
+        );
+        check_join_lines(
+            r#"
+<|>const S: &str = concat!("foo",
+    "bar");
+"#,
+            r#"
+<|>const S: &str = concat!("foobar");
+"#,
+        );
+    }
+
+    #[test]
+    fn join_lines_does_not_merge_raw_strings() {
+        check_join_lines(
+            r##"
+<|>const S: &str = concat!(r"foo",
+    "bar");
+"##,
+            r##"
+<|>const S: &str = concat!(r"foo", "bar");
+"##,
+        );
+    }
 }
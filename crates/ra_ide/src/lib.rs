@@ -30,6 +30,7 @@ mod hover;
 mod call_hierarchy;
 mod call_info;
 mod syntax_highlighting;
+mod highlight_related;
 mod parent_module;
 mod references;
 mod diagnostics;
@@ -38,23 +39,30 @@ mod folding_ranges;
 mod join_lines;
 mod typing;
 mod matching_brace;
+mod move_item;
 mod display;
+mod doc_code_blocks;
 mod inlay_hints;
 mod expand_macro;
 mod ssr;
+mod view_hir;
+mod view_item_tree;
+mod view_crate_graph;
 
 use std::sync::Arc;
 
+use hir::db::AstDatabase;
 use ra_cfg::CfgOptions;
 use ra_db::{
-    salsa::{self, ParallelDatabase},
+    salsa::{self, Durability, ParallelDatabase},
     CheckCanceled, Env, FileLoader, FileSet, SourceDatabase, VfsPath,
 };
 use ra_ide_db::{
-    symbol_index::{self, FileSymbol},
+    symbol_index::{self, FileSymbol, SymbolsDatabase},
     LineIndexDatabase,
 };
 use ra_syntax::{SourceFile, TextRange, TextSize};
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::display::ToNav;
 
@@ -63,13 +71,15 @@ pub use crate::{
     completion::{
         CompletionConfig, CompletionItem, CompletionItemKind, CompletionScore, InsertTextFormat,
     },
-    diagnostics::Severity,
+    diagnostics::{Severity, DIAGNOSTIC_NAMES},
     display::{file_structure, FunctionSignature, NavigationTarget, StructureNode},
     expand_macro::ExpandedMacro,
     folding_ranges::{Fold, FoldKind},
     hover::{HoverAction, HoverConfig, HoverGotoTypeData, HoverResult},
     inlay_hints::{InlayHint, InlayHintsConfig, InlayKind},
     markup::Markup,
+    move_item::Direction,
+    prime_caches::PrimeCachesProgress,
     references::{Declaration, Reference, ReferenceAccess, ReferenceKind, ReferenceSearchResult},
     runnables::{Runnable, RunnableKind, TestId},
     syntax_highlighting::{
@@ -77,7 +87,7 @@ pub use crate::{
     },
 };
 
-pub use hir::{Documentation, Semantics};
+pub use hir::{db::MacroLimits, Documentation, Semantics};
 pub use ra_assists::{Assist, AssistConfig, AssistId, AssistKind, ResolvedAssist};
 pub use ra_db::{
     Canceled, CrateGraph, CrateId, Edition, FileId, FilePosition, FileRange, SourceRoot,
@@ -85,11 +95,12 @@ pub use ra_db::{
 };
 pub use ra_ide_db::{
     change::AnalysisChange,
-    line_index::{LineCol, LineIndex},
+    line_index::{LineCol, LineColUtf8, LineIndex},
+    persistent_cache::PersistentCache,
     search::SearchScope,
     source_change::{FileSystemEdit, SourceChange, SourceFileEdit},
     symbol_index::Query,
-    RootDatabase,
+    LruConfig, RootDatabase,
 };
 pub use ra_ssr::SsrError;
 pub use ra_text_edit::{Indel, TextEdit};
@@ -98,6 +109,8 @@ pub type Cancelable<T> = Result<T, Canceled>;
 
 #[derive(Debug)]
 pub struct Diagnostic {
+    /// The code of the diagnostic, like `missing-match-arm`.
+    pub name: &'static str,
     pub message: String,
     pub range: TextRange,
     pub severity: Severity,
@@ -150,8 +163,23 @@ impl AnalysisHost {
         AnalysisHost { db: RootDatabase::new(lru_capacity) }
     }
 
-    pub fn update_lru_capacity(&mut self, lru_capacity: Option<usize>) {
-        self.db.update_lru_capacity(lru_capacity);
+    pub fn update_lru_config(&mut self, lru_config: LruConfig) {
+        self.db.update_lru_config(lru_config);
+    }
+
+    /// Reuses the results stored in `cache` by a previous run.
+    pub fn set_persistent_cache(&mut self, cache: PersistentCache) {
+        self.db.set_persistent_cache_with_durability(Arc::new(cache), Durability::HIGH);
+    }
+
+    pub fn set_macro_limits(&mut self, limits: MacroLimits) {
+        self.db.set_macro_limits_with_durability(limits, Durability::HIGH);
+    }
+
+    /// Sets whether items whose attribute macro fails to expand are analyzed
+    /// as if the attribute was not there, instead of being dropped.
+    pub fn set_attr_macro_fallback(&mut self, fallback: bool) {
+        self.db.set_attr_macro_fallback_with_durability(fallback, Durability::HIGH);
     }
 
     /// Returns a snapshot of the current state, which you can query for
@@ -250,6 +278,20 @@ impl Analysis {
         self.with_db(|db| prime_caches::prime_caches(db, files))
     }
 
+    /// Computes the def maps and symbol indices of all crates on `num_threads`
+    /// threads (zero means one per core), reporting progress through `cb`.
+    pub fn prime_crate_def_maps<F>(&self, num_threads: usize, cb: F) -> Cancelable<()>
+    where
+        F: Fn(PrimeCachesProgress) + Sync + std::panic::UnwindSafe,
+    {
+        self.with_db(move |db| prime_caches::prime_crate_def_maps(db, num_threads, &cb))
+    }
+
+    /// Writes the results which can be reused by the next run to disk.
+    pub fn store_persistent_cache(&self) -> Cancelable<()> {
+        self.with_db(|db| db.persistent_cache().store(db))
+    }
+
     /// Gets the text of the source file.
     pub fn file_text(&self, file_id: FileId) -> Cancelable<Arc<String>> {
         self.with_db(|db| db.file_text(file_id))
@@ -291,6 +333,22 @@ impl Analysis {
         self.with_db(|db| syntax_tree::syntax_tree(&db, file_id, text_range))
     }
 
+    /// Returns the lowered body of the function at the position, for debug
+    /// purposes.
+    pub fn view_hir(&self, position: FilePosition) -> Cancelable<String> {
+        self.with_db(|db| view_hir::view_hir(&db, position))
+    }
+
+    /// Returns the item tree of the file, for debug purposes.
+    pub fn view_item_tree(&self, file_id: FileId) -> Cancelable<String> {
+        self.with_db(|db| view_item_tree::view_item_tree(&db, file_id))
+    }
+
+    /// Returns the crate graph in the GraphViz DOT format, for debug purposes.
+    pub fn view_crate_graph(&self, excluded_roots: FxHashSet<FileId>) -> Cancelable<String> {
+        self.with_db(|db| view_crate_graph::view_crate_graph(&db, &excluded_roots))
+    }
+
     pub fn expand_macro(&self, position: FilePosition) -> Cancelable<Option<ExpandedMacro>> {
         self.with_db(|db| expand_macro::expand_macro(db, position))
     }
@@ -304,6 +362,19 @@ impl Analysis {
         })
     }
 
+    /// Returns an edit which swaps the item at the range with its previous or
+    /// next sibling.
+    pub fn move_item(
+        &self,
+        frange: FileRange,
+        direction: Direction,
+    ) -> Cancelable<Option<TextEdit>> {
+        self.with_db(|db| {
+            let parse = db.parse(frange.file_id);
+            move_item::move_item(&parse.tree(), frange.range, direction)
+        })
+    }
+
     /// Returns an edit which should be applied when opening a new line, fixing
     /// up minor stuff like continuing the comment.
     /// The edit will be a snippet (with `$0`).
@@ -425,6 +496,11 @@ impl Analysis {
         self.with_db(|db| parent_module::parent_module(db, position))
     }
 
+    /// Returns the dependency crate named by the path under the cursor.
+    pub fn dependency_at(&self, position: FilePosition) -> Cancelable<Option<CrateId>> {
+        self.with_db(|db| parent_module::dependency_at(db, position))
+    }
+
     /// Returns crates this file belongs too.
     pub fn crate_for(&self, file_id: FileId) -> Cancelable<Vec<CrateId>> {
         self.with_db(|db| parent_module::crate_for(db, file_id))
@@ -435,6 +511,15 @@ impl Analysis {
         self.with_db(|db| db.crate_graph()[crate_id].edition)
     }
 
+    /// Returns true if this file belongs to a library, like a dependency or
+    /// the sysroot, which is analyzed but never edited.
+    pub fn is_library_file(&self, file_id: FileId) -> Cancelable<bool> {
+        self.with_db(|db| {
+            let source_root = ra_db::SourceDatabaseExt::file_source_root(db, file_id);
+            ra_db::SourceDatabaseExt::source_root(db, source_root).is_library
+        })
+    }
+
     /// Returns the root file of the given crate.
     pub fn crate_root(&self, crate_id: CrateId) -> Cancelable<FileId> {
         self.with_db(|db| db.crate_graph()[crate_id].root_file_id)
@@ -457,11 +542,28 @@ impl Analysis {
         })
     }
 
+    /// Returns the ranges related to the keyword at the position, like all
+    /// exit points of a function when on `fn`.
+    pub fn highlight_related(&self, position: FilePosition) -> Cancelable<Option<Vec<TextRange>>> {
+        self.with_db(|db| highlight_related::highlight_related(db, position))
+    }
+
     /// Computes syntax highlighting for the given file.
     pub fn highlight_as_html(&self, file_id: FileId, rainbow: bool) -> Cancelable<String> {
         self.with_db(|db| syntax_highlighting::highlight_as_html(db, file_id, rainbow))
     }
 
+    /// Renders the file as a standalone HTML page. References link to their
+    /// definitions in the files in `links`, which maps files to page URLs.
+    pub fn highlight_as_html_page(
+        &self,
+        file_id: FileId,
+        rainbow: bool,
+        links: &FxHashMap<FileId, String>,
+    ) -> Cancelable<String> {
+        self.with_db(|db| syntax_highlighting::highlight_as_html_page(db, file_id, rainbow, links))
+    }
+
     /// Computes completions at the given position.
     pub fn completions(
         &self,
@@ -505,6 +607,16 @@ impl Analysis {
         self.with_db(|db| references::rename(db, position, new_name))
     }
 
+    /// Returns the edits required when the file of a module is renamed, like
+    /// updating its `mod` declaration.
+    pub fn will_rename_file(
+        &self,
+        file_id: FileId,
+        new_name_stem: &str,
+    ) -> Cancelable<Option<SourceChange>> {
+        self.with_db(|db| references::will_rename_file(db, file_id, new_name_stem))
+    }
+
     pub fn structural_search_replace(
         &self,
         query: &str,
@@ -599,6 +711,37 @@ struct Foo;
         assert_eq!(struct_match, Some(STRUCT_DEF));
     }
 
+    #[test]
+    fn test_world_symbols_query_syntax() {
+        let code = r#"
+fn foo() {}
+fn foobar() {}
+
+struct Foo;
+struct FooBar;
+        "#;
+
+        let names = |query: &str| {
+            let (analysis, _) = single_file(code);
+            let mut names = analysis
+                .symbol_search(Query::parse(query))
+                .unwrap()
+                .into_iter()
+                .map(|it| it.name().to_string())
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        assert_eq!(names("foo"), vec!["Foo", "FooBar", "foo", "foobar"]);
+        assert_eq!(names("foo#"), vec!["Foo", "FooBar"]);
+        assert_eq!(names("Foo"), vec!["Foo", "FooBar"]);
+        assert_eq!(names("FB"), vec!["FooBar"]);
+        assert_eq!(names("\"foo\""), vec!["Foo", "foo"]);
+        assert_eq!(names("\"Foo\""), vec!["Foo"]);
+        assert_eq!(names("\"foo\"#"), vec!["Foo"]);
+    }
+
     fn get_symbols_matching(text: &str, query: &str) -> Vec<NavigationTarget> {
         let (analysis, _) = single_file(text);
         analysis.symbol_search(Query::new(query.into())).unwrap()
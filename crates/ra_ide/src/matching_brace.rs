@@ -1,6 +1,8 @@
 use ra_syntax::{
     ast::{self, AstNode},
-    SourceFile, SyntaxKind, TextSize, T,
+    SourceFile, SyntaxKind,
+    SyntaxKind::{PATH_SEGMENT, TYPE_ARG_LIST, TYPE_PARAM_LIST},
+    TextSize, T,
 };
 use test_utils::mark;
 
@@ -31,6 +33,12 @@ pub fn matching_brace(file: &SourceFile, offset: TextSize) -> Option<TextSize> {
         mark::hit!(pipes_not_braces);
         return None;
     }
+    if matches!(brace_token.kind(), T![<] | T![>])
+        && !matches!(parent.kind(), TYPE_ARG_LIST | TYPE_PARAM_LIST | PATH_SEGMENT)
+    {
+        mark::hit!(angles_not_braces);
+        return None;
+    }
     let matching_kind = BRACES[brace_idx ^ 1];
     let matching_node = parent
         .children_with_tokens()
@@ -62,6 +70,20 @@ mod tests {
         do_check("fn main() { |x: i32|<|> x * 2;}", "fn main() { <|>|x: i32| x * 2;}");
         do_check("fn main() { <|>|x: i32| x * 2;}", "fn main() { |x: i32<|>| x * 2;}");
 
+        do_check("fn foo<T: Clone><|>() {}", "fn foo<|><T: Clone>() {}");
+        do_check(
+            "fn main() { Vec::<<|>(u8, u8)>::new(); }",
+            "fn main() { Vec::<(u8, u8)<|>>::new(); }",
+        );
+        do_check("fn f(_: Vec<Vec<|><u8>>) {}", "fn f(_: Vec<Vec<u8<|>>>) {}");
+        do_check("fn f(_: Vec<|><Vec<u8>>) {}", "fn f(_: Vec<Vec<u8><|>>) {}");
+        do_check("fn f() { <Foo as Trait<|>>::bar() }", "fn f() { <|><Foo as Trait>::bar() }");
+
+        {
+            mark::check!(angles_not_braces);
+            do_check("fn main() { a <<|> b; }", "fn main() { a <<|> b; }");
+        }
+
         {
             mark::check!(pipes_not_braces);
             do_check(
@@ -174,3 +174,28 @@ pub fn analysis_and_range(ra_fixture: &str) -> (Analysis, FileRange) {
     };
     (res.analysis(), FileRange { file_id, range })
 }
+
+/// Makes the crate `krate` provide the `identity` attribute and the
+/// `identity_fn` function-like proc macros, which expand to their input.
+#[cfg(test)]
+pub(crate) fn with_identity_proc_macros(host: &mut AnalysisHost, krate: &str) {
+    use ra_db::{fixture::WithFixture, ProcMacro, ProcMacroKind};
+
+    #[derive(Debug)]
+    struct Identity;
+    impl tt::TokenExpander for Identity {
+        fn expand(
+            &self,
+            subtree: &tt::Subtree,
+            _attrs: Option<&tt::Subtree>,
+        ) -> Result<tt::Subtree, tt::ExpansionError> {
+            Ok(subtree.clone())
+        }
+    }
+    let proc_macros =
+        vec![("identity", ProcMacroKind::Attr), ("identity_fn", ProcMacroKind::FuncLike)]
+            .into_iter()
+            .map(|(name, kind)| ProcMacro { name: name.into(), kind, expander: Arc::new(Identity) })
+            .collect();
+    host.raw_database_mut().set_proc_macros(krate, proc_macros);
+}
@@ -0,0 +1,286 @@
+use ra_syntax::{
+    algo::find_covering_element,
+    AstNode, Direction as SyntaxDirection, NodeOrToken, SourceFile,
+    SyntaxKind::{self, *},
+    SyntaxNode, TextRange,
+};
+use ra_text_edit::{TextEdit, TextEditBuilder};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+// Feature: Move Item
+//
+// Move item under cursor or selection up and down. Items, statements, match
+// arms, enum variants and fields are swapped with their sibling, together
+// with their attributes and comments.
+//
+// |===
+// | Editor  | Action Name
+//
+// | VS Code | **Rust Analyzer: Move item up**
+// | VS Code | **Rust Analyzer: Move item down**
+// |===
+
+/// Returns an edit which swaps the item at `range` with its neighbour. The
+/// new text contains a `$0` snippet marker at the new cursor position.
+pub(crate) fn move_item(
+    file: &SourceFile,
+    range: TextRange,
+    direction: Direction,
+) -> Option<TextEdit> {
+    let node = match find_covering_element(file.syntax(), range) {
+        NodeOrToken::Node(it) => it,
+        NodeOrToken::Token(it) => it.parent(),
+    };
+    let item = node.ancestors().find(is_movable)?;
+    let sibling = match direction {
+        Direction::Up => item.prev_sibling(),
+        Direction::Down => item.next_sibling(),
+    }?;
+    if !is_movable(&sibling) {
+        return None;
+    }
+
+    let text = file.syntax().text().to_string();
+    let item_range = range_with_comments(&item);
+    let sibling_range = range_with_comments(&sibling);
+    let slice = |range: TextRange| text[range].to_string();
+
+    // Keep the cursor at the same place relative to the moved item.
+    let cursor = range.start().max(item_range.start()).min(item_range.end()) - item_range.start();
+    let mut moved = slice(item_range);
+    moved.insert_str(usize::from(cursor), "$0");
+
+    let mut builder = TextEditBuilder::default();
+    builder.replace(sibling_range, moved);
+    builder.replace(item_range, slice(sibling_range));
+    Some(builder.finish())
+}
+
+fn is_movable(node: &SyntaxNode) -> bool {
+    const ITEMS: &[SyntaxKind] = &[
+        STRUCT_DEF,
+        UNION_DEF,
+        ENUM_DEF,
+        FN_DEF,
+        EXTERN_CRATE_ITEM,
+        MODULE,
+        USE_ITEM,
+        STATIC_DEF,
+        CONST_DEF,
+        TRAIT_DEF,
+        IMPL_DEF,
+        TYPE_ALIAS_DEF,
+        MACRO_CALL,
+        EXTERN_BLOCK,
+        LET_STMT,
+        EXPR_STMT,
+        MATCH_ARM,
+        ENUM_VARIANT,
+        RECORD_FIELD_DEF,
+    ];
+    const CONTAINERS: &[SyntaxKind] = &[
+        SOURCE_FILE,
+        ITEM_LIST,
+        EXTERN_ITEM_LIST,
+        BLOCK_EXPR,
+        MATCH_ARM_LIST,
+        ENUM_VARIANT_LIST,
+        RECORD_FIELD_DEF_LIST,
+    ];
+    ITEMS.contains(&node.kind())
+        && node.parent().map_or(false, |parent| CONTAINERS.contains(&parent.kind()))
+}
+
+/// Items own their doc comments, but statements don't, so we extend the range
+/// to the comments directly above the node, and to the comment trailing it on
+/// the same line. Comments trailing the previous line belong to that line.
+fn range_with_comments(node: &SyntaxNode) -> TextRange {
+    let mut end = node.text_range().end();
+    let mut next = node.next_sibling_or_token();
+    if let Some(NodeOrToken::Token(token)) = &next {
+        if token.kind() == WHITESPACE && !token.text().contains('\n') {
+            next = token.next_sibling_or_token();
+        }
+    }
+    if let Some(NodeOrToken::Token(token)) = next {
+        if token.kind() == COMMENT {
+            end = token.text_range().end();
+        }
+    }
+
+    let mut start = node.text_range().start();
+    for element in node.siblings_with_tokens(SyntaxDirection::Prev).skip(1) {
+        match element {
+            NodeOrToken::Token(token) if token.kind() == COMMENT => {
+                let on_own_line = match token.prev_sibling_or_token() {
+                    Some(NodeOrToken::Token(prev)) => {
+                        prev.kind() == WHITESPACE && prev.text().contains('\n')
+                    }
+                    Some(NodeOrToken::Node(_)) => false,
+                    None => true,
+                };
+                if !on_own_line {
+                    break;
+                }
+                start = token.text_range().start();
+            }
+            NodeOrToken::Token(token)
+                if token.kind() == WHITESPACE && token.text().matches('\n').count() <= 1 => {}
+            _ => break,
+        }
+    }
+    TextRange::new(start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use expect::{expect, Expect};
+    use ra_syntax::SourceFile;
+    use test_utils::extract_range_or_offset;
+
+    use super::{move_item, Direction};
+
+    fn check(ra_fixture: &str, direction: Direction, expect: Expect) {
+        let (range, before) = extract_range_or_offset(ra_fixture);
+        let file = SourceFile::parse(&before).ok().unwrap();
+        let edit = move_item(&file, range.into(), direction);
+        let actual = match edit {
+            Some(edit) => {
+                let mut text = before;
+                edit.apply(&mut text);
+                text
+            }
+            None => "no move".to_string(),
+        };
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn moves_fn_down() {
+        check(
+            r#"
+/// Docs stay with `foo`.
+#[inline]
+fn f<|>oo() {}
+
+fn bar() {
+    1 + 1
+}
+"#,
+            Direction::Down,
+            expect![[r#"
+
+                fn bar() {
+                    1 + 1
+                }
+
+                /// Docs stay with `foo`.
+                #[inline]
+                fn f$0oo() {}
+            "#]],
+        );
+    }
+
+    #[test]
+    fn moves_statement_up_with_comment() {
+        check(
+            r#"
+fn main() {
+    let a = 1; // trailing comment stays
+    // About `b`.
+    // Still about `b`.
+    let <|>b = 2;
+    a + b
+}
+"#,
+            Direction::Up,
+            expect![[r#"
+
+                fn main() {
+                    // About `b`.
+                    // Still about `b`.
+                    let $0b = 2;
+                    let a = 1; // trailing comment stays
+                    a + b
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn does_not_swap_with_tail_expr() {
+        check(
+            r#"
+fn main() {
+    let <|>b = 2;
+    b
+}
+"#,
+            Direction::Down,
+            expect![[r#"no move"#]],
+        );
+    }
+
+    #[test]
+    fn moves_match_arm() {
+        check(
+            r#"
+fn main() {
+    match x {
+        1 => {}
+        2 => <|>{}
+        _ => {}
+    }
+}
+"#,
+            Direction::Up,
+            expect![[r#"
+
+                fn main() {
+                    match x {
+                        2 => $0{}
+                        1 => {}
+                        _ => {}
+                    }
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn moves_trait_member() {
+        check(
+            r#"
+impl S {
+    fn a() {}
+    fn b<|>() {}
+}
+"#,
+            Direction::Up,
+            expect![[r#"
+
+                impl S {
+                    fn b$0() {}
+                    fn a() {}
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn first_item_does_not_move_up() {
+        check(
+            r#"
+fn f<|>oo() {}
+fn bar() {}
+"#,
+            Direction::Up,
+            expect![[r#"no move"#]],
+        );
+    }
+}
@@ -1,6 +1,9 @@
-use hir::Semantics;
+use hir::{ModuleDef, Semantics};
 use ra_db::{CrateId, FileId, FilePosition};
-use ra_ide_db::RootDatabase;
+use ra_ide_db::{
+    defs::{classify_name_ref, Definition, NameRefClass},
+    RootDatabase,
+};
 use ra_syntax::{
     algo::find_node_at_offset,
     ast::{self, AstNode},
@@ -19,8 +22,8 @@ use crate::NavigationTarget;
 // | VS Code | **Rust Analyzer: Locate parent module**
 // |===
 
-/// This returns `Vec` because a module may be included from several places. We
-/// don't handle this case yet though, so the Vec has length at most one.
+/// This returns `Vec` because a module may be included from several places,
+/// either by several `mod` declarations or by several crates.
 pub(crate) fn parent_module(db: &RootDatabase, position: FilePosition) -> Vec<NavigationTarget> {
     let sema = Semantics::new(db);
     let source_file = sema.parse(position.file_id);
@@ -38,16 +41,42 @@ pub(crate) fn parent_module(db: &RootDatabase, position: FilePosition) -> Vec<Na
         }
     }
 
-    let module = match module {
-        Some(module) => sema.to_def(&module),
-        None => sema.to_module_def(position.file_id),
+    let modules = match module {
+        Some(module) => sema.to_def(&module).into_iter().collect(),
+        None => sema.to_module_defs(position.file_id),
     };
-    let module = match module {
-        None => return Vec::new(),
-        Some(it) => it,
+    let mut res: Vec<NavigationTarget> = Vec::new();
+    for module in modules {
+        let nav = NavigationTarget::from_module_to_decl(db, module);
+        let is_duplicate = res
+            .iter()
+            .any(|it| it.file_id() == nav.file_id() && it.full_range() == nav.full_range());
+        if !is_duplicate {
+            res.push(nav);
+        }
+    }
+    res
+}
+
+/// Returns the crate named by the path segment under the cursor (like `serde`
+/// in `serde::Serialize` or `extern crate serde;`), if it is a dependency of
+/// the current crate rather than the current crate itself.
+pub(crate) fn dependency_at(db: &RootDatabase, position: FilePosition) -> Option<CrateId> {
+    let sema = Semantics::new(db);
+    let source_file = sema.parse(position.file_id);
+    let name_ref = find_node_at_offset::<ast::NameRef>(source_file.syntax(), position.offset)?;
+    let module = match classify_name_ref(&sema, &name_ref)? {
+        NameRefClass::Definition(Definition::ModuleDef(ModuleDef::Module(it))) => it,
+        _ => return None,
     };
-    let nav = NavigationTarget::from_module_to_decl(db, module);
-    vec![nav]
+    if module.parent(db).is_some() {
+        return None;
+    }
+    let krate = module.krate();
+    if sema.to_module_defs(position.file_id).iter().any(|it| it.krate() == krate) {
+        return None;
+    }
+    Some(krate.into())
 }
 
 /// Returns `Vec` for the same reason as `parent_module`
@@ -71,6 +100,7 @@ mod tests {
         mock_analysis::{analysis_and_position, MockAnalysis},
         AnalysisChange, CrateGraph,
         Edition::Edition2018,
+        FileId,
     };
 
     #[test]
@@ -122,6 +152,51 @@ mod tests {
         nav.assert_match("baz MODULE FileId(1) 32..44");
     }
 
+    #[test]
+    fn test_resolve_multi_parent_module() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+//- /main.rs
+mod foo;
+#[path = "foo.rs"]
+mod bar;
+//- /foo.rs
+<|>
+"#,
+        );
+        let navs = analysis.parent_module(pos).unwrap();
+        assert_eq!(navs.len(), 2);
+        navs[0].assert_match("foo MODULE FileId(1) 0..8");
+        navs[1].assert_match("bar MODULE FileId(1) 9..36");
+    }
+
+    #[test]
+    fn test_dependency_at() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+//- /main.rs
+use de<|>p::Foo;
+//- /dep/lib.rs
+pub struct Foo;
+"#,
+        );
+        let dep = analysis.dependency_at(pos).unwrap().unwrap();
+        assert_eq!(analysis.crate_root(dep).unwrap(), FileId(2));
+    }
+
+    #[test]
+    fn test_dependency_at_ignores_own_crate() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+//- /main.rs
+mod foo;
+use crate<|>::foo;
+//- /foo.rs
+"#,
+        );
+        assert!(analysis.dependency_at(pos).unwrap().is_none());
+    }
+
     #[test]
     fn test_resolve_crate_root() {
         let mock = MockAnalysis::with_files(
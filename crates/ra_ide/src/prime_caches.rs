@@ -3,10 +3,132 @@
 //! request takes longer to compute. This modules implemented prepopulating of
 //! various caches, it's not really advanced at the moment.
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use hir::db::DefDatabase;
+use ra_db::{
+    salsa::{self, ParallelDatabase},
+    CheckCanceled, CrateId, SourceDatabase,
+};
+use ra_ide_db::symbol_index::SymbolsDatabase;
+use rayon::prelude::*;
+
 use crate::{FileId, RootDatabase};
 
+#[derive(Debug)]
+pub enum PrimeCachesProgress {
+    Started,
+    /// We started indexing a crate.
+    StartedOnCrate {
+        on_crate: String,
+        n_done: usize,
+        n_total: usize,
+    },
+    /// We finished indexing all crates.
+    Finished,
+}
+
 pub(crate) fn prime_caches(db: &RootDatabase, files: Vec<FileId>) {
     for file in files {
         let _ = crate::syntax_highlighting::highlight(db, file, None, false);
     }
 }
+
+/// Indexes all crates on a pool of `num_threads` threads, or one thread per
+/// core if `num_threads` is zero.
+pub(crate) fn prime_crate_def_maps(
+    db: &RootDatabase,
+    num_threads: usize,
+    cb: &(dyn Fn(PrimeCachesProgress) + Sync),
+) {
+    let graph = db.crate_graph();
+    // Dependencies come first, so that workers rarely block on each other.
+    let crates = graph.crates_in_topological_order();
+    let n_total = crates.len();
+    let n_done = AtomicUsize::new(0);
+
+    let pool = match rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .thread_name(|idx| format!("PrimeCaches{}", idx))
+        .build()
+    {
+        Ok(it) => it,
+        Err(err) => {
+            log::error!("failed to start the cache priming pool: {}", err);
+            return;
+        }
+    };
+
+    let snap = Snap(db.snapshot());
+    cb(PrimeCachesProgress::Started);
+    // A `Canceled` panic of a worker is resumed here and stops the others.
+    pool.install(|| {
+        crates.par_iter().for_each_with(snap, |snap, &krate| {
+            let db = &*snap.0;
+            db.check_canceled();
+            let on_crate = graph[krate].display_name.clone().unwrap_or_default();
+            let done = n_done.load(Ordering::SeqCst);
+            cb(PrimeCachesProgress::StartedOnCrate { on_crate, n_done: done, n_total });
+            prime_crate(db, krate);
+            n_done.fetch_add(1, Ordering::SeqCst);
+        })
+    });
+    cb(PrimeCachesProgress::Finished);
+}
+
+/// Computes the def map of `krate`, which builds the item trees of all its
+/// files, and the symbol index of those files.
+fn prime_crate(db: &RootDatabase, krate: CrateId) {
+    let def_map = db.crate_def_map(krate);
+    for (_, data) in def_map.modules.iter() {
+        if let Some(file_id) = data.origin.file_id() {
+            db.check_canceled();
+            db.file_symbols(file_id);
+        }
+    }
+}
+
+/// Need to wrap Snapshot to provide `Clone` impl for `for_each_with`
+struct Snap<DB>(DB);
+impl<DB: ParallelDatabase> Clone for Snap<salsa::Snapshot<DB>> {
+    fn clone(&self) -> Snap<salsa::Snapshot<DB>> {
+        Snap(self.0.snapshot())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::mock_analysis::MockAnalysis;
+
+    use super::PrimeCachesProgress;
+
+    #[test]
+    fn primes_all_crates_in_parallel() {
+        let analysis = MockAnalysis::with_files(
+            r#"
+//- /main.rs
+fn main() {}
+//- /a/lib.rs
+pub struct A;
+//- /b/lib.rs
+pub struct B;
+"#,
+        )
+        .analysis();
+        let started = Mutex::new(Vec::new());
+        analysis
+            .prime_crate_def_maps(2, |progress| match progress {
+                PrimeCachesProgress::StartedOnCrate { on_crate, n_total, .. } => {
+                    assert_eq!(n_total, 3);
+                    started.lock().unwrap().push(on_crate);
+                }
+                PrimeCachesProgress::Started | PrimeCachesProgress::Finished => (),
+            })
+            .unwrap();
+        let mut started = started.into_inner().unwrap();
+        started.sort();
+        assert_eq!(started, vec!["", "a", "b"]);
+    }
+}
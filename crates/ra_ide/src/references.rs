@@ -26,7 +26,7 @@ use ra_syntax::{
 
 use crate::{display::TryToNav, FilePosition, FileRange, NavigationTarget, RangeInfo};
 
-pub(crate) use self::rename::rename;
+pub(crate) use self::rename::{rename, will_rename_file};
 
 pub use ra_ide_db::search::{Reference, ReferenceAccess, ReferenceKind};
 
@@ -99,7 +99,7 @@ pub(crate) fn find_all_refs(
         (Some(name), ReferenceKind::StructLiteral)
     } else {
         (
-            sema.find_node_at_offset_with_descend::<ast::Name>(&syntax, position.offset),
+            sema.find_node_at_offset_with_descend_first::<ast::Name>(&syntax, position.offset),
             ReferenceKind::Other,
         )
     };
@@ -135,7 +135,7 @@ fn find_name(
         return Some(RangeInfo::new(range, def));
     }
     let name_ref =
-        sema.find_node_at_offset_with_descend::<ast::NameRef>(&syntax, position.offset)?;
+        sema.find_node_at_offset_with_descend_first::<ast::NameRef>(&syntax, position.offset)?;
     let def = classify_name_ref(sema, &name_ref)?.definition();
     let range = name_ref.syntax().text_range();
     Some(RangeInfo::new(range, def))
@@ -169,13 +169,13 @@ fn get_struct_def_name_for_struct_literal_search(
         if right.kind() != SyntaxKind::L_CURLY && right.kind() != SyntaxKind::L_PAREN {
             return None;
         }
-        if let Some(name) =
-            sema.find_node_at_offset_with_descend::<ast::Name>(&syntax, left.text_range().start())
+        if let Some(name) = sema
+            .find_node_at_offset_with_descend_first::<ast::Name>(&syntax, left.text_range().start())
         {
             return name.syntax().ancestors().find_map(ast::StructDef::cast).and_then(|l| l.name());
         }
         if sema
-            .find_node_at_offset_with_descend::<ast::TypeParamList>(
+            .find_node_at_offset_with_descend_first::<ast::TypeParamList>(
                 &syntax,
                 left.text_range().start(),
             )
@@ -190,7 +190,7 @@ fn get_struct_def_name_for_struct_literal_search(
 #[cfg(test)]
 mod tests {
     use crate::{
-        mock_analysis::{analysis_and_position, MockAnalysis},
+        mock_analysis::{analysis_and_position, with_identity_proc_macros, MockAnalysis},
         Declaration, Reference, ReferenceSearchResult, SearchScope,
     };
 
@@ -651,6 +651,28 @@ fn main() {
         );
     }
 
+    #[test]
+    fn test_find_all_refs_in_attr_macro_item() {
+        let code = r#"
+//- /main.rs
+#[proc::identity]
+fn foo() {
+    let x<|> = 92;
+    x + proc::identity_fn!(x);
+}
+//- /proc/lib.rs
+"#;
+        let (mock, position) = MockAnalysis::with_files_and_position(code);
+        let mut host = mock.analysis_host();
+        with_identity_proc_macros(&mut host, "proc");
+        let refs = host.analysis().find_all_refs(position, None).unwrap().unwrap();
+        check_result(
+            refs,
+            "x BIND_PAT FileId(1) 37..38 Other",
+            &["FileId(1) 49..50 Other Read", "FileId(1) 72..73 Other Read"],
+        );
+    }
+
     fn get_all_refs(ra_fixture: &str) -> ReferenceSearchResult {
         let (analysis, position) = analysis_and_position(ra_fixture);
         analysis.find_all_refs(position, None).unwrap().unwrap()
@@ -1,13 +1,13 @@
 //! FIXME: write short doc here
 
 use hir::{Module, ModuleDef, ModuleSource, Semantics};
-use ra_db::SourceDatabaseExt;
+use ra_db::{FileId, SourceDatabaseExt};
 use ra_ide_db::{
     defs::{classify_name, classify_name_ref, Definition, NameClass, NameRefClass},
     RootDatabase,
 };
 use ra_syntax::{
-    algo::find_node_at_offset, ast, ast::NameOwner, ast::TypeAscriptionOwner,
+    algo::find_node_at_offset, ast, ast::AttrsOwner, ast::NameOwner, ast::TypeAscriptionOwner,
     lex_single_valid_syntax_kind, match_ast, AstNode, SyntaxKind, SyntaxNode, SyntaxToken,
 };
 use ra_text_edit::TextEdit;
@@ -26,23 +26,97 @@ pub(crate) fn rename(
 ) -> Option<RangeInfo<SourceChange>> {
     let sema = Semantics::new(db);
 
-    match lex_single_valid_syntax_kind(new_name)? {
-        SyntaxKind::IDENT | SyntaxKind::UNDERSCORE => (),
-        SyntaxKind::SELF_KW => return rename_to_self(&sema, position),
+    let res = match lex_single_valid_syntax_kind(new_name)? {
+        SyntaxKind::IDENT | SyntaxKind::UNDERSCORE => rename_any(&sema, position, new_name)?,
+        SyntaxKind::SELF_KW => rename_to_self(&sema, position)?,
         _ => return None,
+    };
+
+    // Libraries are read-only, renaming something defined in them would
+    // break the code using it.
+    let edits_library = res.info.source_file_edits.iter().any(|it| is_library_file(db, it.file_id));
+    if edits_library {
+        return None;
     }
+    Some(res)
+}
 
+fn rename_any(
+    sema: &Semantics<RootDatabase>,
+    position: FilePosition,
+    new_name: &str,
+) -> Option<RangeInfo<SourceChange>> {
     let source_file = sema.parse(position.file_id);
     let syntax = source_file.syntax();
-    if let Some(module) = find_module_at_offset(&sema, position, syntax) {
-        rename_mod(&sema, position, module, new_name)
+    if let Some(module) = find_module_at_offset(sema, position, syntax) {
+        rename_mod(sema, position, module, new_name)
     } else if let Some(self_token) =
         syntax.token_at_offset(position.offset).find(|t| t.kind() == SyntaxKind::SELF_KW)
     {
-        rename_self_to_param(&sema, position, self_token, new_name)
+        rename_self_to_param(sema, position, self_token, new_name)
     } else {
-        rename_reference(&sema, position, new_name)
+        rename_reference(sema, position, new_name)
+    }
+}
+
+fn is_library_file(db: &RootDatabase, file_id: FileId) -> bool {
+    db.source_root(db.file_source_root(file_id)).is_library
+}
+
+/// Returns the edits which keep the module tree intact when the file of a module
+/// is renamed to `new_name_stem` (the new file name without the extension).
+///
+/// Moving the file itself is left to the client.
+pub(crate) fn will_rename_file(
+    db: &RootDatabase,
+    file_id: FileId,
+    new_name_stem: &str,
+) -> Option<SourceChange> {
+    let sema = Semantics::new(db);
+    let module = sema.to_module_def(file_id)?;
+    let decl = module.declaration_source(db)?;
+    let decl_file_id = decl.file_id.original_file(db);
+
+    // A module with a `#[path]` attribute keeps its name, only the path changes.
+    let path_attr =
+        decl.value.attrs().filter(|attr| attr.simple_name().as_deref() == Some("path")).find_map(
+            |attr| match attr.input()? {
+                ast::AttrInput::Literal(lit) => Some(lit),
+                _ => None,
+            },
+        );
+    if let Some(lit) = path_attr {
+        let text = lit.syntax().text().to_string();
+        let path = text.trim_matches('"');
+        let (dir, file_name) = match path.rfind('/') {
+            Some(idx) => path.split_at(idx + 1),
+            None => ("", path),
+        };
+        let extension = file_name.rfind('.').map_or("", |idx| &file_name[idx..]);
+        let new_path = format!("\"{}{}{}\"", dir, new_name_stem, extension);
+        let edit = SourceFileEdit {
+            file_id: decl_file_id,
+            edit: TextEdit::replace(lit.syntax().text_range(), new_path),
+        };
+        return Some(SourceChange::from_edits(vec![edit], Vec::new()));
     }
+
+    match lex_single_valid_syntax_kind(new_name_stem)? {
+        SyntaxKind::IDENT | SyntaxKind::UNDERSCORE => (),
+        _ => return None,
+    }
+
+    let name = decl.value.name()?;
+    let mut source_file_edits = vec![SourceFileEdit {
+        file_id: decl_file_id,
+        edit: TextEdit::replace(name.syntax().text_range(), new_name_stem.into()),
+    }];
+    let refs = Definition::ModuleDef(module.into()).find_usages(&sema, None);
+    source_file_edits.extend(
+        refs.into_iter().map(|reference| source_edit_from_reference(reference, new_name_stem)),
+    );
+
+    Some(SourceChange::from_edits(source_file_edits, Vec::new()))
 }
 
 fn find_module_at_offset(
@@ -275,11 +349,18 @@ mod tests {
     use stdx::trim_indent;
     use test_utils::{assert_eq_text, mark};
 
-    use crate::{mock_analysis::analysis_and_position, FileId};
+    use crate::{
+        mock_analysis::{analysis_and_position, with_identity_proc_macros, MockAnalysis},
+        Analysis, FileId, FilePosition,
+    };
 
     fn check(new_name: &str, ra_fixture_before: &str, ra_fixture_after: &str) {
-        let ra_fixture_after = &trim_indent(ra_fixture_after);
         let (analysis, position) = analysis_and_position(ra_fixture_before);
+        check_in(analysis, position, new_name, ra_fixture_after)
+    }
+
+    fn check_in(analysis: Analysis, position: FilePosition, new_name: &str, after: &str) {
+        let ra_fixture_after = &trim_indent(after);
         let source_change = analysis.rename(position, new_name).unwrap();
         let mut text_edit_builder = TextEditBuilder::default();
         let mut file_id: Option<FileId> = None;
@@ -302,6 +383,13 @@ mod tests {
         expect.assert_debug_eq(&source_change)
     }
 
+    /// Renames the file with the cursor.
+    fn check_file_rename(new_name_stem: &str, ra_fixture: &str, expect: Expect) {
+        let (analysis, position) = analysis_and_position(ra_fixture);
+        let source_change = analysis.will_rename_file(position.file_id, new_name_stem).unwrap();
+        expect.assert_debug_eq(&source_change)
+    }
+
     #[test]
     fn test_rename_to_underscore() {
         check("_", r#"fn main() { let i<|> = 1; }"#, r#"fn main() { let _ = 1; }"#);
@@ -391,6 +479,56 @@ fn main() {
         );
     }
 
+    #[test]
+    fn test_rename_in_dbg_macro() {
+        check(
+            "y",
+            r#"
+macro_rules! dbg { ($e:expr) => { match $e { tmp => tmp } } }
+fn main() {
+    let x = 92;
+    dbg!(x<|>);
+}
+"#,
+            r#"
+macro_rules! dbg { ($e:expr) => { match $e { tmp => tmp } } }
+fn main() {
+    let y = 92;
+    dbg!(y);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_rename_in_attr_macro_item() {
+        let (mock, position) = MockAnalysis::with_files_and_position(
+            r#"
+//- /main.rs
+#[proc::identity]
+fn foo() {
+    let x = 92;
+    x<|> + 1;
+}
+//- /proc/lib.rs
+"#,
+        );
+        let mut host = mock.analysis_host();
+        with_identity_proc_macros(&mut host, "proc");
+        check_in(
+            host.analysis(),
+            position,
+            "y",
+            r#"
+#[proc::identity]
+fn foo() {
+    let y = 92;
+    y + 1;
+}
+"#,
+        );
+    }
+
     #[test]
     fn test_rename_for_macro_define_fn() {
         check(
@@ -1007,4 +1145,121 @@ impl Foo {
 "#,
         );
     }
+
+    #[test]
+    fn test_will_rename_file() {
+        check_file_rename(
+            "bar2",
+            r#"
+//- /lib.rs
+mod bar;
+use bar::Foo;
+fn f(_: crate::bar::Foo) {}
+
+//- /bar.rs
+<|>pub struct Foo;
+"#,
+            expect![[r#"
+                Some(
+                    SourceChange {
+                        source_file_edits: [
+                            SourceFileEdit {
+                                file_id: FileId(
+                                    1,
+                                ),
+                                edit: TextEdit {
+                                    indels: [
+                                        Indel {
+                                            insert: "bar2",
+                                            delete: 4..7,
+                                        },
+                                    ],
+                                },
+                            },
+                            SourceFileEdit {
+                                file_id: FileId(
+                                    1,
+                                ),
+                                edit: TextEdit {
+                                    indels: [
+                                        Indel {
+                                            insert: "bar2",
+                                            delete: 13..16,
+                                        },
+                                    ],
+                                },
+                            },
+                            SourceFileEdit {
+                                file_id: FileId(
+                                    1,
+                                ),
+                                edit: TextEdit {
+                                    indels: [
+                                        Indel {
+                                            insert: "bar2",
+                                            delete: 38..41,
+                                        },
+                                    ],
+                                },
+                            },
+                        ],
+                        file_system_edits: [],
+                        is_snippet: false,
+                    },
+                )
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_will_rename_file_with_path_attr() {
+        check_file_rename(
+            "bar2",
+            r#"
+//- /lib.rs
+#[path = "dir/bar.rs"]
+mod bar;
+
+//- /dir/bar.rs
+<|>pub struct Foo;
+"#,
+            expect![[r#"
+                Some(
+                    SourceChange {
+                        source_file_edits: [
+                            SourceFileEdit {
+                                file_id: FileId(
+                                    1,
+                                ),
+                                edit: TextEdit {
+                                    indels: [
+                                        Indel {
+                                            insert: "\"dir/bar2.rs\"",
+                                            delete: 9..21,
+                                        },
+                                    ],
+                                },
+                            },
+                        ],
+                        file_system_edits: [],
+                        is_snippet: false,
+                    },
+                )
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_will_rename_crate_root() {
+        check_file_rename(
+            "main2",
+            r#"
+//- /main.rs
+<|>fn main() {}
+"#,
+            expect![[r#"
+                None
+            "#]],
+        );
+    }
 }
@@ -9,7 +9,11 @@ use ra_syntax::{
     match_ast, SyntaxNode,
 };
 
-use crate::{display::ToNav, FileId, NavigationTarget};
+use crate::{
+    display::ToNav,
+    doc_code_blocks::{fence_attributes, is_doc_test},
+    FileId, NavigationTarget,
+};
 
 #[derive(Debug, Clone)]
 pub struct Runnable {
@@ -104,6 +108,13 @@ pub(crate) fn runnable(
         match item {
             ast::FnDef(it) => runnable_fn(sema, it, file_id),
             ast::Module(it) => runnable_mod(sema, it, file_id),
+            ast::StructDef(it) => runnable_doctest(sema, it, file_id),
+            ast::EnumDef(it) => runnable_doctest(sema, it, file_id),
+            ast::UnionDef(it) => runnable_doctest(sema, it, file_id),
+            ast::TraitDef(it) => runnable_doctest(sema, it, file_id),
+            ast::TypeAliasDef(it) => runnable_doctest(sema, it, file_id),
+            ast::ConstDef(it) => runnable_doctest(sema, it, file_id),
+            ast::StaticDef(it) => runnable_doctest(sema, it, file_id),
             _ => None,
         }
     }
@@ -160,16 +171,14 @@ fn runnable_fn(
             RunnableKind::Test { test_id, attr }
         } else if fn_def.has_atom_attr("bench") {
             RunnableKind::Bench { test_id }
-        } else if has_doc_test(&fn_def) {
+        } else if has_runnable_doc_test(&fn_def) {
             RunnableKind::DocTest { test_id }
         } else {
             return None;
         }
     };
 
-    let attrs = Attrs::from_attrs_owner(sema.db, InFile::new(HirFileId::from(file_id), &fn_def));
-    let cfg_exprs =
-        attrs.by_key("cfg").tt_values().map(|subtree| ra_cfg::parse_cfg(subtree)).collect();
+    let cfg_exprs = cfg_exprs(sema, file_id, &fn_def);
 
     let nav = if let RunnableKind::DocTest { .. } = kind {
         NavigationTarget::from_doc_commented(
@@ -212,8 +221,48 @@ fn has_test_related_attribute(fn_def: &ast::FnDef) -> bool {
         .any(|attribute_text| attribute_text.contains("test"))
 }
 
-fn has_doc_test(fn_def: &ast::FnDef) -> bool {
-    fn_def.doc_comment_text().map_or(false, |comment| comment.contains("```"))
+/// Checks whether the doc comment contains a code block which is tested by
+/// `cargo test --doc`.
+fn has_runnable_doc_test(owner: &dyn DocCommentsOwner) -> bool {
+    owner.doc_comment_text().map_or(false, |doc| {
+        let mut in_code_block = false;
+        for line in doc.lines() {
+            if let Some(attributes) = fence_attributes(line) {
+                in_code_block = !in_code_block;
+                if in_code_block && is_doc_test(attributes) {
+                    return true;
+                }
+            }
+        }
+        false
+    })
+}
+
+fn runnable_doctest<N>(sema: &Semantics<RootDatabase>, item: N, file_id: FileId) -> Option<Runnable>
+where
+    N: NameOwner + DocCommentsOwner + AttrsOwner,
+{
+    if !has_runnable_doc_test(&item) {
+        return None;
+    }
+    let name = item.name()?.text().to_string();
+    let module = sema.scope(item.syntax()).module()?;
+    let path = module
+        .path_to_root(sema.db)
+        .into_iter()
+        .rev()
+        .filter_map(|it| it.name(sema.db))
+        .map(|name| name.to_string())
+        .chain(std::iter::once(name))
+        .join("::");
+
+    let cfg_exprs = cfg_exprs(sema, file_id, &item);
+    let nav = NavigationTarget::from_doc_commented(
+        sema.db,
+        InFile::new(file_id.into(), &item),
+        InFile::new(file_id.into(), &item),
+    );
+    Some(Runnable { nav, kind: RunnableKind::DocTest { test_id: TestId::Path(path) }, cfg_exprs })
 }
 
 fn runnable_mod(
@@ -221,15 +270,9 @@ fn runnable_mod(
     module: ast::Module,
     file_id: FileId,
 ) -> Option<Runnable> {
-    let has_test_function = module
-        .item_list()?
-        .items()
-        .filter_map(|it| match it {
-            ast::ModuleItem::FnDef(it) => Some(it),
-            _ => None,
-        })
-        .any(|f| has_test_related_attribute(&f));
-    if !has_test_function {
+    let cfg_exprs = cfg_exprs(sema, file_id, &module);
+    let is_cfg_test = cfg_exprs.contains(&CfgExpr::Atom("test".into()));
+    if !has_test_function(&module, is_cfg_test) {
         return None;
     }
     let module_def = sema.to_def(&module)?;
@@ -241,14 +284,32 @@ fn runnable_mod(
         .filter_map(|it| it.name(sema.db))
         .join("::");
 
-    let attrs = Attrs::from_attrs_owner(sema.db, InFile::new(HirFileId::from(file_id), &module));
-    let cfg_exprs =
-        attrs.by_key("cfg").tt_values().map(|subtree| ra_cfg::parse_cfg(subtree)).collect();
-
     let nav = module_def.to_nav(sema.db);
     Some(Runnable { nav, kind: RunnableKind::TestMod { path }, cfg_exprs })
 }
 
+/// Checks whether the module contains a test function. With `recursive`,
+/// test functions of the nested inline modules are taken into account too,
+/// which is what we want for `#[cfg(test)] mod tests`.
+fn has_test_function(module: &ast::Module, recursive: bool) -> bool {
+    module.item_list().map_or(false, |item_list| {
+        item_list.items().any(|item| match item {
+            ast::ModuleItem::FnDef(it) => has_test_related_attribute(&it),
+            ast::ModuleItem::Module(it) => recursive && has_test_function(&it, true),
+            _ => false,
+        })
+    })
+}
+
+fn cfg_exprs(
+    sema: &Semantics<RootDatabase>,
+    file_id: FileId,
+    owner: &dyn AttrsOwner,
+) -> Vec<CfgExpr> {
+    let attrs = Attrs::from_attrs_owner(sema.db, InFile::new(HirFileId::from(file_id), owner));
+    attrs.by_key("cfg").tt_values().map(|subtree| ra_cfg::parse_cfg(subtree)).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use expect::{expect, Expect};
@@ -827,4 +888,196 @@ mod test_mod {
             "#]],
         );
     }
+
+    #[test]
+    fn test_runnables_doc_test_on_items() {
+        check(
+            r#"
+//- /lib.rs
+<|>
+mod foo {
+    /// ```
+    /// let x = foo::Bar;
+    /// ```
+    struct Bar;
+
+    /// ```should_panic
+    /// panic!();
+    /// ```
+    trait Baz {}
+
+    /// ```text
+    /// not rust
+    /// ```
+    const QUX: i32 = 92;
+
+    /// ```ignore
+    /// let x = 5;
+    /// ```
+    fn ignored() {}
+
+    /// ~~~no_run
+    /// loop {}
+    /// ~~~
+    fn tilde() {}
+}
+"#,
+            &[&DOCTEST, &DOCTEST, &DOCTEST],
+            expect![[r#"
+                [
+                    Runnable {
+                        nav: NavigationTarget {
+                            file_id: FileId(
+                                1,
+                            ),
+                            full_range: 15..76,
+                            name: "Bar",
+                            kind: STRUCT_DEF,
+                            focus_range: None,
+                            container_name: None,
+                            description: None,
+                            docs: None,
+                        },
+                        kind: DocTest {
+                            test_id: Path(
+                                "foo::Bar",
+                            ),
+                        },
+                        cfg_exprs: [],
+                    },
+                    Runnable {
+                        nav: NavigationTarget {
+                            file_id: FileId(
+                                1,
+                            ),
+                            full_range: 82..148,
+                            name: "Baz",
+                            kind: TRAIT_DEF,
+                            focus_range: None,
+                            container_name: None,
+                            description: None,
+                            docs: None,
+                        },
+                        kind: DocTest {
+                            test_id: Path(
+                                "foo::Baz",
+                            ),
+                        },
+                        cfg_exprs: [],
+                    },
+                    Runnable {
+                        nav: NavigationTarget {
+                            file_id: FileId(
+                                1,
+                            ),
+                            full_range: 295..354,
+                            name: "tilde",
+                            kind: FN_DEF,
+                            focus_range: None,
+                            container_name: None,
+                            description: None,
+                            docs: None,
+                        },
+                        kind: DocTest {
+                            test_id: Path(
+                                "foo::tilde",
+                            ),
+                        },
+                        cfg_exprs: [],
+                    },
+                ]
+            "#]],
+        );
+    }
+
+    #[test]
+    fn test_runnables_cfg_test_module() {
+        check(
+            r#"
+//- /lib.rs crate:foo cfg:test
+<|>
+#[cfg(test)]
+mod tests {
+    mod inner {
+        #[test]
+        fn test_foo1() {}
+    }
+}
+"#,
+            &[&TEST, &TEST, &TEST],
+            expect![[r#"
+                [
+                    Runnable {
+                        nav: NavigationTarget {
+                            file_id: FileId(
+                                1,
+                            ),
+                            full_range: 1..91,
+                            name: "tests",
+                            kind: MODULE,
+                            focus_range: Some(
+                                18..23,
+                            ),
+                            container_name: None,
+                            description: None,
+                            docs: None,
+                        },
+                        kind: TestMod {
+                            path: "tests",
+                        },
+                        cfg_exprs: [
+                            Atom(
+                                "test",
+                            ),
+                        ],
+                    },
+                    Runnable {
+                        nav: NavigationTarget {
+                            file_id: FileId(
+                                1,
+                            ),
+                            full_range: 30..89,
+                            name: "inner",
+                            kind: MODULE,
+                            focus_range: Some(
+                                34..39,
+                            ),
+                            container_name: None,
+                            description: None,
+                            docs: None,
+                        },
+                        kind: TestMod {
+                            path: "tests::inner",
+                        },
+                        cfg_exprs: [],
+                    },
+                    Runnable {
+                        nav: NavigationTarget {
+                            file_id: FileId(
+                                1,
+                            ),
+                            full_range: 50..83,
+                            name: "test_foo1",
+                            kind: FN_DEF,
+                            focus_range: Some(
+                                69..78,
+                            ),
+                            container_name: None,
+                            description: None,
+                            docs: None,
+                        },
+                        kind: Test {
+                            test_id: Path(
+                                "tests::inner::test_foo1",
+                            ),
+                            attr: TestAttr {
+                                ignore: false,
+                            },
+                        },
+                        cfg_exprs: [],
+                    },
+                ]
+            "#]],
+        );
+    }
 }
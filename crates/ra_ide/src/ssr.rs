@@ -19,9 +19,21 @@ use ra_ssr::{MatchFinder, SsrError, SsrRule};
 // | Constraint    | Restricts placeholder
 //
 // | kind(literal) | Is a literal (e.g. `42` or `"forty two"`)
+// | kind(expr)    | Is an expression
+// | kind(ty)      | Is a type
+// | kind(pat)     | Is a pattern
+// | kind(path)    | Is a path, e.g. `foo::bar`
+// | path(a::b)    | Is a path that resolves to the item `a::b`, relative to the crate root
+// | in_macro      | Comes from the expansion of a macro call
 // | not(a)        | Negates the constraint `a`
 // |===
 //
+// Paths in the search pattern that don't contain placeholders also match code that resolves to
+// the same item, however it's spelled. A call to a method by its path, like `Foo::bar($s, $a)`,
+// matches method calls like `x.bar(1)` that resolve to it, and vice versa. When the receiver was
+// implicitly referenced or dereferenced, the replacement spells that out unless the placeholder is
+// used as a method call receiver again.
+//
 // Available via the command `rust-analyzer.ssr`.
 //
 // ```rust
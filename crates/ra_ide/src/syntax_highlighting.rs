@@ -5,6 +5,7 @@ mod injection;
 mod tests;
 
 use hir::{Name, Semantics};
+use ra_db::CheckCanceled;
 use ra_ide_db::{
     defs::{classify_name, classify_name_ref, Definition, NameClass, NameRefClass},
     RootDatabase,
@@ -21,7 +22,7 @@ use rustc_hash::FxHashMap;
 use crate::FileId;
 
 use ast::FormatSpecifier;
-pub(crate) use html::highlight_as_html;
+pub(crate) use html::{highlight_as_html, highlight_as_html_page};
 pub use tags::{Highlight, HighlightModifier, HighlightModifiers, HighlightTag};
 
 #[derive(Debug, Clone)]
@@ -40,6 +41,9 @@ pub struct HighlightedRange {
 //
 // The general rule is that a reference to an entity gets colored the same way as the entity itself.
 // We also give special modifier for `mut` and `&mut` local variables.
+// Operations which require `unsafe` (calls to unsafe functions, raw pointer dereferences, accesses to union fields and
+// `static mut` items) get the `unsafe` modifier, and non-`Copy` receivers moved into a method taking `self` get the
+// `consuming` modifier.
 pub(crate) fn highlight(
     db: &RootDatabase,
     file_id: FileId,
@@ -80,6 +84,12 @@ pub(crate) fn highlight(
             WalkEvent::Leave(_) => stack.pop(),
         };
 
+        // Walking a large file takes a while even if all the queries are
+        // already computed, so bail out as soon as a new edit arrives.
+        if let WalkEvent::Enter(NodeOrToken::Node(_)) = &event {
+            db.check_canceled();
+        }
+
         let event_range = match &event {
             WalkEvent::Enter(it) => it.text_range(),
             WalkEvent::Leave(it) => it.text_range(),
@@ -160,7 +170,7 @@ pub(crate) fn highlight(
 
             // Check if macro takes a format string and remember it for highlighting later.
             // The macros that accept a format string expand to a compiler builtin macros
-            // `format_args` and `format_args_nl`.
+            // `format_args` and `format_args_nl`. The asm templates use the same placeholders.
             if let Some(name) = parent
                 .parent()
                 .and_then(ast::MacroCall::cast)
@@ -169,7 +179,7 @@ pub(crate) fn highlight(
                 .and_then(|s| s.name_ref())
             {
                 match name.text().as_str() {
-                    "format_args" | "format_args_nl" => {
+                    "format_args" | "format_args_nl" | "asm" | "global_asm" => {
                         format_string = parent
                             .children_with_tokens()
                             .filter(|t| t.kind() != WHITESPACE)
@@ -436,6 +446,7 @@ fn highlight_format_specifier(kind: FormatSpecifier) -> Option<HighlightTag> {
         | FormatSpecifier::QuestionMark => HighlightTag::FormatSpecifier,
         FormatSpecifier::Integer | FormatSpecifier::Zero => HighlightTag::NumericLiteral,
         FormatSpecifier::Identifier => HighlightTag::Local,
+        FormatSpecifier::Escape => HighlightTag::EscapeSequence,
     })
 }
 
@@ -508,7 +519,7 @@ fn highlight_element(
                                 binding_hash = Some(calc_binding_hash(&name, *shadow_count))
                             }
                         };
-                        highlight_name(db, def)
+                        highlight_name(db, def) | highlight_name_ref_use(sema, &name_ref, def)
                     }
                     NameRefClass::FieldShorthand { .. } => HighlightTag::Field.into(),
                 },
@@ -642,6 +653,85 @@ fn highlight_name(db: &RootDatabase, def: Definition) -> Highlight {
     .into()
 }
 
+/// Computes the modifiers that depend on how a resolved reference is used,
+/// rather than on the referenced definition itself.
+fn highlight_name_ref_use(
+    sema: &Semantics<RootDatabase>,
+    name_ref: &ast::NameRef,
+    def: Definition,
+) -> HighlightModifiers {
+    let db = sema.db;
+    let mut modifiers = HighlightModifiers::default();
+    match def {
+        Definition::ModuleDef(hir::ModuleDef::Function(func)) => {
+            let method_call = name_ref.syntax().parent().and_then(ast::MethodCallExpr::cast);
+            if let Some(receiver) = method_call.and_then(|it| it.expr()) {
+                if is_consumed_by(sema, &receiver, func) {
+                    modifiers |= HighlightModifier::Consuming;
+                }
+            }
+        }
+        Definition::ModuleDef(hir::ModuleDef::Static(s)) => {
+            if s.is_mut(db) {
+                modifiers |= HighlightModifier::Unsafe;
+            }
+        }
+        Definition::Field(field) => {
+            if let hir::VariantDef::Union(_) = field.parent_def(db) {
+                let is_access = name_ref.syntax().parent().and_then(ast::FieldExpr::cast).is_some();
+                if is_access {
+                    modifiers |= HighlightModifier::Unsafe;
+                }
+            }
+        }
+        Definition::Local(_) => {
+            if let Some((method_call, func)) = receiver_of_method_call(sema, name_ref) {
+                if let Some(receiver) = method_call.expr() {
+                    match func.self_param(db).map(|it| it.access(db)) {
+                        Some(hir::Access::Exclusive) => modifiers |= HighlightModifier::Mutable,
+                        _ if is_consumed_by(sema, &receiver, func) => {
+                            modifiers |= HighlightModifier::Consuming
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+        _ => (),
+    }
+    modifiers
+}
+
+/// If `name_ref` is the whole receiver of a method call, returns the call and
+/// the method it resolves to.
+fn receiver_of_method_call(
+    sema: &Semantics<RootDatabase>,
+    name_ref: &ast::NameRef,
+) -> Option<(ast::MethodCallExpr, hir::Function)> {
+    let path_expr = name_ref.syntax().ancestors().find_map(ast::PathExpr::cast)?;
+    let method_call = ast::MethodCallExpr::cast(path_expr.syntax().parent()?)?;
+    if method_call.expr()?.syntax() != path_expr.syntax() {
+        return None;
+    }
+    let func = sema.resolve_method_call(&method_call)?;
+    Some((method_call, func))
+}
+
+fn is_consumed_by(
+    sema: &Semantics<RootDatabase>,
+    receiver: &ast::Expr,
+    func: hir::Function,
+) -> bool {
+    let db = sema.db;
+    match func.self_param(db).map(|it| it.access(db)) {
+        Some(hir::Access::Owned) => match sema.type_of_expr(receiver) {
+            Some(ty) => !ty.is_unknown() && !ty.is_copy(db),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
 fn highlight_name_by_syntax(name: ast::Name) -> Highlight {
     let default = HighlightTag::UnresolvedReference;
 
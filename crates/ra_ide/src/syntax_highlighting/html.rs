@@ -1,11 +1,46 @@
 //! Renders a bit of code as HTML.
 
 use ra_db::SourceDatabase;
-use ra_syntax::{AstNode, TextRange, TextSize};
+use ra_syntax::{ast, AstNode, SourceFile, TextRange, TextSize};
+use rustc_hash::FxHashMap;
 
-use crate::{syntax_highlighting::highlight, FileId, RootDatabase};
+use crate::{
+    goto_definition::goto_definition, syntax_highlighting::highlight, FileId, FilePosition,
+    RootDatabase,
+};
 
 pub(crate) fn highlight_as_html(db: &RootDatabase, file_id: FileId, rainbow: bool) -> String {
+    let mut buf = String::new();
+    buf.push_str(&STYLE);
+    buf.push_str(&render(db, file_id, rainbow, None));
+    buf
+}
+
+/// Renders the file as a standalone HTML page. The names of definitions are
+/// anchors, and references link to them if their file is in `links`, which
+/// maps files to the URLs of their pages.
+pub(crate) fn highlight_as_html_page(
+    db: &RootDatabase,
+    file_id: FileId,
+    rainbow: bool,
+    links: &FxHashMap<FileId, String>,
+) -> String {
+    let mut buf = String::new();
+    buf.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">");
+    buf.push_str(&STYLE);
+    buf.push_str(LINK_STYLE);
+    buf.push_str("</head>\n<body>\n");
+    buf.push_str(&render(db, file_id, rainbow, Some(links)));
+    buf.push_str("\n</body>\n</html>\n");
+    buf
+}
+
+fn render(
+    db: &RootDatabase,
+    file_id: FileId,
+    rainbow: bool,
+    links: Option<&FxHashMap<FileId, String>>,
+) -> String {
     let parse = db.parse(file_id);
 
     fn rainbowify(seed: u64) -> String {
@@ -23,7 +58,6 @@ pub(crate) fn highlight_as_html(db: &RootDatabase, file_id: FileId, rainbow: boo
     let text = parse.tree().syntax().to_string();
     let mut prev_pos = TextSize::from(0);
     let mut buf = String::new();
-    buf.push_str(&STYLE);
     buf.push_str("<pre><code>");
     for range in &ranges {
         if range.range.start() > prev_pos {
@@ -40,7 +74,16 @@ pub(crate) fn highlight_as_html(db: &RootDatabase, file_id: FileId, rainbow: boo
             }
             _ => "".into(),
         };
-        buf.push_str(&format!("<span class=\"{}\"{}>{}</span>", class, color, html_escape(curr)));
+        let (anchor, href) = match links {
+            Some(links) => link(db, &parse.tree(), file_id, range.range, links),
+            None => (None, None),
+        };
+        let id = anchor.map(|it| format!(" id=\"{}\"", u32::from(it))).unwrap_or_default();
+        let span = format!("<span class=\"{}\"{}{}>{}</span>", class, id, color, html_escape(curr));
+        match href {
+            Some(href) => buf.push_str(&format!("<a href=\"{}\">{}</a>", attr_escape(&href), span)),
+            None => buf.push_str(&span),
+        }
 
         prev_pos = range.range.end();
     }
@@ -52,11 +95,49 @@ pub(crate) fn highlight_as_html(db: &RootDatabase, file_id: FileId, rainbow: boo
     buf
 }
 
-//FIXME: like, real html escaping
+/// Returns the anchor of `range` if it is the name of a definition, and
+/// otherwise the URL of the definition it refers to.
+fn link(
+    db: &RootDatabase,
+    file: &SourceFile,
+    file_id: FileId,
+    range: TextRange,
+    links: &FxHashMap<FileId, String>,
+) -> (Option<TextSize>, Option<String>) {
+    let token = match file.syntax().token_at_offset(range.start()).right_biased() {
+        Some(it) if it.text_range() == range => it,
+        _ => return (None, None),
+    };
+    if ast::Name::can_cast(token.parent().kind()) {
+        return (Some(range.start()), None);
+    }
+    if !ast::NameRef::can_cast(token.parent().kind()) {
+        return (None, None);
+    }
+    let href = goto_definition(db, FilePosition { file_id, offset: range.start() })
+        .and_then(|it| it.info.into_iter().next())
+        .and_then(|nav| {
+            let url = links.get(&nav.file_id())?;
+            Some(format!("{}#{}", url, u32::from(nav.range().start())))
+        });
+    (None, href)
+}
+
 fn html_escape(text: &str) -> String {
-    text.replace("<", "&lt;").replace(">", "&gt;")
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
 }
 
+/// Escapes `text` for a double quoted attribute value.
+fn attr_escape(text: &str) -> String {
+    html_escape(text).replace('"', "&quot;")
+}
+
+const LINK_STYLE: &str = "<style>
+a                   { color: inherit; text-decoration: none; }
+a:hover             { text-decoration: underline; }
+</style>
+";
+
 const STYLE: &str = "
 <style>
 body                { margin: 0; }
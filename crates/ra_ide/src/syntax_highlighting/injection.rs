@@ -8,8 +8,9 @@ use ra_syntax::{ast, AstToken, SyntaxNode, SyntaxToken, TextRange, TextSize};
 use stdx::SepBy;
 
 use crate::{
-    call_info::ActiveParameter, Analysis, Highlight, HighlightModifier, HighlightTag,
-    HighlightedRange, RootDatabase,
+    call_info::ActiveParameter,
+    doc_code_blocks::{fence_attributes, is_rust_code_block},
+    Analysis, Highlight, HighlightModifier, HighlightTag, HighlightedRange, RootDatabase,
 };
 
 use super::HighlightedRangeStack;
@@ -56,10 +57,6 @@ pub(super) fn highlight_injection(
 /// Mapping from extracted documentation code to original code
 type RangesMap = BTreeMap<TextSize, TextSize>;
 
-const RUSTDOC_FENCE: &'static str = "```";
-const RUSTDOC_FENCE_TOKENS: &[&'static str] =
-    &["", "rust", "should_panic", "ignore", "no_run", "compile_fail", "edition2015", "edition2018"];
-
 /// Extracts Rust code from documentation comments as well as a mapping from
 /// the extracted source code back to the original source ranges.
 /// Lastly, a vector of new comment highlight ranges (spanning only the
@@ -84,13 +81,9 @@ pub(super) fn extract_doc_comments(
         .filter_map(|el| el.into_token().and_then(ast::Comment::cast))
         .filter(|comment| comment.kind().doc.is_some())
         .filter(|comment| {
-            if let Some(idx) = comment.text().find(RUSTDOC_FENCE) {
+            if let Some(attributes) = fence_attributes(comment.text()) {
                 is_codeblock = !is_codeblock;
-                // Check whether code is rust by inspecting fence guards
-                let guards = &comment.text()[idx + RUSTDOC_FENCE.len()..];
-                let is_rust =
-                    guards.split(',').all(|sub| RUSTDOC_FENCE_TOKENS.contains(&sub.trim()));
-                is_doctest = is_codeblock && is_rust;
+                is_doctest = is_codeblock && is_rust_code_block(attributes);
                 false
             } else {
                 is_doctest
@@ -60,7 +60,12 @@ pub enum HighlightModifier {
     Documentation,
     Injected,
     Mutable,
+    /// Used for operations that are only allowed inside `unsafe` (calls to
+    /// `unsafe fn`, raw pointer derefs, union field and `static mut` access).
     Unsafe,
+    /// Used for non-`Copy` values moved into a call, like the receiver of a
+    /// method taking `self`.
+    Consuming,
 }
 
 impl HighlightTag {
@@ -116,6 +121,7 @@ impl HighlightModifier {
         HighlightModifier::Injected,
         HighlightModifier::Mutable,
         HighlightModifier::Unsafe,
+        HighlightModifier::Consuming,
     ];
 
     fn as_str(self) -> &'static str {
@@ -127,6 +133,7 @@ impl HighlightModifier {
             HighlightModifier::Injected => "injected",
             HighlightModifier::Mutable => "mutable",
             HighlightModifier::Unsafe => "unsafe",
+            HighlightModifier::Consuming => "consuming",
         }
     }
 
@@ -183,6 +190,15 @@ impl ops::BitOrAssign<HighlightModifier> for Highlight {
     }
 }
 
+impl ops::BitOr<HighlightModifiers> for Highlight {
+    type Output = Highlight;
+
+    fn bitor(mut self, rhs: HighlightModifiers) -> Highlight {
+        self.modifiers.0 |= rhs.0;
+        self
+    }
+}
+
 impl ops::BitOr<HighlightModifier> for Highlight {
     type Output = Highlight;
 
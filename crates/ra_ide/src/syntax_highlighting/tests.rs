@@ -3,7 +3,10 @@ use std::fs;
 use expect::{expect_file, ExpectFile};
 use test_utils::project_dir;
 
-use crate::{mock_analysis::single_file, FileRange, TextRange};
+use crate::{
+    mock_analysis::{single_file, MockAnalysis},
+    FileRange, TextRange,
+};
 
 #[test]
 fn test_highlighting() {
@@ -25,6 +28,36 @@ impl Bar for Foo {
     }
 }
 
+impl Foo {
+    fn baz(mut self) -> i32 {
+        self.x
+    }
+
+    fn qux(&mut self) {
+        self.x = 0;
+    }
+}
+
+#[lang = "copy"]
+trait Copy {}
+
+#[derive(Copy)]
+struct FooCopy {
+    x: u32,
+}
+
+impl Copy for FooCopy {}
+
+impl FooCopy {
+    fn baz(self) -> u32 {
+        self.x
+    }
+
+    fn qux(&mut self) {
+        self.x = 0;
+    }
+}
+
 static mut STATIC_MUT: i32 = 0;
 
 fn foo<'a, T>() -> T {
@@ -74,6 +107,14 @@ fn main() {
     let Foo { x: z, y } = Foo { x: z, y };
 
     y;
+
+    let mut foo = Foo { x, y: x };
+    foo.qux();
+    foo.baz();
+
+    let mut copy = FooCopy { x };
+    copy.qux();
+    copy.baz();
 }
 
 enum Option<T> {
@@ -256,6 +297,35 @@ fn main() {
     );
 }
 
+#[test]
+fn test_asm_highlighting() {
+    check_highlighting(
+        r#"
+#[rustc_builtin_macro]
+macro_rules! asm {
+    ("assembly template", $(operands,)* $(options($(option),*))?) => { /* compiler built-in */ };
+}
+
+fn main() {
+    let x = 1u64;
+    let y: u64;
+    unsafe {
+        asm!(
+            "mov {0}, {x:e}",
+            "add {{}} {}, 1",
+            out(reg) y,
+            x = in(reg) x,
+            in("eax") 1,
+            options(nostack),
+        );
+    }
+}"#
+        .trim(),
+        expect_file!["crates/ra_ide/test_data/highlight_asm.html"],
+        false,
+    );
+}
+
 #[test]
 fn test_unsafe_highlighting() {
     check_highlighting(
@@ -268,13 +338,23 @@ impl HasUnsafeFn {
     unsafe fn unsafe_method(&self) {}
 }
 
+union Union {
+    a: u32,
+    b: f32,
+}
+
+static mut MUT_GLOBAL: u32 = 0;
+
 fn main() {
     let x = &5 as *const usize;
+    let u = Union { b: 0.0 };
     unsafe {
         unsafe_fn();
         HasUnsafeFn.unsafe_method();
         let y = *(x);
         let z = -x;
+        let a = u.a;
+        MUT_GLOBAL = a;
     }
 }
 "#
@@ -340,11 +420,21 @@ impl Foo {
     /// ```sh
     /// echo 1
     /// ```
+    ///
+    /// ~~~ignore-wasm32,edition2018
+    /// let baz = Foo::new().foo();
+    /// ~~~
     pub fn foo(&self) -> bool {
         true
     }
 }
 
+mod inner {
+    //! ```
+    //! let inner = 92;
+    //! ```
+}
+
 /// ```
 /// noop!(1);
 /// ```
@@ -360,6 +450,30 @@ macro_rules! noop {
     );
 }
 
+#[test]
+fn html_page_links_references_to_definitions() {
+    let mock = MockAnalysis::with_files(
+        r#"
+//- /main.rs
+mod foo;
+fn main() { foo::bar(); }
+//- /foo.rs
+pub fn bar() {}
+"#,
+    );
+    let main = mock.id_of("/main.rs");
+    let foo = mock.id_of("/foo.rs");
+    let analysis = mock.analysis();
+    let links = vec![(foo, "a&\"b.html".to_string())].into_iter().collect();
+
+    let html = analysis.highlight_as_html_page(main, false, &links).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(
+        html.contains(r#"<a href="a&amp;&quot;b.html#7"><span class="function">bar</span></a>"#)
+    );
+    assert!(html.contains(r#"<span class="function declaration" id="12">main</span>"#));
+}
+
 /// Highlights the code given by the `ra_fixture` argument, renders the
 /// result as HTML, and compares it with the HTML file given as `snapshot`.
 /// Note that the `snapshot` file is overwritten by the rendered HTML.
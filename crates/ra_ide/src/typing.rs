@@ -22,8 +22,8 @@ use ra_syntax::{
     algo::find_node_at_offset,
     ast::{self, AstToken},
     AstNode, SourceFile,
-    SyntaxKind::{FIELD_EXPR, METHOD_CALL_EXPR},
-    TextRange, TextSize,
+    SyntaxKind::{FIELD_EXPR, METHOD_CALL_EXPR, TYPE_ARG_LIST, TYPE_PARAM_LIST},
+    TextRange, TextSize, T,
 };
 
 use ra_text_edit::TextEdit;
@@ -39,8 +39,7 @@ pub(crate) const TRIGGER_CHARS: &str = ".=>";
 // Some features trigger on typing certain characters:
 //
 // - typing `let =` tries to smartly add `;` if `=` is followed by an existing expression
-// - Enter inside comments automatically inserts `///`
-// - typing `.` in a chain method call auto-indents
+// - typing `.` in a chain method call auto-indents to match the previous chain step
 pub(crate) fn on_char_typed(
     db: &RootDatabase,
     position: FilePosition,
@@ -105,8 +104,10 @@ fn on_dot_typed(file: &SourceFile, offset: TextSize) -> Option<TextEdit> {
     if !matches!(parent.kind(), FIELD_EXPR | METHOD_CALL_EXPR) {
         return None;
     }
-    let prev_indent = leading_indent(&parent)?;
-    let target_indent = format!("    {}", prev_indent);
+    let target_indent = match prev_chain_step_indent(&whitespace) {
+        Some(it) => it,
+        None => format!("    {}", leading_indent(&parent)?),
+    };
     let target_indent_len = TextSize::of(&target_indent);
     if current_indent_len == target_indent_len {
         return None;
@@ -115,15 +116,48 @@ fn on_dot_typed(file: &SourceFile, offset: TextSize) -> Option<TextEdit> {
     Some(TextEdit::replace(TextRange::new(offset - current_indent_len, offset), target_indent))
 }
 
-/// Adds a space after an arrow when `fn foo() { ... }` is turned into `fn foo() -> { ... }`
+/// If the line before `whitespace` is itself a step of a method chain (it
+/// starts with a `.`), returns the indent of that line.
+fn prev_chain_step_indent(whitespace: &ast::Whitespace) -> Option<String> {
+    let mut line = String::new();
+    let mut token = whitespace.syntax().prev_token()?;
+    loop {
+        let text = token.text();
+        if let Some(pos) = text.rfind('\n') {
+            let indent = &text[pos + 1..];
+            line.insert_str(0, indent);
+            break;
+        }
+        line.insert_str(0, text);
+        token = match token.prev_token() {
+            Some(it) => it,
+            None => break,
+        };
+    }
+    let step = line.trim_start();
+    if !step.starts_with('.') {
+        return None;
+    }
+    Some(line[..line.len() - step.len()].to_string())
+}
+
+/// Adds a space after an arrow when `fn foo() { ... }` is turned into `fn foo() -> { ... }`,
+/// or after the closing angle bracket of a generic list, like in `impl<T> Foo<T>{ ... }`.
 fn on_arrow_typed(file: &SourceFile, offset: TextSize) -> Option<TextEdit> {
-    let file_text = file.syntax().text();
-    assert_eq!(file_text.char_at(offset), Some('>'));
+    assert_eq!(file.syntax().text().char_at(offset), Some('>'));
     let after_arrow = offset + TextSize::of('>');
-    if file_text.char_at(after_arrow) != Some('{') {
+    let next = file.syntax().token_at_offset(after_arrow).right_biased()?;
+    if next.kind() != T!['{'] || next.text_range().start() != after_arrow {
         return None;
     }
-    if find_node_at_offset::<ast::RetType>(file.syntax(), offset).is_none() {
+    let closes_generics = file
+        .syntax()
+        .token_at_offset(offset)
+        .right_biased()
+        .filter(|it| it.kind() == T![>])
+        .map(|it| it.parent())
+        .map_or(false, |it| matches!(it.kind(), TYPE_ARG_LIST | TYPE_PARAM_LIST));
+    if !closes_generics && find_node_at_offset::<ast::RetType>(file.syntax(), offset).is_none() {
         return None;
     }
 
@@ -363,4 +397,33 @@ fn main() {
     fn adds_space_after_return_type() {
         type_char('>', "fn foo() -<|>{ 92 }", "fn foo() -> { 92 }")
     }
+
+    #[test]
+    fn indents_chain_call_like_previous_step() {
+        type_char(
+            '.',
+            r"
+            fn main() {
+                let x = xs
+                        .foo()
+                <|>
+            }
+            ",
+            r"
+            fn main() {
+                let x = xs
+                        .foo()
+                        .
+            }
+            ",
+        );
+    }
+
+    #[test]
+    fn adds_space_after_closing_generics() {
+        type_char('>', "impl<T> Foo<T<|>{}", "impl<T> Foo<T> {}");
+        type_char_noop('>', "impl<T<|> Foo<T> {}");
+        type_char('>', "fn foo() -> Vec<u32<|>{ vec![] }", "fn foo() -> Vec<u32> { vec![] }");
+        type_char_noop('>', "fn foo() { 1 <|>{ 2 } }");
+    }
 }
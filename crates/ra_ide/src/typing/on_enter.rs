@@ -1,5 +1,5 @@
-//! Handles the `Enter` key press. At the momently, this only continues
-//! comments, but should handle indent some time in the future as well.
+//! Handles the `Enter` key press. This continues comments and indents
+//! continuations of method chains.
 
 use ra_db::{FilePosition, SourceDatabase};
 use ra_ide_db::RootDatabase;
@@ -7,39 +7,116 @@ use ra_syntax::{
     ast::{self, AstToken},
     AstNode, SmolStr, SourceFile,
     SyntaxKind::*,
-    SyntaxToken, TextSize, TokenAtOffset,
+    SyntaxToken, TextSize, TokenAtOffset, T,
 };
 use ra_text_edit::TextEdit;
 
+// Feature: On Enter
+//
+// rust-analyzer can override kbd:[Enter] key to make it smarter:
+//
+// - kbd:[Enter] inside `///` and `//!` comments automatically continues the comment
+// - kbd:[Enter] inside `/* */` comments adds the leading `*`
+// - kbd:[Enter] after a dangling `.` indents the continuation of the method chain
+//
+// This action needs to be assigned to shortcut explicitly.
 pub(crate) fn on_enter(db: &RootDatabase, position: FilePosition) -> Option<TextEdit> {
     let parse = db.parse(position.file_id);
     let file = parse.tree();
-    let comment = file
-        .syntax()
-        .token_at_offset(position.offset)
-        .left_biased()
-        .and_then(ast::Comment::cast)?;
+    let token = file.syntax().token_at_offset(position.offset).left_biased()?;
 
+    if let Some(comment) = ast::Comment::cast(token.clone()) {
+        return on_enter_in_comment(&file, comment, position.offset);
+    }
+
+    if token.kind() == T![.] {
+        return on_enter_after_dot(token, position.offset);
+    }
+
+    None
+}
+
+fn on_enter_in_comment(
+    file: &SourceFile,
+    comment: ast::Comment,
+    offset: TextSize,
+) -> Option<TextEdit> {
     if comment.kind().shape.is_block() {
-        return None;
+        return on_enter_in_block_comment(file, comment, offset);
     }
 
     let prefix = comment.prefix();
     let comment_range = comment.syntax().text_range();
-    if position.offset < comment_range.start() + TextSize::of(prefix) {
+    if offset < comment_range.start() + TextSize::of(prefix) {
         return None;
     }
 
     // Continuing single-line non-doc comments (like this one :) ) is annoying
-    if prefix == "//" && comment_range.end() == position.offset && !followed_by_comment(&comment) {
+    if prefix == "//" && comment_range.end() == offset && !followed_by_comment(&comment) {
         return None;
     }
 
-    let indent = node_indent(&file, comment.syntax())?;
+    let indent = node_indent(file, comment.syntax())?;
     let inserted = format!("\n{}{} $0", indent, prefix);
-    let edit = TextEdit::insert(position.offset, inserted);
+    Some(TextEdit::insert(offset, inserted))
+}
+
+fn on_enter_in_block_comment(
+    file: &SourceFile,
+    comment: ast::Comment,
+    offset: TextSize,
+) -> Option<TextEdit> {
+    let text = comment.text();
+    let comment_range = comment.syntax().text_range();
+    if offset < comment_range.start() + TextSize::of(comment.prefix()) {
+        return None;
+    }
+    // An unterminated comment runs until the end of file, so the cursor is
+    // always inside of it.
+    if text.ends_with("*/") && offset > comment_range.end() - TextSize::of("*/") {
+        return None;
+    }
+
+    let indent = node_indent(file, comment.syntax())?;
+    let inserted = format!("\n{} * $0", indent);
+    Some(TextEdit::insert(offset, inserted))
+}
+
+fn on_enter_after_dot(dot: SyntaxToken, offset: TextSize) -> Option<TextEdit> {
+    if dot.text_range().end() != offset {
+        return None;
+    }
+    // Only handle the `.` dangling at the end of the line
+    match dot.next_token() {
+        Some(next) if next.kind() == WHITESPACE && next.text().contains('\n') => (),
+        Some(_) => return None,
+        None => (),
+    }
+
+    // Collect the line up to the cursor from the preceding tokens, this runs
+    // on every enter and shouldn't copy the whole file.
+    let mut line = String::new();
+    let mut token = Some(dot);
+    while let Some(it) = token {
+        let text = it.text().as_str();
+        match text.rfind('\n') {
+            Some(idx) => {
+                line.insert_str(0, &text[idx + 1..]);
+                break;
+            }
+            None => line.insert_str(0, text),
+        }
+        token = it.prev_token();
+    }
+    let indent = &line[..line.len() - line.trim_start().len()];
 
-    Some(edit)
+    // Lines which already are a continuation of the chain keep their indent
+    let inserted = if line.trim_start().starts_with('.') {
+        format!("\n{}$0", indent)
+    } else {
+        format!("\n{}    $0", indent)
+    };
+    Some(TextEdit::insert(offset, inserted))
 }
 
 fn followed_by_comment(comment: &ast::Comment) -> bool {
@@ -199,6 +276,99 @@ fn main() {
     // Fix me<|>
     let x = 1 + 1;
 }
+",
+        );
+    }
+
+    #[test]
+    fn continues_block_comment() {
+        do_check(
+            r"
+fn main() {
+    /* Fix<|> me */
+    let x = 1 + 1;
+}
+",
+            r"
+fn main() {
+    /* Fix
+     * $0 me */
+    let x = 1 + 1;
+}
+",
+        );
+
+        do_check(
+            r"
+/**<|>
+fn foo() {}
+",
+            r"
+/**
+ * $0
+fn foo() {}
+",
+        );
+    }
+
+    #[test]
+    fn does_not_continue_after_block_comment() {
+        do_check_noop(
+            r"
+fn main() {
+    /* Fix me */<|>
+    let x = 1 + 1;
+}
+",
+        );
+    }
+
+    #[test]
+    fn indents_after_dangling_dot() {
+        do_check(
+            r"
+fn main() {
+    let x = foo.<|>
+}
+",
+            r"
+fn main() {
+    let x = foo.
+        $0
+}
+",
+        );
+
+        do_check(
+            r"
+fn main() {
+    let x = foo
+        .bar().<|>
+}
+",
+            r"
+fn main() {
+    let x = foo
+        .bar().
+        $0
+}
+",
+        );
+
+        do_check(
+            r"const X: i32 = foo.<|>",
+            r"const X: i32 = foo.
+    $0",
+        );
+    }
+
+    #[test]
+    fn does_not_indent_after_non_dangling_dot() {
+        do_check_noop(
+            r"
+fn main() {
+    let x = foo.<|>bar();
+}
 ",
         );
     }
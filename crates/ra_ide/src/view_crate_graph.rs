@@ -0,0 +1,117 @@
+use ra_db::{CrateId, FileId, SourceDatabase, SourceDatabaseExt};
+use ra_ide_db::RootDatabase;
+use rustc_hash::FxHashSet;
+use stdx::format_to;
+
+// Feature: View Crate Graph
+//
+// Renders the currently loaded crate graph as an SVG graphic. Requires the `dot` tool, which
+// is part of graphviz, to be installed.
+//
+// Library crates are drawn with a dashed outline, sysroot crates can be hidden.
+//
+// |===
+// | Editor  | Action Name
+//
+// | VS Code | **Rust Analyzer: View Crate Graph**
+// |===
+
+/// Renders the crate graph in the GraphViz DOT format, skipping the crates
+/// whose root file is in `excluded_roots`. Edges are labeled with the name
+/// under which the dependency is imported.
+pub(crate) fn view_crate_graph(db: &RootDatabase, excluded_roots: &FxHashSet<FileId>) -> String {
+    let crate_graph = db.crate_graph();
+    let mut crates = crate_graph
+        .iter()
+        .filter(|&krate| !excluded_roots.contains(&crate_graph[krate].root_file_id))
+        .collect::<Vec<_>>();
+    crates.sort_by_key(|krate| krate.0);
+    let included = crates.iter().copied().collect::<FxHashSet<_>>();
+
+    let mut buf = String::from("digraph crate_graph {\n");
+    for &krate in crates.iter() {
+        let data = &crate_graph[krate];
+        let name = data.display_name.as_deref().unwrap_or("<unnamed>");
+        let source_root = db.source_root(db.file_source_root(data.root_file_id));
+        let style = if source_root.is_library { ", style=dashed" } else { "" };
+        format_to!(
+            buf,
+            "    {} [label=\"{} (#{})\"{}];\n",
+            node_id(krate),
+            escape(name),
+            krate.0,
+            style
+        );
+    }
+    for &krate in crates.iter() {
+        for dep in crate_graph[krate].dependencies.iter() {
+            if !included.contains(&dep.crate_id) {
+                continue;
+            }
+            format_to!(
+                buf,
+                "    {} -> {} [label=\"{}\"];\n",
+                node_id(krate),
+                node_id(dep.crate_id),
+                escape(&dep.name)
+            );
+        }
+    }
+    buf.push_str("}\n");
+    buf
+}
+
+fn node_id(krate: CrateId) -> String {
+    format!("crate{}", krate.0)
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use expect::expect;
+    use rustc_hash::FxHashSet;
+
+    use crate::mock_analysis::MockAnalysis;
+
+    #[test]
+    fn test_view_crate_graph() {
+        let mock = MockAnalysis::with_files(
+            r#"
+//- /main.rs
+use foo::Foo;
+//- /foo/lib.rs
+pub struct Foo;
+//- /bar/lib.rs
+pub struct Bar;
+"#,
+        );
+        let bar = mock.id_of("/bar/lib.rs");
+        let analysis = mock.analysis();
+
+        let dot = analysis.view_crate_graph(FxHashSet::default()).unwrap();
+        expect![[r#"
+            digraph crate_graph {
+                crate0 [label="<unnamed> (#0)"];
+                crate1 [label="foo (#1)"];
+                crate2 [label="bar (#2)"];
+                crate0 -> crate1 [label="foo"];
+                crate0 -> crate2 [label="bar"];
+            }
+        "#]]
+        .assert_eq(&dot);
+
+        let excluded = vec![bar].into_iter().collect();
+        let dot = analysis.view_crate_graph(excluded).unwrap();
+        expect![[r#"
+            digraph crate_graph {
+                crate0 [label="<unnamed> (#0)"];
+                crate1 [label="foo (#1)"];
+                crate0 -> crate1 [label="foo"];
+            }
+        "#]]
+        .assert_eq(&dot);
+    }
+}
@@ -0,0 +1,66 @@
+//! Renders the lowered HIR body of the function under the cursor.
+
+use hir::{Function, Semantics};
+use ra_ide_db::RootDatabase;
+use ra_syntax::{algo::find_node_at_offset, ast, AstNode};
+
+use crate::FilePosition;
+
+// Feature: View Hir
+//
+// Shows the lowered body of the function under the cursor. It exists mostly
+// for debugging rust-analyzer itself.
+//
+// |===
+// | Editor  | Action Name
+//
+// | VS Code | **Rust Analyzer: View Hir**
+// |===
+pub(crate) fn view_hir(db: &RootDatabase, position: FilePosition) -> String {
+    body_hir(db, position).unwrap_or_else(|| "Not inside a function body".to_string())
+}
+
+fn body_hir(db: &RootDatabase, position: FilePosition) -> Option<String> {
+    let sema = Semantics::new(db);
+    let source_file = sema.parse(position.file_id);
+
+    let function = find_node_at_offset::<ast::FnDef>(source_file.syntax(), position.offset)?;
+
+    let function: Function = sema.to_def(&function)?;
+    Some(function.debug_hir(db))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::analysis_and_position;
+
+    fn view_hir(ra_fixture: &str) -> String {
+        let (analysis, position) = analysis_and_position(ra_fixture);
+        analysis.view_hir(position).unwrap()
+    }
+
+    #[test]
+    fn view_hir_of_function() {
+        let hir = view_hir(
+            r#"
+fn foo(x: i32) -> i32 {
+    x<|> + 1
+}
+"#,
+        );
+        assert!(hir.starts_with("HIR expressions in the body of `foo`:\n"), "{}", hir);
+        for kind in &["Path(", "Literal(", "BinaryOp {", "Block {", "Bind {"] {
+            assert!(hir.contains(kind), "missing {} in {}", kind, hir);
+        }
+    }
+
+    #[test]
+    fn view_hir_outside_of_function() {
+        let hir = view_hir(
+            r#"
+struct Foo<|>;
+"#,
+        );
+        assert_eq!(hir, "Not inside a function body");
+    }
+}
@@ -0,0 +1,18 @@
+//! Renders the item tree of a file, for debugging.
+
+use hir::db::DefDatabase;
+use ra_db::FileId;
+use ra_ide_db::RootDatabase;
+
+// Feature: Debug ItemTree
+//
+// Displays the ItemTree of the currently open file, for debugging.
+//
+// |===
+// | Editor  | Action Name
+//
+// | VS Code | **Rust Analyzer: Debug ItemTree**
+// |===
+pub(crate) fn view_item_tree(db: &RootDatabase, file_id: FileId) -> String {
+    format!("{:#?}", db.item_tree(file_id.into()))
+}
@@ -17,6 +17,7 @@ pub struct AnalysisChange {
     roots: Option<Vec<SourceRoot>>,
     files_changed: Vec<(FileId, Option<Arc<String>>)>,
     crate_graph: Option<CrateGraph>,
+    open_files: Option<FxHashSet<FileId>>,
 }
 
 impl fmt::Debug for AnalysisChange {
@@ -31,6 +32,9 @@ impl fmt::Debug for AnalysisChange {
         if self.crate_graph.is_some() {
             d.field("crate_graph", &self.crate_graph);
         }
+        if let Some(open_files) = &self.open_files {
+            d.field("open_files", &open_files.len());
+        }
         d.finish()
     }
 }
@@ -51,6 +55,10 @@ impl AnalysisChange {
     pub fn set_crate_graph(&mut self, graph: CrateGraph) {
         self.crate_graph = Some(graph);
     }
+
+    pub fn set_open_files(&mut self, open_files: FxHashSet<FileId>) {
+        self.open_files = Some(open_files);
+    }
 }
 
 #[derive(Debug)]
@@ -124,6 +132,11 @@ impl RootDatabase {
         if let Some(crate_graph) = change.crate_graph {
             self.set_crate_graph_with_durability(Arc::new(crate_graph), Durability::HIGH)
         }
+        if let Some(open_files) = change.open_files {
+            // Unlike the texts of local files, this doesn't change on every
+            // keystroke, so queries of libraries stay cheap to validate.
+            self.set_open_files_with_durability(Arc::new(open_files), Durability::MEDIUM)
+        }
     }
 
     pub fn maybe_collect_garbage(&mut self) {
@@ -133,6 +146,7 @@ impl RootDatabase {
 
         if self.last_gc_check.elapsed() > GC_COOLDOWN {
             self.last_gc_check = crate::wasm_shims::Instant::now();
+            self.adapt_lru_capacities();
         }
     }
 
@@ -161,6 +175,8 @@ impl RootDatabase {
         hir::db::ExprScopesQuery.in_db(self).sweep(sweep);
         hir::db::InferQueryQuery.in_db(self).sweep(sweep);
         hir::db::BodyQuery.in_db(self).sweep(sweep);
+
+        hir::collect_interned_garbage();
     }
 
     // Feature: Memory Usage
@@ -176,84 +192,97 @@ impl RootDatabase {
         let mut acc: Vec<(String, Bytes)> = vec![];
         let sweep = SweepStrategy::default().discard_values().sweep_all_revisions();
         macro_rules! sweep_each_query {
-            ($($q:path)*) => {$(
-                let before = memory_usage().allocated;
-                $q.in_db(self).sweep(sweep);
-                let after = memory_usage().allocated;
-                let q: $q = Default::default();
-                let name = format!("{:?}", q);
-                acc.push((name, before - after));
-
-                let before = memory_usage().allocated;
-                $q.in_db(self).sweep(sweep.discard_everything());
-                let after = memory_usage().allocated;
-                let q: $q = Default::default();
-                let name = format!("{:?} (deps)", q);
-                acc.push((name, before - after));
+            ($($group:literal { $($q:path)* })*) => {$(
+                let mut group_total = Bytes::default();
+                $(
+                    let before = memory_usage().allocated;
+                    $q.in_db(self).sweep(sweep);
+                    let after = memory_usage().allocated;
+                    let q: $q = Default::default();
+                    let name = format!("{:?}", q);
+                    acc.push((name, before - after));
+                    group_total = group_total + (before - after);
+
+                    let before = memory_usage().allocated;
+                    $q.in_db(self).sweep(sweep.discard_everything());
+                    let after = memory_usage().allocated;
+                    let q: $q = Default::default();
+                    let name = format!("{:?} (deps)", q);
+                    acc.push((name, before - after));
+                    group_total = group_total + (before - after);
+                )*
+                acc.push((format!("{} (total)", $group), group_total));
             )*}
         }
         sweep_each_query![
-            // SourceDatabase
-            ra_db::ParseQuery
-            ra_db::SourceRootCratesQuery
-
-            // AstDatabase
-            hir::db::AstIdMapQuery
-            hir::db::MacroArgQuery
-            hir::db::MacroDefQuery
-            hir::db::ParseMacroQuery
-            hir::db::MacroExpandQuery
-
-            // DefDatabase
-            hir::db::ItemTreeQuery
-            hir::db::CrateDefMapQueryQuery
-            hir::db::StructDataQuery
-            hir::db::UnionDataQuery
-            hir::db::EnumDataQuery
-            hir::db::ImplDataQuery
-            hir::db::TraitDataQuery
-            hir::db::TypeAliasDataQuery
-            hir::db::FunctionDataQuery
-            hir::db::ConstDataQuery
-            hir::db::StaticDataQuery
-            hir::db::BodyWithSourceMapQuery
-            hir::db::BodyQuery
-            hir::db::ExprScopesQuery
-            hir::db::GenericParamsQuery
-            hir::db::AttrsQuery
-            hir::db::ModuleLangItemsQuery
-            hir::db::CrateLangItemsQuery
-            hir::db::LangItemQuery
-            hir::db::DocumentationQuery
-            hir::db::ImportMapQuery
-
-            // HirDatabase
-            hir::db::InferQueryQuery
-            hir::db::TyQuery
-            hir::db::ValueTyQuery
-            hir::db::ImplSelfTyQuery
-            hir::db::ImplTraitQuery
-            hir::db::FieldTypesQuery
-            hir::db::CallableItemSignatureQuery
-            hir::db::GenericPredicatesForParamQuery
-            hir::db::GenericPredicatesQuery
-            hir::db::GenericDefaultsQuery
-            hir::db::InherentImplsInCrateQuery
-            hir::db::TraitImplsInCrateQuery
-            hir::db::TraitImplsInDepsQuery
-            hir::db::AssociatedTyDataQuery
-            hir::db::TraitDatumQuery
-            hir::db::StructDatumQuery
-            hir::db::ImplDatumQuery
-            hir::db::AssociatedTyValueQuery
-            hir::db::TraitSolveQuery
-            hir::db::ReturnTypeImplTraitsQuery
-
-            // SymbolsDatabase
-            crate::symbol_index::FileSymbolsQuery
-
-            // LineIndexDatabase
-            crate::LineIndexQuery
+            "SourceDatabase" {
+                ra_db::ParseQuery
+                ra_db::SourceRootCratesQuery
+            }
+
+            "AstDatabase" {
+                hir::db::AstIdMapQuery
+                hir::db::MacroArgQuery
+                hir::db::MacroDefQuery
+                hir::db::ParseMacroQuery
+                hir::db::MacroExpandQuery
+                hir::db::MacroExpandErrorQuery
+            }
+
+            "DefDatabase" {
+                hir::db::ItemTreeQuery
+                hir::db::CrateDefMapQueryQuery
+                hir::db::StructDataQuery
+                hir::db::UnionDataQuery
+                hir::db::EnumDataQuery
+                hir::db::ImplDataQuery
+                hir::db::TraitDataQuery
+                hir::db::TypeAliasDataQuery
+                hir::db::FunctionDataQuery
+                hir::db::ConstDataQuery
+                hir::db::StaticDataQuery
+                hir::db::BodyWithSourceMapQuery
+                hir::db::BodyQuery
+                hir::db::ExprScopesQuery
+                hir::db::GenericParamsQuery
+                hir::db::AttrsQuery
+                hir::db::ModuleLangItemsQuery
+                hir::db::CrateLangItemsQuery
+                hir::db::LangItemQuery
+                hir::db::DocumentationQuery
+                hir::db::ImportMapQuery
+            }
+
+            "HirDatabase" {
+                hir::db::InferQueryQuery
+                hir::db::TyQuery
+                hir::db::ValueTyQuery
+                hir::db::ImplSelfTyQuery
+                hir::db::ImplTraitQuery
+                hir::db::FieldTypesQuery
+                hir::db::CallableItemSignatureQuery
+                hir::db::GenericPredicatesForParamQuery
+                hir::db::GenericPredicatesQuery
+                hir::db::GenericDefaultsQuery
+                hir::db::InherentImplsInCrateQuery
+                hir::db::TraitImplsInCrateQuery
+                hir::db::TraitImplsInDepsQuery
+                hir::db::AssociatedTyDataQuery
+                hir::db::TraitDatumQuery
+                hir::db::StructDatumQuery
+                hir::db::ImplDatumQuery
+                hir::db::AssociatedTyValueQuery
+                hir::db::TraitSolveQuery
+                hir::db::ReturnTypeImplTraitsQuery
+            }
+
+            "SymbolsDatabase" {
+                crate::symbol_index::FileSymbolsQuery
+            }
+
+            "LineIndexDatabase" {
+                crate::LineIndexQuery
+            }
         ];
 
         // To collect interned data, we need to bump the revision counter by performing a synthetic
@@ -263,26 +292,29 @@ impl RootDatabase {
         self.salsa_runtime_mut().synthetic_write(Durability::HIGH);
 
         sweep_each_query![
-            // AstDatabase
-            hir::db::InternMacroQuery
-            hir::db::InternEagerExpansionQuery
-
-            // InternDatabase
-            hir::db::InternFunctionQuery
-            hir::db::InternStructQuery
-            hir::db::InternUnionQuery
-            hir::db::InternEnumQuery
-            hir::db::InternConstQuery
-            hir::db::InternStaticQuery
-            hir::db::InternTraitQuery
-            hir::db::InternTypeAliasQuery
-            hir::db::InternImplQuery
-
-            // HirDatabase
-            hir::db::InternTypeCtorQuery
-            hir::db::InternTypeParamIdQuery
-            hir::db::InternChalkImplQuery
-            hir::db::InternAssocTyValueQuery
+            "AstDatabase (interned)" {
+                hir::db::InternMacroQuery
+                hir::db::InternEagerExpansionQuery
+            }
+
+            "InternDatabase" {
+                hir::db::InternFunctionQuery
+                hir::db::InternStructQuery
+                hir::db::InternUnionQuery
+                hir::db::InternEnumQuery
+                hir::db::InternConstQuery
+                hir::db::InternStaticQuery
+                hir::db::InternTraitQuery
+                hir::db::InternTypeAliasQuery
+                hir::db::InternImplQuery
+            }
+
+            "HirDatabase (interned)" {
+                hir::db::InternTypeCtorQuery
+                hir::db::InternTypeParamIdQuery
+                hir::db::InternChalkImplQuery
+                hir::db::InternAssocTyValueQuery
+            }
         ];
 
         acc.sort_by_key(|it| std::cmp::Reverse(it.1));
@@ -3,6 +3,7 @@
 //! It is mainly a `HirDatabase` for semantic analysis, plus a `SymbolsDatabase`, for fuzzy search.
 
 pub mod line_index;
+pub mod persistent_cache;
 pub mod symbol_index;
 pub mod change;
 pub mod defs;
@@ -17,8 +18,9 @@ use hir::db::{AstDatabase, DefDatabase, HirDatabase};
 use ra_db::{
     salsa::{self, Durability},
     Canceled, CheckCanceled, CrateId, FileId, FileLoader, FileLoaderDelegate, SourceDatabase,
-    Upcast,
+    SourceDatabaseExt, Upcast,
 };
+use ra_prof::MemoryUsage;
 use rustc_hash::FxHashSet;
 
 use crate::{line_index::LineIndex, symbol_index::SymbolsDatabase};
@@ -37,6 +39,62 @@ pub struct RootDatabase {
     storage: salsa::Storage<RootDatabase>,
     pub last_gc: crate::wasm_shims::Instant,
     pub last_gc_check: crate::wasm_shims::Instant,
+    lru_config: LruConfig,
+    /// Percentage of the configured capacities currently in use, adapted to
+    /// the memory budget.
+    lru_scale: usize,
+}
+
+/// The capacities of the LRU caches of the queries with big results. Zero
+/// means that the results of a query are never evicted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LruConfig {
+    /// Number of syntax trees of files.
+    pub parse: usize,
+    /// Number of syntax trees and token maps of macro expansions.
+    pub macro_expansion: usize,
+    /// Number of function, const and static bodies.
+    pub body: usize,
+    /// If set, the non-zero capacities grow and shrink to keep the allocated
+    /// memory around this many megabytes.
+    pub memory_budget: Option<usize>,
+}
+
+impl Default for LruConfig {
+    fn default() -> LruConfig {
+        LruConfig::with_capacity(None)
+    }
+}
+
+impl LruConfig {
+    /// Uses `capacity` for syntax trees and macro expansions, and keeps all
+    /// bodies.
+    pub fn with_capacity(capacity: Option<usize>) -> LruConfig {
+        let capacity = capacity.unwrap_or(ra_db::DEFAULT_LRU_CAP);
+        LruConfig { parse: capacity, macro_expansion: capacity, body: 0, memory_budget: None }
+    }
+}
+
+const MIN_LRU_SCALE: usize = 10;
+const MAX_LRU_SCALE: usize = 800;
+
+/// Shrinks the caches while more than the budget is allocated, and grows them
+/// back while the allocations are well below it.
+fn adapt_lru_scale(scale: usize, allocated_mb: usize, budget_mb: usize) -> usize {
+    if allocated_mb > budget_mb {
+        (scale * 3 / 4).max(MIN_LRU_SCALE)
+    } else if allocated_mb < budget_mb * 3 / 4 {
+        (scale * 5 / 4).min(MAX_LRU_SCALE)
+    } else {
+        scale
+    }
+}
+
+fn scale_capacity(capacity: usize, scale: usize) -> usize {
+    if capacity == 0 {
+        return 0;
+    }
+    (capacity * scale / 100).max(1)
 }
 
 impl fmt::Debug for RootDatabase {
@@ -73,6 +131,12 @@ impl FileLoader for RootDatabase {
     fn relevant_crates(&self, file_id: FileId) -> Arc<FxHashSet<CrateId>> {
         FileLoaderDelegate(self).relevant_crates(file_id)
     }
+    fn is_library(&self, file_id: FileId) -> bool {
+        FileLoaderDelegate(self).is_library(file_id)
+    }
+    fn is_open(&self, file_id: FileId) -> bool {
+        FileLoaderDelegate(self).is_open(file_id)
+    }
 }
 
 impl salsa::Database for RootDatabase {
@@ -102,19 +166,59 @@ impl RootDatabase {
             storage: salsa::Storage::default(),
             last_gc: crate::wasm_shims::Instant::now(),
             last_gc_check: crate::wasm_shims::Instant::now(),
+            lru_config: LruConfig::default(),
+            lru_scale: 100,
         };
         db.set_crate_graph_with_durability(Default::default(), Durability::HIGH);
         db.set_local_roots_with_durability(Default::default(), Durability::HIGH);
         db.set_library_roots_with_durability(Default::default(), Durability::HIGH);
-        db.update_lru_capacity(lru_capacity);
+        db.set_open_files_with_durability(Default::default(), Durability::MEDIUM);
+        db.set_persistent_cache_with_durability(Default::default(), Durability::HIGH);
+        db.set_macro_limits_with_durability(Default::default(), Durability::HIGH);
+        db.set_attr_macro_fallback_with_durability(true, Durability::HIGH);
+        db.update_lru_config(LruConfig::with_capacity(lru_capacity));
         db
     }
 
-    pub fn update_lru_capacity(&mut self, lru_capacity: Option<usize>) {
-        let lru_capacity = lru_capacity.unwrap_or(ra_db::DEFAULT_LRU_CAP);
-        ra_db::ParseQuery.in_db_mut(self).set_lru_capacity(lru_capacity);
-        hir::db::ParseMacroQuery.in_db_mut(self).set_lru_capacity(lru_capacity);
-        hir::db::MacroExpandQuery.in_db_mut(self).set_lru_capacity(lru_capacity);
+    pub fn update_lru_config(&mut self, lru_config: LruConfig) {
+        if lru_config.memory_budget.is_some() && !MemoryUsage::is_accurate() {
+            log::warn!("the memory usage is unknown on this platform, ignoring the memory budget");
+        }
+        self.lru_config = lru_config;
+        self.lru_scale = 100;
+        self.set_lru_capacities();
+    }
+
+    /// Rescales the capacities if a memory budget is configured.
+    pub(crate) fn adapt_lru_capacities(&mut self) {
+        let budget = match self.lru_config.memory_budget {
+            Some(it) => it,
+            None => return,
+        };
+        // Without jemalloc, only recent versions of glibc tell how much memory
+        // is allocated.
+        if !MemoryUsage::is_accurate() {
+            return;
+        }
+        let allocated = MemoryUsage::current().allocated.megabytes();
+        let scale = adapt_lru_scale(self.lru_scale, allocated, budget);
+        if scale != self.lru_scale {
+            log::info!("scaling LRU capacities to {}%, {}mb allocated", scale, allocated);
+            self.lru_scale = scale;
+            self.set_lru_capacities();
+        }
+    }
+
+    fn set_lru_capacities(&mut self) {
+        let LruConfig { parse, macro_expansion, body, memory_budget: _ } = self.lru_config;
+        let scale = self.lru_scale;
+        ra_db::ParseQuery.in_db_mut(self).set_lru_capacity(scale_capacity(parse, scale));
+        let macro_expansion = scale_capacity(macro_expansion, scale);
+        hir::db::ParseMacroQuery.in_db_mut(self).set_lru_capacity(macro_expansion);
+        hir::db::MacroExpandQuery.in_db_mut(self).set_lru_capacity(macro_expansion);
+        let body = scale_capacity(body, scale);
+        hir::db::BodyWithSourceMapQuery.in_db_mut(self).set_lru_capacity(body);
+        hir::db::BodyQuery.in_db_mut(self).set_lru_capacity(body);
     }
 }
 
@@ -124,6 +228,8 @@ impl salsa::ParallelDatabase for RootDatabase {
             storage: self.storage.snapshot(),
             last_gc: self.last_gc,
             last_gc_check: self.last_gc_check,
+            lru_config: self.lru_config,
+            lru_scale: self.lru_scale,
         })
     }
 }
@@ -137,3 +243,22 @@ fn line_index(db: &dyn LineIndexDatabase, file_id: FileId) -> Arc<LineIndex> {
     let text = db.file_text(file_id);
     Arc::new(LineIndex::new(&*text))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{adapt_lru_scale, scale_capacity, MAX_LRU_SCALE, MIN_LRU_SCALE};
+
+    #[test]
+    fn lru_scale_follows_memory_budget() {
+        assert_eq!(adapt_lru_scale(100, 1200, 1000), 75);
+        assert_eq!(adapt_lru_scale(100, 900, 1000), 100);
+        assert_eq!(adapt_lru_scale(100, 500, 1000), 125);
+        assert_eq!(adapt_lru_scale(MIN_LRU_SCALE, 1200, 1000), MIN_LRU_SCALE);
+        assert_eq!(adapt_lru_scale(MAX_LRU_SCALE, 500, 1000), MAX_LRU_SCALE);
+
+        assert_eq!(scale_capacity(128, 75), 96);
+        assert_eq!(scale_capacity(128, MIN_LRU_SCALE), 12);
+        assert_eq!(scale_capacity(1, MIN_LRU_SCALE), 1);
+        assert_eq!(scale_capacity(0, MAX_LRU_SCALE), 0);
+    }
+}
@@ -22,6 +22,16 @@ pub struct LineCol {
     pub col_utf16: u32,
 }
 
+/// Like `LineCol`, but with the column counted in bytes, which doesn't need
+/// any translation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LineColUtf8 {
+    /// Zero-based
+    pub line: u32,
+    /// Zero-based
+    pub col: u32,
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub(crate) struct Utf16Char {
     /// Start offset of a character inside a line, zero-based
@@ -89,11 +99,8 @@ impl LineIndex {
     }
 
     pub fn line_col(&self, offset: TextSize) -> LineCol {
-        let line = self.newlines.upper_bound(&offset) - 1;
-        let line_start_offset = self.newlines[line];
-        let col = offset - line_start_offset;
-
-        LineCol { line: line as u32, col_utf16: self.utf8_to_utf16_col(line as u32, col) as u32 }
+        let LineColUtf8 { line, col } = self.line_col_utf8(offset);
+        LineCol { line, col_utf16: self.utf8_to_utf16_col(line, col.into()) as u32 }
     }
 
     pub fn offset(&self, line_col: LineCol) -> TextSize {
@@ -102,6 +109,17 @@ impl LineIndex {
         self.newlines[line_col.line as usize] + col
     }
 
+    pub fn line_col_utf8(&self, offset: TextSize) -> LineColUtf8 {
+        let line = self.newlines.upper_bound(&offset) - 1;
+        let line_start_offset = self.newlines[line];
+        let col = offset - line_start_offset;
+        LineColUtf8 { line: line as u32, col: col.into() }
+    }
+
+    pub fn offset_utf8(&self, line_col: LineColUtf8) -> TextSize {
+        self.newlines[line_col.line as usize] + TextSize::from(line_col.col)
+    }
+
     pub fn lines(&self, range: TextRange) -> impl Iterator<Item = TextRange> + '_ {
         let lo = self.newlines.lower_bound(&range.start());
         let hi = self.newlines.upper_bound(&range.end());
@@ -175,6 +193,20 @@ mod tests {
         assert_eq!(index.line_col(7.into()), LineCol { line: 2, col_utf16: 0 });
     }
 
+    #[test]
+    fn test_line_index_utf8() {
+        let text = "fn メ() {}\n𐐀\n";
+        let index = LineIndex::new(text);
+        for offset in [0, 3, 6, 11, 12, 16, 17].iter().copied() {
+            let line_col = index.line_col_utf8(offset.into());
+            assert_eq!(index.offset_utf8(line_col), TextSize::from(offset));
+        }
+        assert_eq!(index.line_col_utf8(6.into()), LineColUtf8 { line: 0, col: 6 });
+        assert_eq!(index.line_col(6.into()), LineCol { line: 0, col_utf16: 4 });
+        assert_eq!(index.line_col_utf8(16.into()), LineColUtf8 { line: 1, col: 4 });
+        assert_eq!(index.line_col(16.into()), LineCol { line: 1, col_utf16: 2 });
+    }
+
     #[test]
     fn test_char_len() {
         assert_eq!('メ'.len_utf8(), 3);
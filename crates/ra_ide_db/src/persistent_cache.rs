@@ -0,0 +1,320 @@
+//! Caches query results on disk across restarts.
+//!
+//! Most results refer to files and definitions by ids, which are assigned anew
+//! on every start, so only the results that depend solely on the text of a
+//! file can be reused. For now, these are the symbols of a file: building the
+//! symbol index otherwise parses every file of the workspace and of its
+//! dependencies. Entries are keyed by a fingerprint of the text, so a changed
+//! file simply misses the cache.
+//!
+//! The cache is a salsa input which doesn't change after it is loaded, so the
+//! queries reading it stay pure. Storing it reads the symbols of all current
+//! files from the database, which drops the entries of changed and deleted
+//! files.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+};
+
+use ra_db::{FileId, SourceDatabaseExt};
+use ra_syntax::{SmolStr, SyntaxKind, SyntaxNodePtr, TextRange, TextSize};
+use rustc_hash::{FxHashMap, FxHasher};
+use serde::{Deserialize, Serialize};
+
+use crate::symbol_index::{FileSymbol, SymbolsDatabase};
+
+/// Bump this when the layout of the cache or the way symbols are collected
+/// changes.
+const VERSION: u32 = 3;
+
+#[derive(Default)]
+pub struct PersistentCache {
+    path: Option<PathBuf>,
+    /// Identifies the build of rust-analyzer which wrote the cache, the raw
+    /// `SyntaxKind`s of another build might mean something else.
+    version: String,
+    entries: FxHashMap<Fingerprint, Vec<CachedSymbol>>,
+}
+
+impl std::fmt::Debug for PersistentCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PersistentCache").field("path", &self.path).finish()
+    }
+}
+
+/// The cache is a salsa input, and a new cache should always invalidate the
+/// results computed with the old one.
+impl PartialEq for PersistentCache {
+    fn eq(&self, other: &PersistentCache) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for PersistentCache {}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile<S> {
+    version: String,
+    entries: Vec<(Fingerprint, S)>,
+}
+
+/// Two unrelated hashes of the text, as FxHash alone is easy to collide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct Fingerprint {
+    len: usize,
+    fx_hash: u64,
+    sip_hash: u64,
+}
+
+impl Fingerprint {
+    fn of(text: &str) -> Fingerprint {
+        let mut fx_hasher = FxHasher::default();
+        text.hash(&mut fx_hasher);
+        let mut sip_hasher = DefaultHasher::new();
+        text.hash(&mut sip_hasher);
+        Fingerprint { len: text.len(), fx_hash: fx_hasher.finish(), sip_hash: sip_hasher.finish() }
+    }
+}
+
+/// A `FileSymbol` without the file, whose pointer is the range of the symbol.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedSymbol {
+    name: String,
+    kind: u16,
+    range: (u32, u32),
+    name_range: Option<(u32, u32)>,
+    container_name: Option<String>,
+}
+
+impl CachedSymbol {
+    fn new(symbol: &FileSymbol) -> CachedSymbol {
+        CachedSymbol {
+            name: symbol.name.to_string(),
+            kind: symbol.kind as u16,
+            range: to_raw(symbol.range),
+            name_range: symbol.name_range.map(to_raw),
+            container_name: symbol.container_name.as_ref().map(|it| it.to_string()),
+        }
+    }
+
+    /// Whether the symbol can be decoded. The version check should rule out
+    /// foreign kinds, but the file might still be corrupted.
+    fn is_valid(&self) -> bool {
+        let is_valid_range = |(start, end): (u32, u32)| start <= end;
+        self.kind < SyntaxKind::__LAST as u16
+            && is_valid_range(self.range)
+            && self.name_range.into_iter().all(is_valid_range)
+    }
+
+    /// Whether the ranges of the symbol are inside of a text of length `len`.
+    /// A fingerprint collision must not hand out ranges past the end.
+    fn fits(&self, len: usize) -> bool {
+        let fits = |(_, end): (u32, u32)| end as usize <= len;
+        fits(self.range) && self.name_range.into_iter().all(fits)
+    }
+
+    fn to_file_symbol(&self, file_id: FileId) -> FileSymbol {
+        let kind = SyntaxKind::from(self.kind);
+        let range = from_raw(self.range);
+        FileSymbol {
+            file_id,
+            name: SmolStr::new(&self.name),
+            kind,
+            range,
+            ptr: SyntaxNodePtr::from_parts(kind, range),
+            name_range: self.name_range.map(from_raw),
+            container_name: self.container_name.as_deref().map(SmolStr::new),
+        }
+    }
+}
+
+fn to_raw(range: TextRange) -> (u32, u32) {
+    (range.start().into(), range.end().into())
+}
+
+fn from_raw((start, end): (u32, u32)) -> TextRange {
+    TextRange::new(TextSize::from(start), TextSize::from(end))
+}
+
+impl PersistentCache {
+    /// Loads the cache of the workspace at `root` from `dir`, if it was
+    /// written by the same `version` of rust-analyzer. A missing, unreadable
+    /// or outdated cache is not an error, the cache starts out empty then.
+    pub fn load(dir: &Path, root: &Path, version: &str) -> PersistentCache {
+        let mut hasher = FxHasher::default();
+        root.hash(&mut hasher);
+        let path = dir.join(format!("symbols-{:016x}.json", hasher.finish()));
+        let version = format!("{}-{}", VERSION, version);
+
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<CacheFile<Vec<CachedSymbol>>>(&text).ok())
+            .filter(|it| it.version == version)
+            .map(|it| {
+                let entries = it.entries.into_iter();
+                entries.filter(|(_, symbols)| symbols.iter().all(CachedSymbol::is_valid)).collect()
+            })
+            .unwrap_or_default();
+        PersistentCache { path: Some(path), version, entries }
+    }
+
+    /// Writes the symbols of all files of the database to disk.
+    pub fn store(&self, db: &dyn SymbolsDatabase) {
+        let path = match &self.path {
+            Some(it) => it,
+            None => return,
+        };
+
+        let mut symbols_by_file: FxHashMap<FileId, Vec<&FileSymbol>> = FxHashMap::default();
+        let library_symbols = db.library_symbols();
+        for symbol in library_symbols.values().flat_map(|it| it.symbols()) {
+            symbols_by_file.entry(symbol.file_id).or_default().push(symbol);
+        }
+        let local_symbols = db
+            .local_roots()
+            .iter()
+            .flat_map(|&root| db.source_root(root).iter().collect::<Vec<_>>())
+            .map(|file_id| db.file_symbols(file_id))
+            .collect::<Vec<_>>();
+        for symbol in local_symbols.iter().flat_map(|it| it.symbols()) {
+            symbols_by_file.entry(symbol.file_id).or_default().push(symbol);
+        }
+
+        // Files without symbols are stored too, so that they hit the cache.
+        let roots =
+            db.library_roots().iter().chain(db.local_roots().iter()).copied().collect::<Vec<_>>();
+        let entries = roots
+            .into_iter()
+            .flat_map(|root| db.source_root(root).iter().collect::<Vec<_>>())
+            .map(|file_id| {
+                let key = Fingerprint::of(&SourceDatabaseExt::file_text(db, file_id));
+                let symbols =
+                    symbols_by_file.get(&file_id).map(|it| it.as_slice()).unwrap_or_default();
+                (key, symbols.iter().map(|it| CachedSymbol::new(it)).collect())
+            })
+            .collect();
+        let cache_file: CacheFile<Vec<CachedSymbol>> =
+            CacheFile { version: self.version.clone(), entries };
+        let res = (|| -> io::Result<()> {
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, serde_json::to_string(&cache_file)?)
+        })();
+        if let Err(err) = res {
+            log::warn!("failed to write {}: {}", path.display(), err);
+        }
+    }
+
+    pub(crate) fn file_symbols(&self, file_id: FileId, text: &str) -> Option<Vec<FileSymbol>> {
+        let symbols = self.entries.get(&Fingerprint::of(text))?;
+        if !symbols.iter().all(|it| it.fits(text.len())) {
+            return None;
+        }
+        Some(symbols.iter().map(|it| it.to_file_symbol(file_id)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, sync::Arc};
+
+    use ra_db::{FileSet, SourceRoot, VfsPath};
+    use ra_syntax::{AstNode, SourceFile};
+
+    use super::*;
+    use crate::{change::AnalysisChange, symbol_index::source_file_to_file_symbols, RootDatabase};
+
+    fn db(files: &[&str], is_library: bool) -> RootDatabase {
+        let mut file_set = FileSet::default();
+        let mut change = AnalysisChange::new();
+        for (i, text) in files.iter().enumerate() {
+            let file_id = FileId(i as u32);
+            file_set.insert(file_id, VfsPath::new_virtual_path(format!("/f{}.rs", i)));
+            change.change_file(file_id, Some(Arc::new(text.to_string())));
+        }
+        let root = if is_library {
+            SourceRoot::new_library(file_set)
+        } else {
+            SourceRoot::new_local(file_set)
+        };
+        change.set_roots(vec![root]);
+        let mut db = RootDatabase::default();
+        db.apply_change(change);
+        db
+    }
+
+    #[test]
+    fn reuses_symbols_of_unchanged_files() {
+        let dir = env::temp_dir().join(format!("ra-persistent-cache-{}", std::process::id()));
+        let root = Path::new("/workspace");
+        let text = "mod m { struct S; }\nfn f() {}";
+        let file = SourceFile::parse(text).tree();
+        let symbols = source_file_to_file_symbols(&file, FileId(1));
+
+        let cache = PersistentCache::load(&dir, root, "v1");
+        assert!(cache.file_symbols(FileId(1), text).is_none());
+        cache.store(&db(&[text, ""], true));
+        let reloaded = PersistentCache::load(&dir, root, "v1");
+        let other_workspace = PersistentCache::load(&dir, Path::new("/other"), "v1");
+        let other_version = PersistentCache::load(&dir, root, "v2");
+        // Storing drops the entries of files which changed in the meantime.
+        reloaded.store(&db(&["fn g() {}"], false));
+        let after_change = PersistentCache::load(&dir, root, "v1");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut symbols_of_text = reloaded.file_symbols(FileId(2), text).unwrap();
+        symbols_of_text.sort_by_key(|it| it.range.start());
+        assert_eq!(symbols_of_text.len(), 3);
+        for (symbol, expected) in symbols_of_text.iter().zip(&symbols) {
+            assert_eq!(symbol.file_id, FileId(2));
+            assert_eq!(FileSymbol { file_id: FileId(1), ..symbol.clone() }, *expected);
+            let node = symbol.ptr.to_node(file.syntax());
+            assert_eq!(node.kind(), expected.kind);
+        }
+        assert_eq!(reloaded.file_symbols(FileId(3), "").unwrap(), Vec::new());
+        assert!(reloaded.file_symbols(FileId(2), "fn f() {}").is_none());
+        assert!(other_workspace.file_symbols(FileId(1), text).is_none());
+        assert!(other_version.file_symbols(FileId(1), text).is_none());
+        assert!(after_change.file_symbols(FileId(1), text).is_none());
+        assert_eq!(after_change.file_symbols(FileId(1), "fn g() {}").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn drops_invalid_entries() {
+        let dir =
+            env::temp_dir().join(format!("ra-persistent-cache-invalid-{}", std::process::id()));
+        let root = Path::new("/workspace");
+        let symbol = |kind: u16, range: (u32, u32)| CachedSymbol {
+            name: "S".to_string(),
+            kind,
+            range,
+            name_range: None,
+            container_name: None,
+        };
+        let cache_file = CacheFile {
+            version: format!("{}-v1", VERSION),
+            entries: vec![
+                (Fingerprint::of("a"), vec![symbol(SyntaxKind::STRUCT_DEF as u16, (0, 1))]),
+                (Fingerprint::of("b"), vec![symbol(u16::MAX, (0, 1))]),
+                (Fingerprint::of("c"), vec![symbol(SyntaxKind::STRUCT_DEF as u16, (1, 0))]),
+                // As if the entry was written for another text with the same
+                // fingerprint.
+                (Fingerprint::of("d"), vec![symbol(SyntaxKind::STRUCT_DEF as u16, (0, 2))]),
+            ],
+        };
+        let cache = PersistentCache::load(&dir, root, "v1");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(cache.path.as_ref().unwrap(), serde_json::to_string(&cache_file).unwrap())
+            .unwrap();
+        let cache = PersistentCache::load(&dir, root, "v1");
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(cache.file_symbols(FileId(0), "a").is_some());
+        assert!(cache.file_symbols(FileId(0), "b").is_none());
+        assert!(cache.file_symbols(FileId(0), "c").is_none());
+        assert!(cache.file_symbols(FileId(0), "d").is_none());
+    }
+}
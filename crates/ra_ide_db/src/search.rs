@@ -6,9 +6,9 @@
 
 use std::{convert::TryInto, mem};
 
-use hir::{DefWithBody, HasSource, Module, ModuleSource, Semantics, Visibility};
+use hir::{original_range, DefWithBody, HasSource, Module, ModuleSource, Semantics, Visibility};
 use once_cell::unsync::Lazy;
-use ra_db::{FileId, FileRange, SourceDatabaseExt};
+use ra_db::{CheckCanceled, FileId, FileRange, SourceDatabaseExt};
 use ra_prof::profile;
 use ra_syntax::{ast, match_ast, AstNode, TextRange, TextSize};
 use rustc_hash::FxHashMap;
@@ -112,13 +112,15 @@ impl Definition {
         let file_id = module_src.file_id.original_file(db);
 
         if let Definition::Local(var) = self {
-            let range = match var.parent(db) {
-                DefWithBody::Function(f) => f.source(db).value.syntax().text_range(),
-                DefWithBody::Const(c) => c.source(db).value.syntax().text_range(),
-                DefWithBody::Static(s) => s.source(db).value.syntax().text_range(),
+            let source = match var.parent(db) {
+                DefWithBody::Function(f) => f.source(db).map(|it| it.syntax().clone()),
+                DefWithBody::Const(c) => c.source(db).map(|it| it.syntax().clone()),
+                DefWithBody::Static(s) => s.source(db).map(|it| it.syntax().clone()),
             };
+            // The body may come from a macro expansion.
+            let range = original_range(db, source.as_ref());
             let mut res = FxHashMap::default();
-            res.insert(file_id, Some(range));
+            res.insert(range.file_id, Some(range.range));
             return SearchScope::new(res);
         }
 
@@ -202,6 +204,9 @@ impl Definition {
         let mut refs = vec![];
 
         for (file_id, search_range) in search_scope {
+            // Reading the text of a file doesn't check for cancellation by
+            // itself, and searching a big crate takes long.
+            sema.db.check_canceled();
             let text = sema.db.file_text(file_id);
             let search_range =
                 search_range.unwrap_or(TextRange::up_to(TextSize::of(text.as_str())));
@@ -214,12 +219,13 @@ impl Definition {
                     continue;
                 }
 
-                let name_ref: ast::NameRef =
-                    if let Some(name_ref) = sema.find_node_at_offset_with_descend(&tree, offset) {
-                        name_ref
-                    } else {
-                        continue;
-                    };
+                let name_ref: ast::NameRef = if let Some(name_ref) =
+                    sema.find_node_at_offset_with_descend_first(&tree, offset)
+                {
+                    name_ref
+                } else {
+                    continue;
+                };
 
                 match classify_name_ref(&sema, &name_ref) {
                     Some(NameRefClass::Definition(def)) if &def == self => {
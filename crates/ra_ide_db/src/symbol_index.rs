@@ -37,14 +37,17 @@ use ra_db::{
 use ra_prof::profile;
 use ra_syntax::{
     ast::{self, NameOwner},
-    match_ast, AstNode, Parse, SmolStr, SourceFile,
+    match_ast, AstNode, SmolStr, SourceFile,
     SyntaxKind::{self, *},
     SyntaxNode, SyntaxNodePtr, TextRange, WalkEvent,
 };
 use rayon::prelude::*;
 use rustc_hash::{FxHashMap, FxHashSet};
 
-use crate::RootDatabase;
+use crate::{persistent_cache::PersistentCache, RootDatabase};
+
+/// Number of indices searched by one thread.
+const SHARD_SIZE: usize = 32;
 
 #[derive(Debug)]
 pub struct Query {
@@ -53,6 +56,7 @@ pub struct Query {
     only_types: bool,
     libs: bool,
     exact: bool,
+    case_sensitive: bool,
     limit: usize,
 }
 
@@ -65,14 +69,47 @@ impl Query {
             only_types: false,
             libs: false,
             exact: false,
+            case_sensitive: false,
             limit: usize::max_value(),
         }
     }
 
+    /// Parses the query as typed by the user, see the "Workspace Symbol"
+    /// feature for the syntax.
+    pub fn parse(input: &str) -> Query {
+        let mut only_types = false;
+        let mut libs = false;
+        let mut text = String::with_capacity(input.len());
+        for c in input.chars() {
+            match c {
+                '#' => only_types = true,
+                '*' => libs = true,
+                _ => text.push(c),
+            }
+        }
+        let text = text.trim();
+        let (text, exact) = match text.strip_prefix('"').and_then(|it| it.strip_suffix('"')) {
+            Some(quoted) => (quoted, true),
+            None => (text, false),
+        };
+        // "Smart case": queries which contain an upper case letter are
+        // presumably typed that way on purpose.
+        let case_sensitive = text.chars().any(char::is_uppercase);
+
+        let mut res = Query::new(text.to_string());
+        res.only_types = only_types;
+        res.libs = libs;
+        res.exact = exact;
+        res.case_sensitive = case_sensitive;
+        res
+    }
+
     pub fn only_types(&mut self) {
         self.only_types = true;
     }
 
+    /// Includes symbols from libraries in addition to the ones from the
+    /// workspace.
     pub fn libs(&mut self) {
         self.libs = true;
     }
@@ -81,6 +118,10 @@ impl Query {
         self.exact = true;
     }
 
+    pub fn case_sensitive(&mut self) {
+        self.case_sensitive = true;
+    }
+
     pub fn limit(&mut self, limit: usize) {
         self.limit = limit
     }
@@ -98,13 +139,17 @@ pub trait SymbolsDatabase: hir::db::HirDatabase + SourceDatabaseExt {
     /// Files in libraries are assumed to never change.
     #[salsa::input]
     fn library_roots(&self) -> Arc<FxHashSet<SourceRootId>>;
+    /// The symbols of files stored on disk by a previous run.
+    #[salsa::input]
+    fn persistent_cache(&self) -> Arc<PersistentCache>;
 }
 
 fn library_symbols(db: &dyn SymbolsDatabase) -> Arc<FxHashMap<SourceRootId, SymbolIndex>> {
     let _p = profile("library_symbols");
 
-    let roots = db.library_roots();
-    let res = roots
+    let cache = db.persistent_cache();
+    let roots = db
+        .library_roots()
         .iter()
         .map(|&root_id| {
             let root = db.source_root(root_id);
@@ -112,9 +157,21 @@ fn library_symbols(db: &dyn SymbolsDatabase) -> Arc<FxHashMap<SourceRootId, Symb
                 .iter()
                 .map(|it| (it, SourceDatabaseExt::file_text(db, it)))
                 .collect::<Vec<_>>();
-            let symbol_index = SymbolIndex::for_files(
-                files.into_par_iter().map(|(file, text)| (file, SourceFile::parse(&text))),
-            );
+            (root_id, files)
+        })
+        .collect::<Vec<_>>();
+    let res = roots
+        .into_par_iter()
+        .map(|(root_id, files)| {
+            let symbols = files
+                .into_par_iter()
+                .flat_map(|(file_id, text)| {
+                    cache.file_symbols(file_id, &text).unwrap_or_else(|| {
+                        source_file_to_file_symbols(&SourceFile::parse(&text).tree(), file_id)
+                    })
+                })
+                .collect::<Vec<_>>();
+            let symbol_index = SymbolIndex::new(symbols);
             (root_id, symbol_index)
         })
         .collect();
@@ -123,6 +180,11 @@ fn library_symbols(db: &dyn SymbolsDatabase) -> Arc<FxHashMap<SourceRootId, Symb
 
 fn file_symbols(db: &dyn SymbolsDatabase, file_id: FileId) -> Arc<SymbolIndex> {
     db.check_canceled();
+    let cache = db.persistent_cache();
+    let text = SourceDatabaseExt::file_text(db, file_id);
+    if let Some(symbols) = cache.file_symbols(file_id, &text) {
+        return Arc::new(SymbolIndex::new(symbols));
+    }
     let parse = db.parse(file_id);
 
     let symbols = source_file_to_file_symbols(&parse.tree(), file_id);
@@ -145,16 +207,18 @@ impl<DB: ParallelDatabase> Clone for Snap<salsa::Snapshot<DB>> {
 // Uses fuzzy-search to find types, modules and functions by name across your
 // project and dependencies. This is **the** most useful feature, which improves code
 // navigation tremendously. It mostly works on top of the built-in LSP
-// functionality, however `#` and `*` symbols can be used to narrow down the
+// functionality, however `#`, `*` and quotes can be used to narrow down the
 // search. Specifically,
 //
-// - `Foo` searches for `Foo` type in the current workspace
-// - `foo#` searches for `foo` function in the current workspace
-// - `Foo*` searches for `Foo` type among dependencies, including `stdlib`
-// - `foo#*` searches for `foo` function among dependencies
+// - `foo` searches for any `foo` symbol in the current workspace
+// - `Foo#` searches for `Foo` type in the current workspace
+// - `foo*` searches for `foo` among the workspace and its dependencies, including `stdlib`
+// - `Foo#*` searches for `Foo` type among the workspace and its dependencies
+// - `"foo"` searches for symbols named exactly `foo`
 //
-// That is, `#` switches from "types" to all symbols, `*` switches from the current
-// workspace to dependencies.
+// That is, `#` restricts the search to types, `*` extends it to dependencies,
+// and quoting the query disables fuzzy matching.
+// The query is case-insensitive, unless it contains an upper case letter.
 //
 // |===
 // | Editor  | Shortcut
@@ -164,25 +228,24 @@ impl<DB: ParallelDatabase> Clone for Snap<salsa::Snapshot<DB>> {
 pub fn world_symbols(db: &RootDatabase, query: Query) -> Vec<FileSymbol> {
     let _p = ra_prof::profile("world_symbols").detail(|| query.query.clone());
 
-    let tmp1;
-    let tmp2;
-    let buf: Vec<&SymbolIndex> = if query.libs {
-        tmp1 = db.library_symbols();
-        tmp1.values().collect()
-    } else {
-        let mut files = Vec::new();
-        for &root in db.local_roots().iter() {
-            let sr = db.source_root(root);
-            files.extend(sr.iter())
-        }
+    let mut files = Vec::new();
+    for &root in db.local_roots().iter() {
+        let sr = db.source_root(root);
+        files.extend(sr.iter())
+    }
 
-        let snap = Snap(db.snapshot());
-        tmp2 = files
-            .par_iter()
-            .map_with(snap, |db, &file_id| db.0.file_symbols(file_id))
-            .collect::<Vec<_>>();
-        tmp2.iter().map(|it| &**it).collect()
-    };
+    let snap = Snap(db.snapshot());
+    let local_symbols = files
+        .par_iter()
+        .map_with(snap, |db, &file_id| db.0.file_symbols(file_id))
+        .collect::<Vec<_>>();
+    let mut buf: Vec<&SymbolIndex> = local_symbols.iter().map(|it| &**it).collect();
+
+    let library_symbols;
+    if query.libs {
+        library_symbols = db.library_symbols();
+        buf.extend(library_symbols.values());
+    }
     query.search(&buf)
 }
 
@@ -214,6 +277,7 @@ pub fn index_resolve(db: &RootDatabase, name_ref: &ast::NameRef) -> Vec<FileSymb
     let name = name_ref.text();
     let mut query = Query::new(name.to_string());
     query.exact();
+    query.case_sensitive();
     query.limit(4);
     world_symbols(db, query)
 }
@@ -247,9 +311,7 @@ impl Hash for SymbolIndex {
 impl SymbolIndex {
     fn new(mut symbols: Vec<FileSymbol>) -> SymbolIndex {
         fn cmp(lhs: &FileSymbol, rhs: &FileSymbol) -> Ordering {
-            let lhs_chars = lhs.name.chars().map(|c| c.to_ascii_lowercase());
-            let rhs_chars = rhs.name.chars().map(|c| c.to_ascii_lowercase());
-            lhs_chars.cmp(rhs_chars)
+            cmp_names(&lhs.name, &rhs.name)
         }
 
         symbols.par_sort_by(cmp);
@@ -283,17 +345,13 @@ impl SymbolIndex {
         self.symbols.len()
     }
 
-    pub fn memory_size(&self) -> usize {
-        self.map.as_fst().size() + self.symbols.len() * mem::size_of::<FileSymbol>()
+    /// Returns all symbols of the index, sorted by name.
+    pub fn symbols(&self) -> &[FileSymbol] {
+        &self.symbols
     }
 
-    pub(crate) fn for_files(
-        files: impl ParallelIterator<Item = (FileId, Parse<ast::SourceFile>)>,
-    ) -> SymbolIndex {
-        let symbols = files
-            .flat_map(|(file_id, file)| source_file_to_file_symbols(&file.tree(), file_id))
-            .collect::<Vec<_>>();
-        SymbolIndex::new(symbols)
+    pub fn memory_size(&self) -> usize {
+        self.map.as_fst().size() + self.symbols.len() * mem::size_of::<FileSymbol>()
     }
 
     fn range_to_map_value(start: usize, end: usize) -> u64 {
@@ -312,6 +370,18 @@ impl SymbolIndex {
 
 impl Query {
     pub(crate) fn search(self, indices: &[&SymbolIndex]) -> Vec<FileSymbol> {
+        // Every shard yields its first `limit` matches in the order of the
+        // names, so merging them gives the same result as a serial search.
+        let mut res = indices
+            .par_chunks(SHARD_SIZE)
+            .flat_map(|shard| self.search_shard(shard))
+            .collect::<Vec<_>>();
+        res.sort_by(|lhs, rhs| cmp_names(&lhs.name, &rhs.name));
+        res.truncate(self.limit);
+        res
+    }
+
+    fn search_shard(&self, indices: &[&SymbolIndex]) -> Vec<FileSymbol> {
         let mut op = fst::map::OpBuilder::new();
         for file_symbols in indices.iter() {
             let automaton = fst::automaton::Subsequence::new(&self.lowercased);
@@ -328,7 +398,7 @@ impl Query {
                     if self.only_types && !is_type(symbol.kind) {
                         continue;
                     }
-                    if self.exact && symbol.name != self.query {
+                    if !self.matches(&symbol.name) {
                         continue;
                     }
 
@@ -341,10 +411,31 @@ impl Query {
         }
         res
     }
+
+    /// The index is case-insensitive and fuzzy, so the candidates it yields
+    /// need to be checked against the stricter modes.
+    fn matches(&self, name: &str) -> bool {
+        match (self.exact, self.case_sensitive) {
+            (true, true) => name == self.query,
+            (true, false) => name.to_lowercase() == self.lowercased,
+            (false, true) => {
+                let mut name_chars = name.chars();
+                self.query.chars().all(|q| name_chars.any(|c| c == q))
+            }
+            (false, false) => true,
+        }
+    }
+}
+
+/// The order of the keys of the index.
+fn cmp_names(lhs: &str, rhs: &str) -> Ordering {
+    let lhs_chars = lhs.chars().map(|c| c.to_ascii_lowercase());
+    let rhs_chars = rhs.chars().map(|c| c.to_ascii_lowercase());
+    lhs_chars.cmp(rhs_chars)
 }
 
 fn is_type(kind: SyntaxKind) -> bool {
-    matches!(kind, STRUCT_DEF | ENUM_DEF | TRAIT_DEF | TYPE_ALIAS_DEF)
+    matches!(kind, STRUCT_DEF | ENUM_DEF | UNION_DEF | TRAIT_DEF | TYPE_ALIAS_DEF)
 }
 
 /// The actual data that is stored in the index. It should be as compact as
@@ -360,7 +451,10 @@ pub struct FileSymbol {
     pub container_name: Option<SmolStr>,
 }
 
-fn source_file_to_file_symbols(source_file: &SourceFile, file_id: FileId) -> Vec<FileSymbol> {
+pub(crate) fn source_file_to_file_symbols(
+    source_file: &SourceFile,
+    file_id: FileId,
+) -> Vec<FileSymbol> {
     let mut symbols = Vec::new();
     let mut stack = Vec::new();
 
@@ -399,6 +493,7 @@ fn to_symbol(node: &SyntaxNode) -> Option<(SmolStr, SyntaxNodePtr, TextRange)> {
         match node {
             ast::FnDef(it) => decl(it),
             ast::StructDef(it) => decl(it),
+            ast::UnionDef(it) => decl(it),
             ast::EnumDef(it) => decl(it),
             ast::TraitDef(it) => decl(it),
             ast::Module(it) => decl(it),
@@ -428,3 +523,26 @@ fn to_file_symbol(node: &SyntaxNode, file_id: FileId) -> Option<FileSymbol> {
         container_name: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sharded_search_keeps_name_order() {
+        // Enough files for several shards, with the names spread across them.
+        let indices = (0..SHARD_SIZE * 3)
+            .map(|i| {
+                let text = format!("fn f{}() {{}} struct F{};", SHARD_SIZE * 3 - i, i);
+                let file = SourceFile::parse(&text).tree();
+                SymbolIndex::new(source_file_to_file_symbols(&file, FileId(i as u32)))
+            })
+            .collect::<Vec<_>>();
+        let indices = indices.iter().collect::<Vec<_>>();
+
+        let mut query = Query::new("f1".to_string());
+        query.limit(5);
+        let names = query.search(&indices).into_iter().map(|it| it.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["F1", "f1", "F10", "f10", "F11"]);
+    }
+}
@@ -97,8 +97,8 @@ fn expand_subtree(
                 err = err.or(e);
                 arena.push(tt.into());
             }
-            Op::Var { name, kind: _ } => {
-                let ExpandResult(fragment, e) = expand_var(ctx, name);
+            Op::Var { name, id, .. } => {
+                let ExpandResult(fragment, e) = expand_var(ctx, name, id);
                 err = err.or(e);
                 push_fragment(arena, fragment);
             }
@@ -114,12 +114,12 @@ fn expand_subtree(
     ExpandResult(tt::Subtree { delimiter: template.delimiter, token_trees: tts }, err)
 }
 
-fn expand_var(ctx: &mut ExpandCtx, v: &SmolStr) -> ExpandResult<Fragment> {
+fn expand_var(ctx: &mut ExpandCtx, v: &SmolStr, id: tt::TokenId) -> ExpandResult<Fragment> {
     if v == "crate" {
         // We simply produce identifier `$crate` here. And it will be resolved when lowering ast to Path.
-        let tt =
-            tt::Leaf::from(tt::Ident { text: "$crate".into(), id: tt::TokenId::unspecified() })
-                .into();
+        // It keeps the id of `crate` in the definition, so that it can be
+        // mapped back to the macro which wrote it.
+        let tt = tt::Leaf::from(tt::Ident { text: "$crate".into(), id }).into();
         ExpandResult::ok(Fragment::Tokens(tt))
     } else if !ctx.bindings.contains(v) {
         // Note that it is possible to have a `$var` inside a macro which is not bound.
@@ -8,7 +8,7 @@ use crate::{tt_iter::TtIter, ExpandError};
 
 #[derive(Debug)]
 pub(crate) enum Op<'a> {
-    Var { name: &'a SmolStr, kind: Option<&'a SmolStr> },
+    Var { name: &'a SmolStr, kind: Option<&'a SmolStr>, id: tt::TokenId },
     Repeat { subtree: &'a tt::Subtree, kind: RepeatKind, separator: Option<Separator> },
     TokenTree(&'a tt::TokenTree),
 }
@@ -105,13 +105,15 @@ fn next_op<'a>(
                     tt::Leaf::Ident(ident) => {
                         let name = &ident.text;
                         let kind = eat_fragment_kind(src, mode)?;
-                        Op::Var { name, kind }
+                        let id = ident.id;
+                        Op::Var { name, kind, id }
                     }
                     tt::Leaf::Literal(lit) => {
                         if is_boolean_literal(lit) {
                             let name = &lit.text;
                             let kind = eat_fragment_kind(src, mode)?;
-                            Op::Var { name, kind }
+                            let id = lit.id;
+                            Op::Var { name, kind, id }
                         } else {
                             bail!("bad var 2");
                         }
@@ -138,6 +138,11 @@ impl<'a> TokenSource for SubtreeTokenSource<'a> {
             _ => false,
         }
     }
+
+    /// Token trees have no layout, and their delimiters are always balanced.
+    fn is_unindented(&self) -> bool {
+        false
+    }
 }
 
 fn convert_delim(d: Option<tt::DelimiterKind>, closing: bool) -> TtToken {
@@ -155,11 +155,26 @@ pub(crate) fn reparser(
             MODULE => items::mod_item_list,
             _ => return None,
         },
+        STRUCT_DEF | UNION_DEF | ENUM_DEF | FN_DEF | EXTERN_CRATE_ITEM | MODULE | USE_ITEM
+        | STATIC_DEF | CONST_DEF | TRAIT_DEF | IMPL_DEF | TYPE_ALIAS_DEF | MACRO_CALL
+        | EXTERN_BLOCK
+            if parent? == SOURCE_FILE =>
+        {
+            top_level_item
+        }
         _ => return None,
     };
     Some(res)
 }
 
+/// Parses an item within a `SOURCE_FILE`, so that the leading comments are
+/// attached to the item like in a full parse.
+fn top_level_item(p: &mut Parser) {
+    let m = p.start();
+    items::item_or_macro(p, false, items::ItemFlavor::Mod);
+    m.complete(p, SOURCE_FILE);
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum BlockLike {
     Block,
@@ -147,10 +147,20 @@ pub(super) fn stmt(p: &mut Parser, with_semi: StmtWithSemi) {
     //     let _: ! = {};
     //     let f = #[attr]||{};
     // }
+
+    // test let_stmt_or_pat
+    // fn main() {
+    //     let A | B = x;
+    //     let | A | B = x;
+    //     let (A | B, C): (T, T) = x;
+    //     let S { f: A | B, g: box (C | D) } = x;
+    //     let x @ (A | B) = y;
+    //     let f = |(A | B), [C | D, ..]: [T; 2]| ();
+    // }
     fn let_stmt(p: &mut Parser, m: Marker, with_semi: StmtWithSemi) {
         assert!(p.at(T![let]));
         p.bump(T![let]);
-        patterns::pattern(p);
+        patterns::pattern_top(p);
         if p.at(T![:]) {
             types::ascription(p);
         }
@@ -177,7 +187,7 @@ pub(super) fn expr_block_contents(p: &mut Parser) {
     // This is checked by a validator
     attributes::inner_attributes(p);
 
-    while !p.at(EOF) && !p.at(T!['}']) {
+    while !p.at(EOF) && !p.at(T!['}']) && !items::at_unindented_item(p) {
         // test nocontentexpr
         // fn foo(){
         //     ;;;some_expr();;;;{;;;};;;;Ok(())
@@ -331,6 +341,7 @@ fn lhs(p: &mut Parser, r: Restrictions) -> Option<(CompletedMarker, BlockLike)>
         //     // raw reference operator
         //     let _ = &raw mut foo;
         //     let _ = &raw const foo;
+        //     let _ = &&raw const *foo;
         // }
         T![&] => {
             m = p.start();
@@ -608,7 +619,7 @@ pub(crate) fn record_field_list(p: &mut Parser) {
     assert!(p.at(T!['{']));
     let m = p.start();
     p.bump(T!['{']);
-    while !p.at(EOF) && !p.at(T!['}']) {
+    while !p.at(EOF) && !p.at(T!['}']) && !items::at_unindented_item(p) {
         let m = p.start();
         // test record_literal_field_with_attr
         // fn main() {
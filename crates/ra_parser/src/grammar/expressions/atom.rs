@@ -138,7 +138,11 @@ pub(super) fn atom_expr(p: &mut Parser, r: Restrictions) -> Option<(CompletedMar
         T![continue] => continue_expr(p),
         T![break] => break_expr(p, r),
         _ => {
-            p.err_recover("expected expression", EXPR_RECOVERY_SET);
+            if items::at_unindented_item(p) {
+                p.error("expected expression");
+            } else {
+                p.err_recover("expected expression", EXPR_RECOVERY_SET);
+            }
             return None;
         }
     };
@@ -322,12 +326,13 @@ fn while_expr(p: &mut Parser, m: Option<Marker>) -> CompletedMarker {
 // test for_expr
 // fn foo() {
 //     for x in [] {};
+//     for | Some(x) | Ok(x) in [] {};
 // }
 fn for_expr(p: &mut Parser, m: Option<Marker>) -> CompletedMarker {
     assert!(p.at(T![for]));
     let m = m.unwrap_or_else(|| p.start());
     p.bump(T![for]);
-    patterns::pattern(p);
+    patterns::pattern_top(p);
     p.expect(T![in]);
     expr_no_struct(p);
     block_expr(p);
@@ -388,7 +393,7 @@ pub(crate) fn match_arm_list(p: &mut Parser) {
     // }
     attributes::inner_attributes(p);
 
-    while !p.at(EOF) && !p.at(T!['}']) {
+    while !p.at(EOF) && !p.at(T!['}']) && !items::at_unindented_item(p) {
         if p.at(T!['{']) {
             error_block(p, "expected match arm");
             continue;
@@ -21,7 +21,7 @@ use super::*;
 // struct S;
 pub(super) fn mod_contents(p: &mut Parser, stop_on_r_curly: bool) {
     attributes::inner_attributes(p);
-    while !(stop_on_r_curly && p.at(T!['}']) || p.at(EOF)) {
+    while !(stop_on_r_curly && (p.at(T!['}']) || at_unindented_item(p)) || p.at(EOF)) {
         item_or_macro(p, stop_on_r_curly, ItemFlavor::Mod)
     }
 }
@@ -36,6 +36,49 @@ pub(super) const ITEM_RECOVERY_SET: TokenSet = token_set![
     CRATE_KW, USE_KW, MACRO_KW
 ];
 
+// test_err unindented_item_recovery
+// fn foo() {
+//     let x = foo(1,
+// fn bar() {
+//     if true {
+// }
+// struct S {
+//     a: u32,
+// impl S {
+//     fn f(&self) {
+// }
+// #[test]
+// fn baz() {}
+
+/// Checks if the parser is at an item which starts an unindented line while
+/// some `{` are never closed. Nested items are indented, so such an item most
+/// likely means that the enclosing `{` lacks its `}`: the lists stop there, to
+/// parse the rest of the file as top-level items.
+pub(super) fn at_unindented_item(p: &Parser) -> bool {
+    if !p.at_unindented() {
+        return false;
+    }
+    match p.current() {
+        T![fn]
+        | T![struct]
+        | T![enum]
+        | T![impl]
+        | T![trait]
+        | T![mod]
+        | T![use]
+        | T![static]
+        | T![type]
+        | T![extern]
+        | T![pub]
+        | T![#] => true,
+        T![const] | T![unsafe] => !p.nth_at(1, T!['{']),
+        T![async] => p.nth_at(1, T![fn]),
+        IDENT if p.at_contextual_kw("union") => p.nth_at(1, IDENT),
+        IDENT if p.at_contextual_kw("macro_rules") => p.nth_at(1, T![!]),
+        _ => false,
+    }
+}
+
 pub(super) fn item_or_macro(p: &mut Parser, stop_on_r_curly: bool, flavor: ItemFlavor) {
     let m = p.start();
     attributes::outer_attributes(p);
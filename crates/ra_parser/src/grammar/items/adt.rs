@@ -71,7 +71,7 @@ pub(crate) fn enum_variant_list(p: &mut Parser) {
     assert!(p.at(T!['{']));
     let m = p.start();
     p.bump(T!['{']);
-    while !p.at(EOF) && !p.at(T!['}']) {
+    while !p.at(EOF) && !p.at(T!['}']) && !at_unindented_item(p) {
         if p.at(T!['{']) {
             error_block(p, "expected enum variant");
             continue;
@@ -108,7 +108,7 @@ pub(crate) fn record_field_def_list(p: &mut Parser) {
     assert!(p.at(T!['{']));
     let m = p.start();
     p.bump(T!['{']);
-    while !p.at(T!['}']) && !p.at(EOF) {
+    while !p.at(T!['}']) && !p.at(EOF) && !at_unindented_item(p) {
         if p.at(T!['{']) {
             error_block(p, "expected field");
             continue;
@@ -42,7 +42,7 @@ pub(crate) fn trait_item_list(p: &mut Parser) {
     assert!(p.at(T!['{']));
     let m = p.start();
     p.bump(T!['{']);
-    while !p.at(EOF) && !p.at(T!['}']) {
+    while !p.at(EOF) && !p.at(T!['}']) && !at_unindented_item(p) {
         if p.at(T!['{']) {
             error_block(p, "expected an item");
             continue;
@@ -99,7 +99,7 @@ pub(crate) fn impl_item_list(p: &mut Parser) {
     // }
     attributes::inner_attributes(p);
 
-    while !p.at(EOF) && !p.at(T!['}']) {
+    while !p.at(EOF) && !p.at(T!['}']) && !at_unindented_item(p) {
         if p.at(T!['{']) {
             error_block(p, "expected an item");
             continue;
@@ -121,7 +121,7 @@ pub(crate) fn use_tree_list(p: &mut Parser) {
     assert!(p.at(T!['{']));
     let m = p.start();
     p.bump(T!['{']);
-    while !p.at(EOF) && !p.at(T!['}']) {
+    while !p.at(EOF) && !p.at(T!['}']) && !at_unindented_item(p) {
         use_tree(p, false);
         if !p.at(T!['}']) {
             p.expect(T![,]);
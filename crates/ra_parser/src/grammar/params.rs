@@ -87,6 +87,10 @@ fn value_parameter(p: &mut Parser, flavor: Flavor) -> Variadic {
 
         // test fn_def_param
         // fn foo((x, y): (i32, i32)) {}
+        // fn bar((A | B): T, [C | D]: T) {}
+
+        // test_err fn_def_param_or_pat
+        // fn foo(A | B: T) {}
         Flavor::FnDef => {
             patterns::pattern(p);
             if variadic_param(p) {
@@ -59,13 +59,21 @@ fn pattern_single_r(p: &mut Parser, recovery_set: TokenSet) {
         //         0 ... 100 => (),
         //         101 ..= 200 => (),
         //         200 .. 301=> (),
+        //         302 .. => (),
         //     }
+        //     if let -1.. | X.. = 0 {}
         // }
         for &range_op in [T![...], T![..=], T![..]].iter() {
             if p.at(range_op) {
                 let m = lhs.precede(p);
                 p.bump(range_op);
-                atom_pat(p, recovery_set);
+                // test_err half_open_inclusive_range_pat
+                // fn main() { match 92 { 0..= => (), 0... => () } }
+                if is_range_end_start(p) {
+                    atom_pat(p, recovery_set);
+                } else if range_op != T![..] {
+                    p.error("inclusive range patterns must have an end");
+                }
                 m.complete(p, RANGE_PAT);
                 return;
             }
@@ -73,6 +81,12 @@ fn pattern_single_r(p: &mut Parser, recovery_set: TokenSet) {
     }
 }
 
+/// Half-open range patterns like `0..` are only followed by tokens which can't
+/// start a range end.
+fn is_range_end_start(p: &Parser) -> bool {
+    is_literal_pat_start(p) || paths::is_path_start(p)
+}
+
 const PAT_RECOVERY_SET: TokenSet =
     token_set![LET_KW, IF_KW, WHILE_KW, LOOP_KW, MATCH_KW, R_PAREN, COMMA];
 
@@ -190,7 +204,7 @@ fn record_field_pat_list(p: &mut Parser) {
     assert!(p.at(T!['{']));
     let m = p.start();
     p.bump(T!['{']);
-    while !p.at(EOF) && !p.at(T!['}']) {
+    while !p.at(EOF) && !p.at(T!['}']) && !items::at_unindented_item(p) {
         match p.current() {
             // A trailing `..` is *not* treated as a DOT_DOT_PAT.
             T![.] if p.at(T![..]) => p.bump(T![..]),
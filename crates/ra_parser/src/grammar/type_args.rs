@@ -25,6 +25,32 @@ pub(super) fn opt_type_arg_list(p: &mut Parser, colon_colon_required: bool) {
     m.complete(p, TYPE_ARG_LIST);
 }
 
+fn at_const_arg(p: &Parser) -> bool {
+    p.at(T!['{'])
+        || p.at_ts(expressions::LITERAL_FIRST)
+        || (p.at(T![-]) && expressions::LITERAL_FIRST.contains(p.nth(1)))
+}
+
+/// Parses the value of a const argument or of a const parameter default. Like
+/// rustc, we only accept blocks and (possibly negated) literals, other
+/// expressions must be wrapped into a block.
+pub(super) fn const_arg_expr(p: &mut Parser) {
+    match p.current() {
+        T!['{'] => expressions::block_expr(p),
+        T![-] if expressions::LITERAL_FIRST.contains(p.nth(1)) => {
+            let m = p.start();
+            p.bump(T![-]);
+            expressions::literal(p);
+            m.complete(p, PREFIX_EXPR);
+        }
+        _ => {
+            if expressions::literal(p).is_none() {
+                p.error("expected a block or a literal");
+            }
+        }
+    }
+}
+
 // test type_arg
 // type A = B<'static, i32, 1, { 2 }, Item=u64>;
 fn type_arg(p: &mut Parser) {
@@ -47,12 +73,10 @@ fn type_arg(p: &mut Parser) {
             types::type_(p);
             m.complete(p, ASSOC_TYPE_ARG);
         }
-        T!['{'] => {
-            expressions::block_expr(p);
-            m.complete(p, CONST_ARG);
-        }
-        k if k.is_literal() => {
-            p.bump(k);
+        // test const_arg
+        // type T = S<-92, true, 'a', { N + 1 }, _>;
+        _ if at_const_arg(p) => {
+            const_arg_expr(p);
             m.complete(p, CONST_ARG);
         }
         _ => {
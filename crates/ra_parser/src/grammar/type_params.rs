@@ -65,11 +65,17 @@ fn type_param(p: &mut Parser, m: Marker) {
 
 // test const_param
 // struct S<const N: u32>;
+// impl<const N: usize, T> S<N, T> {}
 fn type_const_param(p: &mut Parser, m: Marker) {
     assert!(p.at(CONST_KW));
     p.bump(T![const]);
     name(p);
     types::ascription(p);
+    // test const_param_default
+    // struct A<const N: i32 = -1, const M: usize = { 1 + 2 }>;
+    if p.eat(T![=]) {
+        type_args::const_arg_expr(p);
+    }
     m.complete(p, CONST_PARAM);
 }
 
@@ -41,6 +41,10 @@ pub trait TokenSource {
 
     /// Is the current token a specified keyword?
     fn is_keyword(&self, kw: &str) -> bool;
+
+    /// Does the current token start an unindented line, while some `{` of the
+    /// source are never closed? This is a hint to recover from a missing `}`.
+    fn is_unindented(&self) -> bool;
 }
 
 /// `Token` abstracts the cursor of `TokenSource` operates on.
@@ -126,7 +130,8 @@ pub fn parse_fragment(
 pub struct Reparser(fn(&mut parser::Parser));
 
 impl Reparser {
-    /// If the node is a braced block, return the corresponding `Reparser`.
+    /// If the node is a braced block or a top-level item, return the
+    /// corresponding `Reparser`.
     pub fn for_node(
         node: SyntaxKind,
         first_child: Option<SyntaxKind>,
@@ -137,8 +142,8 @@ impl Reparser {
 
     /// Re-parse given tokens using this `Reparser`.
     ///
-    /// Tokens must start with `{`, end with `}` and form a valid brace
-    /// sequence.
+    /// For blocks, tokens must start with `{`, end with `}` and form a valid
+    /// brace sequence.
     pub fn parse(self, token_source: &mut dyn TokenSource, tree_sink: &mut dyn TreeSink) {
         let Reparser(r) = self;
         let mut p = parser::Parser::new(token_source);
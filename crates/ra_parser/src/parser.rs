@@ -157,6 +157,12 @@ impl<'t> Parser<'t> {
         self.token_source.is_keyword(kw)
     }
 
+    /// Checks if the current token starts an unindented line of a source with
+    /// unclosed `{`.
+    pub(crate) fn at_unindented(&self) -> bool {
+        self.token_source.is_unindented()
+    }
+
     /// Starts a new node in the syntax tree. All nodes and tokens
     /// consumed between the `start` and the corresponding `Marker::complete`
     /// belong to the same node.
@@ -40,9 +40,9 @@ impl ra_tt::TokenExpander for ProcMacroProcessExpander {
     fn expand(
         &self,
         subtree: &Subtree,
-        _attr: Option<&Subtree>,
+        attr: Option<&Subtree>,
     ) -> Result<Subtree, ra_tt::ExpansionError> {
-        self.process.custom_derive(&self.dylib_path, subtree, &self.name)
+        self.process.expand(&self.dylib_path, subtree, attr, &self.name)
     }
 }
 
@@ -75,7 +75,7 @@ impl ProcMacroClient {
     pub fn by_dylib_path(
         &self,
         dylib_path: &Path,
-    ) -> Vec<(SmolStr, Arc<dyn ra_tt::TokenExpander>)> {
+    ) -> Vec<(SmolStr, ProcMacroKind, Arc<dyn ra_tt::TokenExpander>)> {
         match &self.kind {
             ProcMacroClientKind::Dummy => vec![],
             ProcMacroClientKind::Process { process, .. } => {
@@ -89,21 +89,15 @@ impl ProcMacroClient {
 
                 macros
                     .into_iter()
-                    .filter_map(|(name, kind)| {
-                        // FIXME: Support custom derive only for now.
-                        match kind {
-                            ProcMacroKind::CustomDerive => {
-                                let name = SmolStr::new(&name);
-                                let expander: Arc<dyn ra_tt::TokenExpander> =
-                                    Arc::new(ProcMacroProcessExpander {
-                                        process: process.clone(),
-                                        name: name.clone(),
-                                        dylib_path: dylib_path.into(),
-                                    });
-                                Some((name, expander))
-                            }
-                            _ => None,
-                        }
+                    .map(|(name, kind)| {
+                        let name = SmolStr::new(&name);
+                        let expander: Arc<dyn ra_tt::TokenExpander> =
+                            Arc::new(ProcMacroProcessExpander {
+                                process: process.clone(),
+                                name: name.clone(),
+                                dylib_path: dylib_path.into(),
+                            });
+                        (name, kind, expander)
                     })
                     .collect()
             }
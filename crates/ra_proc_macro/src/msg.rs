@@ -11,8 +11,14 @@ use crate::{
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+/// Version of the protocol spoken between the client and the server. Bump it whenever a message
+/// changes in an incompatible way, so that a stale server binary is rejected at startup instead
+/// of failing on every expansion.
+pub const CURRENT_API_VERSION: u32 = 1;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Request {
+    ApiVersion,
     ListMacro(ListMacrosTask),
     ExpansionMacro(ExpansionTask),
 }
@@ -20,6 +26,7 @@ pub enum Request {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Response {
     Error(ResponseError),
+    ApiVersion(u32),
     ListMacro(ListMacrosResult),
     ExpansionMacro(ExpansionResult),
 }
@@ -38,6 +45,7 @@ macro_rules! impl_try_from_response {
     };
 }
 
+impl_try_from_response!(u32, ApiVersion);
 impl_try_from_response!(ListMacrosResult, ListMacro);
 impl_try_from_response!(ExpansionResult, ExpansionMacro);
 
@@ -3,7 +3,7 @@
 use crossbeam_channel::{bounded, Receiver, Sender};
 use ra_tt::Subtree;
 
-use crate::msg::{ErrorCode, Message, Request, Response, ResponseError};
+use crate::msg::{ErrorCode, Message, Request, Response, ResponseError, CURRENT_API_VERSION};
 use crate::rpc::{ExpansionResult, ExpansionTask, ListMacrosResult, ListMacrosTask, ProcMacroKind};
 
 use io::{BufRead, BufReader};
@@ -44,9 +44,29 @@ impl ProcMacroProcessSrv {
         let srv = ProcMacroProcessSrv { inner: Some(Arc::downgrade(&task_tx)) };
         let thread = ProcMacroProcessThread { handle, sender: task_tx };
 
+        let version = srv.version().map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("proc macro server didn't report its api version: {:?}", err),
+            )
+        })?;
+        if version != CURRENT_API_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "proc macro server api version is {}, but {} is required",
+                    version, CURRENT_API_VERSION
+                ),
+            ));
+        }
+
         Ok((thread, srv))
     }
 
+    pub fn version(&self) -> Result<u32, ra_tt::ExpansionError> {
+        self.send_task(Request::ApiVersion)
+    }
+
     pub fn find_proc_macros(
         &self,
         dylib_path: &Path,
@@ -57,16 +77,17 @@ impl ProcMacroProcessSrv {
         Ok(result.macros)
     }
 
-    pub fn custom_derive(
+    pub fn expand(
         &self,
         dylib_path: &Path,
         subtree: &Subtree,
-        derive_name: &str,
+        attributes: Option<&Subtree>,
+        macro_name: &str,
     ) -> Result<Subtree, ra_tt::ExpansionError> {
         let task = ExpansionTask {
             macro_body: subtree.clone(),
-            macro_name: derive_name.to_string(),
-            attributes: None,
+            macro_name: macro_name.to_string(),
+            attributes: attributes.cloned(),
             lib: dylib_path.to_path_buf(),
         };
 
@@ -9,6 +9,7 @@ pub fn run() -> io::Result<()> {
 
     while let Some(req) = read_request()? {
         let res = match req {
+            msg::Request::ApiVersion => Ok(msg::Response::ApiVersion(msg::CURRENT_API_VERSION)),
             msg::Request::ListMacro(task) => srv.list_macros(&task).map(msg::Response::ListMacro),
             msg::Request::ExpansionMacro(task) => {
                 srv.expand(&task).map(msg::Response::ExpansionMacro)
@@ -2,11 +2,12 @@
 use once_cell::sync::Lazy;
 use std::{
     cell::RefCell,
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, io,
     io::{stderr, Write},
     sync::{
         atomic::{AtomicBool, Ordering},
-        RwLock,
+        Mutex, RwLock,
     },
     time::{Duration, Instant},
 };
@@ -17,6 +18,9 @@ use crate::tree::{Idx, Tree};
 /// env RA_PROFILE=*             // dump everything
 /// env RA_PROFILE=foo|bar|baz   // enabled only selected entries
 /// env RA_PROFILE=*@3>10        // dump everything, up to depth 3, if it takes more than 10 ms
+///
+/// While profiling is enabled, the timings of all profiled scopes are also
+/// aggregated, see [`take_summary`].
 pub fn init() {
     let spec = std::env::var("RA_PROFILE").unwrap_or_default();
     init_from(&spec);
@@ -27,6 +31,13 @@ pub fn init_from(spec: &str) {
     filter.install();
 }
 
+/// Like [`init_from`], but only aggregates the timings, without printing the
+/// profiled scopes to stderr.
+pub fn init_summary_from(spec: &str) {
+    let filter = if spec.is_empty() { Filter::disabled() } else { Filter::from_spec(spec) };
+    Filter { print: false, ..filter }.install();
+}
+
 pub type Label = &'static str;
 
 /// This function starts a profiling scope in the current execution stack with a given description.
@@ -93,6 +104,7 @@ impl Drop for ProfilerImpl {
 
 static PROFILING_ENABLED: AtomicBool = AtomicBool::new(false);
 static FILTER: Lazy<RwLock<Filter>> = Lazy::new(Default::default);
+static SUMMARY: Lazy<Mutex<ProfileSummary>> = Lazy::new(Default::default);
 thread_local!(static PROFILE_STACK: RefCell<ProfileStack> = RefCell::new(ProfileStack::new()));
 
 #[derive(Default, Clone, Debug)]
@@ -100,6 +112,8 @@ struct Filter {
     depth: usize,
     allowed: HashSet<String>,
     longer_than: Duration,
+    /// Whether to print the profiled scopes, or only to aggregate them.
+    print: bool,
     version: usize,
 }
 
@@ -126,7 +140,7 @@ impl Filter {
         };
         let allowed =
             if spec == "*" { HashSet::new() } else { spec.split('|').map(String::from).collect() };
-        Filter { depth, allowed, longer_than, version: 0 }
+        Filter { depth, allowed, longer_than, print: true, version: 0 }
     }
 
     fn install(mut self) {
@@ -185,11 +199,15 @@ impl ProfileStack {
             // Convert to millis for comparison to avoid problems with rounding
             // (otherwise we could print `0ms` despite user's `>0` filter when
             // `duration` is just a few nanos).
-            if duration.as_millis() > longer_than.as_millis() {
+            if self.filter.print && duration.as_millis() > longer_than.as_millis() {
                 if let Some(root) = self.messages.root() {
                     print(&self.messages, root, 0, longer_than, &mut stderr().lock());
                 }
             }
+            if let Some(root) = self.messages.root() {
+                let mut summary = SUMMARY.lock().unwrap();
+                summary.add(&self.messages, root, &mut Vec::new());
+            }
             self.messages.clear();
         }
     }
@@ -241,3 +259,125 @@ fn print(
             .expect("printing profiling info");
     }
 }
+
+/// Returns the timings aggregated since the last call, and starts over.
+pub fn take_summary() -> ProfileSummary {
+    std::mem::take(&mut *SUMMARY.lock().unwrap())
+}
+
+/// Timings of all profiled scopes, aggregated by label and by stack.
+#[derive(Default, Debug)]
+pub struct ProfileSummary {
+    labels: HashMap<Label, LabelTotal>,
+    /// Self time of each stack of labels, separated by `;`.
+    stacks: BTreeMap<String, Duration>,
+}
+
+/// The timings of all scopes with the same label. Only code which calls
+/// [`profile`] is measured: some salsa queries do so with a `*_query` label,
+/// most of them are only accounted for in the scopes they are called from.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct LabelTotal {
+    pub count: usize,
+    /// Time spent inside of the scope, not counting recursive occurrences twice.
+    pub total: Duration,
+    /// Time spent inside of the scope, but outside of nested scopes.
+    pub self_time: Duration,
+}
+
+impl ProfileSummary {
+    pub fn labels(&self) -> Vec<(Label, LabelTotal)> {
+        let mut res = self.labels.iter().map(|(&label, &total)| (label, total)).collect::<Vec<_>>();
+        res.sort_by(|(l1, t1), (l2, t2)| t2.total.cmp(&t1.total).then(l1.cmp(l2)));
+        res
+    }
+
+    /// Writes the stacks in the "folded" format understood by `inferno` and
+    /// `flamegraph.pl`: one `label;label;label microseconds` line per stack.
+    pub fn write_folded_stacks(&self, out: &mut impl Write) -> io::Result<()> {
+        for (stack, self_time) in self.stacks.iter() {
+            writeln!(out, "{} {}", stack, self_time.as_micros())?;
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, tree: &Tree<Message>, curr: Idx<Message>, path: &mut Vec<Label>) {
+        let Message { label, duration, .. } = tree[curr];
+        let children_duration = tree.children(curr).map(|child| tree[child].duration).sum();
+        let self_time = duration.checked_sub(children_duration).unwrap_or_default();
+
+        let total = self.labels.entry(label).or_default();
+        total.count += 1;
+        total.self_time += self_time;
+        if !path.contains(&label) {
+            total.total += duration;
+        }
+
+        path.push(label);
+        *self.stacks.entry(path.join(";")).or_default() += self_time;
+        for child in tree.children(curr) {
+            self.add(tree, child, path);
+        }
+        path.pop();
+    }
+}
+
+impl fmt::Display for ProfileSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>10} {:>10} {:>8}  label", "total", "self", "calls")?;
+        for (label, total) in self.labels() {
+            writeln!(
+                f,
+                "{:>8}ms {:>8}ms {:>8}  {}",
+                total.total.as_millis(),
+                total.self_time.as_millis(),
+                total.count,
+                label
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_aggregates_labels_and_stacks() {
+        let mut tree = Tree::default();
+        let message = |label, millis| Message {
+            duration: Duration::from_millis(millis),
+            label,
+            detail: None,
+        };
+        tree.start();
+        tree.start();
+        tree.start();
+        tree.finish(message("infer_query", 3));
+        tree.finish(message("infer_query", 5));
+        tree.start();
+        tree.finish(message("trait_solve_query", 2));
+        tree.finish(message("handle_completion", 10));
+
+        let mut summary = ProfileSummary::default();
+        summary.add(&tree, tree.root().unwrap(), &mut Vec::new());
+
+        let infer = summary.labels[&"infer_query"];
+        assert_eq!(infer.count, 2);
+        assert_eq!(infer.total, Duration::from_millis(5));
+        assert_eq!(infer.self_time, Duration::from_millis(5));
+        assert_eq!(summary.labels()[0].0, "handle_completion");
+
+        let mut folded = Vec::new();
+        summary.write_folded_stacks(&mut folded).unwrap();
+        assert_eq!(
+            String::from_utf8(folded).unwrap(),
+            "handle_completion 3000
+handle_completion;infer_query 2000
+handle_completion;infer_query;infer_query 3000
+handle_completion;trait_solve_query 2000
+"
+        );
+    }
+}
@@ -9,7 +9,9 @@ mod tree;
 use std::cell::RefCell;
 
 pub use crate::{
-    hprof::{init, init_from, profile},
+    hprof::{
+        init, init_from, init_summary_from, profile, take_summary, LabelTotal, ProfileSummary,
+    },
     memory_usage::{Bytes, MemoryUsage},
 };
 
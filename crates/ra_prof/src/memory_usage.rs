@@ -17,10 +17,74 @@ impl MemoryUsage {
         }
     }
 
-    #[cfg(any(not(feature = "jemalloc"), target_env = "msvc"))]
+    /// Without jemalloc, glibc's own allocator statistics are good enough.
+    #[cfg(all(not(feature = "jemalloc"), target_os = "linux", target_env = "gnu"))]
+    pub fn current() -> MemoryUsage {
+        if let Some(mallinfo) = mallinfo2() {
+            let allocated = mallinfo.uordblks + mallinfo.hblkhd;
+            let resident = mallinfo.arena + mallinfo.hblkhd;
+            return MemoryUsage { allocated: Bytes(allocated), resident: Bytes(resident) };
+        }
+        // `mallinfo` reports `int`s which wrap around past 4Gb, but that is
+        // still better than no data at all.
+        let mallinfo = unsafe { libc::mallinfo() };
+        let allocated = mallinfo.uordblks as u32 as usize + mallinfo.hblkhd as u32 as usize;
+        let resident = mallinfo.arena as u32 as usize + mallinfo.hblkhd as u32 as usize;
+        MemoryUsage { allocated: Bytes(allocated), resident: Bytes(resident) }
+    }
+
+    #[cfg(not(any(
+        all(feature = "jemalloc", not(target_env = "msvc")),
+        all(target_os = "linux", target_env = "gnu")
+    )))]
     pub fn current() -> MemoryUsage {
         MemoryUsage { allocated: Bytes(0), resident: Bytes(0) }
     }
+
+    /// Whether `current` reports the actual usage, rather than zero or values
+    /// which wrap around.
+    pub fn is_accurate() -> bool {
+        if cfg!(all(feature = "jemalloc", not(target_env = "msvc"))) {
+            return true;
+        }
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        {
+            mallinfo2().is_some()
+        }
+        #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+        {
+            false
+        }
+    }
+}
+
+/// The fields of `struct mallinfo2`, which are `size_t`s unlike the ones of
+/// `struct mallinfo`.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+#[repr(C)]
+struct Mallinfo2 {
+    arena: usize,
+    ordblks: usize,
+    smblks: usize,
+    hblks: usize,
+    hblkhd: usize,
+    usmblks: usize,
+    fsmblks: usize,
+    uordblks: usize,
+    fordblks: usize,
+    keepcost: usize,
+}
+
+/// Calls `mallinfo2`, which only exists since glibc 2.33, so it is looked up
+/// at runtime.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn mallinfo2() -> Option<Mallinfo2> {
+    let symbol = unsafe { libc::dlsym(libc::RTLD_DEFAULT, b"mallinfo2\0".as_ptr().cast()) };
+    if symbol.is_null() {
+        return None;
+    }
+    let mallinfo2: extern "C" fn() -> Mallinfo2 = unsafe { std::mem::transmute(symbol) };
+    Some(mallinfo2())
 }
 
 impl fmt::Display for MemoryUsage {
@@ -32,6 +96,12 @@ impl fmt::Display for MemoryUsage {
 #[derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub struct Bytes(usize);
 
+impl Bytes {
+    pub fn megabytes(self) -> usize {
+        self.0 / 1024 / 1024
+    }
+}
+
 impl fmt::Display for Bytes {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let bytes = self.0;
@@ -55,9 +125,41 @@ impl std::ops::AddAssign<usize> for Bytes {
     }
 }
 
+impl std::ops::Add for Bytes {
+    type Output = Bytes;
+    fn add(self, rhs: Bytes) -> Bytes {
+        Bytes(self.0 + rhs.0)
+    }
+}
+
+/// Memory can be freed between two measurements, so the difference saturates
+/// at zero.
 impl std::ops::Sub for Bytes {
     type Output = Bytes;
     fn sub(self, rhs: Bytes) -> Bytes {
-        Bytes(self.0 - rhs.0)
+        Bytes(self.0.saturating_sub(rhs.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bytes, MemoryUsage};
+
+    #[test]
+    fn sub_saturates_when_memory_was_freed() {
+        assert!(Bytes(1024) - Bytes(4096) == Bytes(0));
+        assert!(Bytes(4096) - Bytes(1024) == Bytes(3072));
+    }
+
+    #[test]
+    fn accurate_usage_sees_allocations() {
+        if !MemoryUsage::is_accurate() {
+            return;
+        }
+        let before = MemoryUsage::current().allocated;
+        let buf = vec![1u8; 64 * 1024 * 1024];
+        let after = MemoryUsage::current().allocated;
+        drop(buf);
+        assert!(after - before >= Bytes(32 * 1024 * 1024));
     }
 }
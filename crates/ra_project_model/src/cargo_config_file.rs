@@ -0,0 +1,276 @@
+//! Reads the settings of `.cargo/config` files which affect how crates are
+//! compiled.
+//!
+//! Cargo merges the `.cargo/config.toml` (or legacy `.cargo/config`) files of
+//! the project directory, all its ancestors, and `$CARGO_HOME`, the deeper
+//! files taking precedence. Instead of reimplementing the parsing and the
+//! merging, we ask cargo for the merged configuration via `cargo config get`.
+
+use std::{convert::TryFrom, env, fs, iter, path::PathBuf, process::Command};
+
+use anyhow::Result;
+use paths::{AbsPath, AbsPathBuf};
+use rustc_hash::FxHashMap;
+use serde_json::Value;
+
+use crate::output;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct CargoConfigFile {
+    /// `build.target`, or `CARGO_BUILD_TARGET`.
+    pub(crate) target: Option<String>,
+    /// `--cfg` flags passed via the rustflags settings, or `RUSTFLAGS`.
+    pub(crate) cfgs: Vec<String>,
+    /// Variables from the `[env]` table.
+    pub(crate) env: Vec<(String, String)>,
+    /// Commands from the `[alias]` table, split into words.
+    pub(crate) aliases: FxHashMap<String, Vec<String>>,
+}
+
+impl CargoConfigFile {
+    /// Collects the settings which apply to a project in `dir`, with `target`
+    /// overriding the one configured in the files.
+    pub(crate) fn discover(dir: &AbsPath, target: Option<&str>) -> CargoConfigFile {
+        let config = cargo_config_get(dir).unwrap_or_else(|err| {
+            log::warn!("failed to read the cargo configuration of {}: {}", dir.display(), err);
+            Value::Null
+        });
+        let env_var = |name: &str| env::var(name).ok().filter(|it| !it.is_empty());
+        CargoConfigFile::from_json(
+            &config,
+            &|name| relative_base(dir, name),
+            target.map(|it| it.to_string()).or_else(|| env_var("CARGO_BUILD_TARGET")),
+            env_var("CARGO_ENCODED_RUSTFLAGS")
+                .map(|it| it.split('\x1f').map(|it| it.to_string()).collect())
+                .or_else(|| {
+                    env_var("RUSTFLAGS")
+                        .map(|it| it.split_whitespace().map(|it| it.to_string()).collect())
+                }),
+        )
+    }
+
+    /// Extracts the settings from the output of `cargo config get`.
+    /// `relative_base` returns the directory against which the relative
+    /// `[env]` variable with the given name is resolved.
+    fn from_json(
+        config: &Value,
+        relative_base: &dyn Fn(&str) -> AbsPathBuf,
+        target: Option<String>,
+        rustflags: Option<Vec<String>>,
+    ) -> CargoConfigFile {
+        let target = target.or_else(|| match &config["build"]["target"] {
+            Value::String(it) => Some(it.clone()),
+            // Cargo accepts a list of targets, we can only analyze one.
+            Value::Array(it) => it.first().and_then(|it| it.as_str()).map(|it| it.to_string()),
+            _ => None,
+        });
+
+        // The target specific rustflags replace `build.rustflags`.
+        let rustflags = rustflags.unwrap_or_else(|| {
+            let target_flags =
+                target.as_ref().and_then(|it| words(&config["target"][it]["rustflags"]));
+            target_flags.or_else(|| words(&config["build"]["rustflags"])).unwrap_or_default()
+        });
+        let cfgs = cfgs_from_rustflags(&rustflags);
+
+        let mut env = Vec::new();
+        for (name, value) in config["env"].as_object().into_iter().flatten() {
+            let value = match value {
+                Value::String(it) => it.clone(),
+                Value::Object(table) => {
+                    let value = match table.get("value").and_then(|it| it.as_str()) {
+                        Some(it) => it,
+                        None => continue,
+                    };
+                    if table.get("relative") == Some(&Value::Bool(true)) {
+                        relative_base(name).join(value).to_string_lossy().into_owned()
+                    } else {
+                        value.to_string()
+                    }
+                }
+                _ => continue,
+            };
+            env.push((name.clone(), value));
+        }
+        env.sort();
+
+        let aliases = config["alias"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, value)| Some((name.clone(), words(value)?)))
+            .filter(|(_, words)| !words.is_empty())
+            .collect();
+
+        CargoConfigFile { target, cfgs, env, aliases }
+    }
+}
+
+/// Returns the merged configuration which applies in `dir`, as JSON.
+fn cargo_config_get(dir: &AbsPath) -> Result<Value> {
+    let mut cmd = Command::new(ra_toolchain::cargo());
+    // `cargo config` is still unstable.
+    cmd.current_dir(dir).env("RUSTC_BOOTSTRAP", "1").args(&[
+        "-Z",
+        "unstable-options",
+        "config",
+        "get",
+        "--format",
+        "json",
+    ]);
+    let output = output(cmd)?;
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// The merged configuration doesn't say which file defines a variable, so we
+/// resolve relative `[env]` variables against the parent of the `.cargo`
+/// directory of the closest config file which mentions them, like cargo.
+fn relative_base(dir: &AbsPath, name: &str) -> AbsPathBuf {
+    config_dirs(dir)
+        .iter()
+        .filter_map(|it| config_file(it))
+        .find(|file| fs::read_to_string(file).map_or(false, |text| text.contains(name)))
+        .and_then(|file| Some(file.parent()?.parent()?.to_path_buf()))
+        .unwrap_or_else(|| dir.to_path_buf())
+}
+
+/// Returns the directories which cargo searches for config files when invoked
+/// in `dir`, that is the `.cargo` directories of `dir` and its ancestors, and
+/// `$CARGO_HOME`, from the highest precedence to the lowest.
+pub(crate) fn config_dirs(dir: &AbsPath) -> Vec<AbsPathBuf> {
+    let mut res = iter::successors(Some(dir), |it| it.parent())
+        .map(|it| it.join(".cargo"))
+        .collect::<Vec<_>>();
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|it| PathBuf::from(it).join(".cargo")));
+    if let Some(cargo_home) = cargo_home.and_then(|it| AbsPathBuf::try_from(it).ok()) {
+        if !res.contains(&cargo_home) {
+            res.push(cargo_home);
+        }
+    }
+    res
+}
+
+/// Returns the config file in a `.cargo` directory. Like cargo, we prefer the
+/// legacy name if both exist.
+fn config_file(dir: &AbsPath) -> Option<AbsPathBuf> {
+    ["config", "config.toml"].iter().map(|name| dir.join(name)).find(|it| it.is_file())
+}
+
+/// Cargo accepts both a string of whitespace separated words and an array of
+/// strings for flags and aliases.
+fn words(value: &Value) -> Option<Vec<String>> {
+    match value {
+        Value::String(it) => Some(it.split_whitespace().map(|it| it.to_string()).collect()),
+        Value::Array(it) => {
+            Some(it.iter().filter_map(|it| it.as_str()).map(|it| it.to_string()).collect())
+        }
+        _ => None,
+    }
+}
+
+fn cfgs_from_rustflags(rustflags: &[String]) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut flags = rustflags.iter();
+    while let Some(flag) = flags.next() {
+        if flag == "--cfg" {
+            res.extend(flags.next().cloned());
+        } else if let Some(cfg) = flag.strip_prefix("--cfg=") {
+            res.push(cfg.to_string());
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|it| it.to_string()).collect()
+    }
+
+    #[test]
+    fn reads_relevant_keys() {
+        let config = json!({
+            "alias": { "c": "clippy -- -D warnings", "t": ["test", "--all"], "x": 92 },
+            "build": {
+                "target": "x86_64-unknown-linux-gnu",
+                "rustflags": ["--cfg", "project", "--cfg=loom"],
+                "jobs": 4
+            },
+            "target": { "x86_64-unknown-linux-gnu": { "rustflags": "--cfg linux" } },
+            "env": {
+                "FOO": "home",
+                "ASSETS": { "value": "assets", "relative": true },
+                "BAR": { "value": "bar", "force": true }
+            }
+        });
+        let base = |name: &str| {
+            assert_eq!(name, "ASSETS");
+            AbsPathBuf::assert("/home/project".into())
+        };
+
+        let config_file = CargoConfigFile::from_json(&config, &base, None, None);
+        assert_eq!(config_file.target.as_deref(), Some("x86_64-unknown-linux-gnu"));
+        assert_eq!(config_file.cfgs, strings(&["linux"]));
+        assert_eq!(
+            config_file.env,
+            vec![
+                ("ASSETS".to_string(), "/home/project/assets".to_string()),
+                ("BAR".to_string(), "bar".to_string()),
+                ("FOO".to_string(), "home".to_string()),
+            ]
+        );
+        assert_eq!(config_file.aliases["c"], strings(&["clippy", "--", "-D", "warnings"]));
+        assert_eq!(config_file.aliases["t"], strings(&["test", "--all"]));
+        assert!(!config_file.aliases.contains_key("x"));
+
+        let config_file =
+            CargoConfigFile::from_json(&config, &base, Some("wasm32".to_string()), None);
+        assert_eq!(config_file.target.as_deref(), Some("wasm32"));
+        assert_eq!(config_file.cfgs, strings(&["project", "loom"]));
+
+        let config_file =
+            CargoConfigFile::from_json(&config, &base, None, Some(strings(&["--cfg=env"])));
+        assert_eq!(config_file.cfgs, strings(&["env"]));
+    }
+
+    #[test]
+    fn missing_config_is_empty() {
+        let base = |_: &str| unreachable!();
+        let config_file = CargoConfigFile::from_json(&Value::Null, &base, None, None);
+        assert_eq!(config_file, CargoConfigFile::default());
+    }
+
+    #[test]
+    fn discovers_config_files() {
+        let dir = env::temp_dir().join(format!("ra-cargo-config-{}", std::process::id()));
+        let project = dir.join("project");
+        fs::create_dir_all(dir.join(".cargo")).unwrap();
+        fs::create_dir_all(project.join(".cargo")).unwrap();
+        fs::write(
+            dir.join(".cargo/config.toml"),
+            "[alias]\nra-test-alias = \"check --all\"\n[env]\nRA_TEST_OUTER = { value = \"x\", relative = true }\n",
+        )
+        .unwrap();
+        fs::write(
+            project.join(".cargo/config.toml"),
+            "[env]\nRA_TEST_INNER = { value = \"y\", relative = true }\n",
+        )
+        .unwrap();
+
+        let config_file =
+            CargoConfigFile::discover(&AbsPathBuf::assert(project.clone()), Some("wasm32"));
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(config_file.target.as_deref(), Some("wasm32"));
+        assert_eq!(config_file.aliases["ra-test-alias"], strings(&["check", "--all"]));
+        let env = config_file.env.iter().cloned().collect::<FxHashMap<_, _>>();
+        assert_eq!(env["RA_TEST_OUTER"], dir.join("x").to_string_lossy());
+        assert_eq!(env["RA_TEST_INNER"], project.join("y").to_string_lossy());
+    }
+}
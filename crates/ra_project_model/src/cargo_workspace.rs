@@ -8,12 +8,14 @@ use std::{
 };
 
 use anyhow::{Context, Result};
-use cargo_metadata::{BuildScript, CargoOpt, Message, MetadataCommand, PackageId};
+use cargo_metadata::{BuildScript, CargoOpt, DependencyKind, Message, MetadataCommand, PackageId};
 use paths::{AbsPath, AbsPathBuf};
 use ra_arena::{Arena, Idx};
 use ra_db::Edition;
 use rustc_hash::FxHashMap;
 
+use crate::{cargo_config_file::CargoConfigFile, metadata_cache::MetadataCache, CfgOverrides};
+
 /// `CargoWorkspace` represents the logical structure of, well, a Cargo
 /// workspace. It pretty closely mirrors `cargo metadata` output.
 ///
@@ -29,6 +31,7 @@ pub struct CargoWorkspace {
     packages: Arena<PackageData>,
     targets: Arena<TargetData>,
     workspace_root: AbsPathBuf,
+    config_file: CargoConfigFile,
 }
 
 impl ops::Index<Package> for CargoWorkspace {
@@ -53,15 +56,33 @@ pub struct CargoConfig {
     /// Activate all available features
     pub all_features: bool,
 
-    /// List of features to activate.
-    /// This will be ignored if `cargo_all_features` is true.
+    /// List of features to activate, in addition to the default ones unless
+    /// `no_default_features` is set.
+    /// This will be ignored if `all_features` is true.
     pub features: Vec<String>,
 
-    /// Runs cargo check on launch to figure out the correct values of OUT_DIR
+    /// Runs cargo check on launch to figure out the correct values of OUT_DIR, as well as the
+    /// cfgs and env vars set by build scripts
     pub load_out_dirs_from_check: bool,
 
-    /// rustc target
+    /// rustc target, overriding `build.target` from `.cargo/config`
     pub target: Option<String>,
+
+    /// Reuse the `cargo metadata` output of a previous run if the manifests
+    /// and the lock file haven't changed since.
+    pub cache_metadata: bool,
+
+    /// Directory with the standard library sources, overriding the one
+    /// discovered from the sysroot.
+    pub sysroot_src: Option<AbsPathBuf>,
+
+    /// Treat path dependencies as workspace members, so that they get
+    /// diagnostics and can be edited, even if they live outside the workspace.
+    pub path_deps_as_members: bool,
+
+    /// cfg options to enable or disable on top of the computed ones, for all
+    /// crates or for specific ones.
+    pub cfg_overrides: CfgOverrides,
 }
 
 pub type Package = Idx<PackageData>;
@@ -75,10 +96,15 @@ pub struct PackageData {
     pub manifest: AbsPathBuf,
     pub targets: Vec<Target>,
     pub is_member: bool,
+    /// Whether the sources live in a local directory rather than in a registry
+    /// or git checkout, which is the case for members and path dependencies.
+    pub is_local: bool,
     pub dependencies: Vec<PackageDependency>,
     pub edition: Edition,
     pub features: Vec<String>,
     pub cfgs: Vec<String>,
+    /// Environment variables set by the build script with `cargo:rustc-env`.
+    pub envs: Vec<(String, String)>,
     pub out_dir: Option<AbsPathBuf>,
     pub proc_macro_dylib_path: Option<AbsPathBuf>,
 }
@@ -87,6 +113,34 @@ pub struct PackageData {
 pub struct PackageDependency {
     pub pkg: Package,
     pub name: String,
+    pub kind: DepKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DepKind {
+    /// Available to all targets.
+    Normal,
+    /// Available to tests, benches and examples.
+    Dev,
+    /// Available only to the build script.
+    Build,
+}
+
+impl DepKind {
+    /// A package can depend on another one in several ways, we pick the one
+    /// which makes the dependency available to most targets.
+    fn new(kinds: &[cargo_metadata::DepKindInfo]) -> DepKind {
+        kinds
+            .iter()
+            .map(|info| match info.kind {
+                DependencyKind::Normal | DependencyKind::Unknown => DepKind::Normal,
+                DependencyKind::Development => DepKind::Dev,
+                DependencyKind::Build => DepKind::Build,
+            })
+            .min()
+            // Cargo before 1.41 doesn't report dependency kinds.
+            .unwrap_or(DepKind::Normal)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -137,37 +191,53 @@ impl CargoWorkspace {
         cargo_toml: &AbsPath,
         cargo_features: &CargoConfig,
     ) -> Result<CargoWorkspace> {
-        let mut meta = MetadataCommand::new();
-        meta.cargo_path(ra_toolchain::cargo());
-        meta.manifest_path(cargo_toml.to_path_buf());
-        if cargo_features.all_features {
-            meta.features(CargoOpt::AllFeatures);
-        } else if cargo_features.no_default_features {
-            // FIXME: `NoDefaultFeatures` is mutual exclusive with `SomeFeatures`
-            // https://github.com/oli-obk/cargo_metadata/issues/79
-            meta.features(CargoOpt::NoDefaultFeatures);
-        } else if !cargo_features.features.is_empty() {
-            meta.features(CargoOpt::SomeFeatures(cargo_features.features.clone()));
-        }
-        if let Some(parent) = cargo_toml.parent() {
-            meta.current_dir(parent.to_path_buf());
-        }
-        if let Some(target) = cargo_features.target.as_ref() {
-            meta.other_options(vec![String::from("--filter-platform"), target.clone()]);
-        }
-        let meta = meta.exec().with_context(|| {
-            format!("Failed to run `cargo metadata --manifest-path {}`", cargo_toml.display())
-        })?;
-
-        let mut out_dir_by_id = FxHashMap::default();
-        let mut cfgs = FxHashMap::default();
-        let mut proc_macro_dylib_paths = FxHashMap::default();
-        if cargo_features.load_out_dirs_from_check {
-            let resources = load_extern_resources(cargo_toml, cargo_features)?;
-            out_dir_by_id = resources.out_dirs;
-            cfgs = resources.cfgs;
-            proc_macro_dylib_paths = resources.proc_dylib_paths;
-        }
+        // Cargo reads `.cargo/config` relative to the current directory.
+        let cargo_dir = cargo_toml.parent().unwrap_or(cargo_toml);
+        let config_file = CargoConfigFile::discover(cargo_dir, cargo_features.target.as_deref());
+        let meta = metadata_command(cargo_toml, cargo_features, &config_file);
+        let cache = if cargo_features.cache_metadata {
+            meta.cargo_command().ok().and_then(|cmd| MetadataCache::new(&cmd, cargo_dir))
+        } else {
+            None
+        };
+        let meta = match cache.as_ref().and_then(MetadataCache::load) {
+            Some(it) => it,
+            None => {
+                let meta = meta.exec().with_context(|| {
+                    format!(
+                        "Failed to run `cargo metadata --manifest-path {}`",
+                        cargo_toml.display()
+                    )
+                })?;
+                if let Some(cache) = &cache {
+                    cache.store(&meta);
+                }
+                meta
+            }
+        };
+
+        let resources = if cargo_features.load_out_dirs_from_check {
+            load_extern_resources(cargo_toml, cargo_features)?
+        } else {
+            ExternResources::default()
+        };
+        CargoWorkspace::new(meta, cargo_features, resources, config_file)
+    }
+
+    /// Builds the workspace from the output of `cargo metadata`, and the
+    /// results of the build scripts in `resources`.
+    pub(crate) fn new(
+        meta: cargo_metadata::Metadata,
+        cargo_features: &CargoConfig,
+        resources: ExternResources,
+        config_file: CargoConfigFile,
+    ) -> Result<CargoWorkspace> {
+        let ExternResources {
+            out_dirs: out_dir_by_id,
+            proc_dylib_paths: proc_macro_dylib_paths,
+            cfgs,
+            envs,
+        } = resources;
 
         let mut pkg_by_id = FxHashMap::default();
         let mut packages = Arena::default();
@@ -176,9 +246,12 @@ impl CargoWorkspace {
         let ws_members = &meta.workspace_members;
 
         for meta_pkg in meta.packages {
-            let cargo_metadata::Package { id, edition, name, manifest_path, version, .. } =
-                meta_pkg;
-            let is_member = ws_members.contains(&id);
+            let cargo_metadata::Package {
+                id, edition, name, manifest_path, version, source, ..
+            } = meta_pkg;
+            let is_local = source.is_none();
+            let is_member =
+                ws_members.contains(&id) || (cargo_features.path_deps_as_members && is_local);
             let edition = edition
                 .parse::<Edition>()
                 .with_context(|| format!("Failed to parse edition {}", edition))?;
@@ -188,10 +261,12 @@ impl CargoWorkspace {
                 manifest: AbsPathBuf::assert(manifest_path),
                 targets: Vec::new(),
                 is_member,
+                is_local,
                 edition,
                 dependencies: Vec::new(),
                 features: Vec::new(),
                 cfgs: cfgs.get(&id).cloned().unwrap_or_default(),
+                envs: envs.get(&id).cloned().unwrap_or_default(),
                 out_dir: out_dir_by_id.get(&id).cloned(),
                 proc_macro_dylib_path: proc_macro_dylib_paths.get(&id).cloned(),
             });
@@ -232,14 +307,30 @@ impl CargoWorkspace {
                         continue;
                     }
                 };
-                let dep = PackageDependency { name: dep_node.name, pkg };
+                let kind = DepKind::new(&dep_node.dep_kinds);
+                let dep = PackageDependency { name: dep_node.name, pkg, kind };
                 packages[source].dependencies.push(dep);
             }
             packages[source].features.extend(node.features);
         }
 
         let workspace_root = AbsPathBuf::assert(meta.workspace_root);
-        Ok(CargoWorkspace { packages, targets, workspace_root: workspace_root })
+        Ok(CargoWorkspace { packages, targets, workspace_root, config_file })
+    }
+
+    /// The target to analyze the crates for, from the config or from
+    /// `.cargo/config`.
+    pub fn target(&self) -> Option<&str> {
+        self.config_file.target.as_deref()
+    }
+
+    /// Returns the words of the `[alias]` entry `name` of `.cargo/config`.
+    pub fn cargo_alias(&self, name: &str) -> Option<&[String]> {
+        self.config_file.aliases.get(name).map(|it| it.as_slice())
+    }
+
+    pub(crate) fn config_file(&self) -> &CargoConfigFile {
+        &self.config_file
     }
 
     pub fn packages<'a>(&'a self) -> impl Iterator<Item = Package> + ExactSizeIterator + 'a {
@@ -270,11 +361,45 @@ impl CargoWorkspace {
     }
 }
 
+/// Returns the `cargo metadata` invocation for the workspace of `cargo_toml`.
+pub(crate) fn metadata_command(
+    cargo_toml: &AbsPath,
+    cargo_features: &CargoConfig,
+    config_file: &CargoConfigFile,
+) -> MetadataCommand {
+    let mut meta = MetadataCommand::new();
+    meta.cargo_path(ra_toolchain::cargo());
+    meta.manifest_path(cargo_toml.to_path_buf());
+    let mut other_options = Vec::new();
+    if cargo_features.all_features {
+        meta.features(CargoOpt::AllFeatures);
+    } else {
+        if cargo_features.no_default_features {
+            // `NoDefaultFeatures` is mutual exclusive with `SomeFeatures`, so
+            // we pass the flag ourselves.
+            // https://github.com/oli-obk/cargo_metadata/issues/79
+            other_options.push("--no-default-features".to_string());
+        }
+        if !cargo_features.features.is_empty() {
+            meta.features(CargoOpt::SomeFeatures(cargo_features.features.clone()));
+        }
+    }
+    meta.current_dir(cargo_toml.parent().unwrap_or(cargo_toml).to_path_buf());
+    // Unlike `cargo check`, `cargo metadata` ignores `build.target`.
+    if let Some(target) = config_file.target.as_ref() {
+        other_options.push("--filter-platform".to_string());
+        other_options.push(target.clone());
+    }
+    meta.other_options(other_options);
+    meta
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ExternResources {
     out_dirs: FxHashMap<PackageId, AbsPathBuf>,
     proc_dylib_paths: FxHashMap<PackageId, AbsPathBuf>,
     cfgs: FxHashMap<PackageId, Vec<String>>,
+    envs: FxHashMap<PackageId, Vec<(String, String)>>,
 }
 
 pub fn load_extern_resources(
@@ -283,14 +408,21 @@ pub fn load_extern_resources(
 ) -> Result<ExternResources> {
     let mut cmd = Command::new(ra_toolchain::cargo());
     cmd.args(&["check", "--message-format=json", "--manifest-path"]).arg(cargo_toml);
+    if let Some(parent) = cargo_toml.parent() {
+        cmd.current_dir(parent);
+    }
+    if let Some(target) = cargo_features.target.as_ref() {
+        cmd.args(&["--target", target]);
+    }
     if cargo_features.all_features {
         cmd.arg("--all-features");
-    } else if cargo_features.no_default_features {
-        // FIXME: `NoDefaultFeatures` is mutual exclusive with `SomeFeatures`
-        // https://github.com/oli-obk/cargo_metadata/issues/79
-        cmd.arg("--no-default-features");
     } else {
-        cmd.args(&cargo_features.features);
+        if cargo_features.no_default_features {
+            cmd.arg("--no-default-features");
+        }
+        if !cargo_features.features.is_empty() {
+            cmd.arg("--features").arg(cargo_features.features.join(" "));
+        }
     }
 
     let output = cmd.output()?;
@@ -300,7 +432,14 @@ pub fn load_extern_resources(
     for message in cargo_metadata::Message::parse_stream(output.stdout.as_slice()) {
         if let Ok(message) = message {
             match message {
-                Message::BuildScriptExecuted(BuildScript { package_id, out_dir, cfgs, .. }) => {
+                Message::BuildScriptExecuted(BuildScript {
+                    package_id,
+                    out_dir,
+                    cfgs,
+                    env,
+                    ..
+                }) => {
+                    res.envs.insert(package_id.clone(), env);
                     // cargo_metadata crate returns default (empty) path for
                     // older cargos, which is not absolute, so work around that.
                     if out_dir != PathBuf::default() {
@@ -1,8 +1,12 @@
 //! FIXME: write short doc here
 
+mod cargo_config_file;
 mod cargo_workspace;
+mod metadata_cache;
 mod project_json;
 mod sysroot;
+#[cfg(test)]
+mod tests;
 
 use std::{
     fs::{self, read_dir, ReadDir},
@@ -13,12 +17,15 @@ use std::{
 
 use anyhow::{bail, Context, Result};
 use paths::{AbsPath, AbsPathBuf};
-use ra_cfg::CfgOptions;
-use ra_db::{CrateGraph, CrateId, CrateName, Edition, Env, FileId};
+use ra_cfg::{CfgAtom, CfgDiff, CfgOptions};
+use ra_db::{CrateGraph, CrateId, CrateName, Edition, Env, FileId, ProcMacro, ProcMacroKind};
 use rustc_hash::{FxHashMap, FxHashSet};
 
 pub use crate::{
-    cargo_workspace::{CargoConfig, CargoWorkspace, Package, Target, TargetKind},
+    cargo_workspace::{
+        CargoConfig, CargoWorkspace, DepKind, Package, PackageData, Target, TargetKind,
+    },
+    metadata_cache::cache_dir,
     project_json::{ProjectJson, ProjectJsonData},
     sysroot::Sysroot,
 };
@@ -29,7 +36,29 @@ pub enum ProjectWorkspace {
     /// Project workspace was discovered by running `cargo metadata` and `rustc --print sysroot`.
     Cargo { cargo: CargoWorkspace, sysroot: Sysroot },
     /// Project workspace was manually specified using a `rust-project.json` file.
-    Json { project: ProjectJson },
+    Json { project: ProjectJson, sysroot: Option<Sysroot> },
+    /// Files which don't belong to any other workspace, each of which is analyzed as a crate of
+    /// its own.
+    DetachedFiles { files: Vec<AbsPathBuf>, sysroot: Sysroot },
+}
+
+/// User-provided cfg options which are applied on top of the ones computed for
+/// each non-sysroot crate.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct CfgOverrides {
+    /// Applied to every crate.
+    pub global: CfgDiff,
+    /// Applied to crates with the given name, after `global`.
+    pub selective: FxHashMap<String, CfgDiff>,
+}
+
+impl CfgOverrides {
+    fn apply(&self, cfg_options: &mut CfgOptions, crate_name: Option<&str>) {
+        cfg_options.apply_diff(&self.global);
+        if let Some(diff) = crate_name.and_then(|name| self.selective.get(name)) {
+            cfg_options.apply_diff(diff);
+        }
+    }
 }
 
 /// `PackageRoot` describes a package root folder.
@@ -41,14 +70,16 @@ pub struct PackageRoot {
     path: AbsPathBuf,
     /// Is a member of the current workspace
     is_member: bool,
+    /// Lives on the local file system and can change, so should be watched
+    is_local: bool,
     out_dir: Option<AbsPathBuf>,
 }
 impl PackageRoot {
     pub fn new_member(path: AbsPathBuf) -> PackageRoot {
-        Self { path, is_member: true, out_dir: None }
+        Self { path, is_member: true, is_local: true, out_dir: None }
     }
     pub fn new_non_member(path: AbsPathBuf) -> PackageRoot {
-        Self { path, is_member: false, out_dir: None }
+        Self { path, is_member: false, is_local: false, out_dir: None }
     }
     pub fn path(&self) -> &AbsPath {
         &self.path
@@ -59,6 +90,9 @@ impl PackageRoot {
     pub fn is_member(&self) -> bool {
         self.is_member
     }
+    pub fn is_local(&self) -> bool {
+        self.is_local
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
@@ -163,58 +197,109 @@ impl ProjectWorkspace {
                 })?;
                 let project_location = project_json.parent().unwrap().to_path_buf();
                 let project = ProjectJson::new(&project_location, data);
-                ProjectWorkspace::Json { project }
+                ProjectWorkspace::load_inline(project)
             }
             ProjectManifest::CargoToml(cargo_toml) => {
-                let cargo = CargoWorkspace::from_cargo_metadata(&cargo_toml, cargo_config)
-                    .with_context(|| {
-                        format!(
-                            "Failed to read Cargo metadata from Cargo.toml file {}",
-                            cargo_toml.display()
-                        )
-                    })?;
-                let sysroot = if with_sysroot {
-                    Sysroot::discover(&cargo_toml).with_context(|| {
-                        format!(
-                            "Failed to find sysroot for Cargo.toml file {}. Is rust-src installed?",
-                            cargo_toml.display()
+                // Both shell out to cargo or rustc, so we run them concurrently.
+                let (cargo, sysroot) = rayon::join(
+                    || {
+                        CargoWorkspace::from_cargo_metadata(&cargo_toml, cargo_config).with_context(
+                            || {
+                                format!(
+                                    "Failed to read Cargo metadata from Cargo.toml file {}",
+                                    cargo_toml.display()
+                                )
+                            },
                         )
-                    })?
-                } else {
-                    Sysroot::default()
-                };
-                ProjectWorkspace::Cargo { cargo, sysroot }
+                    },
+                    || {
+                        if !with_sysroot {
+                            return Ok(Sysroot::default());
+                        }
+                        Sysroot::discover(&cargo_toml, cargo_config.sysroot_src.as_deref())
+                            .with_context(|| {
+                                format!(
+                                    "Failed to find sysroot for Cargo.toml file {}. Is rust-src installed?",
+                                    cargo_toml.display()
+                                )
+                            })
+                    },
+                );
+                ProjectWorkspace::Cargo { cargo: cargo?, sysroot: sysroot? }
             }
         };
 
         Ok(res)
     }
 
+    pub fn load_inline(project: ProjectJson) -> ProjectWorkspace {
+        let sysroot = project.sysroot_src.as_deref().map(Sysroot::load);
+        ProjectWorkspace::Json { project, sysroot }
+    }
+
+    /// Creates a workspace for files outside of any project. The sysroot is discovered from the
+    /// directory of the first file.
+    pub fn load_detached_files(
+        files: Vec<AbsPathBuf>,
+        cargo_config: &CargoConfig,
+        with_sysroot: bool,
+    ) -> Result<ProjectWorkspace> {
+        let sysroot = match files.first() {
+            Some(file) if with_sysroot => {
+                Sysroot::discover(file, cargo_config.sysroot_src.as_deref()).with_context(|| {
+                    format!(
+                        "Failed to find sysroot for file {}. Is rust-src installed?",
+                        file.display()
+                    )
+                })?
+            }
+            _ => Sysroot::default(),
+        };
+        Ok(ProjectWorkspace::DetachedFiles { files, sysroot })
+    }
+
     /// Returns the roots for the current `ProjectWorkspace`
     /// The return type contains the path and whether or not
     /// the root is a member of the current workspace
     pub fn to_roots(&self) -> Vec<PackageRoot> {
         match self {
-            ProjectWorkspace::Json { project } => {
-                project.roots.iter().map(|r| PackageRoot::new_member(r.path.clone())).collect()
-            }
+            ProjectWorkspace::Json { project, sysroot } => project
+                .roots
+                .iter()
+                .map(|r| PackageRoot::new_member(r.path.clone()))
+                .chain(sysroot.as_ref().into_iter().flat_map(|sysroot| {
+                    sysroot.crates().map(move |krate| {
+                        PackageRoot::new_non_member(sysroot[krate].root_dir().to_path_buf())
+                    })
+                }))
+                .collect(),
             ProjectWorkspace::Cargo { cargo, sysroot } => cargo
                 .packages()
                 .map(|pkg| PackageRoot {
                     path: cargo[pkg].root().to_path_buf(),
                     is_member: cargo[pkg].is_member,
+                    is_local: cargo[pkg].is_local,
                     out_dir: cargo[pkg].out_dir.clone(),
                 })
                 .chain(sysroot.crates().map(|krate| {
                     PackageRoot::new_non_member(sysroot[krate].root_dir().to_path_buf())
                 }))
                 .collect(),
+            // The root of a detached file is the file itself, so that the rest of its directory
+            // is not loaded.
+            ProjectWorkspace::DetachedFiles { files, sysroot } => files
+                .iter()
+                .map(|file| PackageRoot::new_member(file.clone()))
+                .chain(sysroot.crates().map(|krate| {
+                    PackageRoot::new_non_member(sysroot[krate].root_dir().to_path_buf())
+                }))
+                .collect(),
         }
     }
 
     pub fn proc_macro_dylib_paths(&self) -> Vec<AbsPathBuf> {
         match self {
-            ProjectWorkspace::Json { project } => project
+            ProjectWorkspace::Json { project, sysroot: _ } => project
                 .crates
                 .iter()
                 .filter_map(|krate| krate.proc_macro_dylib_path.as_ref())
@@ -225,28 +310,52 @@ impl ProjectWorkspace {
                 .filter_map(|pkg| cargo[pkg].proc_macro_dylib_path.as_ref())
                 .cloned()
                 .collect(),
+            ProjectWorkspace::DetachedFiles { .. } => Vec::new(),
+        }
+    }
+
+    pub fn sysroot(&self) -> Option<&Sysroot> {
+        match self {
+            ProjectWorkspace::Cargo { sysroot, .. }
+            | ProjectWorkspace::DetachedFiles { sysroot, .. } => Some(sysroot),
+            ProjectWorkspace::Json { sysroot, .. } => sysroot.as_ref(),
         }
     }
 
     pub fn n_packages(&self) -> usize {
         match self {
-            ProjectWorkspace::Json { project, .. } => project.crates.len(),
+            ProjectWorkspace::Json { project, sysroot } => {
+                let sysroot_crates = sysroot.as_ref().map_or(0, |sysroot| sysroot.crates().len());
+                project.crates.len() + sysroot_crates
+            }
             ProjectWorkspace::Cargo { cargo, sysroot } => {
                 cargo.packages().len() + sysroot.crates().len()
             }
+            ProjectWorkspace::DetachedFiles { files, sysroot } => {
+                files.len() + sysroot.crates().len()
+            }
         }
     }
 
     pub fn to_crate_graph(
         &self,
         target: Option<&str>,
+        cfg_overrides: &CfgOverrides,
         proc_macro_client: &ProcMacroClient,
         load: &mut dyn FnMut(&AbsPath) -> Option<FileId>,
     ) -> CrateGraph {
         let mut crate_graph = CrateGraph::default();
         match self {
-            ProjectWorkspace::Json { project } => {
+            ProjectWorkspace::Json { project, sysroot } => {
                 let mut target_cfg_map = FxHashMap::<Option<&str>, CfgOptions>::default();
+                let sysroot_deps = sysroot.as_ref().map(|sysroot| {
+                    let cfg_options = target_cfg_map
+                        .entry(target)
+                        .or_insert_with(|| get_rustc_cfg_options(target))
+                        .clone();
+                    sysroot_to_crate_graph(&mut crate_graph, sysroot, &cfg_options, load)
+                });
+
                 let crates: FxHashMap<_, _> = project
                     .crates
                     .iter()
@@ -265,7 +374,7 @@ impl ProjectWorkspace {
                         let proc_macro = krate
                             .proc_macro_dylib_path
                             .clone()
-                            .map(|it| proc_macro_client.by_dylib_path(&it));
+                            .map(|it| load_proc_macro(proc_macro_client, &it));
 
                         let target = krate.target.as_deref().or(target);
                         let target_cfgs = target_cfg_map
@@ -273,15 +382,14 @@ impl ProjectWorkspace {
                             .or_insert_with(|| get_rustc_cfg_options(target.as_deref()));
                         let mut cfg_options = krate.cfg.clone();
                         cfg_options.append(target_cfgs);
+                        cfg_overrides.apply(&mut cfg_options, krate.display_name.as_deref());
 
-                        // FIXME: No crate name in json definition such that we cannot add OUT_DIR to env
                         Some((
                             CrateId(seq_index as u32),
                             crate_graph.add_crate_root(
                                 file_id,
                                 krate.edition,
-                                // FIXME json definitions can store the crate name
-                                None,
+                                krate.display_name.clone(),
                                 cfg_options,
                                 env,
                                 proc_macro.unwrap_or_default(),
@@ -291,12 +399,21 @@ impl ProjectWorkspace {
                     .collect();
 
                 for (id, krate) in project.crates.iter().enumerate() {
+                    let from_crate_id = CrateId(id as u32);
+                    let from = match crates.get(&from_crate_id) {
+                        Some(&it) => it,
+                        None => continue,
+                    };
+                    if let Some(sysroot_deps) = &sysroot_deps {
+                        let crate_name = krate.display_name.as_deref().unwrap_or("<unnamed>");
+                        sysroot_deps.add_public_deps(&mut crate_graph, from, crate_name);
+                        if krate.proc_macro_dylib_path.is_some() {
+                            sysroot_deps.add_proc_macro_dep(&mut crate_graph, from, crate_name);
+                        }
+                    }
                     for dep in &krate.deps {
-                        let from_crate_id = CrateId(id as u32);
                         let to_crate_id = dep.crate_id;
-                        if let (Some(&from), Some(&to)) =
-                            (crates.get(&from_crate_id), crates.get(&to_crate_id))
-                        {
+                        if let Some(&to) = crates.get(&to_crate_id) {
                             if crate_graph.add_dep(from, dep.name.clone(), to).is_err() {
                                 log::error!(
                                     "cyclic dependency {:?} -> {:?}",
@@ -309,53 +426,19 @@ impl ProjectWorkspace {
                 }
             }
             ProjectWorkspace::Cargo { cargo, sysroot } => {
-                let mut cfg_options = get_rustc_cfg_options(target);
-
-                let sysroot_crates: FxHashMap<_, _> = sysroot
-                    .crates()
-                    .filter_map(|krate| {
-                        let file_id = load(&sysroot[krate].root)?;
-
-                        let env = Env::default();
-                        let proc_macro = vec![];
-                        let name = sysroot[krate].name.clone();
-                        let crate_id = crate_graph.add_crate_root(
-                            file_id,
-                            Edition::Edition2018,
-                            Some(name),
-                            cfg_options.clone(),
-                            env,
-                            proc_macro,
-                        );
-                        Some((krate, crate_id))
-                    })
-                    .collect();
+                let cfg_options = get_rustc_cfg_options(cargo.target().or(target));
 
-                for from in sysroot.crates() {
-                    for &to in sysroot[from].deps.iter() {
-                        let name = &sysroot[to].name;
-                        if let (Some(&from), Some(&to)) =
-                            (sysroot_crates.get(&from), sysroot_crates.get(&to))
-                        {
-                            if crate_graph.add_dep(from, CrateName::new(name).unwrap(), to).is_err()
-                            {
-                                log::error!("cyclic dependency between sysroot crates")
-                            }
-                        }
-                    }
-                }
-
-                let libcore = sysroot.core().and_then(|it| sysroot_crates.get(&it).copied());
-                let liballoc = sysroot.alloc().and_then(|it| sysroot_crates.get(&it).copied());
-                let libstd = sysroot.std().and_then(|it| sysroot_crates.get(&it).copied());
-                let libproc_macro =
-                    sysroot.proc_macro().and_then(|it| sysroot_crates.get(&it).copied());
+                let sysroot_deps =
+                    sysroot_to_crate_graph(&mut crate_graph, sysroot, &cfg_options, load);
 
                 let mut pkg_to_lib_crate = FxHashMap::default();
                 let mut pkg_crates = FxHashMap::default();
 
-                // Add test cfg for non-sysroot crates
-                cfg_options.insert_atom("test".into());
+                // Workspace members are analyzed as if compiled with `cargo test`,
+                // so that their test modules are analyzed too. Libraries don't get
+                // `cfg(test)`, their tests are never compiled.
+                let mut test_cfg_options = cfg_options.clone();
+                test_cfg_options.insert_atom("test".into());
 
                 // Next, create crates for each package, target pair
                 for pkg in cargo.packages() {
@@ -365,32 +448,30 @@ impl ProjectWorkspace {
                         if let Some(file_id) = load(root) {
                             let edition = cargo[pkg].edition;
                             let cfg_options = {
-                                let mut opts = cfg_options.clone();
+                                let mut opts = if cargo[pkg].is_member {
+                                    test_cfg_options.clone()
+                                } else {
+                                    cfg_options.clone()
+                                };
                                 for feature in cargo[pkg].features.iter() {
                                     opts.insert_key_value("feature".into(), feature.into());
                                 }
-                                for cfg in cargo[pkg].cfgs.iter() {
-                                    match cfg.find('=') {
-                                        Some(split) => opts.insert_key_value(
-                                            cfg[..split].into(),
-                                            cfg[split + 1..].trim_matches('"').into(),
-                                        ),
-                                        None => opts.insert_atom(cfg.into()),
-                                    };
+                                let rustflags_cfgs = cargo.config_file().cfgs.iter();
+                                for cfg in rustflags_cfgs.chain(cargo[pkg].cfgs.iter()) {
+                                    opts.insert(CfgAtom::parse(cfg));
                                 }
+                                cfg_overrides.apply(&mut opts, Some(&cargo[pkg].name));
                                 opts
                             };
                             let mut env = Env::default();
-                            if let Some(out_dir) = &cargo[pkg].out_dir {
-                                // NOTE: cargo and rustc seem to hide non-UTF-8 strings from env! and option_env!()
-                                if let Some(out_dir) = out_dir.to_str().map(|s| s.to_owned()) {
-                                    env.set("OUT_DIR", out_dir);
-                                }
+                            for (key, value) in cargo.config_file().env.iter() {
+                                env.set(key, value.clone());
                             }
+                            package_env(&cargo[pkg], &mut env);
                             let proc_macro = cargo[pkg]
                                 .proc_macro_dylib_path
                                 .as_ref()
-                                .map(|it| proc_macro_client.by_dylib_path(&it))
+                                .map(|it| load_proc_macro(proc_macro_client, &it))
                                 .unwrap_or_default();
 
                             let crate_id = crate_graph.add_crate_root(
@@ -406,29 +487,22 @@ impl ProjectWorkspace {
                                 pkg_to_lib_crate.insert(pkg, crate_id);
                             }
                             if cargo[tgt].is_proc_macro {
-                                if let Some(proc_macro) = libproc_macro {
-                                    if crate_graph
-                                        .add_dep(
-                                            crate_id,
-                                            CrateName::new("proc_macro").unwrap(),
-                                            proc_macro,
-                                        )
-                                        .is_err()
-                                    {
-                                        log::error!(
-                                            "cyclic dependency on proc_macro for {}",
-                                            &cargo[pkg].name
-                                        )
-                                    }
-                                }
+                                sysroot_deps.add_proc_macro_dep(
+                                    &mut crate_graph,
+                                    crate_id,
+                                    &cargo[pkg].name,
+                                );
                             }
 
-                            pkg_crates.entry(pkg).or_insert_with(Vec::new).push(crate_id);
+                            pkg_crates
+                                .entry(pkg)
+                                .or_insert_with(Vec::new)
+                                .push((crate_id, cargo[tgt].kind));
                         }
                     }
 
                     // Set deps to the core, std and to the lib target of the current package
-                    for &from in pkg_crates.get(&pkg).into_iter().flatten() {
+                    for &(from, _) in pkg_crates.get(&pkg).into_iter().flatten() {
                         if let Some((to, name)) = lib_tgt.clone() {
                             if to != from
                                 && crate_graph
@@ -450,41 +524,29 @@ impl ProjectWorkspace {
                                 }
                             }
                         }
-                        // core is added as a dependency before std in order to
-                        // mimic rustcs dependency order
-                        if let Some(core) = libcore {
-                            if crate_graph
-                                .add_dep(from, CrateName::new("core").unwrap(), core)
-                                .is_err()
-                            {
-                                log::error!("cyclic dependency on core for {}", &cargo[pkg].name)
-                            }
-                        }
-                        if let Some(alloc) = liballoc {
-                            if crate_graph
-                                .add_dep(from, CrateName::new("alloc").unwrap(), alloc)
-                                .is_err()
-                            {
-                                log::error!("cyclic dependency on alloc for {}", &cargo[pkg].name)
-                            }
-                        }
-                        if let Some(std) = libstd {
-                            if crate_graph
-                                .add_dep(from, CrateName::new("std").unwrap(), std)
-                                .is_err()
-                            {
-                                log::error!("cyclic dependency on std for {}", &cargo[pkg].name)
-                            }
-                        }
+                        sysroot_deps.add_public_deps(&mut crate_graph, from, &cargo[pkg].name);
                     }
                 }
 
                 // Now add a dep edge from all targets of upstream to the lib
-                // target of downstream.
+                // target of downstream. Dev-dependencies are only visible to
+                // tests, benches and examples, build dependencies only to build
+                // scripts, which are not part of the crate graph.
                 for pkg in cargo.packages() {
                     for dep in cargo[pkg].dependencies.iter() {
                         if let Some(&to) = pkg_to_lib_crate.get(&dep.pkg) {
-                            for &from in pkg_crates.get(&pkg).into_iter().flatten() {
+                            for &(from, kind) in pkg_crates.get(&pkg).into_iter().flatten() {
+                                let visible = match dep.kind {
+                                    DepKind::Normal => true,
+                                    DepKind::Dev => matches!(
+                                        kind,
+                                        TargetKind::Test | TargetKind::Bench | TargetKind::Example
+                                    ),
+                                    DepKind::Build => false,
+                                };
+                                if !visible {
+                                    continue;
+                                }
                                 if crate_graph
                                     .add_dep(from, CrateName::new(&dep.name).unwrap(), to)
                                     .is_err()
@@ -500,6 +562,34 @@ impl ProjectWorkspace {
                     }
                 }
             }
+            ProjectWorkspace::DetachedFiles { files, sysroot } => {
+                let cfg_options = get_rustc_cfg_options(target);
+                let sysroot_deps =
+                    sysroot_to_crate_graph(&mut crate_graph, sysroot, &cfg_options, load);
+
+                for file in files {
+                    let file_id = match load(file) {
+                        Some(it) => it,
+                        None => continue,
+                    };
+                    let display_name = file
+                        .file_stem()
+                        .and_then(|it| it.to_str())
+                        .unwrap_or("<unnamed>")
+                        .to_string();
+                    let mut cfg_options = cfg_options.clone();
+                    cfg_overrides.apply(&mut cfg_options, Some(&display_name));
+                    let crate_id = crate_graph.add_crate_root(
+                        file_id,
+                        Edition::Edition2018,
+                        Some(display_name.clone()),
+                        cfg_options,
+                        Env::default(),
+                        Vec::new(),
+                    );
+                    sysroot_deps.add_public_deps(&mut crate_graph, crate_id, &display_name);
+                }
+            }
         }
         crate_graph
     }
@@ -513,10 +603,128 @@ impl ProjectWorkspace {
                 .iter()
                 .find(|root| path.starts_with(&root.path))
                 .map(|root| root.path.as_path()),
+            ProjectWorkspace::DetachedFiles { files, .. } => {
+                files.iter().find(|file| path == file.as_path().as_ref()).map(|file| file.as_path())
+            }
         }
     }
 }
 
+/// The sysroot crates which other crates of the workspace depend on.
+struct SysrootDeps {
+    /// Implicit dependencies of every crate, in the order rustc adds them.
+    public_deps: Vec<(CrateName, CrateId)>,
+    proc_macro: Option<CrateId>,
+}
+
+impl SysrootDeps {
+    fn add_public_deps(&self, crate_graph: &mut CrateGraph, from: CrateId, crate_name: &str) {
+        for (name, krate) in self.public_deps.iter() {
+            if crate_graph.add_dep(from, name.clone(), *krate).is_err() {
+                log::error!("cyclic dependency on {} for {}", name, crate_name)
+            }
+        }
+    }
+
+    fn add_proc_macro_dep(&self, crate_graph: &mut CrateGraph, from: CrateId, crate_name: &str) {
+        if let Some(proc_macro) = self.proc_macro {
+            let name = CrateName::new("proc_macro").unwrap();
+            if crate_graph.add_dep(from, name, proc_macro).is_err() {
+                log::error!("cyclic dependency on proc_macro for {}", crate_name)
+            }
+        }
+    }
+}
+
+fn sysroot_to_crate_graph(
+    crate_graph: &mut CrateGraph,
+    sysroot: &Sysroot,
+    cfg_options: &CfgOptions,
+    load: &mut dyn FnMut(&AbsPath) -> Option<FileId>,
+) -> SysrootDeps {
+    let sysroot_crates: FxHashMap<_, _> = sysroot
+        .crates()
+        .filter_map(|krate| {
+            let file_id = load(&sysroot[krate].root)?;
+
+            let env = Env::default();
+            let proc_macro = vec![];
+            let name = sysroot[krate].name.clone();
+            let crate_id = crate_graph.add_crate_root(
+                file_id,
+                Edition::Edition2018,
+                Some(name),
+                cfg_options.clone(),
+                env,
+                proc_macro,
+            );
+            Some((krate, crate_id))
+        })
+        .collect();
+
+    for from in sysroot.crates() {
+        for &to in sysroot[from].deps.iter() {
+            let name = &sysroot[to].name;
+            if let (Some(&from), Some(&to)) = (sysroot_crates.get(&from), sysroot_crates.get(&to)) {
+                if crate_graph.add_dep(from, CrateName::new(name).unwrap(), to).is_err() {
+                    log::error!("cyclic dependency between sysroot crates")
+                }
+            }
+        }
+    }
+
+    // core is added as a dependency before std in order to
+    // mimic rustcs dependency order
+    let public_deps =
+        vec![("core", sysroot.core()), ("alloc", sysroot.alloc()), ("std", sysroot.std())]
+            .into_iter()
+            .filter_map(|(name, krate)| {
+                let krate = sysroot_crates.get(&krate?).copied()?;
+                Some((CrateName::new(name).unwrap(), krate))
+            })
+            .collect();
+    let proc_macro = sysroot.proc_macro().and_then(|it| sysroot_crates.get(&it).copied());
+    SysrootDeps { public_deps, proc_macro }
+}
+
+/// Returns the environment that cargo compiles the crates of `pkg` with, which includes the
+/// variables set by its build script.
+fn package_env(pkg: &PackageData, env: &mut Env) {
+    if let Some(manifest_dir) = pkg.manifest.parent().and_then(|it| it.to_str()) {
+        env.set("CARGO_MANIFEST_DIR", manifest_dir.to_string());
+    }
+    env.set("CARGO_PKG_NAME", pkg.name.clone());
+    env.set("CARGO_PKG_VERSION", pkg.version.clone());
+    let mut version_parts = pkg.version.split(|c| c == '.' || c == '-');
+    for key in &["CARGO_PKG_VERSION_MAJOR", "CARGO_PKG_VERSION_MINOR", "CARGO_PKG_VERSION_PATCH"] {
+        env.set(key, version_parts.next().unwrap_or_default().to_string());
+    }
+    if let Some(out_dir) = &pkg.out_dir {
+        // NOTE: cargo and rustc seem to hide non-UTF-8 strings from env! and option_env!()
+        if let Some(out_dir) = out_dir.to_str().map(|s| s.to_owned()) {
+            env.set("OUT_DIR", out_dir);
+        }
+    }
+    for (key, value) in &pkg.envs {
+        env.set(key, value.clone());
+    }
+}
+
+fn load_proc_macro(client: &ProcMacroClient, path: &Path) -> Vec<ProcMacro> {
+    client
+        .by_dylib_path(path)
+        .into_iter()
+        .map(|(name, kind, expander)| {
+            let kind = match kind {
+                ra_proc_macro::ProcMacroKind::CustomDerive => ProcMacroKind::CustomDerive,
+                ra_proc_macro::ProcMacroKind::FuncLike => ProcMacroKind::FuncLike,
+                ra_proc_macro::ProcMacroKind::Attr => ProcMacroKind::Attr,
+            };
+            ProcMacro { name, kind, expander }
+        })
+        .collect()
+}
+
 fn get_rustc_cfg_options(target: Option<&str>) -> CfgOptions {
     let mut cfg_options = CfgOptions::default();
 
@@ -544,14 +752,7 @@ fn get_rustc_cfg_options(target: Option<&str>) -> CfgOptions {
     match rustc_cfgs {
         Ok(rustc_cfgs) => {
             for line in rustc_cfgs.lines() {
-                match line.find('=') {
-                    None => cfg_options.insert_atom(line.into()),
-                    Some(pos) => {
-                        let key = &line[..pos];
-                        let value = line[pos + 1..].trim_matches('"');
-                        cfg_options.insert_key_value(key.into(), value.into());
-                    }
-                }
+                cfg_options.insert(CfgAtom::parse(line));
             }
         }
         Err(e) => log::error!("failed to get rustc cfgs: {:#}", e),
@@ -0,0 +1,211 @@
+//! Caches the output of `cargo metadata` across restarts.
+//!
+//! `cargo metadata` takes seconds on big workspaces, while its output only
+//! changes when the manifests, the lock file, the cargo configuration or the
+//! set of auto-discovered targets change. We store the output together with
+//! fingerprints of those inputs, and reuse it as long as none of them changed.
+//! The command line and the version of cargo are part of the cache key.
+
+use std::{
+    env, fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use cargo_metadata::Metadata;
+use paths::{AbsPath, AbsPathBuf};
+use rustc_hash::FxHasher;
+use serde::{Deserialize, Serialize};
+
+use crate::cargo_config_file::config_dirs;
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry<M> {
+    fingerprints: Vec<(PathBuf, Option<u64>)>,
+    metadata: M,
+}
+
+pub(crate) struct MetadataCache {
+    path: PathBuf,
+    /// The directory cargo runs in, which decides the config files it reads.
+    cwd: AbsPathBuf,
+}
+
+impl MetadataCache {
+    /// Returns the cache for the `cargo metadata` invocation `cmd`, or `None`
+    /// if there is no cache directory or the version of cargo is unknown.
+    pub(crate) fn new(cmd: &Command, cwd: &AbsPath) -> Option<MetadataCache> {
+        let dir = cache_dir()?;
+        let output = Command::new(cmd.get_program()).arg("--version").current_dir(cwd).output();
+        let version = match output {
+            Ok(output) if output.status.success() => String::from_utf8(output.stdout).ok()?,
+            _ => {
+                log::warn!("failed to get the version of cargo, not caching cargo metadata");
+                return None;
+            }
+        };
+        let key = format!("{:?}\n{}", cmd, version.trim());
+        Some(MetadataCache::in_dir(&dir, &key, cwd))
+    }
+
+    fn in_dir(dir: &Path, key: &str, cwd: &AbsPath) -> MetadataCache {
+        let path = dir.join(format!("cargo-metadata-{:016x}.json", hash(key)));
+        MetadataCache { path, cwd: cwd.to_path_buf() }
+    }
+
+    /// Returns the cached metadata if none of its inputs changed since it was
+    /// stored.
+    pub(crate) fn load(&self) -> Option<Metadata> {
+        let text = fs::read_to_string(&self.path).ok()?;
+        let entry: CacheEntry<Metadata> = serde_json::from_str(&text).ok()?;
+        let is_fresh = entry.fingerprints.iter().all(|(path, it)| fingerprint(path) == *it);
+        if !is_fresh {
+            log::info!("cargo metadata cache is stale: {}", self.path.display());
+            return None;
+        }
+        Some(entry.metadata)
+    }
+
+    pub(crate) fn store(&self, metadata: &Metadata) {
+        let fingerprints = inputs(metadata, &self.cwd)
+            .into_iter()
+            .map(|path| {
+                let fingerprint = fingerprint(&path);
+                (path, fingerprint)
+            })
+            .collect();
+        let entry = CacheEntry { fingerprints, metadata };
+        let res = (|| -> io::Result<()> {
+            fs::create_dir_all(self.path.parent().unwrap())?;
+            fs::write(&self.path, serde_json::to_string(&entry)?)
+        })();
+        if let Err(err) = res {
+            log::warn!("failed to write {}: {}", self.path.display(), err);
+        }
+    }
+}
+
+/// Returns the directory in which rust-analyzer keeps data across restarts.
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CACHE_HOME") {
+        Some(it) => PathBuf::from(it),
+        None if cfg!(windows) => PathBuf::from(env::var_os("LOCALAPPDATA")?),
+        None if cfg!(target_os = "macos") => {
+            PathBuf::from(env::var_os("HOME")?).join("Library/Caches")
+        }
+        None => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("rust-analyzer"))
+}
+
+/// The files and directories which determine the output of `cargo metadata`
+/// run in `cwd`.
+fn inputs(metadata: &Metadata, cwd: &AbsPath) -> Vec<PathBuf> {
+    let root = &metadata.workspace_root;
+    // The root manifest is not a package one in virtual workspaces.
+    let mut res = vec![root.join("Cargo.toml"), root.join("Cargo.lock")];
+    // Config files which don't exist yet are inputs too, creating them
+    // invalidates the cache.
+    for dir in config_dirs(cwd) {
+        res.push(dir.join("config").into());
+        res.push(dir.join("config.toml").into());
+    }
+    // Registry and git packages never change, but local ones can gain targets
+    // which cargo discovers automatically.
+    for package in metadata.packages.iter().filter(|it| it.source.is_none()) {
+        res.push(package.manifest_path.clone());
+        if let Some(dir) = package.manifest_path.parent() {
+            let auto_targets = [
+                "build.rs",
+                "src/lib.rs",
+                "src/main.rs",
+                "src/bin",
+                "examples",
+                "tests",
+                "benches",
+            ];
+            res.extend(auto_targets.iter().map(|it| dir.join(it)));
+        }
+    }
+    res
+}
+
+/// Hashes the contents of a file, or the names of the entries of a directory.
+fn fingerprint(path: &Path) -> Option<u64> {
+    if path.is_dir() {
+        let mut names = fs::read_dir(path)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name())
+            .collect::<Vec<_>>();
+        names.sort();
+        Some(hash(&names))
+    } else {
+        fs::read(path).ok().map(|contents| hash(&contents))
+    }
+}
+
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = FxHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalidates_on_changed_inputs() {
+        let dir = env::temp_dir().join(format!("ra-metadata-cache-{}", std::process::id()));
+        let pkg_dir = dir.join("foo");
+        fs::create_dir_all(pkg_dir.join("src")).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[workspace]").unwrap();
+        fs::write(dir.join("Cargo.lock"), "").unwrap();
+        fs::write(pkg_dir.join("Cargo.toml"), "[package]").unwrap();
+        let json = serde_json::json!({
+            "packages": [{
+                "name": "foo",
+                "version": "0.0.0",
+                "id": "foo 0.0.0",
+                "source": null,
+                "dependencies": [],
+                "targets": [],
+                "features": {},
+                "manifest_path": pkg_dir.join("Cargo.toml"),
+            }],
+            "workspace_members": ["foo 0.0.0"],
+            "resolve": null,
+            "workspace_root": dir,
+            "target_directory": dir.join("target"),
+            "version": 1,
+        });
+        let metadata: Metadata = serde_json::from_value(json).unwrap();
+        let cwd = AbsPathBuf::assert(pkg_dir.clone());
+
+        let cache = MetadataCache::in_dir(&dir.join("cache"), "key", &cwd);
+        assert!(cache.load().is_none());
+        cache.store(&metadata);
+        assert!(cache.load().is_some());
+        assert!(MetadataCache::in_dir(&dir.join("cache"), "other key", &cwd).load().is_none());
+
+        let mut stale = Vec::new();
+        let mut check = |change: &dyn Fn()| {
+            change();
+            stale.push(cache.load().is_none());
+            cache.store(&metadata);
+        };
+        check(&|| fs::write(pkg_dir.join("src/main.rs"), "fn main() {}").unwrap());
+        check(&|| fs::write(dir.join("Cargo.lock"), "# changed").unwrap());
+        check(&|| fs::write(dir.join("Cargo.toml"), "[workspace]\nmembers = []").unwrap());
+        check(&|| {
+            fs::create_dir_all(dir.join(".cargo")).unwrap();
+            fs::write(dir.join(".cargo/config.toml"), "").unwrap();
+        });
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(stale, vec![true; 4]);
+    }
+}
@@ -12,6 +12,8 @@ use stdx::split_delim;
 /// Roots and crates that compose this Rust project.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct ProjectJson {
+    pub(crate) project_root: AbsPathBuf,
+    pub(crate) sysroot_src: Option<AbsPathBuf>,
     pub(crate) roots: Vec<Root>,
     pub(crate) crates: Vec<Crate>,
 }
@@ -27,6 +29,7 @@ pub struct Root {
 /// useful in creating the crate graph.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Crate {
+    pub(crate) display_name: Option<String>,
     pub(crate) root_module: AbsPathBuf,
     pub(crate) edition: Edition,
     pub(crate) deps: Vec<Dependency>,
@@ -39,11 +42,14 @@ pub struct Crate {
 impl ProjectJson {
     pub fn new(base: &AbsPath, data: ProjectJsonData) -> ProjectJson {
         ProjectJson {
+            project_root: base.to_path_buf(),
+            sysroot_src: data.sysroot_src.map(|it| base.join(it)),
             roots: data.roots.into_iter().map(|path| Root { path: base.join(path) }).collect(),
             crates: data
                 .crates
                 .into_iter()
                 .map(|crate_data| Crate {
+                    display_name: crate_data.display_name,
                     root_module: base.join(crate_data.root_module),
                     edition: crate_data.edition.into(),
                     deps: crate_data
@@ -73,16 +79,23 @@ impl ProjectJson {
                 .collect::<Vec<_>>(),
         }
     }
+
+    /// Directory containing the `rust-project.json` file.
+    pub fn path(&self) -> &AbsPath {
+        &self.project_root
+    }
 }
 
 #[derive(Deserialize)]
 pub struct ProjectJsonData {
+    sysroot_src: Option<PathBuf>,
     roots: Vec<PathBuf>,
     crates: Vec<CrateData>,
 }
 
 #[derive(Deserialize)]
 struct CrateData {
+    display_name: Option<String>,
     root_module: PathBuf,
     edition: EditionData,
     deps: Vec<DepData>,
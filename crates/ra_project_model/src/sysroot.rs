@@ -1,4 +1,9 @@
-//! FIXME: write short doc here
+//! Loads the standard library crates from the `rust-src` component.
+//!
+//! The sources are usually discovered via `rustc --print sysroot`, but can also
+//! be given explicitly via `RUST_SRC_PATH` or the configuration. Both the
+//! current `library/<crate>/src/lib.rs` layout and the older
+//! `src/lib<crate>/lib.rs` one are supported.
 
 use std::{convert::TryFrom, env, ops, path::Path, process::Command};
 
@@ -50,12 +55,38 @@ impl Sysroot {
         self.crates.iter().map(|(id, _data)| id)
     }
 
-    pub fn discover(cargo_toml: &AbsPath) -> Result<Sysroot> {
-        let src = get_or_install_rust_src(cargo_toml)?;
+    /// Finds the standard library sources for the toolchain used in the
+    /// directory of `cargo_toml`, unless `sysroot_src` points at them already.
+    pub fn discover(cargo_toml: &AbsPath, sysroot_src: Option<&AbsPath>) -> Result<Sysroot> {
+        let src = match sysroot_src {
+            Some(src) => {
+                if !src.exists() {
+                    bail!("configured standard library sources do not exist: {}", src.display())
+                }
+                src.to_path_buf()
+            }
+            None => discover_rust_src(cargo_toml)?,
+        };
+        let sysroot = Sysroot::load(&src);
+        if sysroot.core().is_none() {
+            bail!("could not find libcore in the standard library sources at {}", src.display())
+        }
+        Ok(sysroot)
+    }
+
+    /// Loads the standard library crates from an explicitly given source
+    /// directory, which contains `core`, `std` and friends.
+    pub fn load(src: &AbsPath) -> Sysroot {
         let mut sysroot = Sysroot { crates: Arena::default() };
         for name in SYSROOT_CRATES.trim().lines() {
-            let root = src.join(format!("lib{}", name)).join("lib.rs");
-            if root.exists() {
+            let root = [
+                src.join(name).join("src/lib.rs"),
+                src.join(format!("lib{}", name)).join("lib.rs"),
+            ]
+            .iter()
+            .find(|it| it.exists())
+            .cloned();
+            if let Some(root) = root {
                 sysroot.crates.alloc(SysrootCrateData {
                     name: name.into(),
                     root,
@@ -75,7 +106,7 @@ impl Sysroot {
                 sysroot.crates[alloc].deps.push(core);
             }
         }
-        Ok(sysroot)
+        sysroot
     }
 
     fn by_name(&self, name: &str) -> Option<SysrootCrate> {
@@ -83,7 +114,7 @@ impl Sysroot {
     }
 }
 
-fn get_or_install_rust_src(cargo_toml: &AbsPath) -> Result<AbsPathBuf> {
+fn discover_rust_src(cargo_toml: &AbsPath) -> Result<AbsPathBuf> {
     if let Ok(path) = env::var("RUST_SRC_PATH") {
         let path = AbsPathBuf::try_from(path.as_str())
             .map_err(|path| format_err!("RUST_SRC_PATH must be absolute: {}", path.display()))?;
@@ -95,23 +126,43 @@ fn get_or_install_rust_src(cargo_toml: &AbsPath) -> Result<AbsPathBuf> {
     let rustc_output = output(rustc)?;
     let stdout = String::from_utf8(rustc_output.stdout)?;
     let sysroot_path = AbsPath::assert(Path::new(stdout.trim()));
-    let src_path = sysroot_path.join("lib/rustlib/src/rust/src");
-
-    if !src_path.exists() {
-        let mut rustup = Command::new(ra_toolchain::rustup());
-        rustup.current_dir(current_dir).args(&["component", "add", "rust-src"]);
-        let _output = output(rustup)?;
-    }
-    if !src_path.exists() {
-        bail!(
-            "can't load standard library from sysroot\n\
-            {}\n\
-            (discovered via `rustc --print sysroot`)\n\
-            try running `rustup component add rust-src` or set `RUST_SRC_PATH`",
-            src_path.display(),
-        )
-    }
-    Ok(src_path)
+
+    // Since Rust 1.47 the sources live in `library`, before that in `src`.
+    let candidates = ["lib/rustlib/src/rust/library", "lib/rustlib/src/rust/src"]
+        .iter()
+        .map(|it| sysroot_path.join(it));
+    for src_path in candidates {
+        if src_path.join("core").exists() || src_path.join("libcore").exists() {
+            return Ok(src_path);
+        }
+    }
+
+    let hint = if rust_src_is_installed(current_dir) {
+        "the `rust-src` component is installed, but its layout is not supported"
+    } else {
+        "try running `rustup component add rust-src` or set `RUST_SRC_PATH`"
+    };
+    bail!(
+        "can't load standard library from sysroot\n\
+        {}\n\
+        (discovered via `rustc --print sysroot`)\n\
+        {}",
+        sysroot_path.display(),
+        hint,
+    )
+}
+
+/// Asks rustup whether the `rust-src` component is installed for the toolchain
+/// used in `dir`. Returns `false` if rustup is not available.
+fn rust_src_is_installed(dir: &AbsPath) -> bool {
+    let mut rustup = Command::new(ra_toolchain::rustup());
+    rustup.current_dir(dir).args(&["component", "list", "--installed"]);
+    match output(rustup) {
+        Ok(output) => {
+            String::from_utf8_lossy(&output.stdout).lines().any(|it| it.starts_with("rust-src"))
+        }
+        Err(_) => false,
+    }
 }
 
 impl SysrootCrateData {
@@ -160,3 +211,40 @@ rustc_lsan
 rustc_msan
 rustc_tsan
 build_helper";
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn check_layout(name: &str, files: &[&str]) {
+        let dir = env::temp_dir().join(format!("ra-sysroot-{}-{}", name, std::process::id()));
+        for file in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "").unwrap();
+        }
+        let src = AbsPathBuf::assert(dir.clone());
+        let sysroot = Sysroot::load(&src);
+        let _ = fs::remove_dir_all(&dir);
+
+        let core = sysroot.core().unwrap();
+        let alloc = sysroot.alloc().unwrap();
+        let std = sysroot.std().unwrap();
+        assert_eq!(sysroot[alloc].deps, vec![core]);
+        assert!(sysroot[std].deps.contains(&alloc));
+        assert!(sysroot[std].deps.contains(&core));
+        assert!(sysroot.proc_macro().is_none());
+    }
+
+    #[test]
+    fn loads_library_layout() {
+        check_layout("library", &["core/src/lib.rs", "alloc/src/lib.rs", "std/src/lib.rs"]);
+    }
+
+    #[test]
+    fn loads_legacy_layout() {
+        check_layout("legacy", &["libcore/lib.rs", "liballoc/lib.rs", "libstd/lib.rs"]);
+    }
+}
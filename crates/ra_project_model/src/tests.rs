@@ -0,0 +1,234 @@
+//! Tests of the crate graph of cargo workspaces, built from handwritten
+//! `cargo metadata` output.
+
+use paths::AbsPathBuf;
+use ra_cfg::{CfgAtom, CfgDiff, CfgExpr};
+use ra_db::{CrateData, CrateGraph, FileId};
+use rustc_hash::FxHashMap;
+use serde_json::{json, Value};
+
+use crate::{
+    cargo_config_file::CargoConfigFile,
+    cargo_workspace::{metadata_command, ExternResources},
+    CargoConfig, CargoWorkspace, CfgOverrides, ProcMacroClient, ProjectWorkspace, Sysroot,
+};
+
+/// A package in `/ws/<name>`. Targets are `(kind, path)` pairs, with paths
+/// relative to the package, and dependencies are `(name, kind)` pairs.
+struct Pkg {
+    name: &'static str,
+    member: bool,
+    targets: &'static [(&'static str, &'static str)],
+    deps: &'static [(&'static str, &'static str)],
+    features: &'static [&'static str],
+}
+
+fn pkg(name: &'static str) -> Pkg {
+    Pkg { name, member: true, targets: &[("lib", "src/lib.rs")], deps: &[], features: &[] }
+}
+
+fn metadata(pkgs: &[Pkg]) -> cargo_metadata::Metadata {
+    let id = |name: &str| format!("{} 0.1.0 (path+file:///ws/{})", name, name);
+    let packages = pkgs
+        .iter()
+        .map(|pkg| {
+            let targets = pkg
+                .targets
+                .iter()
+                .map(|(kind, path)| {
+                    json!({
+                        "name": pkg.name,
+                        "kind": [kind],
+                        "src_path": format!("/ws/{}/{}", pkg.name, path),
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "name": pkg.name,
+                "version": "0.1.0",
+                "id": id(pkg.name),
+                "source": Value::Null,
+                "dependencies": [],
+                "targets": targets,
+                "features": {},
+                "manifest_path": format!("/ws/{}/Cargo.toml", pkg.name),
+                "edition": "2018",
+            })
+        })
+        .collect::<Vec<_>>();
+    let nodes = pkgs
+        .iter()
+        .map(|pkg| {
+            let deps = pkg
+                .deps
+                .iter()
+                .map(|(name, kind)| {
+                    json!({ "name": name, "pkg": id(name), "dep_kinds": [{ "kind": kind }] })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "id": id(pkg.name),
+                "dependencies": pkg.deps.iter().map(|(name, _)| id(name)).collect::<Vec<_>>(),
+                "deps": deps,
+                "features": pkg.features,
+            })
+        })
+        .collect::<Vec<_>>();
+    let members = pkgs.iter().filter(|it| it.member).map(|it| id(it.name)).collect::<Vec<_>>();
+    serde_json::from_value(json!({
+        "packages": packages,
+        "workspace_members": members,
+        "resolve": { "nodes": nodes, "root": Value::Null },
+        "workspace_root": "/ws",
+        "target_directory": "/ws/target",
+        "version": 1,
+    }))
+    .unwrap()
+}
+
+fn workspace(pkgs: &[Pkg], config_file: CargoConfigFile) -> ProjectWorkspace {
+    let cargo = CargoWorkspace::new(
+        metadata(pkgs),
+        &CargoConfig::default(),
+        ExternResources::default(),
+        config_file,
+    )
+    .unwrap();
+    ProjectWorkspace::Cargo { cargo, sysroot: Sysroot::default() }
+}
+
+struct TestGraph {
+    graph: CrateGraph,
+    files: FxHashMap<String, FileId>,
+}
+
+impl TestGraph {
+    fn new(ws: &ProjectWorkspace, target: Option<&str>, cfg_overrides: &CfgOverrides) -> TestGraph {
+        let mut files = FxHashMap::default();
+        let graph =
+            ws.to_crate_graph(target, cfg_overrides, &ProcMacroClient::dummy(), &mut |path| {
+                let path = path.to_str().unwrap().to_string();
+                let next_id = FileId(files.len() as u32);
+                Some(*files.entry(path).or_insert(next_id))
+            });
+        TestGraph { graph, files }
+    }
+
+    /// Returns the crate whose root is `path`, like `/ws/foo/src/lib.rs`.
+    fn krate(&self, path: &str) -> &CrateData {
+        let file_id = self.files[path];
+        let krate = self.graph.iter().find(|&it| self.graph[it].root_file_id == file_id).unwrap();
+        &self.graph[krate]
+    }
+
+    fn is_cfg_enabled(&self, path: &str, cfg: &str) -> bool {
+        let cfg = CfgExpr::from(CfgAtom::parse(cfg));
+        self.krate(path).cfg_options.check(&cfg) == Some(true)
+    }
+
+    fn deps(&self, path: &str) -> Vec<String> {
+        self.krate(path).dependencies.iter().map(|it| it.name.to_string()).collect()
+    }
+}
+
+#[test]
+fn resolved_features_are_cfgs() {
+    let ws = workspace(&[Pkg { features: &["default", "std"], ..pkg("foo") }], Default::default());
+    let graph = TestGraph::new(&ws, None, &CfgOverrides::default());
+    assert!(graph.is_cfg_enabled("/ws/foo/src/lib.rs", r#"feature="std""#));
+    assert!(!graph.is_cfg_enabled("/ws/foo/src/lib.rs", r#"feature="extra""#));
+}
+
+#[test]
+fn features_are_passed_to_cargo_metadata() {
+    let args = |config: &CargoConfig| {
+        let cargo_toml = AbsPathBuf::assert("/ws/Cargo.toml".into());
+        let cmd = metadata_command(&cargo_toml, config, &CargoConfigFile::default());
+        let cmd = cmd.cargo_command().unwrap();
+        cmd.get_args().map(|it| it.to_str().unwrap().to_string()).collect::<Vec<_>>()
+    };
+    let features = vec!["extra".to_string()];
+
+    let res = args(&CargoConfig { no_default_features: true, features, ..Default::default() });
+    assert!(res.contains(&"--no-default-features".to_string()), "{:?}", res);
+    assert!(res.windows(2).any(|it| it == ["--features", "extra"]), "{:?}", res);
+
+    let features = vec!["extra".to_string()];
+    let res = args(&CargoConfig { all_features: true, features, ..Default::default() });
+    assert!(res.contains(&"--all-features".to_string()), "{:?}", res);
+    assert!(!res.contains(&"--features".to_string()), "{:?}", res);
+}
+
+#[test]
+fn target_decides_target_cfgs() {
+    let ws = workspace(&[pkg("foo")], Default::default());
+    let graph = TestGraph::new(&ws, Some("wasm32-unknown-unknown"), &CfgOverrides::default());
+    assert!(graph.is_cfg_enabled("/ws/foo/src/lib.rs", r#"target_arch="wasm32""#));
+    assert!(!graph.is_cfg_enabled("/ws/foo/src/lib.rs", "unix"));
+}
+
+#[test]
+fn target_is_passed_to_cargo_metadata() {
+    let cargo_toml = AbsPathBuf::assert("/ws/Cargo.toml".into());
+    let config_file = CargoConfigFile {
+        target: Some("wasm32-unknown-unknown".to_string()),
+        ..Default::default()
+    };
+    let cmd = metadata_command(&cargo_toml, &CargoConfig::default(), &config_file);
+    let cmd = cmd.cargo_command().unwrap();
+    let args = cmd.get_args().map(|it| it.to_str().unwrap().to_string()).collect::<Vec<_>>();
+    assert!(args.windows(2).any(|it| it == ["--filter-platform", "wasm32-unknown-unknown"]));
+}
+
+#[test]
+fn cfg_overrides_apply_globally_and_per_crate() {
+    let ws = workspace(&[pkg("foo"), pkg("bar")], Default::default());
+    let mut cfg_overrides = CfgOverrides::default();
+    cfg_overrides.global.enable.push(CfgAtom::Flag("fuzzing".into()));
+    cfg_overrides.selective.insert(
+        "foo".to_string(),
+        CfgDiff { enable: Vec::new(), disable: vec![CfgAtom::Flag("test".into())] },
+    );
+    let graph = TestGraph::new(&ws, None, &cfg_overrides);
+    assert!(graph.is_cfg_enabled("/ws/foo/src/lib.rs", "fuzzing"));
+    assert!(!graph.is_cfg_enabled("/ws/foo/src/lib.rs", "test"));
+    assert!(graph.is_cfg_enabled("/ws/bar/src/lib.rs", "fuzzing"));
+    assert!(graph.is_cfg_enabled("/ws/bar/src/lib.rs", "test"));
+}
+
+#[test]
+fn dev_dependencies_are_visible_to_test_targets_only() {
+    let foo = Pkg {
+        targets: &[
+            ("lib", "src/lib.rs"),
+            ("bin", "src/main.rs"),
+            ("test", "tests/it.rs"),
+            ("bench", "benches/bench.rs"),
+            ("example", "examples/ex.rs"),
+        ],
+        deps: &[("normal", "normal"), ("dev", "dev"), ("build", "build")],
+        ..pkg("foo")
+    };
+    let non_member = |name| Pkg { member: false, ..pkg(name) };
+    let ws = workspace(
+        &[foo, non_member("normal"), non_member("dev"), non_member("build")],
+        Default::default(),
+    );
+    let graph = TestGraph::new(&ws, None, &CfgOverrides::default());
+
+    for path in &["/ws/foo/src/lib.rs", "/ws/foo/src/main.rs"] {
+        let deps = graph.deps(path);
+        assert!(deps.contains(&"normal".to_string()), "{}: {:?}", path, deps);
+        assert!(!deps.contains(&"dev".to_string()), "{}: {:?}", path, deps);
+        assert!(!deps.contains(&"build".to_string()), "{}: {:?}", path, deps);
+    }
+    for path in &["/ws/foo/tests/it.rs", "/ws/foo/benches/bench.rs", "/ws/foo/examples/ex.rs"] {
+        let deps = graph.deps(path);
+        assert!(deps.contains(&"normal".to_string()), "{}: {:?}", path, deps);
+        assert!(deps.contains(&"dev".to_string()), "{}: {:?}", path, deps);
+        assert!(!deps.contains(&"build".to_string()), "{}: {:?}", path, deps);
+    }
+    // Members are analyzed with `cfg(test)`, their dependencies are not.
+    assert!(graph.is_cfg_enabled("/ws/foo/src/lib.rs", "test"));
+    assert!(!graph.is_cfg_enabled("/ws/dev/src/lib.rs", "test"));
+}
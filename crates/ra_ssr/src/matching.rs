@@ -5,10 +5,16 @@ use crate::{
     parsing::{Constraint, NodeKind, Placeholder, SsrTemplate},
     SsrMatches, SsrPattern, SsrRule,
 };
-use hir::Semantics;
+use hir::{
+    db::HirDatabase, Access, AsAssocItem, AssocItem, AssocItemContainer, ModuleDef, PathResolution,
+    Semantics, Type,
+};
 use ra_db::FileRange;
-use ra_syntax::ast::{AstNode, AstToken};
-use ra_syntax::{ast, SyntaxElement, SyntaxElementChildren, SyntaxKind, SyntaxNode, SyntaxToken};
+use ra_syntax::ast::{ArgListOwner, AstNode, AstToken};
+use ra_syntax::{
+    ast, match_ast, SmolStr, SyntaxElement, SyntaxElementChildren, SyntaxKind, SyntaxNode,
+    SyntaxToken,
+};
 use rustc_hash::FxHashMap;
 use std::{cell::Cell, iter::Peekable};
 use test_utils::mark;
@@ -66,6 +72,11 @@ pub(crate) struct PlaceholderMatch {
     pub(crate) range: FileRange,
     /// More matches, found within `node`.
     pub(crate) inner_matches: SsrMatches,
+    /// When a method call receiver was matched against an explicit argument, or vice versa, these
+    /// record how the matched code needs to be dereferenced and then referenced to get the value
+    /// that was actually passed.
+    pub(crate) autoderef_count: usize,
+    pub(crate) autoref_kind: Access,
 }
 
 #[derive(Debug)]
@@ -177,6 +188,17 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
             }
             return Ok(());
         }
+        // Function calls and method calls can match each other if they resolve to the same thing.
+        if let (Some(pattern), Some(code)) =
+            (ast::CallExpr::cast(pattern.clone()), ast::MethodCallExpr::cast(code.clone()))
+        {
+            return self.attempt_match_ufcs_to_method_call(phase, &pattern, &code);
+        }
+        if let (Some(pattern), Some(code)) =
+            (ast::MethodCallExpr::cast(pattern.clone()), ast::CallExpr::cast(code.clone()))
+        {
+            return self.attempt_match_method_call_to_ufcs(phase, &pattern, &code);
+        }
         // Non-placeholders.
         if pattern.kind() != code.kind() {
             fail_match!(
@@ -194,6 +216,7 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
                 self.attempt_match_record_field_list(phase, pattern, code)
             }
             SyntaxKind::TOKEN_TREE => self.attempt_match_token_tree(phase, pattern, code),
+            SyntaxKind::PATH => self.attempt_match_path(phase, pattern, code),
             _ => self.attempt_match_node_children(phase, pattern, code),
         }
     }
@@ -301,6 +324,22 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
             Constraint::Kind(kind) => {
                 kind.matches(code)?;
             }
+            Constraint::Path(segments) => {
+                let path = match_ast_path(code).ok_or_else(|| {
+                    match_error!(
+                        "Code '{}' isn't a path, but a path constraint applies",
+                        code.text()
+                    )
+                })?;
+                if !self.path_resolves_to(&path, segments) {
+                    fail_match!("'{}' doesn't resolve to {}", code.text(), segments.join("::"));
+                }
+            }
+            Constraint::InMacro => {
+                if code.ancestors().last().map(|it| it.kind()) == Some(SyntaxKind::SOURCE_FILE) {
+                    fail_match!("'{}' didn't come from a macro expansion", code.text());
+                }
+            }
             Constraint::Not(sub) => {
                 if self.check_constraint(&*sub, code).is_ok() {
                     fail_match!("Constraint {:?} failed for '{}'", constraint, code.text());
@@ -310,6 +349,263 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
         Ok(())
     }
 
+    /// Paths that are spelled differently can still match, if the pattern is a path free of
+    /// placeholders and the path in the code resolves to the item that it names.
+    fn attempt_match_path(
+        &self,
+        phase: &mut Phase,
+        pattern: &SyntaxNode,
+        code: &SyntaxNode,
+    ) -> Result<(), MatchFailed> {
+        let textual = self.attempt_match_node_children(phase, pattern, code);
+        if textual.is_ok() {
+            return textual;
+        }
+        let segments = ast::Path::cast(pattern.clone()).and_then(|it| self.path_segments(&it));
+        if let (Some(segments), Some(code)) = (segments, ast::Path::cast(code.clone())) {
+            let code_name = code.segment().and_then(|it| it.name_ref()).map(|it| it.text().clone());
+            if segments.last() == code_name.as_ref() && self.path_resolves_to(&code, &segments) {
+                return Ok(());
+            }
+        }
+        textual
+    }
+
+    /// Matches a call such as `Foo::bar($s, $a)` against a method call such as `x.bar(1)`,
+    /// provided that the method resolves to the function named by the pattern.
+    fn attempt_match_ufcs_to_method_call(
+        &self,
+        phase: &mut Phase,
+        pattern: &ast::CallExpr,
+        code: &ast::MethodCallExpr,
+    ) -> Result<(), MatchFailed> {
+        let segments = match pattern.expr() {
+            Some(ast::Expr::PathExpr(it)) => it.path().and_then(|it| self.path_segments(&it)),
+            _ => None,
+        };
+        let segments = match segments {
+            Some(it) => it,
+            None => fail_match!(
+                "Pattern '{}' isn't a call to a function named by its path",
+                pattern.syntax().text()
+            ),
+        };
+        let method_name = code.name_ref().map(|it| it.text().clone());
+        if segments.last() != method_name.as_ref() {
+            fail_match!(
+                "Pattern calls {}, code calls method '{}'",
+                segments.join("::"),
+                code.syntax().text()
+            );
+        }
+        if code.type_arg_list().is_some() {
+            fail_match!("Method call '{}' has explicit type arguments", code.syntax().text());
+        }
+        let function = match self.sema.resolve_method_call(code) {
+            Some(it) => it,
+            None => fail_match!("Failed to resolve method call '{}'", code.syntax().text()),
+        };
+        if !def_has_path(self.sema.db, function.into(), &segments) {
+            fail_match!(
+                "Method call '{}' doesn't resolve to {}",
+                code.syntax().text(),
+                segments.join("::")
+            );
+        }
+        let mut pattern_args = arguments(pattern);
+        if pattern_args.is_empty() {
+            fail_match!(
+                "Pattern has no argument to match the receiver of '{}'",
+                code.syntax().text()
+            );
+        }
+        let pattern_receiver = pattern_args.remove(0);
+        let receiver = match code.expr() {
+            Some(it) => it,
+            None => fail_match!("Method call '{}' has no receiver", code.syntax().text()),
+        };
+        self.attempt_match_node(phase, pattern_receiver.syntax(), receiver.syntax())?;
+        if let Phase::Second(_) = phase {
+            let (autoderef_count, autoref_kind) = self.receiver_adjustments(function, &receiver);
+            phase.record_adjustments(
+                self.get_placeholder(&SyntaxElement::Node(pattern_receiver.syntax().clone())),
+                autoderef_count,
+                autoref_kind,
+            );
+        }
+        self.attempt_match_args(phase, &pattern_args, &arguments(code))
+    }
+
+    /// Matches a method call such as `$s.bar($a)` against a call such as `Foo::bar(&x, 1)`,
+    /// provided that the called function is a method with the same name.
+    fn attempt_match_method_call_to_ufcs(
+        &self,
+        phase: &mut Phase,
+        pattern: &ast::MethodCallExpr,
+        code: &ast::CallExpr,
+    ) -> Result<(), MatchFailed> {
+        let pattern_name = match pattern.name_ref() {
+            Some(it)
+                if self.get_placeholder(&SyntaxElement::Node(it.syntax().clone())).is_none() =>
+            {
+                it.text().clone()
+            }
+            _ => fail_match!("Pattern '{}' doesn't name its method", pattern.syntax().text()),
+        };
+        if pattern.type_arg_list().is_some() {
+            fail_match!("Pattern '{}' has explicit type arguments", pattern.syntax().text());
+        }
+        let code_path = match code.expr() {
+            Some(ast::Expr::PathExpr(it)) => it.path(),
+            _ => None,
+        };
+        let code_path = match code_path {
+            Some(it) => it,
+            None => fail_match!("Code '{}' isn't a call to a path", code.syntax().text()),
+        };
+        let code_name =
+            code_path.segment().and_then(|it| it.name_ref()).map(|it| it.text().clone());
+        if code_name.as_ref() != Some(&pattern_name) {
+            fail_match!(
+                "Pattern calls method {}, code calls '{}'",
+                pattern_name,
+                code.syntax().text()
+            );
+        }
+        let function = match self.sema.resolve_path(&code_path) {
+            Some(PathResolution::AssocItem(AssocItem::Function(it)))
+            | Some(PathResolution::Def(ModuleDef::Function(it))) => it,
+            _ => fail_match!("Failed to resolve '{}' to a function", code_path.syntax().text()),
+        };
+        let access = match function.self_param(self.sema.db) {
+            Some(it) => it.access(self.sema.db),
+            None => fail_match!("'{}' isn't a method", code_path.syntax().text()),
+        };
+        let mut code_args = arguments(code);
+        if code_args.is_empty() {
+            fail_match!("Code '{}' has no argument to match the receiver", code.syntax().text());
+        }
+        let mut receiver = code_args.remove(0);
+        // A method call takes the reference implicitly, so `&x` here corresponds to `x.bar()`.
+        let mut autoref_kind = Access::Owned;
+        if let ast::Expr::RefExpr(ref_expr) = &receiver {
+            let same_kind = match access {
+                Access::Shared => ref_expr.mut_token().is_none(),
+                Access::Exclusive => ref_expr.mut_token().is_some(),
+                Access::Owned => false,
+            };
+            if let (true, None, Some(inner)) = (same_kind, ref_expr.raw_token(), ref_expr.expr()) {
+                receiver = inner;
+                autoref_kind = access;
+            }
+        }
+        let pattern_receiver = match pattern.expr() {
+            Some(it) => it,
+            None => fail_match!("Pattern '{}' has no receiver", pattern.syntax().text()),
+        };
+        self.attempt_match_node(phase, pattern_receiver.syntax(), receiver.syntax())?;
+        phase.record_adjustments(
+            self.get_placeholder(&SyntaxElement::Node(pattern_receiver.syntax().clone())),
+            0,
+            autoref_kind,
+        );
+        self.attempt_match_args(phase, &arguments(pattern), &code_args)
+    }
+
+    fn attempt_match_args(
+        &self,
+        phase: &mut Phase,
+        pattern_args: &[ast::Expr],
+        code_args: &[ast::Expr],
+    ) -> Result<(), MatchFailed> {
+        if pattern_args.len() != code_args.len() {
+            fail_match!(
+                "Pattern has {} argument(s), code has {}",
+                pattern_args.len(),
+                code_args.len()
+            );
+        }
+        for (p, c) in pattern_args.iter().zip(code_args) {
+            self.attempt_match_node(phase, p.syntax(), c.syntax())?;
+        }
+        Ok(())
+    }
+
+    /// Works out how a method call adjusts its receiver, so that the same value can be passed
+    /// explicitly instead. Returns the number of derefs, followed by the kind of reference taken.
+    fn receiver_adjustments(&self, method: hir::Function, receiver: &ast::Expr) -> (usize, Access) {
+        let db = self.sema.db;
+        let access = method.self_param(db).map_or(Access::Owned, |it| it.access(db));
+        let receiver_ty = match self.sema.type_of_expr(receiver) {
+            Some(it) => it,
+            None => return (0, access),
+        };
+        let self_adt = match method.as_assoc_item(db).map(|it| it.container(db)) {
+            Some(AssocItemContainer::ImplDef(imp)) => imp.target_ty(db).as_adt(),
+            _ => None,
+        };
+        let steps: Vec<Type> = receiver_ty.autoderef(db).collect();
+        let derefs = steps
+            .iter()
+            .position(|ty| match self_adt {
+                Some(adt) => ty.as_adt() == Some(adt),
+                None => !ty.is_reference(),
+            })
+            .unwrap_or(0);
+        // If we deref'ed a reference only to take a reference again, use the original one.
+        if access != Access::Owned && derefs > 0 {
+            let before = &steps[derefs - 1];
+            if before.is_reference() && (access == Access::Shared || before.is_mutable_reference())
+            {
+                return (derefs - 1, Access::Owned);
+            }
+        }
+        (derefs, access)
+    }
+
+    /// Returns the segments of `path`, if it contains nothing but plain names, without
+    /// placeholders or generic arguments. A leading `crate` is dropped.
+    fn path_segments(&self, path: &ast::Path) -> Option<Vec<SmolStr>> {
+        let mut res = Vec::new();
+        let mut curr = Some(path.clone());
+        while let Some(path) = curr {
+            let segment = path.segment()?;
+            if segment.type_arg_list().is_some()
+                || segment.param_list().is_some()
+                || segment.ret_type().is_some()
+            {
+                return None;
+            }
+            match segment.kind()? {
+                ast::PathSegmentKind::Name(name_ref) => {
+                    if self
+                        .get_placeholder(&SyntaxElement::Node(name_ref.syntax().clone()))
+                        .is_some()
+                    {
+                        return None;
+                    }
+                    res.push(name_ref.text().clone());
+                }
+                ast::PathSegmentKind::CrateKw if path.qualifier().is_none() => {}
+                _ => return None,
+            }
+            curr = path.qualifier();
+        }
+        res.reverse();
+        Some(res)
+    }
+
+    fn path_resolves_to(&self, path: &ast::Path, segments: &[SmolStr]) -> bool {
+        let def = match self.sema.resolve_path(path) {
+            Some(PathResolution::Def(def)) => def,
+            Some(PathResolution::AssocItem(AssocItem::Function(it))) => it.into(),
+            Some(PathResolution::AssocItem(AssocItem::Const(it))) => it.into(),
+            Some(PathResolution::AssocItem(AssocItem::TypeAlias(it))) => it.into(),
+            _ => return false,
+        };
+        def_has_path(self.sema.db, def, segments)
+    }
+
     /// We want to allow the records to match in any order, so we have special matching logic for
     /// them.
     fn attempt_match_record_field_list(
@@ -463,6 +759,22 @@ impl Phase<'_> {
         }
     }
 
+    fn record_adjustments(
+        &mut self,
+        placeholder: Option<&Placeholder>,
+        autoderef_count: usize,
+        autoref_kind: Access,
+    ) {
+        if let (Phase::Second(match_out), Some(placeholder)) = (self, placeholder) {
+            if let Some(placeholder_match) =
+                match_out.placeholder_values.get_mut(&Var(placeholder.ident.to_string()))
+            {
+                placeholder_match.autoderef_count = autoderef_count;
+                placeholder_match.autoref_kind = autoref_kind;
+            }
+        }
+    }
+
     fn record_ignored_comments(&mut self, token: &SyntaxToken) {
         if token.kind() == SyntaxKind::COMMENT {
             if let Phase::Second(match_out) = self {
@@ -502,11 +814,23 @@ fn recording_match_fail_reasons() -> bool {
 
 impl PlaceholderMatch {
     fn new(node: &SyntaxNode, range: FileRange) -> Self {
-        Self { node: Some(node.clone()), range, inner_matches: SsrMatches::default() }
+        Self {
+            node: Some(node.clone()),
+            range,
+            inner_matches: SsrMatches::default(),
+            autoderef_count: 0,
+            autoref_kind: Access::Owned,
+        }
     }
 
     fn from_range(range: FileRange) -> Self {
-        Self { node: None, range, inner_matches: SsrMatches::default() }
+        Self {
+            node: None,
+            range,
+            inner_matches: SsrMatches::default(),
+            autoderef_count: 0,
+            autoref_kind: Access::Owned,
+        }
     }
 }
 
@@ -539,6 +863,10 @@ impl NodeKind {
                 mark::hit!(literal_constraint);
                 ast::Literal::can_cast(node.kind())
             }
+            Self::Expr => ast::Expr::can_cast(node.kind()),
+            Self::Ty => ast::TypeRef::can_cast(node.kind()),
+            Self::Pat => ast::Pat::can_cast(node.kind()),
+            Self::Path => match_ast_path(node).is_some(),
         };
         if !ok {
             fail_match!("Code '{}' isn't of kind {:?}", node.text(), self);
@@ -547,6 +875,71 @@ impl NodeKind {
     }
 }
 
+/// Returns `def`'s path from the root of its crate, e.g. `["foo", "Bar", "new"]` for an associated
+/// function `new` in an impl of `foo::Bar`.
+fn def_path(db: &dyn HirDatabase, def: ModuleDef) -> Option<Vec<SmolStr>> {
+    let mut res = vec![SmolStr::new(def.name(db)?.to_string())];
+    let container = match def {
+        ModuleDef::Function(it) => it.as_assoc_item(db),
+        ModuleDef::Const(it) => it.as_assoc_item(db),
+        ModuleDef::TypeAlias(it) => it.as_assoc_item(db),
+        _ => None,
+    };
+    match container.map(|it| it.container(db)) {
+        Some(AssocItemContainer::Trait(it)) => res.push(SmolStr::new(it.name(db).to_string())),
+        Some(AssocItemContainer::ImplDef(it)) => {
+            res.push(SmolStr::new(it.target_ty(db).as_adt()?.name(db).to_string()))
+        }
+        None => {}
+    }
+    if let ModuleDef::EnumVariant(it) = def {
+        res.push(SmolStr::new(it.parent_enum(db).name(db).to_string()));
+    }
+    if let Some(module) = def.module(db) {
+        for module in module.path_to_root(db) {
+            if let Some(name) = module.name(db) {
+                res.push(SmolStr::new(name.to_string()));
+            }
+        }
+    }
+    res.reverse();
+    Some(res)
+}
+
+/// Checks whether `segments` is the path of `def`, either from the root of its crate or starting
+/// with the name of the crate.
+fn def_has_path(db: &dyn HirDatabase, def: ModuleDef, segments: &[SmolStr]) -> bool {
+    let path = match def_path(db, def) {
+        Some(it) => it,
+        None => return false,
+    };
+    if path == segments {
+        return true;
+    }
+    let crate_name = def.module(db).and_then(|it| it.krate().display_name(db));
+    match (crate_name, segments.split_first()) {
+        (Some(crate_name), Some((first, rest))) => crate_name == first.as_str() && path == rest,
+        _ => false,
+    }
+}
+
+fn arguments(call: &impl ArgListOwner) -> Vec<ast::Expr> {
+    call.arg_list().map(|it| it.args().collect()).unwrap_or_default()
+}
+
+/// Returns the path that `node` consists of, if any.
+fn match_ast_path(node: &SyntaxNode) -> Option<ast::Path> {
+    match_ast! {
+        match node {
+            ast::Path(it) => Some(it),
+            ast::PathExpr(it) => it.path(),
+            ast::PathType(it) => it.path(),
+            ast::PathPat(it) => it.path(),
+            _ => None,
+        }
+    }
+}
+
 // If `node` contains nothing but an ident then return it, otherwise return None.
 fn only_ident(element: SyntaxElement) -> Option<SyntaxToken> {
     match element {
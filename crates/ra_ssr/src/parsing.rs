@@ -40,17 +40,25 @@ pub(crate) struct Placeholder {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) enum Constraint {
     Kind(NodeKind),
+    /// The placeholder must be a path that resolves to the item with this fully qualified path.
+    Path(Vec<SmolStr>),
+    /// The placeholder must have come from a macro expansion.
+    InMacro,
     Not(Box<Constraint>),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) enum NodeKind {
     Literal,
+    Expr,
+    Ty,
+    Pat,
+    Path,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct Token {
-    kind: SyntaxKind,
+    pub(crate) kind: SyntaxKind,
     pub(crate) text: SmolStr,
 }
 
@@ -273,6 +281,28 @@ fn parse_constraint(tokens: &mut std::vec::IntoIter<Token>) -> Result<Constraint
             expect_token(tokens, ")")?;
             Ok(Constraint::Not(Box::new(sub)))
         }
+        "path" => {
+            expect_token(tokens, "(")?;
+            let mut segments = Vec::new();
+            loop {
+                let t = tokens.next().ok_or_else(|| {
+                    SsrError::new("Unexpected end of constraint while looking for path")
+                })?;
+                match t.kind {
+                    SyntaxKind::IDENT => segments.push(t.text),
+                    // The tokenizer produces `::` as two separate colons. A leading `crate` is
+                    // implied, since paths are always compared from the crate root.
+                    T![:] | T![crate] => {}
+                    T![')'] => break,
+                    _ => bail!("Unexpected token '{}' while parsing path constraint", t.text),
+                }
+            }
+            if segments.is_empty() {
+                bail!("Path constraint requires a path");
+            }
+            Ok(Constraint::Path(segments))
+        }
+        "in_macro" => Ok(Constraint::InMacro),
         x => bail!("Unsupported constraint type '{}'", x),
     }
 }
@@ -291,6 +321,10 @@ impl NodeKind {
     fn from(name: &SmolStr) -> Result<NodeKind, SsrError> {
         Ok(match name.as_str() {
             "literal" => NodeKind::Literal,
+            "expr" => NodeKind::Expr,
+            "ty" => NodeKind::Ty,
+            "pat" => NodeKind::Pat,
+            "path" => NodeKind::Path,
             _ => bail!("Unknown node kind '{}'", name),
         })
     }
@@ -3,8 +3,9 @@
 use crate::matching::Var;
 use crate::parsing::PatternElement;
 use crate::{Match, SsrMatches};
+use hir::Access;
 use ra_syntax::ast::AstToken;
-use ra_syntax::TextSize;
+use ra_syntax::{SyntaxKind, TextSize, T};
 use ra_text_edit::TextEdit;
 
 /// Returns a text edit that will replace each match in `matches` with its corresponding replacement
@@ -31,7 +32,8 @@ fn matches_to_edit_at_offset(
 
 fn render_replace(match_info: &Match, file_src: &str) -> String {
     let mut out = String::new();
-    for r in &match_info.template.tokens {
+    let tokens = &match_info.template.tokens;
+    for (index, r) in tokens.iter().enumerate() {
         match r {
             PatternElement::Token(t) => out.push_str(t.text.as_str()),
             PatternElement::Placeholder(p) => {
@@ -47,6 +49,17 @@ fn render_replace(match_info: &Match, file_src: &str) -> String {
                         range.start(),
                     );
                     edit.apply(&mut matched_text);
+                    // Calling a method on the placeholder applies the same implicit adjustments
+                    // that were applied where it matched, otherwise we need to spell them out.
+                    if !is_method_call_receiver(&tokens[index + 1..]) {
+                        let autoref = match placeholder_value.autoref_kind {
+                            Access::Owned => "",
+                            Access::Shared => "&",
+                            Access::Exclusive => "&mut ",
+                        };
+                        let derefs = "*".repeat(placeholder_value.autoderef_count);
+                        matched_text = format!("{}{}{}", autoref, derefs, matched_text);
+                    }
                     out.push_str(&matched_text);
                 } else {
                     // We validated that all placeholder references were valid before we
@@ -64,3 +77,16 @@ fn render_replace(match_info: &Match, file_src: &str) -> String {
     }
     out
 }
+
+/// Returns whether a placeholder followed by `following` is the receiver of a method call or field
+/// access.
+fn is_method_call_receiver(following: &[PatternElement]) -> bool {
+    following
+        .iter()
+        .find_map(|it| match it {
+            PatternElement::Token(t) if t.kind == SyntaxKind::WHITESPACE => None,
+            PatternElement::Token(t) => Some(t.kind == T![.]),
+            PatternElement::Placeholder(_) => Some(false),
+        })
+        .unwrap_or(false)
+}
@@ -580,3 +580,139 @@ fn match_failure_reasons() {
         r#"Pattern wanted token '42' (INT_NUMBER), but code had token '43' (INT_NUMBER)"#,
     );
 }
+
+#[test]
+fn kind_constraints() {
+    let code = r#"
+        mod m { pub fn x() {} }
+        fn foo() {}
+        fn f1() {
+            foo(1);
+            foo(x);
+            foo(m::x);
+            foo(1 + 2);
+        }
+        "#;
+    assert_matches("foo(${a:kind(path)})", code, &["foo(x)", "foo(m::x)"]);
+    assert_matches("foo(${a:not(kind(path))})", code, &["foo(1)", "foo(1 + 2)"]);
+    assert_matches("${a:kind(ty)}", "struct Foo {} fn f() -> Foo {}", &["Foo"]);
+}
+
+#[test]
+fn path_constraint() {
+    let code = r#"
+        mod m1 { pub fn bar(x: i32) {} }
+        mod m2 { pub fn bar(x: i32) {} }
+        use m1::bar;
+        fn f1() {
+            bar(1);
+            m1::bar(2);
+            m2::bar(3);
+        }
+        "#;
+    assert_matches("${f:path(m1::bar)}($a)", code, &["bar(1)", "m1::bar(2)"]);
+    assert_matches("${f:path(crate::m2::bar)}($a)", code, &["m2::bar(3)"]);
+}
+
+#[test]
+fn in_macro_constraint() {
+    let code = r#"
+        macro_rules! m1 { ($a:expr) => {$a} }
+        fn foo() {}
+        fn f1() {
+            foo(1);
+            m1!(foo(2));
+        }
+        "#;
+    assert_matches("foo(${a:in_macro})", code, &["foo(2)"]);
+    assert_matches("foo(${a:not(in_macro)})", code, &["foo(1)"]);
+}
+
+#[test]
+fn parser_path_constraint_without_path() {
+    assert_eq!(
+        parse_error_text("${a:path()} ==>> ()"),
+        "Parse error: Path constraint requires a path"
+    );
+}
+
+#[test]
+fn match_resolved_path() {
+    let code = r#"
+        mod m1 { pub fn bar() {} }
+        mod m2 { pub fn bar() {} }
+        use m1::bar;
+        fn f1() {
+            bar();
+            m2::bar();
+        }
+        "#;
+    assert_matches("m1::bar()", code, &["bar()"]);
+    assert_no_match("m1::bar()", "mod m2 { pub fn bar() {} } use m2::bar; fn f1() { bar(); }");
+}
+
+#[test]
+fn ufcs_matches_method_call() {
+    let code = r#"
+        struct Foo {}
+        impl Foo { fn bar(&self, x: i32) {} }
+        struct Bar {}
+        impl Bar { fn bar(&self, x: i32) {} }
+        fn f1(foo: Foo, r: &Foo, rr: &&Foo, b: Bar) {
+            foo.bar(1);
+            r.bar(2);
+            rr.bar(3);
+            b.bar(4);
+        }
+        "#;
+    assert_matches("Foo::bar($s, $a)", code, &["foo.bar(1)", "r.bar(2)", "rr.bar(3)"]);
+    assert_ssr_transform(
+        "Foo::bar($s, $a) ==>> Foo::baz($s, $a)",
+        code,
+        r#"
+        struct Foo {}
+        impl Foo { fn bar(&self, x: i32) {} }
+        struct Bar {}
+        impl Bar { fn bar(&self, x: i32) {} }
+        fn f1(foo: Foo, r: &Foo, rr: &&Foo, b: Bar) {
+            Foo::baz(&foo, 1);
+            Foo::baz(r, 2);
+            Foo::baz(*rr, 3);
+            b.bar(4);
+        }
+        "#,
+    );
+    // Calling a method on the placeholder in the replacement doesn't need any adjustments.
+    assert_ssr_transform(
+        "Foo::bar($s, $a) ==>> $s.baz($a)",
+        "struct Foo {} impl Foo { fn bar(&self, x: i32) {} } fn f1(foo: Foo) { foo.bar(1); }",
+        "struct Foo {} impl Foo { fn bar(&self, x: i32) {} } fn f1(foo: Foo) { foo.baz(1); }",
+    );
+}
+
+#[test]
+fn method_call_matches_ufcs() {
+    let code = r#"
+        struct Foo {}
+        impl Foo {
+            fn bar(&self, x: i32) {}
+            fn new(x: i32) -> Foo { Foo {} }
+        }
+        fn f1(foo: Foo) {
+            Foo::bar(&foo, 1);
+            Foo::new(2);
+        }
+        "#;
+    assert_matches("$s.bar($a)", code, &["Foo::bar(&foo, 1)"]);
+    assert_no_match("$s.new()", code);
+    assert_ssr_transform(
+        "$s.bar($a) ==>> $s.baz($a)",
+        "struct Foo {} impl Foo { fn bar(&self, x: i32) {} } fn f1(foo: Foo) { Foo::bar(&foo, 1); }",
+        "struct Foo {} impl Foo { fn bar(&self, x: i32) {} } fn f1(foo: Foo) { foo.baz(1); }",
+    );
+    assert_ssr_transform(
+        "$s.bar($a) ==>> baz($s, $a)",
+        "struct Foo {} impl Foo { fn bar(&self, x: i32) {} } fn f1(foo: Foo) { Foo::bar(&foo, 1); }",
+        "struct Foo {} impl Foo { fn bar(&self, x: i32) {} } fn f1(foo: Foo) { baz(&foo, 1); }",
+    );
+}
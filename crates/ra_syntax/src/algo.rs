@@ -106,62 +106,149 @@ pub enum InsertPosition<T> {
     After(T),
 }
 
+#[derive(Debug, Default)]
 pub struct TreeDiff {
     replacements: FxHashMap<SyntaxElement, SyntaxElement>,
+    deletions: Vec<TextRange>,
+    // Elements inserted at the same offset are kept in order.
+    insertions: FxHashMap<TextSize, Vec<SyntaxElement>>,
 }
 
 impl TreeDiff {
     pub fn into_text_edit(&self, builder: &mut TextEditBuilder) {
+        for (offset, to) in self.insertions.iter() {
+            builder.insert(*offset, to.iter().map(|it| it.to_string()).collect())
+        }
+        for range in self.deletions.iter() {
+            builder.delete(*range)
+        }
         for (from, to) in self.replacements.iter() {
             builder.replace(from.text_range(), to.to_string())
         }
     }
 
     pub fn is_empty(&self) -> bool {
-        self.replacements.is_empty()
+        self.replacements.is_empty() && self.deletions.is_empty() && self.insertions.is_empty()
     }
 }
 
 /// Finds minimal the diff, which, applied to `from`, will result in `to`.
 ///
-/// Specifically, returns a map whose keys are descendants of `from` and values
-/// are descendants of `to`, such that  `replace_descendants(from, map) == to`.
+/// Specifically, returns replacements of descendants of `from` by descendants
+/// of `to`, as well as the descendants of `from` to delete and the descendants
+/// of `to` to insert, such that applying them to `from` results in `to`.
 ///
-/// A trivial solution is a singleton map `{ from: to }`, but this function
-/// tries to find a more fine-grained diff.
+/// A trivial solution is a single replacement of `from` by `to`, but this
+/// function tries to find a more fine-grained diff: the children of each pair
+/// of nodes are aligned with the longest common subsequence of equal children,
+/// and the unaligned runs of children are diffed recursively.
 pub fn diff(from: &SyntaxNode, to: &SyntaxNode) -> TreeDiff {
-    let mut buf = FxHashMap::default();
-    // FIXME: this is both horrible inefficient and gives larger than
-    // necessary diff. I bet there's a cool algorithm to diff trees properly.
-    go(&mut buf, from.clone().into(), to.clone().into());
-    return TreeDiff { replacements: buf };
-
-    fn go(
-        buf: &mut FxHashMap<SyntaxElement, SyntaxElement>,
-        lhs: SyntaxElement,
-        rhs: SyntaxElement,
+    let mut diff = TreeDiff::default();
+    go(&mut diff, from.clone().into(), to.clone().into());
+    return diff;
+
+    fn go(diff: &mut TreeDiff, lhs: SyntaxElement, rhs: SyntaxElement) {
+        if is_equal(&lhs, &rhs) {
+            return;
+        }
+        if let (Some(lhs), Some(rhs)) = (lhs.as_node(), rhs.as_node()) {
+            let lhs_children = lhs.children_with_tokens().collect::<Vec<_>>();
+            let rhs_children = rhs.children_with_tokens().collect::<Vec<_>>();
+            go_children(diff, lhs.text_range().start(), &lhs_children, &rhs_children);
+            return;
+        }
+        diff.replacements.insert(lhs, rhs);
+    }
+
+    fn go_children(
+        diff: &mut TreeDiff,
+        start: TextSize,
+        lhs: &[SyntaxElement],
+        rhs: &[SyntaxElement],
     ) {
-        if lhs.kind() == rhs.kind()
+        let mut offset = start;
+        let (mut lhs_pos, mut rhs_pos) = (0, 0);
+        for (lhs_matched, rhs_matched) in
+            common_subsequence(lhs, rhs).into_iter().chain(Some((lhs.len(), rhs.len())))
+        {
+            let lhs_run = &lhs[lhs_pos..lhs_matched];
+            let rhs_run = &rhs[rhs_pos..rhs_matched];
+            if lhs_run.len() == rhs_run.len() {
+                for (lhs, rhs) in lhs_run.iter().zip(rhs_run) {
+                    go(diff, lhs.clone(), rhs.clone());
+                }
+            } else {
+                if let (Some(first), Some(last)) = (lhs_run.first(), lhs_run.last()) {
+                    let range = first.text_range().cover(last.text_range());
+                    diff.deletions.push(range);
+                }
+                if !rhs_run.is_empty() {
+                    diff.insertions.entry(offset).or_default().extend(rhs_run.iter().cloned());
+                }
+            }
+            if let Some(matched) = lhs.get(lhs_matched) {
+                offset = matched.text_range().end();
+            }
+            lhs_pos = lhs_matched + 1;
+            rhs_pos = rhs_matched + 1;
+        }
+    }
+
+    /// Returns the indices of the pairs of equal elements of the longest
+    /// common subsequence of `lhs` and `rhs`.
+    fn common_subsequence(lhs: &[SyntaxElement], rhs: &[SyntaxElement]) -> Vec<(usize, usize)> {
+        // Edits are usually local, so we match common prefix and suffix first
+        // to avoid the quadratic search on long lists of children.
+        let prefix = lhs.iter().zip(rhs).take_while(|(lhs, rhs)| is_equal(lhs, rhs)).count();
+        let suffix = lhs[prefix..]
+            .iter()
+            .rev()
+            .zip(rhs[prefix..].iter().rev())
+            .take_while(|(lhs, rhs)| is_equal(lhs, rhs))
+            .count();
+        let (lhs_mid, rhs_mid) =
+            (&lhs[prefix..lhs.len() - suffix], &rhs[prefix..rhs.len() - suffix]);
+
+        // `lengths[i][j]` is the length of the common subsequence of
+        // `lhs_mid[i..]` and `rhs_mid[j..]`.
+        let mut lengths = vec![vec![0; rhs_mid.len() + 1]; lhs_mid.len() + 1];
+        for i in (0..lhs_mid.len()).rev() {
+            for j in (0..rhs_mid.len()).rev() {
+                lengths[i][j] = if is_equal(&lhs_mid[i], &rhs_mid[j]) {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+
+        let mut res = (0..prefix).map(|i| (i, i)).collect::<Vec<_>>();
+        let (mut i, mut j) = (0, 0);
+        while i < lhs_mid.len() && j < rhs_mid.len() {
+            if is_equal(&lhs_mid[i], &rhs_mid[j]) {
+                res.push((prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        res.extend((0..suffix).map(|k| (lhs.len() - suffix + k, rhs.len() - suffix + k)));
+        res
+    }
+
+    fn is_equal(lhs: &SyntaxElement, rhs: &SyntaxElement) -> bool {
+        lhs.kind() == rhs.kind()
             && lhs.text_range().len() == rhs.text_range().len()
-            && match (&lhs, &rhs) {
+            && match (lhs, rhs) {
                 (NodeOrToken::Node(lhs), NodeOrToken::Node(rhs)) => {
                     lhs.green() == rhs.green() || lhs.text() == rhs.text()
                 }
                 (NodeOrToken::Token(lhs), NodeOrToken::Token(rhs)) => lhs.text() == rhs.text(),
                 _ => false,
             }
-        {
-            return;
-        }
-        if let (Some(lhs), Some(rhs)) = (lhs.as_node(), rhs.as_node()) {
-            if lhs.children_with_tokens().count() == rhs.children_with_tokens().count() {
-                for (lhs, rhs) in lhs.children_with_tokens().zip(rhs.children_with_tokens()) {
-                    go(buf, lhs, rhs)
-                }
-                return;
-            }
-        }
-        buf.insert(lhs, rhs);
     }
 }
 
@@ -243,22 +330,38 @@ fn _replace_children(
     with_children(parent, new_children)
 }
 
+/// A mutable view of a syntax tree.
+///
+/// The rewriter records edits of the elements of the original tree: deletions,
+/// replacements, and insertions of new elements next to them. `rewrite` then
+/// builds the edited copy of the tree, which can be diffed against the
+/// original with [`diff`] to produce a minimal text edit.
+///
+/// Edits inside a replaced or deleted subtree are ignored.
 #[derive(Default)]
 pub struct SyntaxRewriter<'a> {
     f: Option<Box<dyn Fn(&SyntaxElement) -> Option<SyntaxElement> + 'a>>,
     //FIXME: add debug_assertions that all elements are in fact from the same file.
     replacements: FxHashMap<SyntaxElement, Replacement>,
+    insertions: FxHashMap<InsertPos, Vec<SyntaxElement>>,
 }
 
 impl fmt::Debug for SyntaxRewriter<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("SyntaxRewriter").field("replacements", &self.replacements).finish()
+        f.debug_struct("SyntaxRewriter")
+            .field("replacements", &self.replacements)
+            .field("insertions", &self.insertions)
+            .finish()
     }
 }
 
 impl<'a> SyntaxRewriter<'a> {
     pub fn from_fn(f: impl Fn(&SyntaxElement) -> Option<SyntaxElement> + 'a) -> SyntaxRewriter<'a> {
-        SyntaxRewriter { f: Some(Box::new(f)), replacements: FxHashMap::default() }
+        SyntaxRewriter {
+            f: Some(Box::new(f)),
+            replacements: FxHashMap::default(),
+            insertions: FxHashMap::default(),
+        }
     }
     pub fn delete<T: Clone + Into<SyntaxElement>>(&mut self, what: &T) {
         let what = what.clone().into();
@@ -282,9 +385,72 @@ impl<'a> SyntaxRewriter<'a> {
     pub fn replace_ast<T: AstNode>(&mut self, what: &T, with: &T) {
         self.replace(what.syntax(), with.syntax())
     }
+    pub fn insert_before<T: Clone + Into<SyntaxElement>, U: Clone + Into<SyntaxElement>>(
+        &mut self,
+        before: &T,
+        what: &U,
+    ) {
+        self.insert_many_before(before, vec![what.clone().into()])
+    }
+    pub fn insert_many_before<T: Clone + Into<SyntaxElement>>(
+        &mut self,
+        before: &T,
+        what: Vec<SyntaxElement>,
+    ) {
+        let before = before.clone().into();
+        let pos = match before.prev_sibling_or_token() {
+            Some(sibling) => InsertPos::After(sibling),
+            None => match before.parent() {
+                Some(parent) => InsertPos::FirstChildOf(parent),
+                None => panic!("cannot insert before the root node"),
+            },
+        };
+        self.insert_many(pos, what)
+    }
+    pub fn insert_after<T: Clone + Into<SyntaxElement>, U: Clone + Into<SyntaxElement>>(
+        &mut self,
+        after: &T,
+        what: &U,
+    ) {
+        self.insert_many_after(after, vec![what.clone().into()])
+    }
+    pub fn insert_many_after<T: Clone + Into<SyntaxElement>>(
+        &mut self,
+        after: &T,
+        what: Vec<SyntaxElement>,
+    ) {
+        self.insert_many(InsertPos::After(after.clone().into()), what)
+    }
+    pub fn insert_as_first_child<T: Clone + Into<SyntaxElement>>(
+        &mut self,
+        parent: &SyntaxNode,
+        what: &T,
+    ) {
+        self.insert_many_as_first_child(parent, vec![what.clone().into()])
+    }
+    pub fn insert_many_as_first_child(&mut self, parent: &SyntaxNode, what: Vec<SyntaxElement>) {
+        self.insert_many(InsertPos::FirstChildOf(parent.clone()), what)
+    }
+    pub fn insert_as_last_child<T: Clone + Into<SyntaxElement>>(
+        &mut self,
+        parent: &SyntaxNode,
+        what: &T,
+    ) {
+        self.insert_many_as_last_child(parent, vec![what.clone().into()])
+    }
+    pub fn insert_many_as_last_child(&mut self, parent: &SyntaxNode, what: Vec<SyntaxElement>) {
+        let pos = match parent.last_child_or_token() {
+            Some(it) => InsertPos::After(it),
+            None => InsertPos::FirstChildOf(parent.clone()),
+        };
+        self.insert_many(pos, what)
+    }
+    fn insert_many(&mut self, pos: InsertPos, what: Vec<SyntaxElement>) {
+        self.insertions.entry(pos).or_default().extend(what)
+    }
 
     pub fn rewrite(&self, node: &SyntaxNode) -> SyntaxNode {
-        if self.f.is_none() && self.replacements.is_empty() {
+        if self.f.is_none() && self.replacements.is_empty() && self.insertions.is_empty() {
             return node.clone();
         }
         self.rewrite_children(node)
@@ -310,6 +476,10 @@ impl<'a> SyntaxRewriter<'a> {
             // If we only have one replacement, we must return its parent node, since `rewrite` does
             // not replace the node passed to it.
             .map(|it| it.parent().unwrap_or(it))
+            .chain(self.insertions.keys().map(|pos| match pos {
+                InsertPos::FirstChildOf(it) => it.clone(),
+                InsertPos::After(it) => it.parent().unwrap(),
+            }))
             .fold1(|a, b| least_common_ancestor(&a, &b).unwrap())
     }
 
@@ -321,11 +491,20 @@ impl<'a> SyntaxRewriter<'a> {
         self.replacements.get(element).cloned()
     }
 
+    fn insertions(
+        &self,
+        pos: &InsertPos,
+    ) -> impl Iterator<Item = NodeOrToken<rowan::GreenNode, rowan::GreenToken>> + '_ {
+        self.insertions.get(pos).into_iter().flatten().cloned().map(to_green_element)
+    }
+
     fn rewrite_children(&self, node: &SyntaxNode) -> SyntaxNode {
         //  FIXME: this could be made much faster.
         let mut new_children = Vec::new();
+        new_children.extend(self.insertions(&InsertPos::FirstChildOf(node.clone())));
         for child in node.children_with_tokens() {
             self.rewrite_self(&mut new_children, &child);
+            new_children.extend(self.insertions(&InsertPos::After(child)));
         }
         with_children(node, new_children)
     }
@@ -364,7 +543,10 @@ impl<'a> SyntaxRewriter<'a> {
 impl ops::AddAssign for SyntaxRewriter<'_> {
     fn add_assign(&mut self, rhs: SyntaxRewriter) {
         assert!(rhs.f.is_none());
-        self.replacements.extend(rhs.replacements)
+        self.replacements.extend(rhs.replacements);
+        for (pos, what) in rhs.insertions {
+            self.insert_many(pos, what);
+        }
     }
 }
 
@@ -375,6 +557,12 @@ enum Replacement {
     Many(Vec<SyntaxElement>),
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum InsertPos {
+    FirstChildOf(SyntaxNode),
+    After(SyntaxElement),
+}
+
 fn with_children(
     parent: &SyntaxNode,
     new_children: Vec<NodeOrToken<rowan::GreenNode, rowan::GreenToken>>,
@@ -404,3 +592,94 @@ fn to_green_element(element: SyntaxElement) -> NodeOrToken<rowan::GreenNode, row
         NodeOrToken::Token(it) => it.green().clone().into(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use expect::{expect, Expect};
+    use ra_text_edit::TextEditBuilder;
+
+    use super::*;
+    use crate::{ast, SourceFile};
+
+    fn check_diff(from: &str, to: &str, expect: Expect) {
+        let from = SourceFile::parse(from).tree();
+        let to = SourceFile::parse(to).tree();
+        let diff = diff(from.syntax(), to.syntax());
+        let mut builder = TextEditBuilder::default();
+        diff.into_text_edit(&mut builder);
+        let edit = builder.finish();
+
+        let mut actual = String::new();
+        for indel in edit.iter() {
+            actual += &format!("{:?} -> {:?}\n", indel.delete, indel.insert);
+        }
+        let mut text = from.syntax().to_string();
+        edit.apply(&mut text);
+        assert_eq!(text, to.syntax().to_string());
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn diff_replaces_changed_token() {
+        check_diff(
+            "fn foo() { bar(1, 2); }",
+            "fn foo() { bar(1, 92); }",
+            expect![[r#"
+                18..19 -> "92"
+            "#]],
+        );
+    }
+
+    #[test]
+    fn diff_inserts_and_deletes_children() {
+        check_diff(
+            "fn foo() { a; b; c; }",
+            "fn foo() { a; c; d; }",
+            expect![[r#"
+                14..17 -> ""
+                19..19 -> " d;"
+            "#]],
+        );
+        check_diff(
+            "fn foo<T: Copy>(t: T) {}",
+            "fn foo<T>(t: T) where T: Copy {}",
+            expect![[r#"
+                8..14 -> ""
+                21..21 -> " where T: Copy"
+            "#]],
+        );
+    }
+
+    #[test]
+    fn rewriter_inserts_elements() {
+        let file = SourceFile::parse("fn foo() { a; c; }").tree();
+        let stmts = file.syntax().descendants().filter_map(ast::ExprStmt::cast).collect::<Vec<_>>();
+        let new_stmt = SourceFile::parse("fn f() { b; }")
+            .tree()
+            .syntax()
+            .descendants()
+            .find_map(ast::ExprStmt::cast)
+            .unwrap();
+        let block = file.syntax().descendants().find_map(ast::BlockExpr::cast).unwrap();
+        let space = crate::ast::make::tokens::single_space();
+
+        let mut rewriter = SyntaxRewriter::default();
+        rewriter.insert_many_after(
+            stmts[0].syntax(),
+            vec![space.clone().into(), new_stmt.syntax().clone().into()],
+        );
+        rewriter.delete(stmts[1].syntax());
+        rewriter.insert_before(block.syntax(), &space);
+        let comment =
+            SourceFile::parse("// hello\n").tree().syntax().first_child_or_token().unwrap();
+        rewriter.insert_many_as_first_child(
+            file.syntax(),
+            vec![comment, crate::ast::make::tokens::single_newline().into()],
+        );
+
+        let root = rewriter.rewrite_root().unwrap();
+        assert_eq!(root, *file.syntax());
+        let new = rewriter.rewrite(&root);
+        assert_eq!(new.to_string(), "// hello\nfn foo()  { a; b;  }");
+    }
+}
@@ -587,6 +587,79 @@ pub trait AstNodeEdit: AstNode + Clone + Sized {
         let level = IndentLevel::from_node(self.syntax());
         self.dedent(level)
     }
+
+    /// Returns a rewriter which replaces `self` with `new`, reindented to the
+    /// level of `self`.
+    ///
+    /// The parser attaches the comments preceding an item to the item itself,
+    /// so these (and the trailing comments of `self`) are kept unless `new`
+    /// already contains them.
+    fn replace_keeping_comments(&self, new: &Self) -> SyntaxRewriter<'static> {
+        let new = new.reset_indent().indent(IndentLevel::from_node(self.syntax()));
+        let new_comments = new
+            .syntax()
+            .descendants_with_tokens()
+            .filter_map(|it| it.into_token())
+            .filter(|it| it.kind() == COMMENT)
+            .map(|it| it.text().clone())
+            .collect::<Vec<_>>();
+        let comments_to_keep = |trivia: Vec<SyntaxElement>| {
+            let is_kept = trivia.iter().any(|it| it.kind() == COMMENT)
+                && !trivia
+                    .iter()
+                    .filter_map(|it| it.as_token())
+                    .any(|it| it.kind() == COMMENT && new_comments.contains(it.text()));
+            if is_kept {
+                trivia
+            } else {
+                Vec::new()
+            }
+        };
+
+        let children = self.syntax().children_with_tokens();
+        let leading = comments_to_keep(children.take_while(|it| it.kind().is_trivia()).collect());
+        let mut trailing = comments_to_keep(
+            self.syntax()
+                .children_with_tokens()
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .take_while(|it| it.kind().is_trivia())
+                .collect(),
+        );
+        trailing.reverse();
+
+        let mut res = SyntaxRewriter::default();
+        let mut with = leading;
+        with.push(new.syntax().clone().into());
+        with.extend(trailing);
+        res.replace_with_many(self.syntax(), with);
+        res
+    }
+
+    /// Returns a rewriter which deletes `self` together with the whitespace
+    /// around it, so that neither an empty line nor a double space is left
+    /// behind. A comment following `self` on the same line stays in place.
+    fn remove_keeping_comments(&self) -> SyntaxRewriter<'static> {
+        let node = self.syntax();
+        let mut res = SyntaxRewriter::default();
+        res.delete(node);
+        let prev_ws = node
+            .prev_sibling_or_token()
+            .and_then(|it| it.into_token())
+            .filter(|it| it.kind() == WHITESPACE);
+        let next_ws = node
+            .next_sibling_or_token()
+            .and_then(|it| it.into_token())
+            .filter(|it| it.kind() == WHITESPACE);
+        match (prev_ws, next_ws) {
+            (_, Some(next)) if !next.text().contains('\n') => res.delete(&next),
+            (Some(prev), _) => res.delete(&prev),
+            (None, Some(next)) => res.delete(&next),
+            (None, None) => (),
+        }
+        res
+    }
 }
 
 impl<N: AstNode + Clone> AstNodeEdit for N {}
@@ -618,3 +691,52 @@ fn test_increase_indent() {
         }"
     );
 }
+
+#[test]
+fn test_replace_keeping_comments() {
+    let file = crate::SourceFile::parse(
+        "
+mod m {
+    // Explains `foo`.
+    fn foo() {}
+}",
+    )
+    .tree();
+    let fn_def = file.syntax().descendants().find_map(ast::FnDef::cast).unwrap();
+    let new_fn = make::fn_def(
+        None,
+        make::name("foo"),
+        None,
+        make::param_list(None),
+        make::block_expr(None, Some(make::expr_unit())),
+    );
+    let new = fn_def.replace_keeping_comments(&new_fn).rewrite(file.syntax());
+    assert_eq!(
+        new.to_string(),
+        "
+mod m {
+    // Explains `foo`.
+    fn foo() {
+        ()
+    }
+}"
+    );
+}
+
+#[test]
+fn test_remove_keeping_comments() {
+    let check = |before: &str, after: &str| {
+        let file = crate::SourceFile::parse(before).tree();
+        let stmt = file
+            .syntax()
+            .descendants()
+            .filter_map(ast::ExprStmt::cast)
+            .find(|it| it.syntax().text() == "b;")
+            .unwrap();
+        assert_eq!(stmt.remove_keeping_comments().rewrite(file.syntax()).to_string(), after);
+    };
+    check("fn f() {\n    a;\n    b;\n    c;\n}", "fn f() {\n    a;\n    c;\n}");
+    check("fn f() {\n    a;\n    b; // why\n    c;\n}", "fn f() {\n    a;\n    // why\n    c;\n}");
+    check("fn f() { a; b; c; }", "fn f() { a; c; }");
+    check("fn f() { a; b;\n}", "fn f() { a;\n}");
+}
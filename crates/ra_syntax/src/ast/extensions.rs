@@ -440,20 +440,36 @@ impl ast::LifetimeParam {
 }
 
 impl ast::RangePat {
+    fn op_details(&self) -> Option<(usize, ast::RangeOp)> {
+        self.syntax().children_with_tokens().enumerate().find_map(|(ix, child)| {
+            let op = match child.into_token()?.kind() {
+                T![..] => ast::RangeOp::Exclusive,
+                T![..=] | T![...] => ast::RangeOp::Inclusive,
+                _ => return None,
+            };
+            Some((ix, op))
+        })
+    }
+
+    pub fn op_kind(&self) -> Option<ast::RangeOp> {
+        self.op_details().map(|it| it.1)
+    }
+
     pub fn start(&self) -> Option<ast::Pat> {
+        let op_ix = self.op_details()?.0;
         self.syntax()
             .children_with_tokens()
-            .take_while(|it| !(it.kind() == T![..] || it.kind() == T![..=]))
-            .filter_map(|it| it.into_node())
-            .find_map(ast::Pat::cast)
+            .take(op_ix)
+            .find_map(|it| ast::Pat::cast(it.into_node()?))
     }
 
+    /// The end of the range, which is absent for half-open patterns like `1..`.
     pub fn end(&self) -> Option<ast::Pat> {
+        let op_ix = self.op_details()?.0;
         self.syntax()
             .children_with_tokens()
-            .skip_while(|it| !(it.kind() == T![..] || it.kind() == T![..=]))
-            .filter_map(|it| it.into_node())
-            .find_map(ast::Pat::cast)
+            .skip(op_ix + 1)
+            .find_map(|it| ast::Pat::cast(it.into_node()?))
     }
 }
 
@@ -1955,7 +1955,7 @@ impl TypeParam {
 }
 /// Const generic parameter **declaration**.
 /// ```
-/// fn foo<T, U, ❰ const BAR: usize ❱, ❰ const BAZ: bool ❱>() {}
+/// fn foo<T, U, ❰ const BAR: usize ❱, ❰ const BAZ: bool = true ❱>() {}
 /// ```
 ///
 /// [RFC](https://github.com/rust-lang/rfcs/blob/master/text/2000-const-generics.md#declaring-a-const-parameter)
@@ -2475,7 +2475,7 @@ impl LifetimeArg {
 /// ```
 /// foo::<u32, ❰ { true } ❱>();
 ///
-/// bar::<❰ { 2 + 2} ❱>();
+/// bar::<❰ { 2 + 2} ❱, ❰ -1 ❱>();
 /// ```
 ///
 /// [RFC](https://github.com/rust-lang/rfcs/blob/master/text/2000-const-generics.md#declaring-a-const-parameter)
@@ -2484,8 +2484,7 @@ pub struct ConstArg {
     pub(crate) syntax: SyntaxNode,
 }
 impl ConstArg {
-    pub fn literal(&self) -> Option<Literal> { support::child(&self.syntax) }
-    pub fn block_expr(&self) -> Option<BlockExpr> { support::child(&self.syntax) }
+    pub fn expr(&self) -> Option<Expr> { support::child(&self.syntax) }
 }
 /// FIXME: (@edwin0cheng) Remove it to use ItemList instead
 /// https://github.com/rust-analyzer/rust-analyzer/pull/4083#discussion_r422666243
@@ -197,6 +197,8 @@ pub enum FormatSpecifier {
     Dot,
     Asterisk,
     QuestionMark,
+    /// `{{` or `}}`, which stand for a literal brace.
+    Escape,
 }
 
 pub trait HasFormatSpecifier: AstToken {
@@ -221,7 +223,8 @@ pub trait HasFormatSpecifier: AstToken {
                     // Format specifier, see syntax at https://doc.rust-lang.org/std/fmt/index.html#syntax
                     if let Some((_, Ok('{'))) = chars.peek() {
                         // Escaped format specifier, `{{`
-                        chars.next();
+                        let (next, _) = chars.next().unwrap();
+                        callback(range.cover(*next), FormatSpecifier::Escape);
                         continue;
                     }
 
@@ -437,10 +440,15 @@ pub trait HasFormatSpecifier: AstToken {
                         continue;
                     }
                 }
+                Ok('}') if matches!(chars.peek(), Some((_, Ok('}')))) => {
+                    // Escaped closing brace, `}}`
+                    let (next, _) = chars.next().unwrap();
+                    callback(range.cover(*next), FormatSpecifier::Escape);
+                }
                 _ => {
                     while let Some((_, Ok(next_char))) = chars.peek() {
                         match next_char {
-                            '{' => break,
+                            '{' | '}' => break,
                             _ => {}
                         }
                         chars.next();
@@ -40,10 +40,10 @@ pub fn tokenize(text: &str) -> (Vec<Token>, Vec<SyntaxError>) {
         None => 0,
     };
 
-    let text_without_shebang = &text[offset..];
-
-    for rustc_token in rustc_lexer::tokenize(text_without_shebang) {
-        let token_len: TextSize = rustc_token.len.try_into().unwrap();
+    while offset < text.len() {
+        let rustc_token = rustc_lexer::first_token(&text[offset..]);
+        let len = token_len(&rustc_token, &text[offset..]);
+        let token_len: TextSize = len.try_into().unwrap();
         let token_range = TextRange::at(offset.try_into().unwrap(), token_len);
 
         let (syntax_kind, err_message) =
@@ -55,7 +55,7 @@ pub fn tokenize(text: &str) -> (Vec<Token>, Vec<SyntaxError>) {
             errors.push(SyntaxError::new(err_message, token_range));
         }
 
-        offset += rustc_token.len;
+        offset += len;
     }
 
     (tokens, errors)
@@ -101,13 +101,43 @@ fn lex_first_token(text: &str) -> Option<(Token, Option<SyntaxError>)> {
     let rustc_token = rustc_lexer::first_token(text);
     let (syntax_kind, err_message) = rustc_token_kind_to_syntax_kind(&rustc_token.kind, text);
 
-    let token = Token { kind: syntax_kind, len: rustc_token.len.try_into().unwrap() };
+    let token = Token { kind: syntax_kind, len: token_len(&rustc_token, text).try_into().unwrap() };
     let optional_error = err_message
         .map(|err_message| SyntaxError::new(err_message, TextRange::up_to(TextSize::of(text))));
 
     Some((token, optional_error))
 }
 
+/// Returns the length of the token at the start of `text`.
+///
+/// An unterminated string literal extends to the end of the file, which turns
+/// all the code after it into a string while the user types it. We end such a
+/// literal at the end of its line instead, so the rest of the file still parses.
+///
+/// Superfluous `#` after the end of a raw string literal are included into the
+/// literal, validation reports them.
+fn token_len(rustc_token: &rustc_lexer::Token, text: &str) -> usize {
+    let is_unterminated_string = match rustc_token.kind {
+        rustc_lexer::TokenKind::Literal { kind, .. } => match kind {
+            LK::Str { terminated } | LK::ByteStr { terminated } => !terminated,
+            LK::RawStr { err: None, .. } | LK::RawByteStr { err: None, .. } => {
+                let extra_hashes =
+                    text[rustc_token.len..].bytes().take_while(|&b| b == b'#').count();
+                return rustc_token.len + extra_hashes;
+            }
+            LK::RawStr { err, .. } | LK::RawByteStr { err, .. } => {
+                matches!(err, Some(RawStrError::NoTerminator { .. }))
+            }
+            _ => false,
+        },
+        _ => false,
+    };
+    if !is_unterminated_string {
+        return rustc_token.len;
+    }
+    text[..rustc_token.len].find('\n').unwrap_or(rustc_token.len)
+}
+
 /// Returns `SyntaxKind` and an optional tokenize error message.
 fn rustc_token_kind_to_syntax_kind(
     rustc_token_kind: &rustc_lexer::TokenKind,
@@ -3,8 +3,9 @@
 //! We use two simple strategies for this:
 //!   - if the edit modifies only a single token (like changing an identifier's
 //!     letter), we replace only this token.
-//!   - otherwise, we search for the nearest `{}` block which contains the edit
-//!     and try to parse only this block.
+//!   - otherwise, we search for the nearest `{}` block or top-level item which
+//!     contains the edit and try to parse only this node. If the edited node
+//!     can not be reparsed in isolation, we try its reparsable ancestors.
 
 use ra_parser::Reparser;
 use ra_text_edit::Indel;
@@ -62,6 +63,15 @@ fn reparse_token<'node>(
                 return None;
             }
 
+            // A blank line detaches the comments above an item from it.
+            if new_token_kind == WHITESPACE
+                && new_text.contains("\n\n")
+                && !prev_token.text().contains("\n\n")
+                && prev_token.prev_token().map(|it| it.kind()) == Some(COMMENT)
+            {
+                return None;
+            }
+
             // Check that edited token is not a part of the bigger token.
             // E.g. if for source code `bruh"str"` the user removed `ruh`, then
             // `b` no longer remains an identifier, but becomes a part of byte string literal
@@ -90,11 +100,33 @@ fn reparse_block<'node>(
     root: &'node SyntaxNode,
     edit: &Indel,
 ) -> Option<(GreenNode, Vec<SyntaxError>, TextRange)> {
-    let (node, reparser) = find_reparsable_node(root, edit.delete)?;
-    let text = get_text_after_edit(node.clone().into(), edit);
+    reparsable_nodes(root, edit.delete).find_map(|(node, reparser)| {
+        let (green, errors) = reparse_node(&node, reparser, edit)?;
+        Some((node.replace_with(green), errors, node.text_range()))
+    })
+}
 
+fn reparse_node(
+    node: &SyntaxNode,
+    reparser: Reparser,
+    edit: &Indel,
+) -> Option<(GreenNode, Vec<SyntaxError>)> {
+    let text = get_text_after_edit(node.clone().into(), edit);
     let (tokens, new_lexer_errors) = tokenize(&text);
-    if !is_balanced(&tokens) {
+    // An unterminated literal in the node may be terminated by the text after
+    // it, so it has to be lexed in the context of the whole file.
+    if !new_lexer_errors.is_empty() {
+        return None;
+    }
+    let is_block = node.parent().map(|it| it.kind()) != Some(SOURCE_FILE);
+    if is_block {
+        if !is_balanced(&tokens) {
+            return None;
+        }
+    } else if !is_complete_item(&tokens) {
+        return None;
+    }
+    if has_unindented_item_in_braces(&text, &tokens) {
         return None;
     }
 
@@ -103,9 +135,91 @@ fn reparse_block<'node>(
     reparser.parse(&mut token_source, &mut tree_sink);
 
     let (green, mut new_parser_errors) = tree_sink.finish();
+    if green.text_len() != TextSize::of(&text) {
+        return None;
+    }
+    let green = if is_block {
+        green
+    } else {
+        // An item which turns into a different item, or several ones, may
+        // change how the text around it is parsed.
+        match green.children().collect::<Vec<_>>().as_slice() {
+            [NodeOrToken::Node(item)] if item.kind() == rowan::SyntaxKind(node.kind().into()) => {
+                (*item).clone()
+            }
+            _ => return None,
+        }
+    };
     new_parser_errors.extend(new_lexer_errors);
 
-    Some((node.replace_with(green), new_parser_errors, node.text_range()))
+    Some((green, new_parser_errors))
+}
+
+/// Checks that the text of an item is terminated by `;` or a balanced `{}`,
+/// so that the parser can't continue the item after its end.
+fn is_complete_item(tokens: &[Token]) -> bool {
+    match tokens.last().map(|it| it.kind) {
+        Some(T![;]) | Some(T!['}']) => (),
+        _ => return false,
+    }
+    let mut balance = 0usize;
+    for token in tokens {
+        match token.kind {
+            T!['{'] => balance += 1,
+            T!['}'] => {
+                balance = match balance.checked_sub(1) {
+                    Some(b) => b,
+                    None => return false,
+                }
+            }
+            _ => (),
+        }
+    }
+    balance == 0
+}
+
+/// The parser ends the blocks of a file with unclosed `{` at unindented items,
+/// so the parse of such lines inside of braces depends on the text around the
+/// reparsed node.
+fn has_unindented_item_in_braces(text: &str, tokens: &[Token]) -> bool {
+    let mut offset = 0usize;
+    let mut depth = 0usize;
+    for token in tokens {
+        let starts_line = offset > 0 && text[..offset].ends_with('\n');
+        if depth > 0 && starts_line && starts_unindented_item(&text[offset..]) {
+            return true;
+        }
+        match token.kind {
+            T!['{'] => depth += 1,
+            T!['}'] => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+        offset += usize::from(token.len);
+    }
+    false
+}
+
+fn starts_unindented_item(line: &str) -> bool {
+    const ITEM_KEYWORDS: &[&str] = &[
+        "fn",
+        "struct",
+        "enum",
+        "impl",
+        "trait",
+        "mod",
+        "use",
+        "static",
+        "type",
+        "extern",
+        "pub",
+        "const",
+        "unsafe",
+        "async",
+        "union",
+        "macro_rules",
+    ];
+    let word = line.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or_default();
+    line.starts_with('#') || ITEM_KEYWORDS.contains(&word)
 }
 
 fn get_text_after_edit(element: SyntaxElement, edit: &Indel) -> String {
@@ -123,14 +237,18 @@ fn is_contextual_kw(text: &str) -> bool {
     matches!(text, "auto" | "default" | "union")
 }
 
-fn find_reparsable_node(node: &SyntaxNode, range: TextRange) -> Option<(SyntaxNode, Reparser)> {
+/// Returns the reparsable nodes which contain `range`, innermost first.
+fn reparsable_nodes(
+    node: &SyntaxNode,
+    range: TextRange,
+) -> impl Iterator<Item = (SyntaxNode, Reparser)> {
     let node = algo::find_covering_element(node, range);
 
-    let mut ancestors = match node {
+    let ancestors = match node {
         NodeOrToken::Token(it) => it.parent().ancestors(),
         NodeOrToken::Node(it) => it.ancestors(),
     };
-    ancestors.find_map(|node| {
+    ancestors.filter_map(|node| {
         let first_child = node.first_child_or_token().map(|it| it.kind());
         let parent = node.parent().map(|it| it.kind());
         Reparser::for_node(node.kind(), first_child, parent).map(|r| (node, r))
@@ -323,6 +441,52 @@ extern {
         );
     }
 
+    #[test]
+    fn reparse_item_tests() {
+        do_check(
+            r"
+fn foo(<|><|>) -> i32 { 1 }
+fn bar() {}
+",
+            "x: i32",
+            21,
+        );
+        do_check(
+            r"
+/// Docs
+#[inline]
+fn foo(<|><|>) {}
+",
+            "x: u8",
+            30,
+        );
+        do_check(
+            r"
+fn foo() {
+    match x {<|><|>}
+}
+",
+            "} {",
+            18,
+        );
+    }
+
+    #[test]
+    fn reparse_falls_back_to_full_reparse() {
+        let check = |before: &str, replace_with: &str| {
+            let (range, before) = extract_range(before);
+            let edit = Indel::replace(range, replace_with.to_owned());
+            let before = SourceFile::parse(&before);
+            let reparsed =
+                incremental_reparse(before.tree().syntax(), &edit, before.errors.to_vec());
+            assert!(reparsed.is_none());
+        };
+        check("struct S {<|><|>}\n", "a: u32 } fn f() {");
+        check("// comment\n<|><|>fn foo() {}\n", "\n");
+        check("fn foo(<|><|>) {}\n", ") {} fn bar(");
+        check("fn foo() {}\nstruct S<|>;<|>\n", "");
+    }
+
     #[test]
     fn reparse_token_tests() {
         do_check(
@@ -2,7 +2,11 @@
 
 use ra_parser::TokenSource;
 
-use crate::{parsing::lexer::Token, SyntaxKind::EOF, TextRange, TextSize};
+use crate::{
+    parsing::lexer::Token,
+    SyntaxKind::{EOF, L_CURLY, R_CURLY},
+    TextRange, TextSize,
+};
 
 /// Implementation of `ra_parser::TokenSource` that takes tokens from source code text.
 pub(crate) struct TextTokenSource<'t> {
@@ -21,6 +25,9 @@ pub(crate) struct TextTokenSource<'t> {
 
     /// Current token and position
     curr: (ra_parser::Token, usize),
+
+    /// Whether some `{` are never closed
+    has_unclosed_curly: bool,
 }
 
 impl<'t> TokenSource for TextTokenSource<'t> {
@@ -47,6 +54,19 @@ impl<'t> TokenSource for TextTokenSource<'t> {
             .map(|(token, offset)| &self.text[TextRange::at(*offset, token.len)] == kw)
             .unwrap_or(false)
     }
+
+    fn is_unindented(&self) -> bool {
+        if !self.has_unclosed_curly {
+            return false;
+        }
+        match self.token_offset_pairs.get(self.curr.1) {
+            Some((_, offset)) => {
+                let offset = usize::from(*offset);
+                offset == 0 || self.text[..offset].ends_with('\n')
+            }
+            None => false,
+        }
+    }
 }
 
 fn mk_token(pos: usize, token_offset_pairs: &[(Token, TextSize)]) -> ra_parser::Token {
@@ -78,7 +98,21 @@ impl<'t> TextTokenSource<'t> {
             })
             .collect();
 
+        let mut depth = 0i32;
+        for (token, _) in token_offset_pairs.iter() {
+            match token.kind {
+                L_CURLY => depth += 1,
+                R_CURLY => depth -= 1,
+                _ => (),
+            }
+        }
+
         let first = mk_token(0, &token_offset_pairs);
-        TextTokenSource { text, token_offset_pairs, curr: (first, 0) }
+        TextTokenSource {
+            text,
+            token_offset_pairs,
+            curr: (first, 0),
+            has_unclosed_curly: depth > 0,
+        }
     }
 }
@@ -21,6 +21,12 @@ impl SyntaxNodePtr {
         SyntaxNodePtr { range: node.text_range(), kind: node.kind() }
     }
 
+    /// Creates a pointer to the node with the given kind and range, for
+    /// example one which was stored on disk.
+    pub fn from_parts(kind: SyntaxKind, range: TextRange) -> SyntaxNodePtr {
+        SyntaxNodePtr { range, kind }
+    }
+
     pub fn to_node(&self, root: &SyntaxNode) -> SyntaxNode {
         assert!(root.parent().is_none());
         successors(Some(root.clone()), |node| {
@@ -4,13 +4,17 @@ mod block;
 
 use crate::{
     ast, match_ast, AstNode, SyntaxError,
-    SyntaxKind::{BYTE, BYTE_STRING, CHAR, CONST_DEF, FN_DEF, INT_NUMBER, STRING, TYPE_ALIAS_DEF},
-    SyntaxNode, SyntaxToken, TextSize, T,
+    SyntaxKind::{
+        BYTE, BYTE_STRING, CHAR, CONST_DEF, FLOAT_NUMBER, FN_DEF, INT_NUMBER, RAW_BYTE_STRING,
+        RAW_STRING, STRING, TYPE_ALIAS_DEF,
+    },
+    SyntaxNode, SyntaxToken, TextRange, TextSize, T,
 };
-use rustc_lexer::unescape::{
-    self, unescape_byte, unescape_byte_literal, unescape_char, unescape_literal, Mode,
+use rustc_lexer::{
+    unescape::{self, unescape_byte, unescape_byte_literal, unescape_char, unescape_literal, Mode},
+    Base, LiteralKind,
 };
-use std::convert::TryFrom;
+use std::{convert::TryFrom, ops::Range};
 
 fn rustc_unescape_error_to_string(err: unescape::EscapeError) -> &'static str {
     use unescape::EscapeError as EE;
@@ -81,7 +85,6 @@ fn rustc_unescape_error_to_string(err: unescape::EscapeError) -> &'static str {
 
 pub(crate) fn validate(root: &SyntaxNode) -> Vec<SyntaxError> {
     // FIXME:
-    // * Add unescape validation of raw string literals and raw byte string literals
     // * Add validation of doc comments are being attached to nodes
 
     let mut errors = Vec::new();
@@ -95,6 +98,7 @@ pub(crate) fn validate(root: &SyntaxNode) -> Vec<SyntaxError> {
                 ast::Visibility(it) => validate_visibility(it, &mut errors),
                 ast::RangeExpr(it) => validate_range_expr(it, &mut errors),
                 ast::PathSegment(it) => validate_path_keywords(it, &mut errors),
+                ast::Param(it) => validate_param_pat(it, &mut errors),
                 _ => (),
             }
         }
@@ -112,27 +116,33 @@ fn validate_literal(literal: ast::Literal, acc: &mut Vec<SyntaxError>) {
     let text = token.text().as_str();
 
     // FIXME: lift this lambda refactor to `fn` (https://github.com/rust-analyzer/rust-analyzer/pull/2834#discussion_r366199205)
-    let mut push_err = |prefix_len, (off, err): (usize, unescape::EscapeError)| {
-        let off = token.text_range().start() + TextSize::try_from(off + prefix_len).unwrap();
-        acc.push(SyntaxError::new_at_offset(rustc_unescape_error_to_string(err), off));
+    let mut push_err = |prefix_len, range: Range<usize>, err: unescape::EscapeError| {
+        let start = TextSize::try_from(range.start + prefix_len).unwrap();
+        let end = TextSize::try_from(range.end + prefix_len).unwrap();
+        let range = TextRange::new(start, end) + token.text_range().start();
+        acc.push(SyntaxError::new(rustc_unescape_error_to_string(err), range));
     };
 
     match token.kind() {
         BYTE => {
-            if let Some(Err(e)) = unquote(text, 2, '\'').map(unescape_byte) {
-                push_err(2, e);
+            if let Some(without_quotes) = unquote(text, 2, '\'') {
+                if let Err((_, err)) = unescape_byte(without_quotes) {
+                    push_err(2, 0..without_quotes.len(), err);
+                }
             }
         }
         CHAR => {
-            if let Some(Err(e)) = unquote(text, 1, '\'').map(unescape_char) {
-                push_err(1, e);
+            if let Some(without_quotes) = unquote(text, 1, '\'') {
+                if let Err((_, err)) = unescape_char(without_quotes) {
+                    push_err(1, 0..without_quotes.len(), err);
+                }
             }
         }
         BYTE_STRING => {
             if let Some(without_quotes) = unquote(text, 2, '"') {
                 unescape_byte_literal(without_quotes, Mode::ByteStr, &mut |range, char| {
                     if let Err(err) = char {
-                        push_err(2, (range.start, err));
+                        push_err(2, range, err);
                     }
                 })
             }
@@ -141,15 +151,95 @@ fn validate_literal(literal: ast::Literal, acc: &mut Vec<SyntaxError>) {
             if let Some(without_quotes) = unquote(text, 1, '"') {
                 unescape_literal(without_quotes, Mode::Str, &mut |range, char| {
                     if let Err(err) = char {
-                        push_err(1, (range.start, err));
+                        push_err(1, range, err);
                     }
                 })
             }
         }
+        RAW_BYTE_STRING | RAW_STRING => {
+            let (prefix_len, mode) =
+                if token.kind() == RAW_STRING { (1, Mode::RawStr) } else { (2, Mode::RawByteStr) };
+            let n_hashes = text[prefix_len..].bytes().take_while(|&b| b == b'#').count();
+            let prefix_len = prefix_len + n_hashes + 1;
+            if let Some(without_quotes) = unquote(text, prefix_len, '"') {
+                unescape_literal(without_quotes, mode, &mut |range, char| {
+                    if let Err(err) = char {
+                        push_err(prefix_len, range, err);
+                    }
+                });
+
+                // The lexer includes all the `#` after the closing quote into the
+                // token, so that `r#"..."##` is not followed by a stray `#`.
+                let extra_hashes_start = prefix_len + without_quotes.len() + 1 + n_hashes;
+                if extra_hashes_start < text.len() {
+                    let range = TextRange::new(
+                        TextSize::try_from(extra_hashes_start).unwrap(),
+                        TextSize::of(text),
+                    );
+                    acc.push(SyntaxError::new(
+                        format!(
+                            "Too many `#` symbols to terminate the raw string literal, expected {}",
+                            n_hashes
+                        ),
+                        range + token.text_range().start(),
+                    ));
+                }
+            }
+        }
+        INT_NUMBER | FLOAT_NUMBER => validate_number_literal(&token, acc),
         _ => (),
     }
 }
 
+fn validate_number_literal(token: &SyntaxToken, acc: &mut Vec<SyntaxError>) {
+    const INT_SUFFIXES: [&str; 12] =
+        ["u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize"];
+    const FLOAT_SUFFIXES: [&str; 2] = ["f32", "f64"];
+
+    let text = token.text().as_str();
+    let (kind, suffix_start) = match rustc_lexer::first_token(text).kind {
+        rustc_lexer::TokenKind::Literal { kind, suffix_start } => (kind, suffix_start),
+        _ => return,
+    };
+    let suffix = &text[suffix_start..];
+    let suffix_range =
+        TextRange::new(TextSize::try_from(suffix_start).unwrap(), TextSize::of(text))
+            + token.text_range().start();
+
+    let (base, is_float) = match kind {
+        LiteralKind::Int { base, .. } => (base, FLOAT_SUFFIXES.contains(&suffix)),
+        LiteralKind::Float { base, .. } => (base, true),
+        _ => return,
+    };
+    let base_name = match base {
+        Base::Binary => Some("Binary"),
+        Base::Octal => Some("Octal"),
+        Base::Hexadecimal => Some("Hexadecimal"),
+        Base::Decimal => None,
+    };
+    if let (Some(base_name), true) = (base_name, is_float) {
+        acc.push(SyntaxError::new(
+            format!("{} float literals are not supported", base_name),
+            token.text_range(),
+        ));
+        return;
+    }
+
+    let is_valid_suffix = match kind {
+        LiteralKind::Int { .. } => {
+            INT_SUFFIXES.contains(&suffix) || FLOAT_SUFFIXES.contains(&suffix)
+        }
+        _ => FLOAT_SUFFIXES.contains(&suffix),
+    };
+    if !suffix.is_empty() && !is_valid_suffix {
+        let literal = if matches!(kind, LiteralKind::Int { .. }) { "integer" } else { "float" };
+        acc.push(SyntaxError::new(
+            format!("Invalid suffix `{}` for {} literal", suffix, literal),
+            suffix_range,
+        ));
+    }
+}
+
 pub(crate) fn validate_block_structure(root: &SyntaxNode) {
     let mut stack = Vec::new();
     for node in root.descendants() {
@@ -222,6 +312,15 @@ fn validate_range_expr(expr: ast::RangeExpr, errors: &mut Vec<SyntaxError>) {
     }
 }
 
+fn validate_param_pat(param: ast::Param, errors: &mut Vec<SyntaxError>) {
+    if let Some(ast::Pat::OrPat(pat)) = param.pat() {
+        errors.push(SyntaxError::new(
+            "Top-level or-patterns are not allowed in function parameters, wrap them in parentheses",
+            pat.syntax().text_range(),
+        ));
+    }
+}
+
 fn validate_path_keywords(segment: ast::PathSegment, errors: &mut Vec<SyntaxError>) {
     use ast::PathSegmentKind;
 
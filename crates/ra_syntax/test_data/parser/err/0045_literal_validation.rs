@@ -0,0 +1,14 @@
+fn main() {
+    let _ = 92u7;
+    let _ = 1.0u8;
+    let _ = 2.5foo;
+    let _ = 0b101f32;
+    let _ = 1f64 + 2usize + 3.0e1_f32;
+    let _ = '\u{110000}';
+    let _ = '\x80';
+    let _ = "a\u{D800}b\u{zz}c";
+    let _ = b"\u{41}";
+    let _ = r#"fine"#;
+    let _ = r#"done"###;
+    let _ = br##"日本"##;
+}
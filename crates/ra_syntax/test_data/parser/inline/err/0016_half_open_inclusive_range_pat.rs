@@ -0,0 +1 @@
+fn main() { match 92 { 0..= => (), 0... => () } }
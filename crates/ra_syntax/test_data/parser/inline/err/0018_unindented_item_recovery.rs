@@ -0,0 +1,12 @@
+fn foo() {
+    let x = foo(1,
+fn bar() {
+    if true {
+}
+struct S {
+    a: u32,
+impl S {
+    fn f(&self) {
+}
+#[test]
+fn baz() {}
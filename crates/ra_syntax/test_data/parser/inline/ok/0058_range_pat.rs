@@ -3,5 +3,7 @@ fn main() {
         0 ... 100 => (),
         101 ..= 200 => (),
         200 .. 301=> (),
+        302 .. => (),
     }
+    if let -1.. | X.. = 0 {}
 }
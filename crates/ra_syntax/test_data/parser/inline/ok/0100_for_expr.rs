@@ -1,3 +1,4 @@
 fn foo() {
     for x in [] {};
+    for | Some(x) | Ok(x) in [] {};
 }
@@ -1 +1,2 @@
 struct S<const N: u32>;
+impl<const N: usize, T> S<N, T> {}
@@ -1 +1,2 @@
 fn foo((x, y): (i32, i32)) {}
+fn bar((A | B): T, [C | D]: T) {}
@@ -0,0 +1 @@
+type T = S<-92, true, 'a', { N + 1 }, _>;
@@ -0,0 +1 @@
+struct A<const N: i32 = -1, const M: usize = { 1 + 2 }>;
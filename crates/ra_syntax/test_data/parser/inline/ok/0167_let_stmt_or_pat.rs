@@ -0,0 +1,8 @@
+fn main() {
+    let A | B = x;
+    let | A | B = x;
+    let (A | B, C): (T, T) = x;
+    let S { f: A | B, g: box (C | D) } = x;
+    let x @ (A | B) = y;
+    let f = |(A | B), [C | D, ..]: [T; 2]| ();
+}
@@ -5,12 +5,16 @@
 
 use std::{env, fmt::Write, path::PathBuf};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, format_err, Result};
 use pico_args::Arguments;
 use ra_ssr::{SsrPattern, SsrRule};
-use rust_analyzer::cli::{BenchWhat, Position, Verbosity};
+use rust_analyzer::cli::{
+    BenchWhat, DiagnosticsFilter, ExpandWhat, Position, SymbolsFormat, Verbosity,
+};
 use vfs::AbsPathBuf;
 
+use crate::transport::Transport;
+
 pub(crate) struct Args {
     pub(crate) verbosity: Verbosity,
     pub(crate) command: Command,
@@ -21,9 +25,23 @@ pub(crate) enum Command {
         no_dump: bool,
     },
     Symbols,
+    WorkspaceSymbols {
+        path: PathBuf,
+        load_output_dirs: bool,
+        with_proc_macro: bool,
+        with_deps: bool,
+        format: SymbolsFormat,
+    },
     Highlight {
         rainbow: bool,
     },
+    HighlightProject {
+        path: PathBuf,
+        output: Option<PathBuf>,
+        rainbow: bool,
+        load_output_dirs: bool,
+        with_proc_macro: bool,
+    },
     Stats {
         randomize: bool,
         parallel: bool,
@@ -47,16 +65,38 @@ pub(crate) enum Command {
         /// Include files which are not modules. In rust-analyzer
         /// this would include the parser test files.
         all: bool,
+        /// Also check the dependencies of the workspace.
+        with_deps: bool,
+        filter: DiagnosticsFilter,
+    },
+    ExpandMacro {
+        path: PathBuf,
+        what: ExpandWhat,
+        load_output_dirs: bool,
+        with_proc_macro: bool,
+    },
+    CrateGraph {
+        path: PathBuf,
+        exclude_sysroot: bool,
+        svg: bool,
+    },
+    Lsif {
+        path: PathBuf,
+        load_output_dirs: bool,
+        with_proc_macro: bool,
     },
     Ssr {
         rules: Vec<SsrRule>,
+        dry_run: bool,
     },
     StructuredSearch {
         debug_snippet: Option<String>,
         patterns: Vec<SsrPattern>,
     },
     ProcMacro,
-    RunServer,
+    RunServer {
+        transport: Transport,
+    },
     Version,
 }
 
@@ -89,8 +129,16 @@ impl Args {
                     print_subcommands();
                     return Ok(Err(HelpPrinted));
                 }
+                let listen: Option<String> = matches.opt_value_from_str("--listen")?;
+                let pipe: Option<PathBuf> = matches.opt_value_from_str("--pipe")?;
                 matches.finish().or_else(handle_extra_flags)?;
-                return Ok(Ok(Args { verbosity, command: Command::RunServer }));
+                let transport = match (listen, pipe) {
+                    (None, None) => Transport::Stdio,
+                    (Some(addr), None) => Transport::Tcp(addr),
+                    (None, Some(path)) => Transport::Pipe(path),
+                    (Some(_), Some(_)) => bail!("Invalid flags: --listen conflicts with --pipe"),
+                };
+                return Ok(Ok(Args { verbosity, command: Command::RunServer { transport } }));
             }
         };
         let command = match subcommand.as_str() {
@@ -121,17 +169,49 @@ FLAGS:
 rust-analyzer symbols
 
 USAGE:
-    rust-analyzer highlight [FLAGS]
+    rust-analyzer symbols [FLAGS] [OPTIONS] [PATH]
+
+Without a path, prints the structure of the file read from stdin. With a path,
+prints all symbols of the workspace as JSON.
 
 FLAGS:
-    -h, --help    Prints help inforamtion"
+    -h, --help              Prints help information
+        --load-output-dirs  Load OUT_DIR values by running `cargo check` before analysis
+        --with-proc-macro   Use ra-proc-macro-srv for proc-macro expanding
+        --with-deps         Also print the symbols of the dependencies
+
+OPTIONS:
+        --format <FORMAT>   `ndjson` for one object per line (default), or `json` for an array
+
+ARGS:
+    <PATH>"
                     );
                     return Ok(Err(HelpPrinted));
                 }
 
-                matches.finish().or_else(handle_extra_flags)?;
-
-                Command::Symbols
+                let load_output_dirs = matches.contains("--load-output-dirs");
+                let with_proc_macro = matches.contains("--with-proc-macro");
+                let with_deps = matches.contains("--with-deps");
+                let format = matches.opt_value_from_str("--format")?;
+                let mut trailing = matches.free()?;
+                match (trailing.pop(), trailing.is_empty()) {
+                    (None, _)
+                        if !load_output_dirs
+                            && !with_proc_macro
+                            && !with_deps
+                            && format.is_none() =>
+                    {
+                        Command::Symbols
+                    }
+                    (Some(path), true) => Command::WorkspaceSymbols {
+                        path: path.into(),
+                        load_output_dirs,
+                        with_proc_macro,
+                        with_deps,
+                        format: format.unwrap_or(SymbolsFormat::NdJson),
+                    },
+                    _ => bail!("Invalid flags"),
+                }
             }
             "highlight" => {
                 if matches.contains(["-h", "--help"]) {
@@ -140,18 +220,46 @@ FLAGS:
 rust-analyzer highlight
 
 USAGE:
-    rust-analyzer highlight [FLAGS]
+    rust-analyzer highlight [FLAGS] [OPTIONS] [PATH]
+
+Without a path, highlights the file read from stdin. With the path of a file,
+prints it as a standalone HTML page. With the path of a project, renders all
+files of its crates to the `--output` directory, with links from references
+to their definitions.
 
 FLAGS:
-    -h, --help       Prints help information
-    -r, --rainbow"
+    -h, --help              Prints help information
+    -r, --rainbow
+        --load-output-dirs  Load OUT_DIR values by running `cargo check` before analysis
+        --with-proc-macro   Use ra-proc-macro-srv for proc-macro expanding
+
+OPTIONS:
+    -o, --output <PATH>     The file or directory to write the HTML to
+
+ARGS:
+    <PATH>"
                     );
                     return Ok(Err(HelpPrinted));
                 }
 
                 let rainbow = matches.contains(["-r", "--rainbow"]);
-                matches.finish().or_else(handle_extra_flags)?;
-                Command::Highlight { rainbow }
+                let load_output_dirs = matches.contains("--load-output-dirs");
+                let with_proc_macro = matches.contains("--with-proc-macro");
+                let output: Option<PathBuf> = matches.opt_value_from_str(["-o", "--output"])?;
+                let mut trailing = matches.free()?;
+                match (trailing.pop(), trailing.is_empty()) {
+                    (None, _) if !load_output_dirs && !with_proc_macro && output.is_none() => {
+                        Command::Highlight { rainbow }
+                    }
+                    (Some(path), true) => Command::HighlightProject {
+                        path: path.into(),
+                        output,
+                        rainbow,
+                        load_output_dirs,
+                        with_proc_macro,
+                    },
+                    _ => bail!("Invalid flags"),
+                }
             }
             "analysis-stats" => {
                 if matches.contains(["-h", "--help"]) {
@@ -165,9 +273,10 @@ USAGE:
 FLAGS:
     -o, --only              Only analyze items matching this path
     -h, --help              Prints help information
-        --memory-usage      Collect memory usage statistics (requires `--feature jemalloc`)
+        --memory-usage      Collect memory usage statistics per crate, query and query group
+                            (requires `--feature jemalloc`)
         --randomize         Randomize order in which crates, modules, and items are processed
-        --parallel          Run type inference in parallel
+        --parallel          Run type inference in parallel first, using RAYON_NUM_THREADS threads
         --load-output-dirs  Load OUT_DIR values by running `cargo check` before analysis
         --with-proc-macro   Use ra-proc-macro-srv for proc-macro expanding
         --with-deps         Also analyze all dependencies
@@ -229,6 +338,9 @@ OPTIONS:
     --complete <PATH:LINE:COLUMN>    Compute completions at this location
     --goto-def <PATH:LINE:COLUMN>    Compute goto definition at this location
     --highlight <PATH>               Hightlight this file
+    --script <PATH>                  Replay the edits and requests of this typing simulation
+                                     script, and report the latencies of the requests
+    --iterations <N>                 How many times to replay the script, 1 by default
 
 ARGS:
     <PATH>    Project to analyse"
@@ -240,15 +352,21 @@ ARGS:
                 let highlight_path: Option<String> = matches.opt_value_from_str("--highlight")?;
                 let complete_path: Option<Position> = matches.opt_value_from_str("--complete")?;
                 let goto_def_path: Option<Position> = matches.opt_value_from_str("--goto-def")?;
-                let what = match (highlight_path, complete_path, goto_def_path) {
-                    (Some(path), None, None) => {
+                let script_path: Option<String> = matches.opt_value_from_str("--script")?;
+                let iterations = matches.opt_value_from_str("--iterations")?.unwrap_or(1);
+                let what = match (highlight_path, complete_path, goto_def_path, script_path) {
+                    (Some(path), None, None, None) => {
                         let path = env::current_dir().unwrap().join(path);
                         BenchWhat::Highlight { path: AbsPathBuf::assert(path) }
                     }
-                    (None, Some(position), None) => BenchWhat::Complete(position),
-                    (None, None, Some(position)) => BenchWhat::GotoDef(position),
+                    (None, Some(position), None, None) => BenchWhat::Complete(position),
+                    (None, None, Some(position), None) => BenchWhat::GotoDef(position),
+                    (None, None, None, Some(path)) => {
+                        let path = env::current_dir().unwrap().join(path);
+                        BenchWhat::Script { path: AbsPathBuf::assert(path), iterations }
+                    }
                     _ => panic!(
-                        "exactly one of  `--highlight`, `--complete` or `--goto-def` must be set"
+                        "exactly one of  `--highlight`, `--complete`, `--goto-def` or `--script` must be set"
                     ),
                 };
                 let load_output_dirs = matches.contains("--load-output-dirs");
@@ -262,12 +380,19 @@ ARGS:
 rust-analyzer diagnostics
 
 USAGE:
-    rust-analyzer diagnostics [FLAGS] [PATH]
+    rust-analyzer diagnostics [FLAGS] [OPTIONS] [PATH]
 
 FLAGS:
     -h, --help              Prints help information
         --load-output-dirs  Load OUT_DIR values by running `cargo check` before analysis
+        --with-proc-macro   Use ra-proc-macro-srv for proc-macro expanding
         --all               Include all files rather than only modules
+        --with-deps         Also check the dependencies of the workspace
+
+OPTIONS:
+        --enable <NAMES>    Only report the diagnostics with these comma-separated names
+        --disable <NAMES>   Don't report the diagnostics with these comma-separated names,
+                            like `missing-match-arm,use-field-shorthand`
 
 ARGS:
     <PATH>"
@@ -278,6 +403,103 @@ ARGS:
                 let load_output_dirs = matches.contains("--load-output-dirs");
                 let with_proc_macro = matches.contains("--with-proc-macro");
                 let all = matches.contains("--all");
+                let with_deps = matches.contains("--with-deps");
+                let names = |list: Option<String>| -> Vec<String> {
+                    list.iter()
+                        .flat_map(|it| it.split(','))
+                        .map(|it| it.trim().to_string())
+                        .filter(|it| !it.is_empty())
+                        .collect()
+                };
+                let filter = DiagnosticsFilter::new(
+                    names(matches.opt_value_from_str("--enable")?),
+                    names(matches.opt_value_from_str("--disable")?),
+                )?;
+                let path = {
+                    let mut trailing = matches.free()?;
+                    if trailing.len() != 1 {
+                        bail!("Invalid flags");
+                    }
+                    trailing.pop().unwrap().into()
+                };
+
+                Command::Diagnostics {
+                    path,
+                    load_output_dirs,
+                    with_proc_macro,
+                    all,
+                    with_deps,
+                    filter,
+                }
+            }
+            "expand-macro" => {
+                if matches.contains(["-h", "--help"]) {
+                    eprintln!(
+                        "\
+rust-analyzer expand-macro
+
+USAGE:
+    rust-analyzer expand-macro [FLAGS] [OPTIONS]
+
+Prints the recursive expansion of macro calls, as the editor shows it.
+
+FLAGS:
+    -h, --help              Prints help information
+        --load-output-dirs  Load OUT_DIR values by running `cargo check` before analysis
+        --with-proc-macro   Use ra-proc-macro-srv for proc-macro expanding
+
+OPTIONS:
+        --project <PATH>            Path to directory with Cargo.toml
+        --at <PATH:LINE:COLUMN>     Expand the macro call at this location
+        --offset <PATH:OFFSET>      Expand the macro call at this byte offset
+        --item <ITEM_PATH>          Expand all macro calls in this item, like `krate::module::f`"
+                    );
+                    return Ok(Err(HelpPrinted));
+                }
+
+                let path: PathBuf = matches.opt_value_from_str("--project")?.unwrap_or_default();
+                let at: Option<Position> = matches.opt_value_from_str("--at")?;
+                let offset: Option<String> = matches.opt_value_from_str("--offset")?;
+                let item: Option<String> = matches.opt_value_from_str("--item")?;
+                let what = match (at, offset, item) {
+                    (Some(position), None, None) => ExpandWhat::Position(position),
+                    (None, Some(offset), None) => {
+                        let idx =
+                            offset.rfind(':').ok_or_else(|| format_err!("no `:` in {}", offset))?;
+                        let path = env::current_dir().unwrap().join(&offset[..idx]);
+                        let offset = offset[idx + 1..].parse()?;
+                        ExpandWhat::Offset { path: AbsPathBuf::assert(path), offset }
+                    }
+                    (None, None, Some(item)) => ExpandWhat::Item(item),
+                    _ => bail!("exactly one of `--at`, `--offset` or `--item` must be set"),
+                };
+                let load_output_dirs = matches.contains("--load-output-dirs");
+                let with_proc_macro = matches.contains("--with-proc-macro");
+                matches.finish().or_else(handle_extra_flags)?;
+                Command::ExpandMacro { path, what, load_output_dirs, with_proc_macro }
+            }
+            "crate-graph" => {
+                if matches.contains(["-h", "--help"]) {
+                    eprintln!(
+                        "\
+rust-analyzer crate-graph
+
+USAGE:
+    rust-analyzer crate-graph [FLAGS] [PATH]
+
+FLAGS:
+    -h, --help             Prints help information
+        --exclude-sysroot  Hide the standard library crates
+        --svg              Render the graph with `dot` instead of printing DOT source
+
+ARGS:
+    <PATH>"
+                    );
+                    return Ok(Err(HelpPrinted));
+                }
+
+                let exclude_sysroot = matches.contains("--exclude-sysroot");
+                let svg = matches.contains("--svg");
                 let path = {
                     let mut trailing = matches.free()?;
                     if trailing.len() != 1 {
@@ -286,7 +508,40 @@ ARGS:
                     trailing.pop().unwrap().into()
                 };
 
-                Command::Diagnostics { path, load_output_dirs, with_proc_macro, all }
+                Command::CrateGraph { path, exclude_sysroot, svg }
+            }
+            "lsif" => {
+                if matches.contains(["-h", "--help"]) {
+                    eprintln!(
+                        "\
+rust-analyzer lsif
+
+USAGE:
+    rust-analyzer lsif [FLAGS] [PATH]
+
+Prints an LSIF dump of the workspace to stdout.
+
+FLAGS:
+    -h, --help              Prints help information
+        --load-output-dirs  Load OUT_DIR values by running `cargo check` before analysis
+        --with-proc-macro   Use ra-proc-macro-srv for proc-macro expanding
+
+ARGS:
+    <PATH>"
+                    );
+                    return Ok(Err(HelpPrinted));
+                }
+
+                let load_output_dirs = matches.contains("--load-output-dirs");
+                let with_proc_macro = matches.contains("--with-proc-macro");
+                let path = {
+                    let mut trailing = matches.free()?;
+                    if trailing.len() != 1 {
+                        bail!("Invalid flags");
+                    }
+                    trailing.pop().unwrap().into()
+                };
+                Command::Lsif { path, load_output_dirs, with_proc_macro }
             }
             "proc-macro" => Command::ProcMacro,
             "ssr" => {
@@ -299,22 +554,27 @@ USAGE:
     rust-analyzer ssr [FLAGS] [RULE...]
 
 EXAMPLE:
-    rust-analyzer ssr '$a.foo($b) ==> bar($a, $b)'
+    rust-analyzer ssr '$a.foo($b) ==>> bar($a, $b)'
 
 FLAGS:
-    --debug <snippet>   Prints debug information for any nodes with source exactly equal to <snippet>
+    --dry-run           Prints the changes as a diff instead of writing them
     -h, --help          Prints help information
 
 ARGS:
-    <RULE>              A structured search replace rule"
+    <RULE>              A structured search replace rule, applied to the workspace in the
+                        current directory"
                     );
                     return Ok(Err(HelpPrinted));
                 }
+                let dry_run = matches.contains("--dry-run");
                 let mut rules = Vec::new();
                 while let Some(rule) = matches.free_from_str()? {
                     rules.push(rule);
                 }
-                Command::Ssr { rules }
+                if rules.is_empty() {
+                    bail!("Expected at least one rule");
+                }
+                Command::Ssr { rules, dry_run }
             }
             "search" => {
                 if matches.contains(["-h", "--help"]) {
@@ -359,16 +619,22 @@ fn print_subcommands() {
 rust-analyzer
 
 USAGE:
+    rust-analyzer [FLAGS]
     rust-analyzer <SUBCOMMAND>
 
 FLAGS:
-    -h, --help        Prints help information
+    -h, --help           Prints help information
+        --listen <ADDR>  Runs the LSP server on a TCP socket, waiting for a single client
+        --pipe <PATH>    Runs the LSP server over a pipe created by the client
 
 SUBCOMMANDS:
     analysis-bench
     analysis-stats
+    crate-graph
     highlight
     diagnostics
+    expand-macro
+    lsif
     proc-macro
     parse
     search
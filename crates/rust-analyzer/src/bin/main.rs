@@ -2,19 +2,18 @@
 //!
 //! Based on cli flags, either spawns an LSP server, or runs a batch analysis
 mod args;
+mod transport;
 
 use std::convert::TryFrom;
 
-use lsp_server::Connection;
-use ra_project_model::ProjectManifest;
 use rust_analyzer::{
     cli,
-    config::{Config, LinkedProject},
+    config::{Config, OffsetEncoding},
     from_json, Result,
 };
 use vfs::AbsPathBuf;
 
-use crate::args::HelpPrinted;
+use crate::{args::HelpPrinted, transport::Transport};
 
 fn main() -> Result<()> {
     setup_logging()?;
@@ -23,12 +22,38 @@ fn main() -> Result<()> {
         Err(HelpPrinted) => return Ok(()),
     };
     match args.command {
-        args::Command::RunServer => run_server()?,
+        args::Command::RunServer { transport } => run_server(transport)?,
         args::Command::ProcMacro => ra_proc_macro_srv::cli::run()?,
 
         args::Command::Parse { no_dump } => cli::parse(no_dump)?,
         args::Command::Symbols => cli::symbols()?,
+        args::Command::WorkspaceSymbols {
+            path,
+            load_output_dirs,
+            with_proc_macro,
+            with_deps,
+            format,
+        } => cli::workspace_symbols(
+            path.as_ref(),
+            load_output_dirs,
+            with_proc_macro,
+            with_deps,
+            format,
+        )?,
         args::Command::Highlight { rainbow } => cli::highlight(rainbow)?,
+        args::Command::HighlightProject {
+            path,
+            output,
+            rainbow,
+            load_output_dirs,
+            with_proc_macro,
+        } => cli::highlight_project(
+            path.as_ref(),
+            output.as_deref(),
+            rainbow,
+            load_output_dirs,
+            with_proc_macro,
+        )?,
         args::Command::Stats {
             randomize,
             parallel,
@@ -58,17 +83,50 @@ fn main() -> Result<()> {
                 with_proc_macro,
             )?
         }
-        args::Command::Diagnostics { path, load_output_dirs, with_proc_macro, all } => {
-            cli::diagnostics(path.as_ref(), load_output_dirs, with_proc_macro, all)?
+        args::Command::Diagnostics {
+            path,
+            load_output_dirs,
+            with_proc_macro,
+            all,
+            with_deps,
+            filter,
+        } => cli::diagnostics(
+            path.as_ref(),
+            load_output_dirs,
+            with_proc_macro,
+            all,
+            with_deps,
+            &filter,
+        )?,
+        args::Command::ExpandMacro { path, what, load_output_dirs, with_proc_macro } => {
+            cli::expand_macro(path.as_ref(), what, load_output_dirs, with_proc_macro)?
+        }
+        args::Command::CrateGraph { path, exclude_sysroot, svg } => {
+            cli::crate_graph(path.as_ref(), exclude_sysroot, svg)?
         }
-        args::Command::Ssr { rules } => {
-            cli::apply_ssr_rules(rules)?;
+        args::Command::Lsif { path, load_output_dirs, with_proc_macro } => {
+            cli::lsif(path.as_ref(), load_output_dirs, with_proc_macro)?
+        }
+        args::Command::Ssr { rules, dry_run } => {
+            cli::apply_ssr_rules(rules, dry_run)?;
         }
         args::Command::StructuredSearch { patterns, debug_snippet } => {
             cli::search_for_patterns(patterns, debug_snippet)?;
         }
         args::Command::Version => println!("rust-analyzer {}", env!("REV")),
     }
+    write_folded_stacks()?;
+    Ok(())
+}
+
+/// With `RA_PROFILE_FOLDED=path`, writes the stacks profiled during the run to
+/// `path`, in a format suitable for flamegraph tools. Without `RA_PROFILE`,
+/// everything is profiled, but nothing is printed.
+fn write_folded_stacks() -> Result<()> {
+    if let Some(path) = std::env::var_os("RA_PROFILE_FOLDED") {
+        let mut file = std::fs::File::create(path)?;
+        ra_prof::take_summary().write_folded_stacks(&mut file)?;
+    }
     Ok(())
 }
 
@@ -76,15 +134,20 @@ fn setup_logging() -> Result<()> {
     std::env::set_var("RUST_BACKTRACE", "short");
     env_logger::try_init_from_env("RA_LOG")?;
     ra_prof::init();
+    if std::env::var_os("RA_PROFILE").is_none() && std::env::var_os("RA_PROFILE_FOLDED").is_some() {
+        ra_prof::init_summary_from("*");
+    }
     Ok(())
 }
 
-fn run_server() -> Result<()> {
+fn run_server(transport: Transport) -> Result<()> {
     log::info!("lifecycle: server started");
 
-    let (connection, io_threads) = Connection::stdio();
+    let (connection, io_threads) = transport::connect(transport)?;
 
     let (initialize_id, initialize_params) = connection.initialize_start()?;
+    let offset_encoding =
+        rust_analyzer::negotiate_offset_encoding(&initialize_params["capabilities"]);
     let initialize_params =
         from_json::<lsp_types::InitializeParams>("InitializeParams", initialize_params)?;
 
@@ -98,7 +161,13 @@ fn run_server() -> Result<()> {
         }),
     };
 
-    let initialize_result = serde_json::to_value(initialize_result).unwrap();
+    let mut initialize_result = serde_json::to_value(initialize_result).unwrap();
+    initialize_result["capabilities"]["workspace"]["fileOperations"] =
+        rust_analyzer::file_operations_capabilities();
+    if offset_encoding == OffsetEncoding::Utf8 {
+        initialize_result["capabilities"]["positionEncoding"] = "utf-8".into();
+        initialize_result["capabilities"]["offsetEncoding"] = "utf-8".into();
+    }
 
     connection.initialize_finish(initialize_id, initialize_result)?;
 
@@ -124,9 +193,10 @@ fn run_server() -> Result<()> {
             config.update(value);
         }
         config.update_caps(&initialize_params.capabilities);
+        config.client_caps.offset_encoding = offset_encoding;
 
         if config.linked_projects.is_empty() {
-            let workspace_roots = initialize_params
+            config.discovery_roots = initialize_params
                 .workspace_folders
                 .map(|workspaces| {
                     workspaces
@@ -137,11 +207,7 @@ fn run_server() -> Result<()> {
                 })
                 .filter(|workspaces| !workspaces.is_empty())
                 .unwrap_or_else(|| vec![config.root_path.clone()]);
-
-            config.linked_projects = ProjectManifest::discover_all(&workspace_roots)
-                .into_iter()
-                .map(LinkedProject::from)
-                .collect();
+            config.discover_projects();
         }
 
         config
@@ -0,0 +1,119 @@
+//! Transports over which the LSP server can talk to the client.
+//!
+//! Besides stdio, the server can listen on a TCP socket or connect to a pipe
+//! created by the client, which is handy when the server runs in a container
+//! or on a remote machine, or when attaching a debugging tool. All transports
+//! use the same JSON-RPC framing.
+
+use std::{
+    io::{self, BufReader, Read, Write},
+    net::TcpListener,
+    path::PathBuf,
+    thread,
+};
+
+use crossbeam_channel::bounded;
+use lsp_server::{Connection, Message, Notification};
+use rust_analyzer::Result;
+
+pub(crate) enum Transport {
+    Stdio,
+    /// Listen for a single client on the given address.
+    Tcp(String),
+    /// Connect to a pipe (a Unix domain socket, or a named pipe on Windows)
+    /// created by the client.
+    Pipe(PathBuf),
+}
+
+pub(crate) enum IoThreads {
+    Stdio(lsp_server::IoThreads),
+    Stream {
+        reader: thread::JoinHandle<io::Result<()>>,
+        writer: thread::JoinHandle<io::Result<()>>,
+    },
+}
+
+impl IoThreads {
+    pub(crate) fn join(self) -> Result<()> {
+        match self {
+            IoThreads::Stdio(it) => it.join()?,
+            IoThreads::Stream { reader, writer } => {
+                reader.join().map_err(|_| "reader thread panicked")??;
+                writer.join().map_err(|_| "writer thread panicked")??;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn connect(transport: Transport) -> Result<(Connection, IoThreads)> {
+    let res = match transport {
+        Transport::Stdio => {
+            let (connection, io_threads) = Connection::stdio();
+            (connection, IoThreads::Stdio(io_threads))
+        }
+        Transport::Tcp(addr) => {
+            let listener = TcpListener::bind(&addr)?;
+            log::info!("waiting for a client on {}", listener.local_addr()?);
+            let (stream, peer) = listener.accept()?;
+            log::info!("client connected from {}", peer);
+            stream_transport(stream.try_clone()?, stream)
+        }
+        Transport::Pipe(path) => {
+            let (reader, writer) = open_pipe(&path)?;
+            log::info!("connected to {}", path.display());
+            stream_transport(reader, writer)
+        }
+    };
+    Ok(res)
+}
+
+#[cfg(unix)]
+fn open_pipe(
+    path: &std::path::Path,
+) -> io::Result<(impl Read + Send + 'static, impl Write + Send + 'static)> {
+    let stream = std::os::unix::net::UnixStream::connect(path)?;
+    Ok((stream.try_clone()?, stream))
+}
+
+#[cfg(not(unix))]
+fn open_pipe(
+    path: &std::path::Path,
+) -> io::Result<(impl Read + Send + 'static, impl Write + Send + 'static)> {
+    // Named pipes can be opened like regular files on Windows.
+    let file = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+    Ok((file.try_clone()?, file))
+}
+
+fn stream_transport(
+    reader: impl Read + Send + 'static,
+    mut writer: impl Write + Send + 'static,
+) -> (Connection, IoThreads) {
+    let (reader_sender, reader_receiver) = bounded::<Message>(0);
+    let reader = thread::spawn(move || {
+        let mut buf_read = BufReader::new(reader);
+        while let Some(msg) = Message::read(&mut buf_read)? {
+            let is_exit = match &msg {
+                Message::Notification(n) => is_exit(n),
+                _ => false,
+            };
+            if reader_sender.send(msg).is_err() || is_exit {
+                break;
+            }
+        }
+        Ok(())
+    });
+
+    let (writer_sender, writer_receiver) = bounded::<Message>(0);
+    let writer = thread::spawn(move || {
+        writer_receiver.into_iter().try_for_each(|it| it.write(&mut writer))?;
+        writer.flush()
+    });
+
+    let connection = Connection { sender: writer_sender, receiver: reader_receiver };
+    (connection, IoThreads::Stream { reader, writer })
+}
+
+fn is_exit(notification: &Notification) -> bool {
+    notification.method == "exit"
+}
@@ -13,7 +13,7 @@ use lsp_types::{
 };
 use serde_json::json;
 
-use crate::semantic_tokens;
+use crate::{config::OffsetEncoding, semantic_tokens};
 
 pub fn server_capabilities(client_caps: &ClientCapabilities) -> ServerCapabilities {
     let code_action_provider = code_action_capabilities(client_caps);
@@ -76,7 +76,9 @@ pub fn server_capabilities(client_caps: &ClientCapabilities) -> ServerCapabiliti
                     token_modifiers: semantic_tokens::SUPPORTED_MODIFIERS.to_vec(),
                 },
 
-                document_provider: Some(SemanticTokensDocumentProvider::Bool(true)),
+                document_provider: Some(SemanticTokensDocumentProvider::Edits {
+                    edits: Some(true),
+                }),
                 range_provider: Some(true),
                 work_done_progress_options: Default::default(),
             }
@@ -87,6 +89,8 @@ pub fn server_capabilities(client_caps: &ClientCapabilities) -> ServerCapabiliti
             "ssr": true,
             "onEnter": true,
             "parentModule": true,
+            "openCargoToml": true,
+            "moveItem": true,
             "runnables": {
                 "kinds": [ "cargo" ],
             },
@@ -117,3 +121,59 @@ fn code_action_capabilities(client_caps: &ClientCapabilities) -> CodeActionProvi
             })
         })
 }
+
+/// Picks UTF-8 offsets if the client supports them, as advertised either by
+/// `general.positionEncodings` or by the `offsetEncoding` extension, which
+/// `lsp_types` doesn't know about yet. This spares us the translation to
+/// UTF-16.
+pub fn negotiate_offset_encoding(client_caps: &serde_json::Value) -> OffsetEncoding {
+    let supports_utf8 = |pointer: &str| {
+        client_caps
+            .pointer(pointer)
+            .and_then(|it| it.as_array())
+            .map_or(false, |it| it.iter().any(|it| it == "utf-8"))
+    };
+    if supports_utf8("/general/positionEncodings") || supports_utf8("/offsetEncoding") {
+        OffsetEncoding::Utf8
+    } else {
+        OffsetEncoding::Utf16
+    }
+}
+
+/// Capabilities for file operations, which `lsp_types` doesn't know about
+/// yet. They are added to `workspace.fileOperations` of the serialized
+/// `ServerCapabilities`.
+pub fn file_operations_capabilities() -> serde_json::Value {
+    json!({
+        "willRename": {
+            "filters": [
+                { "scheme": "file", "pattern": { "glob": "**/*.rs", "matches": "file" } },
+                { "scheme": "file", "pattern": { "glob": "**", "matches": "folder" } },
+            ]
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::negotiate_offset_encoding;
+    use crate::config::OffsetEncoding;
+
+    #[test]
+    fn negotiates_utf8_offsets() {
+        let caps = json!({ "general": { "positionEncodings": ["utf-32", "utf-8"] } });
+        assert_eq!(negotiate_offset_encoding(&caps), OffsetEncoding::Utf8);
+
+        let caps = json!({ "offsetEncoding": ["utf-8", "utf-16"] });
+        assert_eq!(negotiate_offset_encoding(&caps), OffsetEncoding::Utf8);
+
+        let caps = json!({
+            "general": { "positionEncodings": ["utf-16"] },
+            "offsetEncoding": ["utf-16"],
+        });
+        assert_eq!(negotiate_offset_encoding(&caps), OffsetEncoding::Utf16);
+        assert_eq!(negotiate_offset_encoding(&json!({})), OffsetEncoding::Utf16);
+    }
+}
@@ -3,22 +3,35 @@
 mod load_cargo;
 mod analysis_stats;
 mod analysis_bench;
+mod crate_graph;
 mod diagnostics;
+mod expand_macro;
+mod highlight;
+mod lsif;
 mod progress_report;
 mod ssr;
+mod symbols;
 
 use std::io::Read;
 
 use anyhow::Result;
-use ra_ide::{file_structure, Analysis};
+use hir::Crate;
+use ra_db::SourceDatabaseExt;
+use ra_ide::{file_structure, Analysis, FileId, RootDatabase};
 use ra_prof::profile;
 use ra_syntax::{AstNode, SourceFile};
+use rustc_hash::FxHashSet;
 
 pub use analysis_bench::{analysis_bench, BenchWhat, Position};
 pub use analysis_stats::analysis_stats;
-pub use diagnostics::diagnostics;
+pub use crate_graph::crate_graph;
+pub use diagnostics::{diagnostics, DiagnosticsFilter};
+pub use expand_macro::{expand_macro, ExpandWhat};
+pub use highlight::highlight_project;
 pub use load_cargo::load_cargo;
+pub use lsif::lsif;
 pub use ssr::{apply_ssr_rules, search_for_patterns};
+pub use symbols::{workspace_symbols, SymbolsFormat};
 
 #[derive(Clone, Copy)]
 pub enum Verbosity {
@@ -62,6 +75,28 @@ pub fn highlight(rainbow: bool) -> Result<()> {
     Ok(())
 }
 
+/// Returns the files of all modules of the workspace crates, and of their
+/// dependencies if `with_deps` is set.
+fn module_files(db: &RootDatabase, with_deps: bool) -> Vec<FileId> {
+    let mut res = Vec::new();
+    let mut visited_files = FxHashSet::default();
+    for krate in Crate::all(db) {
+        let source_root = db.source_root(db.file_source_root(krate.root_file(db)));
+        if source_root.is_library && !with_deps {
+            continue;
+        }
+        let mut work = vec![krate.root_module(db).expect("crate without root module")];
+        while let Some(module) = work.pop() {
+            work.extend(module.children(db));
+            let file_id = module.definition_source(db).file_id.original_file(db);
+            if visited_files.insert(file_id) {
+                res.push(file_id);
+            }
+        }
+    }
+    res
+}
+
 fn file() -> Result<SourceFile> {
     let text = read_stdin()?;
     Ok(SourceFile::parse(&text).tree())
@@ -1,23 +1,41 @@
 //! Benchmark operations like highlighting or goto definition.
 
-use std::{env, path::Path, str::FromStr, sync::Arc, time::Instant};
+use std::{
+    env, fs,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use anyhow::{format_err, Result};
+use anyhow::{bail, format_err, Result};
 use ra_db::{
     salsa::{Database, Durability},
     FileId,
 };
-use ra_ide::{Analysis, AnalysisChange, AnalysisHost, CompletionConfig, FilePosition, LineCol};
-use vfs::AbsPathBuf;
+use ra_ide::{
+    Analysis, AnalysisChange, AnalysisHost, CompletionConfig, FilePosition, LineCol, LineIndex,
+};
+use ra_syntax::TextSize;
+use rustc_hash::FxHashMap;
+use vfs::{AbsPathBuf, Vfs};
 
-use crate::cli::{load_cargo::load_cargo, Verbosity};
+use crate::cli::{analysis_stats::Latencies, load_cargo::load_cargo, Verbosity};
 
 pub enum BenchWhat {
-    Highlight { path: AbsPathBuf },
+    Highlight {
+        path: AbsPathBuf,
+    },
     Complete(Position),
     GotoDef(Position),
+    /// Replays the steps of a script, see `ScriptStep`, `iterations` times.
+    Script {
+        path: AbsPathBuf,
+        iterations: usize,
+    },
 }
 
+#[derive(Debug, PartialEq, Eq)]
 pub struct Position {
     pub path: AbsPathBuf,
     pub line: u32,
@@ -35,6 +53,34 @@ impl FromStr for Position {
     }
 }
 
+impl Position {
+    /// Returns the offset of the position in `text`, or an error if the
+    /// position is past the end of its line or of the file.
+    pub(crate) fn offset(&self, line_index: &LineIndex, text: &str) -> Result<TextSize> {
+        let lines = text.split('\n').collect::<Vec<_>>();
+        let line = match self.line.checked_sub(1) {
+            Some(line) if (line as usize) < lines.len() => line,
+            _ => bail!(
+                "line {} is out of range, {} has {} lines",
+                self.line,
+                self.path.display(),
+                lines.len()
+            ),
+        };
+        let line_len = lines[line as usize].trim_end_matches('\r').encode_utf16().count() as u32;
+        if self.column > line_len {
+            bail!(
+                "column {} is out of range, line {} of {} has {} columns",
+                self.column,
+                self.line,
+                self.path.display(),
+                line_len
+            );
+        }
+        Ok(line_index.offset(LineCol { line, col_utf16: self.column }))
+    }
+}
+
 fn rsplit_at_char(s: &str, c: char) -> Result<(&str, &str)> {
     let idx = s.rfind(c).ok_or_else(|| format_err!("no `{}` in {}", c, s))?;
     Ok((&s[..idx], &s[idx + 1..]))
@@ -58,9 +104,12 @@ pub fn analysis_bench(
         let path = match &what {
             BenchWhat::Highlight { path } => path,
             BenchWhat::Complete(pos) | BenchWhat::GotoDef(pos) => &pos.path,
+            BenchWhat::Script { path, iterations } => {
+                let steps = parse_script(&fs::read_to_string(path)?)?;
+                return run_script(&mut host, &|path| file_id(&vfs, path), &steps, *iterations);
+            }
         };
-        let path = path.clone().into();
-        vfs.file_id(&path).ok_or_else(|| format_err!("Can't find {}", path))?
+        file_id(&vfs, path)?
     };
 
     match &what {
@@ -76,10 +125,9 @@ pub fn analysis_bench(
         BenchWhat::Complete(pos) | BenchWhat::GotoDef(pos) => {
             let is_completion = matches!(what, BenchWhat::Complete(..));
 
-            let offset = host
-                .analysis()
-                .file_line_index(file_id)?
-                .offset(LineCol { line: pos.line - 1, col_utf16: pos.column });
+            let analysis = host.analysis();
+            let line_index = analysis.file_line_index(file_id)?;
+            let offset = pos.offset(&line_index, &analysis.file_text(file_id)?)?;
             let file_position = FilePosition { file_id, offset };
 
             if is_completion {
@@ -98,10 +146,171 @@ pub fn analysis_bench(
                 }
             }
         }
+        BenchWhat::Script { .. } => unreachable!(),
+    }
+    Ok(())
+}
+
+fn file_id(vfs: &Vfs, path: &AbsPathBuf) -> Result<FileId> {
+    let path = path.clone().into();
+    vfs.file_id(&path).ok_or_else(|| format_err!("Can't find {}", path))
+}
+
+/// A step of a typing simulation script. Scripts have one step per line, like
+/// `type src/lib.rs:10:4 foo.`, and lines starting with `#` are comments.
+/// Positions refer to the text as edited by the previous steps.
+#[derive(Debug, PartialEq, Eq)]
+enum ScriptStep {
+    /// `type PATH:LINE:COLUMN TEXT` inserts the text one character at a time,
+    /// and highlights the file after each keystroke, as editors do. `\n` in
+    /// the text stands for a newline.
+    Type(Position, String),
+    /// `delete PATH:LINE:COLUMN COUNT` deletes characters before the position
+    /// one at a time, like pressing backspace.
+    Delete(Position, usize),
+    /// `complete PATH:LINE:COLUMN` computes completions.
+    Complete(Position),
+    /// `hover PATH:LINE:COLUMN` computes hover information.
+    Hover(Position),
+}
+
+impl ScriptStep {
+    fn position(&self) -> &Position {
+        match self {
+            ScriptStep::Type(it, _)
+            | ScriptStep::Delete(it, _)
+            | ScriptStep::Complete(it)
+            | ScriptStep::Hover(it) => it,
+        }
+    }
+}
+
+/// Returns the steps of the script with their line numbers.
+fn parse_script(text: &str) -> Result<Vec<(usize, ScriptStep)>> {
+    let mut res = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.splitn(3, ' ');
+        let step = (|| -> Result<ScriptStep> {
+            let kind = parts.next().unwrap();
+            let position = parts.next().ok_or_else(|| format_err!("missing position"))?.parse()?;
+            let rest = parts.next();
+            let step = match (kind, rest) {
+                ("type", Some(text)) => ScriptStep::Type(position, text.replace("\\n", "\n")),
+                ("delete", Some(count)) => ScriptStep::Delete(position, count.trim().parse()?),
+                ("complete", None) => ScriptStep::Complete(position),
+                ("hover", None) => ScriptStep::Hover(position),
+                _ => bail!("expected `type`, `delete`, `complete` or `hover`"),
+            };
+            Ok(step)
+        })()
+        .map_err(|err| format_err!("line {}: {}", idx + 1, err))?;
+        res.push((idx + 1, step));
+    }
+    Ok(res)
+}
+
+fn run_script(
+    host: &mut AnalysisHost,
+    file_id: &dyn Fn(&AbsPathBuf) -> Result<FileId>,
+    steps: &[(usize, ScriptStep)],
+    iterations: usize,
+) -> Result<()> {
+    let at_line = |line: usize| move |err: anyhow::Error| format_err!("line {}: {}", line, err);
+
+    // Analyze the edited files up front, so that the first keystroke does not
+    // pay for analyzing the whole project.
+    let start = Instant::now();
+    eprint!("from scratch:   ");
+    for (line, step) in steps {
+        let file_id = file_id(&step.position().path).map_err(at_line(*line))?;
+        host.analysis().highlight(file_id)?;
+    }
+    eprintln!("{:?}\n", start.elapsed());
+
+    let mut original_texts = FxHashMap::default();
+    let mut typing = Vec::new();
+    let mut deleting = Vec::new();
+    let mut completion = Vec::new();
+    let mut hover = Vec::new();
+
+    for _ in 0..iterations {
+        for (line, step) in steps {
+            let position = step.position();
+            let file_id = file_id(&position.path).map_err(at_line(*line))?;
+            let mut text = host.analysis().file_text(file_id)?.to_string();
+            original_texts.entry(file_id).or_insert_with(|| text.clone());
+            let line_index = host.analysis().file_line_index(file_id)?;
+            let mut offset: usize =
+                position.offset(&line_index, &text).map_err(at_line(*line))?.into();
+            let file_position =
+                |offset: usize| FilePosition { file_id, offset: (offset as u32).into() };
+
+            match step {
+                ScriptStep::Type(_, chars) => {
+                    for c in chars.chars() {
+                        text.insert(offset, c);
+                        offset += c.len_utf8();
+                        typing.push(keystroke(host, file_id, &text)?);
+                    }
+                }
+                ScriptStep::Delete(_, count) => {
+                    for _ in 0..*count {
+                        let c = match text[..offset].chars().next_back() {
+                            Some(it) => it,
+                            None => break,
+                        };
+                        offset -= c.len_utf8();
+                        text.remove(offset);
+                        deleting.push(keystroke(host, file_id, &text)?);
+                    }
+                }
+                ScriptStep::Complete(_) => {
+                    let start = Instant::now();
+                    host.analysis()
+                        .completions(&CompletionConfig::default(), file_position(offset))?;
+                    completion.push(start.elapsed());
+                }
+                ScriptStep::Hover(_) => {
+                    let start = Instant::now();
+                    host.analysis().hover(file_position(offset))?;
+                    hover.push(start.elapsed());
+                }
+            }
+        }
+
+        let mut change = AnalysisChange::new();
+        for (file_id, text) in original_texts.iter() {
+            change.change_file(*file_id, Some(Arc::new(text.clone())));
+        }
+        host.apply_change(change);
+    }
+
+    for (name, latencies) in
+        vec![("type", typing), ("delete", deleting), ("complete", completion), ("hover", hover)]
+    {
+        if !latencies.is_empty() {
+            let n = latencies.len();
+            eprintln!("{:<9} {:>5} requests, {}", name, n, Latencies::new(latencies));
+        }
     }
     Ok(())
 }
 
+/// Applies an edit of a single keystroke and highlights the file, returning
+/// how long it took.
+fn keystroke(host: &mut AnalysisHost, file_id: FileId, text: &str) -> Result<Duration> {
+    let start = Instant::now();
+    let mut change = AnalysisChange::new();
+    change.change_file(file_id, Some(Arc::new(text.to_string())));
+    host.apply_change(change);
+    host.analysis().highlight(file_id)?;
+    Ok(start.elapsed())
+}
+
 fn do_work<F: Fn(&Analysis) -> T, T>(host: &mut AnalysisHost, file_id: FileId, work: F) -> T {
     {
         let start = Instant::now();
@@ -157,3 +366,62 @@ fn do_work<F: Fn(&Analysis) -> T, T>(host: &mut AnalysisHost, file_id: FileId, w
         res
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ra_ide::mock_analysis::MockAnalysis;
+
+    use super::*;
+
+    #[test]
+    fn parses_scripts() {
+        let script = r"
+# add a field access
+type src/lib.rs:3:4 foo.\n
+complete src/lib.rs:4:1
+delete src/lib.rs:4:1 4
+hover src/lib.rs:3:5
+";
+        let position = |line, column| Position {
+            path: AbsPathBuf::assert(env::current_dir().unwrap().join("src/lib.rs")),
+            line,
+            column,
+        };
+        assert_eq!(
+            parse_script(script).unwrap(),
+            vec![
+                (3, ScriptStep::Type(position(3, 4), "foo.\n".to_string())),
+                (4, ScriptStep::Complete(position(4, 1))),
+                (5, ScriptStep::Delete(position(4, 1), 4)),
+                (6, ScriptStep::Hover(position(3, 5))),
+            ]
+        );
+        let err = parse_script("type src/lib.rs:1:1 x\nscroll src/lib.rs:1:1").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: expected `type`, `delete`, `complete` or `hover`");
+    }
+
+    #[test]
+    fn script_positions_are_checked() {
+        let mut host = MockAnalysis::with_files("//- /lib.rs\nfn foo() {}\n").analysis_host();
+        let mut run = |script: &str| {
+            let steps = parse_script(script).unwrap();
+            run_script(&mut host, &|_| Ok(FileId(1)), &steps, 1).map_err(|err| err.to_string())
+        };
+
+        assert_eq!(run("type src/lib.rs:1:11 x\nhover src/lib.rs:1:12"), Ok(()));
+        assert_eq!(
+            run("hover src/lib.rs:1:1\n\ntype src/lib.rs:3:0 x"),
+            Err(format!(
+                "line 3: line 3 is out of range, {} has 2 lines",
+                env::current_dir().unwrap().join("src/lib.rs").display()
+            ))
+        );
+        assert_eq!(
+            run("delete src/lib.rs:1:12 1"),
+            Err(format!(
+                "line 1: column 12 is out of range, line 1 of {} has 11 columns",
+                env::current_dir().unwrap().join("src/lib.rs").display()
+            ))
+        );
+    }
+}
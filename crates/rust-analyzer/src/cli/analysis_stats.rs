@@ -1,12 +1,16 @@
 //! Fully type-check project and print various stats, like the number of type
 //! errors.
 
-use std::{path::Path, time::Instant};
+use std::{
+    cmp, fmt,
+    path::Path,
+    time::{Duration, Instant},
+};
 
 use itertools::Itertools;
 use rand::{seq::SliceRandom, thread_rng};
 use rayon::prelude::*;
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use hir::{
     db::{AstDatabase, DefDatabase, HirDatabase},
@@ -18,6 +22,7 @@ use ra_db::{
     salsa::{self, ParallelDatabase},
     SourceDatabaseExt,
 };
+use ra_prof::Bytes;
 use ra_syntax::AstNode;
 use stdx::format_to;
 
@@ -31,6 +36,50 @@ impl<DB: ParallelDatabase> Clone for Snap<salsa::Snapshot<DB>> {
     }
 }
 
+/// Memory allocated while analyzing a crate. Data shared between crates, like
+/// the def maps of common dependencies, is attributed to the first crate
+/// which needs it.
+#[derive(Default)]
+struct CrateMemory {
+    items: Bytes,
+    inference: Bytes,
+}
+
+/// How long individual operations took, like inferring each function.
+pub(super) struct Latencies(Vec<Duration>);
+
+impl Latencies {
+    pub(super) fn new(mut latencies: Vec<Duration>) -> Latencies {
+        latencies.sort();
+        Latencies(latencies)
+    }
+
+    fn percentile(&self, p: usize) -> Duration {
+        if self.0.is_empty() {
+            return Duration::default();
+        }
+        self.0[(self.0.len() - 1) * p / 100]
+    }
+}
+
+impl fmt::Display for Latencies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+            self.percentile(50),
+            self.percentile(90),
+            self.percentile(99),
+            self.percentile(100)
+        )
+    }
+}
+
+fn allocated_since(before: Bytes) -> Bytes {
+    // Memory can also be freed, e.g. by LRU eviction.
+    cmp::max(ra_prof::memory_usage().allocated, before) - before
+}
+
 pub fn analysis_stats(
     verbosity: Verbosity,
     memory_usage: bool,
@@ -47,53 +96,59 @@ pub fn analysis_stats(
     let db = host.raw_database();
     println!("Database loaded {:?}", db_load_time.elapsed());
     let analysis_time = Instant::now();
-    let mut num_crates = 0;
     let mut visited_modules = FxHashSet::default();
-    let mut visit_queue = Vec::new();
+    let mut crates = Vec::new();
 
     let mut krates = Crate::all(db);
     if randomize {
         krates.shuffle(&mut thread_rng());
     }
     for krate in krates {
-        let module = krate.root_module(db).expect("crate without root module");
-        let file_id = module.definition_source(db).file_id;
-        let file_id = file_id.original_file(db);
-        let source_root = db.file_source_root(file_id);
+        // Don't compute the def map yet, so that its memory is attributed to
+        // the crate below.
+        let source_root = db.file_source_root(krate.root_file(db));
         let source_root = db.source_root(source_root);
         if !source_root.is_library || with_deps {
-            num_crates += 1;
-            visit_queue.push(module);
+            crates.push(krate);
         }
     }
 
     if randomize {
-        visit_queue.shuffle(&mut thread_rng());
+        crates.shuffle(&mut thread_rng());
     }
 
-    println!("Crates in this dir: {}", num_crates);
+    println!("Crates in this dir: {}", crates.len());
+    let mut crate_memory: FxHashMap<Crate, CrateMemory> = FxHashMap::default();
     let mut num_decls = 0;
     let mut funcs = Vec::new();
-    while let Some(module) = visit_queue.pop() {
-        if visited_modules.insert(module) {
-            visit_queue.extend(module.children(db));
+    for krate in crates {
+        let before = ra_prof::memory_usage().allocated;
+        let crate_root = krate.root_module(db).expect("crate without root module");
+        let mut visit_queue = vec![crate_root];
+        while let Some(module) = visit_queue.pop() {
+            if visited_modules.insert(module) {
+                visit_queue.extend(module.children(db));
 
-            for decl in module.declarations(db) {
-                num_decls += 1;
-                if let ModuleDef::Function(f) = decl {
-                    funcs.push(f);
-                }
-            }
-
-            for impl_def in module.impl_defs(db) {
-                for item in impl_def.items(db) {
+                for decl in module.declarations(db) {
                     num_decls += 1;
-                    if let AssocItem::Function(f) = item {
+                    if let ModuleDef::Function(f) = decl {
                         funcs.push(f);
                     }
                 }
+
+                for impl_def in module.impl_defs(db) {
+                    for item in impl_def.items(db) {
+                        num_decls += 1;
+                        if let AssocItem::Function(f) = item {
+                            funcs.push(f);
+                        }
+                    }
+                }
             }
         }
+        if memory_usage {
+            crate_memory.entry(krate).or_default().items = allocated_since(before);
+        }
     }
     println!("Total modules found: {}", visited_modules.len());
     println!("Total declarations: {}", num_decls);
@@ -112,15 +167,23 @@ pub fn analysis_stats(
     if parallel {
         let inference_time = Instant::now();
         let snap = Snap(db.snapshot());
-        funcs
+        let latencies = funcs
             .par_iter()
             .map_with(snap, |snap, &f| {
+                let start = Instant::now();
                 let f_id = FunctionId::from(f);
                 snap.0.body(f_id.into());
                 snap.0.infer(f_id.into());
+                start.elapsed()
             })
-            .count();
-        println!("Parallel Inference: {:?}, {}", inference_time.elapsed(), ra_prof::memory_usage());
+            .collect();
+        println!(
+            "Parallel Inference: {:?} on {} threads, {}",
+            inference_time.elapsed(),
+            rayon::current_num_threads(),
+            ra_prof::memory_usage()
+        );
+        println!("Parallel Inference latency: {}", Latencies::new(latencies));
     }
 
     let inference_time = Instant::now();
@@ -129,6 +192,15 @@ pub fn analysis_stats(
     let mut num_exprs_unknown = 0;
     let mut num_exprs_partially_unknown = 0;
     let mut num_type_mismatches = 0;
+    let mut latencies: Vec<(Duration, String)> = Vec::new();
+    // Measuring memory is slow, so we only do it when the crate changes.
+    let mut current_crate: Option<(Crate, Bytes)> = None;
+    let mut flush_inference_memory = |current_crate: Option<(Crate, Bytes)>| {
+        if let Some((krate, before)) = current_crate {
+            let mem = crate_memory.entry(krate).or_default();
+            mem.inference = mem.inference + allocated_since(before);
+        }
+    };
     for f in funcs {
         let name = f.name(db);
         let full_name = f
@@ -156,9 +228,19 @@ pub fn analysis_stats(
             bar.println(msg.to_string());
         }
         bar.set_message(&msg);
+        if memory_usage {
+            let krate = f.module(db).krate();
+            if current_crate.map(|(it, _)| it) != Some(krate) {
+                flush_inference_memory(current_crate);
+                current_crate = Some((krate, ra_prof::memory_usage().allocated));
+            }
+        }
+        let start = Instant::now();
         let f_id = FunctionId::from(f);
         let body = db.body(f_id.into());
         let inference_result = db.infer(f_id.into());
+        let crate_name = f.module(db).krate().display_name(db).unwrap_or_default();
+        latencies.push((start.elapsed(), format!("{}::{}", crate_name, full_name)));
         let (previous_exprs, previous_unknown, previous_partially_unknown) =
             (num_exprs, num_exprs_unknown, num_exprs_partially_unknown);
         for (expr_id, _) in body.exprs.iter() {
@@ -256,6 +338,7 @@ pub fn analysis_stats(
         }
         bar.inc(1);
     }
+    flush_inference_memory(current_crate);
     bar.finish_and_clear();
     println!("Total expressions: {}", num_exprs);
     println!(
@@ -270,9 +353,33 @@ pub fn analysis_stats(
     );
     println!("Type mismatches: {}", num_type_mismatches);
     println!("Inference: {:?}, {}", inference_time.elapsed(), ra_prof::memory_usage());
+    latencies.sort_by_key(|(latency, _)| cmp::Reverse(*latency));
+    let slowest = latencies
+        .iter()
+        .take(5)
+        .map(|(latency, name)| format!("{} ({:?})", name, latency))
+        .join(", ");
+    println!(
+        "Inference latency: {}",
+        Latencies::new(latencies.iter().map(|(latency, _)| *latency).collect())
+    );
+    println!("Slowest functions: {}", slowest);
     println!("Total: {:?}, {}", analysis_time.elapsed(), ra_prof::memory_usage());
 
     if memory_usage {
+        let mut crate_memory = crate_memory
+            .into_iter()
+            .map(|(krate, mem)| {
+                let name = krate.display_name(db).unwrap_or_else(|| format!("{:?}", krate));
+                (name, mem)
+            })
+            .collect::<Vec<_>>();
+        crate_memory.sort_by_key(|(_, mem)| cmp::Reverse(mem.items + mem.inference));
+        println!("Memory usage per crate (items, inference):");
+        for (name, mem) in crate_memory {
+            println!("{:>8} {:>8} {}", mem.items, mem.inference, name)
+        }
+
         let mut mem = host.per_query_memory_usage();
 
         let before = ra_prof::memory_usage();
@@ -286,7 +393,14 @@ pub fn analysis_stats(
 
         mem.push(("Remaining".into(), ra_prof::memory_usage().allocated));
 
-        for (name, bytes) in mem {
+        let (groups, queries): (Vec<_>, Vec<_>) =
+            mem.into_iter().partition(|(name, _)| name.ends_with(" (total)"));
+        println!("Memory usage per query group:");
+        for (name, bytes) in groups {
+            println!("{:>8} {}", bytes, name.trim_end_matches(" (total)"))
+        }
+        println!("Memory usage per query:");
+        for (name, bytes) in queries {
             println!("{:>8} {}", bytes, name)
         }
     }
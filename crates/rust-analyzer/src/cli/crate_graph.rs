@@ -0,0 +1,48 @@
+//! Prints the crate graph of a project in the GraphViz DOT format, or as an
+//! SVG image rendered by `dot`.
+
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context};
+use ra_project_model::{CargoConfig, ProjectManifest, ProjectWorkspace};
+use rustc_hash::FxHashSet;
+use vfs::AbsPathBuf;
+
+use crate::{
+    cli::{load_cargo::load_workspace, Result},
+    global_state::sysroot_crate_roots,
+};
+
+pub fn crate_graph(path: &Path, exclude_sysroot: bool, svg: bool) -> Result<()> {
+    let root = AbsPathBuf::assert(std::env::current_dir()?.join(path));
+    let root = ProjectManifest::discover_single(&root)?;
+    let ws = ProjectWorkspace::load(root, &CargoConfig::default(), true)?;
+    let workspaces = [ws.clone()];
+    let (host, vfs) = load_workspace(ws, false)?;
+
+    let excluded_roots =
+        if exclude_sysroot { sysroot_crate_roots(&workspaces, &vfs) } else { FxHashSet::default() };
+    let dot = host.analysis().view_crate_graph(excluded_roots)?;
+    if !svg {
+        print!("{}", dot);
+        return Ok(());
+    }
+
+    let mut child = Command::new("dot")
+        .arg("-Tsvg")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("failed to run `dot`, is graphviz installed?")?;
+    child.stdin.take().unwrap().write_all(dot.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("`dot` failed with {}", output.status);
+    }
+    std::io::stdout().write_all(&output.stdout)?;
+    Ok(())
+}
@@ -3,69 +3,135 @@
 
 use std::path::Path;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
+use ra_db::SourceDatabaseExt;
+use ra_ide::{LineCol, Severity, DIAGNOSTIC_NAMES};
 use rustc_hash::FxHashSet;
 
-use hir::Crate;
-use ra_db::SourceDatabaseExt;
-use ra_ide::Severity;
+use crate::cli::{load_cargo::load_cargo, module_files, Result};
+
+/// Selects the reported diagnostics by their names, like `missing-match-arm`.
+#[derive(Debug, Default)]
+pub struct DiagnosticsFilter {
+    /// If not empty, only these diagnostics are reported.
+    enable: Vec<String>,
+    disable: Vec<String>,
+}
+
+impl DiagnosticsFilter {
+    /// Fails if one of the names is not the name of a diagnostic, so that
+    /// typos don't silently disable the checks.
+    pub fn new(enable: Vec<String>, disable: Vec<String>) -> Result<DiagnosticsFilter> {
+        for name in enable.iter().chain(disable.iter()) {
+            if !DIAGNOSTIC_NAMES.contains(&name.as_str()) {
+                bail!(
+                    "unknown diagnostic `{}`, expected one of {}",
+                    name,
+                    DIAGNOSTIC_NAMES.join(", ")
+                );
+            }
+        }
+        Ok(DiagnosticsFilter { enable, disable })
+    }
 
-use crate::cli::{load_cargo::load_cargo, Result};
+    fn allows(&self, name: &str) -> bool {
+        (self.enable.is_empty() || self.enable.iter().any(|it| it == name))
+            && !self.disable.iter().any(|it| it == name)
+    }
+}
 
 pub fn diagnostics(
     path: &Path,
     load_output_dirs: bool,
     with_proc_macro: bool,
-    _all: bool,
+    all: bool,
+    with_deps: bool,
+    filter: &DiagnosticsFilter,
 ) -> Result<()> {
-    let (host, _vfs) = load_cargo(path, load_output_dirs, with_proc_macro)?;
+    let (host, vfs) = load_cargo(path, load_output_dirs, with_proc_macro)?;
     let db = host.raw_database();
     let analysis = host.analysis();
 
-    let mut found_error = false;
-    let mut visited_files = FxHashSet::default();
-
-    let mut work = Vec::new();
-    let krates = Crate::all(db);
-    for krate in krates {
-        let module = krate.root_module(db).expect("crate without root module");
-        let file_id = module.definition_source(db).file_id;
-        let file_id = file_id.original_file(db);
-        let source_root = db.file_source_root(file_id);
-        let source_root = db.source_root(source_root);
-        if !source_root.is_library {
-            work.push(module);
+    let mut files = module_files(db, with_deps);
+    if all {
+        // Also the files which are not part of the module tree, like the
+        // parser test data of rust-analyzer.
+        let mut visited = files.iter().copied().collect::<FxHashSet<_>>();
+        for (file_id, _) in vfs.iter() {
+            let is_library = db.source_root(db.file_source_root(file_id)).is_library;
+            if (!is_library || with_deps) && visited.insert(file_id) {
+                files.push(file_id);
+            }
         }
     }
 
-    for module in work {
-        let file_id = module.definition_source(db).file_id.original_file(db);
-        if !visited_files.contains(&file_id) {
-            let crate_name = if let Some(name) = module.krate().display_name(db) {
-                format!("{}", name)
-            } else {
-                String::from("unknown")
-            };
-            println!("processing crate: {}, module: {}", crate_name, _vfs.file_path(file_id));
-            for diagnostic in analysis.diagnostics(file_id).unwrap() {
-                if matches!(diagnostic.severity, Severity::Error) {
-                    found_error = true;
+    let mut errors = 0;
+    let mut warnings = 0;
+    for file_id in files {
+        let line_index = analysis.file_line_index(file_id)?;
+        let mut diagnostics = analysis.diagnostics(file_id)?;
+        diagnostics.retain(|it| filter.allows(it.name));
+        diagnostics.sort_by_key(|it| it.range.start());
+        for diagnostic in diagnostics {
+            let severity = match diagnostic.severity {
+                Severity::Error => {
+                    errors += 1;
+                    "error"
                 }
-
-                println!("{:?}", diagnostic);
-            }
-
-            visited_files.insert(file_id);
+                Severity::WeakWarning => {
+                    warnings += 1;
+                    "warning"
+                }
+            };
+            let LineCol { line, col_utf16 } = line_index.line_col(diagnostic.range.start());
+            println!(
+                "{}:{}:{}: {}[{}]: {}",
+                vfs.file_path(file_id),
+                line + 1,
+                col_utf16 + 1,
+                severity,
+                diagnostic.name,
+                diagnostic.message
+            );
         }
     }
 
     println!();
-    println!("diagnostic scan complete");
+    println!("diagnostic scan complete: {} errors, {} warnings", errors, warnings);
 
-    if found_error {
-        println!();
+    if errors > 0 {
         Err(anyhow!("diagnostic error detected"))
     } else {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|it| it.to_string()).collect()
+    }
+
+    #[test]
+    fn filter_selects_diagnostics_by_name() {
+        let filter = DiagnosticsFilter::default();
+        assert!(filter.allows("missing-match-arm"));
+
+        let filter = DiagnosticsFilter::new(names(&["missing-match-arm"]), Vec::new()).unwrap();
+        assert!(filter.allows("missing-match-arm"));
+        assert!(!filter.allows("missing-unsafe"));
+
+        let filter = DiagnosticsFilter::new(Vec::new(), names(&["missing-match-arm"])).unwrap();
+        assert!(!filter.allows("missing-match-arm"));
+        assert!(filter.allows("missing-unsafe"));
+    }
+
+    #[test]
+    fn filter_rejects_unknown_names() {
+        let err = DiagnosticsFilter::new(names(&["missing-match-arms"]), Vec::new()).unwrap_err();
+        assert!(err.to_string().starts_with("unknown diagnostic `missing-match-arms`"), "{}", err);
+        assert!(DiagnosticsFilter::new(Vec::new(), names(&["unused-brace"])).is_err());
+    }
+}
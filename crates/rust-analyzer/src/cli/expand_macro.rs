@@ -0,0 +1,223 @@
+//! Prints recursive macro expansions, like the "Expand macro recursively"
+//! editor command.
+
+use std::path::Path;
+
+use anyhow::{bail, format_err};
+use hir::Semantics;
+use ra_db::FileId;
+use ra_ide::{Analysis, AnalysisHost, ExpandedMacro, FilePosition};
+use ra_ide_db::symbol_index::{self, Query};
+use ra_syntax::{ast, AstNode, TextSize};
+use stdx::format_to;
+use vfs::AbsPathBuf;
+
+use crate::cli::{
+    load_cargo::load_cargo,
+    symbols::{module_path, parent_names},
+    Position, Result,
+};
+
+pub enum ExpandWhat {
+    /// The macro call at a line and column.
+    Position(Position),
+    /// The macro call at a byte offset.
+    Offset { path: AbsPathBuf, offset: u32 },
+    /// All macro calls in an item, given as a path like `krate::module::Type::method`.
+    /// The crate name can be omitted.
+    Item(String),
+}
+
+pub fn expand_macro(
+    path: &Path,
+    what: ExpandWhat,
+    load_output_dirs: bool,
+    with_proc_macro: bool,
+) -> Result<()> {
+    let (host, vfs) = load_cargo(path, load_output_dirs, with_proc_macro)?;
+    let file_id = |path: &AbsPathBuf| {
+        let path = path.clone().into();
+        vfs.file_id(&path).ok_or_else(|| format_err!("Can't find {}", path))
+    };
+    print!("{}", expand(&host, &file_id, &what)?);
+    Ok(())
+}
+
+/// Returns the expansions of the macro calls selected by `what`.
+fn expand(
+    host: &AnalysisHost,
+    file_id: &dyn Fn(&AbsPathBuf) -> Result<FileId>,
+    what: &ExpandWhat,
+) -> Result<String> {
+    let analysis = host.analysis();
+    let positions = match what {
+        ExpandWhat::Position(pos) => {
+            let file_id = file_id(&pos.path)?;
+            let line_index = analysis.file_line_index(file_id)?;
+            let offset = pos.offset(&line_index, &analysis.file_text(file_id)?)?;
+            vec![FilePosition { file_id, offset }]
+        }
+        ExpandWhat::Offset { path, offset } => {
+            let file_id = file_id(path)?;
+            let len = analysis.file_text(file_id)?.len();
+            if *offset as usize > len {
+                bail!("offset {} is out of range, {} has {} bytes", offset, path.display(), len);
+            }
+            vec![FilePosition { file_id, offset: TextSize::from(*offset) }]
+        }
+        ExpandWhat::Item(item) => item_macro_calls(host, item)?,
+    };
+
+    let mut res = String::new();
+    for position in positions {
+        if let Some(it) = analysis.expand_macro(position)? {
+            if !res.is_empty() {
+                res.push('\n');
+            }
+            format_expansion(&mut res, &analysis, position, &it)?;
+        }
+    }
+    if res.is_empty() {
+        bail!("no macro call to expand");
+    }
+    Ok(res)
+}
+
+fn format_expansion(
+    buf: &mut String,
+    analysis: &Analysis,
+    position: FilePosition,
+    it: &ExpandedMacro,
+) -> Result<()> {
+    let line_col = analysis.file_line_index(position.file_id)?.line_col(position.offset);
+    let header = format!("// Recursive expansion of {}! macro", it.name);
+    format_to!(buf, "{}\n", header);
+    format_to!(buf, "// at line {}, column {}\n", line_col.line + 1, line_col.col_utf16 + 1);
+    format_to!(buf, "// {}\n\n", "=".repeat(header.len() - 3));
+    format_to!(buf, "{}\n", it.expansion);
+    Ok(())
+}
+
+/// Returns the positions of the macro calls written in the item at
+/// `item_path`.
+fn item_macro_calls(host: &AnalysisHost, item_path: &str) -> Result<Vec<FilePosition>> {
+    let db = host.raw_database();
+    let sema = Semantics::new(db);
+    let mut segments: Vec<&str> = item_path.split("::").collect();
+    let name = segments.pop().unwrap();
+
+    let mut query = Query::new(name.to_string());
+    query.exact();
+    query.case_sensitive();
+    let is_item_path =
+        |path: &[String]| path.iter().map(String::as_str).eq(segments.iter().copied());
+
+    let mut res = Vec::new();
+    let mut found = false;
+    for symbol in symbol_index::world_symbols(db, query) {
+        let root = sema.parse(symbol.file_id).syntax().clone();
+        let node = symbol.ptr.to_node(&root);
+        let mut container = module_path(&sema, symbol.file_id);
+        container.extend(parent_names(&node));
+        // Without the crate name, the path starts at the crate root.
+        if !is_item_path(&container) && !is_item_path(container.get(1..).unwrap_or_default()) {
+            continue;
+        }
+        found = true;
+        let calls = node.descendants().filter_map(ast::MacroCall::cast);
+        for call in calls.filter(|it| it.is_macro_rules().is_none()) {
+            let name_ref = call.path().and_then(|it| it.segment()).and_then(|it| it.name_ref());
+            if let Some(name_ref) = name_ref {
+                let offset = name_ref.syntax().text_range().start();
+                res.push(FilePosition { file_id: symbol.file_id, offset });
+            }
+        }
+    }
+    if !found {
+        bail!("can't find item `{}`", item_path);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use expect::{expect, Expect};
+    use ra_ide::mock_analysis::MockAnalysis;
+
+    use super::*;
+
+    const FIXTURE: &str = r#"
+//- /main.rs
+macro_rules! foo {
+    () => { m::f() }
+}
+fn main() {
+    foo!();
+}
+mod m {
+    pub fn f() { foo!(); }
+}
+"#;
+
+    fn path() -> AbsPathBuf {
+        AbsPathBuf::assert("/main.rs".into())
+    }
+
+    fn expand_in_fixture(what: ExpandWhat) -> Result<String> {
+        let host = MockAnalysis::with_files(FIXTURE).analysis_host();
+        expand(&host, &|_| Ok(FileId(1)), &what)
+    }
+
+    fn check(what: ExpandWhat, expect: Expect) {
+        let res = expand_in_fixture(what).unwrap_or_else(|err| err.to_string());
+        expect.assert_eq(&res);
+    }
+
+    #[test]
+    fn expands_call_at_position() {
+        check(
+            ExpandWhat::Position(Position { path: path(), line: 5, column: 5 }),
+            expect![[r#"
+                // Recursive expansion of foo! macro
+                // at line 5, column 6
+                // =================================
+
+                m::f()
+            "#]],
+        );
+    }
+
+    #[test]
+    fn expands_calls_in_item() {
+        check(
+            ExpandWhat::Item("m::f".to_string()),
+            expect![[r#"
+                // Recursive expansion of foo! macro
+                // at line 8, column 18
+                // =================================
+
+                m::f()
+            "#]],
+        );
+    }
+
+    #[test]
+    fn rejects_positions_out_of_range() {
+        check(
+            ExpandWhat::Position(Position { path: path(), line: 0, column: 0 }),
+            expect![["line 0 is out of range, /main.rs has 10 lines"]],
+        );
+        check(
+            ExpandWhat::Position(Position { path: path(), line: 11, column: 0 }),
+            expect![["line 11 is out of range, /main.rs has 10 lines"]],
+        );
+        check(
+            ExpandWhat::Position(Position { path: path(), line: 5, column: 12 }),
+            expect![["column 12 is out of range, line 5 of /main.rs has 11 columns"]],
+        );
+        check(
+            ExpandWhat::Offset { path: path(), offset: 1000 },
+            expect![[r#"offset 1000 is out of range, /main.rs has 105 bytes"#]],
+        );
+    }
+}
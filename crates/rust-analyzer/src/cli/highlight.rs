@@ -0,0 +1,89 @@
+//! Renders the files of a project with semantic highlighting as standalone
+//! HTML pages.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, format_err};
+use ra_ide::FileId;
+use rustc_hash::FxHashMap;
+use vfs::AbsPathBuf;
+
+use crate::cli::{load_cargo::load_cargo, module_files, Result};
+
+/// Renders `path` if it is a file, or all files of the workspace crates if it
+/// is a directory. Single files are printed to stdout, unless `output` is set,
+/// while workspaces are rendered to the `output` directory, with an
+/// `index.html` and links from references to their definitions.
+pub fn highlight_project(
+    path: &Path,
+    output: Option<&Path>,
+    rainbow: bool,
+    load_output_dirs: bool,
+    with_proc_macro: bool,
+) -> Result<()> {
+    let path = env::current_dir()?.join(path).canonicalize()?;
+    let (host, vfs) = load_cargo(&path, load_output_dirs, with_proc_macro)?;
+    let analysis = host.analysis();
+
+    if path.is_file() {
+        let vfs_path = AbsPathBuf::assert(path.clone()).into();
+        let file_id =
+            vfs.file_id(&vfs_path).ok_or_else(|| format_err!("Can't find {}", vfs_path))?;
+        let mut links = FxHashMap::default();
+        links.insert(file_id, String::new());
+        let html = analysis.highlight_as_html_page(file_id, rainbow, &links)?;
+        match output {
+            Some(output) => fs::write(output, html)?,
+            None => print!("{}", html),
+        }
+        return Ok(());
+    }
+
+    let output = match output {
+        Some(it) => it,
+        None => bail!("`--output` must be set to highlight a whole project"),
+    };
+    // The pages mirror the layout of the source files in the project.
+    let pages: Vec<(FileId, String)> = module_files(host.raw_database(), false)
+        .into_iter()
+        .filter_map(|file_id| {
+            let file = vfs.file_path(file_id);
+            let relative = file.as_path()?.strip_prefix(&AbsPathBuf::assert(path.clone()))?;
+            let relative: &Path = relative.as_ref();
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            Some((file_id, format!("{}.html", relative)))
+        })
+        .collect();
+
+    for (file_id, page) in pages.iter() {
+        let up = "../".repeat(page.matches('/').count());
+        let links = pages.iter().map(|(file_id, it)| (*file_id, format!("{}{}", up, it))).collect();
+        let html = analysis.highlight_as_html_page(*file_id, rainbow, &links)?;
+        let page_path: PathBuf = output.join(page);
+        fs::create_dir_all(page_path.parent().unwrap())?;
+        fs::write(&page_path, html)?;
+    }
+
+    let mut index = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    index.push_str("</head>\n<body>\n<ul>\n");
+    for (_, page) in pages.iter() {
+        let name = page.trim_end_matches(".html");
+        index.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            html_escape(page).replace('"', "&quot;"),
+            html_escape(name)
+        ));
+    }
+    index.push_str("</ul>\n</body>\n</html>\n");
+    fs::create_dir_all(output)?;
+    fs::write(output.join("index.html"), index)?;
+    eprintln!("{} files highlighted to {}", pages.len(), output.display());
+    Ok(())
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
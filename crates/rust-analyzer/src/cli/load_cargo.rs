@@ -23,7 +23,14 @@ pub fn load_cargo(
         &CargoConfig { load_out_dirs_from_check, ..Default::default() },
         true,
     )?;
+    load_workspace(ws, with_proc_macro)
+}
 
+/// Same as `load_cargo`, but for an already loaded workspace.
+pub fn load_workspace(
+    ws: ProjectWorkspace,
+    with_proc_macro: bool,
+) -> Result<(AnalysisHost, vfs::Vfs)> {
     let (sender, receiver) = unbounded();
     let mut vfs = vfs::Vfs::default();
     let mut loader = {
@@ -39,14 +46,15 @@ pub fn load_cargo(
         ProcMacroClient::dummy()
     };
 
-    let crate_graph = ws.to_crate_graph(None, &proc_macro_client, &mut |path: &AbsPath| {
-        let contents = loader.load_sync(path);
-        let path = vfs::VfsPath::from(path.to_path_buf());
-        vfs.set_file_contents(path.clone(), contents);
-        vfs.file_id(&path)
-    });
+    let crate_graph =
+        ws.to_crate_graph(None, &Default::default(), &proc_macro_client, &mut |path: &AbsPath| {
+            let contents = loader.load_sync(path);
+            let path = vfs::VfsPath::from(path.to_path_buf());
+            vfs.set_file_contents(path.clone(), contents);
+            vfs.file_id(&path)
+        });
 
-    let project_folders = ProjectFolders::new(&[ws]);
+    let project_folders = ProjectFolders::new(&[ws], &[]);
     loader.set_config(vfs::loader::Config { load: project_folders.load, watch: vec![] });
 
     log::debug!("crate graph: {:?}", crate_graph);
@@ -0,0 +1,499 @@
+//! Emits an [LSIF] dump of the workspace: the definitions, references and hover
+//! texts of all names, plus monikers which identify public items across
+//! workspaces.
+//!
+//! [LSIF]: https://microsoft.github.io/language-server-protocol/specifications/lsif/0.5.0/specification/
+
+use std::{
+    collections::hash_map,
+    env,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use hir::{AsAssocItem, AssocItemContainer, HirDisplay, ModuleDef, Semantics, Visibility};
+use lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position, Url};
+use ra_db::SourceDatabaseExt;
+use ra_ide::{AnalysisHost, FileId, FilePosition, LineIndex, RootDatabase};
+use ra_ide_db::defs::{classify_name, classify_name_ref, Definition};
+use ra_syntax::{ast, AstNode, SyntaxKind::IDENT, SyntaxToken, TextRange};
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+use vfs::AbsPathBuf;
+
+use crate::cli::{load_cargo::load_cargo, module_files, Result};
+
+pub fn lsif(path: &Path, load_output_dirs: bool, with_proc_macro: bool) -> Result<()> {
+    let (host, vfs) = load_cargo(path, load_output_dirs, with_proc_macro)?;
+    let project_root = env::current_dir()?.join(path).canonicalize()?;
+    let files = module_files(host.raw_database(), false)
+        .into_iter()
+        .filter_map(|file_id| Some((file_id, vfs.file_path(file_id).as_path()?.to_path_buf())))
+        .collect::<Vec<_>>();
+
+    let stdout = io::stdout();
+    let mut lsif = LsifWriter { next_id: 0, out: BufWriter::new(stdout.lock()) };
+    emit(&host, &project_root, &files, &mut lsif)?;
+    lsif.out.flush()?;
+    Ok(())
+}
+
+/// Emits the dump of the given files, which are also emitted as documents.
+fn emit(
+    host: &AnalysisHost,
+    project_root: &Path,
+    files: &[(FileId, AbsPathBuf)],
+    lsif: &mut LsifWriter<impl Write>,
+) -> Result<()> {
+    let db = host.raw_database();
+    let analysis = host.analysis();
+    let sema = Semantics::new(db);
+
+    lsif.vertex(Vertex::MetaData {
+        version: "0.5.0",
+        position_encoding: "utf-16",
+        tool_info: ToolInfo { name: "rust-analyzer", version: env!("REV") },
+        project_root: url(project_root),
+    })?;
+    let project = lsif.vertex(Vertex::Project { kind: "rust" })?;
+
+    // Symbols are identified by the location of their definition.
+    let mut symbols: FxHashMap<(FileId, TextRange), Symbol> = FxHashMap::default();
+    let mut symbol_order = Vec::new();
+    let mut documents = Vec::new();
+    for &(file_id, ref path) in files {
+        let document = lsif.vertex(Vertex::Document { uri: url(path), language_id: "rust" })?;
+        documents.push(document);
+        let line_index = analysis.file_line_index(file_id)?;
+
+        let mut ranges = Vec::new();
+        let file = sema.parse(file_id);
+        let tokens = file.syntax().descendants_with_tokens().filter_map(|it| it.into_token());
+        for token in tokens.filter(|it| it.kind() == IDENT) {
+            let range = token.text_range();
+            let position = FilePosition { file_id, offset: range.start() };
+            let nav =
+                match analysis.goto_definition(position)?.and_then(|it| it.info.into_iter().next())
+                {
+                    Some(it) => it,
+                    None => continue,
+                };
+            let key = (nav.file_id(), nav.range());
+
+            let range_id = lsif.vertex(to_range(&line_index, range))?;
+            ranges.push(range_id);
+            let symbol = match symbols.entry(key) {
+                hash_map::Entry::Occupied(it) => it.into_mut(),
+                hash_map::Entry::Vacant(it) => {
+                    let result_set = lsif.vertex(Vertex::ResultSet)?;
+                    if let Some(hover) = analysis.hover(position)? {
+                        let contents = HoverContents::Markup(MarkupContent {
+                            kind: MarkupKind::Markdown,
+                            value: hover.info.markup.as_str().to_string(),
+                        });
+                        let hover = Hover { contents, range: None };
+                        let hover_result = lsif.vertex(Vertex::HoverResult { result: hover })?;
+                        lsif.edge(Edge::Hover(EdgeData { out_v: result_set, in_v: hover_result }))?;
+                    }
+                    if let Some(moniker) = moniker(&sema, &token) {
+                        let moniker = lsif.vertex(moniker)?;
+                        lsif.edge(Edge::Moniker(EdgeData { out_v: result_set, in_v: moniker }))?;
+                    }
+                    symbol_order.push(key);
+                    it.insert(Symbol { result_set, definition: None, references: Vec::new() })
+                }
+            };
+            lsif.edge(Edge::Next(EdgeData { out_v: range_id, in_v: symbol.result_set }))?;
+            if key == (file_id, range) {
+                symbol.definition = Some((document, range_id));
+            }
+            symbol.references.push((document, range_id));
+        }
+        if !ranges.is_empty() {
+            lsif.edge(Edge::Contains(EdgeDataMultiIn { out_v: document, in_vs: ranges }))?;
+        }
+    }
+    lsif.edge(Edge::Contains(EdgeDataMultiIn { out_v: project, in_vs: documents }))?;
+
+    for key in symbol_order {
+        let symbol = &symbols[&key];
+        let references = lsif.vertex(Vertex::ReferenceResult)?;
+        lsif.edge(Edge::References(EdgeData { out_v: symbol.result_set, in_v: references }))?;
+        if let Some((document, range)) = symbol.definition {
+            let definitions = lsif.vertex(Vertex::DefinitionResult)?;
+            lsif.edge(Edge::Definition(EdgeData { out_v: symbol.result_set, in_v: definitions }))?;
+            lsif.edge(Edge::Item(Item {
+                out_v: definitions,
+                in_vs: vec![range],
+                document,
+                property: None,
+            }))?;
+            lsif.edge(Edge::Item(Item {
+                out_v: references,
+                in_vs: vec![range],
+                document,
+                property: Some(ItemKind::Definitions),
+            }))?;
+        }
+        let mut by_document: Vec<(Id, Vec<Id>)> = Vec::new();
+        for &(document, range) in symbol.references.iter() {
+            if symbol.definition.map(|(_, it)| it) == Some(range) {
+                continue;
+            }
+            match by_document.last_mut() {
+                Some((it, ranges)) if *it == document => ranges.push(range),
+                _ => by_document.push((document, vec![range])),
+            }
+        }
+        for (document, in_vs) in by_document {
+            lsif.edge(Edge::Item(Item {
+                out_v: references,
+                in_vs,
+                document,
+                property: Some(ItemKind::References),
+            }))?;
+        }
+    }
+    Ok(())
+}
+
+struct Symbol {
+    result_set: Id,
+    /// The document and the range of the definition, if it is in the workspace.
+    definition: Option<(Id, Id)>,
+    references: Vec<(Id, Id)>,
+}
+
+/// Returns the moniker of the public item `token` refers to. The identifier is
+/// the path of the item from the root of its crate, which stays the same when
+/// the crate is indexed in another workspace.
+fn moniker(sema: &Semantics<RootDatabase>, token: &SyntaxToken) -> Option<Vertex> {
+    let db = sema.db;
+    let token = sema.descend_into_macros(token.clone());
+    let parent = token.parent();
+    let def = if let Some(name) = ast::Name::cast(parent.clone()) {
+        classify_name(sema, &name)?.definition()
+    } else {
+        classify_name_ref(sema, &ast::NameRef::cast(parent)?)?.definition()
+    };
+    // FIXME: this should check that the item is reachable from the crate root
+    if !matches!(def, Definition::ModuleDef(_) | Definition::Field(_))
+        || def.visibility(db) != Some(Visibility::Public)
+    {
+        return None;
+    }
+    let module = def.module(db)?;
+    let krate = module.krate();
+
+    let mut path = vec![krate.display_name(db)?];
+    path.extend(
+        module.path_to_root(db).into_iter().rev().filter_map(|it| Some(it.name(db)?.to_string())),
+    );
+    let assoc_item = match def {
+        Definition::ModuleDef(ModuleDef::Function(it)) => it.as_assoc_item(db),
+        Definition::ModuleDef(ModuleDef::Const(it)) => it.as_assoc_item(db),
+        Definition::ModuleDef(ModuleDef::TypeAlias(it)) => it.as_assoc_item(db),
+        _ => None,
+    };
+    match assoc_item.map(|it| it.container(db)) {
+        Some(AssocItemContainer::Trait(it)) => path.push(it.name(db).to_string()),
+        Some(AssocItemContainer::ImplDef(it)) => {
+            path.push(it.target_ty(db).display(db).to_string())
+        }
+        None => (),
+    }
+    if let Definition::Field(it) = def {
+        path.push(it.parent_def(db).name(db).to_string());
+    }
+    path.push(def.name(db)?.to_string());
+
+    let source_root = db.source_root(db.file_source_root(krate.root_file(db)));
+    let kind = if source_root.is_library { MonikerKind::Import } else { MonikerKind::Export };
+    Some(Vertex::Moniker { scheme: "rust-analyzer", identifier: path.join("::"), kind })
+}
+
+fn to_range(line_index: &LineIndex, range: TextRange) -> Vertex {
+    let position = |offset| {
+        let line_col = line_index.line_col(offset);
+        Position::new(line_col.line as u64, line_col.col_utf16 as u64)
+    };
+    Vertex::Range { start: position(range.start()), end: position(range.end()) }
+}
+
+fn url(path: &Path) -> Url {
+    Url::from_file_path(path).unwrap()
+}
+
+struct LsifWriter<W> {
+    next_id: Id,
+    out: W,
+}
+
+impl<W: Write> LsifWriter<W> {
+    fn vertex(&mut self, vertex: Vertex) -> Result<Id> {
+        self.emit(Element::Vertex(vertex))
+    }
+
+    fn edge(&mut self, edge: Edge) -> Result<Id> {
+        self.emit(Element::Edge(edge))
+    }
+
+    fn emit(&mut self, data: Element) -> Result<Id> {
+        self.next_id += 1;
+        let id = self.next_id;
+        serde_json::to_writer(&mut self.out, &Entry { id, data })?;
+        writeln!(self.out)?;
+        Ok(id)
+    }
+}
+
+type Id = u64;
+
+#[derive(Serialize)]
+struct Entry {
+    id: Id,
+    #[serde(flatten)]
+    data: Element,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Element {
+    Vertex(Vertex),
+    Edge(Edge),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "label", rename_all = "camelCase")]
+enum Vertex {
+    #[serde(rename_all = "camelCase")]
+    MetaData {
+        version: &'static str,
+        position_encoding: &'static str,
+        tool_info: ToolInfo,
+        project_root: Url,
+    },
+    Project {
+        kind: &'static str,
+    },
+    #[serde(rename_all = "camelCase")]
+    Document {
+        uri: Url,
+        language_id: &'static str,
+    },
+    Range {
+        start: Position,
+        end: Position,
+    },
+    ResultSet,
+    DefinitionResult,
+    ReferenceResult,
+    HoverResult {
+        result: Hover,
+    },
+    Moniker {
+        scheme: &'static str,
+        identifier: String,
+        kind: MonikerKind,
+    },
+}
+
+#[derive(Serialize)]
+struct ToolInfo {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum MonikerKind {
+    Import,
+    Export,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "label")]
+enum Edge {
+    #[serde(rename = "contains")]
+    Contains(EdgeDataMultiIn),
+    #[serde(rename = "next")]
+    Next(EdgeData),
+    #[serde(rename = "item")]
+    Item(Item),
+    #[serde(rename = "moniker")]
+    Moniker(EdgeData),
+    #[serde(rename = "textDocument/definition")]
+    Definition(EdgeData),
+    #[serde(rename = "textDocument/references")]
+    References(EdgeData),
+    #[serde(rename = "textDocument/hover")]
+    Hover(EdgeData),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EdgeData {
+    out_v: Id,
+    in_v: Id,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EdgeDataMultiIn {
+    out_v: Id,
+    in_vs: Vec<Id>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    out_v: Id,
+    in_vs: Vec<Id>,
+    document: Id,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    property: Option<ItemKind>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum ItemKind {
+    Definitions,
+    References,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use expect::expect;
+    use ra_cfg::CfgOptions;
+    use ra_db::{CrateGraph, CrateName, Edition, Env, FileSet, SourceRoot, VfsPath};
+    use ra_ide::AnalysisChange;
+
+    use super::*;
+
+    /// Dumps the workspace crate `ws`, which depends on the library `dep`.
+    fn dump(ws: &str, dep: &str) -> String {
+        let mut host = AnalysisHost::default();
+        let mut change = AnalysisChange::new();
+        let mut crate_graph = CrateGraph::default();
+        let mut roots = Vec::new();
+        let mut files = Vec::new();
+        for (i, (name, text)) in [("ws", ws), ("dep", dep)].iter().enumerate() {
+            let file_id = FileId(i as u32);
+            let path = format!("/{}/src/lib.rs", name);
+            change.change_file(file_id, Some(Arc::new(text.trim_start().to_string())));
+            let mut file_set = FileSet::default();
+            file_set.insert(file_id, VfsPath::from(AbsPathBuf::assert(path.clone().into())));
+            roots.push(if i == 0 {
+                SourceRoot::new_local(file_set)
+            } else {
+                SourceRoot::new_library(file_set)
+            });
+            crate_graph.add_crate_root(
+                file_id,
+                Edition::Edition2018,
+                Some(name.to_string()),
+                CfgOptions::default(),
+                Env::default(),
+                Default::default(),
+            );
+            files.push((file_id, AbsPathBuf::assert(path.into())));
+        }
+        let krates = crate_graph.iter().collect::<Vec<_>>();
+        crate_graph.add_dep(krates[0], CrateName::new("dep").unwrap(), krates[1]).unwrap();
+        change.set_crate_graph(crate_graph);
+        change.set_roots(roots);
+        host.apply_change(change);
+
+        let mut lsif = LsifWriter { next_id: 0, out: Vec::new() };
+        // Only the workspace is dumped.
+        emit(&host, Path::new("/ws"), &files[..1], &mut lsif).unwrap();
+        let dump = String::from_utf8(lsif.out).unwrap();
+        dump.replace(env!("REV"), "$REV")
+    }
+
+    #[test]
+    fn emits_definitions_references_hovers_and_monikers() {
+        let dump = dump(
+            r#"
+/// Says hi.
+pub fn hi(d: dep::Dep) {
+    private(d)
+}
+fn private(_: dep::Dep) {}
+"#,
+            r#"
+pub struct Dep;
+"#,
+        );
+        expect![[r#"
+            {"id":1,"type":"vertex","label":"metaData","version":"0.5.0","positionEncoding":"utf-16","toolInfo":{"name":"rust-analyzer","version":"$REV"},"projectRoot":"file:///ws"}
+            {"id":2,"type":"vertex","label":"project","kind":"rust"}
+            {"id":3,"type":"vertex","label":"document","uri":"file:///ws/src/lib.rs","languageId":"rust"}
+            {"id":4,"type":"vertex","label":"range","start":{"line":1,"character":7},"end":{"line":1,"character":9}}
+            {"id":5,"type":"vertex","label":"resultSet"}
+            {"id":6,"type":"vertex","label":"hoverResult","result":{"contents":{"kind":"markdown","value":"```rust\nws\n```\n\n```rust\npub fn hi(d: dep::Dep)\n```\n___\n\nSays hi."}}}
+            {"id":7,"type":"edge","label":"textDocument/hover","outV":5,"inV":6}
+            {"id":8,"type":"vertex","label":"moniker","scheme":"rust-analyzer","identifier":"ws::hi","kind":"export"}
+            {"id":9,"type":"edge","label":"moniker","outV":5,"inV":8}
+            {"id":10,"type":"edge","label":"next","outV":4,"inV":5}
+            {"id":11,"type":"vertex","label":"range","start":{"line":1,"character":10},"end":{"line":1,"character":11}}
+            {"id":12,"type":"vertex","label":"resultSet"}
+            {"id":13,"type":"vertex","label":"hoverResult","result":{"contents":{"kind":"markdown","value":"```rust\nDep\n```"}}}
+            {"id":14,"type":"edge","label":"textDocument/hover","outV":12,"inV":13}
+            {"id":15,"type":"edge","label":"next","outV":11,"inV":12}
+            {"id":16,"type":"vertex","label":"range","start":{"line":1,"character":13},"end":{"line":1,"character":16}}
+            {"id":17,"type":"vertex","label":"resultSet"}
+            {"id":18,"type":"edge","label":"next","outV":16,"inV":17}
+            {"id":19,"type":"vertex","label":"range","start":{"line":1,"character":18},"end":{"line":1,"character":21}}
+            {"id":20,"type":"vertex","label":"resultSet"}
+            {"id":21,"type":"vertex","label":"hoverResult","result":{"contents":{"kind":"markdown","value":"```rust\ndep\n```\n\n```rust\npub struct Dep\n```"}}}
+            {"id":22,"type":"edge","label":"textDocument/hover","outV":20,"inV":21}
+            {"id":23,"type":"vertex","label":"moniker","scheme":"rust-analyzer","identifier":"dep::Dep","kind":"import"}
+            {"id":24,"type":"edge","label":"moniker","outV":20,"inV":23}
+            {"id":25,"type":"edge","label":"next","outV":19,"inV":20}
+            {"id":26,"type":"vertex","label":"range","start":{"line":2,"character":4},"end":{"line":2,"character":11}}
+            {"id":27,"type":"vertex","label":"resultSet"}
+            {"id":28,"type":"vertex","label":"hoverResult","result":{"contents":{"kind":"markdown","value":"```rust\nws\n```\n\n```rust\nfn private(_: dep::Dep)\n```"}}}
+            {"id":29,"type":"edge","label":"textDocument/hover","outV":27,"inV":28}
+            {"id":30,"type":"edge","label":"next","outV":26,"inV":27}
+            {"id":31,"type":"vertex","label":"range","start":{"line":2,"character":12},"end":{"line":2,"character":13}}
+            {"id":32,"type":"edge","label":"next","outV":31,"inV":12}
+            {"id":33,"type":"vertex","label":"range","start":{"line":4,"character":3},"end":{"line":4,"character":10}}
+            {"id":34,"type":"edge","label":"next","outV":33,"inV":27}
+            {"id":35,"type":"vertex","label":"range","start":{"line":4,"character":14},"end":{"line":4,"character":17}}
+            {"id":36,"type":"edge","label":"next","outV":35,"inV":17}
+            {"id":37,"type":"vertex","label":"range","start":{"line":4,"character":19},"end":{"line":4,"character":22}}
+            {"id":38,"type":"edge","label":"next","outV":37,"inV":20}
+            {"id":39,"type":"edge","label":"contains","outV":3,"inVs":[4,11,16,19,26,31,33,35,37]}
+            {"id":40,"type":"edge","label":"contains","outV":2,"inVs":[3]}
+            {"id":41,"type":"vertex","label":"referenceResult"}
+            {"id":42,"type":"edge","label":"textDocument/references","outV":5,"inV":41}
+            {"id":43,"type":"vertex","label":"definitionResult"}
+            {"id":44,"type":"edge","label":"textDocument/definition","outV":5,"inV":43}
+            {"id":45,"type":"edge","label":"item","outV":43,"inVs":[4],"document":3}
+            {"id":46,"type":"edge","label":"item","outV":41,"inVs":[4],"document":3,"property":"definitions"}
+            {"id":47,"type":"vertex","label":"referenceResult"}
+            {"id":48,"type":"edge","label":"textDocument/references","outV":12,"inV":47}
+            {"id":49,"type":"vertex","label":"definitionResult"}
+            {"id":50,"type":"edge","label":"textDocument/definition","outV":12,"inV":49}
+            {"id":51,"type":"edge","label":"item","outV":49,"inVs":[11],"document":3}
+            {"id":52,"type":"edge","label":"item","outV":47,"inVs":[11],"document":3,"property":"definitions"}
+            {"id":53,"type":"edge","label":"item","outV":47,"inVs":[31],"document":3,"property":"references"}
+            {"id":54,"type":"vertex","label":"referenceResult"}
+            {"id":55,"type":"edge","label":"textDocument/references","outV":17,"inV":54}
+            {"id":56,"type":"edge","label":"item","outV":54,"inVs":[16,35],"document":3,"property":"references"}
+            {"id":57,"type":"vertex","label":"referenceResult"}
+            {"id":58,"type":"edge","label":"textDocument/references","outV":20,"inV":57}
+            {"id":59,"type":"edge","label":"item","outV":57,"inVs":[19,37],"document":3,"property":"references"}
+            {"id":60,"type":"vertex","label":"referenceResult"}
+            {"id":61,"type":"edge","label":"textDocument/references","outV":27,"inV":60}
+            {"id":62,"type":"vertex","label":"definitionResult"}
+            {"id":63,"type":"edge","label":"textDocument/definition","outV":27,"inV":62}
+            {"id":64,"type":"edge","label":"item","outV":62,"inVs":[33],"document":3}
+            {"id":65,"type":"edge","label":"item","outV":60,"inVs":[33],"document":3,"property":"definitions"}
+            {"id":66,"type":"edge","label":"item","outV":60,"inVs":[26],"document":3,"property":"references"}
+        "#]].assert_eq(&dump);
+    }
+}
@@ -1,13 +1,18 @@
 //! Applies structured search replace rules from the command line.
 
+use std::{env, ops::Range};
+
 use crate::cli::{load_cargo::load_cargo, Result};
-use ra_ide::SourceFileEdit;
+use ra_ide::{Indel, SourceFileEdit, TextEdit};
 use ra_ssr::{MatchFinder, SsrPattern, SsrRule};
 
-pub fn apply_ssr_rules(rules: Vec<SsrRule>) -> Result<()> {
+/// Applies `rules` to all files of the workspace in the current directory. With
+/// `dry_run`, the changes are printed as a diff instead of being written.
+pub fn apply_ssr_rules(rules: Vec<SsrRule>, dry_run: bool) -> Result<()> {
     use ra_db::SourceDatabaseExt;
     use ra_ide_db::symbol_index::SymbolsDatabase;
-    let (host, vfs) = load_cargo(&std::env::current_dir()?, true, true)?;
+    let cwd = env::current_dir()?;
+    let (host, vfs) = load_cargo(&cwd, true, true)?;
     let db = host.raw_database();
     let mut match_finder = MatchFinder::new(db);
     for rule in rules {
@@ -22,16 +27,77 @@ pub fn apply_ssr_rules(rules: Vec<SsrRule>) -> Result<()> {
             }
         }
     }
-    for edit in edits {
+    let mut replacements = 0;
+    for edit in &edits {
+        replacements += edit.edit.len();
         if let Some(path) = vfs.file_path(edit.file_id).as_path() {
-            let mut contents = db.file_text(edit.file_id).to_string();
-            edit.edit.apply(&mut contents);
-            std::fs::write(path, contents)?;
+            let text = db.file_text(edit.file_id);
+            if dry_run {
+                let path: &std::path::Path = path.as_ref();
+                let path = path.strip_prefix(&cwd).unwrap_or(path);
+                print!("{}", unified_diff(&path.display().to_string(), &text, &edit.edit));
+            } else {
+                let mut contents = text.to_string();
+                edit.edit.apply(&mut contents);
+                std::fs::write(path, contents)?;
+            }
         }
     }
+    eprintln!("{} replacements in {} files", replacements, edits.len());
     Ok(())
 }
 
+/// Formats the changes made by `edit` to `text` as a unified diff of the lines
+/// it touches, without additional context lines.
+fn unified_diff(path: &str, text: &str, edit: &TextEdit) -> String {
+    let line_start = |offset: usize| text[..offset].rfind('\n').map_or(0, |it| it + 1);
+    let line_end =
+        |offset: usize| text[offset..].find('\n').map_or(text.len(), |it| offset + it + 1);
+
+    // Indels touching the same or adjacent lines end up in the same hunk.
+    let mut hunks: Vec<(Range<usize>, Vec<&Indel>)> = Vec::new();
+    for indel in edit.iter() {
+        let start = line_start(indel.delete.start().into());
+        let end = line_end(indel.delete.end().into());
+        match hunks.last_mut() {
+            Some((range, indels)) if start <= range.end => {
+                range.end = range.end.max(end);
+                indels.push(indel);
+            }
+            _ => hunks.push((start..end, vec![indel])),
+        }
+    }
+
+    let mut res = format!("--- a/{}\n+++ b/{}\n", path, path);
+    let mut line_delta = 0isize;
+    for (range, indels) in hunks {
+        let mut new = String::new();
+        let mut pos = range.start;
+        for indel in indels {
+            new.push_str(&text[pos..indel.delete.start().into()]);
+            new.push_str(&indel.insert);
+            pos = indel.delete.end().into();
+        }
+        new.push_str(&text[pos..range.end]);
+
+        let old_lines = text[range.clone()].lines().collect::<Vec<_>>();
+        let new_lines = new.lines().collect::<Vec<_>>();
+        let old_line = text[..range.start].matches('\n').count() + 1;
+        let new_line = old_line as isize + line_delta;
+        res.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_line,
+            old_lines.len(),
+            new_line,
+            new_lines.len()
+        ));
+        old_lines.iter().for_each(|line| res.push_str(&format!("-{}\n", line)));
+        new_lines.iter().for_each(|line| res.push_str(&format!("+{}\n", line)));
+        line_delta += new_lines.len() as isize - old_lines.len() as isize;
+    }
+    res
+}
+
 /// Searches for `patterns`, printing debug information for any nodes whose text exactly matches
 /// `debug_snippet`. This is intended for debugging and probably isn't in it's current form useful
 /// for much else.
@@ -69,3 +135,41 @@ pub fn search_for_patterns(patterns: Vec<SsrPattern>, debug_snippet: Option<Stri
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use ra_ide::TextEdit;
+    use ra_syntax::{TextRange, TextSize};
+    use ra_text_edit::TextEditBuilder;
+
+    use super::unified_diff;
+
+    #[test]
+    fn diff_groups_indels_by_line() {
+        let text = "fn main() {\n    foo(1, 2);\n    foo(3, 4);\n}\n\nfn bar() { foo(5, 6) }\n";
+        let range =
+            |start: u32, end: u32| TextRange::new(TextSize::from(start), TextSize::from(end));
+        let mut builder = TextEditBuilder::default();
+        builder.replace(range(16, 25), "bar(2, 1)".to_string());
+        builder.replace(range(31, 40), "bar(4,\n    3)".to_string());
+        builder.replace(range(56, 65), "bar(6, 5)".to_string());
+        let edit: TextEdit = builder.finish();
+
+        assert_eq!(
+            unified_diff("src/main.rs", text, &edit),
+            "\
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -2,2 +2,3 @@
+-    foo(1, 2);
+-    foo(3, 4);
++    bar(2, 1);
++    bar(4,
++    3);
+@@ -6,1 +7,1 @@
+-fn bar() { foo(5, 6) }
++fn bar() { bar(6, 5) }
+"
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! Dumps the symbols of a workspace as JSON, for external indexers and as a
+//! replacement for ctags.
+
+use std::{
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::bail;
+use hir::Semantics;
+use lsp_types::{Position, Range};
+use ra_ide::{FileId, LineIndex};
+use ra_ide_db::symbol_index::SymbolsDatabase;
+use ra_syntax::{
+    ast::{self, NameOwner},
+    match_ast, AstNode, SyntaxKind, SyntaxNode, TextRange,
+};
+use serde::Serialize;
+
+use crate::cli::{load_cargo::load_cargo, module_files, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolsFormat {
+    /// A single JSON array.
+    Json,
+    /// One JSON object per line.
+    NdJson,
+}
+
+impl FromStr for SymbolsFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(SymbolsFormat::Json),
+            "ndjson" => Ok(SymbolsFormat::NdJson),
+            _ => bail!("unknown symbols format `{}`, expected `json` or `ndjson`", s),
+        }
+    }
+}
+
+/// A symbol as it is printed. Positions are zero-based, and columns are
+/// counted in UTF-16 code units, as in LSP.
+#[derive(Serialize)]
+struct Symbol {
+    name: String,
+    kind: &'static str,
+    /// The path of the item or module containing the symbol, starting with
+    /// the name of the crate.
+    container: String,
+    file: String,
+    range: Range,
+    name_range: Option<Range>,
+    /// The visibility as written, like `pub(crate)`, or `None` for private
+    /// symbols.
+    visibility: Option<String>,
+    deprecated: bool,
+}
+
+pub fn workspace_symbols(
+    path: &Path,
+    load_output_dirs: bool,
+    with_proc_macro: bool,
+    with_deps: bool,
+    format: SymbolsFormat,
+) -> Result<()> {
+    let (host, vfs) = load_cargo(path, load_output_dirs, with_proc_macro)?;
+    let db = host.raw_database();
+    let analysis = host.analysis();
+    let sema = Semantics::new(db);
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    if format == SymbolsFormat::Json {
+        write!(out, "[")?;
+    }
+    let mut first = true;
+    for file_id in module_files(db, with_deps) {
+        let file = vfs.file_path(file_id).to_string();
+        let line_index = analysis.file_line_index(file_id)?;
+        let root = sema.parse(file_id).syntax().clone();
+        let module_path = module_path(&sema, file_id);
+
+        let index = db.file_symbols(file_id);
+        let mut symbols = index.symbols().to_vec();
+        symbols.sort_by_key(|it| it.range.start());
+        for symbol in symbols {
+            let node = symbol.ptr.to_node(&root);
+            let mut container = module_path.clone();
+            container.extend(parent_names(&node));
+            let symbol = Symbol {
+                name: symbol.name.to_string(),
+                kind: kind_name(symbol.kind),
+                container: container.join("::"),
+                file: file.clone(),
+                range: to_range(&line_index, symbol.range),
+                name_range: symbol.name_range.map(|it| to_range(&line_index, it)),
+                visibility: node
+                    .children()
+                    .find_map(ast::Visibility::cast)
+                    .map(|it| it.syntax().text().to_string()),
+                deprecated: node
+                    .children()
+                    .filter_map(ast::Attr::cast)
+                    .any(|it| it.simple_name().as_deref() == Some("deprecated")),
+            };
+            match format {
+                SymbolsFormat::Json => {
+                    if !first {
+                        write!(out, ",")?;
+                    }
+                    writeln!(out)?;
+                    serde_json::to_writer(&mut out, &symbol)?;
+                }
+                SymbolsFormat::NdJson => {
+                    serde_json::to_writer(&mut out, &symbol)?;
+                    writeln!(out)?;
+                }
+            }
+            first = false;
+        }
+    }
+    if format == SymbolsFormat::Json {
+        writeln!(out, "\n]")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Returns the crate name and the module names leading to `file_id`.
+pub(super) fn module_path(sema: &Semantics<ra_ide::RootDatabase>, file_id: FileId) -> Vec<String> {
+    let db = sema.db;
+    let module = match sema.to_module_def(file_id) {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+    let mut res: Vec<String> = module.krate().display_name(db).into_iter().collect();
+    res.extend(
+        module.path_to_root(db).into_iter().rev().filter_map(|it| Some(it.name(db)?.to_string())),
+    );
+    res
+}
+
+/// Returns the names of the items `node` is nested in, outermost first. Impls
+/// are named after their self type.
+pub(super) fn parent_names(node: &SyntaxNode) -> Vec<String> {
+    let mut res = node
+        .ancestors()
+        .skip(1)
+        .filter_map(|it| {
+            let name = match_ast! {
+                match it {
+                    ast::ImplDef(it) => return Some(it.target_type()?.syntax().text().to_string()),
+                    ast::Module(it) => it.name(),
+                    ast::TraitDef(it) => it.name(),
+                    ast::FnDef(it) => it.name(),
+                    ast::ConstDef(it) => it.name(),
+                    ast::StaticDef(it) => it.name(),
+                    _ => None,
+                }
+            };
+            Some(name?.text().to_string())
+        })
+        .collect::<Vec<_>>();
+    res.reverse();
+    res
+}
+
+fn kind_name(kind: SyntaxKind) -> &'static str {
+    match kind {
+        SyntaxKind::FN_DEF => "function",
+        SyntaxKind::STRUCT_DEF => "struct",
+        SyntaxKind::UNION_DEF => "union",
+        SyntaxKind::ENUM_DEF => "enum",
+        SyntaxKind::TRAIT_DEF => "trait",
+        SyntaxKind::MODULE => "module",
+        SyntaxKind::TYPE_ALIAS_DEF => "type_alias",
+        SyntaxKind::CONST_DEF => "const",
+        SyntaxKind::STATIC_DEF => "static",
+        SyntaxKind::MACRO_CALL => "macro",
+        _ => "unknown",
+    }
+}
+
+fn to_range(line_index: &LineIndex, range: TextRange) -> Range {
+    let position = |offset| {
+        let line_col = line_index.line_col(offset);
+        Position::new(line_col.line as u64, line_col.col_utf16 as u64)
+    };
+    Range::new(position(range.start()), position(range.end()))
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::SourceFile;
+
+    use super::*;
+
+    #[test]
+    fn nested_symbols_are_prefixed_with_their_parents() {
+        let file =
+            SourceFile::parse("mod m { impl Vec<u8> { fn f() { const C: u8 = 0; } } }").tree();
+        let konst = file.syntax().descendants().find_map(ast::ConstDef::cast).unwrap();
+        assert_eq!(parent_names(konst.syntax()), vec!["m", "Vec<u8>", "f"]);
+    }
+}
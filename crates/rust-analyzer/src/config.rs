@@ -11,8 +11,12 @@ use std::{ffi::OsString, path::PathBuf};
 
 use flycheck::FlycheckConfig;
 use lsp_types::ClientCapabilities;
-use ra_ide::{AssistConfig, CompletionConfig, HoverConfig, InlayHintsConfig};
-use ra_project_model::{CargoConfig, ProjectJson, ProjectJsonData, ProjectManifest};
+use ra_cfg::{CfgAtom, CfgDiff};
+use ra_ide::{
+    AssistConfig, CompletionConfig, HoverConfig, InlayHintsConfig, LruConfig, MacroLimits,
+};
+use ra_project_model::{CargoConfig, CfgOverrides, ProjectJson, ProjectJsonData, ProjectManifest};
+use rustc_hash::FxHashMap;
 use serde::Deserialize;
 use vfs::AbsPathBuf;
 
@@ -24,13 +28,22 @@ pub struct Config {
 
     pub publish_diagnostics: bool,
     pub diagnostics: DiagnosticsConfig,
-    pub lru_capacity: Option<usize>,
+    pub lru: LruConfig,
+    pub macro_limits: MacroLimits,
+    /// Number of threads used to prime the caches, zero means one per core.
+    pub prime_caches_num_threads: usize,
+    /// Whether to reuse the symbols of unchanged files from a previous run.
+    pub persistent_cache: bool,
     pub proc_macro_srv: Option<(PathBuf, Vec<OsString>)>,
+    /// Whether items whose attribute macro fails to expand are analyzed as if
+    /// the attribute was not there.
+    pub attr_macro_fallback: bool,
     pub files: FilesConfig,
     pub notifications: NotificationsConfig,
 
     pub cargo: CargoConfig,
     pub rustfmt: RustfmtConfig,
+    pub runnables: RunnablesConfig,
     pub flycheck: Option<FlycheckConfig>,
 
     pub inlay_hints: InlayHintsConfig,
@@ -42,6 +55,9 @@ pub struct Config {
 
     pub with_sysroot: bool,
     pub linked_projects: Vec<LinkedProject>,
+    /// Directories in which `linked_projects` are discovered, empty if the
+    /// projects are configured explicitly.
+    pub discovery_roots: Vec<AbsPathBuf>,
     pub root_path: AbsPathBuf,
 }
 
@@ -68,19 +84,21 @@ pub struct LensConfig {
     pub run: bool,
     pub debug: bool,
     pub implementations: bool,
+    pub references: bool,
 }
 
 impl Default for LensConfig {
     fn default() -> Self {
-        Self { run: true, debug: true, implementations: true }
+        Self { run: true, debug: true, implementations: true, references: false }
     }
 }
 
 impl LensConfig {
-    pub const NO_LENS: LensConfig = Self { run: false, debug: false, implementations: false };
+    pub const NO_LENS: LensConfig =
+        Self { run: false, debug: false, implementations: false, references: false };
 
     pub fn any(&self) -> bool {
-        self.implementations || self.runnable()
+        self.implementations || self.references || self.runnable()
     }
 
     pub fn none(&self) -> bool {
@@ -92,13 +110,14 @@ impl LensConfig {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FilesConfig {
     pub watcher: FilesWatcher,
-    pub exclude: Vec<String>,
+    /// Directories which are never scanned, watched or indexed.
+    pub exclude_dirs: Vec<AbsPathBuf>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FilesWatcher {
     Client,
     Notify,
@@ -109,6 +128,16 @@ pub struct NotificationsConfig {
     pub cargo_toml_not_found: bool,
 }
 
+/// Configuration for runnable items, such as `main` function or tests.
+#[derive(Debug, Clone, Default)]
+pub struct RunnablesConfig {
+    /// Custom command to be executed instead of `cargo` for runnables.
+    pub override_cargo: Option<String>,
+    /// Additional arguments to be passed to cargo for runnables such as
+    /// tests or binaries.
+    pub cargo_extra_args: Vec<String>,
+}
+
 #[derive(Debug, Clone)]
 pub enum RustfmtConfig {
     Rustfmt {
@@ -132,6 +161,23 @@ pub struct ClientCapsConfig {
     pub resolve_code_action: bool,
     pub hover_actions: bool,
     pub status_notification: bool,
+    /// Whether the client can watch files for us, see `Config::files_watcher`.
+    pub watched_files_dynamic_registration: bool,
+    pub offset_encoding: OffsetEncoding,
+}
+
+/// The unit in which the columns of LSP positions are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffsetEncoding {
+    Utf8,
+    /// The default, mandated by the LSP specification.
+    Utf16,
+}
+
+impl Default for OffsetEncoding {
+    fn default() -> Self {
+        OffsetEncoding::Utf16
+    }
 }
 
 impl Config {
@@ -142,18 +188,25 @@ impl Config {
             with_sysroot: true,
             publish_diagnostics: true,
             diagnostics: DiagnosticsConfig::default(),
-            lru_capacity: None,
+            lru: LruConfig::default(),
+            macro_limits: MacroLimits::default(),
+            prime_caches_num_threads: 0,
+            persistent_cache: false,
             proc_macro_srv: None,
-            files: FilesConfig { watcher: FilesWatcher::Notify, exclude: Vec::new() },
+            attr_macro_fallback: true,
+            files: FilesConfig { watcher: FilesWatcher::Notify, exclude_dirs: Vec::new() },
             notifications: NotificationsConfig { cargo_toml_not_found: true },
 
             cargo: CargoConfig::default(),
             rustfmt: RustfmtConfig::Rustfmt { extra_args: Vec::new() },
+            runnables: RunnablesConfig::default(),
             flycheck: Some(FlycheckConfig::CargoCommand {
                 command: "check".to_string(),
+                target_triple: None,
                 all_targets: true,
                 all_features: false,
                 extra_args: Vec::new(),
+                extra_env: Vec::new(),
                 features: Vec::new(),
             }),
 
@@ -174,6 +227,7 @@ impl Config {
             lens: LensConfig::default(),
             hover: HoverConfig::default(),
             linked_projects: Vec::new(),
+            discovery_roots: Vec::new(),
             root_path,
         }
     }
@@ -184,19 +238,35 @@ impl Config {
 
         let client_caps = self.client_caps.clone();
         let linked_projects = self.linked_projects.clone();
+        let discovery_roots = self.discovery_roots.clone();
         *self = Config::new(self.root_path.clone());
         self.client_caps = client_caps;
         self.linked_projects = linked_projects;
+        self.discovery_roots = discovery_roots;
 
         set(value, "/withSysroot", &mut self.with_sysroot);
         set(value, "/diagnostics/enable", &mut self.publish_diagnostics);
         set(value, "/diagnostics/warningsAsInfo", &mut self.diagnostics.warnings_as_info);
         set(value, "/diagnostics/warningsAsHint", &mut self.diagnostics.warnings_as_hint);
-        set(value, "/lruCapacity", &mut self.lru_capacity);
+        if let Some(capacity) = get(value, "/lruCapacity") {
+            self.lru.parse = capacity;
+            self.lru.macro_expansion = capacity;
+        }
+        set(value, "/lru/parseCapacity", &mut self.lru.parse);
+        set(value, "/lru/macroExpansionCapacity", &mut self.lru.macro_expansion);
+        set(value, "/lru/bodyCapacity", &mut self.lru.body);
+        set(value, "/lru/memoryBudget", &mut self.lru.memory_budget);
+        set(value, "/macroExpansion/depthLimit", &mut self.macro_limits.expansion_depth);
+        set(value, "/macroExpansion/tokenLimit", &mut self.macro_limits.token_count);
+        set(value, "/primeCaches/numThreads", &mut self.prime_caches_num_threads);
+        set(value, "/persistentCache/enable", &mut self.persistent_cache);
         self.files.watcher = match get(value, "/files/watcher") {
             Some("client") => FilesWatcher::Client,
             Some("notify") | _ => FilesWatcher::Notify
         };
+        if let Some(exclude_dirs) = get::<Vec<PathBuf>>(value, "/files/excludeDirs") {
+            self.files.exclude_dirs = exclude_dirs.into_iter().map(|it| self.root_path.join(it)).collect();
+        }
         set(value, "/notifications/cargoTomlNotFound", &mut self.notifications.cargo_toml_not_found);
 
         set(value, "/cargo/noDefaultFeatures", &mut self.cargo.no_default_features);
@@ -204,6 +274,18 @@ impl Config {
         set(value, "/cargo/features", &mut self.cargo.features);
         set(value, "/cargo/loadOutDirsFromCheck", &mut self.cargo.load_out_dirs_from_check);
         set(value, "/cargo/target", &mut self.cargo.target);
+        set(value, "/cargo/pathDependenciesAsMembers", &mut self.cargo.path_deps_as_members);
+        set(value, "/cargo/cacheMetadata", &mut self.cargo.cache_metadata);
+        // An empty or missing setting means the sources are discovered anew.
+        self.cargo.sysroot_src = get::<PathBuf>(value, "/cargo/sysrootSrc")
+            .filter(|it| !it.as_os_str().is_empty())
+            .map(|it| self.root_path.join(it));
+        if let Some(overrides) = value.pointer("/cargo/cfgOverrides").and_then(|it| it.as_object()) {
+            self.cargo.cfg_overrides = cfg_overrides(overrides);
+        }
+
+        set(value, "/runnables/overrideCargo", &mut self.runnables.override_cargo);
+        set(value, "/runnables/cargoExtraArgs", &mut self.runnables.cargo_extra_args);
 
         match get(value, "/procMacro/enable") {
             Some(true) => {
@@ -213,6 +295,7 @@ impl Config {
             }
             _ => self.proc_macro_srv = None,
         }
+        set(value, "/procMacro/attributeFallback", &mut self.attr_macro_fallback);
 
         match get::<Vec<String>>(value, "/rustfmt/overrideCommand") {
             Some(mut args) if !args.is_empty() => {
@@ -234,6 +317,12 @@ impl Config {
             self.flycheck = None;
         } else {
             // check is enabled
+            let extra_env = get::<FxHashMap<String, String>>(value, "/checkOnSave/extraEnv")
+                .map(|env| {
+                    let mut env = env.into_iter().collect::<Vec<_>>();
+                    env.sort();
+                    env
+                });
             match get::<Vec<String>>(value, "/checkOnSave/overrideCommand") {
                 // first see if the user has completely overridden the command
                 Some(mut args) if !args.is_empty() => {
@@ -241,16 +330,21 @@ impl Config {
                     self.flycheck = Some(FlycheckConfig::CustomCommand {
                         command,
                         args,
+                        extra_env: extra_env.unwrap_or_default(),
                     });
                 }
                 // otherwise configure command customizations
                 _ => {
-                    if let Some(FlycheckConfig::CargoCommand { command, extra_args, all_targets, all_features, features })
+                    if let Some(FlycheckConfig::CargoCommand { command, target_triple, extra_args, extra_env: env, all_targets, all_features, features })
                         = &mut self.flycheck
                     {
                         set(value, "/checkOnSave/extraArgs", extra_args);
+                        if let Some(extra_env) = extra_env {
+                            *env = extra_env;
+                        }
                         set(value, "/checkOnSave/command", command);
                         set(value, "/checkOnSave/allTargets", all_targets);
+                        *target_triple = self.cargo.target.clone();
                         *all_features = get(value, "/checkOnSave/allFeatures").unwrap_or(self.cargo.all_features);
                         *features = get(value, "/checkOnSave/features").unwrap_or(self.cargo.features.clone());
                     }
@@ -273,6 +367,7 @@ impl Config {
             set(value, "/lens/run", &mut self.lens.run);
             set(value, "/lens/debug", &mut self.lens.debug);
             set(value, "/lens/implementations", &mut self.lens.implementations);
+            set(value, "/lens/references", &mut self.lens.references);
         } else {
             self.lens = LensConfig::NO_LENS;
         }
@@ -280,6 +375,7 @@ impl Config {
         if let Some(linked_projects) = get::<Vec<ManifestOrProjectJson>>(value, "/linkedProjects") {
             if !linked_projects.is_empty() {
                 self.linked_projects.clear();
+                self.discovery_roots.clear();
                 for linked_project in linked_projects {
                     let linked_project = match linked_project {
                         ManifestOrProjectJson::Manifest(it) => {
@@ -352,6 +448,15 @@ impl Config {
             }
         }
 
+        if let Some(value) = caps
+            .workspace
+            .as_ref()
+            .and_then(|it| it.did_change_watched_files.as_ref())
+            .and_then(|it| it.dynamic_registration)
+        {
+            self.client_caps.watched_files_dynamic_registration = value;
+        }
+
         if let Some(window_caps) = caps.window.as_ref() {
             if let Some(value) = window_caps.work_done_progress {
                 self.client_caps.work_done_progress = value;
@@ -372,6 +477,29 @@ impl Config {
             self.client_caps.status_notification = get_bool("statusNotification");
         }
     }
+
+    /// Looks for projects in `discovery_roots` anew, picking up projects which
+    /// were created or removed since the last discovery.
+    pub fn discover_projects(&mut self) {
+        if self.discovery_roots.is_empty() {
+            return;
+        }
+        self.linked_projects = ProjectManifest::discover_all(&self.discovery_roots)
+            .into_iter()
+            .map(LinkedProject::from)
+            .collect();
+    }
+
+    /// The configured file watcher, falling back to `notify` if the client
+    /// can't register file watchers on our behalf.
+    pub fn files_watcher(&self) -> FilesWatcher {
+        match self.files.watcher {
+            FilesWatcher::Client if !self.client_caps.watched_files_dynamic_registration => {
+                FilesWatcher::Notify
+            }
+            ref it => it.clone(),
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -380,3 +508,107 @@ enum ManifestOrProjectJson {
     Manifest(PathBuf),
     ProjectJson(ProjectJsonData),
 }
+
+/// Converts the `cargo.cfgOverrides` setting, which maps crate names (or `*`
+/// for all crates) to cfg options and whether they should be enabled. Invalid
+/// entries are skipped, so that a typo doesn't discard the other ones.
+fn cfg_overrides(value: &serde_json::Map<String, serde_json::Value>) -> CfgOverrides {
+    let mut res = CfgOverrides::default();
+    for (crate_name, cfgs) in value {
+        let cfgs = match cfgs.as_object() {
+            Some(it) => it,
+            None => {
+                log::warn!("invalid cfg overrides for `{}`: {}", crate_name, cfgs);
+                continue;
+            }
+        };
+        let diff = if crate_name == "*" {
+            &mut res.global
+        } else {
+            res.selective.entry(crate_name.clone()).or_insert_with(CfgDiff::default)
+        };
+        for (cfg, enable) in cfgs {
+            let atom = CfgAtom::parse(cfg);
+            match enable.as_bool() {
+                Some(true) => diff.enable.push(atom),
+                Some(false) => diff.disable.push(atom),
+                None => {
+                    log::warn!("invalid cfg override `{}` for `{}`: {}", cfg, crate_name, enable)
+                }
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_cfg_overrides() {
+        let mut config = Config::new(AbsPathBuf::assert("/".into()));
+        config.update(&json!({
+            "cargo": {
+                "cfgOverrides": {
+                    "*": { "fuzzing": true, "debug_assertions": false },
+                    "foo": { "test": false, "feature=\"std\"": true, "invalid": "yes" },
+                    "bar": ["test"]
+                }
+            }
+        }));
+        let overrides = &config.cargo.cfg_overrides;
+        assert_eq!(
+            overrides.global,
+            CfgDiff {
+                enable: vec![CfgAtom::Flag("fuzzing".into())],
+                disable: vec![CfgAtom::Flag("debug_assertions".into())],
+            }
+        );
+        assert_eq!(
+            overrides.selective["foo"],
+            CfgDiff {
+                enable: vec![CfgAtom::KeyValue { key: "feature".into(), value: "std".into() }],
+                disable: vec![CfgAtom::Flag("test".into())],
+            }
+        );
+        assert!(!overrides.selective.contains_key("bar"));
+    }
+
+    #[test]
+    fn invalid_cfg_overrides_are_ignored() {
+        let mut config = Config::new(AbsPathBuf::assert("/".into()));
+        config.update(&json!({ "cargo": { "cfgOverrides": ["fuzzing"] } }));
+        assert_eq!(config.cargo.cfg_overrides, CfgOverrides::default());
+    }
+
+    #[test]
+    fn parses_lru_capacities() {
+        let mut config = Config::new(AbsPathBuf::assert("/".into()));
+        config.update(&json!({ "lruCapacity": 64, "lru": { "macroExpansionCapacity": 32 } }));
+        assert_eq!((config.lru.parse, config.lru.macro_expansion), (64, 32));
+
+        config.update(&json!({ "lruCapacity": 64, "lru": { "parseCapacity": 16 } }));
+        assert_eq!((config.lru.parse, config.lru.macro_expansion), (16, 64));
+    }
+
+    #[test]
+    fn unset_sysroot_src_resets_the_override() {
+        let mut config = Config::new(AbsPathBuf::assert("/ws".into()));
+        config.update(&json!({ "cargo": { "sysrootSrc": "rust/library" } }));
+        assert_eq!(config.cargo.sysroot_src, Some(AbsPathBuf::assert("/ws/rust/library".into())));
+
+        for value in [
+            json!({ "cargo": { "sysrootSrc": null } }),
+            json!({ "cargo": { "sysrootSrc": "" } }),
+            json!({}),
+        ]
+        .iter()
+        {
+            config.update(value);
+            assert_eq!(config.cargo.sysroot_src, None, "{}", value);
+        }
+    }
+}
@@ -91,8 +91,24 @@ fn map_rust_child_diagnostic(
         return MappedRustChildDiagnostic::MessageLine(rd.message.clone());
     }
 
+    match suggested_fix(workspace_root, &rd.message, &spans) {
+        Some(fix) => MappedRustChildDiagnostic::SuggestedFix(fix),
+        None => MappedRustChildDiagnostic::Related(lsp_types::DiagnosticRelatedInformation {
+            location: location(workspace_root, spans[0]),
+            message: rd.message.clone(),
+        }),
+    }
+}
+
+/// Collects machine-applicable `suggested_replacement`s of the spans into a
+/// single quickfix.
+fn suggested_fix(
+    workspace_root: &Path,
+    title: &str,
+    spans: &[&DiagnosticSpan],
+) -> Option<lsp_ext::CodeAction> {
     let mut edit_map: HashMap<lsp_types::Url, Vec<lsp_types::TextEdit>> = HashMap::new();
-    for &span in &spans {
+    for &span in spans {
         if let (Some(Applicability::MachineApplicable), Some(suggested_replacement)) =
             (&span.suggestion_applicability, &span.suggested_replacement)
         {
@@ -103,24 +119,20 @@ fn map_rust_child_diagnostic(
     }
 
     if edit_map.is_empty() {
-        MappedRustChildDiagnostic::Related(lsp_types::DiagnosticRelatedInformation {
-            location: location(workspace_root, spans[0]),
-            message: rd.message.clone(),
-        })
-    } else {
-        MappedRustChildDiagnostic::SuggestedFix(lsp_ext::CodeAction {
-            title: rd.message.clone(),
-            id: None,
-            group: None,
-            kind: Some("quickfix".to_string()),
-            edit: Some(lsp_ext::SnippetWorkspaceEdit {
-                // FIXME: there's no good reason to use edit_map here....
-                changes: Some(edit_map),
-                document_changes: None,
-            }),
-            command: None,
-        })
+        return None;
     }
+    Some(lsp_ext::CodeAction {
+        title: title.to_string(),
+        id: None,
+        group: None,
+        kind: Some("quickfix".to_string()),
+        edit: Some(lsp_ext::SnippetWorkspaceEdit {
+            // FIXME: there's no good reason to use edit_map here....
+            changes: Some(edit_map),
+            document_changes: None,
+        }),
+        command: None,
+    })
 }
 
 #[derive(Debug)]
@@ -138,6 +150,7 @@ pub(crate) struct MappedRustDiagnostic {
 /// 2. Adding any labelled secondary spans to `relatedInformation`
 /// 3. Categorising child diagnostics as either `SuggestedFix`es,
 ///    `relatedInformation` or additional message lines.
+/// 4. Turning machine-applicable suggestions of the primary spans into fixes.
 ///
 /// If the diagnostic has no primary span this will return `None`
 pub(crate) fn map_rust_diagnostic_to_lsp(
@@ -174,7 +187,16 @@ pub(crate) fn map_rust_diagnostic_to_lsp(
         }
     }
 
-    let mut fixes = Vec::new();
+    // Lints occasionally attach the suggestion to the primary span of the
+    // diagnostic itself rather than to a `help` child, using the label for
+    // the help message.
+    let fix_title = primary_spans[0]
+        .label
+        .as_deref()
+        .and_then(|label| label.strip_prefix("help: "))
+        .unwrap_or(&rd.message);
+    let mut fixes: Vec<_> =
+        suggested_fix(workspace_root, fix_title, &primary_spans).into_iter().collect();
     let mut message = rd.message.clone();
     for child in &rd.children {
         let child = map_rust_child_diagnostic(workspace_root, &child);
@@ -1467,4 +1489,54 @@ mod tests {
             expect_file!["crates/rust-analyzer/test_data/snap_multi_line_fix.txt"],
         );
     }
+
+    #[test]
+    fn suggestion_on_primary_span() {
+        check(
+            r##"{
+                "rendered": "warning: redundant field names in struct initialization\n --> src/main.rs:4:15\n  |\n4 |     Foo { bar: bar };\n  |           ^^^^^^^^ help: replace it with: `bar`\n  |\n  = note: `#[warn(clippy::redundant_field_names)]` on by default\n\n",
+                "children": [
+                    {
+                    "children": [],
+                    "code": null,
+                    "level": "note",
+                    "message": "`#[warn(clippy::redundant_field_names)]` on by default",
+                    "rendered": null,
+                    "spans": []
+                    }
+                ],
+                "code": {
+                    "code": "clippy::redundant_field_names",
+                    "explanation": null
+                },
+                "level": "warning",
+                "message": "redundant field names in struct initialization",
+                "spans": [
+                    {
+                    "byte_end": 58,
+                    "byte_start": 50,
+                    "column_end": 23,
+                    "column_start": 15,
+                    "expansion": null,
+                    "file_name": "src/main.rs",
+                    "is_primary": true,
+                    "label": "help: replace it with: `bar`",
+                    "line_end": 4,
+                    "line_start": 4,
+                    "suggested_replacement": "bar",
+                    "suggestion_applicability": "MachineApplicable",
+                    "text": [
+                        {
+                        "highlight_end": 23,
+                        "highlight_start": 15,
+                        "text": "    Foo { bar: bar };"
+                        }
+                    ]
+                    }
+                ]
+            }
+            "##,
+            expect_file!["crates/rust-analyzer/test_data/suggestion_on_primary_span.txt"],
+        );
+    }
 }
@@ -59,13 +59,32 @@ impl<'a> RequestDispatcher<'a> {
             }
         };
 
-        self.global_state.task_pool.handle.spawn({
-            let world = self.global_state.snapshot();
-            move || {
-                let result = f(world, params);
-                Task::Response(result_to_response::<R>(id, result))
+        let task = self.task::<R>(f, id, params);
+        self.global_state.task_pool.handle.spawn(task);
+
+        Ok(self)
+    }
+
+    /// Dispatches the request onto the thread pool reserved for requests
+    /// which block typing, so that they don't wait for other requests.
+    pub(crate) fn on_latency_sensitive<R>(
+        &mut self,
+        f: fn(GlobalStateSnapshot, R::Params) -> Result<R::Result>,
+    ) -> Result<&mut Self>
+    where
+        R: lsp_types::request::Request + 'static,
+        R::Params: DeserializeOwned + Send + 'static,
+        R::Result: Serialize + 'static,
+    {
+        let (id, params) = match self.parse::<R>() {
+            Some(it) => it,
+            None => {
+                return Ok(self);
             }
-        });
+        };
+
+        let task = self.task::<R>(f, id, params);
+        self.global_state.latency_sensitive_pool.spawn(task);
 
         Ok(self)
     }
@@ -82,6 +101,24 @@ impl<'a> RequestDispatcher<'a> {
         }
     }
 
+    fn task<R>(
+        &self,
+        f: fn(GlobalStateSnapshot, R::Params) -> Result<R::Result>,
+        id: lsp_server::RequestId,
+        params: R::Params,
+    ) -> impl FnOnce() -> Task + Send + 'static
+    where
+        R: lsp_types::request::Request + 'static,
+        R::Params: DeserializeOwned + Send + 'static,
+        R::Result: Serialize + 'static,
+    {
+        let world = self.global_state.snapshot();
+        move || {
+            let result = f(world, params);
+            Task::Response(result_to_response::<R>(id, result))
+        }
+    }
+
     fn parse<R>(&mut self) -> Option<(lsp_server::RequestId, R::Params)>
     where
         R: lsp_types::request::Request + 'static,
@@ -2,11 +2,13 @@
 use std::convert::TryFrom;
 
 use ra_db::{FileId, FilePosition, FileRange};
-use ra_ide::{LineCol, LineIndex};
+use ra_ide::{LineCol, LineColUtf8};
 use ra_syntax::{TextRange, TextSize};
 use vfs::AbsPathBuf;
 
-use crate::{global_state::GlobalStateSnapshot, Result};
+use crate::{
+    config::OffsetEncoding, global_state::GlobalStateSnapshot, line_index::LineIndex, Result,
+};
 
 pub(crate) fn abs_path(url: &lsp_types::Url) -> Result<AbsPathBuf> {
     let path = url.to_file_path().map_err(|()| "url is not a file")?;
@@ -18,8 +20,12 @@ pub(crate) fn vfs_path(url: &lsp_types::Url) -> Result<vfs::VfsPath> {
 }
 
 pub(crate) fn offset(line_index: &LineIndex, position: lsp_types::Position) -> TextSize {
-    let line_col = LineCol { line: position.line as u32, col_utf16: position.character as u32 };
-    line_index.offset(line_col)
+    let line = position.line as u32;
+    let col = position.character as u32;
+    match line_index.encoding {
+        OffsetEncoding::Utf8 => line_index.index.offset_utf8(LineColUtf8 { line, col }),
+        OffsetEncoding::Utf16 => line_index.index.offset(LineCol { line, col_utf16: col }),
+    }
 }
 
 pub(crate) fn text_range(line_index: &LineIndex, range: lsp_types::Range) -> TextRange {
@@ -37,8 +43,8 @@ pub(crate) fn file_position(
     tdpp: lsp_types::TextDocumentPositionParams,
 ) -> Result<FilePosition> {
     let file_id = file_id(world, &tdpp.text_document.uri)?;
-    let line_index = world.analysis.file_line_index(file_id)?;
-    let offset = offset(&*line_index, tdpp.position);
+    let line_index = world.file_line_index(file_id)?;
+    let offset = offset(&line_index, tdpp.position);
     Ok(FilePosition { file_id, offset })
 }
 
@@ -48,7 +54,7 @@ pub(crate) fn file_range(
     range: lsp_types::Range,
 ) -> Result<FileRange> {
     let file_id = file_id(world, &text_document_identifier.uri)?;
-    let line_index = world.analysis.file_line_index(file_id)?;
+    let line_index = world.file_line_index(file_id)?;
     let range = text_range(&line_index, range);
     Ok(FileRange { file_id, range })
 }
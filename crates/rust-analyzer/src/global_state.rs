@@ -3,22 +3,28 @@
 //!
 //! Each tick provides an immutable snapshot of the state as `WorldSnapshot`.
 
-use std::{sync::Arc, time::Instant};
+use std::{
+    sync::Arc,
+    time::{Instant, SystemTime},
+};
 
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use flycheck::FlycheckHandle;
-use lsp_types::Url;
-use parking_lot::RwLock;
+use lsp_types::{SemanticTokens, Url};
+use parking_lot::{Mutex, RwLock};
 use ra_db::{CrateId, VfsPath};
-use ra_ide::{Analysis, AnalysisChange, AnalysisHost, FileId};
+use ra_ide::{Analysis, AnalysisChange, AnalysisHost, Cancelable, FileId, PersistentCache};
 use ra_project_model::{CargoWorkspace, ProcMacroClient, ProjectWorkspace, Target};
 use rustc_hash::{FxHashMap, FxHashSet};
+use vfs::AbsPathBuf;
 
 use crate::{
     config::Config,
     diagnostics::{CheckFixes, DiagnosticCollection},
     from_proto,
     line_endings::LineEndings,
+    line_index::LineIndex,
+    lsp_ext,
     main_loop::Task,
     reload::SourceRootConfig,
     request_metrics::{LatestRequests, RequestMetrics},
@@ -61,17 +67,35 @@ pub(crate) struct GlobalState {
     sender: Sender<lsp_server::Message>,
     req_queue: ReqQueue,
     pub(crate) task_pool: Handle<TaskPool<Task>, Receiver<Task>>,
+    /// Runs requests the user is waiting for while typing, like completion,
+    /// so that they are never queued behind long-running analyses. Sends its
+    /// results to the receiver of `task_pool`.
+    pub(crate) latency_sensitive_pool: TaskPool<Task>,
     pub(crate) loader: Handle<Box<dyn vfs::loader::Handle>, Receiver<vfs::loader::Message>>,
-    pub(crate) flycheck: Option<Handle<FlycheckHandle, Receiver<flycheck::Message>>>,
+    pub(crate) flycheck: Vec<FlycheckHandle>,
+    pub(crate) flycheck_sender: Sender<flycheck::Message>,
+    pub(crate) flycheck_receiver: Receiver<flycheck::Message>,
     pub(crate) config: Config,
     pub(crate) analysis_host: AnalysisHost,
     pub(crate) diagnostics: DiagnosticCollection,
     pub(crate) mem_docs: FxHashSet<VfsPath>,
     pub(crate) vfs: Arc<RwLock<(vfs::Vfs, FxHashMap<FileId, LineEndings>)>>,
     pub(crate) status: Status,
+    pub(crate) fetching_workspaces: bool,
+    pub(crate) workspace_errors: Vec<String>,
+    pub(crate) proc_macro_error: Option<String>,
+    pub(crate) flycheck_error: Option<String>,
+    pub(crate) last_reported_status: Option<lsp_ext::StatusParams>,
     pub(crate) source_root_config: SourceRootConfig,
     pub(crate) proc_macro_client: ProcMacroClient,
     pub(crate) workspaces: Arc<Vec<ProjectWorkspace>>,
+    /// Modification times of the proc macro dylibs the crate graph was created
+    /// with.
+    pub(crate) proc_macro_dylibs: FxHashMap<AbsPathBuf, Option<SystemTime>>,
+    /// Opened files which don't belong to any of the workspaces.
+    pub(crate) detached_files: Vec<AbsPathBuf>,
+    /// The last semantic tokens sent for each document, to compute deltas from.
+    pub(crate) semantic_tokens_cache: Arc<Mutex<FxHashMap<Url, SemanticTokens>>>,
     latest_requests: Arc<RwLock<LatestRequests>>,
 }
 
@@ -83,6 +107,7 @@ pub(crate) struct GlobalStateSnapshot {
     pub(crate) latest_requests: Arc<RwLock<LatestRequests>>,
     vfs: Arc<RwLock<(vfs::Vfs, FxHashMap<FileId, LineEndings>)>>,
     pub(crate) workspaces: Arc<Vec<ProjectWorkspace>>,
+    pub(crate) semantic_tokens_cache: Arc<Mutex<FxHashMap<Url, SemanticTokens>>>,
 }
 
 impl GlobalState {
@@ -95,28 +120,49 @@ impl GlobalState {
             Handle { handle, receiver }
         };
 
-        let task_pool = {
-            let (sender, receiver) = unbounded();
-            let handle = TaskPool::new(sender);
-            Handle { handle, receiver }
-        };
+        let (task_sender, task_receiver) = unbounded();
+        let latency_sensitive_pool =
+            TaskPool::with_name(task_sender.clone(), "LatencySensitive", 2);
+        let task_pool = Handle { handle: TaskPool::new(task_sender), receiver: task_receiver };
+
+        let (flycheck_sender, flycheck_receiver) = unbounded();
 
-        let analysis_host = AnalysisHost::new(config.lru_capacity);
+        let mut analysis_host = AnalysisHost::default();
+        analysis_host.update_lru_config(config.lru);
+        analysis_host.set_macro_limits(config.macro_limits);
+        analysis_host.set_attr_macro_fallback(config.attr_macro_fallback);
+        if config.persistent_cache {
+            if let Some(dir) = ra_project_model::cache_dir() {
+                let cache = PersistentCache::load(&dir, config.root_path.as_ref(), env!("REV"));
+                analysis_host.set_persistent_cache(cache);
+            }
+        }
         GlobalState {
             sender,
             req_queue: ReqQueue::default(),
             task_pool,
+            latency_sensitive_pool,
             loader,
-            flycheck: None,
+            flycheck: Vec::new(),
+            flycheck_sender,
+            flycheck_receiver,
             config,
             analysis_host,
             diagnostics: Default::default(),
             mem_docs: FxHashSet::default(),
             vfs: Arc::new(RwLock::new((vfs::Vfs::default(), FxHashMap::default()))),
             status: Status::default(),
+            fetching_workspaces: false,
+            workspace_errors: Vec::new(),
+            proc_macro_error: None,
+            flycheck_error: None,
+            last_reported_status: None,
             source_root_config: SourceRootConfig::default(),
             proc_macro_client: ProcMacroClient::dummy(),
             workspaces: Arc::new(Vec::new()),
+            proc_macro_dylibs: FxHashMap::default(),
+            detached_files: Vec::new(),
+            semantic_tokens_cache: Arc::new(Default::default()),
             latest_requests: Default::default(),
         }
     }
@@ -159,6 +205,17 @@ impl GlobalState {
         true
     }
 
+    /// Tells the analysis which files are open in the editor.
+    pub(crate) fn update_open_files(&mut self) {
+        let open_files = {
+            let vfs = &self.vfs.read().0;
+            self.mem_docs.iter().filter_map(|path| vfs.file_id(path)).collect()
+        };
+        let mut change = AnalysisChange::new();
+        change.set_open_files(open_files);
+        self.analysis_host.apply_change(change);
+    }
+
     pub(crate) fn snapshot(&self) -> GlobalStateSnapshot {
         GlobalStateSnapshot {
             config: self.config.clone(),
@@ -167,6 +224,7 @@ impl GlobalState {
             vfs: Arc::clone(&self.vfs),
             latest_requests: Arc::clone(&self.latest_requests),
             check_fixes: Arc::clone(&self.diagnostics.check_fixes),
+            semantic_tokens_cache: Arc::clone(&self.semantic_tokens_cache),
         }
     }
 
@@ -235,6 +293,11 @@ impl GlobalStateSnapshot {
         file_id_to_url(&self.vfs.read().0, id)
     }
 
+    pub(crate) fn file_line_index(&self, file_id: FileId) -> Cancelable<LineIndex> {
+        let index = self.analysis.file_line_index(file_id)?;
+        Ok(LineIndex { index, encoding: self.config.client_caps.offset_encoding })
+    }
+
     pub(crate) fn file_line_endings(&self, id: FileId) -> LineEndings {
         self.vfs.read().1[&id]
     }
@@ -258,9 +321,26 @@ impl GlobalStateSnapshot {
             ProjectWorkspace::Cargo { cargo, .. } => {
                 cargo.target_by_root(&path).map(|it| (cargo, it))
             }
-            ProjectWorkspace::Json { .. } => None,
+            ProjectWorkspace::Json { .. } | ProjectWorkspace::DetachedFiles { .. } => None,
         })
     }
+
+    pub(crate) fn sysroot_crate_roots(&self) -> FxHashSet<FileId> {
+        sysroot_crate_roots(&self.workspaces, &self.vfs.read().0)
+    }
+}
+
+/// Returns the root files of the standard library crates of `workspaces`.
+pub(crate) fn sysroot_crate_roots(
+    workspaces: &[ProjectWorkspace],
+    vfs: &vfs::Vfs,
+) -> FxHashSet<FileId> {
+    workspaces
+        .iter()
+        .filter_map(|ws| ws.sysroot())
+        .flat_map(|sysroot| sysroot.crates().map(move |krate| sysroot[krate].root.clone()))
+        .filter_map(|root| vfs.file_id(&VfsPath::from(root)))
+        .collect()
 }
 
 pub(crate) fn file_id_to_url(vfs: &vfs::Vfs, id: FileId) -> Url {
@@ -3,6 +3,7 @@
 //! `ra_ide` crate.
 
 use std::{
+    fs,
     io::Write as _,
     process::{self, Stdio},
 };
@@ -13,17 +14,22 @@ use lsp_types::{
     CallHierarchyOutgoingCall, CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams,
     CodeLens, Command, CompletionItem, Diagnostic, DocumentFormattingParams, DocumentHighlight,
     DocumentSymbol, FoldingRange, FoldingRangeParams, HoverContents, Location, Position,
-    PrepareRenameResponse, Range, RenameParams, SemanticTokensParams, SemanticTokensRangeParams,
-    SemanticTokensRangeResult, SemanticTokensResult, SymbolInformation, TextDocumentIdentifier,
-    Url, WorkspaceEdit,
+    PrepareRenameResponse, Range, RenameParams, SemanticTokensEditsParams, SemanticTokensParams,
+    SemanticTokensRangeParams, SemanticTokensRangeResult, SemanticTokensResult, SymbolInformation,
+    TextDocumentIdentifier, Url, WorkspaceEdit,
 };
 use ra_ide::{
     FileId, FilePosition, FileRange, HoverAction, HoverGotoTypeData, NavigationTarget, Query,
-    RangeInfo, Runnable, RunnableKind, SearchScope, TextEdit,
+    RangeInfo, Runnable, RunnableKind, SearchScope, SourceChange, TextEdit,
 };
 use ra_prof::profile;
 use ra_project_model::TargetKind;
-use ra_syntax::{algo, ast, AstNode, SyntaxKind, TextRange, TextSize};
+use ra_syntax::{
+    algo,
+    ast::{self, NameOwner, VisibilityOwner},
+    AstNode, SyntaxKind, TextRange, TextSize,
+};
+use rustc_hash::FxHashSet;
 use serde::{Deserialize, Serialize};
 use serde_json::to_value;
 use stdx::{format_to, split_delim};
@@ -33,7 +39,7 @@ use crate::{
     config::RustfmtConfig,
     from_json, from_proto,
     global_state::{GlobalState, GlobalStateSnapshot},
-    lsp_ext::{self, InlayHint, InlayHintsParams},
+    lsp_ext::{self, InlayHint, InlayHintsParams, ProfileParams},
     to_proto, LspError, Result,
 };
 
@@ -73,25 +79,76 @@ pub(crate) fn handle_memory_usage(state: &mut GlobalState, _: ()) -> Result<Stri
     Ok(out)
 }
 
+pub(crate) fn handle_profile(_state: &mut GlobalState, params: ProfileParams) -> Result<String> {
+    match params.filter {
+        Some(filter) => {
+            ra_prof::take_summary();
+            ra_prof::init_summary_from(&filter);
+            Ok(String::new())
+        }
+        None => {
+            // Goes back to the `RA_PROFILE` the server was started with.
+            ra_prof::init();
+            let summary = ra_prof::take_summary();
+            if let Some(path) = params.folded_stacks_path {
+                let mut file = fs::File::create(path)?;
+                summary.write_folded_stacks(&mut file)?;
+            }
+            Ok(summary.to_string())
+        }
+    }
+}
+
 pub(crate) fn handle_syntax_tree(
     snap: GlobalStateSnapshot,
     params: lsp_ext::SyntaxTreeParams,
 ) -> Result<String> {
     let _p = profile("handle_syntax_tree");
     let id = from_proto::file_id(&snap, &params.text_document.uri)?;
-    let line_index = snap.analysis.file_line_index(id)?;
+    let line_index = snap.file_line_index(id)?;
     let text_range = params.range.map(|r| from_proto::text_range(&line_index, r));
     let res = snap.analysis.syntax_tree(id, text_range)?;
     Ok(res)
 }
 
+pub(crate) fn handle_view_hir(
+    snap: GlobalStateSnapshot,
+    params: lsp_types::TextDocumentPositionParams,
+) -> Result<String> {
+    let _p = profile("handle_view_hir");
+    let position = from_proto::file_position(&snap, params)?;
+    let res = snap.analysis.view_hir(position)?;
+    Ok(res)
+}
+
+pub(crate) fn handle_view_item_tree(
+    snap: GlobalStateSnapshot,
+    params: lsp_ext::ViewItemTreeParams,
+) -> Result<String> {
+    let _p = profile("handle_view_item_tree");
+    let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
+    let res = snap.analysis.view_item_tree(file_id)?;
+    Ok(res)
+}
+
+pub(crate) fn handle_view_crate_graph(
+    snap: GlobalStateSnapshot,
+    params: lsp_ext::ViewCrateGraphParams,
+) -> Result<String> {
+    let _p = profile("handle_view_crate_graph");
+    let excluded_roots =
+        if params.exclude_sysroot { snap.sysroot_crate_roots() } else { FxHashSet::default() };
+    let res = snap.analysis.view_crate_graph(excluded_roots)?;
+    Ok(res)
+}
+
 pub(crate) fn handle_expand_macro(
     snap: GlobalStateSnapshot,
     params: lsp_ext::ExpandMacroParams,
 ) -> Result<Option<lsp_ext::ExpandedMacro>> {
     let _p = profile("handle_expand_macro");
     let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
     let offset = from_proto::offset(&line_index, params.position);
 
     let res = snap.analysis.expand_macro(FilePosition { file_id, offset })?;
@@ -104,7 +161,7 @@ pub(crate) fn handle_selection_range(
 ) -> Result<Option<Vec<lsp_types::SelectionRange>>> {
     let _p = profile("handle_selection_range");
     let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
     let res: Result<Vec<lsp_types::SelectionRange>> = params
         .positions
         .into_iter()
@@ -147,7 +204,7 @@ pub(crate) fn handle_matching_brace(
 ) -> Result<Vec<Position>> {
     let _p = profile("handle_matching_brace");
     let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
     let res = params
         .positions
         .into_iter()
@@ -169,7 +226,7 @@ pub(crate) fn handle_join_lines(
 ) -> Result<Vec<lsp_types::TextEdit>> {
     let _p = profile("handle_join_lines");
     let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
     let line_endings = snap.file_line_endings(file_id);
     let mut res = TextEdit::default();
     for range in params.ranges {
@@ -196,12 +253,32 @@ pub(crate) fn handle_on_enter(
         None => return Ok(None),
         Some(it) => it,
     };
-    let line_index = snap.analysis.file_line_index(position.file_id)?;
+    let line_index = snap.file_line_index(position.file_id)?;
     let line_endings = snap.file_line_endings(position.file_id);
     let edit = to_proto::snippet_text_edit_vec(&line_index, line_endings, true, edit);
     Ok(Some(edit))
 }
 
+pub(crate) fn handle_move_item(
+    snap: GlobalStateSnapshot,
+    params: lsp_ext::MoveItemParams,
+) -> Result<Vec<lsp_ext::SnippetTextEdit>> {
+    let _p = profile("handle_move_item");
+    let frange = from_proto::file_range(&snap, params.text_document, params.range)?;
+    let direction = match params.direction {
+        lsp_ext::MoveItemDirection::Up => ra_ide::Direction::Up,
+        lsp_ext::MoveItemDirection::Down => ra_ide::Direction::Down,
+    };
+    let edit = match snap.analysis.move_item(frange, direction)? {
+        None => return Ok(Vec::new()),
+        Some(it) => it,
+    };
+    let line_index = snap.file_line_index(frange.file_id)?;
+    let line_endings = snap.file_line_endings(frange.file_id);
+    let edit = to_proto::snippet_text_edit_vec(&line_index, line_endings, true, edit);
+    Ok(edit)
+}
+
 // Don't forget to add new trigger characters to `ServerCapabilities` in `caps.rs`.
 pub(crate) fn handle_on_type_formatting(
     snap: GlobalStateSnapshot,
@@ -209,7 +286,7 @@ pub(crate) fn handle_on_type_formatting(
 ) -> Result<Option<Vec<lsp_types::TextEdit>>> {
     let _p = profile("handle_on_type_formatting");
     let mut position = from_proto::file_position(&snap, params.text_document_position)?;
-    let line_index = snap.analysis.file_line_index(position.file_id)?;
+    let line_index = snap.file_line_index(position.file_id)?;
     let line_endings = snap.file_line_endings(position.file_id);
 
     // in `ra_ide`, the `on_type` invariant is that
@@ -248,7 +325,7 @@ pub(crate) fn handle_document_symbol(
 ) -> Result<Option<lsp_types::DocumentSymbolResponse>> {
     let _p = profile("handle_document_symbol");
     let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
 
     let mut parents: Vec<(DocumentSymbol, Option<usize>)> = Vec::new();
 
@@ -265,18 +342,21 @@ pub(crate) fn handle_document_symbol(
         parents.push((doc_symbol, symbol.parent));
     }
     let mut document_symbols = Vec::new();
-    while let Some((node, parent)) = parents.pop() {
-        match parent {
-            None => document_symbols.push(node),
-            Some(i) => {
-                let children = &mut parents[i].0.children;
-                if children.is_none() {
-                    *children = Some(Vec::new());
-                }
-                children.as_mut().unwrap().push(node);
-            }
+    // Parents always come before their children, so popping from the end
+    // attaches every node to its (still unpopped) parent. Both the children
+    // and the top-level symbols are collected backwards and are reversed to
+    // preserve the source order.
+    while let Some((mut node, parent)) = parents.pop() {
+        if let Some(children) = &mut node.children {
+            children.reverse();
         }
+        let siblings = match parent {
+            None => &mut document_symbols,
+            Some(i) => parents[i].0.children.get_or_insert_with(Vec::new),
+        };
+        siblings.push(node);
     }
+    document_symbols.reverse();
 
     let res = if snap.config.client_caps.hierarchical_symbols {
         document_symbols.into()
@@ -315,26 +395,9 @@ pub(crate) fn handle_workspace_symbol(
     params: lsp_types::WorkspaceSymbolParams,
 ) -> Result<Option<Vec<SymbolInformation>>> {
     let _p = profile("handle_workspace_symbol");
-    let all_symbols = params.query.contains('#');
-    let libs = params.query.contains('*');
-    let query = {
-        let query: String = params.query.chars().filter(|&c| c != '#' && c != '*').collect();
-        let mut q = Query::new(query);
-        if !all_symbols {
-            q.only_types();
-        }
-        if libs {
-            q.libs();
-        }
-        q.limit(128);
-        q
-    };
-    let mut res = exec_query(&snap, query)?;
-    if res.is_empty() && !all_symbols {
-        let mut query = Query::new(params.query);
-        query.limit(128);
-        res = exec_query(&snap, query)?;
-    }
+    let mut query = Query::parse(&params.query);
+    query.limit(128);
+    let res = exec_query(&snap, query)?;
 
     return Ok(Some(res));
 
@@ -411,13 +474,101 @@ pub(crate) fn handle_parent_module(
     Ok(Some(res))
 }
 
+pub(crate) fn handle_open_cargo_toml(
+    snap: GlobalStateSnapshot,
+    params: lsp_ext::OpenCargoTomlParams,
+) -> Result<Option<lsp_types::GotoDefinitionResponse>> {
+    let _p = profile("handle_open_cargo_toml");
+    let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
+    let crate_id = match snap.analysis.crate_for(file_id)?.first() {
+        Some(it) => *it,
+        None => return Ok(None),
+    };
+    let (cargo, target) = match snap.cargo_target_for_crate_root(crate_id) {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let package = &cargo[cargo[target].package];
+
+    let dependency = match params.position {
+        Some(position) => {
+            let position = from_proto::file_position(
+                &snap,
+                lsp_types::TextDocumentPositionParams {
+                    text_document: params.text_document,
+                    position,
+                },
+            )?;
+            snap.analysis.dependency_at(position)?
+        }
+        None => None,
+    };
+    let line = dependency.and_then(|dep| {
+        let (dep_cargo, dep_target) = snap.cargo_target_for_crate_root(dep)?;
+        let dep_name = &dep_cargo[dep_cargo[dep_target].package].name;
+        // The dependency might be renamed in the manifest.
+        let mut names = vec![dep_name.as_str()];
+        names.extend(
+            package
+                .dependencies
+                .iter()
+                .filter(|it| &cargo[it.pkg].name == dep_name)
+                .map(|it| it.name.as_str()),
+        );
+        let manifest = std::fs::read_to_string(&package.manifest).ok()?;
+        find_dependency_line(&manifest, &names)
+    });
+
+    let position = Position::new(line.unwrap_or(0), 0);
+    let location = Location::new(
+        to_proto::url_from_abs_path(&package.manifest),
+        Range::new(position, position),
+    );
+    Ok(Some(location.into()))
+}
+
+/// Finds the line declaring one of the `names` in a `[*dependencies]` table.
+fn find_dependency_line(manifest: &str, names: &[&str]) -> Option<u64> {
+    let normalize = |name: &str| name.trim_matches('"').replace('-', "_");
+    let names = names.iter().map(|it| normalize(it)).collect::<Vec<_>>();
+    let mut in_dependencies = false;
+    for (idx, line) in manifest.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            let header = line.trim_start_matches('[').trim_end_matches(']').trim();
+            let mut segments = header.rsplitn(2, '.');
+            let last = segments.next().unwrap_or_default();
+            // `[dependencies.foo]` style tables.
+            if let Some(table) = segments.next() {
+                if table.ends_with("dependencies") && names.contains(&normalize(last)) {
+                    return Some(idx as u64);
+                }
+            }
+            in_dependencies = last.ends_with("dependencies");
+            continue;
+        }
+        if !in_dependencies {
+            continue;
+        }
+        // Dotted keys like `serde.version = "1.0"` also declare `serde`.
+        let key = match split_delim(line, '=') {
+            Some((key, _)) => key.split('.').next().unwrap_or_default().trim(),
+            None => continue,
+        };
+        if names.contains(&normalize(key)) {
+            return Some(idx as u64);
+        }
+    }
+    None
+}
+
 pub(crate) fn handle_runnables(
     snap: GlobalStateSnapshot,
     params: lsp_ext::RunnablesParams,
 ) -> Result<Vec<lsp_ext::Runnable>> {
     let _p = profile("handle_runnables");
     let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
     let offset = params.position.map(|it| from_proto::offset(&line_index, it));
     let cargo_spec = CargoTargetSpec::for_file(&snap, file_id)?;
 
@@ -450,6 +601,7 @@ pub(crate) fn handle_runnables(
     }
 
     // Add `cargo check` and `cargo test` for the whole package
+    let config = &snap.config.runnables;
     match cargo_spec {
         Some(spec) => {
             for &cmd in ["check", "test"].iter() {
@@ -459,11 +611,13 @@ pub(crate) fn handle_runnables(
                     kind: lsp_ext::RunnableKind::Cargo,
                     args: lsp_ext::CargoRunnable {
                         workspace_root: Some(spec.workspace_root.clone().into()),
+                        override_cargo: config.override_cargo.clone(),
                         cargo_args: vec![
                             cmd.to_string(),
                             "--package".to_string(),
                             spec.package.clone(),
                         ],
+                        cargo_extra_args: config.cargo_extra_args.clone(),
                         executable_args: Vec::new(),
                         expect_test: None,
                     },
@@ -477,7 +631,9 @@ pub(crate) fn handle_runnables(
                 kind: lsp_ext::RunnableKind::Cargo,
                 args: lsp_ext::CargoRunnable {
                     workspace_root: None,
+                    override_cargo: config.override_cargo.clone(),
                     cargo_args: vec!["check".to_string(), "--workspace".to_string()],
+                    cargo_extra_args: config.cargo_extra_args.clone(),
                     executable_args: Vec::new(),
                     expect_test: None,
                 },
@@ -519,7 +675,7 @@ pub(crate) fn handle_completion(
         None => return Ok(None),
         Some(items) => items,
     };
-    let line_index = snap.analysis.file_line_index(position.file_id)?;
+    let line_index = snap.file_line_index(position.file_id)?;
     let line_endings = snap.file_line_endings(position.file_id);
     let items: Vec<CompletionItem> = items
         .into_iter()
@@ -537,7 +693,7 @@ pub(crate) fn handle_folding_range(
     let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
     let folds = snap.analysis.folding_ranges(file_id)?;
     let text = snap.analysis.file_text(file_id)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
     let line_folding_only = snap.config.client_caps.line_folding_only;
     let res = folds
         .into_iter()
@@ -580,7 +736,7 @@ pub(crate) fn handle_hover(
         None => return Ok(None),
         Some(info) => info,
     };
-    let line_index = snap.analysis.file_line_index(position.file_id)?;
+    let line_index = snap.file_line_index(position.file_id)?;
     let range = to_proto::range(&line_index, info.range);
     let hover = lsp_ext::Hover {
         hover: lsp_types::Hover {
@@ -606,7 +762,7 @@ pub(crate) fn handle_prepare_rename(
         Some(it) => it.range,
     };
 
-    let line_index = snap.analysis.file_line_index(position.file_id)?;
+    let line_index = snap.file_line_index(position.file_id)?;
     let range = to_proto::range(&line_index, range);
     Ok(Some(PrepareRenameResponse::Range(range)))
 }
@@ -635,6 +791,62 @@ pub(crate) fn handle_rename(
     Ok(Some(workspace_edit))
 }
 
+pub(crate) fn handle_will_rename_files(
+    snap: GlobalStateSnapshot,
+    params: lsp_ext::RenameFilesParams,
+) -> Result<Option<WorkspaceEdit>> {
+    let _p = profile("handle_will_rename_files");
+
+    let mut source_file_edits = Vec::new();
+    for file_rename in params.files {
+        let (from, to) = match (Url::parse(&file_rename.old_uri), Url::parse(&file_rename.new_uri))
+        {
+            (Ok(from), Ok(to)) => (from, to),
+            _ => continue,
+        };
+        let (from_path, to_path) = match (from.to_file_path(), to.to_file_path()) {
+            (Ok(from_path), Ok(to_path)) => (from_path, to_path),
+            _ => continue,
+        };
+        // Only renames within the same directory are supported, as anything
+        // else would move modules to other parents.
+        if from_path.parent() != to_path.parent() {
+            continue;
+        }
+        let (file_id, new_name_stem) = if from_path.is_dir() {
+            // Renaming `foo/` renames the module in `foo/mod.rs`.
+            let mod_rs = match Url::from_file_path(from_path.join("mod.rs")) {
+                Ok(it) => it,
+                Err(()) => continue,
+            };
+            let new_name = to_path.file_name().and_then(|it| it.to_str());
+            match (snap.url_to_file_id(&mod_rs), new_name) {
+                (Ok(file_id), Some(new_name)) => (file_id, new_name.to_string()),
+                _ => continue,
+            }
+        } else {
+            let old_stem = from_path.file_stem().and_then(|it| it.to_str());
+            let new_stem = to_path.file_stem().and_then(|it| it.to_str());
+            match (snap.url_to_file_id(&from), old_stem, new_stem) {
+                (_, Some("mod"), _) | (_, _, Some("mod")) => continue,
+                (Ok(file_id), Some(_), Some(new_stem)) => (file_id, new_stem.to_string()),
+                _ => continue,
+            }
+        };
+        if let Some(source_change) = snap.analysis.will_rename_file(file_id, &new_name_stem)? {
+            source_file_edits.extend(source_change.source_file_edits);
+        }
+    }
+
+    if source_file_edits.is_empty() {
+        return Ok(None);
+    }
+    // The client moves the files itself.
+    let source_change = SourceChange::from_edits(source_file_edits, Vec::new());
+    let workspace_edit = to_proto::workspace_edit(&snap, source_change)?;
+    Ok(Some(workspace_edit))
+}
+
 pub(crate) fn handle_references(
     snap: GlobalStateSnapshot,
     params: lsp_types::ReferenceParams,
@@ -671,7 +883,7 @@ pub(crate) fn handle_formatting(
     let file = snap.analysis.file_text(file_id)?;
     let crate_ids = snap.analysis.crate_for(file_id)?;
 
-    let file_line_index = snap.analysis.file_line_index(file_id)?;
+    let file_line_index = snap.file_line_index(file_id)?;
     let end_position = to_proto::position(&file_line_index, TextSize::of(file.as_str()));
 
     let mut rustfmt = match &snap.config.rustfmt {
@@ -744,7 +956,7 @@ fn handle_fixes(
     res: &mut Vec<lsp_ext::CodeAction>,
 ) -> Result<()> {
     let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
     let range = from_proto::text_range(&line_index, params.range);
     let diagnostics = snap.analysis.diagnostics(file_id)?;
 
@@ -767,11 +979,16 @@ fn handle_fixes(
         res.push(action);
     }
 
-    for fix in snap.check_fixes.get(&file_id).into_iter().flatten() {
+    for fix in snap.check_fixes.values().filter_map(|it| it.get(&file_id)).flatten() {
         let fix_range = from_proto::text_range(&line_index, fix.range);
         if fix_range.intersect(range).is_none() {
             continue;
         }
+        // A diagnostic with several primary spans shares its fixes between all
+        // of them, and rustc often suggests the same fix as our own diagnostics.
+        if res.iter().any(|it| it.title == fix.action.title) {
+            continue;
+        }
         res.push(fix.action.clone());
     }
     Ok(())
@@ -790,7 +1007,7 @@ pub(crate) fn handle_code_action(
     }
 
     let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
     let range = from_proto::text_range(&line_index, params.range);
     let frange = FileRange { file_id, range };
     let mut res: Vec<lsp_ext::CodeAction> = Vec::new();
@@ -818,7 +1035,7 @@ pub(crate) fn handle_resolve_code_action(
 ) -> Result<Option<lsp_ext::SnippetWorkspaceEdit>> {
     let _p = profile("handle_resolve_code_action");
     let file_id = from_proto::file_id(&snap, &params.code_action_params.text_document.uri)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
     let range = from_proto::text_range(&line_index, params.code_action_params.range);
     let frange = FileRange { file_id, range };
 
@@ -843,7 +1060,7 @@ pub(crate) fn handle_code_lens(
     }
 
     let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
     let cargo_spec = CargoTargetSpec::for_file(&snap, file_id)?;
 
     if snap.config.lens.runnable() {
@@ -875,17 +1092,21 @@ pub(crate) fn handle_code_lens(
 
     if snap.config.lens.implementations {
         // Handle impls
+        let structure = snap.analysis.file_structure(file_id)?;
         lenses.extend(
-            snap.analysis
-                .file_structure(file_id)?
-                .into_iter()
+            structure
+                .iter()
                 .filter(|it| match it.kind {
                     SyntaxKind::TRAIT_DEF | SyntaxKind::STRUCT_DEF | SyntaxKind::ENUM_DEF => true,
+                    // Trait methods
+                    SyntaxKind::FN_DEF => {
+                        it.parent.map_or(false, |idx| structure[idx].kind == SyntaxKind::TRAIT_DEF)
+                    }
                     _ => false,
                 })
                 .map(|it| {
                     let range = to_proto::range(&line_index, it.node_range);
-                    let pos = range.start;
+                    let pos = to_proto::position(&line_index, it.navigation_range.start());
                     let lens_params = lsp_types::request::GotoImplementationParams {
                         text_document_position_params: lsp_types::TextDocumentPositionParams::new(
                             params.text_document.clone(),
@@ -902,6 +1123,23 @@ pub(crate) fn handle_code_lens(
                 }),
         );
     }
+
+    if snap.config.lens.references {
+        // Handle references of public items, the count is computed lazily on resolve
+        let source_file = snap.analysis.parse(file_id)?;
+        lenses.extend(public_item_names(&source_file).map(|name| {
+            let range = to_proto::range(&line_index, name.syntax().text_range());
+            let lens_params = lsp_types::TextDocumentPositionParams::new(
+                params.text_document.clone(),
+                range.start,
+            );
+            CodeLens {
+                range,
+                command: None,
+                data: Some(to_value(CodeLensResolveData::References(lens_params)).unwrap()),
+            }
+        }));
+    }
     Ok(Some(lenses))
 }
 
@@ -909,6 +1147,7 @@ pub(crate) fn handle_code_lens(
 #[serde(rename_all = "camelCase")]
 enum CodeLensResolveData {
     Impls(lsp_types::request::GotoImplementationParams),
+    References(lsp_types::TextDocumentPositionParams),
 }
 
 pub(crate) fn handle_code_lens_resolve(
@@ -940,6 +1179,24 @@ pub(crate) fn handle_code_lens_resolve(
             );
             Ok(CodeLens { range: code_lens.range, command: Some(cmd), data: None })
         }
+        Some(CodeLensResolveData::References(position)) => {
+            let params = lsp_types::ReferenceParams {
+                text_document_position: position.clone(),
+                context: lsp_types::ReferenceContext { include_declaration: false },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            };
+            let locations = handle_references(snap, params)?.unwrap_or_default();
+
+            let title = reference_title(locations.len());
+            let cmd = show_references_command(
+                title,
+                &position.text_document.uri,
+                code_lens.range.start,
+                locations,
+            );
+            Ok(CodeLens { range: code_lens.range, command: Some(cmd), data: None })
+        }
         None => Ok(CodeLens {
             range: code_lens.range,
             command: Some(Command { title: "Error".into(), ..Default::default() }),
@@ -954,7 +1211,18 @@ pub(crate) fn handle_document_highlight(
 ) -> Result<Option<Vec<DocumentHighlight>>> {
     let _p = profile("handle_document_highlight");
     let position = from_proto::file_position(&snap, params.text_document_position_params)?;
-    let line_index = snap.analysis.file_line_index(position.file_id)?;
+    let line_index = snap.file_line_index(position.file_id)?;
+
+    if let Some(ranges) = snap.analysis.highlight_related(position)? {
+        let res = ranges
+            .into_iter()
+            .map(|range| DocumentHighlight {
+                range: to_proto::range(&line_index, range),
+                kind: None,
+            })
+            .collect();
+        return Ok(Some(res));
+    }
 
     let refs = match snap
         .analysis
@@ -990,7 +1258,7 @@ pub(crate) fn publish_diagnostics(
     file_id: FileId,
 ) -> Result<Vec<Diagnostic>> {
     let _p = profile("publish_diagnostics");
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
     let diagnostics: Vec<Diagnostic> = snap
         .analysis
         .diagnostics(file_id)?
@@ -998,7 +1266,7 @@ pub(crate) fn publish_diagnostics(
         .map(|d| Diagnostic {
             range: to_proto::range(&line_index, d.range),
             severity: Some(to_proto::diagnostic_severity(d.severity)),
-            code: None,
+            code: Some(lsp_types::NumberOrString::String(d.name.to_string())),
             source: Some("rust-analyzer".to_string()),
             message: d.message,
             related_information: None,
@@ -1014,7 +1282,7 @@ pub(crate) fn handle_inlay_hints(
 ) -> Result<Vec<InlayHint>> {
     let _p = profile("handle_inlay_hints");
     let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
     Ok(snap
         .analysis
         .inlay_hints(file_id, &snap.config.inlay_hints)?
@@ -1065,7 +1333,7 @@ pub(crate) fn handle_call_hierarchy_incoming(
 
     for call_item in call_items.into_iter() {
         let file_id = call_item.target.file_id();
-        let line_index = snap.analysis.file_line_index(file_id)?;
+        let line_index = snap.file_line_index(file_id)?;
         let item = to_proto::call_hierarchy_item(&snap, call_item.target)?;
         res.push(CallHierarchyIncomingCall {
             from: item,
@@ -1100,7 +1368,7 @@ pub(crate) fn handle_call_hierarchy_outgoing(
 
     for call_item in call_items.into_iter() {
         let file_id = call_item.target.file_id();
-        let line_index = snap.analysis.file_line_index(file_id)?;
+        let line_index = snap.file_line_index(file_id)?;
         let item = to_proto::call_hierarchy_item(&snap, call_item.target)?;
         res.push(CallHierarchyOutgoingCall {
             to: item,
@@ -1123,13 +1391,46 @@ pub(crate) fn handle_semantic_tokens(
 
     let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
     let text = snap.analysis.file_text(file_id)?;
-    let line_index = snap.analysis.file_line_index(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
 
     let highlights = snap.analysis.highlight(file_id)?;
     let semantic_tokens = to_proto::semantic_tokens(&text, &line_index, highlights);
+
+    // Unconditionally cache the tokens
+    snap.semantic_tokens_cache.lock().insert(params.text_document.uri, semantic_tokens.clone());
+
     Ok(Some(semantic_tokens.into()))
 }
 
+pub(crate) fn handle_semantic_tokens_delta(
+    snap: GlobalStateSnapshot,
+    params: SemanticTokensEditsParams,
+) -> Result<Option<lsp_ext::SemanticTokensDeltaResult>> {
+    let _p = profile("handle_semantic_tokens_delta");
+
+    let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
+    let text = snap.analysis.file_text(file_id)?;
+    let line_index = snap.file_line_index(file_id)?;
+
+    let highlights = snap.analysis.highlight(file_id)?;
+    let semantic_tokens = to_proto::semantic_tokens(&text, &line_index, highlights);
+
+    let mut cache = snap.semantic_tokens_cache.lock();
+    let cached_tokens = cache.entry(params.text_document.uri).or_default();
+
+    if let Some(prev_id) = &cached_tokens.result_id {
+        if *prev_id == params.previous_result_id {
+            let delta = to_proto::semantic_token_delta(&cached_tokens, &semantic_tokens);
+            *cached_tokens = semantic_tokens;
+            return Ok(Some(lsp_ext::SemanticTokensDeltaResult::TokensDelta(delta)));
+        }
+    }
+
+    *cached_tokens = semantic_tokens.clone();
+
+    Ok(Some(lsp_ext::SemanticTokensDeltaResult::Tokens(semantic_tokens)))
+}
+
 pub(crate) fn handle_semantic_tokens_range(
     snap: GlobalStateSnapshot,
     params: SemanticTokensRangeParams,
@@ -1138,7 +1439,7 @@ pub(crate) fn handle_semantic_tokens_range(
 
     let frange = from_proto::file_range(&snap, params.text_document, params.range)?;
     let text = snap.analysis.file_text(frange.file_id)?;
-    let line_index = snap.analysis.file_line_index(frange.file_id)?;
+    let line_index = snap.file_line_index(frange.file_id)?;
 
     let highlights = snap.analysis.highlight_range(frange)?;
     let semantic_tokens = to_proto::semantic_tokens(&text, &line_index, highlights);
@@ -1153,6 +1454,14 @@ fn implementation_title(count: usize) -> String {
     }
 }
 
+fn reference_title(count: usize) -> String {
+    if count == 1 {
+        "1 reference".into()
+    } else {
+        format!("{} references", count)
+    }
+}
+
 fn show_references_command(
     title: String,
     uri: &lsp_types::Url,
@@ -1218,7 +1527,7 @@ fn show_impl_command_link(
     if snap.config.hover.implementations {
         if let Some(nav_data) = snap.analysis.goto_implementation(*position).unwrap_or(None) {
             let uri = to_proto::url(snap, position.file_id);
-            let line_index = snap.analysis.file_line_index(position.file_id).ok()?;
+            let line_index = snap.file_line_index(position.file_id).ok()?;
             let position = to_proto::position(&line_index, position.offset);
             let locations: Vec<_> = nav_data
                 .info
@@ -1316,3 +1625,88 @@ fn should_skip_target(runnable: &Runnable, cargo_spec: Option<&CargoTargetSpec>)
         _ => false,
     }
 }
+
+/// Returns the names of the `pub` items of the modules in `file`.
+fn public_item_names(file: &ast::SourceFile) -> impl Iterator<Item = ast::Name> {
+    file.syntax()
+        .descendants()
+        .filter_map(ast::ModuleItem::cast)
+        .filter(|it| {
+            it.visibility().map_or(false, |it| matches!(it.kind(), ast::VisibilityKind::Pub))
+        })
+        .filter(|it| match it.syntax().parent() {
+            Some(parent) if parent.kind() == SyntaxKind::SOURCE_FILE => true,
+            Some(parent) if parent.kind() == SyntaxKind::ITEM_LIST => {
+                parent.parent().map_or(false, |it| it.kind() == SyntaxKind::MODULE)
+            }
+            _ => false,
+        })
+        .filter_map(|it| it.name())
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::{ast::NameOwner, SourceFile};
+
+    use super::{find_dependency_line, public_item_names};
+
+    const MANIFEST: &str = r#"
+[package]
+name = "foo"
+
+[dependencies]
+serde = "1.0"
+regex.version = "1"
+my-log = { package = "log", version = "0.4" }
+
+[dev-dependencies]
+expect = "0.1"
+
+[target.'cfg(unix)'.dependencies.libc]
+version = "0.2"
+"#;
+
+    #[test]
+    fn finds_dependency_lines() {
+        assert_eq!(find_dependency_line(MANIFEST, &["serde"]), Some(5));
+        assert_eq!(find_dependency_line(MANIFEST, &["expect"]), Some(10));
+        assert_eq!(find_dependency_line(MANIFEST, &["libc"]), Some(12));
+        assert_eq!(find_dependency_line(MANIFEST, &["foo"]), None);
+    }
+
+    #[test]
+    fn finds_dotted_keys() {
+        assert_eq!(find_dependency_line(MANIFEST, &["regex"]), Some(6));
+    }
+
+    #[test]
+    fn lenses_only_public_items_of_modules() {
+        let file = SourceFile::parse(
+            r#"
+pub fn a() {
+    pub fn nested() {}
+}
+pub(crate) struct B;
+pub(super) fn c() {}
+fn private() {}
+pub mod m {
+    pub struct D;
+    pub(self) fn e() {}
+}
+pub struct S;
+impl S {
+    pub fn method() {}
+}
+"#,
+        )
+        .tree();
+        let names = public_item_names(&file).map(|it| it.text().to_string()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["a", "m", "D", "S"]);
+    }
+
+    #[test]
+    fn finds_renamed_packages() {
+        assert_eq!(find_dependency_line(MANIFEST, &["log", "my-log"]), Some(7));
+        assert_eq!(find_dependency_line(MANIFEST, &["log", "my_log"]), Some(7));
+    }
+}
@@ -30,6 +30,7 @@ mod semantic_tokens;
 mod markdown;
 mod diagnostics;
 mod line_endings;
+mod line_index;
 mod request_metrics;
 mod lsp_utils;
 mod thread_pool;
@@ -39,7 +40,10 @@ pub mod config;
 use serde::de::DeserializeOwned;
 
 pub type Result<T, E = Box<dyn std::error::Error + Send + Sync>> = std::result::Result<T, E>;
-pub use crate::{caps::server_capabilities, main_loop::main_loop};
+pub use crate::{
+    caps::{file_operations_capabilities, negotiate_offset_encoding, server_capabilities},
+    main_loop::main_loop,
+};
 use std::fmt;
 
 pub fn from_json<T: DeserializeOwned>(what: &'static str, json: serde_json::Value) -> Result<T> {
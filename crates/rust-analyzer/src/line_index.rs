@@ -0,0 +1,10 @@
+//! Enhances `ra_ide::LineIndex` with the offset encoding negotiated with the
+//! client, which is required to convert between offsets and LSP positions.
+use std::sync::Arc;
+
+use crate::config::OffsetEncoding;
+
+pub(crate) struct LineIndex {
+    pub(crate) index: Arc<ra_ide::LineIndex>,
+    pub(crate) encoding: OffsetEncoding,
+}
@@ -22,6 +22,23 @@ impl Request for MemoryUsage {
     const METHOD: &'static str = "rust-analyzer/memoryUsage";
 }
 
+pub enum Profile {}
+
+impl Request for Profile {
+    type Params = ProfileParams;
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/profile";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileParams {
+    /// Starts profiling with the given `RA_PROFILE` filter, stops it if `None`.
+    pub filter: Option<String>,
+    /// Where to write the folded stacks when profiling is stopped.
+    pub folded_stacks_path: Option<PathBuf>,
+}
+
 pub enum ReloadWorkspace {}
 
 impl Request for ReloadWorkspace {
@@ -30,6 +47,55 @@ impl Request for ReloadWorkspace {
     const METHOD: &'static str = "rust-analyzer/reloadWorkspace";
 }
 
+/// `textDocument/semanticTokens/full/delta`, which `lsp_types` doesn't know
+/// about yet.
+pub enum SemanticTokensDelta {}
+
+impl Request for SemanticTokensDelta {
+    type Params = lsp_types::SemanticTokensEditsParams;
+    type Result = Option<SemanticTokensDeltaResult>;
+    const METHOD: &'static str = "textDocument/semanticTokens/full/delta";
+}
+
+/// The same request under the name of an earlier version of the proposal,
+/// which is what the VS Code client sends.
+pub enum SemanticTokensEdits {}
+
+impl Request for SemanticTokensEdits {
+    type Params = lsp_types::SemanticTokensEditsParams;
+    type Result = Option<SemanticTokensDeltaResult>;
+    const METHOD: &'static str = "textDocument/semanticTokens/edits";
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum SemanticTokensDeltaResult {
+    Tokens(lsp_types::SemanticTokens),
+    TokensDelta(lsp_types::SemanticTokensEdits),
+}
+
+/// `workspace/willRenameFiles`, which `lsp_types` doesn't know about yet.
+pub enum WillRenameFiles {}
+
+impl Request for WillRenameFiles {
+    type Params = RenameFilesParams;
+    type Result = Option<lsp_types::WorkspaceEdit>;
+    const METHOD: &'static str = "workspace/willRenameFiles";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameFilesParams {
+    pub files: Vec<FileRename>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileRename {
+    pub old_uri: String,
+    pub new_uri: String,
+}
+
 pub enum SyntaxTree {}
 
 impl Request for SyntaxTree {
@@ -45,6 +111,43 @@ pub struct SyntaxTreeParams {
     pub range: Option<Range>,
 }
 
+pub enum ViewHir {}
+
+impl Request for ViewHir {
+    type Params = lsp_types::TextDocumentPositionParams;
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/viewHir";
+}
+
+pub enum ViewItemTree {}
+
+impl Request for ViewItemTree {
+    type Params = ViewItemTreeParams;
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/viewItemTree";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewItemTreeParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+pub enum ViewCrateGraph {}
+
+impl Request for ViewCrateGraph {
+    type Params = ViewCrateGraphParams;
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/viewCrateGraph";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewCrateGraphParams {
+    /// Hide the standard library crates.
+    pub exclude_sysroot: bool,
+}
+
 pub enum ExpandMacro {}
 
 impl Request for ExpandMacro {
@@ -90,6 +193,22 @@ impl Request for ParentModule {
     const METHOD: &'static str = "experimental/parentModule";
 }
 
+pub enum OpenCargoToml {}
+
+impl Request for OpenCargoToml {
+    type Params = OpenCargoTomlParams;
+    type Result = Option<lsp_types::GotoDefinitionResponse>;
+    const METHOD: &'static str = "experimental/openCargoToml";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenCargoTomlParams {
+    pub text_document: TextDocumentIdentifier,
+    /// If the cursor is on a dependency crate, we point at its declaration.
+    pub position: Option<Position>,
+}
+
 pub enum JoinLines {}
 
 impl Request for JoinLines {
@@ -129,6 +248,28 @@ impl Request for OnEnter {
     const METHOD: &'static str = "experimental/onEnter";
 }
 
+pub enum MoveItem {}
+
+impl Request for MoveItem {
+    type Params = MoveItemParams;
+    type Result = Vec<SnippetTextEdit>;
+    const METHOD: &'static str = "experimental/moveItem";
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveItemParams {
+    pub direction: MoveItemDirection,
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum MoveItemDirection {
+    Up,
+    Down,
+}
+
 pub enum Runnables {}
 
 impl Request for Runnables {
@@ -163,10 +304,15 @@ pub enum RunnableKind {
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct CargoRunnable {
+    // command to be executed instead of cargo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub override_cargo: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub workspace_root: Option<PathBuf>,
     // command, --package and --lib stuff
     pub cargo_args: Vec<String>,
+    // user-specified additional cargo args, like `--release`.
+    pub cargo_extra_args: Vec<String>,
     // stuff after --
     pub executable_args: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -218,17 +364,27 @@ pub struct SsrParams {
 
 pub enum StatusNotification {}
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusParams {
+    pub health: Health,
+    /// `false` while the server is still loading the workspace or doing other
+    /// work which makes its answers incomplete.
+    pub quiescent: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-#[derive(Serialize, Deserialize)]
-pub enum Status {
-    Loading,
-    Ready,
-    NeedsReload,
-    Invalid,
+pub enum Health {
+    Ok,
+    Warning,
+    Error,
 }
 
 impl Notification for StatusNotification {
-    type Params = Status;
+    type Params = StatusParams;
     const METHOD: &'static str = "rust-analyzer/status";
 }
 
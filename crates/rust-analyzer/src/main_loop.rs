@@ -5,11 +5,11 @@ use std::{
     time::{Duration, Instant},
 };
 
-use crossbeam_channel::{never, select, Receiver};
+use crossbeam_channel::{select, Receiver};
 use lsp_server::{Connection, Notification, Request, Response};
 use lsp_types::notification::Notification as _;
 use ra_db::VfsPath;
-use ra_ide::{Canceled, FileId};
+use ra_ide::{Canceled, FileId, PrimeCachesProgress};
 use ra_prof::profile;
 
 use crate::{
@@ -60,6 +60,7 @@ pub(crate) enum Task {
     Response(Response),
     Diagnostics(Vec<(FileId, Vec<lsp_types::Diagnostic>)>),
     Workspaces(Vec<anyhow::Result<ProjectWorkspace>>),
+    PrimeCaches(PrimeCachesProgress),
     Unit,
 }
 
@@ -107,7 +108,7 @@ impl GlobalState {
             recv(self.loader.receiver) -> task =>
                 Some(Event::Vfs(task.unwrap())),
 
-            recv(self.flycheck.as_ref().map_or(&never(), |it| &it.receiver)) -> task =>
+            recv(self.flycheck_receiver) -> task =>
                 Some(Event::Flycheck(task.unwrap())),
         }
     }
@@ -192,7 +193,27 @@ impl GlobalState {
                             self.diagnostics.set_native_diagnostics(file_id, diagnostics)
                         }
                     }
-                    Task::Workspaces(workspaces) => self.switch_workspaces(workspaces),
+                    Task::Workspaces(workspaces) => {
+                        self.fetching_workspaces = false;
+                        self.report_progress("fetching workspace", Progress::End, None, None);
+                        self.switch_workspaces(workspaces);
+                        self.load_detached_files();
+                    }
+                    Task::PrimeCaches(progress) => {
+                        let (state, message, percentage) = match progress {
+                            PrimeCachesProgress::Started => (Progress::Begin, None, Some(0.0)),
+                            PrimeCachesProgress::StartedOnCrate { on_crate, n_done, n_total } => (
+                                Progress::Report,
+                                Some(format!("{}/{} ({})", n_done, n_total, on_crate)),
+                                Some(Progress::percentage(n_done, n_total)),
+                            ),
+                            PrimeCachesProgress::Finished => {
+                                self.store_persistent_cache();
+                                (Progress::End, None, None)
+                            }
+                        };
+                        self.report_progress("indexing", state, message, percentage);
+                    }
                     Task::Unit => (),
                 }
                 self.analysis_host.maybe_collect_garbage();
@@ -231,7 +252,7 @@ impl GlobalState {
                 }
             },
             Event::Flycheck(task) => match task {
-                flycheck::Message::AddDiagnostic { workspace_root, diagnostic } => {
+                flycheck::Message::AddDiagnostic { id, workspace_root, diagnostic } => {
                     let diagnostics = crate::diagnostics::to_proto::map_rust_diagnostic_to_lsp(
                         &self.config.diagnostics,
                         &diagnostic,
@@ -240,6 +261,7 @@ impl GlobalState {
                     for diag in diagnostics {
                         match url_to_file_id(&self.vfs.read().0, &diag.url) {
                             Ok(file_id) => self.diagnostics.add_check_diagnostic(
+                                id,
                                 file_id,
                                 diag.diagnostic,
                                 diag.fixes,
@@ -251,10 +273,11 @@ impl GlobalState {
                     }
                 }
 
-                flycheck::Message::Progress(status) => {
-                    let (state, message) = match status {
+                flycheck::Message::Progress { id, progress } => {
+                    let (state, message) = match progress {
                         flycheck::Progress::DidStart => {
-                            self.diagnostics.clear_check();
+                            self.diagnostics.clear_check(id);
+                            self.flycheck_error = None;
                             (Progress::Begin, None)
                         }
                         flycheck::Progress::DidCheckCrate(target) => {
@@ -263,25 +286,39 @@ impl GlobalState {
                         flycheck::Progress::DidCancel => (Progress::End, None),
                         flycheck::Progress::DidFinish(result) => {
                             if let Err(err) = result {
-                                log::error!("cargo check failed: {}", err)
+                                log::error!("cargo check failed: {}", err);
+                                self.flycheck_error = Some(format!("cargo check failed: {}", err));
                             }
+                            // `cargo check` builds proc macros, which might
+                            // have changed.
+                            self.reload_proc_macros_if_changed();
                             (Progress::End, None)
                         }
                     };
 
-                    self.report_progress("cargo check", state, message, None);
+                    // When there are multiple workspaces, each flycheck gets its
+                    // own progress token.
+                    let title = if self.flycheck.len() == 1 {
+                        "cargo check".to_string()
+                    } else {
+                        format!("cargo check (#{})", id + 1)
+                    };
+                    self.report_progress(&title, state, message, None);
                 }
             },
         }
 
         let state_changed = self.process_changes();
         if prev_status == Status::Loading && self.status == Status::Ready {
-            if let Some(flycheck) = &self.flycheck {
-                flycheck.handle.update();
+            for flycheck in &self.flycheck {
+                flycheck.update();
             }
+            self.prime_crate_def_maps();
         }
 
-        if self.status == Status::Ready && (state_changed || prev_status == Status::Loading) {
+        if matches!(self.status, Status::Ready | Status::NeedsReload)
+            && (state_changed || prev_status == Status::Loading)
+        {
             let subscriptions = self
                 .mem_docs
                 .iter()
@@ -301,6 +338,8 @@ impl GlobalState {
             }
         }
 
+        self.report_status();
+
         let loop_duration = loop_start.elapsed();
         if loop_duration > Duration::from_millis(100) {
             log::warn!("overly long loop turn: {:?}", loop_duration);
@@ -315,15 +354,49 @@ impl GlobalState {
     }
 
     fn transition(&mut self, new_status: Status) {
-        self.status = Status::Ready;
-        if self.config.client_caps.status_notification {
-            let lsp_status = match new_status {
-                Status::Loading => lsp_ext::Status::Loading,
-                Status::Ready => lsp_ext::Status::Ready,
-                Status::Invalid => lsp_ext::Status::Invalid,
-                Status::NeedsReload => lsp_ext::Status::NeedsReload,
-            };
-            self.send_notification::<lsp_ext::StatusNotification>(lsp_status);
+        self.status = new_status;
+    }
+
+    fn current_status(&self) -> lsp_ext::StatusParams {
+        let mut status = lsp_ext::StatusParams {
+            health: lsp_ext::Health::Ok,
+            quiescent: !self.fetching_workspaces && self.status != Status::Loading,
+            message: None,
+        };
+        let mut messages = Vec::new();
+        match self.status {
+            Status::Invalid => {
+                status.health = lsp_ext::Health::Error;
+                messages.push("no files were loaded for the workspace".to_string());
+            }
+            Status::NeedsReload => {
+                status.health = lsp_ext::Health::Warning;
+                messages.push("the workspace has changed and needs to be reloaded".to_string());
+            }
+            Status::Loading | Status::Ready => (),
+        }
+        for warning in self.proc_macro_error.iter().chain(self.flycheck_error.iter()) {
+            status.health = status.health.max(lsp_ext::Health::Warning);
+            messages.push(warning.clone());
+        }
+        for error in self.workspace_errors.iter() {
+            status.health = lsp_ext::Health::Error;
+            messages.push(error.clone());
+        }
+        if !messages.is_empty() {
+            status.message = Some(messages.join("\n"));
+        }
+        status
+    }
+
+    fn report_status(&mut self) {
+        if !self.config.client_caps.status_notification {
+            return;
+        }
+        let status = self.current_status();
+        if self.last_reported_status.as_ref() != Some(&status) {
+            self.last_reported_status = Some(status.clone());
+            self.send_notification::<lsp_ext::StatusNotification>(status);
         }
     }
 
@@ -331,10 +404,13 @@ impl GlobalState {
         self.register_request(&req, request_received);
 
         RequestDispatcher { req: Some(req), global_state: self }
-            .on_sync::<lsp_ext::ReloadWorkspace>(|s, ()| Ok(s.fetch_workspaces()))?
+            .on_sync::<lsp_ext::ReloadWorkspace>(|s, ()| Ok(s.reload_workspaces()))?
             .on_sync::<lsp_ext::JoinLines>(|s, p| handlers::handle_join_lines(s.snapshot(), p))?
             .on_sync::<lsp_ext::OnEnter>(|s, p| handlers::handle_on_enter(s.snapshot(), p))?
-            .on_sync::<lsp_types::request::Shutdown>(|_, ()| Ok(()))?
+            .on_sync::<lsp_types::request::Shutdown>(|s, ()| {
+                s.analysis_host.analysis().store_persistent_cache().ok();
+                Ok(())
+            })?
             .on_sync::<lsp_types::request::SelectionRangeRequest>(|s, p| {
                 handlers::handle_selection_range(s.snapshot(), p)
             })?
@@ -342,28 +418,39 @@ impl GlobalState {
                 handlers::handle_matching_brace(s.snapshot(), p)
             })?
             .on_sync::<lsp_ext::MemoryUsage>(|s, p| handlers::handle_memory_usage(s, p))?
+            .on_sync::<lsp_ext::Profile>(|s, p| handlers::handle_profile(s, p))?
             .on::<lsp_ext::AnalyzerStatus>(handlers::handle_analyzer_status)?
             .on::<lsp_ext::SyntaxTree>(handlers::handle_syntax_tree)?
+            .on::<lsp_ext::ViewHir>(handlers::handle_view_hir)?
+            .on::<lsp_ext::ViewItemTree>(handlers::handle_view_item_tree)?
+            .on::<lsp_ext::ViewCrateGraph>(handlers::handle_view_crate_graph)?
             .on::<lsp_ext::ExpandMacro>(handlers::handle_expand_macro)?
             .on::<lsp_ext::ParentModule>(handlers::handle_parent_module)?
+            .on::<lsp_ext::OpenCargoToml>(handlers::handle_open_cargo_toml)?
+            .on::<lsp_ext::MoveItem>(handlers::handle_move_item)?
             .on::<lsp_ext::Runnables>(handlers::handle_runnables)?
             .on::<lsp_ext::InlayHints>(handlers::handle_inlay_hints)?
             .on::<lsp_ext::CodeActionRequest>(handlers::handle_code_action)?
             .on::<lsp_ext::ResolveCodeActionRequest>(handlers::handle_resolve_code_action)?
-            .on::<lsp_ext::HoverRequest>(handlers::handle_hover)?
-            .on::<lsp_types::request::OnTypeFormatting>(handlers::handle_on_type_formatting)?
+            .on_latency_sensitive::<lsp_ext::HoverRequest>(handlers::handle_hover)?
+            .on_latency_sensitive::<lsp_types::request::OnTypeFormatting>(
+                handlers::handle_on_type_formatting,
+            )?
             .on::<lsp_types::request::DocumentSymbolRequest>(handlers::handle_document_symbol)?
             .on::<lsp_types::request::WorkspaceSymbol>(handlers::handle_workspace_symbol)?
             .on::<lsp_types::request::GotoDefinition>(handlers::handle_goto_definition)?
             .on::<lsp_types::request::GotoImplementation>(handlers::handle_goto_implementation)?
             .on::<lsp_types::request::GotoTypeDefinition>(handlers::handle_goto_type_definition)?
-            .on::<lsp_types::request::Completion>(handlers::handle_completion)?
+            .on_latency_sensitive::<lsp_types::request::Completion>(handlers::handle_completion)?
             .on::<lsp_types::request::CodeLensRequest>(handlers::handle_code_lens)?
             .on::<lsp_types::request::CodeLensResolve>(handlers::handle_code_lens_resolve)?
             .on::<lsp_types::request::FoldingRangeRequest>(handlers::handle_folding_range)?
-            .on::<lsp_types::request::SignatureHelpRequest>(handlers::handle_signature_help)?
+            .on_latency_sensitive::<lsp_types::request::SignatureHelpRequest>(
+                handlers::handle_signature_help,
+            )?
             .on::<lsp_types::request::PrepareRenameRequest>(handlers::handle_prepare_rename)?
             .on::<lsp_types::request::Rename>(handlers::handle_rename)?
+            .on::<lsp_ext::WillRenameFiles>(handlers::handle_will_rename_files)?
             .on::<lsp_types::request::References>(handlers::handle_references)?
             .on::<lsp_types::request::Formatting>(handlers::handle_formatting)?
             .on::<lsp_types::request::DocumentHighlightRequest>(
@@ -379,6 +466,8 @@ impl GlobalState {
                 handlers::handle_call_hierarchy_outgoing,
             )?
             .on::<lsp_types::request::SemanticTokensRequest>(handlers::handle_semantic_tokens)?
+            .on::<lsp_ext::SemanticTokensDelta>(handlers::handle_semantic_tokens_delta)?
+            .on::<lsp_ext::SemanticTokensEdits>(handlers::handle_semantic_tokens_delta)?
             .on::<lsp_types::request::SemanticTokensRangeRequest>(
                 handlers::handle_semantic_tokens_range,
             )?
@@ -405,6 +494,8 @@ impl GlobalState {
                         .write()
                         .0
                         .set_file_contents(path, Some(params.text_document.text.into_bytes()));
+                    this.update_open_files();
+                    this.load_detached_files();
                 }
                 Ok(())
             })?
@@ -414,7 +505,11 @@ impl GlobalState {
                     let vfs = &mut this.vfs.write().0;
                     let file_id = vfs.file_id(&path).unwrap();
                     let mut text = String::from_utf8(vfs.file_contents(file_id).to_vec()).unwrap();
-                    apply_document_changes(&mut text, params.content_changes);
+                    apply_document_changes(
+                        this.config.client_caps.offset_encoding,
+                        &mut text,
+                        params.content_changes,
+                    );
                     vfs.set_file_contents(path, Some(text.into_bytes()))
                 }
                 Ok(())
@@ -424,6 +519,8 @@ impl GlobalState {
                     if !this.mem_docs.remove(&path) {
                         log::error!("orphan DidCloseTextDocument: {}", path)
                     }
+                    this.update_open_files();
+                    this.semantic_tokens_cache.lock().remove(&params.text_document.uri);
                     if let Some(path) = path.as_path() {
                         this.loader.handle.invalidate(path.to_path_buf());
                     }
@@ -438,8 +535,24 @@ impl GlobalState {
                 Ok(())
             })?
             .on::<lsp_types::notification::DidSaveTextDocument>(|this, params| {
-                if let Some(flycheck) = &this.flycheck {
-                    flycheck.handle.update();
+                // Only check the workspace the file belongs to, falling back
+                // to all of them for files outside of any workspace.
+                let path = from_proto::abs_path(&params.text_document.uri).ok();
+                let owners = this
+                    .flycheck
+                    .iter()
+                    .filter(|flycheck| {
+                        let ws = match this.workspaces.get(flycheck.id()) {
+                            Some(it) => it,
+                            None => return false,
+                        };
+                        path.as_ref().map_or(false, |path| ws.workspace_root_for(path).is_some())
+                    })
+                    .collect::<Vec<_>>();
+                if owners.is_empty() {
+                    this.flycheck.iter().for_each(|it| it.update());
+                } else {
+                    owners.iter().for_each(|it| it.update());
                 }
                 let uri = params.text_document.uri.as_str();
                 if uri.ends_with("Cargo.toml") || uri.ends_with("Cargo.lock") {
@@ -486,6 +599,9 @@ impl GlobalState {
             .on::<lsp_types::notification::DidChangeWatchedFiles>(|this, params| {
                 for change in params.changes {
                     if let Ok(path) = from_proto::abs_path(&change.uri) {
+                        if this.config.files.exclude_dirs.iter().any(|dir| path.starts_with(dir)) {
+                            continue;
+                        }
                         this.loader.handle.invalidate(path);
                     }
                 }
@@ -494,7 +610,7 @@ impl GlobalState {
             .finish();
         Ok(())
     }
-    fn update_file_notifications_on_threadpool(&mut self, subscriptions: Vec<FileId>) {
+    pub(crate) fn update_file_notifications_on_threadpool(&mut self, subscriptions: Vec<FileId>) {
         log::trace!("updating notifications for {:?}", subscriptions);
         if self.config.publish_diagnostics {
             let snapshot = self.snapshot();
@@ -503,6 +619,10 @@ impl GlobalState {
                 let diagnostics = subscriptions
                     .into_iter()
                     .filter_map(|file_id| {
+                        // Diagnostics in dependencies can't be fixed by the user.
+                        if snapshot.analysis.is_library_file(file_id).ok()? {
+                            return None;
+                        }
                         handlers::publish_diagnostics(&snapshot, file_id)
                             .map_err(|err| {
                                 if !is_canceled(&*err) {
@@ -526,4 +646,31 @@ impl GlobalState {
             }
         });
     }
+
+    fn store_persistent_cache(&mut self) {
+        if !self.config.persistent_cache {
+            return;
+        }
+        let snap = self.snapshot();
+        self.task_pool.handle.spawn(move || {
+            // If this is canceled, the cache is stored again on shutdown.
+            let _ = snap.analysis.store_persistent_cache();
+            Task::Unit
+        });
+    }
+
+    fn prime_crate_def_maps(&mut self) {
+        let snap = self.snapshot();
+        let num_threads = self.config.prime_caches_num_threads;
+        self.task_pool.handle.spawn_with_sender(move |sender| {
+            let cb = {
+                let sender = sender.clone();
+                move |progress| sender.send(Task::PrimeCaches(progress)).unwrap()
+            };
+            if snap.analysis.prime_crate_def_maps(num_threads, cb).is_err() {
+                // Close the progress the client is still showing.
+                sender.send(Task::PrimeCaches(PrimeCachesProgress::Finished)).unwrap()
+            }
+        });
+    }
 }
@@ -1,39 +1,93 @@
 //! Project loading & configuration updates
-use std::{mem, sync::Arc};
+use std::{fs, mem, sync::Arc, time::SystemTime};
 
-use crossbeam_channel::unbounded;
-use flycheck::FlycheckHandle;
+use flycheck::{FlycheckConfig, FlycheckHandle};
 use ra_db::{CrateGraph, SourceRoot, VfsPath};
 use ra_ide::AnalysisChange;
-use ra_project_model::{PackageRoot, ProcMacroClient, ProjectWorkspace};
-use vfs::{file_set::FileSetConfig, AbsPath};
+use ra_project_model::{
+    CargoConfig, CargoWorkspace, PackageRoot, ProcMacroClient, ProjectWorkspace,
+};
+use rayon::prelude::*;
+use rustc_hash::FxHashMap;
+use vfs::{file_set::FileSetConfig, AbsPath, AbsPathBuf};
 
 use crate::{
     config::{Config, FilesWatcher, LinkedProject},
-    global_state::{GlobalState, Handle},
+    global_state::GlobalState,
+    lsp_utils::Progress,
     main_loop::Task,
 };
 
 impl GlobalState {
     pub(crate) fn update_configuration(&mut self, config: Config) {
         let old_config = mem::replace(&mut self.config, config);
-        if self.config.lru_capacity != old_config.lru_capacity {
-            self.analysis_host.update_lru_capacity(old_config.lru_capacity);
+        if self.config.lru != old_config.lru {
+            self.analysis_host.update_lru_config(self.config.lru);
+        }
+        if self.config.macro_limits != old_config.macro_limits {
+            self.analysis_host.set_macro_limits(self.config.macro_limits);
+        }
+        if self.config.attr_macro_fallback != old_config.attr_macro_fallback {
+            self.analysis_host.set_attr_macro_fallback(self.config.attr_macro_fallback);
         }
-        if self.config.linked_projects != old_config.linked_projects {
+        // Everything that feeds into the crate graph or the set of loaded files
+        // requires loading the workspaces anew, which also restarts flycheck.
+        let workspace_changed = self.config.linked_projects != old_config.linked_projects
+            || self.config.cargo != old_config.cargo
+            || self.config.with_sysroot != old_config.with_sysroot
+            || self.config.proc_macro_srv != old_config.proc_macro_srv
+            || self.config.files != old_config.files;
+        if workspace_changed {
             self.fetch_workspaces()
         } else if self.config.flycheck != old_config.flycheck {
             self.reload_flycheck();
         }
+        if self.config.publish_diagnostics != old_config.publish_diagnostics
+            || self.config.diagnostics != old_config.diagnostics
+        {
+            self.refresh_diagnostics(!workspace_changed);
+        }
+    }
+    /// Re-publishes all diagnostics after diagnostics settings changed.
+    ///
+    /// Check diagnostics are mapped with the settings as they arrive, so the
+    /// check has to run again to pick the new settings up.
+    fn refresh_diagnostics(&mut self, restart_check: bool) {
+        self.diagnostics.clear_check_all();
+        if restart_check {
+            for flycheck in &self.flycheck {
+                flycheck.update();
+            }
+        }
+        if self.config.publish_diagnostics {
+            let subscriptions = self
+                .mem_docs
+                .iter()
+                .filter_map(|path| self.vfs.read().0.file_id(&path))
+                .collect::<Vec<_>>();
+            self.update_file_notifications_on_threadpool(subscriptions);
+        } else {
+            self.diagnostics.clear_native();
+        }
+    }
+    /// Handles an explicit reload request. Unlike a reload caused by a
+    /// configuration change, this also looks for new projects on disk. The
+    /// analysis database is kept, so unchanged files stay warm.
+    pub(crate) fn reload_workspaces(&mut self) {
+        self.config.discover_projects();
+        self.fetch_workspaces();
     }
     pub(crate) fn fetch_workspaces(&mut self) {
+        self.fetching_workspaces = true;
+        self.report_progress("fetching workspace", Progress::Begin, None, None);
         self.task_pool.handle.spawn({
             let linked_projects = self.config.linked_projects.clone();
             let cargo_config = self.config.cargo.clone();
             let with_sysroot = self.config.with_sysroot.clone();
+            let detached_files = self.detached_files.clone();
             move || {
-                let workspaces = linked_projects
-                    .iter()
+                let mut workspaces = linked_projects
+                    .par_iter()
                     .map(|project| match project {
                         LinkedProject::ProjectManifest(manifest) => {
                             ra_project_model::ProjectWorkspace::load(
@@ -43,16 +97,24 @@ impl GlobalState {
                             )
                         }
                         LinkedProject::InlineJsonProject(it) => {
-                            Ok(ra_project_model::ProjectWorkspace::Json { project: it.clone() })
+                            Ok(ra_project_model::ProjectWorkspace::load_inline(it.clone()))
                         }
                     })
                     .collect::<Vec<_>>();
+                if !detached_files.is_empty() {
+                    workspaces.push(ra_project_model::ProjectWorkspace::load_detached_files(
+                        detached_files,
+                        &cargo_config,
+                        with_sysroot,
+                    ));
+                }
                 Task::Workspaces(workspaces)
             }
         });
     }
     pub(crate) fn switch_workspaces(&mut self, workspaces: Vec<anyhow::Result<ProjectWorkspace>>) {
         log::info!("reloading projects: {:?}", self.config.linked_projects);
+        self.workspace_errors.clear();
         let workspaces = workspaces
             .into_iter()
             .filter_map(|res| {
@@ -62,17 +124,18 @@ impl GlobalState {
                         lsp_types::MessageType::Error,
                         format!("rust-analyzer failed to load workspace: {:#}", err),
                     );
+                    self.workspace_errors.push(format!("failed to load workspace: {:#}", err));
                 })
                 .ok()
             })
             .collect::<Vec<_>>();
 
-        if let FilesWatcher::Client = self.config.files.watcher {
+        if let FilesWatcher::Client = self.config.files_watcher() {
             let registration_options = lsp_types::DidChangeWatchedFilesRegistrationOptions {
                 watchers: workspaces
                     .iter()
                     .flat_map(ProjectWorkspace::to_roots)
-                    .filter(PackageRoot::is_member)
+                    .filter(PackageRoot::is_local)
                     .map(|root| format!("{}/**/*.rs", root.path().display()))
                     .map(|glob_pattern| lsp_types::FileSystemWatcher { glob_pattern, kind: None })
                     .collect(),
@@ -90,8 +153,9 @@ impl GlobalState {
 
         let mut change = AnalysisChange::new();
 
-        let project_folders = ProjectFolders::new(&workspaces);
+        let project_folders = ProjectFolders::new(&workspaces, &self.config.files.exclude_dirs);
 
+        self.proc_macro_error = None;
         self.proc_macro_client = match &self.config.proc_macro_srv {
             None => ProcMacroClient::dummy(),
             Some((path, args)) => match ProcMacroClient::extern_process(path.into(), args) {
@@ -102,71 +166,130 @@ impl GlobalState {
                         path.display(),
                         err
                     );
+                    self.proc_macro_error =
+                        Some(format!("failed to run the proc macro server: {}", err));
                     ProcMacroClient::dummy()
                 }
             },
         };
-        let watch = match self.config.files.watcher {
+        let watch = match self.config.files_watcher() {
             FilesWatcher::Client => vec![],
             FilesWatcher::Notify => project_folders.watch,
         };
         self.loader.handle.set_config(vfs::loader::Config { load: project_folders.load, watch });
 
-        // Create crate graph from all the workspaces
-        let crate_graph = {
-            let mut crate_graph = CrateGraph::default();
-            let vfs = &mut self.vfs.write().0;
-            let loader = &mut self.loader;
-            let mut load = |path: &AbsPath| {
-                let contents = loader.handle.load_sync(path);
-                let path = vfs::VfsPath::from(path.to_path_buf());
-                vfs.set_file_contents(path.clone(), contents);
-                vfs.file_id(&path)
-            };
-            for ws in workspaces.iter() {
-                crate_graph.extend(ws.to_crate_graph(
-                    self.config.cargo.target.as_deref(),
-                    &self.proc_macro_client,
-                    &mut load,
-                ));
-            }
-
-            crate_graph
-        };
-        change.set_crate_graph(crate_graph);
-
         self.source_root_config = project_folders.source_root_config;
         self.workspaces = Arc::new(workspaces);
+        self.proc_macro_dylibs = proc_macro_dylib_mtimes(&self.workspaces);
+
+        change.set_crate_graph(self.create_crate_graph());
 
         self.analysis_host.apply_change(change);
         self.process_changes();
         self.reload_flycheck();
     }
 
+    /// Creates the crate graph from all the workspaces, loading the crate roots
+    /// and the proc macros.
+    fn create_crate_graph(&mut self) -> CrateGraph {
+        let vfs = &mut self.vfs.write().0;
+        crate_graph(
+            &self.workspaces,
+            &self.config.cargo,
+            &self.proc_macro_client,
+            vfs,
+            &mut *self.loader.handle,
+        )
+    }
+
+    /// Reloads the proc macros if any of their dylibs was rebuilt since the
+    /// crate graph was created, for example by `cargo check`.
+    ///
+    /// The proc macro server loads the new dylib by itself, but the expansions
+    /// of the old one are cached in the database. Setting the crate graph anew
+    /// invalidates them.
+    pub(crate) fn reload_proc_macros_if_changed(&mut self) {
+        let dylibs = proc_macro_dylib_mtimes(&self.workspaces);
+        if dylibs == self.proc_macro_dylibs {
+            return;
+        }
+        log::info!("proc macro dylibs changed, reloading");
+        self.proc_macro_dylibs = dylibs;
+        let mut change = AnalysisChange::new();
+        change.set_crate_graph(self.create_crate_graph());
+        self.analysis_host.apply_change(change);
+    }
+
+    /// Picks up opened `.rs` files which are not covered by any workspace and
+    /// reloads the workspaces to analyze them as standalone crates.
+    pub(crate) fn load_detached_files(&mut self) {
+        if self.fetching_workspaces {
+            // Checked again once the workspaces are loaded.
+            return;
+        }
+        let roots = self.workspaces.iter().flat_map(|it| it.to_roots()).collect::<Vec<_>>();
+        let new_files = self
+            .mem_docs
+            .iter()
+            .filter_map(|path| path.as_path())
+            .filter(|path| path.extension().map_or(false, |it| it == "rs"))
+            .filter(|&path| !roots.iter().any(|root| path.starts_with(root.path())))
+            .filter(|&path| !self.detached_files.iter().any(|it| it == path))
+            .map(|path| path.to_path_buf())
+            .collect::<Vec<_>>();
+        if new_files.is_empty() {
+            return;
+        }
+        log::info!("loading detached files: {:?}", new_files);
+        self.detached_files.extend(new_files);
+        self.fetch_workspaces();
+    }
+
     fn reload_flycheck(&mut self) {
+        // The diagnostics of the old flychecks belong to the old workspaces.
+        self.diagnostics.clear_check_all();
         let config = match self.config.flycheck.clone() {
             Some(it) => it,
             None => {
-                self.flycheck = None;
+                self.flycheck = Vec::new();
                 return;
             }
         };
 
-        // FIXME: Figure out the multi-workspace situation
-        self.flycheck = self.workspaces.iter().find_map(move |w| match w {
-            ProjectWorkspace::Cargo { cargo, .. } => {
-                let (sender, receiver) = unbounded();
+        // Flychecks are identified by the index of their workspace, so that
+        // diagnostics and saved files can be routed to the right one.
+        let sender = self.flycheck_sender.clone();
+        self.flycheck = self
+            .workspaces
+            .iter()
+            .enumerate()
+            .filter_map(|(id, w)| {
+                let mut config = config.clone();
+                let workspace_root = match w {
+                    ProjectWorkspace::Cargo { cargo, .. } => {
+                        expand_cargo_alias(&mut config, cargo);
+                        cargo.workspace_root().to_path_buf()
+                    }
+                    // A custom command doesn't need a `Cargo.toml`, so it can also check
+                    // `rust-project.json` workspaces, from the directory of the project file.
+                    ProjectWorkspace::Json { project, .. }
+                        if matches!(config, FlycheckConfig::CustomCommand { .. }) =>
+                    {
+                        project.path().to_path_buf()
+                    }
+                    ProjectWorkspace::Json { .. } => {
+                        log::warn!(
+                            "Cargo check watching only supported for cargo workspaces, disabling"
+                        );
+                        return None;
+                    }
+                    ProjectWorkspace::DetachedFiles { .. } => return None,
+                };
+                let sender = sender.clone();
                 let sender = Box::new(move |msg| sender.send(msg).unwrap());
-                let cargo_project_root = cargo.workspace_root().to_path_buf();
-                let handle =
-                    FlycheckHandle::spawn(sender, config.clone(), cargo_project_root.into());
-                Some(Handle { handle, receiver })
-            }
-            ProjectWorkspace::Json { .. } => {
-                log::warn!("Cargo check watching only supported for cargo workspaces, disabling");
-                None
-            }
-        })
+                Some(FlycheckHandle::spawn(id, sender, config, workspace_root.into()))
+            })
+            .collect();
     }
 }
 
@@ -178,7 +301,10 @@ pub(crate) struct ProjectFolders {
 }
 
 impl ProjectFolders {
-    pub(crate) fn new(workspaces: &[ProjectWorkspace]) -> ProjectFolders {
+    pub(crate) fn new(
+        workspaces: &[ProjectWorkspace],
+        exclude_dirs: &[AbsPathBuf],
+    ) -> ProjectFolders {
         let mut res = ProjectFolders::default();
         let mut fsc = FileSetConfig::builder();
         let mut local_filesets = vec![];
@@ -188,20 +314,24 @@ impl ProjectFolders {
 
             let mut file_set_roots: Vec<VfsPath> = vec![];
 
-            let entry = if root.is_member() {
+            let entry = if path.extension().map_or(false, |it| it == "rs") {
+                // A detached file, see `ProjectWorkspace::DetachedFiles`.
+                vfs::loader::Entry::Files(vec![path.to_path_buf()])
+            } else if root.is_member() {
                 vfs::loader::Entry::local_cargo_package(path.to_path_buf())
             } else {
                 vfs::loader::Entry::cargo_package_dependency(path.to_path_buf())
             };
-            res.load.push(entry);
-            if root.is_member() {
+            res.load.push(exclude(entry, exclude_dirs));
+            if root.is_local() {
                 res.watch.push(res.load.len() - 1);
             }
 
             if let Some(out_dir) = root.out_dir() {
                 let out_dir = out_dir.to_path_buf();
-                res.load.push(vfs::loader::Entry::rs_files_recursively(out_dir.clone()));
-                if root.is_member() {
+                let entry = vfs::loader::Entry::rs_files_recursively(out_dir.clone());
+                res.load.push(exclude(entry, exclude_dirs));
+                if root.is_local() {
                     res.watch.push(res.load.len() - 1);
                 }
                 file_set_roots.push(out_dir.into());
@@ -221,6 +351,80 @@ impl ProjectFolders {
     }
 }
 
+/// Replaces a cargo command which is an `[alias]` of `.cargo/config` with its
+/// expansion, so that the arguments of the alias come last. Cargo would expand
+/// it by itself, but then our arguments would end up after the `--` of aliases
+/// like `clippy -- -D warnings`.
+fn expand_cargo_alias(config: &mut FlycheckConfig, cargo: &CargoWorkspace) {
+    if let FlycheckConfig::CargoCommand { command, extra_args, .. } = config {
+        if let Some((alias_command, alias_args)) =
+            cargo.cargo_alias(command).and_then(|it| it.split_first())
+        {
+            *command = alias_command.clone();
+            extra_args.extend(alias_args.iter().cloned());
+        }
+    }
+}
+
+/// Creates the crate graph of `workspaces`. Crate roots which are already in
+/// the VFS keep their contents, which might be unsaved editor changes, the
+/// others are loaded from disk.
+fn crate_graph(
+    workspaces: &[ProjectWorkspace],
+    cargo_config: &CargoConfig,
+    proc_macro_client: &ProcMacroClient,
+    vfs: &mut vfs::Vfs,
+    loader: &mut dyn vfs::loader::Handle,
+) -> CrateGraph {
+    let mut load = |path: &AbsPath| {
+        let vfs_path = vfs::VfsPath::from(path.to_path_buf());
+        if vfs.file_id(&vfs_path).is_none() {
+            let contents = loader.load_sync(path);
+            vfs.set_file_contents(vfs_path.clone(), contents);
+        }
+        vfs.file_id(&vfs_path)
+    };
+    let mut crate_graph = CrateGraph::default();
+    for ws in workspaces {
+        crate_graph.extend(ws.to_crate_graph(
+            cargo_config.target.as_deref(),
+            &cargo_config.cfg_overrides,
+            proc_macro_client,
+            &mut load,
+        ));
+    }
+    crate_graph
+}
+
+fn proc_macro_dylib_mtimes(
+    workspaces: &[ProjectWorkspace],
+) -> FxHashMap<AbsPathBuf, Option<SystemTime>> {
+    workspaces
+        .iter()
+        .flat_map(ProjectWorkspace::proc_macro_dylib_paths)
+        .map(|path| {
+            let mtime = fs::metadata(&path).and_then(|it| it.modified()).ok();
+            (path, mtime)
+        })
+        .collect()
+}
+
+/// Adds the `exclude_dirs` nested in the directory of `entry` to its excludes,
+/// and skips the directory altogether if it is excluded itself.
+fn exclude(mut entry: vfs::loader::Entry, exclude_dirs: &[AbsPathBuf]) -> vfs::loader::Entry {
+    if let vfs::loader::Entry::Directory { path, include } = &mut entry {
+        if exclude_dirs.iter().any(|dir| path.starts_with(dir)) {
+            return vfs::loader::Entry::Files(Vec::new());
+        }
+        for dir in exclude_dirs {
+            if let Some(rel_path) = dir.strip_prefix(path) {
+                include.push(format!("!/{}/", rel_path.as_ref().display()));
+            }
+        }
+    }
+    entry
+}
+
 #[derive(Default, Debug)]
 pub(crate) struct SourceRootConfig {
     pub(crate) fsc: FileSetConfig,
@@ -244,3 +448,83 @@ impl SourceRootConfig {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ra_project_model::Sysroot;
+
+    use super::*;
+
+    /// Records the files which are loaded from "disk".
+    #[derive(Debug, Default)]
+    struct RecordingLoader {
+        loaded: Vec<AbsPathBuf>,
+    }
+
+    impl vfs::loader::Handle for RecordingLoader {
+        fn spawn(_sender: vfs::loader::Sender) -> RecordingLoader {
+            RecordingLoader::default()
+        }
+        fn set_config(&mut self, _config: vfs::loader::Config) {}
+        fn invalidate(&mut self, _path: AbsPathBuf) {}
+        fn load_sync(&mut self, path: &AbsPath) -> Option<Vec<u8>> {
+            self.loaded.push(path.to_path_buf());
+            Some(b"fn on_disk() {}".to_vec())
+        }
+    }
+
+    #[test]
+    fn crate_graph_reuses_vfs_contents() {
+        let open = AbsPathBuf::assert("/ws/open.rs".into());
+        let closed = AbsPathBuf::assert("/ws/closed.rs".into());
+        let workspace = ProjectWorkspace::DetachedFiles {
+            files: vec![open.clone(), closed.clone()],
+            sysroot: Sysroot::default(),
+        };
+        let mut vfs = vfs::Vfs::default();
+        vfs.set_file_contents(open.clone().into(), Some(b"fn unsaved() {}".to_vec()));
+        let mut loader = RecordingLoader::default();
+
+        let graph = crate_graph(
+            &[workspace],
+            &CargoConfig::default(),
+            &ProcMacroClient::dummy(),
+            &mut vfs,
+            &mut loader,
+        );
+
+        assert_eq!(loader.loaded, vec![closed.clone()]);
+        let open_id = vfs.file_id(&open.into()).unwrap();
+        let closed_id = vfs.file_id(&closed.into()).unwrap();
+        assert_eq!(vfs.file_contents(open_id), b"fn unsaved() {}");
+        let mut roots = graph.iter().map(|it| graph[it].root_file_id).collect::<Vec<_>>();
+        roots.sort();
+        assert_eq!(roots, vec![open_id, closed_id]);
+    }
+
+    fn check_exclude(entry: &str, exclude_dirs: &[&str]) -> Option<Vec<String>> {
+        let entry = vfs::loader::Entry::local_cargo_package(AbsPathBuf::assert(entry.into()));
+        let exclude_dirs =
+            exclude_dirs.iter().map(|it| AbsPathBuf::assert(it.into())).collect::<Vec<_>>();
+        match exclude(entry, &exclude_dirs) {
+            vfs::loader::Entry::Directory { include, .. } => Some(include),
+            vfs::loader::Entry::Files(files) => {
+                assert!(files.is_empty());
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn nested_exclude_dirs_become_globs() {
+        let include = check_exclude("/ws", &["/ws/src/gen", "/other"]).unwrap();
+        assert!(include.contains(&"!/src/gen/".to_string()), "{:?}", include);
+        assert!(!include.iter().any(|it| it.contains("other")), "{:?}", include);
+    }
+
+    #[test]
+    fn excluded_entries_are_skipped() {
+        assert_eq!(check_exclude("/ws/src/gen", &["/ws/src/gen"]), None);
+        assert_eq!(check_exclude("/ws/src/gen/sub", &["/ws/src"]), None);
+    }
+}
@@ -12,6 +12,10 @@ impl<T> TaskPool<T> {
         TaskPool { sender, inner: threadpool::ThreadPool::default() }
     }
 
+    pub(crate) fn with_name(sender: Sender<T>, name: &str, n_threads: usize) -> TaskPool<T> {
+        TaskPool { sender, inner: threadpool::ThreadPool::with_name(name.to_string(), n_threads) }
+    }
+
     pub(crate) fn spawn<F>(&mut self, task: F)
     where
         F: FnOnce() -> T + Send + 'static,
@@ -23,6 +27,18 @@ impl<T> TaskPool<T> {
         })
     }
 
+    /// Like `spawn`, but lets the task send any number of messages itself.
+    pub(crate) fn spawn_with_sender<F>(&mut self, task: F)
+    where
+        F: FnOnce(Sender<T>) + Send + 'static,
+        T: Send + 'static,
+    {
+        self.inner.execute({
+            let sender = self.sender.clone();
+            move || task(sender)
+        })
+    }
+
     pub(crate) fn len(&self) -> usize {
         self.inner.queued_count()
     }
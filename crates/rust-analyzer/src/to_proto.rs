@@ -1,26 +1,36 @@
 //! Conversion of rust-analyzer specific types to lsp_types equivalents.
-use std::path::{self, Path};
+use std::{
+    path::{self, Path},
+    sync::atomic::{AtomicU32, Ordering},
+};
 
 use itertools::Itertools;
 use ra_db::{FileId, FileRange};
 use ra_ide::{
     Assist, AssistKind, CompletionItem, CompletionItemKind, Documentation, FileSystemEdit, Fold,
     FoldKind, FunctionSignature, Highlight, HighlightModifier, HighlightTag, HighlightedRange,
-    Indel, InlayHint, InlayKind, InsertTextFormat, LineIndex, Markup, NavigationTarget,
-    ReferenceAccess, ResolvedAssist, Runnable, Severity, SourceChange, SourceFileEdit, TextEdit,
+    Indel, InlayHint, InlayKind, InsertTextFormat, Markup, NavigationTarget, ReferenceAccess,
+    ResolvedAssist, Runnable, Severity, SourceChange, SourceFileEdit, TextEdit,
 };
 use ra_syntax::{SyntaxKind, TextRange, TextSize};
 
 use crate::{
-    cargo_target_spec::CargoTargetSpec, global_state::GlobalStateSnapshot,
-    line_endings::LineEndings, lsp_ext, semantic_tokens, Result,
+    cargo_target_spec::CargoTargetSpec, config::OffsetEncoding, global_state::GlobalStateSnapshot,
+    line_endings::LineEndings, line_index::LineIndex, lsp_ext, semantic_tokens, Result,
 };
 
 pub(crate) fn position(line_index: &LineIndex, offset: TextSize) -> lsp_types::Position {
-    let line_col = line_index.line_col(offset);
-    let line = u64::from(line_col.line);
-    let character = u64::from(line_col.col_utf16);
-    lsp_types::Position::new(line, character)
+    let (line, character) = match line_index.encoding {
+        OffsetEncoding::Utf8 => {
+            let line_col = line_index.index.line_col_utf8(offset);
+            (line_col.line, line_col.col)
+        }
+        OffsetEncoding::Utf16 => {
+            let line_col = line_index.index.line_col(offset);
+            (line_col.line, line_col.col_utf16)
+        }
+    };
+    lsp_types::Position::new(u64::from(line), u64::from(character))
 }
 
 pub(crate) fn range(line_index: &LineIndex, range: TextRange) -> lsp_types::Range {
@@ -33,6 +43,7 @@ pub(crate) fn symbol_kind(syntax_kind: SyntaxKind) -> lsp_types::SymbolKind {
     match syntax_kind {
         SyntaxKind::FN_DEF => lsp_types::SymbolKind::Function,
         SyntaxKind::STRUCT_DEF => lsp_types::SymbolKind::Struct,
+        SyntaxKind::UNION_DEF => lsp_types::SymbolKind::Struct,
         SyntaxKind::ENUM_DEF => lsp_types::SymbolKind::Enum,
         SyntaxKind::ENUM_VARIANT => lsp_types::SymbolKind::EnumMember,
         SyntaxKind::TRAIT_DEF => lsp_types::SymbolKind::Interface,
@@ -256,19 +267,22 @@ pub(crate) fn inlay_int(line_index: &LineIndex, inlay_hint: InlayHint) -> lsp_ex
     }
 }
 
+static TOKEN_RESULT_COUNTER: AtomicU32 = AtomicU32::new(1);
+
 pub(crate) fn semantic_tokens(
     text: &str,
     line_index: &LineIndex,
     highlights: Vec<HighlightedRange>,
 ) -> lsp_types::SemanticTokens {
-    let mut builder = semantic_tokens::SemanticTokensBuilder::default();
+    let id = TOKEN_RESULT_COUNTER.fetch_add(1, Ordering::SeqCst).to_string();
+    let mut builder = semantic_tokens::SemanticTokensBuilder::new(id);
 
     for highlight_range in highlights {
         let (type_, mods) = semantic_token_type_and_modifiers(highlight_range.highlight);
         let token_index = semantic_tokens::type_index(type_);
         let modifier_bitset = mods.0;
 
-        for mut text_range in line_index.lines(highlight_range.range) {
+        for mut text_range in line_index.index.lines(highlight_range.range) {
             if text[text_range].ends_with('\n') {
                 text_range =
                     TextRange::new(text_range.start(), text_range.end() - TextSize::of('\n'));
@@ -281,6 +295,15 @@ pub(crate) fn semantic_tokens(
     builder.build()
 }
 
+pub(crate) fn semantic_token_delta(
+    previous: &lsp_types::SemanticTokens,
+    current: &lsp_types::SemanticTokens,
+) -> lsp_types::SemanticTokensEdits {
+    let result_id = current.result_id.clone();
+    let edits = semantic_tokens::diff_tokens(&previous.data, &current.data);
+    lsp_types::SemanticTokensEdits { result_id, edits }
+}
+
 fn semantic_token_type_and_modifiers(
     highlight: Highlight,
 ) -> (lsp_types::SemanticTokenType, semantic_tokens::ModifierSet) {
@@ -337,6 +360,7 @@ fn semantic_token_type_and_modifiers(
             HighlightModifier::ControlFlow => semantic_tokens::CONTROL_FLOW,
             HighlightModifier::Mutable => semantic_tokens::MUTABLE,
             HighlightModifier::Unsafe => semantic_tokens::UNSAFE,
+            HighlightModifier::Consuming => semantic_tokens::CONSUMING,
         };
         mods |= modifier;
     }
@@ -353,7 +377,12 @@ pub(crate) fn folding_range(
     let kind = match fold.kind {
         FoldKind::Comment => Some(lsp_types::FoldingRangeKind::Comment),
         FoldKind::Imports => Some(lsp_types::FoldingRangeKind::Imports),
-        FoldKind::Mods | FoldKind::Block | FoldKind::ArgList => None,
+        FoldKind::Region => Some(lsp_types::FoldingRangeKind::Region),
+        FoldKind::Mods
+        | FoldKind::Block
+        | FoldKind::ArgList
+        | FoldKind::WhereClause
+        | FoldKind::MatchArm => None,
     };
 
     let range = range(line_index, fold.range);
@@ -441,7 +470,7 @@ pub(crate) fn location(
     frange: FileRange,
 ) -> Result<lsp_types::Location> {
     let url = url(snap, frange.file_id);
-    let line_index = snap.analysis.file_line_index(frange.file_id)?;
+    let line_index = snap.file_line_index(frange.file_id)?;
     let range = range(&line_index, frange.range);
     let loc = lsp_types::Location::new(url, range);
     Ok(loc)
@@ -453,7 +482,7 @@ pub(crate) fn location_from_nav(
     nav: NavigationTarget,
 ) -> Result<lsp_types::Location> {
     let url = url(snap, nav.file_id());
-    let line_index = snap.analysis.file_line_index(nav.file_id())?;
+    let line_index = snap.file_line_index(nav.file_id())?;
     let range = range(&line_index, nav.full_range());
     let loc = lsp_types::Location::new(url, range);
     Ok(loc)
@@ -466,7 +495,7 @@ pub(crate) fn location_link(
 ) -> Result<lsp_types::LocationLink> {
     let origin_selection_range = match src {
         Some(src) => {
-            let line_index = snap.analysis.file_line_index(src.file_id)?;
+            let line_index = snap.file_line_index(src.file_id)?;
             let range = range(&line_index, src.range);
             Some(range)
         }
@@ -486,7 +515,7 @@ fn location_info(
     snap: &GlobalStateSnapshot,
     target: NavigationTarget,
 ) -> Result<(lsp_types::Url, lsp_types::Range, lsp_types::Range)> {
-    let line_index = snap.analysis.file_line_index(target.file_id())?;
+    let line_index = snap.file_line_index(target.file_id())?;
 
     let target_uri = url(snap, target.file_id());
     let target_range = range(&line_index, target.full_range());
@@ -529,7 +558,7 @@ pub(crate) fn snippet_text_document_edit(
     source_file_edit: SourceFileEdit,
 ) -> Result<lsp_ext::SnippetTextDocumentEdit> {
     let text_document = versioned_text_document_identifier(snap, source_file_edit.file_id, None);
-    let line_index = snap.analysis.file_line_index(source_file_edit.file_id)?;
+    let line_index = snap.file_line_index(source_file_edit.file_id)?;
     let line_endings = snap.file_line_endings(source_file_edit.file_id);
     let edits = source_file_edit
         .edit
@@ -675,6 +704,7 @@ pub(crate) fn runnable(
     file_id: FileId,
     runnable: Runnable,
 ) -> Result<lsp_ext::Runnable> {
+    let config = &snap.config.runnables;
     let spec = CargoTargetSpec::for_file(snap, file_id)?;
     let workspace_root = spec.as_ref().map(|it| it.workspace_root.clone());
     let target = spec.as_ref().map(|s| s.target.clone());
@@ -689,7 +719,9 @@ pub(crate) fn runnable(
         kind: lsp_ext::RunnableKind::Cargo,
         args: lsp_ext::CargoRunnable {
             workspace_root: workspace_root.map(|it| it.into()),
+            override_cargo: config.override_cargo.clone(),
             cargo_args,
+            cargo_extra_args: config.cargo_extra_args.clone(),
             executable_args,
             expect_test: None,
         },
@@ -702,6 +734,8 @@ pub(crate) fn markup_content(markup: Markup) -> lsp_types::MarkupContent {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use ra_ide::Analysis;
 
     use super::*;
@@ -724,7 +758,10 @@ fn main() {
         let folds = analysis.folding_ranges(file_id).unwrap();
         assert_eq!(folds.len(), 4);
 
-        let line_index = LineIndex::new(&text);
+        let line_index = LineIndex {
+            index: Arc::new(ra_ide::LineIndex::new(&text)),
+            encoding: OffsetEncoding::Utf16,
+        };
         let converted: Vec<lsp_types::FoldingRange> =
             folds.into_iter().map(|it| folding_range(&text, &line_index, true, it)).collect();
 
@@ -1,16 +1,22 @@
 mod support;
 
-use std::{collections::HashMap, path::PathBuf, time::Instant};
+use std::{collections::HashMap, fs, path::PathBuf, time::Instant};
 
 use lsp_types::{
     notification::DidOpenTextDocument,
-    request::{CodeActionRequest, Completion, Formatting, GotoTypeDefinition, HoverRequest},
+    request::{
+        CodeActionRequest, Completion, Formatting, GotoDefinition, GotoTypeDefinition,
+        HoverRequest, Rename,
+    },
     CodeActionContext, CodeActionParams, CompletionParams, DidOpenTextDocumentParams,
     DocumentFormattingParams, FormattingOptions, GotoDefinitionParams, HoverParams,
-    PartialResultParams, Position, Range, TextDocumentItem, TextDocumentPositionParams,
-    WorkDoneProgressParams,
+    PartialResultParams, Position, Range, RenameParams, TextDocumentItem,
+    TextDocumentPositionParams, WorkDoneProgressParams,
+};
+use rust_analyzer::{
+    config::OffsetEncoding,
+    lsp_ext::{OnEnter, ReloadWorkspace, Runnables, RunnablesParams},
 };
-use rust_analyzer::lsp_ext::{OnEnter, Runnables, RunnablesParams};
 use serde_json::json;
 use tempfile::TempDir;
 use test_utils::skip_slow_tests;
@@ -84,7 +90,14 @@ version = "0.0.0"
 fn main() {}
 "#;
 
-    let server = Project::with_fixture(code).root("foo").root("bar").server();
+    let server = Project::with_fixture(code)
+        .root("foo")
+        .root("bar")
+        .with_config(|config| {
+            config.runnables.override_cargo = Some("cross".to_string());
+            config.runnables.cargo_extra_args = vec!["--release".to_string()];
+        })
+        .server();
 
     server.wait_until_workspace_is_loaded();
     server.request::<Runnables>(
@@ -93,7 +106,9 @@ fn main() {}
           {
             "args": {
               "cargoArgs": ["test", "--package", "foo", "--test", "spam"],
+              "cargoExtraArgs": ["--release"],
               "executableArgs": ["test_eggs", "--exact", "--nocapture"],
+              "overrideCargo": "cross",
               "workspaceRoot": server.path().join("foo")
             },
             "kind": "cargo",
@@ -113,7 +128,9 @@ fn main() {}
           {
             "args": {
               "cargoArgs": ["check", "--package", "foo"],
+              "cargoExtraArgs": ["--release"],
               "executableArgs": [],
+              "overrideCargo": "cross",
               "workspaceRoot": server.path().join("foo")
             },
             "kind": "cargo",
@@ -122,7 +139,9 @@ fn main() {}
           {
             "args": {
               "cargoArgs": ["test", "--package", "foo"],
+              "cargoExtraArgs": ["--release"],
               "executableArgs": [],
+              "overrideCargo": "cross",
               "workspaceRoot": server.path().join("foo")
             },
             "kind": "cargo",
@@ -372,6 +391,428 @@ fn main() {{}}
     );
 }
 
+#[test]
+fn resolves_sysroot_of_json_project() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let tmp_dir = TempDir::new().unwrap();
+    let path = tmp_dir.path();
+
+    let project = json!({
+        "sysroot_src": path.join("sysroot"),
+        "roots": [path.join("src")],
+        "crates": [ {
+            "display_name": "hello",
+            "root_module": path.join("src/lib.rs"),
+            "deps": [],
+            "edition": "2018",
+            "cfg": [],
+        } ]
+    });
+
+    let code = format!(
+        r#"
+//- /rust-project.json
+{PROJECT}
+
+//- /sysroot/libcore/lib.rs
+pub struct CoreThing;
+
+//- /src/lib.rs
+fn f(thing: core::CoreThing) {{}}
+"#,
+        PROJECT = project.to_string(),
+    );
+
+    let server = Project::with_fixture(&code).tmp_dir(tmp_dir).server();
+    server.wait_until_workspace_is_loaded();
+    server.request::<GotoTypeDefinition>(
+        GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams::new(
+                server.doc_id("src/lib.rs"),
+                Position::new(0, 6),
+            ),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        },
+        json!([{
+            "originSelectionRange": {
+                "end": { "character": 10, "line": 0 },
+                "start": { "character": 5, "line": 0 }
+            },
+            "targetRange": {
+                "end": { "character": 21, "line": 0 },
+                "start": { "character": 0, "line": 0 }
+            },
+            "targetSelectionRange": {
+                "end": { "character": 20, "line": 0 },
+                "start": { "character": 11, "line": 0 }
+            },
+            "targetUri": "file:///[..]sysroot/libcore/lib.rs"
+        }]),
+    );
+}
+
+#[test]
+fn analyzes_detached_file() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /foo/Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- /foo/src/lib.rs
+pub fn foo() {}
+
+//- /scratch.rs
+fn main() { let x = helper(); }
+fn helper() -> u32 { 92 }
+"#,
+    )
+    .root("foo")
+    .server();
+    server.wait_until_workspace_is_loaded();
+
+    server.notification::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri: server.doc_id("scratch.rs").uri,
+            language_id: "rust".to_string(),
+            version: 0,
+            text: "fn main() { let x = helper(); }\nfn helper() -> u32 { 92 }\n".to_string(),
+        },
+    });
+    server.wait_until_workspace_is_loaded_n_times(2);
+
+    server.request::<HoverRequest>(
+        HoverParams {
+            text_document_position_params: TextDocumentPositionParams::new(
+                server.doc_id("scratch.rs"),
+                Position::new(0, 21),
+            ),
+            work_done_progress_params: Default::default(),
+        },
+        json!({
+            "contents": {
+                "kind": "markdown",
+                "value": "```rust\nscratch\n```\n\n```rust\nfn helper() -> u32\n```"
+            },
+            "range": {
+                "end": { "character": 26, "line": 0 },
+                "start": { "character": 20, "line": 0 }
+            }
+        }),
+    );
+}
+
+#[test]
+fn reload_workspace_discovers_new_projects() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /foo/Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- /foo/src/lib.rs
+pub fn foo() {}
+"#,
+    )
+    .with_config(|config| config.discovery_roots = vec![config.root_path.clone()])
+    .server();
+    server.wait_until_workspace_is_loaded();
+
+    let bar = server.path().join("bar");
+    fs::create_dir_all(bar.join("src")).unwrap();
+    fs::write(bar.join("Cargo.toml"), "[package]\nname = \"bar\"\nversion = \"0.0.0\"\n").unwrap();
+    fs::write(bar.join("src/lib.rs"), "pub fn bar() {}\n").unwrap();
+
+    server.request::<ReloadWorkspace>((), json!(null));
+    server.wait_until_workspace_is_loaded_n_times(2);
+
+    server.request::<Runnables>(
+        RunnablesParams { text_document: server.doc_id("bar/src/lib.rs"), position: None },
+        json!([
+          {
+            "args": {
+              "cargoArgs": ["check", "--package", "bar"],
+              "cargoExtraArgs": [],
+              "executableArgs": [],
+              "workspaceRoot": bar
+            },
+            "kind": "cargo",
+            "label": "cargo check -p bar"
+          },
+          {
+            "args": {
+              "cargoArgs": ["test", "--package", "bar"],
+              "cargoExtraArgs": [],
+              "executableArgs": [],
+              "workspaceRoot": bar
+            },
+            "kind": "cargo",
+            "label": "cargo test -p bar"
+          }
+        ]),
+    );
+}
+
+#[test]
+fn utf8_offset_encoding() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- /src/lib.rs
+pub fn foo() { let _ = "メ"; bar(); }
+pub fn bar() {}
+"#,
+    )
+    .with_config(|config| config.client_caps.offset_encoding = OffsetEncoding::Utf8)
+    .server();
+    server.wait_until_workspace_is_loaded();
+
+    // `メ` is one UTF-16 code unit, but three UTF-8 ones.
+    server.request::<HoverRequest>(
+        HoverParams {
+            text_document_position_params: TextDocumentPositionParams::new(
+                server.doc_id("src/lib.rs"),
+                Position::new(0, 31),
+            ),
+            work_done_progress_params: Default::default(),
+        },
+        json!({
+            "contents": {
+                "kind": "markdown",
+                "value": "```rust\nfoo\n```\n\n```rust\npub fn bar()\n```"
+            },
+            "range": {
+                "end": { "character": 33, "line": 0 },
+                "start": { "character": 30, "line": 0 }
+            }
+        }),
+    );
+}
+
+#[test]
+fn navigates_in_dependency_sources() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /foo/Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+[dependencies]
+dep = { path = "../dep" }
+
+//- /foo/src/lib.rs
+pub fn foo() { dep::dep_fn(); }
+
+//- /dep/Cargo.toml
+[package]
+name = "dep"
+version = "0.0.0"
+
+//- /dep/src/lib.rs
+pub fn dep_fn() { helper(); }
+fn helper() {}
+"#,
+    )
+    .root("foo")
+    .server();
+    server.wait_until_workspace_is_loaded();
+
+    server.request::<GotoDefinition>(
+        GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams::new(
+                server.doc_id("foo/src/lib.rs"),
+                Position::new(0, 22),
+            ),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        },
+        json!([{
+            "originSelectionRange": {
+                "end": { "character": 26, "line": 0 },
+                "start": { "character": 20, "line": 0 }
+            },
+            "targetRange": {
+                "end": { "character": 29, "line": 0 },
+                "start": { "character": 0, "line": 0 }
+            },
+            "targetSelectionRange": {
+                "end": { "character": 13, "line": 0 },
+                "start": { "character": 7, "line": 0 }
+            },
+            "targetUri": "file:///[..]dep/src/lib.rs"
+        }]),
+    );
+
+    // The dependency is analyzed as well, so navigation continues from there.
+    server.request::<GotoDefinition>(
+        GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams::new(
+                server.doc_id("dep/src/lib.rs"),
+                Position::new(0, 20),
+            ),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        },
+        json!([{
+            "originSelectionRange": {
+                "end": { "character": 24, "line": 0 },
+                "start": { "character": 18, "line": 0 }
+            },
+            "targetRange": {
+                "end": { "character": 14, "line": 1 },
+                "start": { "character": 0, "line": 1 }
+            },
+            "targetSelectionRange": {
+                "end": { "character": 9, "line": 1 },
+                "start": { "character": 3, "line": 1 }
+            },
+            "targetUri": "file:///[..]dep/src/lib.rs"
+        }]),
+    );
+
+    // But it is read-only.
+    server.request::<Rename>(
+        RenameParams {
+            text_document_position: TextDocumentPositionParams::new(
+                server.doc_id("foo/src/lib.rs"),
+                Position::new(0, 22),
+            ),
+            new_name: "renamed".to_string(),
+            work_done_progress_params: Default::default(),
+        },
+        json!(null),
+    );
+}
+
+#[test]
+fn path_dependencies_as_members() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /foo/Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+[dependencies]
+dep = { path = "../dep" }
+
+//- /foo/src/lib.rs
+pub fn foo() { dep::dep_fn(); }
+
+//- /dep/Cargo.toml
+[package]
+name = "dep"
+version = "0.0.0"
+
+//- /dep/src/lib.rs
+pub fn dep_fn() {}
+"#,
+    )
+    .root("foo")
+    .with_config(|config| config.cargo.path_deps_as_members = true)
+    .server();
+    server.wait_until_workspace_is_loaded();
+
+    let res = server.send_request::<Rename>(RenameParams {
+        text_document_position: TextDocumentPositionParams::new(
+            server.doc_id("foo/src/lib.rs"),
+            Position::new(0, 22),
+        ),
+        new_name: "renamed".to_string(),
+        work_done_progress_params: Default::default(),
+    });
+    let changes = res["documentChanges"].as_array().unwrap();
+    let uris = changes.iter().map(|it| it["textDocument"]["uri"].as_str().unwrap());
+    assert!(uris.clone().any(|uri| uri.ends_with("foo/src/lib.rs")), "{}", res);
+    assert!(uris.clone().any(|uri| uri.ends_with("dep/src/lib.rs")), "{}", res);
+}
+
+#[test]
+fn respects_dot_cargo_config() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- /.cargo/config.toml
+[build]
+target = "wasm32-unknown-unknown"
+
+//- /src/lib.rs
+#[cfg(not(target_arch = "wasm32"))]
+pub fn imp() {}
+#[cfg(target_arch = "wasm32")]
+pub fn imp() {}
+pub fn foo() { imp(); }
+"#,
+    )
+    .server();
+    server.wait_until_workspace_is_loaded();
+
+    server.request::<GotoDefinition>(
+        GotoDefinitionParams {
+            text_document_position_params: TextDocumentPositionParams::new(
+                server.doc_id("src/lib.rs"),
+                Position::new(4, 16),
+            ),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        },
+        json!([{
+            "originSelectionRange": {
+                "end": { "character": 18, "line": 4 },
+                "start": { "character": 15, "line": 4 }
+            },
+            "targetRange": {
+                "end": { "character": 15, "line": 3 },
+                "start": { "character": 0, "line": 2 }
+            },
+            "targetSelectionRange": {
+                "end": { "character": 10, "line": 3 },
+                "start": { "character": 7, "line": 3 }
+            },
+            "targetUri": "file:///[..]src/lib.rs"
+        }]),
+    );
+}
+
 #[test]
 fn diagnostics_dont_block_typing() {
     if skip_slow_tests() {
@@ -489,6 +930,9 @@ fn main() {
         r#"pub fn message() -> &'static str { "Hello, World!" }"#,
     )
     .unwrap();
+    let world_path = Path::new(&out_dir).join("world.rs");
+    fs::write(&world_path, r#"pub fn world() -> &'static str { "World" }"#).unwrap();
+    println!("cargo:rustc-env=WORLD_RS={}", world_path.display());
     println!("cargo:rustc-cfg=atom_cfg");
     println!("cargo:rustc-cfg=featlike=\"set\"");
     println!("cargo:rerun-if-changed=build.rs");
@@ -515,7 +959,10 @@ fn main() {
     let vb = B;
     let should_be_str = message();
     let another_str = include_str!("main.rs");
+    let from_env = world();
 }
+
+include!(env!("WORLD_RS"));
 "###,
     )
     .with_config(|config| {
@@ -539,6 +986,14 @@ fn main() {
         work_done_progress_params: Default::default(),
     });
     assert!(res.to_string().contains("&str"));
+    let res = server.send_request::<HoverRequest>(HoverParams {
+        text_document_position_params: TextDocumentPositionParams::new(
+            server.doc_id("src/main.rs"),
+            Position::new(21, 10),
+        ),
+        work_done_progress_params: Default::default(),
+    });
+    assert!(res.to_string().contains("&str"));
     server.request::<GotoTypeDefinition>(
         GotoDefinitionParams {
             text_document_position_params: TextDocumentPositionParams::new(
@@ -677,3 +1132,73 @@ pub fn foo(_input: TokenStream) -> TokenStream {
     let value = res.get("contents").unwrap().get("value").unwrap().to_string();
     assert_eq!(value, r#""```rust\nfoo::Bar\n```\n\n```rust\nfn bar()\n```""#)
 }
+
+#[test]
+fn resolve_function_like_proc_macro() {
+    if skip_slow_tests() {
+        return;
+    }
+    let server = Project::with_fixture(
+        r###"
+//- /foo/Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+edition = "2018"
+[dependencies]
+bar = {path = "../bar"}
+
+//- /foo/src/main.rs
+bar::make_fn!();
+fn main() {
+  baz();
+}
+
+//- /bar/Cargo.toml
+[package]
+name = "bar"
+version = "0.0.0"
+edition = "2018"
+
+[lib]
+proc-macro = true
+
+//- /bar/src/lib.rs
+extern crate proc_macro;
+use proc_macro::{Delimiter, Group, Ident, Span, TokenStream, TokenTree};
+#[proc_macro]
+pub fn make_fn(_input: TokenStream) -> TokenStream {
+    // fn baz() {}
+    let mut res = TokenStream::new();
+    res.extend(vec![
+        TokenTree::from(Ident::new("fn", Span::call_site())),
+        TokenTree::from(Ident::new("baz", Span::call_site())),
+        TokenTree::from(Group::new(Delimiter::Parenthesis, TokenStream::new())),
+        TokenTree::from(Group::new(Delimiter::Brace, TokenStream::new())),
+    ]);
+    res
+}
+
+"###,
+    )
+    .with_config(|config| {
+        let macro_srv_path = PathBuf::from(env!("CARGO_BIN_EXE_rust-analyzer"));
+
+        config.cargo.load_out_dirs_from_check = true;
+        config.proc_macro_srv = Some((macro_srv_path, vec!["proc-macro".into()]));
+    })
+    .root("foo")
+    .root("bar")
+    .server();
+    server.wait_until_workspace_is_loaded();
+    let res = server.send_request::<HoverRequest>(HoverParams {
+        text_document_position_params: TextDocumentPositionParams::new(
+            server.doc_id("foo/src/main.rs"),
+            Position::new(2, 3),
+        ),
+        work_done_progress_params: Default::default(),
+    });
+
+    let value = res.get("contents").unwrap().get("value").unwrap().to_string();
+    assert_eq!(value, r#""```rust\nfoo\n```\n\n```rust\nfn baz()\n```""#)
+}
@@ -87,11 +87,12 @@ impl<'a> Project<'a> {
                 location_link: true,
                 code_action_literals: true,
                 work_done_progress: true,
+                watched_files_dynamic_registration: true,
                 ..Default::default()
             },
             with_sysroot: self.with_sysroot,
             linked_projects,
-            files: FilesConfig { watcher: FilesWatcher::Client, exclude: Vec::new() },
+            files: FilesConfig { watcher: FilesWatcher::Client, exclude_dirs: Vec::new() },
             ..Config::new(tmp_dir_path)
         };
         if let Some(f) = &self.config {
@@ -202,7 +203,11 @@ impl Server {
         panic!("no response");
     }
     pub fn wait_until_workspace_is_loaded(&self) {
-        self.wait_for_message_cond(1, &|msg: &Message| match msg {
+        self.wait_until_workspace_is_loaded_n_times(1)
+    }
+    /// Like `wait_until_workspace_is_loaded`, for tests which trigger reloads.
+    pub fn wait_until_workspace_is_loaded_n_times(&self, n: usize) {
+        self.wait_for_message_cond(n, &|msg: &Message| match msg {
             Message::Notification(n) if n.method == "$/progress" => {
                 match n.clone().extract::<ProgressParams>("$/progress").unwrap() {
                     ProgressParams {
@@ -1,18 +1,24 @@
 //! An implementation of `loader::Handle`, based on `walkdir` and `notify`.
 //!
 //! The file watching bits here are untested and quite probably buggy. For this
-//! reason, by default we don't watch files and rely on editor's file watching
-//! capabilities.
+//! reason, we prefer to rely on editor's file watching capabilities, and only
+//! fall back to `notify` if the client can't watch files for us. Bursts of
+//! notify events are debounced before being fed into the VFS.
 //!
 //! Hopefully, one day a reliable file watching/walking crate appears on
 //! crates.io, and we can reduce this to trivial glue code.
 mod include;
 
-use std::convert::{TryFrom, TryInto};
+use std::{
+    convert::{TryFrom, TryInto},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use crossbeam_channel::{never, select, unbounded, Receiver, Sender};
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use paths::{AbsPath, AbsPathBuf};
+use rustc_hash::FxHashSet;
 use vfs::loader;
 use walkdir::WalkDir;
 
@@ -51,6 +57,12 @@ impl loader::Handle for NotifyHandle {
 
 type NotifyEvent = notify::Result<notify::Event>;
 
+/// How long to wait for further events after a change before reporting it.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+/// Upper bound on the delay, so that a constantly changing directory still
+/// gets reported.
+const MAX_DEBOUNCE: Duration = Duration::from_millis(500);
+
 struct NotifyActor {
     sender: loader::Sender,
     config: Vec<(AbsPathBuf, Include, bool)>,
@@ -108,50 +120,82 @@ impl NotifyActor {
                     }
                 },
                 Event::NotifyEvent(event) => {
+                    let mut paths = FxHashSet::default();
                     if let Some(event) = log_notify_error(event) {
-                        let files = event
-                            .paths
-                            .into_iter()
-                            .map(|path| AbsPathBuf::try_from(path).unwrap())
-                            .filter_map(|path| {
-                                let is_dir = path.is_dir();
-                                let is_file = path.is_file();
-
-                                let config_idx =
-                                    match self.config.binary_search_by(|it| it.0.cmp(&path)) {
-                                        Ok(it) => it,
-                                        Err(it) => it.saturating_sub(1),
-                                    };
-                                let include = self.config.get(config_idx).and_then(|it| {
-                                    let rel_path = path.strip_prefix(&it.0)?;
-                                    Some((rel_path, &it.1))
-                                });
-
-                                if let Some((rel_path, include)) = include {
-                                    if is_dir && include.exclude_dir(&rel_path)
-                                        || is_file && !include.include_file(&rel_path)
-                                    {
-                                        return None;
-                                    }
-                                }
-
-                                if is_dir {
-                                    self.watch(path);
-                                    return None;
-                                }
-                                if !is_file {
-                                    return None;
-                                }
-                                let contents = read(&path);
-                                Some((path, contents))
-                            })
-                            .collect();
-                        self.send(loader::Message::Loaded { files })
+                        paths.extend(event.paths);
                     }
+                    self.debounce(&mut paths);
+                    self.handle_changed_paths(paths);
                 }
             }
         }
     }
+    /// Editors and build tools tend to touch files in bursts (write to a
+    /// temporary file, rename, touch the directory...), so we wait for the
+    /// dust to settle before reporting changes.
+    fn debounce(&self, paths: &mut FxHashSet<PathBuf>) {
+        let watcher_receiver = match &self.watcher {
+            Some((_, receiver)) => receiver,
+            None => return,
+        };
+        let deadline = Instant::now() + MAX_DEBOUNCE;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            let timeout = DEBOUNCE.min(deadline - now);
+            match watcher_receiver.recv_timeout(timeout) {
+                Ok(event) => {
+                    if let Some(event) = log_notify_error(event) {
+                        paths.extend(event.paths);
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    }
+    fn handle_changed_paths(&mut self, paths: FxHashSet<PathBuf>) {
+        let files = paths
+            .into_iter()
+            .map(|path| AbsPathBuf::try_from(path).unwrap())
+            .filter_map(|path| {
+                let is_dir = path.is_dir();
+                let is_file = path.is_file();
+
+                let config_idx = match self.config.binary_search_by(|it| it.0.cmp(&path)) {
+                    Ok(it) => it,
+                    Err(it) => it.saturating_sub(1),
+                };
+                let include = self.config.get(config_idx).and_then(|it| {
+                    let rel_path = path.strip_prefix(&it.0)?;
+                    Some((rel_path, &it.1))
+                });
+
+                if let Some((rel_path, include)) = include {
+                    // Checking `exclude_dir` for files as well makes sure we
+                    // ignore build artifacts in `target/`.
+                    if include.exclude_dir(&rel_path) || is_file && !include.include_file(&rel_path)
+                    {
+                        return None;
+                    }
+                }
+
+                if is_dir {
+                    self.watch(path);
+                    return None;
+                }
+                if !is_file {
+                    return None;
+                }
+                let contents = read(&path);
+                Some((path, contents))
+            })
+            .collect::<Vec<_>>();
+        if !files.is_empty() {
+            self.send(loader::Message::Loaded { files })
+        }
+    }
     fn load_entry(
         &mut self,
         entry: loader::Entry,
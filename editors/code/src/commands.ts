@@ -128,6 +128,31 @@ export function joinLines(ctx: Ctx): Cmd {
     };
 }
 
+export function moveItemUp(ctx: Ctx): Cmd {
+    return moveItem(ctx, "Up");
+}
+
+export function moveItemDown(ctx: Ctx): Cmd {
+    return moveItem(ctx, "Down");
+}
+
+function moveItem(ctx: Ctx, direction: ra.MoveItemDirection): Cmd {
+    return async () => {
+        const editor = ctx.activeRustEditor;
+        const client = ctx.client;
+        if (!editor || !client) return;
+
+        const edits = await client.sendRequest(ra.moveItem, {
+            range: client.code2ProtocolConverter.asRange(editor.selection),
+            textDocument: { uri: editor.document.uri.toString() },
+            direction,
+        });
+        if (edits.length === 0) return;
+
+        await applySnippetTextEdits(editor, client.protocol2CodeConverter.asTextEdits(edits));
+    };
+}
+
 export function onEnter(ctx: Ctx): Cmd {
     async function handleKeypress() {
         const editor = ctx.activeRustEditor;
@@ -170,6 +195,20 @@ export function parentModule(ctx: Ctx): Cmd {
                 editor.selection.active,
             ),
         });
+        if (response.length > 1) {
+            // The file is included from several places, let the user pick.
+            const locations = response.map(loc => new vscode.Location(
+                client.protocol2CodeConverter.asUri(loc.targetUri),
+                client.protocol2CodeConverter.asRange(loc.targetSelectionRange),
+            ));
+            await vscode.commands.executeCommand(
+                'editor.action.showReferences',
+                editor.document.uri,
+                editor.selection.active,
+                locations,
+            );
+            return;
+        }
         const loc = response[0];
         if (!loc) return;
 
@@ -183,6 +222,30 @@ export function parentModule(ctx: Ctx): Cmd {
     };
 }
 
+export function openCargoToml(ctx: Ctx): Cmd {
+    return async () => {
+        const editor = ctx.activeRustEditor;
+        const client = ctx.client;
+        if (!editor || !client) return;
+
+        const response = await client.sendRequest(ra.openCargoToml, {
+            textDocument: { uri: editor.document.uri.toString() },
+            position: client.code2ProtocolConverter.asPosition(
+                editor.selection.active,
+            ),
+        });
+        if (!response) return;
+
+        const uri = client.protocol2CodeConverter.asUri(response.uri);
+        const range = client.protocol2CodeConverter.asRange(response.range);
+
+        const doc = await vscode.workspace.openTextDocument(uri);
+        const e = await vscode.window.showTextDocument(doc);
+        e.selection = new vscode.Selection(range.start, range.start);
+        e.revealRange(range, vscode.TextEditorRevealType.InCenter);
+    };
+}
+
 export function ssr(ctx: Ctx): Cmd {
     return async () => {
         const client = ctx.client;
@@ -306,6 +369,128 @@ export function syntaxTree(ctx: Ctx): Cmd {
 }
 
 
+// Opens the virtual file that will show the HIR of the function containing the cursor position
+//
+// The contents of the file come from the `TextDocumentContentProvider`
+export function viewHir(ctx: Ctx): Cmd {
+    const tdcp = new class implements vscode.TextDocumentContentProvider {
+        readonly uri = vscode.Uri.parse('rust-analyzer://viewHir/hir.txt');
+        readonly eventEmitter = new vscode.EventEmitter<vscode.Uri>();
+        constructor() {
+            vscode.workspace.onDidChangeTextDocument(this.onDidChangeTextDocument, this, ctx.subscriptions);
+            vscode.window.onDidChangeActiveTextEditor(this.onDidChangeActiveTextEditor, this, ctx.subscriptions);
+        }
+
+        private onDidChangeTextDocument(event: vscode.TextDocumentChangeEvent) {
+            if (isRustDocument(event.document)) {
+                // We need to order this after language server updates, but there's no API for that.
+                // Hence, good old sleep().
+                void sleep(10).then(() => this.eventEmitter.fire(this.uri));
+            }
+        }
+        private onDidChangeActiveTextEditor(editor: vscode.TextEditor | undefined) {
+            if (editor && isRustEditor(editor)) {
+                this.eventEmitter.fire(this.uri);
+            }
+        }
+
+        provideTextDocumentContent(_uri: vscode.Uri, ct: vscode.CancellationToken): vscode.ProviderResult<string> {
+            const rustEditor = ctx.activeRustEditor;
+            const client = ctx.client;
+            if (!rustEditor || !client) return '';
+
+            const params = {
+                textDocument: client.code2ProtocolConverter.asTextDocumentIdentifier(rustEditor.document),
+                position: client.code2ProtocolConverter.asPosition(rustEditor.selection.active),
+            };
+            return client.sendRequest(ra.viewHir, params, ct);
+        }
+
+        get onDidChange(): vscode.Event<vscode.Uri> {
+            return this.eventEmitter.event;
+        }
+    };
+
+    ctx.pushCleanup(vscode.workspace.registerTextDocumentContentProvider('rust-analyzer', tdcp));
+
+    return async () => {
+        const document = await vscode.workspace.openTextDocument(tdcp.uri);
+        tdcp.eventEmitter.fire(tdcp.uri);
+        void await vscode.window.showTextDocument(document, {
+            viewColumn: vscode.ViewColumn.Two,
+            preserveFocus: true
+        });
+    };
+}
+
+// Opens the virtual file that will show the item tree of the current file
+//
+// The contents of the file come from the `TextDocumentContentProvider`
+export function viewItemTree(ctx: Ctx): Cmd {
+    const tdcp = new class implements vscode.TextDocumentContentProvider {
+        readonly uri = vscode.Uri.parse('rust-analyzer://viewItemTree/itemtree.rs');
+        readonly eventEmitter = new vscode.EventEmitter<vscode.Uri>();
+
+        provideTextDocumentContent(_uri: vscode.Uri, ct: vscode.CancellationToken): vscode.ProviderResult<string> {
+            const rustEditor = ctx.activeRustEditor;
+            const client = ctx.client;
+            if (!rustEditor || !client) return '';
+
+            const params = {
+                textDocument: client.code2ProtocolConverter.asTextDocumentIdentifier(rustEditor.document),
+            };
+            return client.sendRequest(ra.viewItemTree, params, ct);
+        }
+
+        get onDidChange(): vscode.Event<vscode.Uri> {
+            return this.eventEmitter.event;
+        }
+    };
+
+    ctx.pushCleanup(vscode.workspace.registerTextDocumentContentProvider('rust-analyzer', tdcp));
+
+    return async () => {
+        const document = await vscode.workspace.openTextDocument(tdcp.uri);
+        tdcp.eventEmitter.fire(tdcp.uri);
+        void await vscode.window.showTextDocument(document, {
+            viewColumn: vscode.ViewColumn.Two,
+            preserveFocus: true
+        });
+    };
+}
+
+export function viewCrateGraph(ctx: Ctx): Cmd {
+    return crateGraph(ctx, false);
+}
+
+export function viewFullCrateGraph(ctx: Ctx): Cmd {
+    return crateGraph(ctx, true);
+}
+
+function crateGraph(ctx: Ctx, full: boolean): Cmd {
+    return async () => {
+        const client = ctx.client;
+        if (!client) return;
+
+        const dot = await client.sendRequest(ra.viewCrateGraph, { excludeSysroot: !full });
+        const res = spawnSync("dot", ["-Tsvg"], { input: dot, encoding: "utf8" });
+        if (res.error || res.status !== 0) {
+            // Without graphviz, show the graph source instead.
+            const document = await vscode.workspace.openTextDocument({ content: dot, language: "dot" });
+            await vscode.window.showTextDocument(document, vscode.ViewColumn.Two);
+            return;
+        }
+
+        const svg = res.stdout.substring(res.stdout.indexOf("<svg"));
+        const panel = vscode.window.createWebviewPanel(
+            "rust-analyzer.crate-graph",
+            "rust-analyzer crate graph",
+            vscode.ViewColumn.Two,
+        );
+        panel.webview.html = `<!DOCTYPE html><html><body>${svg}</body></html>`;
+    };
+}
+
 // Opens the virtual file that will show the syntax tree
 //
 // The contents of the file come from the `TextDocumentContentProvider`
@@ -366,6 +551,33 @@ export function reloadWorkspace(ctx: Ctx): Cmd {
     return async () => ctx.client.sendRequest(ra.reloadWorkspace, null);
 }
 
+export function startProfiling(ctx: Ctx): Cmd {
+    return async () => {
+        const filter = await vscode.window.showInputBox({
+            prompt: "Profiling filter, as in RA_PROFILE",
+            value: "*",
+        });
+        if (filter === undefined) return;
+
+        await ctx.client.sendRequest(ra.profile, { filter, foldedStacksPath: null });
+    };
+}
+
+export function stopProfiling(ctx: Ctx): Cmd {
+    return async () => {
+        // Dismissing the dialog stops profiling without saving the stacks.
+        const uri = await vscode.window.showSaveDialog({
+            saveLabel: "Save Folded Stacks",
+            filters: { "Folded stacks": ["folded"] },
+        });
+        const foldedStacksPath = uri?.fsPath ?? null;
+
+        const summary = await ctx.client.sendRequest(ra.profile, { filter: null, foldedStacksPath });
+        const document = await vscode.workspace.openTextDocument({ content: summary });
+        return vscode.window.showTextDocument(document, vscode.ViewColumn.Two, true);
+    };
+}
+
 export function showReferences(ctx: Ctx): Cmd {
     return (uri: string, position: lc.Position, locations: lc.Location[]) => {
         const client = ctx.client;
@@ -13,9 +13,6 @@ export class Config {
     readonly rootSection = "rust-analyzer";
     private readonly requiresReloadOpts = [
         "serverPath",
-        "cargo",
-        "procMacro",
-        "files",
         "highlighting",
         "updates.channel",
         "lens", // works as lens.*
@@ -138,6 +135,7 @@ export class Config {
             run: this.get<boolean>("lens.run"),
             debug: this.get<boolean>("lens.debug"),
             implementations: this.get<boolean>("lens.implementations"),
+            references: this.get<boolean>("lens.references"),
         };
     }
 
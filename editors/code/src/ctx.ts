@@ -5,7 +5,7 @@ import * as ra from './lsp_ext';
 import { Config } from './config';
 import { createClient } from './client';
 import { isRustEditor, RustEditor } from './util';
-import { Status } from './lsp_ext';
+import { StatusParams } from './lsp_ext';
 
 export class Ctx {
     private constructor(
@@ -66,33 +66,27 @@ export class Ctx {
         return this.extCtx.subscriptions;
     }
 
-    setStatus(status: Status) {
-        switch (status) {
-            case "loading":
-                this.statusBar.text = "$(sync~spin) rust-analyzer";
-                this.statusBar.tooltip = "Loading the project";
-                this.statusBar.command = undefined;
+    setStatus(status: StatusParams) {
+        this.statusBar.tooltip = status.message ?? (status.quiescent ? "Ready" : "Loading the project");
+        this.statusBar.command = undefined;
+        let icon = "";
+        switch (status.health) {
+            case "ok":
                 this.statusBar.color = undefined;
                 break;
-            case "ready":
-                this.statusBar.text = "rust-analyzer";
-                this.statusBar.tooltip = "Ready";
-                this.statusBar.command = undefined;
-                this.statusBar.color = undefined;
+            case "warning":
+                this.statusBar.color = new vscode.ThemeColor("notificationsWarningIcon.foreground");
+                this.statusBar.command = "rust-analyzer.reloadWorkspace";
+                icon = "$(warning) ";
                 break;
-            case "invalid":
-                this.statusBar.text = "$(error) rust-analyzer";
-                this.statusBar.tooltip = "Failed to load the project";
-                this.statusBar.command = undefined;
+            case "error":
                 this.statusBar.color = new vscode.ThemeColor("notificationsErrorIcon.foreground");
-                break;
-            case "needsReload":
-                this.statusBar.text = "$(warning) rust-analyzer";
-                this.statusBar.tooltip = "Click to reload";
                 this.statusBar.command = "rust-analyzer.reloadWorkspace";
-                this.statusBar.color = new vscode.ThemeColor("notificationsWarningIcon.foreground");
+                icon = "$(error) ";
                 break;
         }
+        if (!status.quiescent) icon = "$(sync~spin) ";
+        this.statusBar.text = `${icon}rust-analyzer`;
     }
 
     pushCleanup(d: Disposable) {
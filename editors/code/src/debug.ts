@@ -117,7 +117,8 @@ async function getDebugConfiguration(ctx: Ctx, runnable: ra.Runnable): Promise<v
 
 async function getDebugExecutable(runnable: ra.Runnable): Promise<string> {
     const cargo = new Cargo(runnable.args.workspaceRoot || '.', debugOutput);
-    const executable = await cargo.executableFromArgs(runnable.args.cargoArgs);
+    const args = [...runnable.args.cargoArgs, ...(runnable.args.cargoExtraArgs ?? [])];
+    const executable = await cargo.executableFromArgs(args);
 
     // if we are here, there were no compilation errors.
     return executable;
@@ -7,17 +7,39 @@ import * as lc from "vscode-languageclient";
 export const analyzerStatus = new lc.RequestType<null, string, void>("rust-analyzer/analyzerStatus");
 export const memoryUsage = new lc.RequestType<null, string, void>("rust-analyzer/memoryUsage");
 
-export type Status = "loading" | "ready" | "invalid" | "needsReload";
-export const status = new lc.NotificationType<Status>("rust-analyzer/status");
+export interface StatusParams {
+    health: "ok" | "warning" | "error";
+    quiescent: boolean;
+    message?: string;
+}
+export const status = new lc.NotificationType<StatusParams>("rust-analyzer/status");
 
 export const reloadWorkspace = new lc.RequestType<null, null, void>("rust-analyzer/reloadWorkspace");
 
+export interface ProfileParams {
+    filter: string | null;
+    foldedStacksPath: string | null;
+}
+export const profile = new lc.RequestType<ProfileParams, string, void>("rust-analyzer/profile");
+
 export interface SyntaxTreeParams {
     textDocument: lc.TextDocumentIdentifier;
     range: lc.Range | null;
 }
 export const syntaxTree = new lc.RequestType<SyntaxTreeParams, string, void>("rust-analyzer/syntaxTree");
 
+export const viewHir = new lc.RequestType<lc.TextDocumentPositionParams, string, void>("rust-analyzer/viewHir");
+
+export interface ViewItemTreeParams {
+    textDocument: lc.TextDocumentIdentifier;
+}
+export const viewItemTree = new lc.RequestType<ViewItemTreeParams, string, void>("rust-analyzer/viewItemTree");
+
+export interface ViewCrateGraphParams {
+    excludeSysroot: boolean;
+}
+export const viewCrateGraph = new lc.RequestType<ViewCrateGraphParams, string, void>("rust-analyzer/viewCrateGraph");
+
 
 export interface ExpandMacroParams {
     textDocument: lc.TextDocumentIdentifier;
@@ -37,6 +59,12 @@ export const matchingBrace = new lc.RequestType<MatchingBraceParams, lc.Position
 
 export const parentModule = new lc.RequestType<lc.TextDocumentPositionParams, lc.LocationLink[], void>("experimental/parentModule");
 
+export interface OpenCargoTomlParams {
+    textDocument: lc.TextDocumentIdentifier;
+    position?: lc.Position;
+}
+export const openCargoToml = new lc.RequestType<OpenCargoTomlParams, lc.Location | null, void>("experimental/openCargoToml");
+
 export interface ResolveCodeActionParams {
     id: string;
     codeActionParams: lc.CodeActionParams;
@@ -51,6 +79,14 @@ export const joinLines = new lc.RequestType<JoinLinesParams, lc.TextEdit[], void
 
 export const onEnter = new lc.RequestType<lc.TextDocumentPositionParams, lc.TextEdit[], void>("experimental/onEnter");
 
+export type MoveItemDirection = "Up" | "Down";
+export interface MoveItemParams {
+    textDocument: lc.TextDocumentIdentifier;
+    range: lc.Range;
+    direction: MoveItemDirection;
+}
+export const moveItem = new lc.RequestType<MoveItemParams, lc.TextEdit[], void>("experimental/moveItem");
+
 export interface RunnablesParams {
     textDocument: lc.TextDocumentIdentifier;
     position: lc.Position | null;
@@ -62,7 +98,9 @@ export interface Runnable {
     kind: "cargo";
     args: {
         workspaceRoot?: string;
+        overrideCargo?: string;
         cargoArgs: string[];
+        cargoExtraArgs: string[];
         executableArgs: string[];
         expectTest?: boolean;
     };
@@ -98,10 +98,19 @@ async function tryActivate(context: vscode.ExtensionContext) {
     ctx.registerCommand('analyzerStatus', commands.analyzerStatus);
     ctx.registerCommand('memoryUsage', commands.memoryUsage);
     ctx.registerCommand('reloadWorkspace', commands.reloadWorkspace);
+    ctx.registerCommand('startProfiling', commands.startProfiling);
+    ctx.registerCommand('stopProfiling', commands.stopProfiling);
     ctx.registerCommand('matchingBrace', commands.matchingBrace);
     ctx.registerCommand('joinLines', commands.joinLines);
     ctx.registerCommand('parentModule', commands.parentModule);
+    ctx.registerCommand('openCargoToml', commands.openCargoToml);
+    ctx.registerCommand('moveItemUp', commands.moveItemUp);
+    ctx.registerCommand('moveItemDown', commands.moveItemDown);
     ctx.registerCommand('syntaxTree', commands.syntaxTree);
+    ctx.registerCommand('viewHir', commands.viewHir);
+    ctx.registerCommand('viewItemTree', commands.viewItemTree);
+    ctx.registerCommand('viewCrateGraph', commands.viewCrateGraph);
+    ctx.registerCommand('viewFullCrateGraph', commands.viewFullCrateGraph);
     ctx.registerCommand('expandMacro', commands.expandMacro);
     ctx.registerCommand('run', commands.run);
     ctx.registerCommand('debug', commands.debug);
@@ -1644,7 +1644,7 @@ pub(crate) const AST_SRC: AstSrc = AstSrc {
 
         /// Const generic parameter **declaration**.
         /// ```
-        /// fn foo<T, U, ❰ const BAR: usize ❱, ❰ const BAZ: bool ❱>() {}
+        /// fn foo<T, U, ❰ const BAR: usize ❱, ❰ const BAZ: bool = true ❱>() {}
         /// ```
         ///
         /// [RFC](https://github.com/rust-lang/rfcs/blob/master/text/2000-const-generics.md#declaring-a-const-parameter)
@@ -2002,11 +2002,11 @@ pub(crate) const AST_SRC: AstSrc = AstSrc {
         /// ```
         /// foo::<u32, ❰ { true } ❱>();
         ///
-        /// bar::<❰ { 2 + 2} ❱>();
+        /// bar::<❰ { 2 + 2} ❱, ❰ -1 ❱>();
         /// ```
         ///
         /// [RFC](https://github.com/rust-lang/rfcs/blob/master/text/2000-const-generics.md#declaring-a-const-parameter)
-        struct ConstArg { Literal, BlockExpr }
+        struct ConstArg { Expr }
 
 
         /// FIXME: (@edwin0cheng) Remove it to use ItemList instead